@@ -0,0 +1,62 @@
+//! Azure TDX launch endorsements.
+//!
+//! Azure confidential VMs run the guest behind a paravisor, which exposes the TD report
+//! through the vTPM as an HCL report in NV index `0x01400001`. The MRTD it contains
+//! measures the paravisor firmware and is checked against the configured endorsement.
+
+use super::HostPlatform;
+use crate::cc_attestation::tpm::run_tool;
+use crate::error::{Error, Result};
+use std::process::Command;
+
+const HCL_REPORT_NV_INDEX: &str = "0x01400001";
+const HCL_SIGNATURE: &[u8] = b"HCLA";
+const HCL_HEADER_LEN: usize = 32;
+// MRTD offset within the TDREPORT (TDINFO at 0x200, MRTD at 0x10 within TDINFO)
+const TDREPORT_MRTD_OFFSET: usize = 0x210;
+const MRTD_LEN: usize = 48;
+
+pub struct AzureTdxPlatform;
+
+impl HostPlatform for AzureTdxPlatform {
+    fn name(&self) -> &'static str {
+        "azure-tdx"
+    }
+
+    fn launch_measurement(&self) -> Result<Vec<u8>> {
+        let report = run_tool(Command::new("tpm2_nvread").args(["-C", "o", HCL_REPORT_NV_INDEX]))?;
+        mrtd_from_hcl_report(&report)
+    }
+}
+
+/// Extracts the MRTD from an Azure HCL report wrapping a TDREPORT
+pub fn mrtd_from_hcl_report(report: &[u8]) -> Result<Vec<u8>> {
+    if !report.starts_with(HCL_SIGNATURE) {
+        return Err(Error::CCAttestationError(
+            "vTPM NV index does not contain an HCL report".to_string(),
+        ));
+    }
+
+    let offset = HCL_HEADER_LEN + TDREPORT_MRTD_OFFSET;
+    report
+        .get(offset..offset + MRTD_LEN)
+        .map(|mrtd| mrtd.to_vec())
+        .ok_or_else(|| Error::CCAttestationError("HCL report is truncated".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mrtd_from_hcl_report() {
+        let mut report = vec![0u8; HCL_HEADER_LEN + 1024];
+        report[..4].copy_from_slice(HCL_SIGNATURE);
+        let offset = HCL_HEADER_LEN + TDREPORT_MRTD_OFFSET;
+        report[offset..offset + MRTD_LEN].copy_from_slice(&[0x5A; MRTD_LEN]);
+
+        assert_eq!(mrtd_from_hcl_report(&report).unwrap(), vec![0x5A; MRTD_LEN]);
+        assert!(mrtd_from_hcl_report(&report[..offset]).is_err());
+        assert!(mrtd_from_hcl_report(&[0u8; 1100]).is_err());
+    }
+}
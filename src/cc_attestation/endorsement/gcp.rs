@@ -0,0 +1,35 @@
+//! GCP TDX launch endorsements.
+//!
+//! GCP publishes a signed launch endorsement for every TDX firmware image. Retrieval and
+//! verification against the GCE TCB root are handled by `tdx_workload_attestation`.
+
+use super::{EndorsementOptions, HostPlatform, verify_with_source};
+use crate::cc_attestation::get_launch_measurement;
+use crate::error::{Error, Result};
+use tdx_workload_attestation::gcp::GcpTdxHost;
+use tdx_workload_attestation::host::TeeHost;
+
+pub struct GcpTdxPlatform;
+
+impl HostPlatform for GcpTdxPlatform {
+    fn name(&self) -> &'static str {
+        "gcp-tdx"
+    }
+
+    fn launch_measurement(&self) -> Result<Vec<u8>> {
+        Ok(get_launch_measurement()?.to_vec())
+    }
+
+    fn verify_launch_endorsement(&self, options: &EndorsementOptions) -> Result<bool> {
+        // an explicitly configured source takes precedence over the GCP bucket
+        if options.source.is_some() {
+            return verify_with_source(self, options);
+        }
+
+        let measurement = get_launch_measurement()?;
+        let gcp_host = GcpTdxHost::new(&measurement);
+        gcp_host
+            .verify_launch_endorsement()
+            .map_err(|e| Error::CCAttestationError(e.to_string()))
+    }
+}
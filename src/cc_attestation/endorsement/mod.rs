@@ -0,0 +1,353 @@
+//! Launch endorsement verification for CC host platforms.
+//!
+//! A launch endorsement is a statement by the host (cloud provider, firmware vendor or
+//! an on-prem operator) listing the launch measurements it vouches for. Verification
+//! is split in two pluggable parts:
+//!
+//! - a [`HostPlatform`] knows how to obtain the launch measurement of the running guest,
+//! - an [`EndorsementFetcher`] retrieves the endorsement covering that measurement.
+//!
+//! New platforms only need a `HostPlatform` implementation registered in
+//! [`host_platforms`]; the CLI handler looks platforms up by name.
+//!
+//! Verification fails closed: endorsements must be signed by a configured endorser key and
+//! fetched over HTTPS unless [`EndorsementOptions`] explicitly allows otherwise.
+
+use crate::error::{Error, Result};
+use crate::http;
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use openssl::pkey::{PKey, Public};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+pub mod azure;
+#[cfg(feature = "with-tdx")]
+pub mod gcp;
+pub mod onprem;
+pub mod snp;
+
+/// Endorsed launch measurements for a platform.
+///
+/// When `signature` is present it is a base64-encoded signature over the JSON
+/// serialization of `platform` and `measurements`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LaunchEndorsement {
+    pub platform: String,
+    /// Hex-encoded endorsed launch measurements
+    pub measurements: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub signature: Option<String>,
+}
+
+impl LaunchEndorsement {
+    /// Bytes covered by the endorsement signature
+    pub fn signed_payload(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(&serde_json::json!({
+            "platform": self.platform,
+            "measurements": self.measurements,
+        }))?)
+    }
+
+    /// Verifies the endorsement signature with the endorser's public key
+    pub fn verify_signature(&self, public_key: &PKey<Public>) -> Result<bool> {
+        let signature = match &self.signature {
+            Some(sig) => STANDARD.decode(sig).map_err(|e| {
+                Error::CCAttestationError(format!("Invalid endorsement signature encoding: {e}"))
+            })?,
+            None => return Ok(false),
+        };
+
+        crate::signing::verify_signature(&self.signed_payload()?, &signature, public_key)
+    }
+
+    /// Returns true if `measurement` is one of the endorsed measurements
+    pub fn endorses(&self, measurement: &[u8]) -> bool {
+        let measurement = hex::encode(measurement);
+        self.measurements
+            .iter()
+            .any(|m| m.eq_ignore_ascii_case(&measurement))
+    }
+}
+
+/// Retrieves launch endorsements
+pub trait EndorsementFetcher {
+    /// Fetches the endorsement covering `measurement`
+    fn fetch(&self, measurement: &[u8]) -> Result<LaunchEndorsement>;
+}
+
+/// Reads an endorsement document from a local file
+pub struct FileEndorsementFetcher {
+    pub path: PathBuf,
+}
+
+impl EndorsementFetcher for FileEndorsementFetcher {
+    fn fetch(&self, _measurement: &[u8]) -> Result<LaunchEndorsement> {
+        let contents = fs::read_to_string(&self.path)?;
+        serde_json::from_str(&contents)
+            .map_err(|e| Error::CCAttestationError(format!("Invalid endorsement document: {e}")))
+    }
+}
+
+/// Downloads an endorsement document over HTTP(S).
+///
+/// A `{measurement}` placeholder in the URL is replaced with the hex-encoded measurement,
+/// for endorsement services that publish one document per measurement.
+pub struct HttpEndorsementFetcher {
+    pub url: String,
+}
+
+impl EndorsementFetcher for HttpEndorsementFetcher {
+    fn fetch(&self, measurement: &[u8]) -> Result<LaunchEndorsement> {
+        let url = self.url.replace("{measurement}", &hex::encode(measurement));
+        let response = http::client_builder()
+            .build()
+            .and_then(|client| client.get(&url).send())
+            .map_err(|e| Error::CCAttestationError(format!("Failed to fetch endorsement: {e}")))?;
+
+        if !response.status().is_success() {
+            return Err(Error::CCAttestationError(format!(
+                "Failed to fetch endorsement from {url}: HTTP {}",
+                response.status()
+            )));
+        }
+
+        response
+            .json()
+            .map_err(|e| Error::CCAttestationError(format!("Invalid endorsement document: {e}")))
+    }
+}
+
+/// Creates a fetcher for an endorsement source, which is either an `http(s)://` URL
+/// or a local file path. Plain `http://` URLs are rejected unless `allow_http` is set.
+pub fn fetcher_for_source(source: &str, allow_http: bool) -> Result<Box<dyn EndorsementFetcher>> {
+    if source.starts_with("http://") && !allow_http {
+        return Err(Error::CCAttestationError(format!(
+            "Refusing to fetch endorsement over plain HTTP from {source}; use an https:// URL or pass --allow-http-endorsement"
+        )));
+    }
+
+    if source.starts_with("http://") || source.starts_with("https://") {
+        Ok(Box::new(HttpEndorsementFetcher {
+            url: source.to_string(),
+        }))
+    } else {
+        Ok(Box::new(FileEndorsementFetcher {
+            path: PathBuf::from(source),
+        }))
+    }
+}
+
+/// Options controlling where endorsements come from and how they are authenticated
+#[derive(Default)]
+pub struct EndorsementOptions {
+    /// Endorsement source (file path or URL); required by platforms without their own
+    /// endorsement service
+    pub source: Option<String>,
+    /// Public key of the endorser; endorsements must be signed with it
+    pub public_key: Option<PKey<Public>>,
+    /// Accepts endorsements without checking a signature when no public key is set
+    pub allow_unsigned: bool,
+    /// Accepts endorsement sources served over plain `http://`
+    pub allow_http: bool,
+}
+
+/// A host platform whose launch endorsements can be verified
+pub trait HostPlatform {
+    /// Name used to select the platform (`--host-platform`)
+    fn name(&self) -> &'static str;
+
+    /// Retrieves the launch measurement of the running guest
+    fn launch_measurement(&self) -> Result<Vec<u8>>;
+
+    /// Verifies the guest launch measurement against the platform's endorsement
+    fn verify_launch_endorsement(&self, options: &EndorsementOptions) -> Result<bool> {
+        verify_with_source(self, options)
+    }
+}
+
+/// Fetches the endorsement from the configured source and checks the platform's launch
+/// measurement against it
+pub fn verify_with_source(
+    platform: &(impl HostPlatform + ?Sized),
+    options: &EndorsementOptions,
+) -> Result<bool> {
+    let source = options.source.as_deref().ok_or_else(|| {
+        Error::CCAttestationError(format!(
+            "No endorsement source configured for platform {}",
+            platform.name()
+        ))
+    })?;
+    let fetcher = fetcher_for_source(source, options.allow_http)?;
+
+    let measurement = platform.launch_measurement()?;
+    let endorsement = fetcher.fetch(&measurement)?;
+    verify_endorsement(platform.name(), &measurement, &endorsement, options)
+}
+
+/// Checks a fetched endorsement against a launch measurement
+pub fn verify_endorsement(
+    platform: &str,
+    measurement: &[u8],
+    endorsement: &LaunchEndorsement,
+    options: &EndorsementOptions,
+) -> Result<bool> {
+    if endorsement.platform != platform {
+        return Err(Error::CCAttestationError(format!(
+            "Endorsement is for platform {}, expected {platform}",
+            endorsement.platform
+        )));
+    }
+
+    match &options.public_key {
+        Some(public_key) => {
+            if !endorsement.verify_signature(public_key)? {
+                return Err(Error::CCAttestationError(
+                    "Invalid launch endorsement signature".to_string(),
+                ));
+            }
+        }
+        None if !options.allow_unsigned => {
+            return Err(Error::CCAttestationError(
+                "No endorser public key configured; pass --endorsement-key, or --allow-unsigned-endorsement to accept the endorsement without checking its signature".to_string(),
+            ));
+        }
+        None => {}
+    }
+
+    Ok(endorsement.endorses(measurement))
+}
+
+/// All host platforms known to this build
+pub fn host_platforms() -> Vec<Box<dyn HostPlatform>> {
+    vec![
+        #[cfg(feature = "with-tdx")]
+        Box::new(gcp::GcpTdxPlatform),
+        Box::new(azure::AzureTdxPlatform),
+        Box::new(onprem::OnPremTdxPlatform),
+        Box::new(snp::SevSnpPlatform),
+    ]
+}
+
+/// Looks up a host platform by name
+pub fn host_platform(name: &str) -> Result<Box<dyn HostPlatform>> {
+    host_platforms()
+        .into_iter()
+        .find(|platform| platform.name() == name)
+        .ok_or_else(|| {
+            Error::CCAttestationError(format!(
+                "Launch endorsement verification not supported for platform {name}"
+            ))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing::{sign_data, test_utils::generate_temp_key};
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    struct FixedPlatform;
+
+    impl HostPlatform for FixedPlatform {
+        fn name(&self) -> &'static str {
+            "test-platform"
+        }
+
+        fn launch_measurement(&self) -> Result<Vec<u8>> {
+            Ok(vec![0xAB; 48])
+        }
+    }
+
+    fn endorsement(measurements: Vec<String>) -> LaunchEndorsement {
+        LaunchEndorsement {
+            platform: "test-platform".to_string(),
+            measurements,
+            signature: None,
+        }
+    }
+
+    #[test]
+    fn test_endorses_measurement() {
+        let e = endorsement(vec![hex::encode([0xAB; 48]).to_uppercase()]);
+        assert!(e.endorses(&[0xAB; 48]));
+        assert!(!e.endorses(&[0xCD; 48]));
+    }
+
+    #[test]
+    fn test_verify_with_file_source() -> Result<()> {
+        let mut file = NamedTempFile::new()?;
+        let doc = endorsement(vec![hex::encode([0xAB; 48])]);
+        file.write_all(serde_json::to_string(&doc)?.as_bytes())?;
+
+        let mut options = EndorsementOptions {
+            source: Some(file.path().to_string_lossy().to_string()),
+            ..Default::default()
+        };
+        // unsigned endorsements need an explicit opt-out of signature checks
+        assert!(FixedPlatform.verify_launch_endorsement(&options).is_err());
+        options.allow_unsigned = true;
+        assert!(FixedPlatform.verify_launch_endorsement(&options)?);
+
+        // the endorsement source is required
+        assert!(
+            FixedPlatform
+                .verify_launch_endorsement(&EndorsementOptions::default())
+                .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_signed_endorsement() -> Result<()> {
+        let (key, _dir) = generate_temp_key()?;
+        let public_key =
+            PKey::public_key_from_pem(&key.as_pkey().public_key_to_pem().unwrap()).unwrap();
+
+        let mut doc = endorsement(vec![hex::encode([0xAB; 48])]);
+        let options = EndorsementOptions {
+            public_key: Some(public_key),
+            ..Default::default()
+        };
+
+        // unsigned endorsements are rejected once a key is configured
+        assert!(verify_endorsement("test-platform", &[0xAB; 48], &doc, &options).is_err());
+
+        doc.signature = Some(STANDARD.encode(sign_data(&doc.signed_payload()?, &key)?));
+        assert!(verify_endorsement(
+            "test-platform",
+            &[0xAB; 48],
+            &doc,
+            &options
+        )?);
+
+        doc.measurements.push(hex::encode([0xCD; 48]));
+        assert!(verify_endorsement("test-platform", &[0xAB; 48], &doc, &options).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_plain_http_source_needs_opt_in() {
+        let url = "http://endorsements.example.com/{measurement}.json";
+        assert!(fetcher_for_source(url, false).is_err());
+        assert!(fetcher_for_source(url, true).is_ok());
+        assert!(fetcher_for_source("https://endorsements.example.com/e.json", false).is_ok());
+        assert!(fetcher_for_source("endorsement.json", false).is_ok());
+    }
+
+    #[test]
+    fn test_platform_mismatch_and_lookup() {
+        let doc = endorsement(vec![]);
+        let options = EndorsementOptions {
+            allow_unsigned: true,
+            ..Default::default()
+        };
+        assert!(verify_endorsement("azure-tdx", &[0; 48], &doc, &options).is_err());
+
+        assert!(host_platform("azure-tdx").is_ok());
+        assert!(host_platform("onprem-tdx").is_ok());
+        assert!(host_platform("sev-snp").is_ok());
+        assert!(host_platform("unknown").is_err());
+    }
+}
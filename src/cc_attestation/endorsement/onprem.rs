@@ -0,0 +1,21 @@
+//! On-prem TDX launch endorsements.
+//!
+//! On-prem hosts have no provider endorsement service. Instead, the operator publishes the
+//! MRTDs of approved TD images as an endorsement document, either as a file or at a URL,
+//! optionally signed with the operator's key.
+
+use super::HostPlatform;
+use crate::cc_attestation::get_launch_measurement;
+use crate::error::Result;
+
+pub struct OnPremTdxPlatform;
+
+impl HostPlatform for OnPremTdxPlatform {
+    fn name(&self) -> &'static str {
+        "onprem-tdx"
+    }
+
+    fn launch_measurement(&self) -> Result<Vec<u8>> {
+        Ok(get_launch_measurement()?.to_vec())
+    }
+}
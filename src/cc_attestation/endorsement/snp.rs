@@ -0,0 +1,74 @@
+//! AMD SEV-SNP launch endorsements.
+//!
+//! The guest launch measurement is read from an SNP attestation report obtained through
+//! the kernel's configfs-tsm interface and checked against the configured endorsement.
+
+use super::HostPlatform;
+use crate::error::{Error, Result};
+use std::fs;
+use std::path::Path;
+use uuid::Uuid;
+
+const TSM_REPORT_DIR: &str = "/sys/kernel/config/tsm/report";
+const SNP_MEASUREMENT_OFFSET: usize = 0x90;
+const SNP_MEASUREMENT_LEN: usize = 48;
+
+pub struct SevSnpPlatform;
+
+impl HostPlatform for SevSnpPlatform {
+    fn name(&self) -> &'static str {
+        "sev-snp"
+    }
+
+    fn launch_measurement(&self) -> Result<Vec<u8>> {
+        let report = read_tsm_report(Path::new(TSM_REPORT_DIR))?;
+        measurement_from_snp_report(&report)
+    }
+}
+
+fn read_tsm_report(tsm_dir: &Path) -> Result<Vec<u8>> {
+    let entry = tsm_dir.join(format!("atlas-{}", Uuid::new_v4()));
+    fs::create_dir(&entry)
+        .map_err(|e| Error::CCAttestationError(format!("configfs-tsm is not available: {e}")))?;
+
+    let result = (|| {
+        fs::write(entry.join("inblob"), [0u8; 64])?;
+        let provider = fs::read_to_string(entry.join("provider"))?;
+        if provider.trim() != "sev_guest" {
+            return Err(Error::CCAttestationError(format!(
+                "Attestation provider is {}, not SEV-SNP",
+                provider.trim()
+            )));
+        }
+        Ok(fs::read(entry.join("outblob"))?)
+    })();
+
+    let _ = fs::remove_dir(&entry);
+    result
+}
+
+/// Extracts the launch measurement from an SNP attestation report
+pub fn measurement_from_snp_report(report: &[u8]) -> Result<Vec<u8>> {
+    report
+        .get(SNP_MEASUREMENT_OFFSET..SNP_MEASUREMENT_OFFSET + SNP_MEASUREMENT_LEN)
+        .map(|m| m.to_vec())
+        .ok_or_else(|| Error::CCAttestationError("SNP report is truncated".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_measurement_from_snp_report() {
+        let mut report = vec![0u8; 0x4A0];
+        report[SNP_MEASUREMENT_OFFSET..SNP_MEASUREMENT_OFFSET + SNP_MEASUREMENT_LEN]
+            .copy_from_slice(&[0x11; SNP_MEASUREMENT_LEN]);
+
+        assert_eq!(
+            measurement_from_snp_report(&report).unwrap(),
+            vec![0x11; SNP_MEASUREMENT_LEN]
+        );
+        assert!(measurement_from_snp_report(&report[..0x90]).is_err());
+    }
+}
@@ -0,0 +1,446 @@
+//! TDX RTMR event log (CCEL) capture and replay.
+//!
+//! A TDX report only carries the final RTMR digests. The CC event log published by the
+//! firmware in the ACPI CCEL table records every measurement extended into those
+//! registers, so a verifier can replay the log and see which components produced the
+//! RTMR values instead of trusting opaque digests.
+//!
+//! The log uses the TCG crypto-agile format: a legacy SHA-1 header event carrying the
+//! `Spec ID Event03` structure, followed by `TCG_PCR_EVENT2` entries. In the CCEL the
+//! register index is an MR index, where 0 is MRTD and 1-4 are RTMR0-RTMR3.
+
+use crate::cli::color;
+use crate::error::{Error, Result};
+use crate::manifest::claims;
+use atlas_c2pa_lib::assertion::Assertion;
+use atlas_c2pa_lib::manifest::Manifest;
+use openssl::sha::Sha384;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// Location of the CCEL event log data exposed by the Linux ACPI driver
+pub const CCEL_DATA_PATH: &str = "/sys/firmware/acpi/tables/data/CCEL";
+
+const TPM_ALG_SHA1: u16 = 0x0004;
+const TPM_ALG_SHA256: u16 = 0x000B;
+const TPM_ALG_SHA384: u16 = 0x000C;
+const TPM_ALG_SHA512: u16 = 0x000D;
+const EV_NO_ACTION: u32 = 0x0000_0003;
+const SPEC_ID_SIGNATURE: &[u8] = b"Spec ID Event03\0";
+const RTMR_COUNT: usize = 4;
+const RTMR_LEN: usize = 48;
+
+/// A single measurement recorded in the event log
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EventLogEntry {
+    /// MR index (0 = MRTD, 1-4 = RTMR0-RTMR3)
+    pub mr_index: u32,
+    pub event_type: u32,
+    pub event_type_name: String,
+    /// Hex-encoded SHA-384 digest extended into the register
+    pub digest: Option<String>,
+    /// Hex-encoded event data
+    pub event_data: String,
+    /// Event data as text, when it is printable
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub description: Option<String>,
+}
+
+/// A parsed event log together with the RTMR values it replays to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EventLog {
+    pub entries: Vec<EventLogEntry>,
+    /// Hex-encoded RTMR0-RTMR3 values obtained by replaying `entries`
+    pub rtmrs: Vec<String>,
+}
+
+impl EventLog {
+    /// Replays the log, returning the RTMR values it produces
+    pub fn replay(&self) -> Result<[[u8; RTMR_LEN]; RTMR_COUNT]> {
+        let mut rtmrs = [[0u8; RTMR_LEN]; RTMR_COUNT];
+
+        for entry in &self.entries {
+            // MRTD is measured by the TDX module, not extended from the log
+            if entry.event_type == EV_NO_ACTION || entry.mr_index == 0 {
+                continue;
+            }
+            let rtmr = (entry.mr_index - 1) as usize;
+            if rtmr >= RTMR_COUNT {
+                return Err(Error::CCAttestationError(format!(
+                    "Event log entry has invalid MR index {}",
+                    entry.mr_index
+                )));
+            }
+            let Some(digest) = &entry.digest else {
+                continue;
+            };
+
+            let mut hasher = Sha384::new();
+            hasher.update(&rtmrs[rtmr]);
+            hasher.update(&hex::decode(digest)?);
+            rtmrs[rtmr] = hasher.finish();
+        }
+
+        Ok(rtmrs)
+    }
+}
+
+/// Reads and parses the CCEL event log of the current TD.
+///
+/// Returns `Ok(None)` if the platform does not expose a CCEL table.
+pub fn read_ccel() -> Result<Option<EventLog>> {
+    read_event_log(Path::new(CCEL_DATA_PATH))
+}
+
+/// Reads and parses an event log from `path`, if it exists
+pub fn read_event_log(path: &Path) -> Result<Option<EventLog>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let data = fs::read(path)?;
+
+    parse_event_log(&data).map(Some)
+}
+
+/// Parses a crypto-agile event log and replays its RTMR values
+pub fn parse_event_log(data: &[u8]) -> Result<EventLog> {
+    let mut r = Reader { data, pos: 0 };
+
+    // the header event uses the legacy SHA-1 format
+    let _mr_index = r.u32()?;
+    let _event_type = r.u32()?;
+    r.take(20)?;
+    let spec_id = r.sized_u32()?;
+    let digest_sizes = parse_spec_id_event(spec_id)?;
+
+    let mut entries = Vec::new();
+    while r.remaining() >= 8 {
+        let mr_index = r.u32()?;
+        let event_type = r.u32()?;
+
+        // the log area is padded with 0xFF (or zeroes) after the last event
+        if (mr_index == u32::MAX && event_type == u32::MAX) || (mr_index == 0 && event_type == 0) {
+            break;
+        }
+
+        let digest_count = r.u32()?;
+        let mut digest = None;
+        for _ in 0..digest_count {
+            let alg = r.u16()?;
+            let size = digest_sizes
+                .iter()
+                .find(|(id, _)| *id == alg)
+                .map(|(_, size)| *size as usize)
+                .or_else(|| default_digest_size(alg))
+                .ok_or_else(|| {
+                    Error::CCAttestationError(format!(
+                        "Unknown digest algorithm 0x{alg:04x} in event log"
+                    ))
+                })?;
+            let value = r.take(size)?;
+            if alg == TPM_ALG_SHA384 {
+                digest = Some(hex::encode(value));
+            }
+        }
+
+        let event_data = r.sized_u32()?;
+        entries.push(EventLogEntry {
+            mr_index,
+            event_type,
+            event_type_name: event_type_name(event_type).to_string(),
+            digest,
+            event_data: hex::encode(event_data),
+            description: printable(event_data),
+        });
+    }
+
+    let mut log = EventLog {
+        entries,
+        rtmrs: Vec::new(),
+    };
+    log.rtmrs = log.replay()?.iter().map(hex::encode).collect();
+
+    Ok(log)
+}
+
+/// Checks that replaying `log` reproduces the RTMR values of a TD report.
+///
+/// `report` is the JSON-serialized TD report returned by the attestation provider.
+pub fn verify_against_report(log: &EventLog, report: &str) -> Result<bool> {
+    let report: serde_json::Value = serde_json::from_str(report)?;
+    let replayed = log.replay()?;
+
+    let mut matches = true;
+    for (i, replayed) in replayed.iter().enumerate() {
+        let reported: Vec<u8> = report
+            .pointer(&format!("/td_info/rtmr{i}"))
+            .and_then(|v| v.as_array())
+            .map(|bytes| {
+                bytes
+                    .iter()
+                    .filter_map(|b| b.as_u64().map(|b| b as u8))
+                    .collect()
+            })
+            .ok_or_else(|| {
+                Error::CCAttestationError(format!("TD report does not contain RTMR{i}"))
+            })?;
+
+        if reported.as_slice() != replayed {
+            println!(
+                "{} RTMR{i} mismatch: report has {}, event log replays to {}",
+                color::cross_mark(),
+                hex::encode(&reported),
+                hex::encode(replayed)
+            );
+            matches = false;
+        }
+    }
+
+    Ok(matches)
+}
+
+/// Extracts the embedded event log and the TD report it belongs to from a manifest's
+/// CC attestation assertion
+pub fn event_log_from_manifest(manifest: &Manifest) -> Result<(EventLog, String)> {
+    let claim = claims::signed_claim(manifest);
+
+    claim
+        .created_assertions
+        .iter()
+        .rev()
+        .filter_map(|assertion| match assertion {
+            Assertion::CustomAssertion(custom) => find_event_log(&custom.data),
+            _ => None,
+        })
+        .next()
+        .ok_or_else(|| {
+            Error::CCAttestationError(
+                "Manifest does not contain a CC attestation with an event log".to_string(),
+            )
+        })
+}
+
+// Attached attestations wrap the original assertion data under "report"
+fn find_event_log(data: &serde_json::Value) -> Option<(EventLog, String)> {
+    let report = data.get("report")?;
+    match data.get("event_log") {
+        Some(log) => Some((
+            serde_json::from_value(log.clone()).ok()?,
+            report.as_str()?.to_string(),
+        )),
+        None => find_event_log(report),
+    }
+}
+
+fn parse_spec_id_event(event: &[u8]) -> Result<Vec<(u16, u16)>> {
+    let mut r = Reader {
+        data: event,
+        pos: 0,
+    };
+    if r.take(16)? != SPEC_ID_SIGNATURE {
+        return Err(Error::CCAttestationError(
+            "Event log does not start with a Spec ID event".to_string(),
+        ));
+    }
+    // platformClass, specVersionMinor, specVersionMajor, specErrata, uintnSize
+    r.take(4 + 1 + 1 + 1 + 1)?;
+
+    let count = r.u32()?;
+    let mut sizes = Vec::new();
+    for _ in 0..count {
+        sizes.push((r.u16()?, r.u16()?));
+    }
+
+    Ok(sizes)
+}
+
+fn default_digest_size(alg: u16) -> Option<usize> {
+    match alg {
+        TPM_ALG_SHA1 => Some(20),
+        TPM_ALG_SHA256 => Some(32),
+        TPM_ALG_SHA384 => Some(48),
+        TPM_ALG_SHA512 => Some(64),
+        _ => None,
+    }
+}
+
+fn event_type_name(event_type: u32) -> &'static str {
+    match event_type {
+        0x0000_0001 => "EV_POST_CODE",
+        0x0000_0003 => "EV_NO_ACTION",
+        0x0000_0004 => "EV_SEPARATOR",
+        0x0000_0005 => "EV_ACTION",
+        0x0000_0006 => "EV_EVENT_TAG",
+        0x0000_0008 => "EV_S_CRTM_VERSION",
+        0x0000_000A => "EV_PLATFORM_CONFIG_FLAGS",
+        0x0000_000C => "EV_COMPACT_HASH",
+        0x0000_000D => "EV_IPL",
+        0x8000_0001 => "EV_EFI_VARIABLE_DRIVER_CONFIG",
+        0x8000_0002 => "EV_EFI_VARIABLE_BOOT",
+        0x8000_0003 => "EV_EFI_BOOT_SERVICES_APPLICATION",
+        0x8000_0004 => "EV_EFI_BOOT_SERVICES_DRIVER",
+        0x8000_0005 => "EV_EFI_RUNTIME_SERVICES_DRIVER",
+        0x8000_0006 => "EV_EFI_GPT_EVENT",
+        0x8000_0007 => "EV_EFI_ACTION",
+        0x8000_0008 => "EV_EFI_PLATFORM_FIRMWARE_BLOB",
+        0x8000_0009 => "EV_EFI_HANDOFF_TABLES",
+        0x8000_000A => "EV_EFI_PLATFORM_FIRMWARE_BLOB2",
+        0x8000_000B => "EV_EFI_HANDOFF_TABLES2",
+        0x8000_0010 => "EV_EFI_HCRTM_EVENT",
+        0x8000_00E0 => "EV_EFI_VARIABLE_AUTHORITY",
+        _ => "EV_UNKNOWN",
+    }
+}
+
+fn printable(data: &[u8]) -> Option<String> {
+    let text = std::str::from_utf8(data).ok()?.trim_end_matches('\0');
+    if text.is_empty() || !text.chars().all(|c| !c.is_control() || c == '\n') {
+        return None;
+    }
+    Some(text.to_string())
+}
+
+/// Minimal little-endian reader for event log structures
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        if n > self.remaining() {
+            return Err(Error::CCAttestationError(
+                "Truncated event log entry".to_string(),
+            ));
+        }
+        let slice = &self.data[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(slice)
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let b = self.take(2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn sized_u32(&mut self) -> Result<&'a [u8]> {
+        let size = self.u32()? as usize;
+        self.take(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header_event() -> Vec<u8> {
+        let mut spec_id = Vec::new();
+        spec_id.extend(SPEC_ID_SIGNATURE);
+        spec_id.extend([0u8; 4]);
+        spec_id.extend([0, 2, 0, 2]);
+        spec_id.extend(1u32.to_le_bytes());
+        spec_id.extend(TPM_ALG_SHA384.to_le_bytes());
+        spec_id.extend(48u16.to_le_bytes());
+        spec_id.push(0);
+
+        let mut out = Vec::new();
+        out.extend(0u32.to_le_bytes());
+        out.extend(EV_NO_ACTION.to_le_bytes());
+        out.extend([0u8; 20]);
+        out.extend((spec_id.len() as u32).to_le_bytes());
+        out.extend(spec_id);
+        out
+    }
+
+    fn event(mr_index: u32, event_type: u32, digest: [u8; 48], data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend(mr_index.to_le_bytes());
+        out.extend(event_type.to_le_bytes());
+        out.extend(1u32.to_le_bytes());
+        out.extend(TPM_ALG_SHA384.to_le_bytes());
+        out.extend(digest);
+        out.extend((data.len() as u32).to_le_bytes());
+        out.extend(data);
+        out
+    }
+
+    fn extend(rtmr: [u8; 48], digest: [u8; 48]) -> [u8; 48] {
+        let mut hasher = Sha384::new();
+        hasher.update(&rtmr);
+        hasher.update(&digest);
+        hasher.finish()
+    }
+
+    fn sample_log() -> Vec<u8> {
+        let mut log = header_event();
+        log.extend(event(1, 0x8000_000A, [1; 48], b"firmware"));
+        log.extend(event(2, 0x0000_000D, [2; 48], b"grub_cmd linux /vmlinuz\0"));
+        log.extend(event(1, 0x0000_0004, [3; 48], &[0, 0, 0, 0]));
+        // padding after the last event
+        log.extend([0xFF; 32]);
+        log
+    }
+
+    #[test]
+    fn test_parse_and_replay_event_log() {
+        let log = parse_event_log(&sample_log()).unwrap();
+
+        assert_eq!(log.entries.len(), 3);
+        assert_eq!(
+            log.entries[0].event_type_name,
+            "EV_EFI_PLATFORM_FIRMWARE_BLOB2"
+        );
+        assert_eq!(
+            log.entries[1].description.as_deref(),
+            Some("grub_cmd linux /vmlinuz")
+        );
+        assert_eq!(log.entries[2].description, None);
+
+        let rtmr0 = extend(extend([0; 48], [1; 48]), [3; 48]);
+        let rtmr1 = extend([0; 48], [2; 48]);
+        assert_eq!(log.rtmrs[0], hex::encode(rtmr0));
+        assert_eq!(log.rtmrs[1], hex::encode(rtmr1));
+        assert_eq!(log.rtmrs[2], hex::encode([0u8; 48]));
+    }
+
+    #[test]
+    fn test_verify_against_report() {
+        let log = parse_event_log(&sample_log()).unwrap();
+        let rtmrs = log.replay().unwrap();
+
+        let mut report = serde_json::json!({
+            "td_info": {
+                "rtmr0": rtmrs[0].to_vec(),
+                "rtmr1": rtmrs[1].to_vec(),
+                "rtmr2": rtmrs[2].to_vec(),
+                "rtmr3": rtmrs[3].to_vec(),
+            }
+        });
+        assert!(verify_against_report(&log, &report.to_string()).unwrap());
+
+        report["td_info"]["rtmr1"] = serde_json::json!([0u8; 48].to_vec());
+        assert!(!verify_against_report(&log, &report.to_string()).unwrap());
+    }
+
+    #[test]
+    fn test_parse_event_log_rejects_invalid_input() {
+        assert!(parse_event_log(&[0u8; 8]).is_err());
+
+        let mut log = sample_log();
+        log[32] = b'X'; // corrupt the Spec ID signature
+        assert!(parse_event_log(&log).is_err());
+
+        let truncated = &sample_log()[..header_event().len() + 20];
+        assert!(parse_event_log(truncated).is_err());
+    }
+}
@@ -1,15 +1,14 @@
 use crate::error::{Error, Result};
 
+pub mod endorsement;
+pub mod event_log;
 pub mod mock;
+pub mod tpm;
 use mock::MockAttestationProvider;
 
 use tdx_workload_attestation::get_platform_name;
 use tdx_workload_attestation::provider::AttestationProvider;
 
-#[cfg(feature = "with-tdx")]
-use tdx_workload_attestation::gcp::GcpTdxHost;
-#[cfg(feature = "with-tdx")]
-use tdx_workload_attestation::host::TeeHost;
 #[cfg(feature = "with-tdx")]
 use tdx_workload_attestation::tdx::LinuxTdxProvider;
 
@@ -53,21 +52,10 @@ pub fn get_launch_measurement() -> Result<[u8; 48]> {
     Ok(measurement)
 }
 
-pub fn verify_launch_endorsement(host_platform: &str) -> Result<bool> {
-    // Get the launch endorsement from the specific host, if possible
-    match host_platform {
-        #[cfg(feature = "with-tdx")]
-        "gcp-tdx" => {
-            // Get the launch measurement for the current platform
-            let measurement =
-                get_launch_measurement().map_err(|e| Error::CCAttestationError(e.to_string()))?;
-            let gcp_host = GcpTdxHost::new(&measurement);
-            Ok(gcp_host
-                .verify_launch_endorsement()
-                .map_err(|e| Error::CCAttestationError(e.to_string()))?)
-        }
-        _ => Err(Error::CCAttestationError(format!(
-            "Launch endorsement verification not supported for platform {host_platform}"
-        ))),
-    }
+/// Verifies the VM launch measurement against the launch endorsement of `host_platform`
+pub fn verify_launch_endorsement(
+    host_platform: &str,
+    options: &endorsement::EndorsementOptions,
+) -> Result<bool> {
+    endorsement::host_platform(host_platform)?.verify_launch_endorsement(options)
 }
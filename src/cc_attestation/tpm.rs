@@ -0,0 +1,608 @@
+//! TPM 2.0 quote collection and verification.
+//!
+//! Hosts without a TEE can still provide boot-time evidence through a TPM quote: a
+//! signature by the attestation key (AK) over a digest of selected PCR values. Quotes
+//! are collected with the `tpm2-tools` utilities and verified against golden PCR
+//! values from a policy file.
+
+use crate::cli::color;
+use crate::error::{Error, Result};
+use crate::manifest::claims;
+use atlas_c2pa_lib::assertion::Assertion;
+use atlas_c2pa_lib::manifest::Manifest;
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use openssl::hash::{MessageDigest, hash};
+use openssl::pkey::PKey;
+use openssl::sign::Verifier;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// Label of the custom assertion carrying a TPM quote
+pub const TPM_QUOTE_ASSERTION_LABEL: &str = "tpm2-quote";
+
+const TPM_GENERATED_VALUE: u32 = 0xff54_4347;
+const TPM_ST_ATTEST_QUOTE: u16 = 0x8018;
+const MAX_PCR_INDEX: u8 = 23;
+
+/// A PCR bank and the set of PCR indices selected from it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PcrSelection {
+    pub bank: String,
+    pub pcrs: Vec<u8>,
+}
+
+impl PcrSelection {
+    fn message_digest(&self) -> Result<MessageDigest> {
+        match self.bank.as_str() {
+            "sha1" => Ok(MessageDigest::sha1()),
+            "sha256" => Ok(MessageDigest::sha256()),
+            "sha384" => Ok(MessageDigest::sha384()),
+            "sha512" => Ok(MessageDigest::sha512()),
+            other => Err(Error::CCAttestationError(format!(
+                "Unsupported PCR bank: {other}"
+            ))),
+        }
+    }
+
+    fn tpm_alg_id(&self) -> Result<u16> {
+        match self.bank.as_str() {
+            "sha1" => Ok(0x0004),
+            "sha256" => Ok(0x000B),
+            "sha384" => Ok(0x000C),
+            "sha512" => Ok(0x000D),
+            other => Err(Error::CCAttestationError(format!(
+                "Unsupported PCR bank: {other}"
+            ))),
+        }
+    }
+}
+
+impl FromStr for PcrSelection {
+    type Err = Error;
+
+    /// Parses the `tpm2-tools` selection syntax, e.g. `sha256:0,1,2,7`
+    fn from_str(s: &str) -> Result<Self> {
+        let (bank, list) = s.split_once(':').ok_or_else(|| {
+            Error::Validation(format!(
+                "Invalid PCR selection '{s}': expected <bank>:<pcr>[,<pcr>...]"
+            ))
+        })?;
+
+        let mut pcrs = Vec::new();
+        for item in list.split(',') {
+            let index: u8 = item.trim().parse().map_err(|_| {
+                Error::Validation(format!("Invalid PCR index '{item}' in selection '{s}'"))
+            })?;
+            if index > MAX_PCR_INDEX {
+                return Err(Error::Validation(format!(
+                    "PCR index {index} out of range (0-{MAX_PCR_INDEX})"
+                )));
+            }
+            pcrs.push(index);
+        }
+        pcrs.sort_unstable();
+        pcrs.dedup();
+
+        let selection = PcrSelection {
+            bank: bank.trim().to_lowercase(),
+            pcrs,
+        };
+        selection.tpm_alg_id()?;
+
+        Ok(selection)
+    }
+}
+
+impl fmt::Display for PcrSelection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pcrs: Vec<String> = self.pcrs.iter().map(|p| p.to_string()).collect();
+        write!(f, "{}:{}", self.bank, pcrs.join(","))
+    }
+}
+
+/// Options for collecting a TPM quote during manifest creation
+#[derive(Debug, Clone)]
+pub struct TpmQuoteOptions {
+    pub pcr_selection: PcrSelection,
+    /// AK persistent handle or context file, as accepted by `tpm2_quote -c`
+    pub ak: String,
+}
+
+/// A TPM quote together with the PCR values it covers
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TpmQuote {
+    pub pcr_selection: String,
+    /// Hex-encoded PCR values keyed by PCR index
+    pub pcr_values: BTreeMap<u8, String>,
+    /// Base64-encoded `TPMS_ATTEST` structure
+    pub attest: String,
+    /// Base64-encoded AK signature over `attest`
+    pub signature: String,
+    /// AK public key (PEM)
+    pub ak_public_key: String,
+    /// Hex-encoded qualifying data, if any
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub nonce: Option<String>,
+}
+
+/// Golden PCR values a quote is checked against
+///
+/// ```json
+/// {
+///   "bank": "sha256",
+///   "pcrs": { "0": ["3d45..."], "7": ["b5a1...", "65ca..."] }
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TpmPolicy {
+    #[serde(default)]
+    pub bank: Option<String>,
+    /// Accepted hex-encoded values for each PCR index
+    pub pcrs: BTreeMap<u8, Vec<String>>,
+}
+
+impl TpmPolicy {
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        serde_json::from_str(&contents)
+            .map_err(|e| Error::Validation(format!("Invalid TPM policy file: {e}")))
+    }
+}
+
+/// Fields of a `TPMS_ATTEST` quote needed for verification
+struct QuoteInfo {
+    extra_data: Vec<u8>,
+    selections: Vec<(u16, Vec<u8>)>,
+    pcr_digest: Vec<u8>,
+}
+
+/// Collects a quote over `selection` signed with the attestation key `ak`.
+///
+/// Requires `tpm2_quote`, `tpm2_pcrread` and `tpm2_readpublic` to be on the `PATH`.
+pub fn collect_quote(selection: &PcrSelection, ak: &str, nonce: Option<&[u8]>) -> Result<TpmQuote> {
+    let work_dir = std::env::temp_dir().join(format!("atlas-tpm-{}", Uuid::new_v4()));
+    fs::create_dir_all(&work_dir)?;
+
+    let result = collect_quote_in(&work_dir, selection, ak, nonce);
+    let _ = fs::remove_dir_all(&work_dir);
+
+    result
+}
+
+fn collect_quote_in(
+    work_dir: &Path,
+    selection: &PcrSelection,
+    ak: &str,
+    nonce: Option<&[u8]>,
+) -> Result<TpmQuote> {
+    let ak_pem = work_dir.join("ak.pem");
+    let attest_path = work_dir.join("attest.bin");
+    let sig_path = work_dir.join("sig.bin");
+    let pcrs_path = work_dir.join("pcrs.bin");
+    let selection_str = selection.to_string();
+
+    run_tool(
+        Command::new("tpm2_readpublic")
+            .args(["-c", ak, "-f", "pem", "-o"])
+            .arg(&ak_pem),
+    )?;
+
+    let mut quote_cmd = Command::new("tpm2_quote");
+    quote_cmd
+        .args([
+            "-c",
+            ak,
+            "-l",
+            &selection_str,
+            "-g",
+            &selection.bank,
+            "-f",
+            "plain",
+        ])
+        .arg("-m")
+        .arg(&attest_path)
+        .arg("-s")
+        .arg(&sig_path);
+    if let Some(nonce) = nonce {
+        quote_cmd.args(["-q", &hex::encode(nonce)]);
+    }
+    run_tool(&mut quote_cmd)?;
+
+    run_tool(
+        Command::new("tpm2_pcrread")
+            .arg(&selection_str)
+            .arg("-o")
+            .arg(&pcrs_path),
+    )?;
+
+    let digest_len = selection.message_digest()?.size();
+    let pcr_bytes = fs::read(&pcrs_path)?;
+    if pcr_bytes.len() != digest_len * selection.pcrs.len() {
+        return Err(Error::CCAttestationError(format!(
+            "Unexpected PCR output size: {} bytes for {} PCRs",
+            pcr_bytes.len(),
+            selection.pcrs.len()
+        )));
+    }
+
+    let pcr_values = selection
+        .pcrs
+        .iter()
+        .zip(pcr_bytes.chunks(digest_len))
+        .map(|(index, value)| (*index, hex::encode(value)))
+        .collect();
+
+    Ok(TpmQuote {
+        pcr_selection: selection_str,
+        pcr_values,
+        attest: STANDARD.encode(fs::read(&attest_path)?),
+        signature: STANDARD.encode(fs::read(&sig_path)?),
+        ak_public_key: fs::read_to_string(&ak_pem)?,
+        nonce: nonce.map(hex::encode),
+    })
+}
+
+/// Runs a `tpm2-tools` command, returning its standard output
+pub(crate) fn run_tool(cmd: &mut Command) -> Result<Vec<u8>> {
+    let program = cmd.get_program().to_string_lossy().to_string();
+    let output = cmd.output().map_err(|e| {
+        Error::CCAttestationError(format!(
+            "Failed to run {program} (is tpm2-tools installed?): {e}"
+        ))
+    })?;
+
+    if !output.status.success() {
+        return Err(Error::CCAttestationError(format!(
+            "{program} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(output.stdout)
+}
+
+/// Verifies a TPM quote and checks its PCR values against `policy`.
+///
+/// Returns an error if the quote is malformed, and `Ok(false)` if the signature,
+/// PCR digest, nonce or any golden value does not match.
+pub fn verify_quote(quote: &TpmQuote, policy: &TpmPolicy) -> Result<bool> {
+    let selection: PcrSelection = quote.pcr_selection.parse()?;
+    let digest = selection.message_digest()?;
+
+    if let Some(bank) = &policy.bank
+        && !bank.eq_ignore_ascii_case(&selection.bank)
+    {
+        println!(
+            "{} Quote uses PCR bank {} but policy requires {bank}",
+            color::cross_mark(),
+            selection.bank
+        );
+        return Ok(false);
+    }
+
+    let decode = |field: &str, value: &str| {
+        STANDARD
+            .decode(value)
+            .map_err(|e| Error::CCAttestationError(format!("Invalid base64 in quote {field}: {e}")))
+    };
+    let attest = decode("attest", &quote.attest)?;
+    let signature = decode("signature", &quote.signature)?;
+
+    // 1. the AK signature must cover the attestation structure
+    let ak = PKey::public_key_from_pem(quote.ak_public_key.as_bytes())
+        .map_err(|e| Error::CCAttestationError(format!("Invalid AK public key: {e}")))?;
+    let mut verifier = Verifier::new(digest, &ak)
+        .map_err(|e| Error::CCAttestationError(format!("Failed to create verifier: {e}")))?;
+    verifier
+        .update(&attest)
+        .map_err(|e| Error::CCAttestationError(e.to_string()))?;
+    if !verifier.verify(&signature).unwrap_or(false) {
+        println!(
+            "{} Quote signature does not verify with the attestation key",
+            color::cross_mark()
+        );
+        return Ok(false);
+    }
+
+    // 2. the signed structure must be a quote over the claimed selection
+    let info = parse_attest(&attest)?;
+    let expected_bitmap = pcr_bitmap(&selection.pcrs);
+    if info.selections != vec![(selection.tpm_alg_id()?, expected_bitmap)] {
+        println!(
+            "{} Quote PCR selection does not match {}",
+            color::cross_mark(),
+            quote.pcr_selection
+        );
+        return Ok(false);
+    }
+
+    let expected_nonce = match &quote.nonce {
+        Some(n) => hex::decode(n)?,
+        None => Vec::new(),
+    };
+    if info.extra_data != expected_nonce {
+        println!(
+            "{} Quote qualifying data does not match the recorded nonce",
+            color::cross_mark()
+        );
+        return Ok(false);
+    }
+
+    // 3. the reported PCR values must hash to the signed PCR digest
+    let mut concatenated = Vec::new();
+    for index in &selection.pcrs {
+        let value = quote.pcr_values.get(index).ok_or_else(|| {
+            Error::CCAttestationError(format!("Quote is missing the value of PCR {index}"))
+        })?;
+        concatenated.extend(hex::decode(value)?);
+    }
+    let computed = hash(digest, &concatenated)
+        .map_err(|e| Error::CCAttestationError(format!("Failed to hash PCR values: {e}")))?;
+    if computed.as_ref() != info.pcr_digest.as_slice() {
+        println!(
+            "{} PCR values do not match the digest signed by the TPM",
+            color::cross_mark()
+        );
+        return Ok(false);
+    }
+
+    // 4. every PCR in the policy must hold one of its golden values
+    let mut passed = true;
+    for (index, golden) in &policy.pcrs {
+        match quote.pcr_values.get(index) {
+            Some(value) if golden.iter().any(|g| g.eq_ignore_ascii_case(value)) => {}
+            Some(value) => {
+                println!(
+                    "{} PCR {index} value {value} does not match the policy",
+                    color::cross_mark()
+                );
+                passed = false;
+            }
+            None => {
+                println!(
+                    "{} PCR {index} is required by the policy but not quoted",
+                    color::cross_mark()
+                );
+                passed = false;
+            }
+        }
+    }
+
+    Ok(passed)
+}
+
+fn pcr_bitmap(pcrs: &[u8]) -> Vec<u8> {
+    let mut bitmap = vec![0u8; 3];
+    for pcr in pcrs {
+        bitmap[(*pcr / 8) as usize] |= 1 << (pcr % 8);
+    }
+    bitmap
+}
+
+/// Extracts the TPM quote assertion from a manifest
+pub fn quote_from_manifest(manifest: &Manifest) -> Result<TpmQuote> {
+    let claim = claims::signed_claim(manifest);
+
+    claim
+        .created_assertions
+        .iter()
+        .find_map(|assertion| match assertion {
+            Assertion::CustomAssertion(custom) if custom.label == TPM_QUOTE_ASSERTION_LABEL => {
+                Some(custom.data.clone())
+            }
+            _ => None,
+        })
+        .ok_or_else(|| {
+            Error::CCAttestationError("Manifest does not contain a TPM quote assertion".to_string())
+        })
+        .and_then(|data| Ok(serde_json::from_value(data)?))
+}
+
+/// Minimal big-endian reader for TPM structures
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        if self.pos + n > self.data.len() {
+            return Err(Error::CCAttestationError(
+                "Truncated TPM attestation structure".to_string(),
+            ));
+        }
+        let slice = &self.data[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let b = self.take(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let b = self.take(4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn sized(&mut self) -> Result<&'a [u8]> {
+        let size = self.u16()? as usize;
+        self.take(size)
+    }
+}
+
+fn parse_attest(data: &[u8]) -> Result<QuoteInfo> {
+    let mut r = Reader { data, pos: 0 };
+
+    if r.u32()? != TPM_GENERATED_VALUE {
+        return Err(Error::CCAttestationError(
+            "Attestation was not generated by a TPM".to_string(),
+        ));
+    }
+    if r.u16()? != TPM_ST_ATTEST_QUOTE {
+        return Err(Error::CCAttestationError(
+            "Attestation is not a TPM quote".to_string(),
+        ));
+    }
+
+    let _qualified_signer = r.sized()?;
+    let extra_data = r.sized()?.to_vec();
+    // clockInfo (clock, resetCount, restartCount, safe) and firmwareVersion
+    r.take(8 + 4 + 4 + 1 + 8)?;
+
+    let count = r.u32()?;
+    let mut selections = Vec::new();
+    for _ in 0..count {
+        let alg = r.u16()?;
+        let size = r.u8()? as usize;
+        selections.push((alg, r.take(size)?.to_vec()));
+    }
+    let pcr_digest = r.sized()?.to_vec();
+
+    Ok(QuoteInfo {
+        extra_data,
+        selections,
+        pcr_digest,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::pkey::Private;
+    use openssl::rsa::Rsa;
+    use openssl::sign::Signer;
+
+    fn make_attest(selection: &PcrSelection, extra: &[u8], pcr_digest: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend(TPM_GENERATED_VALUE.to_be_bytes());
+        out.extend(TPM_ST_ATTEST_QUOTE.to_be_bytes());
+        out.extend(4u16.to_be_bytes());
+        out.extend([0xAA; 4]);
+        out.extend((extra.len() as u16).to_be_bytes());
+        out.extend(extra);
+        out.extend([0u8; 25]);
+        out.extend(1u32.to_be_bytes());
+        out.extend(selection.tpm_alg_id().unwrap().to_be_bytes());
+        out.push(3);
+        out.extend(pcr_bitmap(&selection.pcrs));
+        out.extend((pcr_digest.len() as u16).to_be_bytes());
+        out.extend(pcr_digest);
+        out
+    }
+
+    fn make_quote(key: &PKey<Private>, values: &[(u8, [u8; 32])]) -> TpmQuote {
+        let selection = PcrSelection {
+            bank: "sha256".to_string(),
+            pcrs: values.iter().map(|(i, _)| *i).collect(),
+        };
+        let concatenated: Vec<u8> = values.iter().flat_map(|(_, v)| v.to_vec()).collect();
+        let digest = hash(MessageDigest::sha256(), &concatenated).unwrap();
+        let attest = make_attest(&selection, b"nonce", &digest);
+
+        let mut signer = Signer::new(MessageDigest::sha256(), key).unwrap();
+        signer.update(&attest).unwrap();
+        let signature = signer.sign_to_vec().unwrap();
+
+        TpmQuote {
+            pcr_selection: selection.to_string(),
+            pcr_values: values.iter().map(|(i, v)| (*i, hex::encode(v))).collect(),
+            attest: STANDARD.encode(attest),
+            signature: STANDARD.encode(signature),
+            ak_public_key: String::from_utf8(key.public_key_to_pem().unwrap()).unwrap(),
+            nonce: Some(hex::encode(b"nonce")),
+        }
+    }
+
+    fn policy_for(values: &[(u8, [u8; 32])]) -> TpmPolicy {
+        TpmPolicy {
+            bank: Some("sha256".to_string()),
+            pcrs: values
+                .iter()
+                .map(|(i, v)| (*i, vec![hex::encode(v)]))
+                .collect(),
+        }
+    }
+
+    fn test_key() -> PKey<Private> {
+        PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_parse_pcr_selection() {
+        let selection: PcrSelection = "sha256:7,0,1,1".parse().unwrap();
+        assert_eq!(selection.bank, "sha256");
+        assert_eq!(selection.pcrs, vec![0, 1, 7]);
+        assert_eq!(selection.to_string(), "sha256:0,1,7");
+
+        assert!("sha256".parse::<PcrSelection>().is_err());
+        assert!("sha256:24".parse::<PcrSelection>().is_err());
+        assert!("md5:0".parse::<PcrSelection>().is_err());
+        assert!("sha256:a".parse::<PcrSelection>().is_err());
+    }
+
+    #[test]
+    fn test_verify_quote_matches_policy() {
+        let values = [(0, [1u8; 32]), (7, [2u8; 32])];
+        let quote = make_quote(&test_key(), &values);
+
+        assert!(verify_quote(&quote, &policy_for(&values)).unwrap());
+    }
+
+    #[test]
+    fn test_verify_quote_golden_mismatch() {
+        let values = [(0, [1u8; 32]), (7, [2u8; 32])];
+        let quote = make_quote(&test_key(), &values);
+        let policy = policy_for(&[(0, [1u8; 32]), (7, [3u8; 32])]);
+
+        assert!(!verify_quote(&quote, &policy).unwrap());
+    }
+
+    #[test]
+    fn test_verify_quote_tampered_pcr_value() {
+        let values = [(0, [1u8; 32]), (7, [2u8; 32])];
+        let mut quote = make_quote(&test_key(), &values);
+        quote.pcr_values.insert(7, hex::encode([3u8; 32]));
+
+        // the policy accepts the tampered value, but the signed digest does not
+        let policy = policy_for(&[(0, [1u8; 32]), (7, [3u8; 32])]);
+        assert!(!verify_quote(&quote, &policy).unwrap());
+    }
+
+    #[test]
+    fn test_verify_quote_wrong_key() {
+        let values = [(0, [1u8; 32])];
+        let mut quote = make_quote(&test_key(), &values);
+        quote.ak_public_key = String::from_utf8(test_key().public_key_to_pem().unwrap()).unwrap();
+
+        assert!(!verify_quote(&quote, &policy_for(&values)).unwrap());
+    }
+
+    #[test]
+    fn test_verify_quote_nonce_mismatch() {
+        let values = [(0, [1u8; 32])];
+        let mut quote = make_quote(&test_key(), &values);
+        quote.nonce = Some(hex::encode(b"other"));
+
+        assert!(!verify_quote(&quote, &policy_for(&values)).unwrap());
+    }
+
+    #[test]
+    fn test_parse_attest_rejects_non_quote() {
+        let mut attest = make_attest(&"sha256:0".parse().unwrap(), &[], &[0u8; 32]);
+        attest[5] = 0x17;
+        assert!(parse_attest(&attest).is_err());
+        assert!(parse_attest(&attest[..6]).is_err());
+    }
+}
@@ -0,0 +1,91 @@
+//! Colored status symbols.
+//!
+//! Verification results are marked with ✓, ❌/✗ and ⚠. With `--color=auto` (the default)
+//! they are colored only when stdout is a terminal and `NO_COLOR` is not set, so output
+//! captured in logs or pipes is plain text and stays the same across runs.
+
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Environment variable that disables color in auto mode (see <https://no-color.org>)
+pub const NO_COLOR_ENV: &str = "NO_COLOR";
+
+/// When to color output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Color when stdout is a terminal and `NO_COLOR` is not set
+    Auto,
+    Always,
+    Never,
+}
+
+static CHOICE: AtomicU8 = AtomicU8::new(0);
+
+/// Sets when to color output, for the rest of the process
+pub fn set_color_choice(choice: ColorChoice) {
+    let value = match choice {
+        ColorChoice::Auto => 0,
+        ColorChoice::Always => 1,
+        ColorChoice::Never => 2,
+    };
+    CHOICE.store(value, Ordering::Relaxed);
+}
+
+/// Whether output is colored, see [`ColorChoice`]
+pub fn is_enabled() -> bool {
+    match CHOICE.load(Ordering::Relaxed) {
+        1 => true,
+        2 => false,
+        _ => {
+            std::env::var_os(NO_COLOR_ENV).is_none_or(|value| value.is_empty())
+                && std::env::var("TERM").map_or(true, |term| term != "dumb")
+                && std::io::stdout().is_terminal()
+        }
+    }
+}
+
+fn paint(symbol: &'static str, code: &str) -> String {
+    if is_enabled() {
+        format!("\x1b[{code}m{symbol}\x1b[0m")
+    } else {
+        symbol.to_string()
+    }
+}
+
+/// ✓, in green when colored
+pub fn check_mark() -> String {
+    paint("✓", "32")
+}
+
+/// ❌, in red when colored
+pub fn cross_mark() -> String {
+    paint("❌", "31")
+}
+
+/// ✗, in red when colored
+pub fn ballot_x() -> String {
+    paint("✗", "31")
+}
+
+/// ⚠, in yellow when colored
+pub fn warning_sign() -> String {
+    paint("⚠", "33")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color_choice() {
+        set_color_choice(ColorChoice::Always);
+        assert_eq!(check_mark(), "\x1b[32m✓\x1b[0m");
+        set_color_choice(ColorChoice::Never);
+        assert_eq!(check_mark(), "✓");
+        assert_eq!(cross_mark(), "❌");
+
+        // Tests don't run on a terminal
+        set_color_choice(ColorChoice::Auto);
+        assert_eq!(warning_sign(), "⚠");
+    }
+}
@@ -18,26 +18,570 @@ impl HashAlgorithmChoice {
     }
 }
 
+#[derive(Debug, Clone, clap::ValueEnum)]
+pub enum BuildSystemChoice {
+    Buildkit,
+    Ko,
+    Bazel,
+}
+
+impl BuildSystemChoice {
+    pub fn to_build_system(&self) -> crate::slsa::import::BuildSystem {
+        match self {
+            BuildSystemChoice::Buildkit => crate::slsa::import::BuildSystem::Buildkit,
+            BuildSystemChoice::Ko => crate::slsa::import::BuildSystem::Ko,
+            BuildSystemChoice::Bazel => crate::slsa::import::BuildSystem::Bazel,
+        }
+    }
+}
+
+#[derive(Debug, Clone, clap::ValueEnum)]
+pub enum ResultsFormatChoice {
+    LmEval,
+    OpenaiEvals,
+    Mlflow,
+}
+
+impl ResultsFormatChoice {
+    pub fn to_results_format(&self) -> crate::manifest::results::ResultsFormat {
+        match self {
+            ResultsFormatChoice::LmEval => crate::manifest::results::ResultsFormat::LmEval,
+            ResultsFormatChoice::OpenaiEvals => {
+                crate::manifest::results::ResultsFormat::OpenAiEvals
+            }
+            ResultsFormatChoice::Mlflow => crate::manifest::results::ResultsFormat::Mlflow,
+        }
+    }
+}
+
+#[derive(Debug, Clone, clap::ValueEnum)]
+pub enum ApprovalDecisionChoice {
+    Approve,
+    Reject,
+}
+
+impl ApprovalDecisionChoice {
+    pub fn to_decision(&self) -> crate::manifest::approval::ApprovalDecision {
+        match self {
+            ApprovalDecisionChoice::Approve => {
+                crate::manifest::approval::ApprovalDecision::Approved
+            }
+            ApprovalDecisionChoice::Reject => crate::manifest::approval::ApprovalDecision::Rejected,
+        }
+    }
+}
+
+#[derive(Debug, Clone, clap::ValueEnum)]
+pub enum RetirementActionChoice {
+    Deletion,
+    Retention,
+}
+
+impl RetirementActionChoice {
+    pub fn to_action(&self) -> crate::manifest::retention::RetirementAction {
+        match self {
+            RetirementActionChoice::Deletion => {
+                crate::manifest::retention::RetirementAction::Deletion
+            }
+            RetirementActionChoice::Retention => {
+                crate::manifest::retention::RetirementAction::Retention
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, clap::ValueEnum)]
+pub enum SymlinkPolicyChoice {
+    Follow,
+    Record,
+    Skip,
+}
+
+impl SymlinkPolicyChoice {
+    pub fn to_policy(&self) -> crate::manifest::ingest::SymlinkPolicy {
+        match self {
+            SymlinkPolicyChoice::Follow => crate::manifest::ingest::SymlinkPolicy::Follow,
+            SymlinkPolicyChoice::Record => crate::manifest::ingest::SymlinkPolicy::Record,
+            SymlinkPolicyChoice::Skip => crate::manifest::ingest::SymlinkPolicy::Skip,
+        }
+    }
+}
+
+#[derive(Debug, Clone, clap::ValueEnum)]
+pub enum HardlinkPolicyChoice {
+    Hash,
+    Reject,
+}
+
+impl HardlinkPolicyChoice {
+    pub fn to_policy(&self) -> crate::manifest::ingest::HardlinkPolicy {
+        match self {
+            HardlinkPolicyChoice::Hash => crate::manifest::ingest::HardlinkPolicy::Hash,
+            HardlinkPolicyChoice::Reject => crate::manifest::ingest::HardlinkPolicy::Reject,
+        }
+    }
+}
+
+#[derive(Debug, Clone, clap::ValueEnum)]
+pub enum SpecialFilePolicyChoice {
+    Skip,
+    Reject,
+}
+
+impl SpecialFilePolicyChoice {
+    pub fn to_policy(&self) -> crate::manifest::ingest::SpecialFilePolicy {
+        match self {
+            SpecialFilePolicyChoice::Skip => crate::manifest::ingest::SpecialFilePolicy::Skip,
+            SpecialFilePolicyChoice::Reject => crate::manifest::ingest::SpecialFilePolicy::Reject,
+        }
+    }
+}
+
+#[derive(Debug, Clone, clap::ValueEnum)]
+pub enum EmptyDirPolicyChoice {
+    Record,
+    Skip,
+}
+
+impl EmptyDirPolicyChoice {
+    pub fn to_policy(&self) -> crate::manifest::ingest::EmptyDirPolicy {
+        match self {
+            EmptyDirPolicyChoice::Record => crate::manifest::ingest::EmptyDirPolicy::Record,
+            EmptyDirPolicyChoice::Skip => crate::manifest::ingest::EmptyDirPolicy::Skip,
+        }
+    }
+}
+
+#[derive(Debug, Clone, clap::ValueEnum)]
+pub enum LineageDirectionChoice {
+    Up,
+    Down,
+    Both,
+}
+
+impl LineageDirectionChoice {
+    pub fn to_direction(&self) -> crate::manifest::lineage::LineageDirection {
+        match self {
+            LineageDirectionChoice::Up => crate::manifest::lineage::LineageDirection::Up,
+            LineageDirectionChoice::Down => crate::manifest::lineage::LineageDirection::Down,
+            LineageDirectionChoice::Both => crate::manifest::lineage::LineageDirection::Both,
+        }
+    }
+}
+
+#[derive(Debug, Clone, clap::ValueEnum)]
+pub enum RegistryTargetChoice {
+    Mlflow,
+    Huggingface,
+    Vertex,
+}
+
+impl RegistryTargetChoice {
+    pub fn to_target(&self) -> crate::registry::RegistryTarget {
+        match self {
+            RegistryTargetChoice::Mlflow => crate::registry::RegistryTarget::Mlflow,
+            RegistryTargetChoice::Huggingface => crate::registry::RegistryTarget::HuggingFace,
+            RegistryTargetChoice::Vertex => crate::registry::RegistryTarget::Vertex,
+        }
+    }
+}
+
+#[derive(Debug, Clone, clap::ValueEnum)]
+pub enum ListFormatChoice {
+    Text,
+    Table,
+    Csv,
+    Json,
+}
+
+#[derive(Debug, Clone, clap::ValueEnum)]
+pub enum ListColumnChoice {
+    Id,
+    Name,
+    Type,
+    #[value(name = "created_at")]
+    CreatedAt,
+    #[value(name = "valid_until")]
+    ValidUntil,
+}
+
+#[derive(Debug, Clone, clap::ValueEnum)]
+pub enum ListSortChoice {
+    #[value(name = "created_at")]
+    CreatedAt,
+    Name,
+    Type,
+}
+
+/// Output options shared by the list commands
+#[derive(Debug, Clone, clap::Args)]
+pub struct ListArgs {
+    /// Output format
+    #[arg(long = "format", value_enum, default_value = "text")]
+    pub format: ListFormatChoice,
+
+    /// Columns of table, CSV and JSON output (comma-separated)
+    #[arg(long = "columns", value_enum, value_delimiter = ',')]
+    pub columns: Vec<ListColumnChoice>,
+
+    /// Sort the manifests by this field
+    #[arg(long = "sort", value_enum)]
+    pub sort: Option<ListSortChoice>,
+
+    /// Reverse the order
+    #[arg(long = "reverse")]
+    pub reverse: bool,
+
+    /// Show at most this many manifests
+    #[arg(long = "limit")]
+    pub limit: Option<usize>,
+
+    #[command(flatten)]
+    pub signature: SignatureFilterArgs,
+}
+
+impl ListArgs {
+    pub fn to_options(&self) -> crate::manifest::presentation::ListOptions {
+        use crate::manifest::presentation::{ListColumn, ListFormat, ListOptions, ListSort};
+
+        ListOptions {
+            format: match self.format {
+                ListFormatChoice::Text => ListFormat::Text,
+                ListFormatChoice::Table => ListFormat::Table,
+                ListFormatChoice::Csv => ListFormat::Csv,
+                ListFormatChoice::Json => ListFormat::Json,
+            },
+            columns: self
+                .columns
+                .iter()
+                .map(|column| match column {
+                    ListColumnChoice::Id => ListColumn::Id,
+                    ListColumnChoice::Name => ListColumn::Name,
+                    ListColumnChoice::Type => ListColumn::Type,
+                    ListColumnChoice::CreatedAt => ListColumn::CreatedAt,
+                    ListColumnChoice::ValidUntil => ListColumn::ValidUntil,
+                })
+                .collect(),
+            sort: self.sort.as_ref().map(|sort| match sort {
+                ListSortChoice::CreatedAt => ListSort::CreatedAt,
+                ListSortChoice::Name => ListSort::Name,
+                ListSortChoice::Type => ListSort::Type,
+            }),
+            reverse: self.reverse,
+            limit: self.limit,
+            signature: self.signature.to_filter(),
+        }
+    }
+}
+
+/// Selection of manifests by signature, shared by the list commands and `verify-all`
+#[derive(Debug, Clone, clap::Args)]
+pub struct SignatureFilterArgs {
+    /// Only select signed manifests
+    #[arg(
+        long = "signed-only",
+        conflicts_with_all = ["unsigned_only", "signed_by", "signed_by_revoked"]
+    )]
+    pub signed_only: bool,
+
+    /// Only select unsigned manifests
+    #[arg(long = "unsigned-only", conflicts_with_all = ["signed_by", "signed_by_revoked"])]
+    pub unsigned_only: bool,
+
+    /// Only select manifests signed by a key with this SHA-256 fingerprint (comma-separated)
+    #[arg(
+        long = "signed-by",
+        value_delimiter = ',',
+        conflicts_with = "signed_by_revoked"
+    )]
+    pub signed_by: Vec<String>,
+
+    /// Only select manifests with a signature made by a revoked key after its revocation
+    #[arg(long = "signed-by-revoked")]
+    pub signed_by_revoked: bool,
+}
+
+impl SignatureFilterArgs {
+    pub fn to_filter(&self) -> crate::manifest::signer::SignatureFilter {
+        use crate::manifest::signer::SignatureFilter;
+
+        if self.signed_only {
+            SignatureFilter::Signed
+        } else if self.unsigned_only {
+            SignatureFilter::Unsigned
+        } else if !self.signed_by.is_empty() {
+            SignatureFilter::SignedBy(self.signed_by.clone())
+        } else if self.signed_by_revoked {
+            SignatureFilter::SignedByRevoked
+        } else {
+            SignatureFilter::Any
+        }
+    }
+}
+
+/// Ingredient selection options shared by the verify commands
+#[derive(Debug, Clone, clap::Args)]
+pub struct IngredientSelectionArgs {
+    /// Only verify the hashes of the ingredients with these names (comma-separated)
+    #[arg(long = "only-ingredients", value_delimiter = ',')]
+    pub only_ingredients: Vec<String>,
+
+    /// Skip ingredients stored remotely, such as S3 and GCS objects
+    #[arg(long = "skip-remote")]
+    pub skip_remote: bool,
+
+    /// Accept S3 and GCS objects whose store still reports the MD5 or SHA-256 recorded at
+    /// creation, re-hashing only objects without such a digest
+    #[arg(long = "fast-cloud")]
+    pub fast_cloud: bool,
+}
+
+impl IngredientSelectionArgs {
+    pub fn to_selection(&self) -> crate::manifest::common::IngredientSelection {
+        crate::manifest::common::IngredientSelection {
+            only: self.only_ingredients.clone(),
+            skip_remote: self.skip_remote,
+            fast_cloud: self.fast_cloud,
+        }
+    }
+}
+
+/// Quorum options shared by the verify commands
+#[derive(Debug, Clone, clap::Args)]
+pub struct QuorumArgs {
+    /// Pass when this fraction of the ingredients verifies, e.g. 0.9 or 90%, reporting
+    /// unreachable ingredients instead of failing on them
+    #[arg(long = "quorum", value_parser = parse_quorum)]
+    pub quorum: Option<f64>,
+
+    /// Ingredients that must verify in quorum mode (comma-separated)
+    #[arg(long = "require-ingredients", value_delimiter = ',')]
+    pub require_ingredients: Vec<String>,
+
+    /// Write the coverage report of quorum mode to this file as JSON
+    #[arg(long = "coverage-report")]
+    pub coverage_report: Option<PathBuf>,
+}
+
+impl QuorumArgs {
+    /// The quorum rule, if any quorum option is given. Without `--quorum`, every ingredient
+    /// must verify, or only the required ones if some are named.
+    pub fn to_rule(&self) -> Option<crate::manifest::coverage::QuorumRule> {
+        if self.quorum.is_none()
+            && self.require_ingredients.is_empty()
+            && self.coverage_report.is_none()
+        {
+            return None;
+        }
+
+        Some(crate::manifest::coverage::QuorumRule {
+            min_fraction: self
+                .quorum
+                .unwrap_or(if self.require_ingredients.is_empty() {
+                    1.0
+                } else {
+                    0.0
+                }),
+            required: self.require_ingredients.clone(),
+        })
+    }
+}
+
+/// Outputs of a successful verification, shared by the verify commands
+#[derive(Debug, Clone, clap::Args)]
+pub struct VerificationOutputArgs {
+    /// Write a status badge for registry UIs to this file: SVG if it ends in .svg, and
+    /// otherwise shields.io endpoint JSON
+    #[arg(long = "badge")]
+    pub badge: Option<PathBuf>,
+
+    /// Write a signed record of the verification to this file (compact JWS)
+    #[arg(long = "verification-record", requires = "record_key")]
+    pub verification_record: Option<PathBuf>,
+
+    /// Verifier's private key signing the record: a PEM or DER file, env:<VAR> or - (stdin)
+    #[arg(long = "record-key")]
+    pub record_key: Option<PathBuf>,
+
+    /// DID of the verifier (default: did:key of an Ed25519 or P-256 record key)
+    #[arg(long = "verifier")]
+    pub verifier: Option<String>,
+
+    /// ID of the policy the manifest was verified against, recorded in the record
+    #[arg(long = "policy-id", default_value = crate::manifest::badge::DEFAULT_POLICY_ID)]
+    pub policy_id: String,
+}
+
+fn parse_quorum(value: &str) -> std::result::Result<f64, String> {
+    crate::manifest::coverage::QuorumRule::parse_fraction(value).map_err(|e| e.to_string())
+}
+
+/// Assertion encryption options shared by the create commands
+#[derive(Debug, Clone, clap::Args)]
+pub struct EncryptionArgs {
+    /// Labels of assertions to encrypt (comma-separated), e.g. software.python_environment,
+    /// or c2pa.actions for the action parameters
+    #[arg(
+        long = "encrypt-assertions",
+        value_delimiter = ',',
+        requires = "recipients"
+    )]
+    pub encrypt_assertions: Vec<String>,
+
+    /// age X25519 recipient (age1...) that can decrypt the encrypted assertions; may be
+    /// repeated
+    #[arg(long = "recipient", requires = "encrypt_assertions")]
+    pub recipients: Vec<String>,
+}
+
+impl EncryptionArgs {
+    pub fn to_encryption(
+        &self,
+    ) -> crate::error::Result<Option<crate::manifest::encryption::AssertionEncryption>> {
+        if self.encrypt_assertions.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(crate::manifest::encryption::AssertionEncryption {
+            labels: self.encrypt_assertions.clone(),
+            recipients: self
+                .recipients
+                .iter()
+                .map(|recipient| recipient.parse())
+                .collect::<crate::error::Result<_>>()?,
+        }))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ShowOutputChoice {
+    /// Sectioned, human-readable layout
+    Text,
+    Json,
+    /// Needs the yaml feature
+    Yaml,
+    /// Identity of the manifest and counts of its parts
+    Summary,
+}
+
+impl ShowOutputChoice {
+    pub fn to_format(self) -> crate::manifest::presentation::ShowFormat {
+        use crate::manifest::presentation::ShowFormat;
+
+        match self {
+            ShowOutputChoice::Text => ShowFormat::Text,
+            ShowOutputChoice::Json => ShowFormat::Json,
+            ShowOutputChoice::Yaml => ShowFormat::Yaml,
+            ShowOutputChoice::Summary => ShowFormat::Summary,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ErrorFormatChoice {
+    /// Human-readable message with its error code
+    Text,
+    /// JSON object with code, category, message and retryable fields
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ProgressChoice {
+    /// Each verification result as it completes, then a summary
+    Text,
+    /// Also one JSON event per result and summary on stderr
+    Ndjson,
+}
+
+impl ProgressChoice {
+    pub fn to_progress_format(self) -> crate::manifest::verification::ProgressFormat {
+        match self {
+            ProgressChoice::Text => crate::manifest::verification::ProgressFormat::Text,
+            ProgressChoice::Ndjson => crate::manifest::verification::ProgressFormat::Ndjson,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ColorChoice {
+    /// Color when stdout is a terminal and NO_COLOR is not set
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn to_color_choice(self) -> crate::cli::color::ColorChoice {
+        match self {
+            ColorChoice::Auto => crate::cli::color::ColorChoice::Auto,
+            ColorChoice::Always => crate::cli::color::ColorChoice::Always,
+            ColorChoice::Never => crate::cli::color::ColorChoice::Never,
+        }
+    }
+}
+
 #[derive(Debug, Subcommand)]
+#[allow(clippy::large_enum_variant)]
 pub enum DatasetCommands {
     /// Create a new dataset manifest
     Create {
         #[arg(long = "paths", num_args = 1.., value_delimiter = ',')]
         paths: Vec<PathBuf>,
 
-        /// Names for each ingredient (comma-separated)
+        /// Names for each ingredient (comma-separated); paths without a name are named after
+        /// their relative path
         #[arg(long = "ingredient-names", num_args = 1.., value_delimiter = ',')]
         ingredient_names: Vec<String>,
 
+        /// C2PA relationship of each path's ingredients: parentOf, componentOf (default) or
+        /// inputTo (comma-separated)
+        #[arg(long = "ingredient-relationships", num_args = 1.., value_delimiter = ',')]
+        ingredient_relationships: Vec<String>,
+
+        /// Record each file in tar/zip archives as its own ingredient
+        #[arg(long = "expand-archives")]
+        expand_archives: bool,
+
+        /// Record row counts, schema and column statistics of Parquet, CSV and JSONL files
+        #[arg(long = "profile-data")]
+        profile_data: bool,
+
+        /// Maximum number of bytes sampled from each CSV or JSONL file when profiling
+        #[arg(long = "max-sample-bytes", default_value_t = crate::manifest::profile::DEFAULT_MAX_SAMPLE_BYTES)]
+        max_sample_bytes: u64,
+
+        /// Concurrent ranged reads per object when hashing s3:// and gs:// paths
+        #[arg(long = "stream-connections", default_value_t = crate::manifest::object_store::DEFAULT_CONNECTIONS)]
+        stream_connections: usize,
+
+        /// Symlinks in directory paths: follow them, record them, or skip them
+        #[arg(long = "symlinks", value_enum, default_value = "skip")]
+        symlinks: SymlinkPolicyChoice,
+
+        /// Files with several hard links in directory paths: hash them or reject them
+        #[arg(long = "hardlinks", value_enum, default_value = "reject")]
+        hardlinks: HardlinkPolicyChoice,
+
+        /// Sockets, FIFOs and device files in directory paths: skip them or reject them
+        #[arg(long = "special-files", value_enum, default_value = "reject")]
+        special_files: SpecialFilePolicyChoice,
+
+        /// Empty directories in directory paths: record them or skip them
+        #[arg(long = "empty-dirs", value_enum, default_value = "skip")]
+        empty_dirs: EmptyDirPolicyChoice,
+
+        /// Record local ingredient URLs relative to this directory, for portable manifests
+        #[arg(long = "path-base")]
+        path_base: Option<PathBuf>,
+
         /// Dataset name
         #[arg(long = "name")]
         name: String,
 
-        /// Author organization name
+        /// Author organization name or DID (e.g. did:web:example.com)
         #[arg(long = "author-org")]
         author_org: Option<String>,
 
-        /// Author name
+        /// Author name or DID
         #[arg(long = "author-name")]
         author_name: Option<String>,
 
@@ -45,18 +589,47 @@ pub enum DatasetCommands {
         #[arg(long = "description")]
         description: Option<String>,
 
+        /// SPDX license identifier of the asset (e.g. Apache-2.0)
+        #[arg(long = "license")]
+        license: Option<String>,
+
+        #[command(flatten)]
+        encryption: EncryptionArgs,
+
+        /// Derive the manifest ID from its content (urn:c2pa:sha256-<hex>) instead of a random UUID
+        #[arg(long = "content-addressed-id")]
+        content_addressed_id: bool,
+
+        /// Store a new manifest even if an identical one is already stored
+        #[arg(long = "force-new")]
+        force_new: bool,
+
         /// Optional linked manifest IDs
         #[arg(long = "linked-manifests")]
         linked_manifests: Option<Vec<String>>,
 
-        /// Path to private key file for signing (PEM format)
+        /// Private key for signing: a PEM or DER file, env:<VAR>, - (stdin) or piv:<SLOT>
         #[arg(long = "key")]
         key: Option<PathBuf>,
 
+        /// Path to an ML-DSA private key for hybrid signing, used together with --key
+        /// (experimental, needs the `pq` feature)
+        #[arg(long = "pq-key", requires = "key")]
+        pq_key: Option<PathBuf>,
+
         /// Hash algorithm to use for signing (default: sha384)
         #[arg(long = "hash-alg", value_enum, default_value = "sha384")]
         hash_alg: HashAlgorithmChoice,
 
+        /// Expiry of the manifest: RFC 3339 timestamp, date (YYYY-MM-DD) or duration (e.g. 90d)
+        #[arg(long = "valid-until")]
+        valid_until: Option<String>,
+
+        /// Creation time to record instead of now, for historical artifacts: RFC 3339
+        /// timestamp or date (YYYY-MM-DD), stored in UTC
+        #[arg(long = "created-at")]
+        created_at: Option<String>,
+
         /// Only print manifest without storing
         #[arg(long = "print")]
         print: bool,
@@ -65,6 +638,10 @@ pub enum DatasetCommands {
         #[arg(long = "encoding", default_value = "json")]
         encoding: String,
 
+        /// Write the output to a file (CBOR is written as binary)
+        #[arg(long = "output")]
+        output: Option<PathBuf>,
+
         /// Storage backend (local or rekor)
         #[arg(long = "storage-type", default_value = "database")]
         storage_type: Box<String>,
@@ -76,9 +653,24 @@ pub enum DatasetCommands {
         /// Collect the underlying TDX attestation, if available
         #[arg(long = "with-tdx", default_value = "false")]
         with_tdx: bool,
+
+        /// Collect a TPM 2.0 quote, for hosts without a TEE
+        #[arg(long = "with-tpm", default_value = "false")]
+        with_tpm: bool,
+
+        /// PCR selection to quote (tpm2-tools syntax, e.g. sha256:0,1,7)
+        #[arg(long = "tpm-pcrs", default_value = "sha256:0,1,2,3,4,5,6,7")]
+        tpm_pcrs: String,
+
+        /// Attestation key handle or context file used to sign the quote
+        #[arg(long = "tpm-ak", default_value = "0x81010002")]
+        tpm_ak: String,
     },
     /// List all dataset manifests
     List {
+        #[command(flatten)]
+        list: ListArgs,
+
         /// Storage backend (local or rekor)
         #[arg(long = "storage-type", default_value = "database")]
         storage_type: Box<String>,
@@ -91,6 +683,20 @@ pub enum DatasetCommands {
         /// Manifest ID to verify
         #[arg(long = "id")]
         id: String,
+
+        /// Directory against which relative ingredient URLs are resolved
+        #[arg(long = "artifact-root")]
+        artifact_root: Option<PathBuf>,
+
+        #[command(flatten)]
+        selection: IngredientSelectionArgs,
+
+        #[command(flatten)]
+        quorum: QuorumArgs,
+
+        #[command(flatten)]
+        outputs: VerificationOutputArgs,
+
         /// Storage backend (local or rekor)
         #[arg(long = "storage-type", default_value = "database")]
         storage_type: Box<String>,
@@ -99,29 +705,33 @@ pub enum DatasetCommands {
         #[arg(long = "storage-url", default_value = "http://localhost:8080")]
         storage_url: Box<String>,
     },
-}
+    /// Create manifests for the train, validation and test splits of a stored dataset
+    Split {
+        /// Manifest ID of the dataset the splits were taken from
+        #[arg(long = "parent")]
+        parent: String,
 
-#[derive(Debug, Subcommand)]
-pub enum ModelCommands {
-    /// Create a new signed model manifest compliant with OpenSSF Model Signing (OMS) specification
-    Create {
-        /// Paths to the model ingredient files
-        #[arg(long = "paths", num_args = 1.., value_delimiter = ',')]
-        paths: Vec<PathBuf>,
+        /// Files of the training split (comma-separated)
+        #[arg(long = "train", num_args = 1.., value_delimiter = ',')]
+        train: Vec<PathBuf>,
 
-        /// Names for each ingredient (comma-separated)
-        #[arg(long = "ingredient-names", num_args = 1.., value_delimiter = ',')]
-        ingredient_names: Vec<String>,
+        /// Files of the validation split (comma-separated)
+        #[arg(long = "val", num_args = 1.., value_delimiter = ',')]
+        val: Vec<PathBuf>,
 
-        /// Model name
+        /// Files of the test split (comma-separated)
+        #[arg(long = "test", num_args = 1.., value_delimiter = ',')]
+        test: Vec<PathBuf>,
+
+        /// Name of the split manifests, followed by the split (default: the parent's name)
         #[arg(long = "name")]
-        name: String,
+        name: Option<String>,
 
-        /// Author organization name
+        /// Author organization name or DID (e.g. did:web:example.com)
         #[arg(long = "author-org")]
         author_org: Option<String>,
 
-        /// Author name
+        /// Author name or DID
         #[arg(long = "author-name")]
         author_name: Option<String>,
 
@@ -129,44 +739,37 @@ pub enum ModelCommands {
         #[arg(long = "description")]
         description: Option<String>,
 
-        /// Optional linked manifest IDs
-        #[arg(long = "linked-manifests")]
-        linked_manifests: Option<Vec<String>>,
-
-        /// Path to private key file for signing (PEM format)
+        /// Private key for signing: a PEM or DER file, env:<VAR>, - (stdin) or piv:<SLOT>
         #[arg(long = "key")]
         key: Option<PathBuf>,
 
+        /// Path to an ML-DSA private key for hybrid signing, used together with --key
+        /// (experimental, needs the `pq` feature)
+        #[arg(long = "pq-key", requires = "key")]
+        pq_key: Option<PathBuf>,
+
         /// Hash algorithm to use for signing (default: sha384)
         #[arg(long = "hash-alg", value_enum, default_value = "sha384")]
         hash_alg: HashAlgorithmChoice,
 
-        /// Only print manifest without storing
-        #[arg(long = "print")]
-        print: bool,
-
-        /// Encoding (json or cbor)
-        #[arg(long = "encoding", default_value = "json")]
-        encoding: String,
-
-        /// Format (standalone c2pa or oms)
-        #[arg(long = "format", default_value = "standalone")]
-        format: String,
-
-        /// Storage backend (local or rekor)
+        /// Storage backend (database or local-fs)
         #[arg(long = "storage-type", default_value = "database")]
         storage_type: Box<String>,
 
         /// Storage URL
         #[arg(long = "storage-url", default_value = "http://localhost:8080")]
         storage_url: Box<String>,
-
-        /// Collect the underlying CC attestation, if available
-        #[arg(long = "with-tdx", default_value = "false")]
-        with_tdx: bool,
     },
-    /// List all model manifests
-    List {
+    /// Report files shared between datasets, e.g. test data contaminating a training set
+    DedupCheck {
+        /// Dataset manifest IDs to compare (comma-separated; default: all datasets)
+        #[arg(long = "ids", value_delimiter = ',')]
+        ids: Vec<String>,
+
+        /// Output format (table or json)
+        #[arg(long = "format", default_value = "table")]
+        format: String,
+
         /// Storage backend (local or rekor)
         #[arg(long = "storage-type", default_value = "database")]
         storage_type: Box<String>,
@@ -175,11 +778,59 @@ pub enum ModelCommands {
         #[arg(long = "storage-url", default_value = "http://localhost:8080")]
         storage_url: Box<String>,
     },
-    Verify {
-        /// Manifest ID to verify
+    /// Record the deletion or retention of a dataset in a signed manifest
+    Retire {
+        /// Manifest ID of the retired dataset
         #[arg(long = "id")]
         id: String,
-        /// Storage backend (local or rekor)
+
+        /// Evidence of the action, e.g. a deletion log
+        #[arg(long = "evidence")]
+        evidence: PathBuf,
+
+        /// What was done with the data
+        #[arg(long = "action", value_enum, default_value = "deletion")]
+        action: RetirementActionChoice,
+
+        /// Why the dataset was retired, e.g. the reference of an erasure request
+        #[arg(long = "reason")]
+        reason: Option<String>,
+
+        /// End of the retention period: RFC 3339 timestamp, date (YYYY-MM-DD) or duration
+        /// (e.g. 365d); required for retention
+        #[arg(long = "retain-until")]
+        retain_until: Option<String>,
+
+        /// Name of the manifest (default: the dataset's name, followed by the action)
+        #[arg(long = "name")]
+        name: Option<String>,
+
+        /// Author organization name or DID (e.g. did:web:example.com)
+        #[arg(long = "author-org")]
+        author_org: Option<String>,
+
+        /// Author name or DID
+        #[arg(long = "author-name")]
+        author_name: Option<String>,
+
+        /// Optional description
+        #[arg(long = "description")]
+        description: Option<String>,
+
+        /// Private key for signing: a PEM or DER file, env:<VAR>, - (stdin) or piv:<SLOT>
+        #[arg(long = "key")]
+        key: Option<PathBuf>,
+
+        /// Path to an ML-DSA private key for hybrid signing, used together with --key
+        /// (experimental, needs the `pq` feature)
+        #[arg(long = "pq-key", requires = "key")]
+        pq_key: Option<PathBuf>,
+
+        /// Hash algorithm to use for signing (default: sha384)
+        #[arg(long = "hash-alg", value_enum, default_value = "sha384")]
+        hash_alg: HashAlgorithmChoice,
+
+        /// Storage backend (database or local-fs)
         #[arg(long = "storage-type", default_value = "database")]
         storage_type: Box<String>,
 
@@ -187,14 +838,246 @@ pub enum ModelCommands {
         #[arg(long = "storage-url", default_value = "http://localhost:8080")]
         storage_url: Box<String>,
     },
-    LinkDataset {
-        /// Model manifest ID
-        #[arg(long = "model-id")]
-        model_id: String,
-
-        /// Dataset manifest ID
-        #[arg(long = "dataset-id")]
-        dataset_id: String,
+}
+
+#[derive(Debug, Subcommand)]
+#[allow(clippy::large_enum_variant)]
+pub enum ModelCommands {
+    /// Create a new signed model manifest compliant with OpenSSF Model Signing (OMS) specification
+    Create {
+        /// Paths to the model ingredient files
+        #[arg(long = "paths", num_args = 1.., value_delimiter = ',')]
+        paths: Vec<PathBuf>,
+
+        /// Names for each ingredient (comma-separated); paths without a name are named after
+        /// their relative path
+        #[arg(long = "ingredient-names", num_args = 1.., value_delimiter = ',')]
+        ingredient_names: Vec<String>,
+
+        /// C2PA relationship of each path's ingredients: parentOf, componentOf (default) or
+        /// inputTo (comma-separated)
+        #[arg(long = "ingredient-relationships", num_args = 1.., value_delimiter = ',')]
+        ingredient_relationships: Vec<String>,
+
+        /// Symlinks in directory paths: follow them, record them, or skip them
+        #[arg(long = "symlinks", value_enum, default_value = "skip")]
+        symlinks: SymlinkPolicyChoice,
+
+        /// Files with several hard links in directory paths: hash them or reject them
+        #[arg(long = "hardlinks", value_enum, default_value = "reject")]
+        hardlinks: HardlinkPolicyChoice,
+
+        /// Sockets, FIFOs and device files in directory paths: skip them or reject them
+        #[arg(long = "special-files", value_enum, default_value = "reject")]
+        special_files: SpecialFilePolicyChoice,
+
+        /// Empty directories in directory paths: record them or skip them
+        #[arg(long = "empty-dirs", value_enum, default_value = "skip")]
+        empty_dirs: EmptyDirPolicyChoice,
+
+        /// Record local ingredient URLs relative to this directory, for portable manifests
+        #[arg(long = "path-base")]
+        path_base: Option<PathBuf>,
+
+        /// Dataset manifests the model was trained on (comma-separated)
+        #[arg(long = "dataset-ids", num_args = 1.., value_delimiter = ',')]
+        dataset_ids: Vec<String>,
+
+        /// Model name
+        #[arg(long = "name")]
+        name: String,
+
+        /// Author organization name or DID (e.g. did:web:example.com)
+        #[arg(long = "author-org")]
+        author_org: Option<String>,
+
+        /// Author name or DID
+        #[arg(long = "author-name")]
+        author_name: Option<String>,
+
+        /// Optional description
+        #[arg(long = "description")]
+        description: Option<String>,
+
+        /// SPDX license identifier of the asset (e.g. Apache-2.0)
+        #[arg(long = "license")]
+        license: Option<String>,
+
+        /// Take the authors, description, license, links and custom assertions not given
+        /// from an existing model manifest, such as that of the previous version
+        #[arg(long = "like")]
+        like: Option<String>,
+
+        #[command(flatten)]
+        encryption: EncryptionArgs,
+
+        /// Derive the manifest ID from its content (urn:c2pa:sha256-<hex>) instead of a random UUID
+        #[arg(long = "content-addressed-id")]
+        content_addressed_id: bool,
+
+        /// Store a new manifest even if an identical one is already stored
+        #[arg(long = "force-new")]
+        force_new: bool,
+
+        /// Optional linked manifest IDs
+        #[arg(long = "linked-manifests")]
+        linked_manifests: Option<Vec<String>>,
+
+        /// Private key for signing: a PEM or DER file, env:<VAR>, - (stdin) or piv:<SLOT>
+        #[arg(long = "key")]
+        key: Option<PathBuf>,
+
+        /// Path to an ML-DSA private key for hybrid signing, used together with --key
+        /// (experimental, needs the `pq` feature)
+        #[arg(long = "pq-key", requires = "key")]
+        pq_key: Option<PathBuf>,
+
+        /// Hash algorithm to use for signing (default: sha384)
+        #[arg(long = "hash-alg", value_enum, default_value = "sha384")]
+        hash_alg: HashAlgorithmChoice,
+
+        /// Expiry of the manifest: RFC 3339 timestamp, date (YYYY-MM-DD) or duration (e.g. 90d)
+        #[arg(long = "valid-until")]
+        valid_until: Option<String>,
+
+        /// Creation time to record instead of now, for historical artifacts: RFC 3339
+        /// timestamp or date (YYYY-MM-DD), stored in UTC
+        #[arg(long = "created-at")]
+        created_at: Option<String>,
+
+        /// Only print manifest without storing
+        #[arg(long = "print")]
+        print: bool,
+
+        /// Encoding (json or cbor)
+        #[arg(long = "encoding", default_value = "json")]
+        encoding: String,
+
+        /// Write the output to a file (CBOR is written as binary)
+        #[arg(long = "output")]
+        output: Option<PathBuf>,
+
+        /// Format (standalone c2pa or oms)
+        #[arg(long = "format", default_value = "standalone")]
+        format: String,
+
+        /// Storage backend (local or rekor)
+        #[arg(long = "storage-type", default_value = "database")]
+        storage_type: Box<String>,
+
+        /// Storage URL
+        #[arg(long = "storage-url", default_value = "http://localhost:8080")]
+        storage_url: Box<String>,
+
+        /// Collect the underlying CC attestation, if available
+        #[arg(long = "with-tdx", default_value = "false")]
+        with_tdx: bool,
+
+        /// Collect a TPM 2.0 quote, for hosts without a TEE
+        #[arg(long = "with-tpm", default_value = "false")]
+        with_tpm: bool,
+
+        /// PCR selection to quote (tpm2-tools syntax, e.g. sha256:0,1,7)
+        #[arg(long = "tpm-pcrs", default_value = "sha256:0,1,2,3,4,5,6,7")]
+        tpm_pcrs: String,
+
+        /// Attestation key handle or context file used to sign the quote
+        #[arg(long = "tpm-ak", default_value = "0x81010002")]
+        tpm_ak: String,
+    },
+    /// List all model manifests
+    List {
+        #[command(flatten)]
+        list: ListArgs,
+
+        /// Storage backend (local or rekor)
+        #[arg(long = "storage-type", default_value = "database")]
+        storage_type: Box<String>,
+
+        /// Storage URL
+        #[arg(long = "storage-url", default_value = "http://localhost:8080")]
+        storage_url: Box<String>,
+    },
+    Verify {
+        /// Manifest ID to verify
+        #[arg(long = "id", required_unless_present = "envelope")]
+        id: Option<String>,
+
+        /// Format (standalone c2pa or oms)
+        #[arg(long = "format", default_value = "standalone")]
+        format: String,
+
+        /// OMS envelope to verify, as written by `model create --format oms`
+        #[arg(long = "envelope")]
+        envelope: Option<PathBuf>,
+
+        /// PEM public key of the OMS envelope's signer
+        #[arg(long = "public-key")]
+        public_key: Option<PathBuf>,
+
+        /// Directory against which relative ingredient URLs are resolved
+        #[arg(long = "artifact-root")]
+        artifact_root: Option<PathBuf>,
+
+        #[command(flatten)]
+        selection: IngredientSelectionArgs,
+
+        #[command(flatten)]
+        quorum: QuorumArgs,
+
+        #[command(flatten)]
+        outputs: VerificationOutputArgs,
+
+        /// Number of reviewer approvals required for the model to be release-ready
+        #[arg(long = "required-approvals", default_value = "0")]
+        required_approvals: usize,
+
+        /// Only count approvals given in this role
+        #[arg(long = "approval-role")]
+        approval_role: Option<String>,
+
+        /// Storage backend (local or rekor)
+        #[arg(long = "storage-type", default_value = "database")]
+        storage_type: Box<String>,
+
+        /// Storage URL
+        #[arg(long = "storage-url", default_value = "http://localhost:8080")]
+        storage_url: Box<String>,
+    },
+    LinkDataset {
+        /// Model manifest ID
+        #[arg(long = "model-id")]
+        model_id: String,
+
+        /// Dataset manifest IDs (comma-separated)
+        #[arg(long = "dataset-id", alias = "dataset-ids", num_args = 1.., value_delimiter = ',', required = true)]
+        dataset_ids: Vec<String>,
+
+        /// Storage backend (local or rekor)
+        #[arg(long = "storage-type", default_value = "database")]
+        storage_type: Box<String>,
+
+        /// Storage URL
+        #[arg(long = "storage-url", default_value = "http://localhost:8080")]
+        storage_url: Box<String>,
+    },
+    /// Show the base models, datasets, software, fine-tunes and deployments of a model
+    Lineage {
+        /// Model manifest ID
+        #[arg(long = "id")]
+        id: String,
+
+        /// Walk up to ancestors, down to descendants, or both
+        #[arg(long = "direction", value_enum, default_value = "both")]
+        direction: LineageDirectionChoice,
+
+        /// Max number of levels shown in each direction (default: 10)
+        #[arg(long = "max-depth", default_value = "10")]
+        max_depth: u32,
+
+        /// Output format (tree or json)
+        #[arg(long = "format", default_value = "tree")]
+        format: String,
 
         /// Storage backend (local or rekor)
         #[arg(long = "storage-type", default_value = "database")]
@@ -227,6 +1110,51 @@ pub enum ManifestCommands {
         storage_url: Box<String>,
     },
 
+    /// Create many cross-references in one pass, storing each source manifest once
+    LinkBatch {
+        /// Link specification file (YAML or JSON) with a list of sources and their targets
+        #[arg(
+            long = "spec",
+            conflicts_with = "source",
+            required_unless_present = "source"
+        )]
+        spec: Option<PathBuf>,
+
+        /// Source manifest ID
+        #[arg(short, long, requires = "targets")]
+        source: Option<String>,
+
+        /// Target manifest IDs
+        #[arg(long = "targets", num_args = 1.., value_delimiter = ',')]
+        targets: Vec<String>,
+
+        /// Storage backend (local or rekor)
+        #[arg(long = "storage-type", default_value = "database")]
+        storage_type: Box<String>,
+
+        /// Storage URL
+        #[arg(long = "storage-url", default_value = "http://localhost:8080")]
+        storage_url: Box<String>,
+    },
+
+    /// List manifests
+    List {
+        #[command(flatten)]
+        list: ListArgs,
+
+        /// Only list manifests expiring within this duration (e.g. 30d), including expired ones
+        #[arg(long = "expiring-within")]
+        expiring_within: Option<String>,
+
+        /// Storage backend (local or rekor)
+        #[arg(long = "storage-type", default_value = "database")]
+        storage_type: Box<String>,
+
+        /// Storage URL
+        #[arg(long = "storage-url", default_value = "http://localhost:8080")]
+        storage_url: Box<String>,
+    },
+
     /// Show manifest details
     Show {
         /// Manifest ID to show
@@ -240,6 +1168,18 @@ pub enum ManifestCommands {
         /// Storage URL
         #[arg(long = "storage-url", default_value = "http://localhost:8080")]
         storage_url: Box<String>,
+
+        /// Output format
+        #[arg(long = "output", value_enum, default_value = "text")]
+        output: ShowOutputChoice,
+
+        /// Show only the values at this path, such as ingredients[].data.hash (repeatable)
+        #[arg(long = "field")]
+        field: Vec<String>,
+
+        /// age identity file (AGE-SECRET-KEY-1...) to decrypt encrypted assertions with
+        #[arg(long = "identity")]
+        identity: Option<PathBuf>,
     },
 
     /// Validate manifest cross-references
@@ -301,60 +1241,33 @@ pub enum ManifestCommands {
         #[arg(long = "max-depth", default_value = "10")]
         max_depth: u32,
     },
-}
-/// Commands for evaluation results
-#[derive(Subcommand)]
-pub enum EvaluationCommands {
-    /// Create a new evaluation result manifest
-    Create {
-        /// Path to evaluation results file
-        #[arg(long = "path")]
-        path: PathBuf,
-
-        /// Evaluation name
-        #[arg(long = "name")]
-        name: String,
-
-        /// Model ID that was evaluated
-        #[arg(long = "model-id")]
-        model_id: String,
-
-        /// Evaluation dataset ID
-        #[arg(long = "dataset-id")]
-        dataset_id: String,
 
-        /// Evaluation metrics (key=value pairs)
-        #[arg(long = "metrics", num_args = 1.., value_delimiter = ',')]
-        metrics: Vec<String>,
+    /// Append a signed reviewer approval to a manifest
+    Approve {
+        /// ID of the manifest to approve
+        #[arg(long = "id")]
+        id: String,
 
-        /// Author organization name
-        #[arg(long = "author-org")]
-        author_org: Option<String>,
+        /// Role of the reviewer (e.g. reviewer, security, release-manager)
+        #[arg(long = "role")]
+        role: String,
 
-        /// Author name
-        #[arg(long = "author-name")]
-        author_name: Option<String>,
+        /// Name or DID of the reviewer (default: fingerprint of the reviewer key)
+        #[arg(long = "reviewer")]
+        reviewer: Option<String>,
 
-        /// Optional description
-        #[arg(long = "description")]
-        description: Option<String>,
+        /// Review decision
+        #[arg(long = "decision", value_enum, default_value = "approve")]
+        decision: ApprovalDecisionChoice,
 
-        /// Path to private key file for signing (PEM format)
+        /// Reviewer's private key: a PEM or DER file, env:<VAR> or - (stdin)
         #[arg(long = "key")]
-        key: Option<PathBuf>,
+        key: PathBuf,
 
-        /// Hash algorithm to use for signing (default: sha384)
+        /// Hash algorithm to use for binding and signing (default: sha384)
         #[arg(long = "hash-alg", value_enum, default_value = "sha384")]
         hash_alg: HashAlgorithmChoice,
 
-        /// Only print manifest without storing
-        #[arg(long = "print")]
-        print: bool,
-
-        /// Output encoding (json or cbor)
-        #[arg(long = "encoding", default_value = "json")]
-        encoding: String,
-
         /// Storage backend (local or rekor)
         #[arg(long = "storage-type", default_value = "database")]
         storage_type: Box<String>,
@@ -364,8 +1277,29 @@ pub enum EvaluationCommands {
         storage_url: Box<String>,
     },
 
-    /// List all evaluation results
-    List {
+    /// Redact assertions of a manifest, leaving salted hash placeholders and a redaction record
+    Redact {
+        /// ID of the manifest to redact
+        #[arg(long = "id")]
+        id: String,
+
+        /// Label of an assertion to redact (repeatable)
+        #[arg(long = "assertion", required = true)]
+        assertions: Vec<String>,
+
+        /// Reason for the redaction, recorded in the manifest
+        #[arg(long = "reason")]
+        reason: Option<String>,
+
+        /// Private key re-signing a manifest signed before its assertions were salted: a PEM
+        /// or DER file, env:<VAR> or - (stdin). Other manifests keep their signature
+        #[arg(long = "key")]
+        key: Option<PathBuf>,
+
+        /// Hash algorithm of the placeholders of an unsigned manifest and of re-signing (default: sha384)
+        #[arg(long = "hash-alg", value_enum, default_value = "sha384")]
+        hash_alg: HashAlgorithmChoice,
+
         /// Storage backend (local or rekor)
         #[arg(long = "storage-type", default_value = "database")]
         storage_type: Box<String>,
@@ -375,12 +1309,28 @@ pub enum EvaluationCommands {
         storage_url: Box<String>,
     },
 
-    /// Verify an evaluation result manifest
-    Verify {
-        /// Evaluation result manifest ID to verify
+    /// Export the authorship and approvals of a manifest as a signed W3C Verifiable Credential
+    ExportCredential {
+        /// ID of the manifest to export
         #[arg(long = "id")]
         id: String,
 
+        /// Issuer's private key: a PEM or DER file, env:<VAR> or - (stdin)
+        #[arg(long = "key")]
+        key: PathBuf,
+
+        /// DID of the issuer (default: did:key of an Ed25519 or P-256 key)
+        #[arg(long = "issuer")]
+        issuer: Option<String>,
+
+        /// Hash algorithm of the manifest digest (default: sha384)
+        #[arg(long = "hash-alg", value_enum, default_value = "sha384")]
+        hash_alg: HashAlgorithmChoice,
+
+        /// Output file path (defaults to stdout if not provided)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
         /// Storage backend (local or rekor)
         #[arg(long = "storage-type", default_value = "database")]
         storage_type: Box<String>,
@@ -389,52 +1339,155 @@ pub enum EvaluationCommands {
         #[arg(long = "storage-url", default_value = "http://localhost:8080")]
         storage_url: Box<String>,
     },
-}
 
-/// CCAttestationCommands are mostly for debugging since CC attestations
-/// will be collected directly during asset creation/modification
-#[derive(Subcommand)]
-pub enum CCAttestationCommands {
-    /// Reads the CC attestation and display it
-    Show,
-    /// Extracts the VM launch measurement value from the CC attestation
-    GetLaunchMeasurement,
-    /// Verify VM launch measurement against a launch endorsement
-    VerifyLaunch {
-        /// VM host platform (determines download command and format)
-        #[arg(long = "host-platform", default_value = "gcp-tdx")]
-        host_platform: String,
+    /// Verify a credential exported with export-credential and print it
+    VerifyCredential {
+        /// Credential file (compact JWS)
+        #[arg(long = "credential")]
+        credential: PathBuf,
+
+        /// Issuer's public key (PEM)
+        #[arg(long = "public-key")]
+        public_key: PathBuf,
     },
-}
 
-#[derive(Debug, Subcommand)]
-pub enum SoftwareCommands {
-    /// Create a new software component manifest
-    Create {
-        #[arg(long = "paths", num_args = 1.., value_delimiter = ',')]
-        paths: Vec<PathBuf>,
+    /// Check the store for broken cross-references and other integrity problems
+    Fsck {
+        /// Repair what can be repaired (recompute stale cross-reference hashes)
+        #[arg(long = "repair")]
+        repair: bool,
 
-        /// Names for each component (comma-separated)
-        #[arg(long = "ingredient-names", num_args = 1.., value_delimiter = ',')]
-        ingredient_names: Vec<String>,
+        /// Don't ask for confirmation before each repair
+        #[arg(short = 'y', long = "yes", requires = "repair")]
+        yes: bool,
 
-        /// Software name
+        /// Storage backend (local or rekor)
+        #[arg(long = "storage-type", default_value = "database")]
+        storage_type: Box<String>,
+
+        /// Storage URL
+        #[arg(long = "storage-url", default_value = "http://localhost:8080")]
+        storage_url: Box<String>,
+    },
+
+    /// Verify every stored manifest, or those selected by signature
+    VerifyAll {
+        #[command(flatten)]
+        signature: SignatureFilterArgs,
+
+        /// Directory against which relative ingredient URLs are resolved
+        #[arg(long = "artifact-root")]
+        artifact_root: Option<PathBuf>,
+
+        /// Storage backend (local or rekor)
+        #[arg(long = "storage-type", default_value = "database")]
+        storage_type: Box<String>,
+
+        /// Storage URL
+        #[arg(long = "storage-url", default_value = "http://localhost:8080")]
+        storage_url: Box<String>,
+    },
+
+    /// Check manifests against the lint rules, at the severities of ~/.atlas/lint.yaml
+    Lint {
+        /// Manifest to lint; every stored manifest if not given
+        #[arg(long = "id")]
+        id: Option<String>,
+
+        /// Fix what can be fixed in unsigned manifests, storing them again
+        #[arg(long = "fix")]
+        fix: bool,
+
+        /// Directory that absolute ingredient paths are made relative to by --fix
+        #[arg(long = "path-base", requires = "fix")]
+        path_base: Option<PathBuf>,
+
+        /// Output format (text or json)
+        #[arg(long = "format", default_value = "text")]
+        format: String,
+
+        /// Storage backend (local or rekor)
+        #[arg(long = "storage-type", default_value = "database")]
+        storage_type: Box<String>,
+
+        /// Storage URL
+        #[arg(long = "storage-url", default_value = "http://localhost:8080")]
+        storage_url: Box<String>,
+    },
+
+    /// Compress manifests stored before compression was enabled
+    Compress {
+        /// Storage backend (database or local-fs)
+        #[arg(long = "storage-type", default_value = "database")]
+        storage_type: Box<String>,
+
+        /// Storage URL
+        #[arg(long = "storage-url", default_value = "http://localhost:8080")]
+        storage_url: Box<String>,
+    },
+
+    /// Migrate a manifest written by an earlier version to the current schema
+    Upgrade {
+        /// ID of the manifest to migrate
+        #[arg(long = "id")]
+        id: String,
+
+        /// Private key re-signing the migrated manifest, required if it is signed: a PEM or
+        /// DER file, env:<VAR> or - (stdin)
+        #[arg(long = "key")]
+        key: Option<PathBuf>,
+
+        /// Hash algorithm of the migration record and the signature (default: sha384)
+        #[arg(long = "hash-alg", value_enum, default_value = "sha384")]
+        hash_alg: HashAlgorithmChoice,
+
+        /// Only print the pending migration steps
+        #[arg(long = "dry-run")]
+        dry_run: bool,
+
+        /// Storage backend (local or rekor)
+        #[arg(long = "storage-type", default_value = "database")]
+        storage_type: Box<String>,
+
+        /// Storage URL
+        #[arg(long = "storage-url", default_value = "http://localhost:8080")]
+        storage_url: Box<String>,
+    },
+}
+/// Commands for evaluation results
+#[derive(Subcommand)]
+pub enum EvaluationCommands {
+    /// Create a new evaluation result manifest
+    Create {
+        /// Path to evaluation results file
+        #[arg(long = "path")]
+        path: PathBuf,
+
+        /// Evaluation name
         #[arg(long = "name")]
         name: String,
 
-        /// Software type (script, container, VM, etc.)
-        #[arg(long = "software-type")]
-        software_type: String,
+        /// Model ID that was evaluated
+        #[arg(long = "model-id")]
+        model_id: String,
 
-        /// Software version
-        #[arg(long = "version")]
-        version: Option<String>,
+        /// Evaluation dataset ID
+        #[arg(long = "dataset-id")]
+        dataset_id: String,
+
+        /// Evaluation metrics (key=value pairs)
+        #[arg(long = "metrics", num_args = 1.., value_delimiter = ',')]
+        metrics: Vec<String>,
+
+        /// Read the metrics from the results file, a report of this evaluation harness
+        #[arg(long = "results-format", value_enum)]
+        results_format: Option<ResultsFormatChoice>,
 
-        /// Author organization name
+        /// Author organization name or DID (e.g. did:web:example.com)
         #[arg(long = "author-org")]
         author_org: Option<String>,
 
-        /// Author name
+        /// Author name or DID
         #[arg(long = "author-name")]
         author_name: Option<String>,
 
@@ -442,18 +1495,39 @@ pub enum SoftwareCommands {
         #[arg(long = "description")]
         description: Option<String>,
 
-        /// Optional linked manifest IDs
-        #[arg(long = "linked-manifests")]
-        linked_manifests: Option<Vec<String>>,
+        #[command(flatten)]
+        encryption: EncryptionArgs,
+
+        /// Derive the manifest ID from its content (urn:c2pa:sha256-<hex>) instead of a random UUID
+        #[arg(long = "content-addressed-id")]
+        content_addressed_id: bool,
 
-        /// Path to private key file for signing (PEM format)
+        /// Store a new manifest even if an identical one is already stored
+        #[arg(long = "force-new")]
+        force_new: bool,
+
+        /// Private key for signing: a PEM or DER file, env:<VAR>, - (stdin) or piv:<SLOT>
         #[arg(long = "key")]
         key: Option<PathBuf>,
 
+        /// Path to an ML-DSA private key for hybrid signing, used together with --key
+        /// (experimental, needs the `pq` feature)
+        #[arg(long = "pq-key", requires = "key")]
+        pq_key: Option<PathBuf>,
+
         /// Hash algorithm to use for signing (default: sha384)
         #[arg(long = "hash-alg", value_enum, default_value = "sha384")]
         hash_alg: HashAlgorithmChoice,
 
+        /// Expiry of the manifest: RFC 3339 timestamp, date (YYYY-MM-DD) or duration (e.g. 90d)
+        #[arg(long = "valid-until")]
+        valid_until: Option<String>,
+
+        /// Creation time to record instead of now, for historical artifacts: RFC 3339
+        /// timestamp or date (YYYY-MM-DD), stored in UTC
+        #[arg(long = "created-at")]
+        created_at: Option<String>,
+
         /// Only print manifest without storing
         #[arg(long = "print")]
         print: bool,
@@ -462,6 +1536,10 @@ pub enum SoftwareCommands {
         #[arg(long = "encoding", default_value = "json")]
         encoding: String,
 
+        /// Write the output to a file (CBOR is written as binary)
+        #[arg(long = "output")]
+        output: Option<PathBuf>,
+
         /// Storage backend (local or rekor)
         #[arg(long = "storage-type", default_value = "database")]
         storage_type: Box<String>,
@@ -469,13 +1547,13 @@ pub enum SoftwareCommands {
         /// Storage URL
         #[arg(long = "storage-url", default_value = "http://localhost:8080")]
         storage_url: Box<String>,
-
-        /// Collect the underlying CC attestation, if available
-        #[arg(long = "with-tdx", default_value = "false")]
-        with_tdx: bool,
     },
-    /// List all software component manifests
+
+    /// List all evaluation results
     List {
+        #[command(flatten)]
+        list: ListArgs,
+
         /// Storage backend (local or rekor)
         #[arg(long = "storage-type", default_value = "database")]
         storage_type: Box<String>,
@@ -484,11 +1562,13 @@ pub enum SoftwareCommands {
         #[arg(long = "storage-url", default_value = "http://localhost:8080")]
         storage_url: Box<String>,
     },
-    /// Verify a software component manifest
+
+    /// Verify an evaluation result manifest
     Verify {
-        /// Manifest ID to verify
+        /// Evaluation result manifest ID to verify
         #[arg(long = "id")]
         id: String,
+
         /// Storage backend (local or rekor)
         #[arg(long = "storage-type", default_value = "database")]
         storage_type: Box<String>,
@@ -497,16 +1577,25 @@ pub enum SoftwareCommands {
         #[arg(long = "storage-url", default_value = "http://localhost:8080")]
         storage_url: Box<String>,
     },
-    /// Link software to a model
-    LinkModel {
-        /// Software manifest ID
-        #[arg(long = "software-id")]
-        software_id: String,
 
-        /// Model manifest ID
+    /// Show the history of a model's evaluation metrics across its versions
+    History {
+        /// Model ID
         #[arg(long = "model-id")]
         model_id: String,
 
+        /// Only show this metric
+        #[arg(long = "metric")]
+        metric: Option<String>,
+
+        /// Output format (table, json or csv)
+        #[arg(long = "format", default_value = "table")]
+        format: String,
+
+        /// Write the history to a file
+        #[arg(long = "output")]
+        output: Option<PathBuf>,
+
         /// Storage backend (local or rekor)
         #[arg(long = "storage-type", default_value = "database")]
         storage_type: Box<String>,
@@ -515,15 +1604,118 @@ pub enum SoftwareCommands {
         #[arg(long = "storage-url", default_value = "http://localhost:8080")]
         storage_url: Box<String>,
     },
-    /// Link software to a dataset
-    LinkDataset {
-        /// Software manifest ID
-        #[arg(long = "software-id")]
-        software_id: String,
+}
 
-        /// Dataset manifest ID
-        #[arg(long = "dataset-id")]
-        dataset_id: String,
+/// CCAttestationCommands are mostly for debugging since CC attestations
+/// will be collected directly during asset creation/modification
+#[derive(Subcommand)]
+pub enum CCAttestationCommands {
+    /// Reads the CC attestation and display it
+    Show,
+    /// Extracts the VM launch measurement value from the CC attestation
+    GetLaunchMeasurement,
+    /// Verify VM launch measurement against a launch endorsement
+    VerifyLaunch {
+        /// VM host platform (gcp-tdx, azure-tdx, onprem-tdx or sev-snp)
+        #[arg(long = "host-platform", default_value = "gcp-tdx")]
+        host_platform: String,
+
+        /// Endorsement document source (file path or http(s) URL)
+        #[arg(long = "endorsement")]
+        endorsement: Option<String>,
+
+        /// Public key (PEM) of the endorser; required unless --allow-unsigned-endorsement
+        #[arg(long = "endorsement-key")]
+        endorsement_key: Option<PathBuf>,
+
+        /// Accept endorsements without checking their signature when no key is given
+        #[arg(
+            long = "allow-unsigned-endorsement",
+            conflicts_with = "endorsement_key"
+        )]
+        allow_unsigned_endorsement: bool,
+
+        /// Allow fetching the endorsement from a plain http:// URL
+        #[arg(long = "allow-http-endorsement")]
+        allow_http_endorsement: bool,
+    },
+    /// Reads the RTMR event log (CCEL) and displays the measured boot chain
+    EventLog {
+        /// Read the event log from a file instead of the ACPI CCEL table
+        #[arg(long = "path")]
+        path: Option<PathBuf>,
+    },
+    /// Replay the event log embedded in a manifest against its TDX report RTMRs
+    VerifyEventLog {
+        /// Manifest ID to verify
+        #[arg(long = "id")]
+        id: String,
+
+        /// Storage backend (local or rekor)
+        #[arg(long = "storage-type", default_value = "database")]
+        storage_type: Box<String>,
+
+        /// Storage URL
+        #[arg(long = "storage-url", default_value = "http://localhost:8080")]
+        storage_url: Box<String>,
+    },
+    /// Collect a fresh CC attestation and attach it to an existing manifest
+    Attach {
+        /// ID of the manifest to attest
+        #[arg(long = "id")]
+        id: String,
+
+        /// Private key for re-signing: a PEM or DER file, env:<VAR>, - (stdin) or piv:<SLOT>
+        #[arg(long = "key")]
+        key: Option<PathBuf>,
+
+        /// Hash algorithm to use for binding and signing (default: sha384)
+        #[arg(long = "hash-alg", value_enum, default_value = "sha384")]
+        hash_alg: HashAlgorithmChoice,
+
+        /// Storage backend (local or rekor)
+        #[arg(long = "storage-type", default_value = "database")]
+        storage_type: Box<String>,
+
+        /// Storage URL
+        #[arg(long = "storage-url", default_value = "http://localhost:8080")]
+        storage_url: Box<String>,
+    },
+    /// Collect a TPM 2.0 quote over the selected PCRs and display it
+    TpmQuote {
+        /// PCR selection to quote (tpm2-tools syntax, e.g. sha256:0,1,7)
+        #[arg(long = "pcrs", default_value = "sha256:0,1,2,3,4,5,6,7")]
+        pcrs: String,
+
+        /// Attestation key handle or context file used to sign the quote
+        #[arg(long = "ak", default_value = "0x81010002")]
+        ak: String,
+
+        /// Optional hex-encoded nonce to include as qualifying data
+        #[arg(long = "nonce")]
+        nonce: Option<String>,
+
+        /// Write the quote to a file instead of stdout
+        #[arg(long = "output")]
+        output: Option<PathBuf>,
+    },
+    /// Verify a TPM quote against golden PCR values from a policy file
+    VerifyTpm {
+        /// Manifest ID whose TPM quote assertion should be verified
+        #[arg(
+            long = "id",
+            conflicts_with = "quote",
+            required_unless_present = "quote"
+        )]
+        id: Option<String>,
+
+        /// Quote file produced by `tpm-quote --output`
+        #[arg(long = "quote")]
+        quote: Option<PathBuf>,
+
+        /// Policy file (JSON) with golden PCR values
+        #[arg(long = "policy")]
+        policy: PathBuf,
 
         /// Storage backend (local or rekor)
         #[arg(long = "storage-type", default_value = "database")]
@@ -536,30 +1728,110 @@ pub enum SoftwareCommands {
 }
 
 #[derive(Debug, Subcommand)]
-pub enum PipelineCommands {
-    /// Generate SLSA Build Provenance v1 for the given pipeline
-    GenerateProvenance {
-        /// Paths to any pipeline inputs and other external parameters
-        #[arg(long = "inputs", num_args = 1.., value_delimiter = ',')]
-        inputs: Vec<PathBuf>,
+pub enum SoftwareCommands {
+    /// Create a new software component manifest
+    Create {
+        #[arg(long = "paths", num_args = 1.., value_delimiter = ',')]
+        paths: Vec<PathBuf>,
 
-        /// Path to pipeline script or configuration
-        #[arg(long = "pipeline")]
-        pipeline: PathBuf,
+        /// Names for each component (comma-separated); paths without a name are named after
+        /// their relative path
+        #[arg(long = "ingredient-names", num_args = 1.., value_delimiter = ',')]
+        ingredient_names: Vec<String>,
 
-        /// Paths to any pipeline products
-        #[arg(long = "products", num_args = 1.., value_delimiter = ',')]
-        products: Vec<PathBuf>,
+        /// C2PA relationship of each path's ingredients: parentOf, componentOf (default) or
+        /// inputTo (comma-separated)
+        #[arg(long = "ingredient-relationships", num_args = 1.., value_delimiter = ',')]
+        ingredient_relationships: Vec<String>,
+
+        /// Record local ingredient URLs relative to this directory, for portable manifests
+        #[arg(long = "path-base")]
+        path_base: Option<PathBuf>,
+
+        /// Add the packages installed in a virtualenv or conda environment as ingredients:
+        /// the directory of the environment (default: the active one)
+        #[arg(long = "from-python-env", num_args = 0..=1, value_name = "PREFIX")]
+        from_python_env: Option<Option<PathBuf>>,
+
+        /// pip wheel cache to hash packages from where possible (default: the pip cache)
+        #[arg(long = "wheel-cache", requires = "from_python_env")]
+        wheel_cache: Option<PathBuf>,
+
+        /// Add a Dockerfile and its build context (honoring .dockerignore) as ingredients,
+        /// and record its base images
+        #[arg(long = "from-dockerfile")]
+        from_dockerfile: Option<PathBuf>,
+
+        /// Build context of the Dockerfile (default: the directory of the Dockerfile)
+        #[arg(long = "context", requires = "from_dockerfile")]
+        context: Option<PathBuf>,
+
+        /// Software name
+        #[arg(long = "name")]
+        name: String,
+
+        /// Software type (script, container, VM, etc.)
+        #[arg(long = "software-type")]
+        software_type: String,
+
+        /// Software version
+        #[arg(long = "version")]
+        version: Option<String>,
+
+        /// Author organization name or DID (e.g. did:web:example.com)
+        #[arg(long = "author-org")]
+        author_org: Option<String>,
+
+        /// Author name or DID
+        #[arg(long = "author-name")]
+        author_name: Option<String>,
+
+        /// Optional description
+        #[arg(long = "description")]
+        description: Option<String>,
+
+        /// SPDX license identifier of the asset (e.g. Apache-2.0)
+        #[arg(long = "license")]
+        license: Option<String>,
+
+        #[command(flatten)]
+        encryption: EncryptionArgs,
+
+        /// Derive the manifest ID from its content (urn:c2pa:sha256-<hex>) instead of a random UUID
+        #[arg(long = "content-addressed-id")]
+        content_addressed_id: bool,
+
+        /// Store a new manifest even if an identical one is already stored
+        #[arg(long = "force-new")]
+        force_new: bool,
+
+        /// Optional linked manifest IDs
+        #[arg(long = "linked-manifests")]
+        linked_manifests: Option<Vec<String>>,
 
-        /// Path to private key file for signing (PEM format)
+        /// Private key for signing: a PEM or DER file, env:<VAR>, - (stdin) or piv:<SLOT>
         #[arg(long = "key")]
         key: Option<PathBuf>,
 
+        /// Path to an ML-DSA private key for hybrid signing, used together with --key
+        /// (experimental, needs the `pq` feature)
+        #[arg(long = "pq-key", requires = "key")]
+        pq_key: Option<PathBuf>,
+
         /// Hash algorithm to use for signing (default: sha384)
         #[arg(long = "hash-alg", value_enum, default_value = "sha384")]
         hash_alg: HashAlgorithmChoice,
 
-        /// Only print SLSA Provenance without storing
+        /// Expiry of the manifest: RFC 3339 timestamp, date (YYYY-MM-DD) or duration (e.g. 90d)
+        #[arg(long = "valid-until")]
+        valid_until: Option<String>,
+
+        /// Creation time to record instead of now, for historical artifacts: RFC 3339
+        /// timestamp or date (YYYY-MM-DD), stored in UTC
+        #[arg(long = "created-at")]
+        created_at: Option<String>,
+
+        /// Only print manifest without storing
         #[arg(long = "print")]
         print: bool,
 
@@ -567,16 +1839,810 @@ pub enum PipelineCommands {
         #[arg(long = "encoding", default_value = "json")]
         encoding: String,
 
-        /// Storage backend (only local supported)
-        #[arg(long = "storage-type", default_value = "local-fs")]
+        /// Write the output to a file (CBOR is written as binary)
+        #[arg(long = "output")]
+        output: Option<PathBuf>,
+
+        /// Storage backend (local or rekor)
+        #[arg(long = "storage-type", default_value = "database")]
         storage_type: Box<String>,
 
         /// Storage URL
         #[arg(long = "storage-url", default_value = "http://localhost:8080")]
         storage_url: Box<String>,
 
-        /// Collect the underlying TDX attestation, if available
+        /// Collect the underlying CC attestation, if available
         #[arg(long = "with-tdx", default_value = "false")]
         with_tdx: bool,
+
+        /// Collect a TPM 2.0 quote, for hosts without a TEE
+        #[arg(long = "with-tpm", default_value = "false")]
+        with_tpm: bool,
+
+        /// PCR selection to quote (tpm2-tools syntax, e.g. sha256:0,1,7)
+        #[arg(long = "tpm-pcrs", default_value = "sha256:0,1,2,3,4,5,6,7")]
+        tpm_pcrs: String,
+
+        /// Attestation key handle or context file used to sign the quote
+        #[arg(long = "tpm-ak", default_value = "0x81010002")]
+        tpm_ak: String,
+    },
+    /// List all software component manifests
+    List {
+        #[command(flatten)]
+        list: ListArgs,
+
+        /// Storage backend (local or rekor)
+        #[arg(long = "storage-type", default_value = "database")]
+        storage_type: Box<String>,
+
+        /// Storage URL
+        #[arg(long = "storage-url", default_value = "http://localhost:8080")]
+        storage_url: Box<String>,
+    },
+    /// Verify a software component manifest
+    Verify {
+        /// Manifest ID to verify
+        #[arg(long = "id")]
+        id: String,
+
+        /// Directory against which relative ingredient URLs are resolved
+        #[arg(long = "artifact-root")]
+        artifact_root: Option<PathBuf>,
+
+        #[command(flatten)]
+        selection: IngredientSelectionArgs,
+
+        #[command(flatten)]
+        quorum: QuorumArgs,
+
+        #[command(flatten)]
+        outputs: VerificationOutputArgs,
+
+        /// Storage backend (local or rekor)
+        #[arg(long = "storage-type", default_value = "database")]
+        storage_type: Box<String>,
+
+        /// Storage URL
+        #[arg(long = "storage-url", default_value = "http://localhost:8080")]
+        storage_url: Box<String>,
+    },
+    /// Link software to a model
+    LinkModel {
+        /// Software manifest ID
+        #[arg(long = "software-id")]
+        software_id: String,
+
+        /// Model manifest ID
+        #[arg(long = "model-id")]
+        model_id: String,
+
+        /// Storage backend (local or rekor)
+        #[arg(long = "storage-type", default_value = "database")]
+        storage_type: Box<String>,
+
+        /// Storage URL
+        #[arg(long = "storage-url", default_value = "http://localhost:8080")]
+        storage_url: Box<String>,
+    },
+    /// Link software to a dataset
+    LinkDataset {
+        /// Software manifest ID
+        #[arg(long = "software-id")]
+        software_id: String,
+
+        /// Dataset manifest ID
+        #[arg(long = "dataset-id")]
+        dataset_id: String,
+
+        /// Storage backend (local or rekor)
+        #[arg(long = "storage-type", default_value = "database")]
+        storage_type: Box<String>,
+
+        /// Storage URL
+        #[arg(long = "storage-url", default_value = "http://localhost:8080")]
+        storage_url: Box<String>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum PipelineCommands {
+    /// Generate SLSA Build Provenance v1 for the given pipeline
+    GenerateProvenance {
+        /// Paths to any pipeline inputs and other external parameters
+        #[arg(long = "inputs", num_args = 1.., value_delimiter = ',')]
+        inputs: Vec<PathBuf>,
+
+        /// Path to pipeline script or configuration
+        #[arg(long = "pipeline")]
+        pipeline: PathBuf,
+
+        /// Paths to any pipeline products
+        #[arg(long = "products", num_args = 1.., value_delimiter = ',')]
+        products: Vec<PathBuf>,
+
+        /// Private key for signing: a PEM or DER file, env:<VAR>, - (stdin) or piv:<SLOT>
+        #[arg(long = "key")]
+        key: Option<PathBuf>,
+
+        /// Hash algorithm to use for signing (default: sha384)
+        #[arg(long = "hash-alg", value_enum, default_value = "sha384")]
+        hash_alg: HashAlgorithmChoice,
+
+        /// Only print SLSA Provenance without storing
+        #[arg(long = "print")]
+        print: bool,
+
+        /// Output encoding (json or cbor)
+        #[arg(long = "encoding", default_value = "json")]
+        encoding: String,
+
+        /// Write the output to a file (CBOR is written as binary)
+        #[arg(long = "output")]
+        output: Option<PathBuf>,
+
+        /// Storage backend (database or local-fs)
+        #[arg(long = "storage-type", default_value = "local-fs")]
+        storage_type: Box<String>,
+
+        /// Storage URL
+        #[arg(long = "storage-url", default_value = "http://localhost:8080")]
+        storage_url: Box<String>,
+
+        /// Collect the underlying TDX attestation, if available
+        #[arg(long = "with-tdx", default_value = "false")]
+        with_tdx: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SlsaCommands {
+    /// Import the build metadata of another build system as signed SLSA Build Provenance v1
+    Import {
+        /// Build system that wrote the metadata
+        #[arg(long = "from", value_enum)]
+        from: BuildSystemChoice,
+
+        /// Build metadata: a buildx metadata file, ko image references or a Bazel JSON execution log
+        #[arg(long = "metadata")]
+        metadata: PathBuf,
+
+        /// Private key for signing: a PEM or DER file, env:<VAR>, - (stdin) or piv:<SLOT>
+        #[arg(long = "key")]
+        key: Option<PathBuf>,
+
+        /// Hash algorithm to use for signing (default: sha384)
+        #[arg(long = "hash-alg", value_enum, default_value = "sha384")]
+        hash_alg: HashAlgorithmChoice,
+
+        /// Only print SLSA Provenance without storing
+        #[arg(long = "print")]
+        print: bool,
+
+        /// Output encoding (json or cbor)
+        #[arg(long = "encoding", default_value = "json")]
+        encoding: String,
+
+        /// Write the output to a file (CBOR is written as binary)
+        #[arg(long = "output")]
+        output: Option<PathBuf>,
+
+        /// Storage backend (database or local-fs)
+        #[arg(long = "storage-type", default_value = "local-fs")]
+        storage_type: Box<String>,
+
+        /// Storage URL
+        #[arg(long = "storage-url", default_value = "http://localhost:8080")]
+        storage_url: Box<String>,
+    },
+    /// Run a command and generate signed SLSA Build Provenance v1 for it
+    Run {
+        /// Paths to inputs of the command, hashed before it runs
+        #[arg(long = "inputs", num_args = 1.., value_delimiter = ',')]
+        inputs: Vec<PathBuf>,
+
+        /// Paths to products of the command, hashed after it exits
+        #[arg(long = "products", num_args = 1.., value_delimiter = ',', required = true)]
+        products: Vec<PathBuf>,
+
+        /// Environment variables whose values are recorded (comma-separated)
+        #[arg(long = "env", value_delimiter = ',')]
+        env: Vec<String>,
+
+        /// Private key for signing: a PEM or DER file, env:<VAR>, - (stdin) or piv:<SLOT>
+        #[arg(long = "key")]
+        key: Option<PathBuf>,
+
+        /// Hash algorithm to use for hashing and signing (default: sha384)
+        #[arg(long = "hash-alg", value_enum, default_value = "sha384")]
+        hash_alg: HashAlgorithmChoice,
+
+        /// Only print SLSA Provenance without storing
+        #[arg(long = "print")]
+        print: bool,
+
+        /// Output encoding (json or cbor)
+        #[arg(long = "encoding", default_value = "json")]
+        encoding: String,
+
+        /// Write the output to a file (CBOR is written as binary)
+        #[arg(long = "output")]
+        output: Option<PathBuf>,
+
+        /// Storage backend (database or local-fs)
+        #[arg(long = "storage-type", default_value = "local-fs")]
+        storage_type: Box<String>,
+
+        /// Storage URL
+        #[arg(long = "storage-url", default_value = "http://localhost:8080")]
+        storage_url: Box<String>,
+
+        /// Command to run and its arguments, after --
+        #[arg(last = true, required = true)]
+        command: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct DoctorArgs {
+    /// Storage backend to check (database, rekor, local-fs or s3)
+    #[arg(long = "storage-type", default_value = "database")]
+    pub storage_type: String,
+
+    /// Storage URL
+    #[arg(long = "storage-url", default_value = "http://localhost:8080")]
+    pub storage_url: String,
+
+    /// Signing key to check: a PEM or DER file, env:<VAR>, - (stdin) or piv:<SLOT>
+    #[arg(long = "key")]
+    pub key: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct SyncArgs {
+    /// Model registry to sync with
+    #[arg(long = "target", value_enum)]
+    pub target: RegistryTargetChoice,
+
+    /// Registry endpoint (default: MLFLOW_TRACKING_URI, huggingface.co or the regional
+    /// Vertex AI endpoint)
+    #[arg(long = "registry-url")]
+    pub registry_url: Option<String>,
+
+    /// Hugging Face user or organization, or Vertex AI projects/<PROJECT>/locations/<LOCATION>
+    #[arg(long = "namespace")]
+    pub namespace: Option<String>,
+
+    /// Manifests to push (comma-separated; default: every model manifest)
+    #[arg(long = "ids", value_delimiter = ',')]
+    pub ids: Vec<String>,
+
+    /// Registry entry to push a single manifest to (default: the manifest's name)
+    #[arg(long = "registry-entry")]
+    pub registry_entry: Option<String>,
+
+    /// Report registry entries lacking a stored manifest instead of pushing
+    #[arg(long = "gaps", conflicts_with_all = ["ids", "registry_entry"])]
+    pub gaps: bool,
+
+    /// Push without verifying the manifests, recording their status as not-checked
+    #[arg(long = "skip-verify")]
+    pub skip_verify: bool,
+
+    /// Directory against which relative ingredient URLs are resolved
+    #[arg(long = "artifact-root")]
+    pub artifact_root: Option<PathBuf>,
+
+    /// Format of the gap report (text or json)
+    #[arg(long = "format", default_value = "text")]
+    pub format: String,
+
+    /// Storage backend (local or rekor)
+    #[arg(long = "storage-type", default_value = "database")]
+    pub storage_type: String,
+
+    /// Storage URL
+    #[arg(long = "storage-url", default_value = "http://localhost:8080")]
+    pub storage_url: String,
+}
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct AttestationKeyArgs {
+    /// Private key for signing: a PEM or DER file, env:<VAR>, - (stdin) or piv:<SLOT>
+    #[arg(long = "key")]
+    pub key: Option<PathBuf>,
+
+    /// Path to an ML-DSA private key for hybrid signing, used together with --key
+    /// (experimental, needs the `pq` feature)
+    #[arg(long = "pq-key", requires = "key")]
+    pub pq_key: Option<PathBuf>,
+
+    /// Hash algorithm to use for hashing subjects and signing (default: sha384)
+    #[arg(long = "hash-alg", value_enum, default_value = "sha384")]
+    pub hash_alg: HashAlgorithmChoice,
+}
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct AttestationStatementArgs {
+    /// Files the statement is about (comma-separated, or --subject repeated)
+    #[arg(
+        long = "subjects",
+        visible_alias = "subject",
+        num_args = 1..,
+        value_delimiter = ',',
+        required_unless_present = "subject_manifests"
+    )]
+    pub subjects: Vec<PathBuf>,
+
+    /// IDs of stored manifests the statement is about, hashed as stored (comma-separated)
+    #[arg(long = "subject-manifest", num_args = 1.., value_delimiter = ',')]
+    pub subject_manifests: Vec<String>,
+
+    /// Predicate type of the statement: an absolute URI such as
+    /// https://example.com/evaluation/v1
+    #[arg(long = "predicate-type")]
+    pub predicate_type: String,
+
+    /// JSON or YAML file with the predicate, a JSON object kept as given (default: empty)
+    #[arg(long = "predicate")]
+    pub predicate: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct AttestationOutputArgs {
+    /// Only print the envelope without storing it
+    #[arg(long = "print")]
+    pub print: bool,
+
+    /// Output encoding (json or cbor)
+    #[arg(long = "encoding", default_value = "json")]
+    pub encoding: String,
+
+    /// Write the output to a file (CBOR is written as binary)
+    #[arg(long = "output")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AttestationCommands {
+    /// Create an in-toto statement about files, signed into a DSSE envelope with --key
+    Create {
+        #[command(flatten)]
+        statement: AttestationStatementArgs,
+
+        #[command(flatten)]
+        keys: AttestationKeyArgs,
+
+        #[command(flatten)]
+        output: AttestationOutputArgs,
+
+        /// Name of the stored attestation (default: file name of the first subject)
+        #[arg(long = "name")]
+        name: Option<String>,
+
+        /// Storage backend (database or local-fs)
+        #[arg(long = "storage-type", default_value = "database")]
+        storage_type: Box<String>,
+
+        /// Storage URL
+        #[arg(long = "storage-url", default_value = "http://localhost:8080")]
+        storage_url: Box<String>,
+    },
+    /// Sign an unsigned statement, or co-sign an envelope
+    Sign {
+        /// Statement or DSSE envelope to sign (JSON, or CBOR for envelopes)
+        #[arg(long = "input")]
+        input: PathBuf,
+
+        #[command(flatten)]
+        keys: AttestationKeyArgs,
+
+        #[command(flatten)]
+        output: AttestationOutputArgs,
+
+        /// Name of the stored attestation (default: file name of the input)
+        #[arg(long = "name")]
+        name: Option<String>,
+
+        /// Storage backend (database or local-fs)
+        #[arg(long = "storage-type", default_value = "database")]
+        storage_type: Box<String>,
+
+        /// Storage URL
+        #[arg(long = "storage-url", default_value = "http://localhost:8080")]
+        storage_url: Box<String>,
+    },
+    /// Verify an envelope's signature and the artifacts it names
+    Verify {
+        /// ID of a stored envelope
+        #[arg(
+            long = "id",
+            required_unless_present_any = ["envelope", "bundle"],
+            conflicts_with_all = ["envelope", "bundle"]
+        )]
+        id: Option<String>,
+
+        /// DSSE envelope file (JSON or CBOR)
+        #[arg(long = "envelope", conflicts_with = "bundle")]
+        envelope: Option<PathBuf>,
+
+        /// JSON Lines bundle of DSSE envelopes, each verified and reported on its own
+        #[arg(long = "bundle")]
+        bundle: Option<PathBuf>,
+
+        /// PEM public key of the envelope's signer
+        #[arg(long = "public-key")]
+        public_key: PathBuf,
+
+        /// Directory against which relative artifact paths are resolved
+        #[arg(long = "artifact-root")]
+        artifact_root: Option<PathBuf>,
+
+        /// Storage backend (database or local-fs)
+        #[arg(long = "storage-type", default_value = "database")]
+        storage_type: Box<String>,
+
+        /// Storage URL
+        #[arg(long = "storage-url", default_value = "http://localhost:8080")]
+        storage_url: Box<String>,
+    },
+    /// List stored envelopes (OMS manifests, SLSA provenance and other attestations)
+    List {
+        /// Output format (text or json)
+        #[arg(long = "format", default_value = "text")]
+        format: String,
+
+        /// Storage backend (database or local-fs)
+        #[arg(long = "storage-type", default_value = "database")]
+        storage_type: Box<String>,
+
+        /// Storage URL
+        #[arg(long = "storage-url", default_value = "http://localhost:8080")]
+        storage_url: Box<String>,
+    },
+    /// Attach a stored attestation to a manifest
+    Attach {
+        /// Manifest ID
+        #[arg(long = "manifest-id")]
+        manifest_id: String,
+
+        /// Envelope ID of the attestation
+        #[arg(long = "id")]
+        id: String,
+
+        /// Private key to re-sign the manifest with, required if it is signed
+        #[arg(long = "key")]
+        key: Option<PathBuf>,
+
+        /// Hash algorithm to use for signing (default: sha384)
+        #[arg(long = "hash-alg", value_enum, default_value = "sha384")]
+        hash_alg: HashAlgorithmChoice,
+
+        /// Storage backend (database or local-fs)
+        #[arg(long = "storage-type", default_value = "database")]
+        storage_type: Box<String>,
+
+        /// Storage URL
+        #[arg(long = "storage-url", default_value = "http://localhost:8080")]
+        storage_url: Box<String>,
+    },
+    /// Export stored envelopes
+    Export {
+        /// Envelope IDs (comma-separated); several envelopes need --bundle
+        #[arg(long = "id", num_args = 1.., value_delimiter = ',', required = true)]
+        id: Vec<String>,
+
+        /// Write the envelopes as a JSON Lines bundle, one envelope per line
+        #[arg(long = "bundle")]
+        bundle: bool,
+
+        /// Encoding (json or cbor)
+        #[arg(long = "encoding", default_value = "json")]
+        encoding: String,
+
+        /// Write the envelope to a file (CBOR is written as binary)
+        #[arg(long = "output")]
+        output: Option<PathBuf>,
+
+        /// Storage backend (database or local-fs)
+        #[arg(long = "storage-type", default_value = "database")]
+        storage_type: Box<String>,
+
+        /// Storage URL
+        #[arg(long = "storage-url", default_value = "http://localhost:8080")]
+        storage_url: Box<String>,
+    },
+    /// Move the payload of an envelope to a file published separately, leaving its URL and
+    /// digest in the envelope
+    Detach {
+        /// DSSE envelope file (JSON or CBOR)
+        #[arg(long = "envelope")]
+        envelope: PathBuf,
+
+        /// URL (HTTP(S) or file:) or path where the payload will be published; relative
+        /// paths are resolved against --artifact-root when verifying
+        #[arg(long = "payload-url")]
+        payload_url: String,
+
+        /// File to write the payload to
+        #[arg(long = "payload-output")]
+        payload_output: PathBuf,
+
+        /// Hash algorithm of the payload digest (default: sha384)
+        #[arg(long = "hash-alg", value_enum, default_value = "sha384")]
+        hash_alg: HashAlgorithmChoice,
+
+        /// Encoding of the detached envelope (json or cbor)
+        #[arg(long = "encoding", default_value = "json")]
+        encoding: String,
+
+        /// File to write the detached envelope to
+        #[arg(long = "output")]
+        output: PathBuf,
+    },
+    /// Sign a file as the detached payload of a new envelope, reading it a chunk at a time
+    /// instead of into memory
+    SignDetached {
+        /// File to sign, to be published at --payload-url
+        #[arg(long = "payload")]
+        payload: PathBuf,
+
+        /// URL (HTTP(S) or file:) or path where the payload will be published; relative
+        /// paths are resolved against --artifact-root when verifying
+        #[arg(long = "payload-url")]
+        payload_url: String,
+
+        /// Payload type of the envelope
+        #[arg(long = "payload-type", default_value = "application/vnd.in-toto+json")]
+        payload_type: String,
+
+        /// RSA or ECDSA private key for signing: a PEM or DER file, env:<VAR> or - (stdin)
+        #[arg(long = "key")]
+        key: PathBuf,
+
+        /// Hash algorithm of the signature and the payload digest (default: sha384)
+        #[arg(long = "hash-alg", value_enum, default_value = "sha384")]
+        hash_alg: HashAlgorithmChoice,
+
+        /// Encoding of the detached envelope (json or cbor)
+        #[arg(long = "encoding", default_value = "json")]
+        encoding: String,
+
+        /// File to write the detached envelope to
+        #[arg(long = "output")]
+        output: PathBuf,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum RekorCommands {
+    /// Find log entries for an artifact digest and show who signed them and when
+    Find {
+        /// Artifact digest (sha256:<hex>, sha512:<hex> or bare hex)
+        #[arg(
+            long = "hash",
+            required_unless_present = "file",
+            conflicts_with = "file"
+        )]
+        hash: Option<String>,
+
+        /// Artifact file to hash and search for
+        #[arg(long = "file")]
+        file: Option<PathBuf>,
+
+        /// Output format (text or json)
+        #[arg(long = "format", default_value = "text")]
+        format: String,
+
+        /// Rekor URL
+        #[arg(long = "rekor-url", default_value = "https://rekor.sigstore.dev")]
+        rekor_url: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum StoreCommands {
+    /// Export every manifest and attestation envelope of a store to a .tar.zst archive
+    Export {
+        /// Archive to write
+        #[arg(long = "output")]
+        output: PathBuf,
+
+        /// Only export entries created after this RFC 3339 timestamp or date, or created or
+        /// changed since this checkpoint file
+        #[arg(long = "since")]
+        since: Option<String>,
+
+        /// Write a checkpoint of the store to this file, for a later --since
+        #[arg(long = "checkpoint")]
+        checkpoint: Option<PathBuf>,
+
+        /// Storage backend (local or rekor)
+        #[arg(long = "storage-type", default_value = "database")]
+        storage_type: Box<String>,
+
+        /// Storage URL
+        #[arg(long = "storage-url", default_value = "http://localhost:8080")]
+        storage_url: Box<String>,
+    },
+
+    /// Import an exported store, after checking the checksum of every entry
+    Import {
+        /// Archive written by store export
+        #[arg(long = "input")]
+        input: PathBuf,
+
+        /// Leave manifests and envelopes already in the store as they are
+        #[arg(long = "skip-existing", conflicts_with = "dedupe")]
+        skip_existing: bool,
+
+        /// Leave manifests and envelopes already in the store as they are if they are
+        /// identical, and store changed ones again
+        #[arg(long = "dedupe")]
+        dedupe: bool,
+
+        /// Storage backend (local or rekor)
+        #[arg(long = "storage-type", default_value = "database")]
+        storage_type: Box<String>,
+
+        /// Storage URL
+        #[arg(long = "storage-url", default_value = "http://localhost:8080")]
+        storage_url: Box<String>,
+    },
+    /// Archive or delete the manifests older than the retention policy allows, leaving a
+    /// signed tombstone for each
+    ApplyRetention {
+        /// Retention policy (defaults to ATLAS_RETENTION_FILE or ~/.atlas/retention.yaml)
+        #[arg(long = "policy")]
+        policy: Option<PathBuf>,
+
+        /// Directory for archived manifests and tombstones (overrides the policy's archive_dir)
+        #[arg(long = "archive-dir")]
+        archive_dir: Option<PathBuf>,
+
+        /// Key signing the tombstones
+        #[arg(long = "key", required_unless_present = "dry_run")]
+        key: Option<PathBuf>,
+
+        /// DID issuing the tombstones (defaults to the did:key of --key)
+        #[arg(long = "issuer")]
+        issuer: Option<String>,
+
+        /// List the manifests that would be archived or deleted without changing the store
+        #[arg(long = "dry-run")]
+        dry_run: bool,
+
+        /// Storage backend (local or rekor)
+        #[arg(long = "storage-type", default_value = "database")]
+        storage_type: Box<String>,
+
+        /// Storage URL
+        #[arg(long = "storage-url", default_value = "http://localhost:8080")]
+        storage_url: Box<String>,
+    },
+    /// List the storage plugins found in ATLAS_STORAGE_PLUGIN_PATH and PATH
+    Plugins {
+        /// Output format (table or json)
+        #[arg(long = "format", default_value = "table")]
+        format: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ImportCommands {
+    /// Create manifests for pre-existing models, datasets and software listed in a CSV
+    /// inventory with path, name, type, author, date and description columns
+    Legacy {
+        /// Inventory of the artifacts to import
+        #[arg(long = "manifest-csv")]
+        manifest_csv: PathBuf,
+
+        /// Asset type of rows without a type: model, dataset or software
+        #[arg(long = "type", default_value = "model")]
+        default_type: String,
+
+        /// Record each row's date as the creation time of its manifest
+        #[arg(long = "backdate")]
+        backdate: bool,
+
+        /// Number of artifacts hashed at the same time
+        #[arg(long = "jobs", default_value_t = crate::manifest::legacy::DEFAULT_IMPORT_JOBS)]
+        jobs: usize,
+
+        /// Author organization recorded for every artifact
+        #[arg(long = "author-org")]
+        author_org: Option<String>,
+
+        /// Record local ingredient URLs relative to this directory
+        #[arg(long = "path-base")]
+        path_base: Option<PathBuf>,
+
+        /// Private key for signing: a PEM or DER file, env:<VAR> or piv:<SLOT>
+        #[arg(long = "key")]
+        key: Option<PathBuf>,
+
+        /// Hash algorithm to use for hashing and signing (default: sha384)
+        #[arg(long = "hash-alg", value_enum, default_value = "sha384")]
+        hash_alg: HashAlgorithmChoice,
+
+        /// Storage backend (local or rekor)
+        #[arg(long = "storage-type", default_value = "database")]
+        storage_type: Box<String>,
+
+        /// Storage URL
+        #[arg(long = "storage-url", default_value = "http://localhost:8080")]
+        storage_url: Box<String>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum QueueCommands {
+    /// List the manifests waiting in the offline queue
+    List,
+    /// Store the queued manifests in the order they were created
+    Flush {
+        /// Also store manifests queued for other storage in this backend
+        #[arg(long = "all-targets")]
+        all_targets: bool,
+
+        /// Storage backend (local or rekor)
+        #[arg(long = "storage-type", default_value = "database")]
+        storage_type: Box<String>,
+
+        /// Storage URL
+        #[arg(long = "storage-url", default_value = "http://localhost:8080")]
+        storage_url: Box<String>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum KeyCommands {
+    /// Report the manifests and envelopes signed with a key, and when
+    Usage {
+        /// Public key to look for (PEM)
+        #[arg(long = "public-key")]
+        public_key: PathBuf,
+
+        /// Output format (table or json)
+        #[arg(long = "format", default_value = "table")]
+        format: String,
+
+        /// Storage backend (local or rekor)
+        #[arg(long = "storage-type", default_value = "database")]
+        storage_type: Box<String>,
+
+        /// Storage URL
+        #[arg(long = "storage-url", default_value = "http://localhost:8080")]
+        storage_url: Box<String>,
+    },
+    /// Add a key to the signed revocation list of the trust store
+    Revoke {
+        /// Public key to revoke (PEM)
+        #[arg(long = "public-key", required_unless_present = "fingerprint")]
+        public_key: Option<PathBuf>,
+
+        /// SHA-256 fingerprint of the key to revoke, instead of --public-key
+        #[arg(long = "fingerprint", conflicts_with = "public_key")]
+        fingerprint: Option<String>,
+
+        /// Revoke signatures made from this time on (RFC 3339 or YYYY-MM-DD, defaults to now)
+        #[arg(long = "revoked-at")]
+        revoked_at: Option<String>,
+
+        /// Why the key is revoked
+        #[arg(long = "reason")]
+        reason: Option<String>,
+
+        /// Private key of the revocation authority, signing the list
+        #[arg(long = "authority-key")]
+        authority_key: PathBuf,
+
+        /// DID issuing the list (defaults to the did:key of --authority-key)
+        #[arg(long = "issuer")]
+        issuer: Option<String>,
+
+        /// Revocation list to update (defaults to the revocation_list of the trust store)
+        #[arg(long = "list")]
+        list: Option<PathBuf>,
     },
 }
@@ -1,19 +1,31 @@
 use crate::error::{Error, Result};
 
+use super::color;
 use super::commands::{
-    CCAttestationCommands, DatasetCommands, EvaluationCommands, ManifestCommands, ModelCommands,
-    PipelineCommands, SoftwareCommands,
+    AttestationCommands, CCAttestationCommands, DatasetCommands, DoctorArgs, EvaluationCommands,
+    ImportCommands, IngredientSelectionArgs, KeyCommands, ManifestCommands, ModelCommands,
+    PipelineCommands, QueueCommands, QuorumArgs, RekorCommands, SlsaCommands, SoftwareCommands,
+    StoreCommands, SyncArgs, VerificationOutputArgs,
 };
 use crate::cc_attestation;
+use crate::cc_attestation::endorsement::EndorsementOptions;
+use crate::cc_attestation::event_log;
+use crate::cc_attestation::tpm::{self, TpmPolicy, TpmQuote, TpmQuoteOptions};
+use crate::doctor::{self, DoctorOptions};
 use crate::manifest;
+use crate::manifest::common::AssetKind;
 use crate::manifest::config::ManifestCreationConfig;
-use crate::manifest::dataset::list_dataset_manifests;
+use crate::manifest::dataset::DatasetSplit;
+use crate::manifest::ingest::IngestPolicy;
+use crate::manifest::lint;
+use crate::manifest::presentation::{ListRow, ShowOptions};
 use crate::slsa;
-use crate::storage::database::DatabaseStorage;
-use crate::storage::filesystem::FilesystemStorage;
+use crate::storage;
+use crate::storage::plugin::PLUGIN_STORAGE_PREFIX;
 use crate::storage::rekor::RekorStorage;
 
 use crate::StorageBackend;
+use openssl::pkey::PKey;
 
 pub fn handle_dataset_command(cmd: DatasetCommands) -> Result<()> {
     let _storage = RekorStorage::new()?;
@@ -21,38 +33,63 @@ pub fn handle_dataset_command(cmd: DatasetCommands) -> Result<()> {
         DatasetCommands::Create {
             paths,
             ingredient_names,
+            ingredient_relationships,
+            expand_archives,
+            profile_data,
+            max_sample_bytes,
+            stream_connections,
+            symlinks,
+            hardlinks,
+            special_files,
+            empty_dirs,
+            path_base,
             name,
             author_org,
             author_name,
             description,
+            encryption,
+            content_addressed_id,
+            force_new,
+            license,
             linked_manifests,
             storage_type,
             storage_url,
             print,
             encoding,
+            output,
             key,
+            pq_key,
             hash_alg,
+            valid_until,
+            created_at,
             with_tdx,
+            with_tpm,
+            tpm_pcrs,
+            tpm_ak,
         } => {
-            let storage: Option<&'static dyn StorageBackend> = match storage_type.as_str() {
-                "database" => {
-                    let db_storage = Box::new(DatabaseStorage::new(*storage_url.clone())?);
-                    Some(Box::leak(db_storage))
-                }
-                "rekor" => {
-                    let rekor_storage = Box::new(RekorStorage::new_with_url(*storage_url.clone())?);
-                    Some(Box::leak(rekor_storage))
-                }
-                "local-fs" => {
-                    let fs_storage = Box::new(FilesystemStorage::new(storage_url.as_str())?);
-                    Some(Box::leak(fs_storage))
-                }
-                _ => None,
-            };
+            let storage: Option<&'static dyn StorageBackend> = Some(Box::leak(
+                storage::create_storage(&storage_type, *storage_url.clone())?,
+            ));
 
-            let config = ManifestCreationConfig {
+            let mut config = ManifestCreationConfig {
                 paths,
                 ingredient_names,
+                ingredient_relationships: ingredient_relationships
+                    .iter()
+                    .map(|relationship| relationship.parse())
+                    .collect::<Result<_>>()?,
+                expand_archives,
+                profile_data,
+                max_sample_bytes,
+                stream_connections,
+                ingest_policy: IngestPolicy {
+                    symlinks: symlinks.to_policy(),
+                    hardlinks: hardlinks.to_policy(),
+                    special_files: special_files.to_policy(),
+                    empty_dirs: empty_dirs.to_policy(),
+                },
+                path_base,
+                dataset_ids: Vec::new(),
                 name,
                 author_org,
                 author_name,
@@ -61,42 +98,234 @@ pub fn handle_dataset_command(cmd: DatasetCommands) -> Result<()> {
                 storage,
                 print,
                 output_encoding: encoding,
+                output_path: output,
                 key_path: key,
+                pq_key_path: pq_key,
                 hash_alg: hash_alg.to_cose_algorithm(),
+                valid_until: valid_until
+                    .as_deref()
+                    .map(manifest::validity::parse_valid_until)
+                    .transpose()?,
+                created_at: created_at
+                    .as_deref()
+                    .map(manifest::timestamp::parse_created_at)
+                    .transpose()?,
                 with_cc: with_tdx,
+                tpm: if with_tpm {
+                    Some(TpmQuoteOptions {
+                        pcr_selection: tpm_pcrs.parse()?,
+                        ak: tpm_ak,
+                    })
+                } else {
+                    None
+                },
                 software_type: None,
                 version: None,
+                encryption: encryption.to_encryption()?,
+                content_addressed_id,
+                force_new,
                 custom_fields: None,
+                inherited_assertions: vec![],
             };
 
+            if let Some(license) = &license {
+                config.set_license(license)?;
+            }
+
             manifest::create_dataset_manifest(config)
         }
         DatasetCommands::List {
+            list,
             storage_type,
             storage_url,
         } => {
-            let storage: Box<dyn StorageBackend> = match storage_type.as_str() {
-                "database" => Box::new(DatabaseStorage::new(*storage_url.clone())?),
-                "rekor" => Box::new(RekorStorage::new_with_url(*storage_url.clone())?),
-                "local-fs" => Box::new(FilesystemStorage::new(storage_url.as_str())?),
-                _ => return Err(Error::Validation("Invalid storage type".to_string())),
-            };
+            let storage = storage::create_storage(&storage_type, *storage_url.clone())?;
 
-            list_dataset_manifests(storage.as_ref())
+            manifest::common::list_manifests_with_options(
+                storage.as_ref(),
+                Some(AssetKind::Dataset),
+                &list.to_options(),
+            )
         }
         DatasetCommands::Verify {
             id,
+            artifact_root,
+            selection,
+            quorum,
+            outputs,
+            storage_type,
+            storage_url,
+        } => {
+            let storage = storage::create_storage(&storage_type, *storage_url.clone())?;
+
+            verify_stored_manifest(
+                &id,
+                storage.as_ref(),
+                artifact_root.as_deref(),
+                &selection,
+                &quorum,
+            )?;
+            write_verification_outputs(&id, storage.as_ref(), &outputs)
+        }
+        DatasetCommands::Split {
+            parent,
+            train,
+            val,
+            test,
+            name,
+            author_org,
+            author_name,
+            description,
+            key,
+            pq_key,
+            hash_alg,
+            storage_type,
+            storage_url,
+        } => {
+            let storage: &'static dyn StorageBackend = Box::leak(storage::create_storage(
+                &storage_type,
+                *storage_url.clone(),
+            )?);
+
+            let config = ManifestCreationConfig {
+                paths: Vec::new(),
+                ingredient_names: Vec::new(),
+                ingredient_relationships: Vec::new(),
+                expand_archives: false,
+                profile_data: false,
+                max_sample_bytes: manifest::profile::DEFAULT_MAX_SAMPLE_BYTES,
+                stream_connections: manifest::object_store::DEFAULT_CONNECTIONS,
+                ingest_policy: IngestPolicy::default(),
+                path_base: None,
+                dataset_ids: Vec::new(),
+                name: name.unwrap_or_default(), // The parent's name if empty
+                author_org,
+                author_name,
+                description,
+                linked_manifests: None,
+                storage: Some(storage),
+                print: false,
+                output_encoding: "json".to_string(),
+                output_path: None,
+                key_path: key,
+                pq_key_path: pq_key,
+                hash_alg: hash_alg.to_cose_algorithm(),
+                valid_until: None,
+                created_at: None,
+                with_cc: false,
+                tpm: None,
+                software_type: None,
+                version: None,
+                encryption: None,
+                content_addressed_id: false,
+                force_new: false,
+                custom_fields: None,
+                inherited_assertions: vec![],
+            };
+
+            manifest::dataset::split_dataset(
+                &parent,
+                vec![
+                    (DatasetSplit::Train, train),
+                    (DatasetSplit::Validation, val),
+                    (DatasetSplit::Test, test),
+                ],
+                config,
+            )?;
+            Ok(())
+        }
+        DatasetCommands::DedupCheck {
+            ids,
+            format,
+            storage_type,
+            storage_url,
+        } => {
+            let storage = storage::create_storage(&storage_type, *storage_url.clone())?;
+
+            let report = manifest::dataset::find_duplicates(&ids, storage.as_ref())?;
+            print!(
+                "{}",
+                manifest::dataset::render_duplicates(&report, &format)?
+            );
+
+            if report.is_clean() {
+                Ok(())
+            } else {
+                Err(Error::Validation(format!(
+                    "Found {} file(s) shared between datasets",
+                    report.overlaps.len()
+                )))
+            }
+        }
+        DatasetCommands::Retire {
+            id,
+            evidence,
+            action,
+            reason,
+            retain_until,
+            name,
+            author_org,
+            author_name,
+            description,
+            key,
+            pq_key,
+            hash_alg,
             storage_type,
             storage_url,
         } => {
-            let storage: Box<dyn StorageBackend> = match storage_type.as_str() {
-                "database" => Box::new(DatabaseStorage::new(*storage_url.clone())?),
-                "rekor" => Box::new(RekorStorage::new_with_url(*storage_url.clone())?),
-                "local-fs" => Box::new(FilesystemStorage::new(storage_url.as_str())?),
-                _ => return Err(Error::Validation("Invalid storage type".to_string())),
+            let storage: &'static dyn StorageBackend = Box::leak(storage::create_storage(
+                &storage_type,
+                *storage_url.clone(),
+            )?);
+
+            let config = ManifestCreationConfig {
+                paths: Vec::new(),
+                ingredient_names: Vec::new(),
+                ingredient_relationships: Vec::new(),
+                expand_archives: false,
+                profile_data: false,
+                max_sample_bytes: manifest::profile::DEFAULT_MAX_SAMPLE_BYTES,
+                stream_connections: manifest::object_store::DEFAULT_CONNECTIONS,
+                ingest_policy: IngestPolicy::default(),
+                path_base: None,
+                dataset_ids: Vec::new(),
+                name: name.unwrap_or_default(), // Named after the dataset if empty
+                author_org,
+                author_name,
+                description,
+                linked_manifests: None,
+                storage: Some(storage),
+                print: false,
+                output_encoding: "json".to_string(),
+                output_path: None,
+                key_path: key,
+                pq_key_path: pq_key,
+                hash_alg: hash_alg.to_cose_algorithm(),
+                valid_until: None,
+                created_at: None,
+                with_cc: false,
+                tpm: None,
+                software_type: None,
+                version: None,
+                encryption: None,
+                content_addressed_id: false,
+                force_new: false,
+                custom_fields: None,
+                inherited_assertions: vec![],
             };
 
-            manifest::verify_dataset_manifest(&id, storage.as_ref())
+            manifest::retention::retire_dataset(
+                &id,
+                action.to_action(),
+                &evidence,
+                reason,
+                retain_until
+                    .as_deref()
+                    .map(manifest::validity::parse_valid_until)
+                    .transpose()?,
+                config,
+            )?;
+            Ok(())
         }
     }
 }
@@ -107,39 +336,62 @@ pub fn handle_model_command(cmd: ModelCommands) -> Result<()> {
         ModelCommands::Create {
             paths,
             ingredient_names,
+            ingredient_relationships,
+            symlinks,
+            hardlinks,
+            special_files,
+            empty_dirs,
+            path_base,
+            dataset_ids,
             name,
             author_org,
             author_name,
             description,
+            encryption,
+            content_addressed_id,
+            force_new,
+            license,
+            like,
             linked_manifests,
             storage_type,
             storage_url,
             print,
             encoding,
+            output,
             format,
             key,
+            pq_key,
             hash_alg,
+            valid_until,
+            created_at,
             with_tdx,
+            with_tpm,
+            tpm_pcrs,
+            tpm_ak,
         } => {
-            let storage: Option<&'static dyn StorageBackend> = match storage_type.as_str() {
-                "database" => {
-                    let db_storage = Box::new(DatabaseStorage::new(*storage_url.clone())?);
-                    Some(Box::leak(db_storage))
-                }
-                "rekor" => {
-                    let rekor_storage = Box::new(RekorStorage::new_with_url(*storage_url.clone())?);
-                    Some(Box::leak(rekor_storage))
-                }
-                "local-fs" => {
-                    let fs_storage = Box::new(FilesystemStorage::new(storage_url.as_str())?);
-                    Some(Box::leak(fs_storage))
-                }
-                _ => None,
-            };
+            let storage: Option<&'static dyn StorageBackend> = Some(Box::leak(
+                storage::create_storage(&storage_type, *storage_url.clone())?,
+            ));
 
-            let config = ManifestCreationConfig {
+            let mut config = ManifestCreationConfig {
                 paths,
                 ingredient_names,
+                ingredient_relationships: ingredient_relationships
+                    .iter()
+                    .map(|relationship| relationship.parse())
+                    .collect::<Result<_>>()?,
+                expand_archives: false,
+                profile_data: false,
+                max_sample_bytes: manifest::profile::DEFAULT_MAX_SAMPLE_BYTES,
+                stream_connections: manifest::object_store::DEFAULT_CONNECTIONS,
+                ingest_policy: IngestPolicy {
+                    symlinks: symlinks.to_policy(),
+                    hardlinks: hardlinks.to_policy(),
+                    special_files: special_files.to_policy(),
+                    empty_dirs: empty_dirs.to_policy(),
+                },
+                path_base,
+                dataset_ids,
                 name,
                 author_org,
                 author_name,
@@ -148,14 +400,48 @@ pub fn handle_model_command(cmd: ModelCommands) -> Result<()> {
                 storage,
                 print,
                 output_encoding: encoding,
+                output_path: output,
                 key_path: key,
+                pq_key_path: pq_key,
                 hash_alg: hash_alg.to_cose_algorithm(),
+                valid_until: valid_until
+                    .as_deref()
+                    .map(manifest::validity::parse_valid_until)
+                    .transpose()?,
+                created_at: created_at
+                    .as_deref()
+                    .map(manifest::timestamp::parse_created_at)
+                    .transpose()?,
                 with_cc: with_tdx,
+                tpm: if with_tpm {
+                    Some(TpmQuoteOptions {
+                        pcr_selection: tpm_pcrs.parse()?,
+                        ak: tpm_ak,
+                    })
+                } else {
+                    None
+                },
                 software_type: None,
                 version: None,
+                encryption: encryption.to_encryption()?,
+                content_addressed_id,
+                force_new,
                 custom_fields: None,
+                inherited_assertions: vec![],
             };
 
+            if let Some(license) = &license {
+                config.set_license(license)?;
+            }
+
+            if let Some(like) = &like {
+                let storage = storage.ok_or_else(|| {
+                    Error::Validation("--like requires a storage backend".to_string())
+                })?;
+                let template = storage.retrieve_manifest(like)?;
+                manifest::template::apply_template(&mut config, &template)?;
+            }
+
             match format.as_str() {
                 "standalone" => manifest::create_model_manifest(config),
                 "oms" => manifest::common::create_oms_manifest(config),
@@ -167,53 +453,120 @@ pub fn handle_model_command(cmd: ModelCommands) -> Result<()> {
             }
         }
         ModelCommands::List {
+            list,
             storage_type,
             storage_url,
         } => {
-            let storage: Box<dyn StorageBackend> = match storage_type.as_str() {
-                "database" => Box::new(DatabaseStorage::new(*storage_url.clone())?),
-                "rekor" => Box::new(RekorStorage::new_with_url(*storage_url.clone())?),
-                "local-fs" => Box::new(FilesystemStorage::new(storage_url.as_str())?),
-                _ => return Err(Error::Validation("Invalid storage type".to_string())),
-            };
+            let storage = storage::create_storage(&storage_type, *storage_url.clone())?;
 
-            manifest::list_model_manifest(storage.as_ref())
+            manifest::common::list_manifests_with_options(
+                storage.as_ref(),
+                Some(AssetKind::Model),
+                &list.to_options(),
+            )
         }
         ModelCommands::Verify {
             id,
+            format,
+            envelope,
+            public_key,
+            artifact_root,
+            selection,
+            quorum,
+            outputs,
+            required_approvals,
+            approval_role,
             storage_type,
             storage_url,
         } => {
-            let storage: Box<dyn StorageBackend> = match storage_type.as_str() {
-                "database" => Box::new(DatabaseStorage::new(*storage_url.clone())?),
-                "rekor" => Box::new(RekorStorage::new_with_url(*storage_url.clone())?),
-                "local-fs" => Box::new(FilesystemStorage::new(storage_url.as_str())?),
-                _ => return Err(Error::Validation("Invalid storage type".to_string())),
-            };
+            match format.as_str() {
+                "standalone" => {}
+                "oms" => {
+                    // The subject digest covers every artifact
+                    if !selection.to_selection().is_all() || quorum.to_rule().is_some() {
+                        return Err(Error::Validation(
+                            "Ingredient selection and quorum options don't apply to OMS verification"
+                                .to_string(),
+                        ));
+                    }
+                    let (Some(envelope), Some(public_key)) = (envelope, public_key) else {
+                        return Err(Error::Validation(
+                            "OMS verification requires --envelope and --public-key".to_string(),
+                        ));
+                    };
+                    let public_key = PKey::public_key_from_pem(&std::fs::read(&public_key)?)
+                        .map_err(|e| Error::Validation(format!("Invalid public key: {e}")))?;
+                    return manifest::common::verify_oms_manifest(
+                        &envelope,
+                        &public_key,
+                        artifact_root.as_deref(),
+                    );
+                }
+                _ => {
+                    return Err(Error::Validation("Unsupported format".to_string()));
+                }
+            }
+            let id = id.ok_or_else(|| Error::Validation("--id is required".to_string()))?;
+
+            let storage = storage::create_storage(&storage_type, *storage_url.clone())?;
+
+            verify_stored_manifest(
+                &id,
+                storage.as_ref(),
+                artifact_root.as_deref(),
+                &selection,
+                &quorum,
+            )?;
 
-            manifest::verify_model_manifest(&id, storage.as_ref())
+            if required_approvals > 0 || approval_role.is_some() {
+                let policy = manifest::approval::ApprovalPolicy {
+                    required_approvals,
+                    role: approval_role,
+                };
+                manifest::approval::verify_release_ready(&id, storage.as_ref(), &policy)?;
+            }
+            write_verification_outputs(&id, storage.as_ref(), &outputs)
         }
         ModelCommands::LinkDataset {
             model_id,
-            dataset_id,
+            dataset_ids,
             storage_type,
             storage_url,
         } => {
-            let storage: Box<dyn StorageBackend> = match storage_type.as_str() {
-                "database" => Box::new(DatabaseStorage::new(*storage_url.clone())?),
-                "rekor" => Box::new(RekorStorage::new_with_url(*storage_url.clone())?),
-                "local-fs" => Box::new(FilesystemStorage::new(storage_url.as_str())?),
-                _ => return Err(Error::Validation("Invalid storage type".to_string())),
-            };
+            let storage = storage::create_storage(&storage_type, *storage_url.clone())?;
 
-            let updated_manifest =
-                manifest::linking::link_dataset_to_model(&model_id, &dataset_id, storage.as_ref())?;
+            let updated_manifest = manifest::linking::link_datasets_to_model(
+                &model_id,
+                &dataset_ids,
+                storage.as_ref(),
+            )?;
 
-            println!("Successfully linked dataset {dataset_id} to model {model_id}");
+            println!(
+                "Successfully linked {} dataset(s) to model {model_id}",
+                dataset_ids.len()
+            );
             println!("Updated manifest ID: {}", updated_manifest.instance_id);
 
             Ok(())
         }
+        ModelCommands::Lineage {
+            id,
+            direction,
+            max_depth,
+            format,
+            storage_type,
+            storage_url,
+        } => {
+            let storage = storage::create_storage(&storage_type, *storage_url.clone())?;
+
+            manifest::lineage::print_lineage(
+                &id,
+                storage.as_ref(),
+                direction.to_direction(),
+                max_depth,
+                &format,
+            )
+        }
     }
 }
 
@@ -225,40 +578,89 @@ pub fn handle_manifest_command(cmd: ManifestCommands) -> Result<()> {
             storage_type,
             storage_url,
         } => {
-            let storage: Box<dyn StorageBackend> = match storage_type.as_str() {
-                "database" => Box::new(DatabaseStorage::new(*storage_url.clone())?),
-                "rekor" => Box::new(RekorStorage::new_with_url(*storage_url.clone())?),
-                "local-fs" => Box::new(FilesystemStorage::new(storage_url.as_str())?),
-                _ => return Err(Error::Validation("Invalid storage type".to_string())),
-            };
+            let storage = storage::create_storage(&storage_type, *storage_url.clone())?;
 
             manifest::link_manifests(&source, &target, &*storage)
         }
+        ManifestCommands::LinkBatch {
+            spec,
+            source,
+            targets,
+            storage_type,
+            storage_url,
+        } => {
+            let storage = storage::create_storage(&storage_type, *storage_url.clone())?;
+
+            let spec = match (spec, source) {
+                (Some(path), _) => manifest::LinkBatchSpec::from_file(&path)?,
+                (None, Some(source)) => manifest::LinkBatchSpec {
+                    links: vec![manifest::LinkBatchEntry { source, targets }],
+                },
+                (None, None) => {
+                    return Err(Error::Validation(
+                        "Either --spec or --source with --targets is required".to_string(),
+                    ));
+                }
+            };
+
+            let updated_ids = manifest::link_manifests_batch(&spec, &*storage)?;
+            println!("Updated {} source manifest(s)", updated_ids.len());
+            Ok(())
+        }
+        ManifestCommands::List {
+            list,
+            expiring_within,
+            storage_type,
+            storage_url,
+        } => {
+            let storage = storage::create_storage(&storage_type, *storage_url.clone())?;
+
+            let options = list.to_options();
+            let Some(within) = expiring_within else {
+                return manifest::common::list_manifests_with_options(
+                    storage.as_ref(),
+                    None,
+                    &options,
+                );
+            };
+
+            let within = manifest::validity::parse_duration(&within)?;
+            let rows = manifest::validity::expiring_manifests(&*storage, within)?
+                .into_iter()
+                .map(|(metadata, until)| ListRow {
+                    metadata,
+                    valid_until: Some(until),
+                })
+                .collect();
+            let rows = manifest::common::filter_by_signature(&*storage, rows, &options.signature)?;
+            manifest::presentation::print_manifest_list(rows, &options)
+        }
         ManifestCommands::Show {
             id,
             storage_type,
             storage_url,
+            output,
+            field,
+            identity,
         } => {
-            let storage: Box<dyn StorageBackend> = match storage_type.as_str() {
-                "database" => Box::new(DatabaseStorage::new(*storage_url.clone())?),
-                "rekor" => Box::new(RekorStorage::new_with_url(*storage_url.clone())?),
-                "local-fs" => Box::new(FilesystemStorage::new(storage_url.as_str())?),
-                _ => return Err(Error::Validation("Invalid storage type".to_string())),
-            };
+            let storage = storage::create_storage(&storage_type, *storage_url.clone())?;
 
-            manifest::show_manifest(&id, &*storage)
+            let options = ShowOptions {
+                format: output.to_format(),
+                fields: field,
+            };
+            let identities = match identity {
+                Some(path) => manifest::encryption::load_identities(&path)?,
+                None => Vec::new(),
+            };
+            manifest::show_manifest_with_options(&id, &*storage, &options, &identities)
         }
         ManifestCommands::Validate {
             id,
             storage_type,
             storage_url,
         } => {
-            let storage: Box<dyn StorageBackend> = match storage_type.as_str() {
-                "database" => Box::new(DatabaseStorage::new(*storage_url.clone())?),
-                "rekor" => Box::new(RekorStorage::new_with_url(*storage_url.clone())?),
-                "local-fs" => Box::new(FilesystemStorage::new(storage_url.as_str())?),
-                _ => return Err(Error::Validation("Invalid storage type".to_string())),
-            };
+            let storage = storage::create_storage(&storage_type, *storage_url.clone())?;
 
             manifest::validate_linked_manifests(&id, &*storage)
         }
@@ -268,12 +670,7 @@ pub fn handle_manifest_command(cmd: ManifestCommands) -> Result<()> {
             storage_type,
             storage_url,
         } => {
-            let storage: Box<dyn StorageBackend> = match storage_type.as_str() {
-                "database" => Box::new(DatabaseStorage::new(*storage_url.clone())?),
-                "rekor" => Box::new(RekorStorage::new_with_url(*storage_url.clone())?),
-                "local-fs" => Box::new(FilesystemStorage::new(storage_url.as_str())?),
-                _ => return Err(Error::Validation("Invalid storage type".to_string())),
-            };
+            let storage = storage::create_storage(&storage_type, *storage_url.clone())?;
 
             let result = manifest::verify_manifest_link(&source, &target, &*storage)?;
             if result {
@@ -291,12 +688,7 @@ pub fn handle_manifest_command(cmd: ManifestCommands) -> Result<()> {
             output,
             max_depth,
         } => {
-            let storage: Box<dyn StorageBackend> = match storage_type.as_str() {
-                "database" => Box::new(DatabaseStorage::new(*storage_url.clone())?),
-                "rekor" => Box::new(RekorStorage::new_with_url(*storage_url.clone())?),
-                "local-fs" => Box::new(FilesystemStorage::new(storage_url.as_str())?),
-                _ => return Err(Error::Validation("Invalid storage type".to_string())),
-            };
+            let storage = storage::create_storage(&storage_type, *storage_url.clone())?;
 
             manifest::export_provenance(
                 &id,
@@ -306,6 +698,288 @@ pub fn handle_manifest_command(cmd: ManifestCommands) -> Result<()> {
                 max_depth,
             )
         }
+        ManifestCommands::Approve {
+            id,
+            role,
+            reviewer,
+            decision,
+            key,
+            hash_alg,
+            storage_type,
+            storage_url,
+        } => {
+            let storage = storage::create_storage(&storage_type, *storage_url.clone())?;
+
+            let decision = decision.to_decision();
+            let updated_id = manifest::approval::approve_manifest(
+                &id,
+                storage.as_ref(),
+                &role,
+                reviewer,
+                decision,
+                key,
+                hash_alg.to_cose_algorithm(),
+            )?;
+            println!("Recorded {decision} decision ({role}) for manifest {id}");
+            println!("Updated manifest ID: {updated_id}");
+            Ok(())
+        }
+        ManifestCommands::Redact {
+            id,
+            assertions,
+            reason,
+            key,
+            hash_alg,
+            storage_type,
+            storage_url,
+        } => {
+            let storage = storage::create_storage(&storage_type, *storage_url.clone())?;
+
+            let updated_id = manifest::redaction::redact_manifest(
+                &id,
+                storage.as_ref(),
+                &assertions,
+                reason,
+                key,
+                hash_alg.to_cose_algorithm(),
+            )?;
+            println!(
+                "Redacted {} assertion(s) of manifest {id}",
+                assertions.len()
+            );
+            println!("Updated manifest ID: {updated_id}");
+            Ok(())
+        }
+        ManifestCommands::ExportCredential {
+            id,
+            key,
+            issuer,
+            hash_alg,
+            output,
+            storage_type,
+            storage_url,
+        } => {
+            let storage = storage::create_storage(&storage_type, *storage_url.clone())?;
+
+            let jws = manifest::credential::export_credential(
+                &id,
+                storage.as_ref(),
+                &key,
+                issuer,
+                &hash_alg.to_cose_algorithm(),
+            )?;
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, format!("{jws}\n"))?;
+                    println!("Credential for manifest {id} written to {}", path.display());
+                }
+                None => println!("{jws}"),
+            }
+            Ok(())
+        }
+        ManifestCommands::VerifyCredential {
+            credential,
+            public_key,
+        } => {
+            let public_key = PKey::public_key_from_pem(&std::fs::read(&public_key)?)
+                .map_err(|e| Error::Validation(format!("Invalid public key: {e}")))?;
+            let credential = manifest::credential::verify_credential(
+                &std::fs::read_to_string(&credential)?,
+                &public_key,
+            )?;
+
+            println!("Credential signature verification successful");
+            println!("{}", serde_json::to_string_pretty(&credential)?);
+            Ok(())
+        }
+        ManifestCommands::VerifyAll {
+            signature,
+            artifact_root,
+            storage_type,
+            storage_url,
+        } => {
+            let storage = storage::create_storage(&storage_type, *storage_url.clone())?;
+
+            let report = manifest::common::verify_all_manifests(
+                &*storage,
+                &signature.to_filter(),
+                artifact_root.as_deref(),
+            )?;
+            for (id, reason) in &report.failed {
+                println!("  {} {id}: {reason}", color::cross_mark());
+            }
+            println!(
+                "{} manifest(s) verified, {} failed",
+                report.verified.len(),
+                report.failed.len()
+            );
+            if report.failed.is_empty() {
+                Ok(())
+            } else {
+                Err(Error::Validation(format!(
+                    "{} manifest(s) failed verification",
+                    report.failed.len()
+                )))
+            }
+        }
+        ManifestCommands::Fsck {
+            repair,
+            yes,
+            storage_type,
+            storage_url,
+        } => {
+            let storage = storage::create_storage(&storage_type, *storage_url.clone())?;
+
+            let report = manifest::fsck::check_store(&*storage)?;
+            println!("Checked {} manifest(s)", report.manifests_checked);
+            for issue in &report.issues {
+                println!("  {} {issue}", color::cross_mark());
+            }
+
+            if report.is_clean() {
+                println!("No problems found");
+                return Ok(());
+            }
+
+            let mut remaining = report.issues.len();
+            if repair {
+                let repaired = manifest::fsck::repair_store(&report, &*storage, |issue| {
+                    yes || crate::cli::confirm_action(&format!(
+                        "Recompute the cross-reference hash of {} in {}?",
+                        issue.target_id.as_deref().unwrap_or_default(),
+                        issue.manifest_id
+                    ))
+                })?;
+                println!("Repaired {repaired} issue(s)");
+                remaining = remaining.saturating_sub(repaired);
+            }
+
+            if remaining == 0 {
+                Ok(())
+            } else {
+                Err(Error::Validation(format!(
+                    "Found {remaining} unrepaired integrity problem(s)"
+                )))
+            }
+        }
+        ManifestCommands::Lint {
+            id,
+            fix,
+            path_base,
+            format,
+            storage_type,
+            storage_url,
+        } => {
+            let storage = storage::create_storage(&storage_type, *storage_url.clone())?;
+            if format != "text" && format != "json" {
+                return Err(Error::Validation(format!("Invalid format: {format}")));
+            }
+
+            let config = lint::LintConfig::load(&lint::lint_file())?;
+            let options = lint::LintOptions { path_base };
+            let ids = match id {
+                Some(id) => vec![id],
+                None => storage
+                    .list_manifests()?
+                    .into_iter()
+                    .map(|metadata| metadata.id)
+                    .collect(),
+            };
+
+            let mut findings = Vec::new();
+            for id in &ids {
+                let mut manifest = storage.retrieve_manifest(id)?;
+                let mut found = lint::lint_manifest(id, &manifest, &config, &options);
+                if fix {
+                    let fixed = lint::fix_manifest(&mut manifest, &found, &options)?;
+                    if !fixed.is_empty() {
+                        storage.store_manifest(&manifest)?;
+                        found.retain(|finding| !fixed.contains(&finding.rule));
+                        let names: Vec<_> = fixed.iter().map(|rule| rule.name()).collect();
+                        eprintln!("Fixed {} in {id}", names.join(", "));
+                    }
+                }
+                findings.extend(found);
+            }
+
+            if format == "json" {
+                println!("{}", serde_json::to_string_pretty(&findings)?);
+            } else {
+                for finding in &findings {
+                    let mark = match finding.severity {
+                        lint::Severity::Error => color::cross_mark(),
+                        lint::Severity::Warning => color::warning_sign(),
+                        _ => "ℹ".to_string(),
+                    };
+                    println!("{mark} {finding}");
+                }
+                println!(
+                    "Linted {} manifest(s): {} finding(s)",
+                    ids.len(),
+                    findings.len()
+                );
+            }
+
+            let errors = findings
+                .iter()
+                .filter(|finding| finding.severity == lint::Severity::Error)
+                .count();
+            if errors > 0 {
+                return Err(Error::Validation(format!("Found {errors} lint error(s)")));
+            }
+            Ok(())
+        }
+        ManifestCommands::Compress {
+            storage_type,
+            storage_url,
+        } => {
+            let storage = storage::create_storage(&storage_type, *storage_url.clone())?;
+
+            let migrated = storage.migrate_compression()?;
+            println!("Compressed {migrated} manifest(s)");
+            Ok(())
+        }
+        ManifestCommands::Upgrade {
+            id,
+            key,
+            hash_alg,
+            dry_run,
+            storage_type,
+            storage_url,
+        } => {
+            let storage = storage::create_storage(&storage_type, *storage_url.clone())?;
+
+            let current = manifest::migration::CURRENT_SCHEMA_VERSION;
+            if dry_run {
+                let steps = manifest::migration::pending_steps(&storage.retrieve_manifest(&id)?)?;
+                if steps.is_empty() {
+                    println!("Manifest {id} is at schema version {current}");
+                } else {
+                    println!("Manifest {id} is at schema version 1:");
+                    for step in steps {
+                        println!("  - {step}");
+                    }
+                }
+                return Ok(());
+            }
+
+            match manifest::migration::upgrade_manifest(
+                &id,
+                storage.as_ref(),
+                key,
+                hash_alg.to_cose_algorithm(),
+            )? {
+                Some((updated_id, steps)) => {
+                    println!("Migrated manifest {id} to schema version {current}:");
+                    for step in steps {
+                        println!("  - {step}");
+                    }
+                    println!("Updated manifest ID: {updated_id}");
+                }
+                None => println!("Manifest {id} is already at schema version {current}"),
+            }
+            Ok(())
+        }
     }
 }
 
@@ -317,35 +991,39 @@ pub fn handle_evaluation_command(cmd: EvaluationCommands) -> Result<()> {
             model_id,
             dataset_id,
             metrics,
+            results_format,
             author_org,
             author_name,
             description,
+            encryption,
+            content_addressed_id,
+            force_new,
             storage_type,
             storage_url,
             print,
             encoding,
+            output,
             key,
+            pq_key,
             hash_alg,
+            valid_until,
+            created_at,
         } => {
-            let storage: Option<&'static dyn StorageBackend> = match storage_type.as_str() {
-                "database" => {
-                    let db_storage = Box::new(DatabaseStorage::new(*storage_url.clone())?);
-                    Some(Box::leak(db_storage))
-                }
-                "rekor" => {
-                    let rekor_storage = Box::new(RekorStorage::new_with_url(*storage_url.clone())?);
-                    Some(Box::leak(rekor_storage))
-                }
-                "local-fs" => {
-                    let fs_storage = Box::new(FilesystemStorage::new(storage_url.as_str())?);
-                    Some(Box::leak(fs_storage))
-                }
-                _ => None,
-            };
+            let storage: Option<&'static dyn StorageBackend> = Some(Box::leak(
+                storage::create_storage(&storage_type, *storage_url.clone())?,
+            ));
 
             let config = ManifestCreationConfig {
                 paths: vec![path],
                 ingredient_names: vec!["Evaluation Results".to_string()],
+                ingredient_relationships: Vec::new(),
+                expand_archives: false,
+                profile_data: false,
+                max_sample_bytes: manifest::profile::DEFAULT_MAX_SAMPLE_BYTES,
+                stream_connections: manifest::object_store::DEFAULT_CONNECTIONS,
+                ingest_policy: IngestPolicy::default(),
+                path_base: None,
+                dataset_ids: Vec::new(),
                 name,
                 author_org,
                 author_name,
@@ -354,48 +1032,82 @@ pub fn handle_evaluation_command(cmd: EvaluationCommands) -> Result<()> {
                 storage,
                 print,
                 output_encoding: encoding,
+                output_path: output,
                 key_path: key,
+                pq_key_path: pq_key,
                 hash_alg: hash_alg.to_cose_algorithm(),
+                valid_until: valid_until
+                    .as_deref()
+                    .map(manifest::validity::parse_valid_until)
+                    .transpose()?,
+                created_at: created_at
+                    .as_deref()
+                    .map(manifest::timestamp::parse_created_at)
+                    .transpose()?,
                 with_cc: false,
+                tpm: None,
                 software_type: None,
                 version: None,
+                encryption: encryption.to_encryption()?,
+                content_addressed_id,
+                force_new,
                 custom_fields: None, // Will be populated by create_manifest
+                inherited_assertions: vec![],
             };
 
-            manifest::evaluation::create_manifest(config, model_id, dataset_id, metrics)
+            manifest::evaluation::create_manifest(
+                config,
+                model_id,
+                dataset_id,
+                metrics,
+                results_format.map(|format| format.to_results_format()),
+            )
         }
         EvaluationCommands::List {
+            list,
             storage_type,
             storage_url,
         } => {
-            let storage: Box<dyn StorageBackend> = match storage_type.as_str() {
-                "database" => Box::new(DatabaseStorage::new(*storage_url.clone())?),
-                "rekor" => Box::new(RekorStorage::new_with_url(*storage_url.clone())?),
-                "local-fs" => Box::new(FilesystemStorage::new(storage_url.as_str())?),
-                _ => return Err(Error::Validation("Invalid storage type".to_string())),
-            };
+            let storage = storage::create_storage(&storage_type, *storage_url.clone())?;
 
-            manifest::evaluation::list_evaluation_manifests(storage.as_ref())
+            manifest::common::list_manifests_with_options(
+                storage.as_ref(),
+                Some(AssetKind::Evaluation),
+                &list.to_options(),
+            )
         }
         EvaluationCommands::Verify {
             id,
             storage_type,
             storage_url,
         } => {
-            let storage: Box<dyn StorageBackend> = match storage_type.as_str() {
-                "database" => Box::new(DatabaseStorage::new(*storage_url.clone())?),
-                "rekor" => Box::new(RekorStorage::new_with_url(*storage_url.clone())?),
-                "local-fs" => Box::new(FilesystemStorage::new(storage_url.as_str())?),
-                _ => return Err(Error::Validation("Invalid storage type".to_string())),
-            };
+            let storage = storage::create_storage(&storage_type, *storage_url.clone())?;
 
             manifest::evaluation::verify_evaluation_manifest(&id, storage.as_ref())
         }
-    }
-}
-
-pub fn handle_cc_attestation_command(cmd: CCAttestationCommands) -> Result<()> {
-    match cmd {
+        EvaluationCommands::History {
+            model_id,
+            metric,
+            format,
+            output,
+            storage_type,
+            storage_url,
+        } => {
+            let storage = storage::create_storage(&storage_type, *storage_url.clone())?;
+
+            manifest::evaluation::print_evaluation_history(
+                &model_id,
+                metric.as_deref(),
+                storage.as_ref(),
+                &format,
+                output.as_deref(),
+            )
+        }
+    }
+}
+
+pub fn handle_cc_attestation_command(cmd: CCAttestationCommands) -> Result<()> {
+    match cmd {
         CCAttestationCommands::Show => {
             let _r = cc_attestation::get_report(true).unwrap();
             Ok(())
@@ -407,8 +1119,28 @@ pub fn handle_cc_attestation_command(cmd: CCAttestationCommands) -> Result<()> {
             Ok(())
         }
 
-        CCAttestationCommands::VerifyLaunch { host_platform } => {
-            let result = cc_attestation::verify_launch_endorsement(&host_platform).unwrap();
+        CCAttestationCommands::VerifyLaunch {
+            host_platform,
+            endorsement,
+            endorsement_key,
+            allow_unsigned_endorsement,
+            allow_http_endorsement,
+        } => {
+            let public_key = match endorsement_key {
+                Some(path) => Some(
+                    PKey::public_key_from_pem(&std::fs::read(&path)?)
+                        .map_err(|e| Error::Validation(format!("Invalid endorsement key: {e}")))?,
+                ),
+                None => None,
+            };
+            let options = EndorsementOptions {
+                source: endorsement,
+                public_key,
+                allow_unsigned: allow_unsigned_endorsement,
+                allow_http: allow_http_endorsement,
+            };
+
+            let result = cc_attestation::verify_launch_endorsement(&host_platform, &options)?;
             if result {
                 println!(
                     "Passed: launch endorsement verification for {host_platform} host platform"
@@ -420,6 +1152,127 @@ pub fn handle_cc_attestation_command(cmd: CCAttestationCommands) -> Result<()> {
             }
             Ok(())
         }
+
+        CCAttestationCommands::EventLog { path } => {
+            let path = path.unwrap_or_else(|| event_log::CCEL_DATA_PATH.into());
+            let log = event_log::read_event_log(&path)?.ok_or_else(|| {
+                Error::CCAttestationError(format!("No event log found at {}", path.display()))
+            })?;
+
+            for entry in &log.entries {
+                println!(
+                    "MR[{}] {} {}{}",
+                    entry.mr_index,
+                    entry.event_type_name,
+                    entry.digest.as_deref().unwrap_or("-"),
+                    entry
+                        .description
+                        .as_ref()
+                        .map(|d| format!(" ({d})"))
+                        .unwrap_or_default()
+                );
+            }
+            for (i, rtmr) in log.rtmrs.iter().enumerate() {
+                println!("Replayed RTMR{i}: {rtmr}");
+            }
+            Ok(())
+        }
+
+        CCAttestationCommands::VerifyEventLog {
+            id,
+            storage_type,
+            storage_url,
+        } => {
+            let storage = storage::create_storage(&storage_type, *storage_url.clone())?;
+
+            let manifest = storage.retrieve_manifest(&id)?;
+            let (log, report) = event_log::event_log_from_manifest(&manifest)?;
+
+            if event_log::verify_against_report(&log, &report)? {
+                println!(
+                    "Passed: event log ({} entries) replays to the reported RTMR values",
+                    log.entries.len()
+                );
+                Ok(())
+            } else {
+                Err(Error::CCAttestationError(
+                    "Event log does not match the reported RTMR values".to_string(),
+                ))
+            }
+        }
+
+        CCAttestationCommands::Attach {
+            id,
+            key,
+            hash_alg,
+            storage_type,
+            storage_url,
+        } => {
+            let storage = storage::create_storage(&storage_type, *storage_url.clone())?;
+
+            let updated_id = manifest::common::attach_cc_attestation(
+                &id,
+                storage.as_ref(),
+                key,
+                hash_alg.to_cose_algorithm(),
+            )?;
+            println!("Attached CC attestation to manifest {id}");
+            println!("Updated manifest ID: {updated_id}");
+            Ok(())
+        }
+
+        CCAttestationCommands::TpmQuote {
+            pcrs,
+            ak,
+            nonce,
+            output,
+        } => {
+            let selection: tpm::PcrSelection = pcrs.parse()?;
+            let nonce = nonce.map(hex::decode).transpose()?;
+            let quote = tpm::collect_quote(&selection, &ak, nonce.as_deref())?;
+            let quote_json = serde_json::to_string_pretty(&quote)?;
+
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, quote_json)?;
+                    println!("TPM quote written to {}", path.display());
+                }
+                None => println!("{quote_json}"),
+            }
+            Ok(())
+        }
+
+        CCAttestationCommands::VerifyTpm {
+            id,
+            quote,
+            policy,
+            storage_type,
+            storage_url,
+        } => {
+            let quote: TpmQuote = match (id, quote) {
+                (Some(id), _) => {
+                    let storage = storage::create_storage(&storage_type, *storage_url.clone())?;
+                    let manifest = storage.retrieve_manifest(&id)?;
+                    tpm::quote_from_manifest(&manifest)?
+                }
+                (None, Some(path)) => serde_json::from_str(&std::fs::read_to_string(path)?)?,
+                (None, None) => {
+                    return Err(Error::Validation(
+                        "Either --id or --quote must be provided".to_string(),
+                    ));
+                }
+            };
+            let policy = TpmPolicy::from_file(&policy)?;
+
+            if tpm::verify_quote(&quote, &policy)? {
+                println!("Passed: TPM quote verification against policy");
+                Ok(())
+            } else {
+                Err(Error::CCAttestationError(
+                    "TPM quote verification failed".to_string(),
+                ))
+            }
+        }
     }
 }
 
@@ -428,40 +1281,56 @@ pub fn handle_software_command(cmd: SoftwareCommands) -> Result<()> {
         SoftwareCommands::Create {
             paths,
             ingredient_names,
+            ingredient_relationships,
+            path_base,
+            from_python_env,
+            wheel_cache,
+            from_dockerfile,
+            context,
             name,
             software_type,
             version,
             author_org,
             author_name,
             description,
+            encryption,
+            content_addressed_id,
+            force_new,
+            license,
             linked_manifests,
             storage_type,
             storage_url,
             print,
             encoding,
+            output,
             key,
+            pq_key,
             hash_alg,
+            valid_until,
+            created_at,
             with_tdx,
+            with_tpm,
+            tpm_pcrs,
+            tpm_ak,
         } => {
-            let storage: Option<&'static dyn StorageBackend> = match storage_type.as_str() {
-                "database" => {
-                    let db_storage = Box::new(DatabaseStorage::new(*storage_url.clone())?);
-                    Some(Box::leak(db_storage))
-                }
-                "rekor" => {
-                    let rekor_storage = Box::new(RekorStorage::new_with_url(*storage_url.clone())?);
-                    Some(Box::leak(rekor_storage))
-                }
-                "local-fs" => {
-                    let fs_storage = Box::new(FilesystemStorage::new(storage_url.as_str())?);
-                    Some(Box::leak(fs_storage))
-                }
-                _ => None,
-            };
+            let storage: Option<&'static dyn StorageBackend> = Some(Box::leak(
+                storage::create_storage(&storage_type, *storage_url.clone())?,
+            ));
 
-            let config = ManifestCreationConfig {
+            let mut config = ManifestCreationConfig {
                 paths,
                 ingredient_names,
+                ingredient_relationships: ingredient_relationships
+                    .iter()
+                    .map(|relationship| relationship.parse())
+                    .collect::<Result<_>>()?,
+                expand_archives: false,
+                profile_data: false,
+                max_sample_bytes: manifest::profile::DEFAULT_MAX_SAMPLE_BYTES,
+                stream_connections: manifest::object_store::DEFAULT_CONNECTIONS,
+                ingest_policy: IngestPolicy::default(),
+                path_base,
+                dataset_ids: Vec::new(),
                 name,
                 author_org,
                 author_name,
@@ -470,42 +1339,98 @@ pub fn handle_software_command(cmd: SoftwareCommands) -> Result<()> {
                 storage,
                 print,
                 output_encoding: encoding,
+                output_path: output,
                 key_path: key,
+                pq_key_path: pq_key,
                 hash_alg: hash_alg.to_cose_algorithm(),
+                valid_until: valid_until
+                    .as_deref()
+                    .map(manifest::validity::parse_valid_until)
+                    .transpose()?,
+                created_at: created_at
+                    .as_deref()
+                    .map(manifest::timestamp::parse_created_at)
+                    .transpose()?,
                 with_cc: with_tdx,
+                tpm: if with_tpm {
+                    Some(TpmQuoteOptions {
+                        pcr_selection: tpm_pcrs.parse()?,
+                        ak: tpm_ak,
+                    })
+                } else {
+                    None
+                },
                 software_type: Some(software_type.clone()),
                 version: version.clone(),
+                encryption: encryption.to_encryption()?,
+                content_addressed_id,
+                force_new,
                 custom_fields: None,
+                inherited_assertions: vec![],
             };
 
+            if let Some(license) = &license {
+                config.set_license(license)?;
+            }
+
+            if let Some(prefix) = from_python_env {
+                let prefix = match prefix {
+                    Some(prefix) => prefix,
+                    None => manifest::python_env::active_environment()?,
+                };
+                let wheel_cache = wheel_cache.or_else(manifest::python_env::default_wheel_cache);
+                let environment = manifest::python_env::snapshot(&prefix, wheel_cache.as_deref())?;
+                println!(
+                    "Recording {} package(s) from {}",
+                    environment.packages.len(),
+                    prefix.display()
+                );
+                manifest::python_env::add_to_config(&environment, &mut config)?;
+            }
+
+            if let Some(dockerfile) = from_dockerfile {
+                let build = manifest::dockerfile::snapshot(&dockerfile, context.as_deref())?;
+                println!(
+                    "Recording {} and {} build context file(s) from {}",
+                    build.dockerfile, build.context_files, build.context
+                );
+                manifest::dockerfile::add_to_config(&build, &mut config)?;
+            }
+
             manifest::software::create_manifest(config, software_type, version)
         }
         SoftwareCommands::List {
+            list,
             storage_type,
             storage_url,
         } => {
-            let storage: Box<dyn StorageBackend> = match storage_type.as_str() {
-                "database" => Box::new(DatabaseStorage::new(*storage_url.clone())?),
-                "rekor" => Box::new(RekorStorage::new_with_url(*storage_url.clone())?),
-                "local-fs" => Box::new(FilesystemStorage::new(storage_url.as_str())?),
-                _ => return Err(Error::Validation("Invalid storage type".to_string())),
-            };
+            let storage = storage::create_storage(&storage_type, *storage_url.clone())?;
 
-            manifest::software::list_software_manifests(storage.as_ref())
+            manifest::common::list_manifests_with_options(
+                storage.as_ref(),
+                Some(AssetKind::Software),
+                &list.to_options(),
+            )
         }
         SoftwareCommands::Verify {
             id,
+            artifact_root,
+            selection,
+            quorum,
+            outputs,
             storage_type,
             storage_url,
         } => {
-            let storage: Box<dyn StorageBackend> = match storage_type.as_str() {
-                "database" => Box::new(DatabaseStorage::new(*storage_url.clone())?),
-                "rekor" => Box::new(RekorStorage::new_with_url(*storage_url.clone())?),
-                "local-fs" => Box::new(FilesystemStorage::new(storage_url.as_str())?),
-                _ => return Err(Error::Validation("Invalid storage type".to_string())),
-            };
+            let storage = storage::create_storage(&storage_type, *storage_url.clone())?;
 
-            manifest::software::verify_software_manifest(&id, storage.as_ref())
+            verify_stored_manifest(
+                &id,
+                storage.as_ref(),
+                artifact_root.as_deref(),
+                &selection,
+                &quorum,
+            )?;
+            write_verification_outputs(&id, storage.as_ref(), &outputs)
         }
         SoftwareCommands::LinkModel {
             software_id,
@@ -513,15 +1438,18 @@ pub fn handle_software_command(cmd: SoftwareCommands) -> Result<()> {
             storage_type,
             storage_url,
         } => {
-            let storage: Box<dyn StorageBackend> = match storage_type.as_str() {
-                "database" => Box::new(DatabaseStorage::new(*storage_url.clone())?),
-                "rekor" => Box::new(RekorStorage::new_with_url(*storage_url.clone())?),
-                "local-fs" => Box::new(FilesystemStorage::new(storage_url.as_str())?),
-                _ => return Err(Error::Validation("Invalid storage type".to_string())),
-            };
+            let storage = storage::create_storage(&storage_type, *storage_url.clone())?;
+
+            let updated_manifest = manifest::linking::link_software_to_model(
+                &model_id,
+                &software_id,
+                storage.as_ref(),
+            )?;
+
+            println!("Successfully linked software {software_id} to model {model_id}");
+            println!("Updated manifest ID: {}", updated_manifest.instance_id);
 
-            // Link software to model
-            manifest::link_manifests(&model_id, &software_id, storage.as_ref())
+            Ok(())
         }
         SoftwareCommands::LinkDataset {
             software_id,
@@ -529,12 +1457,7 @@ pub fn handle_software_command(cmd: SoftwareCommands) -> Result<()> {
             storage_type,
             storage_url,
         } => {
-            let storage: Box<dyn StorageBackend> = match storage_type.as_str() {
-                "database" => Box::new(DatabaseStorage::new(*storage_url.clone())?),
-                "rekor" => Box::new(RekorStorage::new_with_url(*storage_url.clone())?),
-                "local-fs" => Box::new(FilesystemStorage::new(storage_url.as_str())?),
-                _ => return Err(Error::Validation("Invalid storage type".to_string())),
-            };
+            let storage = storage::create_storage(&storage_type, *storage_url.clone())?;
 
             // Link software to dataset
             manifest::link_manifests(&dataset_id, &software_id, storage.as_ref())
@@ -551,18 +1474,15 @@ pub fn handle_pipeline_command(cmd: PipelineCommands) -> Result<()> {
             key,
             hash_alg,
             encoding,
+            output,
             print,
             storage_type,
             storage_url,
             with_tdx,
         } => {
-            let storage: Option<&'static dyn StorageBackend> = match storage_type.as_str() {
-                "local-fs" => {
-                    let fs_storage = Box::new(FilesystemStorage::new(storage_url.as_str())?);
-                    Some(Box::leak(fs_storage))
-                }
-                _ => None,
-            };
+            let storage: Option<&'static dyn StorageBackend> = Some(Box::leak(
+                storage::create_storage(&storage_type, *storage_url.clone())?,
+            ));
 
             slsa::cli::generate_build_provenance(
                 inputs,
@@ -571,6 +1491,7 @@ pub fn handle_pipeline_command(cmd: PipelineCommands) -> Result<()> {
                 key,
                 hash_alg.to_cose_algorithm(),
                 encoding,
+                output,
                 print,
                 storage,
                 with_tdx,
@@ -578,3 +1499,870 @@ pub fn handle_pipeline_command(cmd: PipelineCommands) -> Result<()> {
         }
     }
 }
+
+pub fn handle_slsa_command(cmd: SlsaCommands) -> Result<()> {
+    match cmd {
+        SlsaCommands::Import {
+            from,
+            metadata,
+            key,
+            hash_alg,
+            print,
+            encoding,
+            output,
+            storage_type,
+            storage_url,
+        } => {
+            let storage: Option<&'static dyn StorageBackend> = Some(Box::leak(
+                storage::create_storage(&storage_type, *storage_url.clone())?,
+            ));
+
+            slsa::import::import_build_provenance(
+                from.to_build_system(),
+                metadata,
+                key,
+                hash_alg.to_cose_algorithm(),
+                encoding,
+                output,
+                print,
+                storage,
+            )
+        }
+        SlsaCommands::Run {
+            inputs,
+            products,
+            env,
+            key,
+            hash_alg,
+            print,
+            encoding,
+            output,
+            storage_type,
+            storage_url,
+            command,
+        } => {
+            let storage: Option<&'static dyn StorageBackend> = Some(Box::leak(
+                storage::create_storage(&storage_type, *storage_url.clone())?,
+            ));
+
+            slsa::run::run_build(slsa::run::RunConfig {
+                command,
+                inputs,
+                products,
+                env,
+                key_path: key,
+                hash_alg: hash_alg.to_cose_algorithm(),
+                output_encoding: encoding,
+                output_path: output,
+                print,
+                storage,
+            })
+        }
+    }
+}
+
+pub fn handle_attestation_command(cmd: AttestationCommands) -> Result<()> {
+    match cmd {
+        AttestationCommands::Create {
+            statement,
+            keys,
+            output,
+            name,
+            storage_type,
+            storage_url,
+        } => {
+            let storage: Option<&'static dyn StorageBackend> = Some(Box::leak(
+                storage::create_storage(&storage_type, *storage_url.clone())?,
+            ));
+
+            manifest::attestation::create_attestation(manifest::attestation::AttestationConfig {
+                subjects: statement.subjects,
+                subject_manifests: statement.subject_manifests,
+                predicate_type: statement.predicate_type,
+                predicate_path: statement.predicate,
+                name,
+                key_path: keys.key,
+                pq_key_path: keys.pq_key,
+                hash_alg: keys.hash_alg.to_cose_algorithm(),
+                output_encoding: output.encoding,
+                output_path: output.output,
+                print: output.print,
+                storage,
+            })
+        }
+        AttestationCommands::Sign {
+            input,
+            keys,
+            output,
+            name,
+            storage_type,
+            storage_url,
+        } => {
+            let key = keys.key.ok_or_else(|| {
+                Error::Validation("--key is required to sign an attestation".to_string())
+            })?;
+            let storage: Option<&'static dyn StorageBackend> = Some(Box::leak(
+                storage::create_storage(&storage_type, *storage_url.clone())?,
+            ));
+
+            let envelope = manifest::attestation::sign_attestation(
+                &input,
+                key,
+                keys.pq_key.as_deref(),
+                keys.hash_alg.to_cose_algorithm(),
+            )?;
+            let name = name.unwrap_or_else(|| {
+                input
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().to_string())
+                    .unwrap_or_else(|| input.display().to_string())
+            });
+            manifest::attestation::emit_envelope(
+                envelope,
+                &name,
+                &output.encoding,
+                output.output.as_deref(),
+                output.print,
+                storage,
+            )
+        }
+        AttestationCommands::Verify {
+            id,
+            envelope,
+            bundle,
+            public_key,
+            artifact_root,
+            storage_type,
+            storage_url,
+        } => {
+            let storage = storage::create_storage(&storage_type, *storage_url.clone())?;
+
+            let public_key = PKey::public_key_from_pem(&std::fs::read(&public_key)?)
+                .map_err(|e| Error::Validation(format!("Invalid public key: {e}")))?;
+
+            // Envelope files are verified against the storage only for manifest subjects
+            if let Some(bundle) = bundle {
+                let results = manifest::attestation::verify_bundle(
+                    &bundle,
+                    &public_key,
+                    artifact_root.as_deref(),
+                    Some(storage.as_ref()),
+                )?;
+                print!("{}", manifest::attestation::render_bundle_results(&results));
+
+                let failed = results.iter().filter(|r| r.error.is_some()).count();
+                if failed > 0 {
+                    return Err(Error::Validation(format!(
+                        "{failed} of {} statements in the bundle failed verification",
+                        results.len()
+                    )));
+                }
+                return Ok(());
+            }
+
+            if let Some(envelope) = envelope {
+                let envelope = manifest::attestation::read_envelope(&envelope)?;
+                return manifest::attestation::verify_attestation(
+                    &envelope,
+                    &public_key,
+                    artifact_root.as_deref(),
+                    Some(storage.as_ref()),
+                );
+            }
+
+            let id = id.ok_or_else(|| Error::Validation("--id is required".to_string()))?;
+            manifest::attestation::verify_envelope(
+                &id,
+                storage.as_ref(),
+                &public_key,
+                artifact_root.as_deref(),
+            )
+        }
+        AttestationCommands::List {
+            format,
+            storage_type,
+            storage_url,
+        } => {
+            let storage = storage::create_storage(&storage_type, *storage_url.clone())?;
+
+            let json = match format.to_lowercase().as_str() {
+                "text" => false,
+                "json" => true,
+                _ => {
+                    return Err(Error::Validation(format!(
+                        "Invalid output format '{format}'. Valid options are: text, json"
+                    )));
+                }
+            };
+            manifest::attestation::list_envelopes(storage.as_ref(), json)
+        }
+        AttestationCommands::Attach {
+            manifest_id,
+            id,
+            key,
+            hash_alg,
+            storage_type,
+            storage_url,
+        } => {
+            let storage = storage::create_storage(&storage_type, *storage_url.clone())?;
+
+            let new_id = manifest::attestation::attach_attestation(
+                &manifest_id,
+                &id,
+                storage.as_ref(),
+                key,
+                hash_alg.to_cose_algorithm(),
+            )?;
+            println!("Attestation {id} attached; manifest stored with ID: {new_id}");
+            Ok(())
+        }
+        AttestationCommands::Export {
+            id,
+            bundle,
+            encoding,
+            output,
+            storage_type,
+            storage_url,
+        } => {
+            let storage = storage::create_storage(&storage_type, *storage_url.clone())?;
+
+            if !bundle && id.len() > 1 {
+                return Err(Error::Validation(
+                    "Exporting several envelopes needs --bundle".to_string(),
+                ));
+            }
+            let envelopes = id
+                .iter()
+                .map(|id| storage.retrieve_envelope(id).map(|record| record.envelope))
+                .collect::<Result<Vec<_>>>()?;
+
+            if bundle {
+                manifest::attestation::write_bundle(&envelopes, output.as_deref())
+            } else {
+                manifest::output::write_encoded(&envelopes[0], &encoding, output.as_deref())
+            }
+        }
+        AttestationCommands::Detach {
+            envelope,
+            payload_url,
+            payload_output,
+            hash_alg,
+            encoding,
+            output,
+        } => manifest::attestation::detach_payload(
+            &envelope,
+            payload_url,
+            &payload_output,
+            &hash_alg.to_cose_algorithm(),
+            &encoding,
+            &output,
+        ),
+        AttestationCommands::SignDetached {
+            payload,
+            payload_url,
+            payload_type,
+            key,
+            hash_alg,
+            encoding,
+            output,
+        } => manifest::attestation::sign_detached_payload(
+            &payload,
+            payload_type,
+            payload_url,
+            &key,
+            &hash_alg.to_cose_algorithm(),
+            &encoding,
+            &output,
+        ),
+    }
+}
+
+pub fn handle_doctor_command(args: DoctorArgs) -> Result<()> {
+    doctor::run_doctor(&DoctorOptions {
+        storage_type: args.storage_type,
+        storage_url: args.storage_url,
+        key_path: args.key,
+    })
+}
+
+pub fn handle_key_command(cmd: KeyCommands) -> Result<()> {
+    match cmd {
+        KeyCommands::Usage {
+            public_key,
+            format,
+            storage_type,
+            storage_url,
+        } => {
+            let storage = storage::create_storage(&storage_type, *storage_url.clone())?;
+            let public_key = PKey::public_key_from_pem(&std::fs::read(&public_key)?)
+                .map_err(|e| Error::Signing(format!("Invalid public key: {e}")))?;
+
+            let report = manifest::key_usage::scan_key_usage(storage.as_ref(), &public_key)?;
+            match format.as_str() {
+                "json" => println!("{}", serde_json::to_string_pretty(&report)?),
+                "table" => {
+                    println!("Key {}", report.fingerprint);
+                    for usage in &report.usages {
+                        println!(
+                            "{}  {:<18}  {}  {}{}",
+                            usage.used_at,
+                            usage.key_use,
+                            usage.id,
+                            usage.name,
+                            usage
+                                .detail
+                                .as_ref()
+                                .map(|detail| format!(" ({detail})"))
+                                .unwrap_or_default()
+                        );
+                    }
+                    for (id, reason) in &report.unchecked {
+                        println!(
+                            "{} {id} could not be checked: {reason}",
+                            color::cross_mark()
+                        );
+                    }
+                    match (report.first_used(), report.last_used()) {
+                        (Some(first), Some(last)) => println!(
+                            "{} use(s) in {} manifest(s) and {} envelope(s), first {first}, last {last}",
+                            report.usages.len(),
+                            report.manifests_scanned,
+                            report.envelopes_scanned
+                        ),
+                        _ => println!(
+                            "Not used in {} manifest(s) and {} envelope(s)",
+                            report.manifests_scanned, report.envelopes_scanned
+                        ),
+                    }
+                }
+                _ => return Err(Error::Validation(format!("Invalid format: {format}"))),
+            }
+            Ok(())
+        }
+        KeyCommands::Revoke {
+            public_key,
+            fingerprint,
+            revoked_at,
+            reason,
+            authority_key,
+            issuer,
+            list,
+        } => {
+            use crate::manifest::revocation::{self, RevokedKey, TrustStore};
+
+            let fingerprint = match (public_key, fingerprint) {
+                (Some(path), _) => {
+                    let public_key = PKey::public_key_from_pem(&std::fs::read(&path)?)
+                        .map_err(|e| Error::Signing(format!("Invalid public key: {e}")))?;
+                    manifest::signer::key_fingerprint(&public_key)?
+                }
+                (None, Some(fingerprint)) => fingerprint.to_lowercase(),
+                (None, None) => {
+                    return Err(Error::Validation(
+                        "Revoking a key requires --public-key or --fingerprint".to_string(),
+                    ));
+                }
+            };
+            let list = match list {
+                Some(list) => list,
+                None => TrustStore::load_default()?.revocation_list.ok_or_else(|| {
+                    Error::Validation(format!(
+                        "No revocation_list in the trust store {}, pass --list",
+                        revocation::trust_store_file().display()
+                    ))
+                })?,
+            };
+            let revoked = RevokedKey {
+                fingerprint,
+                revoked_at: revoked_at
+                    .as_deref()
+                    .map(manifest::timestamp::parse_created_at)
+                    .transpose()?
+                    .unwrap_or_else(time::OffsetDateTime::now_utc),
+                reason,
+            };
+
+            let authority = crate::signing::load_private_key(&authority_key)?;
+            let updated = revocation::revoke_key(&list, &authority, issuer, revoked.clone())?;
+            println!(
+                "{} Revoked key {} from {}",
+                color::check_mark(),
+                revoked.fingerprint,
+                manifest::timestamp::to_rfc3339_utc(revoked.revoked_at)
+            );
+            println!(
+                "{} revoked key(s) in {}",
+                updated.revoked.len(),
+                list.display()
+            );
+            Ok(())
+        }
+    }
+}
+
+pub fn handle_queue_command(cmd: QueueCommands) -> Result<()> {
+    let queue = crate::storage::queue::OfflineQueue::open_default()?;
+    match cmd {
+        QueueCommands::List => {
+            let entries = queue.entries()?;
+            if entries.is_empty() {
+                println!("No manifests queued in {}", queue.dir().display());
+            }
+            for entry in entries {
+                println!(
+                    "{:>4}  {}  {}  queued {}",
+                    entry.seq, entry.manifest_id, entry.target, entry.queued_at
+                );
+            }
+            Ok(())
+        }
+        QueueCommands::Flush {
+            all_targets,
+            storage_type,
+            storage_url,
+        } => {
+            let storage = storage::create_storage(&storage_type, *storage_url.clone())?;
+
+            let report = queue.flush(storage.as_ref(), all_targets)?;
+            for (queued_id, id) in &report.stored {
+                println!("{} {queued_id} stored as {id}", color::check_mark());
+            }
+            if report.other_targets > 0 {
+                println!(
+                    "{} manifest(s) queued for other storage were left queued (use --all-targets to store them here)",
+                    report.other_targets
+                );
+            }
+            match report.failed {
+                Some((queued_id, e)) => Err(Error::Storage(format!(
+                    "Stopped at {queued_id}, which stays queued with the manifests after it: {e}"
+                ))),
+                None => {
+                    println!("Stored {} queued manifest(s)", report.stored.len());
+                    Ok(())
+                }
+            }
+        }
+    }
+}
+
+pub fn handle_sync_command(args: SyncArgs) -> Result<()> {
+    let storage = storage::create_storage(&args.storage_type, args.storage_url.clone())?;
+    let registry = crate::registry::connect(
+        args.target.to_target(),
+        args.registry_url.clone(),
+        args.namespace.clone(),
+    )?;
+
+    if args.gaps {
+        let report = crate::registry::find_gaps(registry.as_ref(), storage.as_ref())?;
+        match args.format.as_str() {
+            "json" => println!("{}", serde_json::to_string_pretty(&report)?),
+            "text" if report.is_empty() => {
+                println!("Every registry entry has a stored manifest")
+            }
+            "text" => print!("{}", report.render()),
+            format => {
+                return Err(Error::Validation(format!(
+                    "Unsupported format {format}, expected text or json"
+                )));
+            }
+        }
+        return Ok(());
+    }
+
+    let results = crate::registry::push_summaries(
+        registry.as_ref(),
+        storage.as_ref(),
+        &args.ids,
+        args.registry_entry.as_deref(),
+        !args.skip_verify,
+        args.artifact_root.as_deref(),
+    )?;
+    let mut failed = 0;
+    for result in &results {
+        match &result.outcome {
+            Ok(()) => println!(
+                "{} {} -> {} ({})",
+                color::check_mark(),
+                result.manifest_id,
+                result.entry,
+                result.verification
+            ),
+            Err(e) => {
+                failed += 1;
+                println!(
+                    "{} {} -> {}: {e}",
+                    color::cross_mark(),
+                    result.manifest_id,
+                    result.entry
+                );
+            }
+        }
+    }
+    println!(
+        "Pushed {} of {} manifest summaries",
+        results.len() - failed,
+        results.len()
+    );
+    if failed > 0 {
+        return Err(Error::Storage(format!(
+            "{failed} manifest summaries could not be pushed"
+        )));
+    }
+    Ok(())
+}
+
+pub fn handle_rekor_command(cmd: RekorCommands) -> Result<()> {
+    match cmd {
+        RekorCommands::Find {
+            hash,
+            file,
+            format,
+            rekor_url,
+        } => {
+            let digest = match (hash, file) {
+                (Some(hash), _) => hash,
+                (None, Some(file)) => format!(
+                    "sha256:{}",
+                    crate::hash::calculate_file_hash_with_algorithm(
+                        &file,
+                        &atlas_c2pa_lib::cose::HashAlgorithm::Sha256
+                    )?
+                ),
+                (None, None) => {
+                    return Err(Error::Validation(
+                        "Either --hash or --file is required".to_string(),
+                    ));
+                }
+            };
+
+            let results = RekorStorage::new_with_url(rekor_url)?.find_entries(&digest)?;
+
+            match format.to_lowercase().as_str() {
+                "json" => println!("{}", serde_json::to_string_pretty(&results)?),
+                "text" => {
+                    if results.is_empty() {
+                        println!("No Rekor entries found for {digest}");
+                    } else {
+                        println!("Found {} Rekor entries for {digest}:", results.len());
+                    }
+                    for result in &results {
+                        let integrated =
+                            time::OffsetDateTime::from_unix_timestamp(result.integrated_time)
+                                .ok()
+                                .and_then(|t| {
+                                    t.format(&time::format_description::well_known::Rfc3339)
+                                        .ok()
+                                })
+                                .unwrap_or_else(|| result.integrated_time.to_string());
+                        println!("\n  UUID: {}", result.uuid);
+                        println!("  Log index: {}", result.log_index);
+                        println!("  Integrated: {integrated}");
+                        println!("  Kind: {}", result.kind);
+                        if result.signers.is_empty() {
+                            println!("  Signed by: unknown");
+                        }
+                        for signer in &result.signers {
+                            println!("  Signed by: {signer}");
+                        }
+                    }
+                }
+                _ => {
+                    return Err(Error::Validation(format!(
+                        "Invalid output format '{format}'. Valid options are: text, json"
+                    )));
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+pub fn handle_store_command(cmd: StoreCommands) -> Result<()> {
+    match cmd {
+        StoreCommands::Export {
+            output,
+            since,
+            checkpoint,
+            storage_type,
+            storage_url,
+        } => {
+            let storage = storage::create_storage(&storage_type, *storage_url.clone())?;
+
+            let since = since
+                .as_deref()
+                .map(crate::storage::export::ExportSince::parse)
+                .transpose()?;
+            let (summary, new_checkpoint) = crate::storage::export::export_store_since(
+                storage.as_ref(),
+                &output,
+                since.as_ref(),
+            )?;
+            println!(
+                "Exported {} manifest(s) and {} envelope(s) to {}",
+                summary.manifests,
+                summary.envelopes,
+                output.display()
+            );
+            if since.is_some() {
+                println!("Left out {} unchanged entries", summary.skipped);
+            }
+            if let Some(path) = checkpoint {
+                new_checkpoint.write(&path)?;
+                println!("Checkpoint written to {}", path.display());
+            }
+            Ok(())
+        }
+        StoreCommands::Import {
+            input,
+            skip_existing,
+            dedupe,
+            storage_type,
+            storage_url,
+        } => {
+            let storage = storage::create_storage(&storage_type, *storage_url.clone())?;
+
+            let on_existing = if dedupe {
+                crate::storage::export::OnExisting::Dedupe
+            } else if skip_existing {
+                crate::storage::export::OnExisting::Skip
+            } else {
+                crate::storage::export::OnExisting::Store
+            };
+            let summary =
+                crate::storage::export::import_store(storage.as_ref(), &input, on_existing)?;
+            println!(
+                "Imported {} manifest(s) and {} envelope(s) from {}",
+                summary.manifests,
+                summary.envelopes,
+                input.display()
+            );
+            if summary.skipped > 0 {
+                let existing = if dedupe { "unchanged" } else { "existing" };
+                println!("Skipped {} {existing} entries", summary.skipped);
+            }
+            Ok(())
+        }
+        StoreCommands::ApplyRetention {
+            policy,
+            archive_dir,
+            key,
+            issuer,
+            dry_run,
+            storage_type,
+            storage_url,
+        } => {
+            use crate::storage::retention::{self, RetentionPolicy};
+
+            let storage = storage::create_storage(&storage_type, *storage_url.clone())?;
+
+            let policy = RetentionPolicy::load(&policy.unwrap_or_else(retention::retention_file))?;
+            let archive_dir = archive_dir
+                .or_else(|| policy.archive_dir.clone())
+                .unwrap_or_else(retention::default_archive_dir);
+            let plan = retention::plan_retention(
+                storage.as_ref(),
+                &policy,
+                time::OffsetDateTime::now_utc(),
+            )?;
+
+            for (manifest, by) in &plan.held {
+                println!(
+                    "held     {} ({}): expired {}, still referenced by {}",
+                    manifest.id,
+                    manifest.name,
+                    crate::manifest::timestamp::to_rfc3339_utc(manifest.expired_at),
+                    by.join(", ")
+                );
+            }
+            if dry_run {
+                for manifest in &plan.expired {
+                    println!(
+                        "{:<8} {} ({} {}, {})",
+                        manifest.rule.action.to_string(),
+                        manifest.id,
+                        manifest.kind,
+                        manifest.name,
+                        manifest.rule
+                    );
+                }
+                println!(
+                    "{} manifest(s) would be removed, {} held",
+                    plan.expired.len(),
+                    plan.held.len()
+                );
+                return Ok(());
+            }
+
+            let key_path = key.ok_or_else(|| {
+                Error::Validation("Signing tombstones requires --key".to_string())
+            })?;
+            let private_key = crate::signing::load_private_key(&key_path)?;
+            let removed = retention::apply_retention(
+                storage.as_ref(),
+                &plan,
+                &archive_dir,
+                &private_key,
+                issuer,
+                &atlas_c2pa_lib::cose::HashAlgorithm::Sha384,
+            )?;
+            for manifest in &removed {
+                match &manifest.archive {
+                    Some(archive) => println!(
+                        "{} archived {} to {}",
+                        color::check_mark(),
+                        manifest.id,
+                        archive.display()
+                    ),
+                    None => println!("{} deleted {}", color::check_mark(), manifest.id),
+                }
+            }
+            println!(
+                "Removed {} manifest(s), {} held; tombstones written to {}",
+                removed.len(),
+                plan.held.len(),
+                archive_dir.join("tombstones").display()
+            );
+            Ok(())
+        }
+        StoreCommands::Plugins { format } => {
+            let plugins = crate::storage::plugin::discover();
+            match format.as_str() {
+                "json" => println!("{}", serde_json::to_string_pretty(&plugins)?),
+                "table" if plugins.is_empty() => println!("No storage plugins found"),
+                "table" => {
+                    for plugin in plugins {
+                        println!(
+                            "{PLUGIN_STORAGE_PREFIX}{:<24} {}",
+                            plugin.name,
+                            plugin.path.display()
+                        );
+                    }
+                }
+                _ => return Err(Error::Validation(format!("Invalid format: {format}"))),
+            }
+            Ok(())
+        }
+    }
+}
+
+pub fn handle_import_command(cmd: ImportCommands) -> Result<()> {
+    match cmd {
+        ImportCommands::Legacy {
+            manifest_csv,
+            default_type,
+            backdate,
+            jobs,
+            author_org,
+            path_base,
+            key,
+            hash_alg,
+            storage_type,
+            storage_url,
+        } => {
+            let storage: &'static dyn StorageBackend = Box::leak(storage::create_storage(
+                &storage_type,
+                *storage_url.clone(),
+            )?);
+
+            let options = manifest::legacy::ImportOptions {
+                default_kind: manifest::legacy::parse_asset_kind(&default_type)?,
+                backdate,
+                jobs,
+                author_org,
+                path_base,
+                key_path: key,
+                hash_alg: hash_alg.to_cose_algorithm(),
+            };
+            let rows = manifest::legacy::read_inventory(&manifest_csv)?;
+            let results =
+                manifest::legacy::import_inventory(&manifest_csv, &rows, &options, storage)?;
+
+            let mut failed = 0;
+            for result in &results {
+                match &result.id {
+                    Ok(id) => println!("{} {}: {id}", color::check_mark(), result.name),
+                    Err(e) => {
+                        failed += 1;
+                        println!(
+                            "{} {} (line {}): {e}",
+                            color::cross_mark(),
+                            result.name,
+                            result.line
+                        );
+                    }
+                }
+            }
+            println!(
+                "Imported {} of {} artifact(s) from {}",
+                results.len() - failed,
+                results.len(),
+                manifest_csv.display()
+            );
+            if failed > 0 {
+                return Err(Error::Validation(format!(
+                    "{failed} inventory row(s) failed to import"
+                )));
+            }
+            Ok(())
+        }
+    }
+}
+
+// Writes the badge and the signed verification record of a verified manifest, if asked for
+fn write_verification_outputs(
+    id: &str,
+    storage: &dyn StorageBackend,
+    outputs: &VerificationOutputArgs,
+) -> Result<()> {
+    if let Some(path) = &outputs.badge {
+        manifest::badge::write_badge(path)?;
+        println!("Verification badge written to {}", path.display());
+    }
+    if let (Some(path), Some(key)) = (&outputs.verification_record, &outputs.record_key) {
+        let manifest = storage.retrieve_manifest(id)?;
+        let record = manifest::badge::issue_verification_record(
+            &manifest,
+            key,
+            outputs.verifier.clone(),
+            &outputs.policy_id,
+            &atlas_c2pa_lib::cose::HashAlgorithm::Sha384,
+        )?;
+        std::fs::write(path, format!("{record}\n"))?;
+        println!("Verification record written to {}", path.display());
+    }
+    Ok(())
+}
+
+// Verifies a stored manifest, in quorum mode when a quorum option is given
+fn verify_stored_manifest(
+    id: &str,
+    storage: &dyn StorageBackend,
+    artifact_root: Option<&std::path::Path>,
+    selection: &IngredientSelectionArgs,
+    quorum: &QuorumArgs,
+) -> Result<()> {
+    let Some(rule) = quorum.to_rule() else {
+        return manifest::common::verify_manifest_with_selection(
+            id,
+            storage,
+            artifact_root,
+            &selection.to_selection(),
+        );
+    };
+
+    let report = manifest::common::verify_manifest_with_quorum(
+        id,
+        storage,
+        artifact_root,
+        &selection.to_selection(),
+        &rule,
+    )?;
+    print!("{}", report.render());
+    if let Some(path) = &quorum.coverage_report {
+        std::fs::write(path, serde_json::to_string_pretty(&report)?)?;
+        println!("Coverage report written to {}", path.display());
+    }
+    report.into_result()
+}
@@ -1,3 +1,4 @@
+pub mod color;
 pub mod commands;
 pub mod handlers;
 use crate::error::Error;
@@ -16,10 +17,14 @@ pub use handlers::{
 pub const CLI_VERSION: &str = env!("CARGO_PKG_VERSION");
 pub const CLI_NAME: &str = "atlas-cli";
 
+/// Formats an error for the terminal, prefixed with its stable error code
 pub fn format_error(error: &Error) -> String {
-    match error {
+    let message = match error {
         Error::Io(err) => format!("IO error: {err}"),
-        Error::Storage(msg) => format!("Storage error: {msg}"),
+        Error::Storage(msg)
+        | Error::NotFound(msg)
+        | Error::StorageUnavailable(msg)
+        | Error::Unauthorized(msg) => format!("Storage error: {msg}"),
         Error::Validation(msg) => format!("Validation error: {msg}"),
         Error::Manifest(msg) => format!("Manifest error: {msg}"),
         Error::Signing(msg) => format!("Signing error: {msg}"),
@@ -28,7 +33,8 @@ pub fn format_error(error: &Error) -> String {
         Error::HexDecode(err) => format!("Hex decode error: {err}"),
         Error::CCAttestationError(msg) => format!("CC attestation error: {msg}"),
         Error::Json(err) => format!("JSON error: {err}"),
-    }
+    };
+    format!("[{}] {message}", error.code())
 }
 
 /// Helper function to print validation warnings to the user
@@ -0,0 +1,457 @@
+//! Environment diagnostics for `atlas-cli doctor`.
+//!
+//! Each check looks at one thing the other commands depend on (the OpenSSL library, the TDX
+//! guest device, the storage service, the signing key and the system clock) and, when it
+//! finds a problem, says how to fix it. Checks never change anything.
+
+use crate::cli::color;
+use crate::error::{Error, Result};
+use crate::http;
+use crate::signing;
+use crate::signing::piv;
+use crate::storage::plugin::PLUGIN_STORAGE_PREFIX;
+use crate::storage::traits::StorageBackend;
+use crate::storage::{FilesystemStorage, PluginStorage, S3Storage};
+use crate::utils::file_url_to_path;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use time::OffsetDateTime;
+
+/// Device of the TDX guest driver, used for attestation with the `with-tdx` feature
+pub const TDX_GUEST_DEVICE: &str = "/dev/tdx_guest";
+
+/// Clock skew from the storage service above which timestamps are reported as unreliable
+pub const MAX_CLOCK_SKEW: Duration = Duration::from_secs(60);
+
+/// Clock skew from the storage service above which the clock check fails
+pub const MAX_CLOCK_SKEW_FAILURE: Duration = Duration::from_secs(300);
+
+const STORAGE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Outcome of a check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Ok,
+    /// Works, but something is likely to cause trouble
+    Warning,
+    Failed,
+    /// Not applicable to this build or these options
+    Skipped,
+}
+
+/// Result of one diagnostic check
+#[derive(Debug, Clone)]
+pub struct CheckResult {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+    /// How to fix the problem, for warnings and failures
+    pub fix: Option<String>,
+}
+
+impl CheckResult {
+    fn new(name: &'static str, status: CheckStatus, detail: impl Into<String>) -> Self {
+        CheckResult {
+            name,
+            status,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn with_fix(mut self, fix: impl Into<String>) -> Self {
+        self.fix = Some(fix.into());
+        self
+    }
+}
+
+impl fmt::Display for CheckResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mark = match self.status {
+            CheckStatus::Ok => color::check_mark(),
+            CheckStatus::Warning => color::warning_sign(),
+            CheckStatus::Failed => color::cross_mark(),
+            CheckStatus::Skipped => "-".to_string(),
+        };
+        write!(f, "{mark} {}: {}", self.name, self.detail)?;
+        if let Some(fix) = &self.fix {
+            write!(f, "\n    Fix: {fix}")?;
+        }
+        Ok(())
+    }
+}
+
+/// What to check
+#[derive(Debug, Clone)]
+pub struct DoctorOptions {
+    pub storage_type: String,
+    pub storage_url: String,
+    /// Signing key to check, in any form accepted by `--key`
+    pub key_path: Option<PathBuf>,
+}
+
+/// Runs all checks
+pub fn run_checks(options: &DoctorOptions) -> Vec<CheckResult> {
+    let (storage, server_time) = check_storage(&options.storage_type, &options.storage_url);
+    vec![
+        check_openssl(),
+        check_tee_device(),
+        storage,
+        check_key(options.key_path.as_deref()),
+        check_clock(OffsetDateTime::now_utc(), server_time),
+    ]
+}
+
+/// Runs all checks and prints their results, failing if any check failed
+pub fn run_doctor(options: &DoctorOptions) -> Result<()> {
+    let results = run_checks(options);
+    for result in &results {
+        println!("{result}");
+    }
+
+    let failed = results
+        .iter()
+        .filter(|result| result.status == CheckStatus::Failed)
+        .count();
+    if failed > 0 {
+        return Err(Error::Validation(format!("{failed} check(s) failed")));
+    }
+    Ok(())
+}
+
+/// Checks the version of the OpenSSL library in use
+pub fn check_openssl() -> CheckResult {
+    const NAME: &str = "OpenSSL";
+
+    let version = openssl::version::version();
+    // 0xMNN00PP0L
+    let number = openssl::version::number();
+    let (major, minor) = (number >> 28, (number >> 20) & 0xff);
+
+    if cfg!(feature = "pq") && (major, minor) < (3, 5) {
+        return CheckResult::new(NAME, CheckStatus::Failed, version).with_fix(
+            "ML-DSA signatures (the pq feature) need OpenSSL 3.5 or newer; point OPENSSL_DIR \
+             at a newer installation and rebuild",
+        );
+    }
+    if major < 3 {
+        return CheckResult::new(NAME, CheckStatus::Warning, version)
+            .with_fix("FIPS mode needs the OpenSSL 3 FIPS provider; upgrade OpenSSL and rebuild");
+    }
+    CheckResult::new(NAME, CheckStatus::Ok, version)
+}
+
+/// Checks that the TDX guest device is present when TDX attestation is built in
+pub fn check_tee_device() -> CheckResult {
+    const NAME: &str = "TEE device";
+
+    if !cfg!(feature = "with-tdx") {
+        return CheckResult::new(
+            NAME,
+            CheckStatus::Skipped,
+            "built without the with-tdx feature, attestation uses the mock provider",
+        );
+    }
+
+    let device = Path::new(TDX_GUEST_DEVICE);
+    if !device.exists() {
+        return CheckResult::new(
+            NAME,
+            CheckStatus::Failed,
+            format!("{TDX_GUEST_DEVICE} not found"),
+        )
+        .with_fix(
+            "run inside an Intel TDX guest VM with the tdx_guest kernel module loaded \
+             (`modprobe tdx_guest`)",
+        );
+    }
+    match std::fs::File::open(device) {
+        Ok(_) => CheckResult::new(
+            NAME,
+            CheckStatus::Ok,
+            format!("{TDX_GUEST_DEVICE} is readable"),
+        ),
+        Err(e) => CheckResult::new(
+            NAME,
+            CheckStatus::Failed,
+            format!("{TDX_GUEST_DEVICE} cannot be opened: {e}"),
+        )
+        .with_fix(format!(
+            "run as root or grant your user access to {TDX_GUEST_DEVICE}"
+        )),
+    }
+}
+
+/// Checks that the storage backend is reachable, returning the service's time if it sent one
+///
+/// HTTP backends get a single request, which any response answers; the local filesystem
+/// and S3 backends must list their manifests.
+pub fn check_storage(storage_type: &str, url: &str) -> (CheckResult, Option<OffsetDateTime>) {
+    const NAME: &str = "Storage";
+
+    let probe_url = match storage_type {
+        "database" | "local" => url.trim_end_matches('/').to_string(),
+        "rekor" => format!("{}/api/v1/log", url.trim_end_matches('/')),
+        "local-fs" | "filesystem" => {
+            let path = file_url_to_path(url).unwrap_or_else(|| PathBuf::from(url));
+            // FilesystemStorage creates missing directories, which a check must not do
+            if !path.exists() {
+                let check = CheckResult::new(
+                    NAME,
+                    CheckStatus::Warning,
+                    format!("local-fs directory {} does not exist", path.display()),
+                )
+                .with_fix("it is created when the first manifest is stored; check --storage-url");
+                return (check, None);
+            }
+            let result = FilesystemStorage::new(url).and_then(|storage| storage.list_manifests());
+            let check = match result {
+                Ok(manifests) => CheckResult::new(
+                    NAME,
+                    CheckStatus::Ok,
+                    format!("local-fs at {url} holds {} manifest(s)", manifests.len()),
+                ),
+                Err(e) => {
+                    CheckResult::new(NAME, CheckStatus::Failed, format!("local-fs at {url}: {e}"))
+                        .with_fix("check that the directory exists and is readable and writable")
+                }
+            };
+            return (check, None);
+        }
+        "s3" => {
+            let result = S3Storage::new(url).and_then(|storage| storage.list_manifests());
+            let check = match result {
+                Ok(manifests) => CheckResult::new(
+                    NAME,
+                    CheckStatus::Ok,
+                    format!("s3 at {url} holds {} manifest(s)", manifests.len()),
+                ),
+                Err(e) => CheckResult::new(NAME, CheckStatus::Failed, format!("s3 at {url}: {e}"))
+                    .with_fix(
+                        "check the bucket, AWS_ENDPOINT_URL and the AWS_ACCESS_KEY_ID and \
+                         AWS_SECRET_ACCESS_KEY credentials",
+                    ),
+            };
+            return (check, None);
+        }
+        kind if kind.starts_with(PLUGIN_STORAGE_PREFIX) => {
+            let result = PluginStorage::new(kind, url).and_then(|storage| storage.list_manifests());
+            let check = match result {
+                Ok(manifests) => CheckResult::new(
+                    NAME,
+                    CheckStatus::Ok,
+                    format!("{kind} at {url} holds {} manifest(s)", manifests.len()),
+                ),
+                Err(e) => CheckResult::new(NAME, CheckStatus::Failed, format!("{kind}: {e}"))
+                    .with_fix("run atlas-cli store plugins to list the plugins that were found"),
+            };
+            return (check, None);
+        }
+        _ => {
+            let check = CheckResult::new(
+                NAME,
+                CheckStatus::Failed,
+                format!("invalid storage type '{storage_type}'"),
+            )
+            .with_fix(
+                "use --storage-type with one of: database, rekor, local-fs, s3, plugin:<name>",
+            );
+            return (check, None);
+        }
+    };
+
+    let response = http::client_builder()
+        .timeout(STORAGE_TIMEOUT)
+        .build()
+        .and_then(|client| client.get(&probe_url).send());
+    let response = match response {
+        Ok(response) => response,
+        Err(e) => {
+            let check = CheckResult::new(
+                NAME,
+                CheckStatus::Failed,
+                format!("{storage_type} at {url} is unreachable: {e}"),
+            )
+            .with_fix(
+                "check that the service is running and that --storage-url, proxies and \
+                 firewalls allow the connection",
+            );
+            return (check, None);
+        }
+    };
+
+    let server_time = response
+        .headers()
+        .get(reqwest::header::DATE)
+        .and_then(|date| date.to_str().ok())
+        .and_then(parse_http_date);
+    let status = response.status();
+    let check = if status.is_server_error() {
+        CheckResult::new(
+            NAME,
+            CheckStatus::Warning,
+            format!("{storage_type} at {url} answered with {status}"),
+        )
+        .with_fix("the service is reachable but failing; check its logs")
+    } else {
+        CheckResult::new(
+            NAME,
+            CheckStatus::Ok,
+            format!("{storage_type} at {url} is reachable"),
+        )
+    };
+    (check, server_time)
+}
+
+/// Checks that the signing key can be loaded and used
+pub fn check_key(key_path: Option<&Path>) -> CheckResult {
+    const NAME: &str = "Signing key";
+
+    let Some(key_path) = key_path else {
+        return CheckResult::new(NAME, CheckStatus::Skipped, "no --key given");
+    };
+
+    if let Some(slot) = piv::slot_from_key_spec(key_path) {
+        return match slot.and_then(|slot| Ok((slot, piv::read_certificate(slot)?))) {
+            Ok((slot, certificate)) => CheckResult::new(
+                NAME,
+                CheckStatus::Ok,
+                format!(
+                    "PIV slot {slot} holds a certificate for {:?}",
+                    certificate.subject_name()
+                ),
+            ),
+            Err(e) => CheckResult::new(NAME, CheckStatus::Failed, e.to_string())
+                .with_fix("insert the smartcard and check that yubico-piv-tool can read the slot"),
+        };
+    }
+
+    let key = match signing::load_private_key(key_path) {
+        Ok(key) => key,
+        Err(e) => {
+            return CheckResult::new(
+                NAME,
+                CheckStatus::Failed,
+                format!("{}: {e}", key_path.display()),
+            )
+            .with_fix("pass the path of a readable PEM or DER private key");
+        }
+    };
+    let pkey = key.as_pkey();
+    let description = format!("{:?} key, {} bits", pkey.id(), pkey.bits());
+
+    if signing::fips::is_enabled()
+        && let Err(e) = signing::fips::check_key(pkey)
+    {
+        return CheckResult::new(NAME, CheckStatus::Failed, e.to_string())
+            .with_fix("use an RSA key of at least 2048 bits or a P-256, P-384 or P-521 key");
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        if let Ok(metadata) = std::fs::metadata(key_path)
+            && metadata.permissions().mode() & 0o077 != 0
+        {
+            return CheckResult::new(
+                NAME,
+                CheckStatus::Warning,
+                format!("{description}, readable by other users"),
+            )
+            .with_fix(format!("chmod 600 {}", key_path.display()));
+        }
+    }
+
+    CheckResult::new(NAME, CheckStatus::Ok, description)
+}
+
+/// Checks the system clock against the time reported by the storage service
+///
+/// Without a reference time, only checks that the clock is not obviously unset.
+pub fn check_clock(now: OffsetDateTime, server_time: Option<OffsetDateTime>) -> CheckResult {
+    const NAME: &str = "Clock";
+
+    // Clocks without a battery or network time start at the epoch
+    if now.year() < 2024 {
+        return CheckResult::new(NAME, CheckStatus::Failed, format!("system time is {now}"))
+            .with_fix("set the system clock, for example by enabling NTP");
+    }
+
+    let Some(server_time) = server_time else {
+        return CheckResult::new(
+            NAME,
+            CheckStatus::Ok,
+            format!("system time is {now}, no reference time from storage"),
+        );
+    };
+
+    let skew = (now - server_time).unsigned_abs();
+    let detail = format!("{}s from the storage service", skew.as_secs());
+    let fix = "synchronize the clock with NTP; timestamps and validity windows depend on it";
+    if skew > MAX_CLOCK_SKEW_FAILURE {
+        CheckResult::new(NAME, CheckStatus::Failed, detail).with_fix(fix)
+    } else if skew > MAX_CLOCK_SKEW {
+        CheckResult::new(NAME, CheckStatus::Warning, detail).with_fix(fix)
+    } else {
+        CheckResult::new(NAME, CheckStatus::Ok, detail)
+    }
+}
+
+// HTTP dates are always in GMT, e.g. "Sun, 06 Nov 1994 08:49:37 GMT"
+fn parse_http_date(date: &str) -> Option<OffsetDateTime> {
+    const FORMAT: &[time::format_description::BorrowedFormatItem<'_>] = time::macros::format_description!(
+        "[weekday repr:short], [day] [month repr:short] [year] [hour]:[minute]:[second] GMT"
+    );
+
+    time::PrimitiveDateTime::parse(date, FORMAT)
+        .ok()
+        .map(|date| date.assume_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    #[test]
+    fn test_check_clock() {
+        let server = parse_http_date("Tue, 01 Oct 2024 12:00:00 GMT").unwrap();
+        assert_eq!(server, datetime!(2024-10-01 12:00:00 UTC));
+
+        let status = |now| check_clock(now, Some(server)).status;
+        assert_eq!(status(datetime!(2024-10-01 12:00:30 UTC)), CheckStatus::Ok);
+        assert_eq!(
+            status(datetime!(2024-10-01 11:58:00 UTC)),
+            CheckStatus::Warning
+        );
+        assert_eq!(
+            status(datetime!(2024-10-01 12:10:00 UTC)),
+            CheckStatus::Failed
+        );
+        assert_eq!(
+            check_clock(datetime!(1970-01-01 00:00:00 UTC), None).status,
+            CheckStatus::Failed
+        );
+    }
+
+    #[test]
+    fn test_check_key_and_storage() {
+        assert_eq!(check_key(None).status, CheckStatus::Skipped);
+        let missing = check_key(Some(Path::new("/nonexistent/key.pem")));
+        assert_eq!(missing.status, CheckStatus::Failed);
+        assert!(missing.fix.is_some());
+
+        let dir = tempfile::tempdir().unwrap();
+        let (storage, server_time) = check_storage("local-fs", dir.path().to_str().unwrap());
+        assert_eq!(storage.status, CheckStatus::Ok);
+        assert!(server_time.is_none());
+        assert_eq!(check_storage("s3", "").0.status, CheckStatus::Failed);
+
+        let missing = dir.path().join("missing");
+        let (storage, _) = check_storage("local-fs", missing.to_str().unwrap());
+        assert_eq!(storage.status, CheckStatus::Warning);
+        assert!(!missing.exists());
+    }
+}
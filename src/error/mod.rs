@@ -1,8 +1,8 @@
 mod types;
 
-pub use types::{Error, Result};
+pub use types::{Error, ErrorCategory, ErrorReport, Result};
 
-/// Format an error for display to the user
+/// Format an error for display to the user, prefixed with its stable error code
 ///
 /// # Examples
 ///
@@ -11,12 +11,30 @@ pub use types::{Error, Result};
 ///
 /// let error = Error::Validation("Invalid input".to_string());
 /// let formatted = format_error(&error);
-/// assert_eq!(formatted, "Validation error: Invalid input");
+/// assert_eq!(formatted, "[ATLAS-VAL-001] Validation error: Invalid input");
 ///
 /// let io_error = Error::Storage("Connection failed".to_string());
 /// let formatted = format_error(&io_error);
-/// assert_eq!(formatted, "Storage error: Connection failed");
+/// assert_eq!(formatted, "[ATLAS-STOR-001] Storage error: Connection failed");
 /// ```
 pub fn format_error(error: &Error) -> String {
-    error.to_string()
+    format!("[{}] {error}", error.code())
+}
+
+/// Format an error as a JSON object for programmatic consumers
+///
+/// # Examples
+///
+/// ```
+/// use atlas_cli::error::{Error, format_error_json};
+///
+/// let error = Error::StorageUnavailable("Connection refused".to_string());
+/// let report: serde_json::Value = serde_json::from_str(&format_error_json(&error)).unwrap();
+/// assert_eq!(report["code"], "ATLAS-STOR-003");
+/// assert_eq!(report["category"], "storage");
+/// assert_eq!(report["retryable"], true);
+/// ```
+pub fn format_error_json(error: &Error) -> String {
+    serde_json::to_string(&ErrorReport::from(error))
+        .unwrap_or_else(|_| format!("{{\"code\":\"{}\"}}", error.code()))
 }
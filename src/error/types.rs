@@ -1,3 +1,4 @@
+use serde::Serialize;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -8,6 +9,18 @@ pub enum Error {
     #[error("Storage error: {0}")]
     Storage(String),
 
+    /// The requested manifest or object does not exist
+    #[error("Storage error: {0}")]
+    NotFound(String),
+
+    /// The storage service could not be reached or is temporarily failing
+    #[error("Storage error: {0}")]
+    StorageUnavailable(String),
+
+    /// The storage service refused the credentials, or they lack the role an operation needs
+    #[error("Storage error: {0}")]
+    Unauthorized(String),
+
     #[error("Validation error: {0}")]
     Validation(String),
 
@@ -33,4 +46,159 @@ pub enum Error {
     Json(#[from] serde_json::Error),
 }
 
+/// Broad class of an error, the middle part of its code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ErrorCategory {
+    Io,
+    Storage,
+    Validation,
+    Manifest,
+    Signing,
+    Serialization,
+    Initialization,
+    Attestation,
+}
+
+impl Error {
+    /// Category of the error
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            Error::Io(_) => ErrorCategory::Io,
+            Error::Storage(_)
+            | Error::NotFound(_)
+            | Error::StorageUnavailable(_)
+            | Error::Unauthorized(_) => ErrorCategory::Storage,
+            Error::Validation(_) => ErrorCategory::Validation,
+            Error::Manifest(_) => ErrorCategory::Manifest,
+            Error::Signing(_) => ErrorCategory::Signing,
+            Error::Serialization(_) | Error::HexDecode(_) | Error::Json(_) => {
+                ErrorCategory::Serialization
+            }
+            Error::InitializationError(_) => ErrorCategory::Initialization,
+            Error::CCAttestationError(_) => ErrorCategory::Attestation,
+        }
+    }
+
+    /// Stable code identifying the kind of error, such as `ATLAS-STOR-003`
+    ///
+    /// Codes never change meaning once released; new kinds of errors get new numbers.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use atlas_cli::error::Error;
+    ///
+    /// assert_eq!(Error::Validation("bad input".to_string()).code(), "ATLAS-VAL-001");
+    /// assert_eq!(Error::NotFound("manifest m1".to_string()).code(), "ATLAS-STOR-002");
+    /// ```
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::Io(_) => "ATLAS-IO-001",
+            Error::Storage(_) => "ATLAS-STOR-001",
+            Error::NotFound(_) => "ATLAS-STOR-002",
+            Error::StorageUnavailable(_) => "ATLAS-STOR-003",
+            Error::Unauthorized(_) => "ATLAS-STOR-004",
+            Error::Validation(_) => "ATLAS-VAL-001",
+            Error::Manifest(_) => "ATLAS-MAN-001",
+            Error::Signing(_) => "ATLAS-SIGN-001",
+            Error::Serialization(_) => "ATLAS-SER-001",
+            Error::Json(_) => "ATLAS-SER-002",
+            Error::HexDecode(_) => "ATLAS-SER-003",
+            Error::InitializationError(_) => "ATLAS-INIT-001",
+            Error::CCAttestationError(_) => "ATLAS-ATT-001",
+        }
+    }
+
+    /// Whether the same operation may succeed if tried again later
+    pub fn is_retryable(&self) -> bool {
+        use std::io::ErrorKind;
+
+        match self {
+            Error::StorageUnavailable(_) => true,
+            Error::Io(e) => matches!(
+                e.kind(),
+                ErrorKind::Interrupted
+                    | ErrorKind::TimedOut
+                    | ErrorKind::WouldBlock
+                    | ErrorKind::ConnectionReset
+                    | ErrorKind::ConnectionAborted
+                    | ErrorKind::ConnectionRefused
+            ),
+            _ => false,
+        }
+    }
+
+    /// Error for a storage request that failed before a response was received
+    pub fn storage_request(context: &str, e: reqwest::Error) -> Self {
+        if e.is_connect() || e.is_timeout() {
+            Error::StorageUnavailable(format!("{context}: {e}"))
+        } else {
+            Error::Storage(format!("{context}: {e}"))
+        }
+    }
+
+    /// Error for a storage request answered with an unsuccessful status
+    pub fn storage_status(status: reqwest::StatusCode, message: String) -> Self {
+        if status == reqwest::StatusCode::NOT_FOUND {
+            Error::NotFound(message)
+        } else if status == reqwest::StatusCode::UNAUTHORIZED
+            || status == reqwest::StatusCode::FORBIDDEN
+        {
+            Error::Unauthorized(message)
+        } else if status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            Error::StorageUnavailable(message)
+        } else {
+            Error::Storage(message)
+        }
+    }
+}
+
+/// Machine-readable form of an error
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorReport {
+    pub code: &'static str,
+    pub category: ErrorCategory,
+    pub message: String,
+    pub retryable: bool,
+}
+
+impl From<&Error> for ErrorReport {
+    fn from(error: &Error) -> Self {
+        ErrorReport {
+            code: error.code(),
+            category: error.category(),
+            message: error.to_string(),
+            retryable: error.is_retryable(),
+        }
+    }
+}
+
 pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::StatusCode;
+
+    #[test]
+    fn test_storage_status_classification() {
+        let error = |status| Error::storage_status(status, "request failed".to_string());
+
+        assert_eq!(error(StatusCode::NOT_FOUND).code(), "ATLAS-STOR-002");
+        assert!(!error(StatusCode::NOT_FOUND).is_retryable());
+        assert!(error(StatusCode::SERVICE_UNAVAILABLE).is_retryable());
+        assert!(error(StatusCode::TOO_MANY_REQUESTS).is_retryable());
+        assert_eq!(error(StatusCode::BAD_REQUEST).code(), "ATLAS-STOR-001");
+        assert_eq!(error(StatusCode::FORBIDDEN).code(), "ATLAS-STOR-004");
+        assert!(!error(StatusCode::UNAUTHORIZED).is_retryable());
+        assert_eq!(
+            error(StatusCode::BAD_GATEWAY).to_string(),
+            "Storage error: request failed"
+        );
+
+        let timeout = Error::Io(std::io::Error::from(std::io::ErrorKind::TimedOut));
+        assert!(timeout.is_retryable());
+        assert_eq!(timeout.category(), ErrorCategory::Io);
+    }
+}
@@ -8,6 +8,8 @@
 //!
 //! - Calculate hashes of byte data with configurable algorithms
 //! - Calculate file hashes efficiently using streaming
+//! - With the `io-uring` feature on Linux, read files through io_uring with several reads
+//!   in flight, falling back to streaming where io_uring is not available
 //! - Combine multiple hashes into a single hash
 //! - Verify data integrity by comparing hashes
 //! - Automatic algorithm detection based on hash length
@@ -56,9 +58,12 @@ use crate::utils::safe_open_file;
 use atlas_c2pa_lib::cose::HashAlgorithm;
 use sha2::{Digest, Sha256, Sha384, Sha512};
 use std::io::Read;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use subtle::ConstantTimeEq;
 
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+mod uring;
+
 /// Calculate SHA-384 hash of the given data
 ///
 /// This function uses SHA-384 by default. For other algorithms, use
@@ -209,11 +214,38 @@ pub fn calculate_file_hash_with_algorithm(
     algorithm: &HashAlgorithm,
 ) -> Result<String> {
     let file = safe_open_file(path.as_ref(), false)?;
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    if let Some(hash) = uring::hash_file(&file, algorithm) {
+        return Ok(hash);
+    }
+    calculate_reader_hash_with_algorithm(file, algorithm)
+}
 
+/// Calculate hash of everything read from a reader using the specified algorithm
+///
+/// Like [`calculate_file_hash_with_algorithm`], the data is hashed in chunks, so this
+/// can be used for streams that don't fit in memory, such as archive members.
+///
+/// # Examples
+///
+/// ```
+/// use atlas_cli::hash::{calculate_hash_with_algorithm, calculate_reader_hash_with_algorithm};
+/// use atlas_c2pa_lib::cose::HashAlgorithm;
+///
+/// let data = b"streamed data";
+/// let hash = calculate_reader_hash_with_algorithm(&data[..], &HashAlgorithm::Sha256)?;
+/// assert_eq!(hash, calculate_hash_with_algorithm(data, &HashAlgorithm::Sha256));
+///
+/// # Ok::<(), atlas_cli::error::Error>(())
+/// ```
+pub fn calculate_reader_hash_with_algorithm<R: Read>(
+    reader: R,
+    algorithm: &HashAlgorithm,
+) -> Result<String> {
     match algorithm {
-        HashAlgorithm::Sha256 => hash_reader::<Sha256, _>(file),
-        HashAlgorithm::Sha512 => hash_reader::<Sha512, _>(file),
-        _ => hash_reader::<Sha384, _>(file),
+        HashAlgorithm::Sha256 => hash_reader::<Sha256, _>(reader),
+        HashAlgorithm::Sha512 => hash_reader::<Sha512, _>(reader),
+        _ => hash_reader::<Sha384, _>(reader),
     }
 }
 
@@ -496,6 +528,56 @@ pub fn parse_algorithm(s: &str) -> Result<HashAlgorithm> {
     HashAlgorithm::from_str(s).map_err(Error::Validation)
 }
 
+/// Splits the hash algorithm suffix from an ingredient path, as in `model.onnx:sha256`
+///
+/// The suffix selects the algorithm for that ingredient instead of `--hash-alg`. It is only
+/// recognized when the path as given doesn't exist and the part after the last `:` is a
+/// plain word, so Windows drive letters, URLs and file names with colons are left alone.
+///
+/// # Examples
+///
+/// ```
+/// use atlas_cli::hash::split_path_algorithm;
+/// use atlas_c2pa_lib::cose::HashAlgorithm;
+/// use std::path::Path;
+///
+/// let (path, algorithm) = split_path_algorithm(Path::new("model.onnx:sha256")).unwrap();
+/// assert_eq!(path, Path::new("model.onnx"));
+/// assert!(matches!(algorithm, Some(HashAlgorithm::Sha256)));
+///
+/// let (path, algorithm) = split_path_algorithm(Path::new("s3://bucket/data.bin")).unwrap();
+/// assert_eq!(path, Path::new("s3://bucket/data.bin"));
+/// assert!(algorithm.is_none());
+///
+/// assert!(split_path_algorithm(Path::new("weights.bin:md5")).is_err());
+/// ```
+pub fn split_path_algorithm(path: &Path) -> Result<(PathBuf, Option<HashAlgorithm>)> {
+    let spec = path.to_string_lossy();
+    let suffix = match spec.rsplit_once(':') {
+        Some((rest, suffix))
+            if !rest.is_empty()
+                && !suffix.is_empty()
+                && suffix.chars().all(|c| c.is_ascii_alphanumeric())
+                && !path.exists() =>
+        {
+            Some((rest, suffix))
+        }
+        _ => None,
+    };
+
+    match suffix {
+        Some((rest, suffix)) => {
+            let algorithm = parse_algorithm(&suffix.to_lowercase()).map_err(|_| {
+                Error::Validation(format!(
+                    "Unsupported hash algorithm '{suffix}' for {rest}. Valid options are: sha256, sha384, sha512"
+                ))
+            })?;
+            Ok((PathBuf::from(rest), Some(algorithm)))
+        }
+        None => Ok((path.to_path_buf(), None)),
+    }
+}
+
 /// Internal helper to hash data from a reader using streaming
 fn hash_reader<D: Digest, R: Read>(mut reader: R) -> Result<String> {
     let mut hasher = D::new();
@@ -0,0 +1,189 @@
+//! io_uring file reading for the hashing hot path.
+//!
+//! Hashing checkpoints on NVMe drives is bound by how fast they are read, and a read loop
+//! leaves the drive idle while each chunk is hashed. With the `io-uring` feature on Linux,
+//! files are hashed from several reads kept in flight on an io_uring instance. Where
+//! io_uring can't be used, such as on kernels without it or under a seccomp profile that
+//! forbids it, hashing falls back to the read loop.
+
+use atlas_c2pa_lib::cose::HashAlgorithm;
+use io_uring::{IoUring, opcode, types};
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use std::fs::File;
+use std::io;
+use std::os::fd::AsRawFd;
+
+/// Size of each read
+const CHUNK_SIZE: usize = 1 << 20;
+
+/// Reads kept in flight
+const QUEUE_DEPTH: usize = 4;
+
+/// Hashes a file through io_uring, or returns `None` if io_uring can't be used for it, in
+/// which case the caller reads the file itself
+pub(crate) fn hash_file(file: &File, algorithm: &HashAlgorithm) -> Option<String> {
+    let result = file.metadata().and_then(|metadata| {
+        let size = metadata.len();
+        match algorithm {
+            HashAlgorithm::Sha256 => hash_with::<Sha256>(file, size),
+            HashAlgorithm::Sha512 => hash_with::<Sha512>(file, size),
+            _ => hash_with::<Sha384>(file, size),
+        }
+    });
+
+    result
+        .inspect_err(|e| log::debug!("Hashing with io_uring failed, using the read loop: {e}"))
+        .ok()
+}
+
+// Hashes the first `size` bytes of the file, failing if any read comes up short
+fn hash_with<D: Digest>(file: &File, size: u64) -> io::Result<String> {
+    // Declared before the ring, so that they are dropped after it
+    let mut buffers = vec![vec![0u8; CHUNK_SIZE]; QUEUE_DEPTH];
+    let mut ring = IoUring::new(QUEUE_DEPTH as u32)?;
+    // Length read into each buffer whose read completed and which is not hashed yet
+    let mut completed: Vec<Option<usize>> = vec![None; QUEUE_DEPTH];
+    // Chunks are aligned, so each offset has its own buffer among the chunks in flight
+    let slot = |offset: u64| (offset / CHUNK_SIZE as u64) as usize % QUEUE_DEPTH;
+    let window = (QUEUE_DEPTH * CHUNK_SIZE) as u64;
+
+    let mut hasher = D::new();
+    let mut submitted = 0u64;
+    let mut hashed = 0u64;
+    let mut in_flight = 0;
+    // The first failed read. Reads still in flight are waited for before it is returned,
+    // since they write into the buffers.
+    let mut failure = None;
+
+    loop {
+        // Read ahead into every buffer that was hashed
+        while failure.is_none() && submitted < size && submitted < hashed + window {
+            let len = (size - submitted).min(CHUNK_SIZE as u64) as u32;
+            let entry = opcode::Read::new(
+                types::Fd(file.as_raw_fd()),
+                buffers[slot(submitted)].as_mut_ptr(),
+                len,
+            )
+            .offset(submitted)
+            .build()
+            .user_data(submitted);
+            // SAFETY: the buffer is neither touched nor freed until the read completed, as
+            // every read in flight is waited for before returning, or the buffers leaked
+            if unsafe { ring.submission().push(&entry) }.is_err() {
+                failure = Some(io::Error::other("io_uring submission queue is full"));
+                break;
+            }
+            submitted += u64::from(len);
+            in_flight += 1;
+        }
+        if in_flight == 0 {
+            break;
+        }
+
+        let waited = loop {
+            match ring.submit_and_wait(1) {
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                waited => break waited,
+            }
+        };
+        if let Err(e) = waited {
+            // Reads that can't be waited for may still write into the buffers
+            std::mem::forget(buffers);
+            return Err(e);
+        }
+
+        let results: Vec<_> = ring
+            .completion()
+            .map(|entry| (entry.user_data(), entry.result()))
+            .collect();
+        for (offset, result) in results {
+            in_flight -= 1;
+            if result < 0 {
+                failure.get_or_insert(io::Error::from_raw_os_error(-result));
+                continue;
+            }
+            let expected = (size - offset).min(CHUNK_SIZE as u64) as usize;
+            if result as usize != expected {
+                failure.get_or_insert(io::Error::other(format!(
+                    "short read at offset {offset}, the file may have changed"
+                )));
+                continue;
+            }
+            completed[slot(offset)] = Some(expected);
+        }
+
+        // Hash completed chunks in file order
+        while failure.is_none() && hashed < size {
+            let Some(len) = completed[slot(hashed)].take() else {
+                break;
+            };
+            hasher.update(&buffers[slot(hashed)][..len]);
+            hashed += len as u64;
+        }
+    }
+
+    match failure {
+        Some(e) => Err(e),
+        None => Ok(hex::encode(hasher.finalize())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::calculate_reader_hash_with_algorithm;
+    use std::io::Write;
+
+    #[test]
+    fn test_hash_file_matches_read_loop() -> crate::error::Result<()> {
+        let mut file = tempfile::tempfile()?;
+        // Several chunks and a partial one, so reads complete in any order
+        let data: Vec<u8> = (0..CHUNK_SIZE * 5 + 12345)
+            .map(|i| (i % 251) as u8)
+            .collect();
+        file.write_all(&data)?;
+
+        for algorithm in [
+            HashAlgorithm::Sha256,
+            HashAlgorithm::Sha384,
+            HashAlgorithm::Sha512,
+        ] {
+            // io_uring may not be allowed where tests run
+            let Some(hash) = hash_file(&file, &algorithm) else {
+                return Ok(());
+            };
+            assert_eq!(
+                hash,
+                calculate_reader_hash_with_algorithm(&data[..], &algorithm)?
+            );
+        }
+
+        let empty = tempfile::tempfile()?;
+        if let Some(hash) = hash_file(&empty, &HashAlgorithm::Sha256) {
+            assert_eq!(
+                hash,
+                calculate_reader_hash_with_algorithm(&b""[..], &HashAlgorithm::Sha256)?
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_short_read_with_reads_in_flight() -> crate::error::Result<()> {
+        // io_uring may not be allowed where tests run
+        if IoUring::new(QUEUE_DEPTH as u32).is_err() {
+            return Ok(());
+        }
+
+        // Hashing more than the file holds makes the reads past its end come up short
+        // while the reads before them are still queued
+        let mut file = tempfile::tempfile()?;
+        file.write_all(&vec![7u8; CHUNK_SIZE * 2 + 1])?;
+        for _ in 0..8 {
+            let err =
+                hash_with::<Sha256>(&file, (CHUNK_SIZE * QUEUE_DEPTH * 2) as u64).unwrap_err();
+            assert!(err.to_string().contains("short read"), "{err}");
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,123 @@
+//! Shared configuration of HTTP clients.
+//!
+//! Every client talking to storage backends, object stores, Rekor and endorsement services
+//! is built with [`client_builder`], so that all requests carry the same user agent and
+//! go through the same proxy. Proxies are taken from `HTTP_PROXY`, `HTTPS_PROXY` and
+//! `ALL_PROXY`, except for the hosts listed in `NO_PROXY`. `--proxy` sends requests through
+//! the given proxy instead, still leaving out the hosts in `NO_PROXY`.
+
+use crate::error::{Error, Result};
+use reqwest::blocking::{Client, ClientBuilder};
+use reqwest::{NoProxy, Proxy};
+use std::sync::Mutex;
+
+/// Environment variable with text appended to the user agent, e.g. to name a team or job
+pub const USER_AGENT_ENV: &str = "ATLAS_USER_AGENT";
+
+// Proxy given with --proxy, if any
+static PROXY: Mutex<Option<String>> = Mutex::new(None);
+
+/// Sends all requests through `proxy` instead of the proxies from the environment
+///
+/// # Errors
+///
+/// Returns an error if `proxy` is not a valid proxy URL.
+pub fn set_proxy(proxy: Option<String>) -> Result<()> {
+    if let Some(proxy) = &proxy {
+        Proxy::all(proxy)
+            .map_err(|e| Error::Validation(format!("Invalid proxy URL {proxy}: {e}")))?;
+    }
+    *PROXY.lock().unwrap() = proxy;
+    Ok(())
+}
+
+/// User agent of all requests: `atlas-cli/<version> (<os>; <arch>)`, followed by the
+/// value of `ATLAS_USER_AGENT` if it is set
+///
+/// # Examples
+///
+/// ```
+/// use atlas_cli::http::user_agent;
+///
+/// assert!(user_agent().starts_with(concat!("atlas-cli/", env!("CARGO_PKG_VERSION"))));
+/// ```
+pub fn user_agent() -> String {
+    let agent = format!(
+        "atlas-cli/{} ({}; {})",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    );
+    match std::env::var(USER_AGENT_ENV) {
+        Ok(extra) if !extra.trim().is_empty() => format!("{agent} {}", extra.trim()),
+        _ => agent,
+    }
+}
+
+/// Client builder with the user agent and proxy of all requests
+pub fn client_builder() -> ClientBuilder {
+    builder_with_proxy(PROXY.lock().unwrap().as_deref())
+}
+
+fn builder_with_proxy(proxy: Option<&str>) -> ClientBuilder {
+    let builder = Client::builder().user_agent(user_agent());
+    // The proxy was checked when it was set
+    match proxy.and_then(|proxy| Proxy::all(proxy).ok()) {
+        Some(proxy) => builder.proxy(proxy.no_proxy(NoProxy::from_env())),
+        None => builder,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_requests_go_through_proxy() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let proxy = format!("http://{}", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut lines = Vec::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                lines.push(line.trim().to_string());
+            }
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                .unwrap();
+            lines
+        });
+
+        let client = builder_with_proxy(Some(&proxy)).build().unwrap();
+        let response = client
+            .get("http://storage.example.invalid/manifests")
+            .send()
+            .unwrap();
+        assert!(response.status().is_success());
+
+        // The proxy is asked for the absolute URL, by an identifiable client
+        let lines = server.join().unwrap();
+        assert_eq!(
+            lines[0],
+            "GET http://storage.example.invalid/manifests HTTP/1.1"
+        );
+        assert!(lines.iter().any(|line| {
+            line.split_once(':').is_some_and(|(name, value)| {
+                name.eq_ignore_ascii_case("user-agent") && value.trim() == user_agent()
+            })
+        }));
+    }
+
+    #[test]
+    fn test_invalid_proxy() {
+        assert!(set_proxy(Some("not a url".to_string())).is_err());
+    }
+}
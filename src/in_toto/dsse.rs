@@ -19,6 +19,20 @@
 //!
 //! - [`Envelope`] - The main DSSE container structure
 //! - [`Signature`] - Individual cryptographic signatures with optional key identifiers
+//! - [`PayloadRef`] - Where the payload of a detached envelope is published
+//!
+//! ## Detached Payloads
+//!
+//! Large payloads, such as OMS statements embedding a whole manifest, can be published
+//! separately: [`Envelope::detach`] replaces the payload with a [`PayloadRef`] holding its
+//! URL and digest, and [`Envelope::attach`] puts a fetched payload back after checking it
+//! against the digest. Signatures always cover the payload itself, so they are checked the
+//! same way once the payload is attached.
+//!
+//! Payloads too large to hold in memory are signed from a reader into a detached envelope
+//! with [`Envelope::sign_detached`], and checked the same way with
+//! [`Envelope::verify_detached`]. Both hash the payload as it is read, so they need an RSA
+//! or ECDSA key; Ed25519, Ed448 and ML-DSA keys are rejected, see [`signing::prehash`].
 //!
 //! ## Examples
 //!
@@ -68,11 +82,15 @@
 //! - Validation of required fields and signature integrity
 
 use crate::error::{Error, Result};
+use crate::hash;
 use crate::signing;
 use crate::signing::signable::Signable;
 
 use atlas_c2pa_lib::cose::HashAlgorithm;
-use std::path::PathBuf;
+use openssl::pkey::{PKey, Public};
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
 
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
@@ -203,6 +221,18 @@ pub struct Envelope {
     payload: Vec<u8>,
     payload_type: String,
     signatures: Vec<Signature>,
+    /// Where the payload is published, if it is detached; `payload` is empty then
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    payload_ref: Option<PayloadRef>,
+}
+
+/// Reference to the payload of a detached envelope, see [`Envelope::detach`]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PayloadRef {
+    /// HTTP(S) or `file:` URL, or path, of the payload
+    pub uri: String,
+    /// Hex-encoded digests of the payload by algorithm, e.g. `sha384`
+    pub digest: BTreeMap<String, String>,
 }
 
 impl Envelope {
@@ -236,7 +266,202 @@ impl Envelope {
             payload: payload.to_vec(),
             payload_type: payload_type,
             signatures: vec![],
+            payload_ref: None,
+        }
+    }
+
+    /// Returns the reference to the payload of a detached envelope.
+    ///
+    /// # Returns
+    ///
+    /// `None` if the envelope holds its payload.
+    pub fn payload_ref(&self) -> Option<&PayloadRef> {
+        self.payload_ref.as_ref()
+    }
+
+    /// Moves the payload out of the envelope, leaving a reference to `uri` with the
+    /// payload's digest.
+    ///
+    /// # Arguments
+    ///
+    /// * `uri` - Where the payload will be published
+    /// * `hash_alg` - Hash algorithm of the recorded digest
+    ///
+    /// # Returns
+    ///
+    /// The payload, to be published at `uri`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `Validation` error if the envelope is already detached or has no payload.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use atlas_cli::in_toto::dsse::Envelope;
+    /// use atlas_c2pa_lib::cose::HashAlgorithm;
+    ///
+    /// let mut envelope = Envelope::new(&b"large payload".to_vec(), "text/plain".to_string());
+    /// let payload = envelope
+    ///     .detach("https://example.com/payload".to_string(), &HashAlgorithm::Sha384)
+    ///     .unwrap();
+    /// assert!(envelope.payload().is_empty());
+    ///
+    /// envelope.attach(payload).unwrap();
+    /// assert_eq!(envelope.payload(), b"large payload");
+    /// ```
+    pub fn detach(&mut self, uri: String, hash_alg: &HashAlgorithm) -> Result<Vec<u8>> {
+        if self.payload_ref.is_some() {
+            return Err(Error::Validation(
+                "The envelope's payload is already detached".to_string(),
+            ));
+        }
+        if self.payload.is_empty() {
+            return Err(Error::Validation("The envelope has no payload".to_string()));
+        }
+
+        let digest = hash::calculate_hash_with_algorithm(&self.payload, hash_alg);
+        self.payload_ref = Some(PayloadRef {
+            uri,
+            digest: BTreeMap::from([(hash::algorithm_to_string(hash_alg).to_string(), digest)]),
+        });
+        Ok(std::mem::take(&mut self.payload))
+    }
+
+    /// Signs the payload read from `payload` into a detached envelope referring to `uri`,
+    /// recording the payload's digest. The payload is hashed as it is read, a chunk at a
+    /// time, so it is never held in memory.
+    ///
+    /// The signature is the one [`Signable::sign`] makes over the same payload, so the
+    /// envelope also verifies once its payload is attached.
+    ///
+    /// # Arguments
+    ///
+    /// * `payload` - Reader of the payload, such as the file to be published at `uri`
+    /// * `payload_type` - String describing the payload format (e.g., MIME type)
+    /// * `uri` - Where the payload will be published
+    /// * `private_key` - RSA or ECDSA signing key
+    /// * `hash_alg` - Hash algorithm of the signature and the recorded digest
+    ///
+    /// # Errors
+    ///
+    /// Returns a `Signing` error for Ed25519, Ed448 and ML-DSA keys, which sign the whole
+    /// payload at once, and an error if the payload can't be read.
+    pub fn sign_detached(
+        payload: &mut dyn Read,
+        payload_type: String,
+        uri: String,
+        private_key: &signing::SecurePrivateKey,
+        hash_alg: &HashAlgorithm,
+    ) -> Result<Self> {
+        let mut digesting =
+            signing::prehash::DigestingReader::new(payload, std::slice::from_ref(hash_alg))?;
+        let signature = signing::prehash::sign_reader(
+            &mut payload_type.as_bytes().chain(&mut digesting),
+            private_key,
+            hash_alg,
+        )?;
+        let digest = digesting.finish()?.remove(0);
+
+        let mut envelope = Self {
+            payload: Vec::new(),
+            payload_type,
+            signatures: vec![],
+            payload_ref: Some(PayloadRef {
+                uri,
+                digest: BTreeMap::from([(hash::algorithm_to_string(hash_alg).to_string(), digest)]),
+            }),
+        };
+        envelope.add_signature(signature, "".to_string())?;
+        Ok(envelope)
+    }
+
+    /// Checks whether any signature of a detached envelope was made with the given key,
+    /// reading the payload from `payload`. The payload is checked against every digest of
+    /// the reference in the same pass, so it is never held in memory.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `Validation` error if the envelope is not detached or the payload doesn't
+    /// match a digest, and a `Signing` error for keys that can't verify streamed data, see
+    /// [`Envelope::sign_detached`].
+    pub fn verify_detached(
+        &self,
+        payload: &mut dyn Read,
+        public_key: &PKey<Public>,
+        hash_alg: &HashAlgorithm,
+    ) -> Result<bool> {
+        let payload_ref = self.payload_ref.as_ref().ok_or_else(|| {
+            Error::Validation("The envelope's payload is not detached".to_string())
+        })?;
+        if payload_ref.digest.is_empty() {
+            return Err(Error::Validation(format!(
+                "The detached payload {} has no digest",
+                payload_ref.uri
+            )));
+        }
+        let algorithms = payload_ref
+            .digest
+            .keys()
+            .map(|alg| hash::parse_algorithm(alg))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut digesting = signing::prehash::DigestingReader::new(payload, &algorithms)?;
+        let signatures: Vec<&[u8]> = self
+            .signatures
+            .iter()
+            .map(|signature| signature.sig.as_slice())
+            .collect();
+        let verified = signing::prehash::verify_reader_any(
+            &mut self.payload_type.as_bytes().chain(&mut digesting),
+            &signatures,
+            public_key,
+            hash_alg,
+        )?;
+
+        // No signature counts unless the payload is the one referenced
+        for ((alg, expected), calculated) in payload_ref.digest.iter().zip(digesting.finish()?) {
+            if !calculated.eq_ignore_ascii_case(expected) {
+                return Err(Error::Validation(format!(
+                    "Detached payload {} doesn't match its {alg} digest: expected {expected}, calculated {calculated}",
+                    payload_ref.uri
+                )));
+            }
+        }
+        Ok(verified)
+    }
+
+    /// Puts the fetched payload of a detached envelope back, after checking it against
+    /// every digest of the reference.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `Validation` error if the envelope is not detached, or the payload
+    /// doesn't match a digest.
+    pub fn attach(&mut self, payload: Vec<u8>) -> Result<()> {
+        let payload_ref = self.payload_ref.as_ref().ok_or_else(|| {
+            Error::Validation("The envelope's payload is not detached".to_string())
+        })?;
+        if payload_ref.digest.is_empty() {
+            return Err(Error::Validation(format!(
+                "The detached payload {} has no digest",
+                payload_ref.uri
+            )));
+        }
+        for (alg, expected) in &payload_ref.digest {
+            let calculated =
+                hash::calculate_hash_with_algorithm(&payload, &hash::parse_algorithm(alg)?);
+            if !calculated.eq_ignore_ascii_case(expected) {
+                return Err(Error::Validation(format!(
+                    "Detached payload {} doesn't match its {alg} digest: expected {expected}, calculated {calculated}",
+                    payload_ref.uri
+                )));
+            }
         }
+
+        self.payload = payload;
+        self.payload_ref = None;
+        Ok(())
     }
 
     /// Adds a signature to the envelope.
@@ -377,6 +602,104 @@ impl Envelope {
     pub fn signatures(&self) -> &[Signature] {
         &self.signatures
     }
+
+    /// Adds an ML-DSA signature, for hybrid (classical + post-quantum) signing.
+    ///
+    /// The signature covers the same data as the classical signature added by
+    /// [`Signable::sign`], and its key identifier is the ML-DSA parameter set.
+    ///
+    /// # Arguments
+    ///
+    /// * `pq_key_path` - Path to the ML-DSA private key file
+    ///
+    /// # Errors
+    ///
+    /// Returns a `Signing` error if the key is not an ML-DSA key, or if atlas-cli was
+    /// built without the `pq` feature.
+    pub fn add_pq_signature(&mut self, pq_key_path: &Path) -> Result<()> {
+        self.check_attached()?;
+        let private_key = signing::pq::load_ml_dsa_key(pq_key_path)?;
+        let keyid = signing::pq::ml_dsa_variant(private_key.as_pkey())
+            .expect("checked when loading")
+            .to_string();
+
+        // ML-DSA ignores the hash algorithm
+        let signature = signing::sign_data_with_algorithm(
+            &self.signed_data(),
+            &private_key,
+            &HashAlgorithm::Sha384,
+        )?;
+        self.add_signature(signature, keyid)
+    }
+
+    /// Checks whether any signature of the envelope was made with the given key.
+    ///
+    /// # Arguments
+    ///
+    /// * `public_key` - Public key to verify with, classical or ML-DSA
+    /// * `hash_alg` - Hash algorithm of classical signatures
+    ///
+    /// # Returns
+    ///
+    /// `Ok(true)` if a signature verifies with the key. No signature verifies until the
+    /// payload of a detached envelope is attached.
+    pub fn verify_signature(
+        &self,
+        public_key: &PKey<Public>,
+        hash_alg: &HashAlgorithm,
+    ) -> Result<bool> {
+        // RSA and ECDSA keys hash the signed data as it is read rather than copying it
+        let streamed = signing::prehash::supports_streaming(public_key);
+        let data = if streamed {
+            Vec::new()
+        } else {
+            self.signed_data()
+        };
+        for signature in &self.signatures {
+            let verified = if streamed {
+                signing::prehash::verify_reader(
+                    &mut self.signed_data_reader(),
+                    &signature.sig,
+                    public_key,
+                    hash_alg,
+                )
+            } else {
+                signing::verify_signature_with_algorithm(
+                    &data,
+                    &signature.sig,
+                    public_key,
+                    hash_alg,
+                )
+            };
+            if verified.unwrap_or(false) {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    // Signatures cover the payload, so a detached one must be attached to sign
+    fn check_attached(&self) -> Result<()> {
+        match &self.payload_ref {
+            Some(payload_ref) => Err(Error::Signing(format!(
+                "The envelope's payload is detached at {}; attach it before signing",
+                payload_ref.uri
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    // DSSE requires that payload_type and payload be signed
+    fn signed_data(&self) -> Vec<u8> {
+        let mut data = self.payload_type.clone().into_bytes();
+        data.extend_from_slice(&self.payload);
+        data
+    }
+
+    // The signed data, read without copying the payload
+    fn signed_data_reader(&self) -> impl Read + '_ {
+        self.payload_type.as_bytes().chain(self.payload.as_slice())
+    }
 }
 
 /// Implementation of the `Signable` trait for DSSE envelopes.
@@ -422,18 +745,30 @@ impl Signable for Envelope {
     /// assert!(envelope.validate());
     /// ```
     fn sign(&mut self, key_path: PathBuf, hash_alg: HashAlgorithm) -> Result<()> {
-        let private_key = signing::load_private_key(&key_path)?;
-
-        // DSSE requires that payload_type and payload be signed
-        // We assume the payload is public
-        let mut data_to_sign: Vec<u8> = Vec::new();
-        data_to_sign.extend_from_slice(&self.payload_type.clone().into_bytes());
+        self.check_attached()?;
+
+        // Keys on a PIV card are identified by the SHA-256 fingerprint of their certificate
+        if let Some(slot) = signing::piv::slot_from_key_spec(&key_path) {
+            let slot = slot?;
+            // We assume the payload is public
+            let data_to_sign = self.signed_data();
+            let certificate = signing::piv::read_certificate(slot)?;
+            let signature = signing::piv::sign(&data_to_sign, slot, &certificate, &hash_alg)?;
+            let fingerprint = certificate
+                .digest(openssl::hash::MessageDigest::sha256())
+                .map_err(|e| Error::Signing(e.to_string()))?;
+            return self.add_signature(signature, hex::encode(fingerprint));
+        }
 
-        // DSSE requires payload to be JSON bytes
-        data_to_sign.extend_from_slice(&self.payload);
+        let private_key = signing::load_private_key(&key_path)?;
 
-        // Use the signing module with the specified algorithm
-        let signature = signing::sign_data_with_algorithm(&data_to_sign, &private_key, &hash_alg)?;
+        // RSA and ECDSA keys hash the signed data as it is read, so large payloads aren't
+        // copied; other keys need it in one piece
+        let signature = if signing::prehash::supports_streaming(private_key.as_pkey()) {
+            signing::prehash::sign_reader(&mut self.signed_data_reader(), &private_key, &hash_alg)?
+        } else {
+            signing::sign_data_with_algorithm(&self.signed_data(), &private_key, &hash_alg)?
+        };
 
         self.add_signature(signature, "".to_string()) // keyid is optional
     }
@@ -693,4 +1028,94 @@ mod tests {
         assert_eq!(deserialized.signatures()[0].keyid(), "key1");
         assert_eq!(deserialized.signatures()[0].sig(), &[0x01, 0x02, 0x03]);
     }
+
+    #[test]
+    fn test_envelope_detach_and_attach() {
+        let mut envelope = Envelope::new(&b"statement".to_vec(), "text/plain".to_string());
+        envelope
+            .add_signature(vec![0xab, 0xcd], "key1".to_string())
+            .unwrap();
+        let json = to_string(&envelope).unwrap();
+        assert!(!json.contains("payload_ref"));
+
+        let payload = envelope
+            .detach("payload.txt".to_string(), &HashAlgorithm::Sha256)
+            .unwrap();
+        assert_eq!(payload, b"statement");
+        assert!(!envelope.validate());
+        assert!(
+            envelope
+                .detach("payload.txt".to_string(), &HashAlgorithm::Sha256)
+                .is_err()
+        );
+
+        // The reference survives serialization
+        let mut detached: Envelope = from_str(&to_string(&envelope).unwrap()).unwrap();
+        assert_eq!(detached.payload_ref(), envelope.payload_ref());
+        assert!(detached.attach(b"statement!".to_vec()).is_err());
+        detached.attach(payload).unwrap();
+        assert!(detached.payload_ref().is_none());
+        assert!(detached.validate());
+        assert!(detached.attach(b"statement".to_vec()).is_err());
+    }
+
+    #[test]
+    fn test_sign_and_verify_detached() -> Result<()> {
+        use crate::signing::test_utils::generate_temp_key;
+
+        // Spans several chunks
+        let payload = vec![0x42; 2 * signing::prehash::CHUNK_SIZE + 5];
+        let (key, _dir) = generate_temp_key()?;
+        let public_key =
+            PKey::public_key_from_pem(&key.as_pkey().public_key_to_pem().unwrap()).unwrap();
+        let envelope = Envelope::sign_detached(
+            &mut payload.as_slice(),
+            "application/octet-stream".to_string(),
+            "payload.bin".to_string(),
+            &key,
+            &HashAlgorithm::Sha384,
+        )?;
+        assert!(envelope.payload().is_empty());
+        assert_eq!(
+            envelope.payload_ref().unwrap().digest["sha384"],
+            hash::calculate_hash_with_algorithm(&payload, &HashAlgorithm::Sha384)
+        );
+
+        assert!(envelope.verify_detached(
+            &mut payload.as_slice(),
+            &public_key,
+            &HashAlgorithm::Sha384
+        )?);
+        let mut changed = payload.clone();
+        changed[0] ^= 1;
+        assert!(
+            envelope
+                .verify_detached(&mut changed.as_slice(), &public_key, &HashAlgorithm::Sha384)
+                .is_err()
+        );
+
+        // The same signature as over the attached payload
+        let mut attached = envelope.clone();
+        attached.attach(payload)?;
+        assert!(attached.verify_signature(&public_key, &HashAlgorithm::Sha384)?);
+        assert!(
+            attached
+                .verify_detached(&mut b"".as_slice(), &public_key, &HashAlgorithm::Sha384)
+                .is_err()
+        );
+
+        // EdDSA keys can't sign a payload they don't hold
+        let ed25519 = signing::pkey_to_secure(PKey::generate_ed25519().unwrap())?;
+        let error = Envelope::sign_detached(
+            &mut b"payload".as_slice(),
+            "text/plain".to_string(),
+            "payload.txt".to_string(),
+            &ed25519,
+            &HashAlgorithm::Sha384,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(error.contains("Ed25519ph is not supported"), "{error}");
+        Ok(())
+    }
 }
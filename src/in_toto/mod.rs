@@ -46,14 +46,16 @@ use in_toto_attestation::generate_statement_v1;
 use in_toto_attestation::v1::resource_descriptor::ResourceDescriptor;
 use protobuf::well_known_types::struct_::Struct;
 use protobuf_json_mapping::{parse_from_str, print_to_string};
-use std::collections::HashMap;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
 
 pub mod dsse;
 
 use dsse::Envelope;
 
-const DSSE_PAYLOAD_TYPE: &str = "application/vnd.in-toto+json";
+/// Payload type of DSSE envelopes holding in-toto statements
+pub const DSSE_PAYLOAD_TYPE: &str = "application/vnd.in-toto+json";
 
 /// Converts a JSON string to a protobuf Struct.
 ///
@@ -248,11 +250,7 @@ pub fn generate_signed_statement_v1(
     key_path: PathBuf,
     hash_alg: HashAlgorithm,
 ) -> Result<Envelope> {
-    let statement = generate_statement_v1(subject, predicate_type, predicate)
-        .map_err(|e| Error::Signing(e.to_string()))?;
-
-    let serialized_statement =
-        print_to_string(&statement).map_err(|e| Error::Serialization(e.to_string()))?;
+    let serialized_statement = generate_statement_json_v1(subject, predicate_type, predicate)?;
 
     let mut envelope = Envelope::new(
         &serialized_statement.into_bytes(),
@@ -263,6 +261,135 @@ pub fn generate_signed_statement_v1(
     Ok(envelope)
 }
 
+/// Generates an unsigned in-toto Statement v1, serialized as JSON.
+///
+/// See [`generate_signed_statement_v1`] for a statement signed into a DSSE envelope.
+///
+/// # Examples
+///
+/// ```
+/// use atlas_cli::in_toto::{generate_statement_json_v1, make_minimal_resource_descriptor};
+/// use protobuf::well_known_types::struct_::Struct;
+///
+/// let subjects = vec![make_minimal_resource_descriptor("model.onnx", "sha256", "abc123")];
+/// let statement =
+///     generate_statement_json_v1(&subjects, "https://example.com/predicate", &Struct::new())
+///         .unwrap();
+///
+/// assert!(statement.contains("https://example.com/predicate"));
+/// ```
+pub fn generate_statement_json_v1(
+    subject: &[ResourceDescriptor],
+    predicate_type: &str,
+    predicate: &Struct,
+) -> Result<String> {
+    let statement = generate_statement_v1(subject, predicate_type, predicate)
+        .map_err(|e| Error::Signing(e.to_string()))?;
+
+    print_to_string(&statement).map_err(|e| Error::Serialization(e.to_string()))
+}
+
+/// Generates an unsigned in-toto Statement v1 with a JSON predicate, serialized as JSON.
+///
+/// Unlike [`generate_statement_json_v1`], the predicate is kept as given: a protobuf
+/// `Struct` holds numbers as floats, so integers such as `3` would be written as `3.0`.
+///
+/// # Examples
+///
+/// ```
+/// use atlas_cli::in_toto::{generate_statement_json_v1_with_value, make_minimal_resource_descriptor};
+/// use serde_json::json;
+///
+/// let subjects = vec![make_minimal_resource_descriptor("model.onnx", "sha256", "abc123")];
+/// let statement = generate_statement_json_v1_with_value(
+///     &subjects,
+///     "https://example.com/predicate",
+///     &json!({"epochs": 3}),
+/// )
+/// .unwrap();
+///
+/// assert!(statement.contains(r#""epochs":3"#));
+/// ```
+pub fn generate_statement_json_v1_with_value(
+    subject: &[ResourceDescriptor],
+    predicate_type: &str,
+    predicate: &serde_json::Value,
+) -> Result<String> {
+    if !predicate.is_object() {
+        return Err(Error::Validation(
+            "A statement's predicate must be a JSON object".to_string(),
+        ));
+    }
+
+    let statement = generate_statement_json_v1(subject, predicate_type, &Struct::new())?;
+    let mut statement: serde_json::Value = serde_json::from_str(&statement)?;
+    statement["predicate"] = predicate.clone();
+
+    serde_json::to_string(&statement).map_err(|e| Error::Serialization(e.to_string()))
+}
+
+/// An in-toto statement, as read back from a DSSE envelope
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Statement {
+    #[serde(default)]
+    pub subject: Vec<StatementSubject>,
+    pub predicate_type: String,
+    #[serde(default)]
+    pub predicate: serde_json::Value,
+}
+
+/// A subject of an in-toto statement, with its digests keyed by algorithm
+#[derive(Debug, Clone, Deserialize)]
+pub struct StatementSubject {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub digest: BTreeMap<String, String>,
+    #[serde(default, rename = "mediaType")]
+    pub media_type: String,
+}
+
+/// Reads the in-toto statement of a signed DSSE envelope.
+///
+/// Signatures are not verified; see [`Envelope::verify_signature`].
+///
+/// # Errors
+///
+/// Returns a `Validation` error if the envelope is unsigned, doesn't hold an in-toto
+/// statement, or the statement can't be parsed.
+///
+/// # Examples
+///
+/// ```
+/// use atlas_cli::in_toto::{DSSE_PAYLOAD_TYPE, dsse::Envelope, read_statement};
+///
+/// let payload = br#"{"_type": "https://in-toto.io/Statement/v1",
+///     "subject": [{"name": "model.onnx", "digest": {"sha256": "abc123"}}],
+///     "predicateType": "https://slsa.dev/provenance/v1", "predicate": {}}"#;
+/// let mut envelope = Envelope::new(&payload.to_vec(), DSSE_PAYLOAD_TYPE.to_string());
+/// envelope.add_signature(vec![0xab, 0xcd], "".to_string()).unwrap();
+///
+/// let statement = read_statement(&envelope).unwrap();
+/// assert_eq!(statement.subject[0].digest["sha256"], "abc123");
+/// ```
+pub fn read_statement(envelope: &Envelope) -> Result<Statement> {
+    if let Some(payload_ref) = envelope.payload_ref() {
+        return Err(Error::Validation(format!(
+            "The envelope's payload is detached at {} and has not been fetched",
+            payload_ref.uri
+        )));
+    }
+    if !envelope.validate() || envelope.payload_type() != DSSE_PAYLOAD_TYPE {
+        return Err(Error::Validation(format!(
+            "Envelope must be signed and contain an in-toto statement ({DSSE_PAYLOAD_TYPE})"
+        )));
+    }
+
+    serde_json::from_slice(envelope.payload())
+        .map_err(|e| Error::Validation(format!("Invalid in-toto statement: {e}")))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
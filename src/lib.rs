@@ -33,10 +33,14 @@
 
 pub mod cc_attestation;
 pub mod cli;
+pub mod doctor;
 pub mod error;
 pub mod hash;
+pub mod http;
 pub mod in_toto;
 pub mod manifest;
+pub mod metrics;
+pub mod registry;
 pub mod signing;
 pub mod slsa;
 pub mod storage;
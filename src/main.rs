@@ -2,17 +2,81 @@ use atlas_cli::{
     cli::{
         self,
         commands::{
-            CCAttestationCommands, DatasetCommands, EvaluationCommands, ManifestCommands,
-            ModelCommands, PipelineCommands, SoftwareCommands,
+            AttestationCommands, CCAttestationCommands, ColorChoice, DatasetCommands, DoctorArgs,
+            ErrorFormatChoice, EvaluationCommands, ImportCommands, KeyCommands, ManifestCommands,
+            ModelCommands, PipelineCommands, ProgressChoice, QueueCommands, RekorCommands,
+            SlsaCommands, SoftwareCommands, StoreCommands, SyncArgs,
         },
     },
     error::Result,
 };
-use clap::{Parser, Subcommand};
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand};
+use std::path::PathBuf;
+use std::time::Instant;
 
 #[derive(Parser)]
 #[command(author, version, about)]
 pub struct Cli {
+    /// Restrict hashing and signing to FIPS-approved algorithms (also set by ATLAS_FIPS=1)
+    #[arg(long = "fips", global = true)]
+    fips: bool,
+
+    /// How errors are reported on stderr
+    #[arg(
+        long = "error-format",
+        global = true,
+        value_enum,
+        default_value = "text"
+    )]
+    error_format: ErrorFormatChoice,
+
+    /// When to color output such as verification marks
+    #[arg(long = "color", global = true, value_enum, default_value = "auto")]
+    color: ColorChoice,
+
+    /// Fail instead of warning when a storage endpoint's pinned identity changes
+    #[arg(long = "strict", global = true)]
+    strict: bool,
+
+    /// How verification progress is shown: text, or also NDJSON events on stderr
+    #[arg(long = "progress", global = true, value_enum, default_value = "text")]
+    progress: ProgressChoice,
+
+    /// Explain what each verification check compared and why it passed or failed
+    #[arg(long = "explain", global = true)]
+    explain: bool,
+
+    /// Create manifests that violate the signing policy, recording this reason in them
+    #[arg(
+        long = "override-policy",
+        global = true,
+        value_name = "REASON",
+        requires = "acknowledge_policy_override"
+    )]
+    override_policy: Option<String>,
+
+    /// Confirm that --override-policy creates manifests violating the signing policy
+    #[arg(long = "acknowledge-policy-override", global = true)]
+    acknowledge_policy_override: bool,
+
+    /// Queue manifests locally when their storage is unreachable, to be stored later with
+    /// `queue flush` (also set by ATLAS_OFFLINE_QUEUE=1)
+    #[arg(long = "offline-queue", global = true)]
+    offline_queue: bool,
+
+    /// Send HTTP requests through this proxy instead of those from HTTP(S)_PROXY; hosts in
+    /// NO_PROXY are still reached directly
+    #[arg(long = "proxy", global = true, value_name = "URL")]
+    proxy: Option<String>,
+
+    /// Serve Prometheus metrics at http://<ADDR>/metrics while the command runs
+    #[arg(long = "metrics-addr", global = true, value_name = "ADDR")]
+    metrics_addr: Option<String>,
+
+    /// Write Prometheus metrics to this file when the command ends
+    #[arg(long = "metrics-file", global = true, value_name = "FILE")]
+    metrics_file: Option<PathBuf>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -49,11 +113,50 @@ enum Commands {
         #[command(subcommand)]
         command: PipelineCommands,
     },
+    /// SLSA provenance commands
+    Slsa {
+        #[command(subcommand)]
+        command: SlsaCommands,
+    },
     /// CC Attestation-related commands
     CCAttestation {
         #[command(subcommand)]
         command: CCAttestationCommands,
     },
+    /// in-toto attestation commands (OMS manifests, SLSA provenance and custom statements)
+    Attestation {
+        #[command(subcommand)]
+        command: AttestationCommands,
+    },
+    /// Rekor transparency log commands
+    Rekor {
+        #[command(subcommand)]
+        command: RekorCommands,
+    },
+    /// Export and import of a whole provenance store
+    Store {
+        #[command(subcommand)]
+        command: StoreCommands,
+    },
+    /// Bulk creation of manifests for artifacts that predate atlas-cli
+    Import {
+        #[command(subcommand)]
+        command: ImportCommands,
+    },
+    /// Audit the use of signing keys
+    Key {
+        #[command(subcommand)]
+        command: KeyCommands,
+    },
+    /// Manifests queued while their storage was unreachable
+    Queue {
+        #[command(subcommand)]
+        command: QueueCommands,
+    },
+    /// Push manifest summaries to a model registry, or report its entries lacking manifests
+    Sync(SyncArgs),
+    /// Check the environment: OpenSSL, TEE device, storage, signing key and clock
+    Doctor(DoctorArgs),
 }
 
 fn main() -> Result<()> {
@@ -61,7 +164,32 @@ fn main() -> Result<()> {
     atlas_cli::init_logging()?;
 
     // Parse command line arguments
-    let cli = Cli::parse();
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let operation = operation_name(&matches);
+
+    if cli.fips {
+        atlas_cli::signing::fips::enable();
+    }
+    cli::color::set_color_choice(cli.color.to_color_choice());
+    atlas_cli::storage::pinning::set_strict(cli.strict);
+    atlas_cli::http::set_proxy(cli.proxy.clone())?;
+    if cli.offline_queue {
+        atlas_cli::storage::queue::enable();
+    }
+    atlas_cli::manifest::verification::set_explain(cli.explain);
+    atlas_cli::manifest::verification::set_progress_format(cli.progress.to_progress_format());
+    atlas_cli::manifest::policy::set_override(
+        cli.override_policy
+            .clone()
+            .filter(|_| cli.acknowledge_policy_override),
+    );
+    if let Some(addr) = &cli.metrics_addr {
+        let addr = atlas_cli::metrics::serve(addr)?;
+        log::info!("Serving metrics at http://{addr}/metrics");
+    }
+
+    let started = Instant::now();
 
     // Handle commands
     let result = match cli.command {
@@ -72,15 +200,54 @@ fn main() -> Result<()> {
         Commands::Manifest { command } => cli::handlers::handle_manifest_command(command),
         Commands::Evaluation { command } => cli::handlers::handle_evaluation_command(command),
         Commands::Pipeline { command } => cli::handlers::handle_pipeline_command(command),
+        Commands::Slsa { command } => cli::handlers::handle_slsa_command(command),
         Commands::CCAttestation { command } => {
             cli::handlers::handle_cc_attestation_command(command)
         }
+        Commands::Attestation { command } => cli::handlers::handle_attestation_command(command),
+        Commands::Rekor { command } => cli::handlers::handle_rekor_command(command),
+        Commands::Store { command } => cli::handlers::handle_store_command(command),
+        Commands::Import { command } => cli::handlers::handle_import_command(command),
+        Commands::Key { command } => cli::handlers::handle_key_command(command),
+        Commands::Queue { command } => cli::handlers::handle_queue_command(command),
+        Commands::Sync(args) => cli::handlers::handle_sync_command(args),
+        Commands::Doctor(args) => cli::handlers::handle_doctor_command(args),
     };
 
+    atlas_cli::metrics::record_operation(&operation, started.elapsed(), result.is_ok());
+    if operation
+        .rsplit(' ')
+        .next()
+        .is_some_and(|command| command.starts_with("verify"))
+    {
+        atlas_cli::metrics::record_verification(&operation, result.is_ok());
+    }
+    if let Some(path) = &cli.metrics_file
+        && let Err(e) = atlas_cli::metrics::write_file(path)
+    {
+        eprintln!("Failed to write metrics to {}: {e}", path.display());
+    }
+
     // Format and display any errors
     if let Err(ref e) = result {
+        if cli.error_format == ErrorFormatChoice::Json {
+            // Only the JSON object goes to stderr, so it can be parsed as a whole
+            eprintln!("{}", atlas_cli::error::format_error_json(e));
+            std::process::exit(1);
+        }
         eprintln!("{}", cli::format_error(e));
     }
 
     result
 }
+
+// Name of the command run, such as "model verify"
+fn operation_name(matches: &ArgMatches) -> String {
+    let mut names = Vec::new();
+    let mut matches = matches;
+    while let Some((name, subcommand)) = matches.subcommand() {
+        names.push(name);
+        matches = subcommand;
+    }
+    names.join(" ")
+}
@@ -0,0 +1,369 @@
+//! Reviewer approvals of manifests.
+//!
+//! `manifest approve` appends an approval assertion recording who reviewed a manifest, in
+//! which role, when, and what they decided. Each approval is signed with the reviewer's
+//! key over the hash of the manifest without its approvals and signatures, so approvals
+//! from several reviewers can be collected in any order, and any change to the reviewed
+//! content invalidates them. An [`ApprovalPolicy`] decides how many approvals a manifest
+//! needs before it is release-ready.
+
+use crate::cli::color;
+use crate::error::{Error, Result};
+use crate::hash;
+use crate::manifest::verification::{self, Explanation};
+use crate::manifest::{claims, credential, redaction, signer};
+use crate::signing;
+use crate::storage::traits::StorageBackend;
+use atlas_c2pa_lib::assertion::{Assertion, CustomAssertion};
+use atlas_c2pa_lib::cose::HashAlgorithm;
+use atlas_c2pa_lib::manifest::Manifest;
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use openssl::pkey::PKey;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use time::OffsetDateTime;
+
+/// Label of the approval assertion
+pub const APPROVAL_ASSERTION_LABEL: &str = "approval";
+
+/// Decision recorded by a reviewer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApprovalDecision {
+    Approved,
+    Rejected,
+}
+
+impl fmt::Display for ApprovalDecision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApprovalDecision::Approved => write!(f, "approved"),
+            ApprovalDecision::Rejected => write!(f, "rejected"),
+        }
+    }
+}
+
+/// Contents of the approval assertion
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApprovalAssertion {
+    /// Name of the reviewer, the key fingerprint if no name was given
+    pub reviewer: String,
+    pub role: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub timestamp: OffsetDateTime,
+    pub decision: ApprovalDecision,
+    /// Hash of the approved manifest, see [`approval_subject_hash`]
+    pub manifest_hash: String,
+    /// PEM-encoded public key of the reviewer
+    pub public_key: String,
+    /// Base64-encoded signature over all other fields
+    pub signature: String,
+}
+
+impl ApprovalAssertion {
+    /// Bytes covered by the approval signature
+    pub fn signed_payload(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(&serde_json::json!({
+            "reviewer": self.reviewer,
+            "role": self.role,
+            "timestamp": self.timestamp.unix_timestamp(),
+            "decision": self.decision,
+            "manifest_hash": self.manifest_hash,
+            "public_key": self.public_key,
+        }))?)
+    }
+
+    /// Verifies the approval signature with the embedded public key
+    pub fn verify_signature(&self) -> Result<bool> {
+        let public_key = PKey::public_key_from_pem(self.public_key.as_bytes())
+            .map_err(|e| Error::Signing(format!("Invalid reviewer public key: {e}")))?;
+        let signature = STANDARD
+            .decode(&self.signature)
+            .map_err(|e| Error::Signing(format!("Invalid approval signature encoding: {e}")))?;
+
+        signing::verify_signature_with_algorithm(
+            &self.signed_payload()?,
+            &signature,
+            &public_key,
+            &hash::detect_hash_algorithm(&self.manifest_hash),
+        )
+    }
+
+    /// SHA-256 fingerprint of the reviewer public key
+    pub fn key_fingerprint(&self) -> String {
+        hash::calculate_hash_with_algorithm(self.public_key.as_bytes(), &HashAlgorithm::Sha256)
+    }
+}
+
+/// Number of approvals a manifest needs to be release-ready
+#[derive(Debug, Clone, Default)]
+pub struct ApprovalPolicy {
+    /// Minimum number of distinct reviewers that approved the manifest
+    pub required_approvals: usize,
+    /// Only approvals given in this role count
+    pub role: Option<String>,
+}
+
+/// Hash of a manifest as seen by reviewers: without approval assertions and signatures,
+/// and without the record of the signer key and the assertion salts, which change when a
+/// reviewer re-signs
+pub fn approval_subject_hash(manifest: &Manifest, hash_alg: &HashAlgorithm) -> Result<String> {
+    let is_approval = |assertion: &Assertion| matches!(assertion, Assertion::CustomAssertion(custom) if custom.label == APPROVAL_ASSERTION_LABEL || custom.label == signer::SIGNER_KEY_ASSERTION_LABEL || custom.label == redaction::ASSERTION_SALTS_ASSERTION_LABEL);
+
+    let mut subject = manifest.clone();
+    subject.claim.created_assertions.retain(|a| !is_approval(a));
+    subject.claim.signature = None;
+    if let Some(claim) = subject.claim_v2.as_mut() {
+        claim.created_assertions.retain(|a| !is_approval(a));
+        claim.signature = None;
+    }
+
+    let json = serde_json::to_string(&subject)?;
+    Ok(hash::calculate_hash_with_algorithm(
+        json.as_bytes(),
+        hash_alg,
+    ))
+}
+
+/// Returns the approvals recorded in a manifest, in the order they were given
+pub fn approvals(manifest: &Manifest) -> Result<Vec<ApprovalAssertion>> {
+    let assertions = &claims::signed_claim(manifest).created_assertions;
+
+    assertions
+        .iter()
+        .filter_map(|assertion| match assertion {
+            Assertion::CustomAssertion(custom) if custom.label == APPROVAL_ASSERTION_LABEL => Some(
+                serde_json::from_value(custom.data.clone())
+                    .map_err(|e| Error::Manifest(format!("Invalid approval assertion: {e}"))),
+            ),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Appends an approval signed with the reviewer key to a stored manifest.
+///
+/// The updated claim is re-signed with the same key. Returns the ID under which the
+/// updated manifest was stored.
+pub fn approve_manifest(
+    manifest_id: &str,
+    storage: &dyn StorageBackend,
+    role: &str,
+    reviewer: Option<String>,
+    decision: ApprovalDecision,
+    key_path: PathBuf,
+    hash_alg: HashAlgorithm,
+) -> Result<String> {
+    if let Some(reviewer) = &reviewer {
+        credential::check_identity(reviewer)?;
+    }
+    let mut manifest = storage.retrieve_manifest(manifest_id)?;
+
+    let private_key = signing::load_private_key(&key_path)?;
+    let public_key = private_key
+        .as_pkey()
+        .public_key_to_pem()
+        .map_err(|e| Error::Signing(format!("Failed to export reviewer public key: {e}")))?;
+    let public_key = String::from_utf8(public_key)
+        .map_err(|e| Error::Signing(format!("Invalid reviewer public key: {e}")))?;
+
+    let mut approval = ApprovalAssertion {
+        reviewer: String::new(),
+        role: role.to_string(),
+        timestamp: OffsetDateTime::now_utc(),
+        decision,
+        manifest_hash: approval_subject_hash(&manifest, &hash_alg)?,
+        public_key,
+        signature: String::new(),
+    };
+    approval.reviewer = reviewer.unwrap_or_else(|| approval.key_fingerprint());
+
+    let signature =
+        signing::sign_data_with_algorithm(&approval.signed_payload()?, &private_key, &hash_alg)?;
+    approval.signature = STANDARD.encode(signature);
+
+    let assertion = Assertion::CustomAssertion(CustomAssertion {
+        label: APPROVAL_ASSERTION_LABEL.to_string(),
+        data: serde_json::to_value(&approval)?,
+    });
+    manifest.claim.created_assertions.push(assertion.clone());
+    manifest.claim.signature = None;
+    if let Some(claim_v2) = manifest.claim_v2.as_mut() {
+        claim_v2.created_assertions.push(assertion);
+    }
+
+    // Signed with the loaded key, since a key from stdin can only be read once
+    signer::sign_with_key(&mut manifest, &private_key, &hash_alg)?;
+    storage.store_manifest(&manifest)
+}
+
+/// Checks the approvals of a manifest against a policy, returning the number of
+/// approvals that count towards it.
+///
+/// Approvals with an invalid signature fail the check. Approvals of an earlier version
+/// of the manifest are ignored. When a reviewer decided more than once, only the latest
+/// decision counts, and a rejection by any reviewer fails the check.
+pub fn check_approvals(manifest: &Manifest, policy: &ApprovalPolicy) -> Result<usize> {
+    // Latest decision of each reviewer key
+    let mut decisions: HashMap<String, ApprovalAssertion> = HashMap::new();
+
+    for approval in approvals(manifest)? {
+        let explain = |outcome: &str| {
+            Explanation::new()
+                .line(format!(
+                    "approval by {} ({}), key {}...: {outcome}",
+                    approval.reviewer,
+                    approval.role,
+                    verification::digest_prefix(&approval.key_fingerprint())
+                ))
+                .print()
+        };
+
+        if !approval.verify_signature()? {
+            return Err(Error::Validation(format!(
+                "Invalid signature on approval by {} ({})",
+                approval.reviewer, approval.role
+            )));
+        }
+
+        if policy
+            .role
+            .as_ref()
+            .is_some_and(|role| *role != approval.role)
+        {
+            explain("signature verified, not counted for another role");
+            continue;
+        }
+
+        let alg = hash::detect_hash_algorithm(&approval.manifest_hash);
+        let subject_hash = approval_subject_hash(manifest, &alg)?;
+        if subject_hash != approval.manifest_hash {
+            println!(
+                "{} Warning: Ignoring approval by {} given for a different version of the manifest",
+                color::warning_sign(),
+                approval.reviewer
+            );
+            Explanation::new()
+                .digests(alg.as_str(), &approval.manifest_hash, &subject_hash)
+                .print();
+            continue;
+        }
+        explain(&format!("signature verified, {}", approval.decision));
+
+        let fingerprint = approval.key_fingerprint();
+        if decisions
+            .get(&fingerprint)
+            .is_none_or(|previous| previous.timestamp <= approval.timestamp)
+        {
+            decisions.insert(fingerprint, approval);
+        }
+    }
+
+    if let Some(rejection) = decisions
+        .values()
+        .find(|approval| approval.decision == ApprovalDecision::Rejected)
+    {
+        return Err(Error::Validation(format!(
+            "Manifest was rejected by {} ({}) at {}",
+            rejection.reviewer, rejection.role, rejection.timestamp
+        )));
+    }
+
+    let approved = decisions.len();
+    if approved < policy.required_approvals {
+        let rule = match &policy.role {
+            Some(role) => format!("{} approval(s) in role {role}", policy.required_approvals),
+            None => format!("{} approval(s)", policy.required_approvals),
+        };
+        return Err(Explanation::new()
+            .line(format!(
+                "policy: {rule} from distinct reviewer keys required, {approved} counted"
+            ))
+            .error(Error::Validation(format!(
+                "Manifest has {approved} of {} required approvals",
+                policy.required_approvals
+            ))));
+    }
+
+    Ok(approved)
+}
+
+/// Checks that a stored manifest satisfies an approval policy
+pub fn verify_release_ready(
+    manifest_id: &str,
+    storage: &dyn StorageBackend,
+    policy: &ApprovalPolicy,
+) -> Result<()> {
+    let manifest = storage.retrieve_manifest(manifest_id)?;
+    let approved = check_approvals(&manifest, policy)?;
+
+    println!(
+        "{} Release-ready: {approved} approval(s)",
+        color::check_mark()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest::common::AssetKind;
+    use crate::tests::common::{MockStorageBackend, create_test_manifest_internal};
+
+    #[test]
+    fn test_manifest_approvals() -> Result<()> {
+        use crate::signing::test_utils::generate_temp_key;
+
+        let (_, alice_dir) = generate_temp_key()?;
+        let (_, bob_dir) = generate_temp_key()?;
+        let alice_key = alice_dir.path().join("test_key.pem");
+        let bob_key = bob_dir.path().join("test_key.pem");
+
+        let manifest =
+            create_test_manifest_internal("model".to_string(), vec![], "Model", AssetKind::Model)?;
+        let storage = MockStorageBackend::new(manifest);
+        let policy = ApprovalPolicy {
+            required_approvals: 2,
+            role: Some("reviewer".to_string()),
+        };
+
+        let approve = |key: &std::path::Path, role: &str, decision| {
+            approve_manifest(
+                "model",
+                &storage,
+                role,
+                None,
+                decision,
+                key.to_path_buf(),
+                HashAlgorithm::Sha384,
+            )
+        };
+
+        // Repeated approvals by the same reviewer and approvals in other roles don't count
+        approve(&alice_key, "reviewer", ApprovalDecision::Approved)?;
+        approve(&alice_key, "reviewer", ApprovalDecision::Approved)?;
+        approve(&bob_key, "security", ApprovalDecision::Approved)?;
+        let approved = storage.retrieve_manifest("model")?;
+        assert!(approved.claim.signature.is_some());
+        assert!(check_approvals(&approved, &policy).is_err());
+
+        approve(&bob_key, "reviewer", ApprovalDecision::Approved)?;
+        assert_eq!(
+            check_approvals(&storage.retrieve_manifest("model")?, &policy)?,
+            2
+        );
+
+        // Changing the reviewed content invalidates earlier approvals
+        let mut modified = storage.retrieve_manifest("model")?;
+        modified.title = "Modified Model".to_string();
+        assert!(check_approvals(&modified, &policy).is_err());
+
+        // A rejection blocks the release even with enough approvals
+        approve(&bob_key, "reviewer", ApprovalDecision::Rejected)?;
+        assert!(check_approvals(&storage.retrieve_manifest("model")?, &policy).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,261 @@
+//! Archive members as manifest ingredients.
+//!
+//! With `--expand-archives`, tar (optionally gzip-compressed) and zip archives are
+//! streamed rather than extracted, and every regular file in them is hashed and recorded
+//! as its own ingredient. Members are addressed by URLs of the form
+//! `file:///path/to/dataset.tar.gz!/member/path`, which verification uses to re-hash
+//! the member from the archive.
+
+use crate::error::{Error, Result};
+use crate::hash;
+use crate::utils::{file_url_to_path, path_to_file_url, relative_url_to_path, safe_open_file};
+use atlas_c2pa_lib::cose::HashAlgorithm;
+use flate2::read::GzDecoder;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Separator between the archive path and the member path in member URLs
+pub const MEMBER_SEPARATOR: &str = "!/";
+
+/// Supported archive formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Tar,
+    TarGz,
+    Zip,
+}
+
+impl ArchiveFormat {
+    /// Detects the archive format from the file name
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use atlas_cli::manifest::archive::ArchiveFormat;
+    /// use std::path::Path;
+    ///
+    /// assert_eq!(ArchiveFormat::detect(Path::new("data.tar.gz")), Some(ArchiveFormat::TarGz));
+    /// assert_eq!(ArchiveFormat::detect(Path::new("data.zip")), Some(ArchiveFormat::Zip));
+    /// assert_eq!(ArchiveFormat::detect(Path::new("data.csv")), None);
+    /// ```
+    pub fn detect(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+
+        if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(ArchiveFormat::TarGz)
+        } else if name.ends_with(".tar") {
+            Some(ArchiveFormat::Tar)
+        } else if name.ends_with(".zip") {
+            Some(ArchiveFormat::Zip)
+        } else {
+            None
+        }
+    }
+}
+
+/// A regular file inside an archive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveMember {
+    /// Path of the member inside the archive
+    pub path: String,
+    /// Uncompressed size in bytes
+    pub size: u64,
+    pub hash: String,
+}
+
+/// Hashes every regular file in an archive, in archive order
+pub fn hash_members(
+    archive: &Path,
+    format: ArchiveFormat,
+    algorithm: &HashAlgorithm,
+) -> Result<Vec<ArchiveMember>> {
+    let mut members = Vec::new();
+    for_each_member(archive, format, |path, size, reader| {
+        members.push(ArchiveMember {
+            path: path.to_string(),
+            size,
+            hash: hash::calculate_reader_hash_with_algorithm(reader, algorithm)?,
+        });
+        Ok(true)
+    })?;
+
+    Ok(members)
+}
+
+/// Hashes a single member of an archive, returning `None` if the archive has no such
+/// member
+pub fn hash_member(
+    archive: &Path,
+    member: &str,
+    algorithm: &HashAlgorithm,
+) -> Result<Option<ArchiveMember>> {
+    let format = ArchiveFormat::detect(archive).ok_or_else(|| {
+        Error::Validation(format!("Unsupported archive format: {}", archive.display()))
+    })?;
+
+    let mut found = None;
+    for_each_member(archive, format, |path, size, reader| {
+        if path != member {
+            return Ok(true);
+        }
+        found = Some(ArchiveMember {
+            path: path.to_string(),
+            size,
+            hash: hash::calculate_reader_hash_with_algorithm(reader, algorithm)?,
+        });
+        Ok(false)
+    })?;
+
+    Ok(found)
+}
+
+/// URL of an archive member
+///
+/// # Examples
+///
+/// ```
+/// use atlas_cli::manifest::archive::{member_url, parse_member_url};
+/// use std::path::Path;
+///
+/// let url = member_url(Path::new("/data/images.tar"), "train/0001.png");
+/// assert_eq!(url, "file:///data/images.tar!/train/0001.png");
+///
+/// let (archive, member) = parse_member_url(&url).unwrap();
+/// assert_eq!(archive, Path::new("/data/images.tar"));
+/// assert_eq!(member, "train/0001.png");
+/// ```
+pub fn member_url(archive: &Path, member: &str) -> String {
+    format!("{}{MEMBER_SEPARATOR}{member}", path_to_file_url(archive))
+}
+
+/// Splits an archive member URL into the archive path and the member path. Archives
+/// recorded relative to a path base yield a relative path.
+pub fn parse_member_url(url: &str) -> Option<(PathBuf, String)> {
+    let (archive, member) = url.split_once(MEMBER_SEPARATOR)?;
+    let archive = file_url_to_path(archive).or_else(|| relative_url_to_path(archive))?;
+
+    ArchiveFormat::detect(&archive).map(|_| (archive, member.to_string()))
+}
+
+// Streams the regular files of an archive to `f` until it returns false
+fn for_each_member(
+    archive: &Path,
+    format: ArchiveFormat,
+    mut f: impl FnMut(&str, u64, &mut dyn Read) -> Result<bool>,
+) -> Result<()> {
+    let file = safe_open_file(archive, false)?;
+    let archive_error = |e: &dyn std::fmt::Display| {
+        Error::Validation(format!("Failed to read archive {}: {e}", archive.display()))
+    };
+
+    match format {
+        ArchiveFormat::Tar => for_each_tar_member(tar::Archive::new(file), &mut f, archive_error),
+        ArchiveFormat::TarGz => for_each_tar_member(
+            tar::Archive::new(GzDecoder::new(file)),
+            &mut f,
+            archive_error,
+        ),
+        ArchiveFormat::Zip => {
+            let mut zip = zip::ZipArchive::new(file).map_err(|e| archive_error(&e))?;
+            for index in 0..zip.len() {
+                let mut entry = zip.by_index(index).map_err(|e| archive_error(&e))?;
+                if !entry.is_file() {
+                    continue;
+                }
+
+                let name = entry.name().to_string();
+                let size = entry.size();
+                if !f(&name, size, &mut entry)? {
+                    break;
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+fn for_each_tar_member<R: Read>(
+    mut archive: tar::Archive<R>,
+    f: &mut impl FnMut(&str, u64, &mut dyn Read) -> Result<bool>,
+    archive_error: impl Fn(&dyn std::fmt::Display) -> Error,
+) -> Result<()> {
+    for entry in archive.entries().map_err(|e| archive_error(&e))? {
+        let mut entry = entry.map_err(|e| archive_error(&e))?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+
+        let path = entry
+            .path()
+            .map_err(|e| archive_error(&e))?
+            .to_string_lossy()
+            .into_owned();
+        let size = entry.size();
+        if !f(&path, size, &mut entry)? {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    const FILES: [(&str, &[u8]); 2] = [("a.csv", b"x,y\n1,2\n"), ("nested/b.json", b"{}")];
+
+    #[test]
+    fn test_tar_gz_and_zip_members() -> Result<()> {
+        let dir = tempdir()?;
+
+        let tar_path = dir.path().join("data.tar.gz");
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            File::create(&tar_path)?,
+            flate2::Compression::default(),
+        ));
+        for (name, data) in FILES {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, name, data)?;
+        }
+        builder.into_inner()?.finish()?;
+
+        let zip_path = dir.path().join("data.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&zip_path)?);
+        zip.add_directory("nested/", zip::write::SimpleFileOptions::default())
+            .unwrap();
+        for (name, data) in FILES {
+            zip.start_file(name, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            zip.write_all(data)?;
+        }
+        zip.finish().unwrap();
+
+        for path in [&tar_path, &zip_path] {
+            let format = ArchiveFormat::detect(path).unwrap();
+            let members = hash_members(path, format, &HashAlgorithm::Sha256)?;
+
+            let expected: Vec<ArchiveMember> = FILES
+                .iter()
+                .map(|(name, data)| ArchiveMember {
+                    path: name.to_string(),
+                    size: data.len() as u64,
+                    hash: hash::calculate_hash_with_algorithm(data, &HashAlgorithm::Sha256),
+                })
+                .collect();
+            assert_eq!(members, expected);
+
+            let member = hash_member(path, "nested/b.json", &HashAlgorithm::Sha256)?;
+            assert_eq!(member.as_ref(), expected.get(1));
+            assert!(hash_member(path, "missing", &HashAlgorithm::Sha256)?.is_none());
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,897 @@
+//! in-toto attestations and their DSSE envelopes.
+//!
+//! OMS manifests, SLSA build provenance and statements created with `attestation create`
+//! are in-toto statements signed into DSSE envelopes. Storage backends that support it
+//! keep them as [`EnvelopeRecord`]s, so they can be listed, exported and verified by ID
+//! like manifests, and attached to the manifests they concern. Verification checks the
+//! envelope's signature with the signer's public key and re-hashes the local artifacts
+//! named by the statement.
+
+use crate::cli::color;
+use crate::error::{Error, Result};
+use crate::hash;
+use crate::http;
+use crate::in_toto::{self, DSSE_PAYLOAD_TYPE, dsse::Envelope};
+use crate::manifest::claims;
+use crate::manifest::common::{OMS_PREDICATE_TYPE, check_oms_envelope};
+use crate::manifest::output::write_encoded;
+use crate::manifest::verification::{Check, CheckKind, Outcome, VerificationEngine};
+use crate::signing::{self, signable::Signable};
+use crate::slsa::BUILD_PROVENANCE_PREDICATE_TYPE_V1;
+use crate::storage::traits::{
+    EnvelopeKind, EnvelopeMetadata, EnvelopeRecord, ManifestType, StorageBackend,
+};
+use crate::utils::{file_url_to_path, relative_url_to_path, safe_create_file, safe_open_file};
+use atlas_c2pa_lib::assertion::{Assertion, CustomAssertion};
+use atlas_c2pa_lib::cose::HashAlgorithm;
+use atlas_c2pa_lib::cross_reference::CrossReference;
+use atlas_c2pa_lib::ingredient::Ingredient;
+use atlas_c2pa_lib::manifest::Manifest;
+use in_toto_attestation::v1::resource_descriptor::ResourceDescriptor;
+use openssl::pkey::{PKey, Public};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+
+/// Label of the assertion recording an attestation attached to a manifest
+pub const ATTESTATION_ASSERTION_LABEL: &str = "attestation";
+
+/// Media type of statement subjects that are stored manifests, named by their ID
+pub const MANIFEST_SUBJECT_MEDIA_TYPE: &str = "application/vnd.atlas.manifest+json";
+
+/// Media type of cross-references to stored envelopes rather than manifests
+pub const ENVELOPE_MEDIA_TYPE: &str = "application/vnd.dsse.envelope.v1+json";
+
+/// Reference from a manifest to a stored attestation envelope
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttachedAttestation {
+    pub envelope_id: String,
+    pub predicate_type: String,
+    /// SHA-256 of the envelope as stored, in JSON
+    pub envelope_hash: String,
+}
+
+/// Configuration of `attestation create`
+pub struct AttestationConfig {
+    /// Files the statement is about
+    pub subjects: Vec<PathBuf>,
+    /// IDs of stored manifests the statement is about
+    pub subject_manifests: Vec<String>,
+    pub predicate_type: String,
+    /// JSON or YAML file with the predicate; the predicate is empty if not given
+    pub predicate_path: Option<PathBuf>,
+    /// Name of the stored record; defaults to the name of the first subject
+    pub name: Option<String>,
+    /// Signing key; without one, the statement is written unsigned and not stored
+    pub key_path: Option<PathBuf>,
+    pub pq_key_path: Option<PathBuf>,
+    pub hash_alg: HashAlgorithm,
+    pub output_encoding: String,
+    pub output_path: Option<PathBuf>,
+    pub print: bool,
+    pub storage: Option<&'static dyn StorageBackend>,
+}
+
+/// Kind of the stored record of a statement with the given predicate type
+pub fn envelope_kind(predicate_type: &str) -> EnvelopeKind {
+    match predicate_type {
+        OMS_PREDICATE_TYPE => EnvelopeKind::Oms,
+        BUILD_PROVENANCE_PREDICATE_TYPE_V1 => EnvelopeKind::Slsa,
+        _ => EnvelopeKind::Custom,
+    }
+}
+
+/// Reads a predicate from a JSON or YAML file; the predicate must be an object
+pub fn read_predicate(path: &Path) -> Result<serde_json::Value> {
+    let contents = fs::read_to_string(path)?;
+    let predicate: serde_json::Value = serde_yaml::from_str(&contents).map_err(|e| {
+        Error::Validation(format!("Invalid predicate file {}: {e}", path.display()))
+    })?;
+    if !predicate.is_object() {
+        return Err(Error::Validation(format!(
+            "Predicate in {} must be an object",
+            path.display()
+        )));
+    }
+
+    Ok(predicate)
+}
+
+/// Checks that a predicate type is an absolute URI, as in-toto requires
+pub fn validate_predicate_type(predicate_type: &str) -> Result<()> {
+    reqwest::Url::parse(predicate_type)
+        .map(|_| ())
+        .map_err(|e| {
+            Error::Validation(format!(
+                "Predicate type '{predicate_type}' is not an absolute URI: {e}"
+            ))
+        })
+}
+
+/// Reads a DSSE envelope from a JSON or binary CBOR file
+pub fn read_envelope(path: &Path) -> Result<Envelope> {
+    let bytes = fs::read(path)?;
+    serde_json::from_slice(&bytes)
+        .or_else(|_| serde_cbor::from_slice(&bytes))
+        .map_err(|e| Error::Validation(format!("{} is not a DSSE envelope: {e}", path.display())))
+}
+
+/// Returns the envelope with its payload: the payload of a detached envelope is fetched and
+/// checked against its digest, before any signature is checked with it.
+///
+/// The payload is fetched from an HTTP(S) or `file:` URL, or a path, with relative paths
+/// resolved against `base`.
+///
+/// # Errors
+///
+/// Returns an error if the payload can't be fetched, and a `Validation` error if it doesn't
+/// match its digest.
+pub fn resolve_payload<'a>(
+    envelope: &'a Envelope,
+    base: Option<&Path>,
+) -> Result<Cow<'a, Envelope>> {
+    let Some(payload_ref) = envelope.payload_ref() else {
+        return Ok(Cow::Borrowed(envelope));
+    };
+    let uri = payload_ref.uri.as_str();
+
+    let payload = if uri.starts_with("http://") || uri.starts_with("https://") {
+        let context = format!("Failed to fetch detached payload {uri}");
+        http::client_builder()
+            .build()
+            .and_then(|client| client.get(uri).send())
+            .and_then(|response| response.error_for_status())
+            .and_then(|response| response.bytes())
+            .map_err(|e| Error::Storage(format!("{context}: {e}")))?
+            .to_vec()
+    } else {
+        let path = match (file_url_to_path(uri), relative_url_to_path(uri)) {
+            (Some(path), _) => path,
+            (None, Some(path)) => match base {
+                Some(base) => base.join(path),
+                None => path,
+            },
+            (None, None) if uri.contains("://") => {
+                return Err(Error::Validation(format!(
+                    "Unsupported URL {uri} of a detached payload; use an HTTP(S) or file: URL"
+                )));
+            }
+            (None, None) => PathBuf::from(uri),
+        };
+        fs::read(&path).map_err(|e| {
+            Error::Validation(format!(
+                "Failed to read detached payload {}: {e}",
+                path.display()
+            ))
+        })?
+    };
+
+    let mut envelope = envelope.clone();
+    envelope.attach(payload)?;
+    Ok(Cow::Owned(envelope))
+}
+
+/// Detaches the payload of the envelope at `input`, see [`Envelope::detach`]. The payload
+/// is written to `payload_output`, to be published at `uri`, and the envelope to `output`.
+pub fn detach_payload(
+    input: &Path,
+    uri: String,
+    payload_output: &Path,
+    hash_alg: &HashAlgorithm,
+    encoding: &str,
+    output: &Path,
+) -> Result<()> {
+    let mut envelope = read_envelope(input)?;
+    let payload = envelope.detach(uri.clone(), hash_alg)?;
+    safe_create_file(payload_output, false)?.write_all(&payload)?;
+    println!(
+        "Payload ({} bytes) written to {}; publish it at {uri}",
+        payload.len(),
+        payload_output.display()
+    );
+    write_encoded(&envelope, encoding, Some(output))
+}
+
+/// Signs the file at `payload` as the detached payload of a new envelope referring to `uri`,
+/// see [`Envelope::sign_detached`], and writes the envelope to `output`. The file is read a
+/// chunk at a time, so it is never held in memory.
+pub fn sign_detached_payload(
+    payload: &Path,
+    payload_type: String,
+    uri: String,
+    key_path: &Path,
+    hash_alg: &HashAlgorithm,
+    encoding: &str,
+    output: &Path,
+) -> Result<()> {
+    let private_key = signing::load_private_key(key_path)?;
+    let mut file = safe_open_file(payload, false)?;
+    let envelope =
+        Envelope::sign_detached(&mut file, payload_type, uri.clone(), &private_key, hash_alg)?;
+    println!(
+        "Signed {} as a detached payload; publish it at {uri}",
+        payload.display()
+    );
+    write_encoded(&envelope, encoding, Some(output))
+}
+
+/// Reads a JSON Lines bundle: one JSON DSSE envelope per line, blank lines skipped
+pub fn read_bundle(path: &Path) -> Result<Vec<Envelope>> {
+    let contents = fs::read_to_string(path)?;
+
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(number, line)| {
+            serde_json::from_str(line).map_err(|e| {
+                Error::Validation(format!(
+                    "Line {} of {} is not a DSSE envelope: {e}",
+                    number + 1,
+                    path.display()
+                ))
+            })
+        })
+        .collect()
+}
+
+/// Writes envelopes as a JSON Lines bundle, to `output` or to stdout
+pub fn write_bundle(envelopes: &[Envelope], output: Option<&Path>) -> Result<()> {
+    let mut bundle = String::new();
+    for envelope in envelopes {
+        bundle.push_str(&serde_json::to_string(envelope)?);
+        bundle.push('\n');
+    }
+
+    match output {
+        Some(path) => {
+            safe_create_file(path, false)?.write_all(bundle.as_bytes())?;
+            println!(
+                "Bundle of {} envelopes written to {}",
+                envelopes.len(),
+                path.display()
+            );
+        }
+        None => print!("{bundle}"),
+    }
+    Ok(())
+}
+
+/// Hash of a manifest as a statement subject: without attestation assertions and
+/// signatures, so attaching an attestation to the manifest it covers keeps it valid
+pub fn manifest_subject_hash(manifest: &Manifest, hash_alg: &HashAlgorithm) -> Result<String> {
+    let is_attestation = |assertion: &Assertion| matches!(assertion, Assertion::CustomAssertion(custom) if custom.label == ATTESTATION_ASSERTION_LABEL);
+
+    let mut subject = manifest.clone();
+    subject
+        .claim
+        .created_assertions
+        .retain(|a| !is_attestation(a));
+    subject.claim.signature = None;
+    if let Some(claim) = subject.claim_v2.as_mut() {
+        claim.created_assertions.retain(|a| !is_attestation(a));
+        claim.signature = None;
+    }
+
+    let json = serde_json::to_string(&subject)?;
+    Ok(hash::calculate_hash_with_algorithm(
+        json.as_bytes(),
+        hash_alg,
+    ))
+}
+
+/// Resource descriptor of a stored manifest, see [`manifest_subject_hash`]
+pub fn generate_manifest_resource_descriptor(
+    manifest_id: &str,
+    storage: &dyn StorageBackend,
+    hash_alg: &HashAlgorithm,
+) -> Result<ResourceDescriptor> {
+    let manifest = storage.retrieve_manifest(manifest_id)?;
+    let digest = manifest_subject_hash(&manifest, hash_alg)?;
+
+    let mut rd = in_toto::make_minimal_resource_descriptor(manifest_id, hash_alg.as_str(), &digest);
+    rd.media_type = MANIFEST_SUBJECT_MEDIA_TYPE.to_string();
+    Ok(rd)
+}
+
+/// Creates an in-toto statement about local files and stored manifests, signed into a
+/// DSSE envelope if a key is given.
+///
+/// Signed envelopes are written out and stored like manifests: written if printing, if
+/// no storage is given or if an output path is set, and stored unless printing.
+pub fn create_attestation(config: AttestationConfig) -> Result<()> {
+    if config.subjects.is_empty() && config.subject_manifests.is_empty() {
+        return Err(Error::Validation(
+            "An attestation needs at least one subject".to_string(),
+        ));
+    }
+    validate_predicate_type(&config.predicate_type)?;
+
+    let mut subjects = config
+        .subjects
+        .iter()
+        .map(|path| in_toto::generate_file_resource_descriptor_from_path(path, &config.hash_alg))
+        .collect::<Result<Vec<_>>>()?;
+    if !config.subject_manifests.is_empty() {
+        let storage = config.storage.ok_or_else(|| {
+            Error::Validation("Manifest subjects need a storage backend".to_string())
+        })?;
+        for manifest_id in &config.subject_manifests {
+            subjects.push(generate_manifest_resource_descriptor(
+                manifest_id,
+                storage,
+                &config.hash_alg,
+            )?);
+        }
+    }
+    let predicate = match &config.predicate_path {
+        Some(path) => read_predicate(path)?,
+        None => serde_json::Value::Object(serde_json::Map::new()),
+    };
+    let statement = in_toto::generate_statement_json_v1_with_value(
+        &subjects,
+        &config.predicate_type,
+        &predicate,
+    )?;
+
+    let Some(key_path) = config.key_path else {
+        let statement: serde_json::Value = serde_json::from_str(&statement)?;
+        write_encoded(
+            &statement,
+            &config.output_encoding,
+            config.output_path.as_deref(),
+        )?;
+        println!("The statement is unsigned; sign it with `attestation sign` to store it");
+        return Ok(());
+    };
+
+    let mut envelope = Envelope::new(&statement.into_bytes(), DSSE_PAYLOAD_TYPE.to_string());
+    envelope.sign(key_path, config.hash_alg)?;
+    if let Some(pq_key_path) = &config.pq_key_path {
+        envelope.add_pq_signature(pq_key_path)?;
+    }
+
+    let name = config
+        .name
+        .unwrap_or_else(|| match config.subjects.first() {
+            Some(first) => first
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| first.display().to_string()),
+            None => config.subject_manifests[0].clone(),
+        });
+    emit_envelope(
+        envelope,
+        &name,
+        &config.output_encoding,
+        config.output_path.as_deref(),
+        config.print,
+        config.storage,
+    )
+}
+
+/// Signs a statement or an envelope read from `input`.
+///
+/// A statement (as written by `attestation create` without a key) is wrapped in a new
+/// envelope; an envelope gets another signature, so several parties can co-sign it.
+pub fn sign_attestation(
+    input: &Path,
+    key_path: PathBuf,
+    pq_key_path: Option<&Path>,
+    hash_alg: HashAlgorithm,
+) -> Result<Envelope> {
+    let mut envelope = match read_envelope(input) {
+        Ok(envelope) => envelope,
+        Err(_) => {
+            let bytes = fs::read(input)?;
+            serde_json::from_slice::<in_toto::Statement>(&bytes).map_err(|e| {
+                Error::Validation(format!(
+                    "{} is neither a DSSE envelope nor an in-toto statement: {e}",
+                    input.display()
+                ))
+            })?;
+            Envelope::new(&bytes, DSSE_PAYLOAD_TYPE.to_string())
+        }
+    };
+
+    envelope.sign(key_path, hash_alg)?;
+    if let Some(pq_key_path) = pq_key_path {
+        envelope.add_pq_signature(pq_key_path)?;
+    }
+    Ok(envelope)
+}
+
+/// Writes out and stores a signed envelope, see [`create_attestation`]
+pub fn emit_envelope(
+    envelope: Envelope,
+    name: &str,
+    encoding: &str,
+    output_path: Option<&Path>,
+    print: bool,
+    storage: Option<&'static dyn StorageBackend>,
+) -> Result<()> {
+    if print || storage.is_none() || output_path.is_some() {
+        write_encoded(&envelope, encoding, output_path)?;
+    }
+
+    if let Some(storage) = storage
+        && !print
+    {
+        let statement = in_toto::read_statement(&envelope)?;
+        let record = EnvelopeRecord::new(
+            envelope_kind(&statement.predicate_type),
+            name,
+            None,
+            envelope,
+        );
+        let id = storage.store_envelope(&record)?;
+        println!("Attestation stored successfully with ID: {id}");
+    }
+
+    Ok(())
+}
+
+/// Renders a list of stored envelopes as text, one per line, or as JSON
+pub fn render_envelope_list(envelopes: &[EnvelopeMetadata], json: bool) -> Result<String> {
+    if json {
+        return serde_json::to_string_pretty(envelopes)
+            .map_err(|e| Error::Serialization(e.to_string()));
+    }
+
+    let mut out = String::new();
+    for envelope in envelopes {
+        let _ = write!(
+            out,
+            "{} ({}): {} [{}]",
+            envelope.id, envelope.kind, envelope.name, envelope.created_at
+        );
+        if let Some(manifest_id) = &envelope.manifest_id {
+            let _ = write!(out, " manifest: {manifest_id}");
+        }
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Lists the envelopes of a storage backend, oldest first
+pub fn list_envelopes(storage: &dyn StorageBackend, json: bool) -> Result<()> {
+    let mut envelopes = storage.list_envelopes()?;
+    envelopes.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+
+    if envelopes.is_empty() && !json {
+        println!("No envelopes found");
+        return Ok(());
+    }
+    print!("{}", render_envelope_list(&envelopes, json)?);
+    Ok(())
+}
+
+/// Verifies a stored envelope, see [`verify_attestation`]
+pub fn verify_envelope(
+    id: &str,
+    storage: &dyn StorageBackend,
+    public_key: &PKey<Public>,
+    artifact_root: Option<&Path>,
+) -> Result<()> {
+    let record = storage.retrieve_envelope(id)?;
+    println!("Verifying {} envelope with ID: {id}", record.kind);
+
+    verify_attestation(&record.envelope, public_key, artifact_root, Some(storage))
+}
+
+/// Verifies a DSSE envelope: its signature with `public_key`, and the local artifacts it
+/// names, resolving relative paths against `artifact_root`.
+///
+/// OMS manifests are verified with [`crate::manifest::common::verify_oms_envelope`]. For other statements, each
+/// subject names a file that must match its digests, or a manifest in `storage` whose
+/// [`manifest_subject_hash`] must match them.
+///
+/// # Errors
+///
+/// Returns a `Validation` error if the envelope doesn't hold an in-toto statement, no
+/// signature verifies with the key, or a subject is missing or doesn't match its digests.
+pub fn verify_attestation(
+    envelope: &Envelope,
+    public_key: &PKey<Public>,
+    artifact_root: Option<&Path>,
+    storage: Option<&dyn StorageBackend>,
+) -> Result<()> {
+    for message in check_attestation(envelope, public_key, artifact_root, storage)? {
+        println!("{message}");
+    }
+    Ok(())
+}
+
+// Verifies a DSSE envelope like `verify_attestation`, returning the progress messages
+// instead of printing them
+fn check_attestation(
+    envelope: &Envelope,
+    public_key: &PKey<Public>,
+    artifact_root: Option<&Path>,
+    storage: Option<&dyn StorageBackend>,
+) -> Result<Vec<String>> {
+    let detached = envelope
+        .payload_ref()
+        .map(|payload_ref| payload_ref.uri.clone());
+    let resolved = resolve_payload(envelope, artifact_root)?;
+    let envelope = resolved.as_ref();
+    let statement = in_toto::read_statement(envelope)?;
+    let mut messages = Vec::new();
+    if let Some(uri) = detached {
+        messages.push(format!(
+            "{} Detached payload fetched from {uri} and matches its digest",
+            color::check_mark()
+        ));
+    }
+    if statement.predicate_type == OMS_PREDICATE_TYPE {
+        messages.extend(check_oms_envelope(envelope, public_key, artifact_root)?);
+        return Ok(messages);
+    }
+
+    // Subjects are hashed with the algorithm the envelope was signed with
+    let alg = statement
+        .subject
+        .first()
+        .and_then(|subject| subject.digest.keys().next())
+        .ok_or_else(|| Error::Validation("Statement has no subject digest".to_string()))?;
+    let hash_alg = hash::parse_algorithm(alg)?;
+
+    if !envelope.verify_signature(public_key, &hash_alg)? {
+        return Err(Error::Validation(
+            "DSSE signature verification failed: no signature matches the public key".to_string(),
+        ));
+    }
+    messages.push(format!("{} DSSE signature verified", color::check_mark()));
+
+    for subject in &statement.subject {
+        if subject.media_type == MANIFEST_SUBJECT_MEDIA_TYPE {
+            messages.push(verify_manifest_subject(subject, storage)?);
+            continue;
+        }
+
+        let path = match artifact_root {
+            Some(root) if Path::new(&subject.name).is_relative() => root.join(&subject.name),
+            _ => PathBuf::from(&subject.name),
+        };
+
+        for (alg, digest) in &subject.digest {
+            let calculated = hash::parse_algorithm(alg)
+                .and_then(|alg| hash::calculate_file_hash_with_algorithm(&path, &alg))
+                .map_err(|e| {
+                    Error::Validation(format!(
+                        "Error verifying subject {}: {e}. The file may be missing or inaccessible.",
+                        subject.name
+                    ))
+                })?;
+            if &calculated != digest {
+                return Err(Error::Validation(format!(
+                    "Hash verification failed for subject: {}. The file may have been modified.",
+                    subject.name
+                )));
+            }
+        }
+        messages.push(format!(
+            "{} Successfully verified hash for subject: {}",
+            color::check_mark(),
+            subject.name
+        ));
+    }
+
+    messages.push(format!(
+        "{} Attestation verification successful",
+        color::check_mark()
+    ));
+    Ok(messages)
+}
+
+fn verify_manifest_subject(
+    subject: &in_toto::StatementSubject,
+    storage: Option<&dyn StorageBackend>,
+) -> Result<String> {
+    let storage = storage.ok_or_else(|| {
+        Error::Validation(format!(
+            "Subject {} is a manifest; verifying it needs a storage backend",
+            subject.name
+        ))
+    })?;
+    let manifest = storage.retrieve_manifest(&subject.name)?;
+
+    for (alg, digest) in &subject.digest {
+        let calculated = manifest_subject_hash(&manifest, &hash::parse_algorithm(alg)?)?;
+        if &calculated != digest {
+            return Err(Error::Validation(format!(
+                "Hash verification failed for manifest subject: {}. The manifest has changed since it was attested.",
+                subject.name
+            )));
+        }
+    }
+    Ok(format!(
+        "{} Successfully verified hash for manifest subject: {}",
+        color::check_mark(),
+        subject.name
+    ))
+}
+
+/// Verification result of one statement of a bundle
+#[derive(Debug, Clone, Serialize)]
+pub struct BundleEntryResult {
+    /// Line of the envelope in the bundle, counting from 1
+    pub line: usize,
+    pub predicate_type: Option<String>,
+    pub subjects: Vec<String>,
+    /// Why verification failed; `None` if it succeeded
+    pub error: Option<String>,
+}
+
+/// Verifies every envelope of a JSON Lines bundle, see [`verify_attestation`].
+///
+/// All envelopes are verified, concurrently, and the results reported per statement in
+/// bundle order, even after one fails. Returns an error if any of them failed.
+pub fn verify_bundle(
+    path: &Path,
+    public_key: &PKey<Public>,
+    artifact_root: Option<&Path>,
+    storage: Option<&dyn StorageBackend>,
+) -> Result<Vec<BundleEntryResult>> {
+    let contents = fs::read_to_string(path)?;
+    let lines: Vec<(usize, &str)> = contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(number, line)| (number + 1, line))
+        .collect();
+    if lines.is_empty() {
+        return Err(Error::Validation(format!(
+            "Bundle {} holds no envelopes",
+            path.display()
+        )));
+    }
+
+    let checks = lines
+        .iter()
+        .map(|(number, line)| {
+            Check::new(CheckKind::Signature, format!("line {number}"), move || {
+                let envelope = serde_json::from_str::<Envelope>(line)?;
+                check_attestation(&envelope, public_key, artifact_root, storage)
+                    .map(|messages| messages.join("\n"))
+            })
+        })
+        .collect();
+    let outcomes = VerificationEngine::new().run(checks);
+
+    let mut results = Vec::new();
+    for ((number, line), outcome) in lines.iter().zip(outcomes) {
+        println!("Verifying statement on line {number}...");
+        let error = match outcome.outcome {
+            Outcome::Passed(messages) => {
+                println!("{messages}");
+                None
+            }
+            Outcome::Failed(e) => Some(e.to_string()),
+            Outcome::Cancelled => Some("Verification was cancelled".to_string()),
+        };
+        if let Some(error) = &error {
+            println!("{} {error}", color::cross_mark());
+        }
+
+        let statement = serde_json::from_str::<Envelope>(line)
+            .ok()
+            .and_then(|envelope| in_toto::read_statement(&envelope).ok());
+        results.push(BundleEntryResult {
+            line: *number,
+            predicate_type: statement.as_ref().map(|s| s.predicate_type.clone()),
+            subjects: statement
+                .map(|s| s.subject.into_iter().map(|subject| subject.name).collect())
+                .unwrap_or_default(),
+            error,
+        });
+    }
+    Ok(results)
+}
+
+/// Renders bundle verification results, one line per statement
+pub fn render_bundle_results(results: &[BundleEntryResult]) -> String {
+    let mut out = String::new();
+    for result in results {
+        let mark = match result.error {
+            None => color::check_mark(),
+            Some(_) => color::cross_mark(),
+        };
+        let _ = write!(
+            out,
+            "{mark} line {}: {} [{}]",
+            result.line,
+            result
+                .predicate_type
+                .as_deref()
+                .unwrap_or("unreadable statement"),
+            result.subjects.join(", ")
+        );
+        if let Some(error) = &result.error {
+            let _ = write!(out, ": {error}");
+        }
+        out.push('\n');
+    }
+
+    let failed = results.iter().filter(|r| r.error.is_some()).count();
+    let _ = writeln!(
+        out,
+        "{} of {} statements verified",
+        results.len() - failed,
+        results.len()
+    );
+    out
+}
+
+/// Hash of an envelope as stored, in JSON
+pub fn envelope_hash(envelope: &Envelope, hash_alg: &HashAlgorithm) -> Result<String> {
+    let json = serde_json::to_string(envelope)?;
+    Ok(hash::calculate_hash_with_algorithm(
+        json.as_bytes(),
+        hash_alg,
+    ))
+}
+
+/// Whether a cross-reference points to a stored envelope, see [`link_envelope`]
+pub fn is_envelope_reference(cross_ref: &CrossReference) -> bool {
+    cross_ref.media_type.as_deref() == Some(ENVELOPE_MEDIA_TYPE)
+}
+
+/// Checks a cross-reference to a stored envelope against the envelope's current hash,
+/// returning the envelope record
+pub fn verify_envelope_reference(
+    cross_ref: &CrossReference,
+    storage: &(impl StorageBackend + ?Sized),
+) -> Result<EnvelopeRecord> {
+    let record = storage.retrieve_envelope(&cross_ref.manifest_url)?;
+    let algorithm = hash::detect_hash_algorithm(&cross_ref.manifest_hash);
+    let calculated = envelope_hash(&record.envelope, &algorithm)?;
+
+    if calculated != cross_ref.manifest_hash {
+        return Err(Error::Validation(format!(
+            "Hash mismatch for envelope {}: stored={}, calculated={calculated}",
+            cross_ref.manifest_url, cross_ref.manifest_hash
+        )));
+    }
+    Ok(record)
+}
+
+/// Finds the stored model and software manifests with an ingredient matching a subject
+/// of the statement, such as the products named in SLSA provenance
+pub fn find_subject_manifests(
+    statement: &in_toto::Statement,
+    storage: &dyn StorageBackend,
+) -> Result<Vec<String>> {
+    let digests: HashSet<&str> = statement
+        .subject
+        .iter()
+        .flat_map(|subject| subject.digest.values().map(String::as_str))
+        .collect();
+
+    let mut ids = Vec::new();
+    for metadata in storage.list_manifests()? {
+        if !matches!(
+            metadata.manifest_type,
+            ManifestType::Model | ManifestType::Software
+        ) {
+            continue;
+        }
+
+        let manifest = storage.retrieve_manifest(&metadata.id)?;
+        if manifest_ingredients(&manifest)
+            .any(|ingredient| digests.contains(ingredient.data.hash.as_str()))
+            && !ids.contains(&metadata.id)
+        {
+            ids.push(metadata.id);
+        }
+    }
+    Ok(ids)
+}
+
+// Ingredients of a manifest, whether recorded in the claim or next to it
+fn manifest_ingredients(manifest: &Manifest) -> impl Iterator<Item = &Ingredient> {
+    manifest
+        .ingredients
+        .iter()
+        .chain(manifest.claim.ingredients.iter())
+}
+
+/// Adds a cross-reference from a stored manifest to a stored envelope, returning the ID
+/// under which the updated manifest was stored.
+///
+/// Cross-references are not part of the signed claim, so the manifest isn't re-signed.
+/// The cross-reference is hashed with the manifest's ingredient algorithm.
+pub fn link_envelope(
+    manifest_id: &str,
+    record: &EnvelopeRecord,
+    storage: &dyn StorageBackend,
+) -> Result<String> {
+    let mut manifest = storage.retrieve_manifest(manifest_id)?;
+    let hash_alg = match manifest_ingredients(&manifest).next() {
+        Some(ingredient) => hash::parse_algorithm(&ingredient.data.alg)?,
+        None => HashAlgorithm::Sha384,
+    };
+
+    if manifest
+        .cross_references
+        .iter()
+        .any(|cross_ref| cross_ref.manifest_url == record.id)
+    {
+        return Ok(manifest_id.to_string());
+    }
+    manifest
+        .cross_references
+        .push(CrossReference::new_with_media_type(
+            record.id.clone(),
+            envelope_hash(&record.envelope, &hash_alg)?,
+            ENVELOPE_MEDIA_TYPE.to_string(),
+        ));
+
+    storage.store_manifest(&manifest)
+}
+
+/// Attaches a stored attestation to a stored manifest, returning the ID under which the
+/// updated manifest was stored.
+///
+/// The manifest records the envelope's ID, predicate type and hash in an attestation
+/// assertion. A signed manifest is re-signed with `key_path`, which is then required.
+pub fn attach_attestation(
+    manifest_id: &str,
+    envelope_id: &str,
+    storage: &dyn StorageBackend,
+    key_path: Option<PathBuf>,
+    hash_alg: HashAlgorithm,
+) -> Result<String> {
+    let mut manifest = storage.retrieve_manifest(manifest_id)?;
+    let record = storage.retrieve_envelope(envelope_id)?;
+    let statement = in_toto::read_statement(&record.envelope)?;
+
+    if claims::is_signed(&manifest) && key_path.is_none() {
+        return Err(Error::Validation(format!(
+            "Manifest {manifest_id} is signed; --key is required to re-sign it"
+        )));
+    }
+
+    let attached = AttachedAttestation {
+        envelope_id: record.id.clone(),
+        predicate_type: statement.predicate_type,
+        envelope_hash: envelope_hash(&record.envelope, &HashAlgorithm::Sha256)?,
+    };
+    let assertion = Assertion::CustomAssertion(CustomAssertion {
+        label: ATTESTATION_ASSERTION_LABEL.to_string(),
+        data: serde_json::to_value(&attached)?,
+    });
+    manifest.claim.created_assertions.push(assertion.clone());
+    manifest.claim.signature = None;
+    if let Some(claim_v2) = manifest.claim_v2.as_mut() {
+        claim_v2.created_assertions.push(assertion);
+    }
+
+    if let Some(key_path) = key_path {
+        manifest.sign(key_path, hash_alg)?;
+    }
+    storage.store_manifest(&manifest)
+}
+
+/// Returns the attestations attached to a manifest
+pub fn attached_attestations(manifest: &Manifest) -> Result<Vec<AttachedAttestation>> {
+    let assertions = &claims::signed_claim(manifest).created_assertions;
+
+    assertions
+        .iter()
+        .filter_map(|assertion| match assertion {
+            Assertion::CustomAssertion(custom) if custom.label == ATTESTATION_ASSERTION_LABEL => {
+                Some(
+                    serde_json::from_value(custom.data.clone()).map_err(|e| {
+                        Error::Manifest(format!("Invalid attestation assertion: {e}"))
+                    }),
+                )
+            }
+            _ => None,
+        })
+        .collect()
+}
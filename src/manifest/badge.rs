@@ -0,0 +1,183 @@
+//! Verification records and badges.
+//!
+//! After a manifest verifies, `verify --verification-record` issues a signed record of the
+//! verification: a Verifiable Credential naming the manifest and its subject digest, the
+//! verifier, the ID of the policy it was verified against and the time of verification,
+//! secured like the credentials of `manifest export-credential`. Consumers that trust the
+//! verifier can rely on the record instead of verifying the manifest again.
+//!
+//! `verify --badge` writes a status badge for model registry UIs and READMEs: an SVG image,
+//! or a JSON description in the format of shields.io endpoint badges.
+
+use crate::error::Result;
+use crate::manifest::attestation;
+use crate::manifest::credential::{self, VC_CONTEXT};
+use crate::signing;
+use atlas_c2pa_lib::cose::HashAlgorithm;
+use atlas_c2pa_lib::manifest::Manifest;
+use serde_json::{Value, json};
+use std::path::Path;
+use time::OffsetDateTime;
+
+/// Credential type of verification records, besides `VerifiableCredential`
+pub const VERIFICATION_CREDENTIAL_TYPE: &str = "AtlasVerificationCredential";
+
+/// Policy ID recorded when none is given
+pub const DEFAULT_POLICY_ID: &str = "atlas:verify";
+
+/// Label shown on the left of badges
+pub const BADGE_LABEL: &str = "provenance";
+
+const BADGE_MESSAGE: &str = "verified";
+const BADGE_COLOR: &str = "#4c1";
+
+/// Builds an unsecured record stating that `verifier` verified a manifest against the
+/// policy `policy_id` at `verified_at`
+pub fn verification_record(
+    manifest: &Manifest,
+    verifier: &str,
+    policy_id: &str,
+    hash_alg: &HashAlgorithm,
+    verified_at: OffsetDateTime,
+) -> Result<Value> {
+    credential::validate_did(verifier)?;
+    let verified_at = crate::manifest::timestamp::to_rfc3339_utc(verified_at);
+
+    Ok(json!({
+        "@context": [VC_CONTEXT],
+        "type": ["VerifiableCredential", VERIFICATION_CREDENTIAL_TYPE],
+        "issuer": verifier,
+        "validFrom": verified_at,
+        "credentialSubject": {
+            "id": manifest.instance_id,
+            "name": manifest.title,
+            "digest": {
+                "algorithm": hash_alg.as_str(),
+                "value": attestation::manifest_subject_hash(manifest, hash_alg)?,
+            },
+            "verification": {
+                "result": BADGE_MESSAGE,
+                "policy": policy_id,
+                "verifiedAt": verified_at,
+            },
+        },
+    }))
+}
+
+/// Issues a verification record of a verified manifest, signed with the key at `key_path`.
+///
+/// Without a `verifier`, the record is issued by the `did:key` of the signing key.
+pub fn issue_verification_record(
+    manifest: &Manifest,
+    key_path: &Path,
+    verifier: Option<String>,
+    policy_id: &str,
+    hash_alg: &HashAlgorithm,
+) -> Result<String> {
+    let private_key = signing::load_private_key(key_path)?;
+    let (verifier, kid) = credential::issuer_and_kid(&private_key, verifier)?;
+
+    let record = verification_record(
+        manifest,
+        &verifier,
+        policy_id,
+        hash_alg,
+        OffsetDateTime::now_utc(),
+    )?;
+    credential::sign_credential(&record, &private_key, &kid)
+}
+
+/// Badge of a verified manifest in the shields.io endpoint format
+pub fn badge_json() -> Value {
+    json!({
+        "schemaVersion": 1,
+        "label": BADGE_LABEL,
+        "message": BADGE_MESSAGE,
+        "color": BADGE_COLOR,
+    })
+}
+
+/// Badge of a verified manifest as a flat SVG image
+pub fn badge_svg() -> String {
+    render_svg(BADGE_LABEL, BADGE_MESSAGE, BADGE_COLOR)
+}
+
+// Approximates the width of text in 11px Verdana, as badges are sized without fonts
+fn text_width(text: &str) -> usize {
+    text.chars().count() * 7 + 10
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn render_svg(label: &str, message: &str, color: &str) -> String {
+    let label_width = text_width(label);
+    let message_width = text_width(message);
+    let width = label_width + message_width;
+    let (label, message) = (escape_xml(label), escape_xml(message));
+
+    format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="20" role="img" aria-label="{label}: {message}">
+  <title>{label}: {message}</title>
+  <rect width="{label_width}" height="20" fill="#555"/>
+  <rect x="{label_width}" width="{message_width}" height="20" fill="{color}"/>
+  <g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="11">
+    <text x="{}" y="14">{label}</text>
+    <text x="{}" y="14">{message}</text>
+  </g>
+</svg>
+"##,
+        label_width / 2,
+        label_width + message_width / 2
+    )
+}
+
+/// Writes the badge of a verified manifest: an SVG image if `path` ends in `.svg`, and
+/// otherwise JSON
+pub fn write_badge(path: &Path) -> Result<()> {
+    let is_svg = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("svg"));
+    let badge = if is_svg {
+        badge_svg()
+    } else {
+        format!("{}\n", serde_json::to_string_pretty(&badge_json())?)
+    };
+    std::fs::write(path, badge)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_badge_svg() {
+        let svg = render_svg("a<b", "ok", BADGE_COLOR);
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.contains(r#"width="55""#));
+        assert!(svg.contains("<text x=\"15\" y=\"14\">a&lt;b</text>"));
+        assert!(!svg.contains("a<b"));
+
+        assert_eq!(badge_json()["message"], "verified");
+        assert_eq!(badge_json()["schemaVersion"], 1);
+    }
+
+    #[test]
+    fn test_write_badge() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let svg = dir.path().join("badge.SVG");
+        write_badge(&svg)?;
+        assert!(std::fs::read_to_string(&svg)?.starts_with("<svg "));
+
+        let json = dir.path().join("badge.json");
+        write_badge(&json)?;
+        let badge: Value = serde_json::from_str(&std::fs::read_to_string(&json)?)?;
+        assert_eq!(badge, badge_json());
+        Ok(())
+    }
+}
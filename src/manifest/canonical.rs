@@ -0,0 +1,304 @@
+//! Deterministic CBOR encoding of signing inputs.
+//!
+//! Claim signatures are made over a CBOR encoding of the claim. The encoding produced by
+//! `serde_cbor` follows the declaration order of struct fields and the preferences of the
+//! serializer, so a new field order or a serde upgrade could change the bytes and break
+//! verification of existing signatures. Claims are therefore signed over their core
+//! deterministic encoding ([RFC 8949, section 4.2.1]):
+//!
+//! - integers, lengths and tags take their shortest form
+//! - arrays, maps and strings have definite lengths
+//! - map keys are sorted by the bytewise order of their encodings
+//! - floats take the shortest of half, single and double precision that keeps their value,
+//!   and NaN is encoded as `0xf97e00`
+//!
+//! The encoding depends only on the data model of the claim, not on how it is serialized.
+//! Manifests signed before claims were encoded deterministically keep verifying, see
+//! [`claim_signing_inputs`].
+//!
+//! [RFC 8949, section 4.2.1]: https://www.rfc-editor.org/rfc/rfc8949#section-4.2.1
+
+use crate::error::{Error, Result};
+use crate::manifest::redaction;
+use atlas_c2pa_lib::claim::ClaimV2;
+use serde::Serialize;
+use serde_cbor::Value;
+
+const MAJOR_UNSIGNED: u8 = 0;
+const MAJOR_NEGATIVE: u8 = 1;
+const MAJOR_BYTES: u8 = 2;
+const MAJOR_TEXT: u8 = 3;
+const MAJOR_ARRAY: u8 = 4;
+const MAJOR_MAP: u8 = 5;
+const MAJOR_TAG: u8 = 6;
+
+/// Encodes a value as deterministic CBOR
+///
+/// # Examples
+///
+/// ```
+/// use atlas_cli::manifest::canonical;
+/// use std::collections::HashMap;
+///
+/// let map: HashMap<&str, u64> = [("bb", 1), ("a", 500)].into_iter().collect();
+/// // Keys sorted, 500 in two bytes
+/// assert_eq!(
+///     canonical::to_vec(&map).unwrap(),
+///     [0xa2, 0x61, b'a', 0x19, 0x01, 0xf4, 0x62, b'b', b'b', 0x01]
+/// );
+/// ```
+pub fn to_vec<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>> {
+    let value = serde_cbor::value::to_value(value)
+        .map_err(|e| Error::Serialization(format!("Failed to encode CBOR: {e}")))?;
+    let mut out = Vec::new();
+    encode(&value, &mut out)?;
+    Ok(out)
+}
+
+/// Bytes of a claim covered by its signature: the claim without its signature, with its
+/// salted assertions committed to, see [`redaction::commit_assertions`], encoded
+/// deterministically
+pub fn claim_signing_input(claim: &ClaimV2) -> Result<Vec<u8>> {
+    let mut claim = claim.clone();
+    claim.signature = None;
+    redaction::commit_assertions(&mut claim)?;
+    to_vec(&claim)
+}
+
+/// Bytes a claim signature may have been made over: the deterministic encoding, and the
+/// `serde_cbor` encoding of manifests signed before it, if it differs. Claims with salted
+/// assertions were signed after the deterministic encoding was introduced.
+pub fn claim_signing_inputs(claim: &ClaimV2) -> Result<Vec<Vec<u8>>> {
+    let mut claim = claim.clone();
+    claim.signature = None;
+    if redaction::commit_assertions(&mut claim)? {
+        return Ok(vec![to_vec(&claim)?]);
+    }
+    let canonical = to_vec(&claim)?;
+    let legacy = serde_cbor::to_vec(&claim).map_err(|e| Error::Serialization(e.to_string()))?;
+    Ok(if legacy == canonical {
+        vec![canonical]
+    } else {
+        vec![canonical, legacy]
+    })
+}
+
+/// Encoding of the claim a signature was made over
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClaimEncoding {
+    /// The deterministic encoding, see [`claim_signing_input`]
+    Deterministic,
+    /// The `serde_cbor` encoding of manifests signed before the deterministic one
+    Legacy,
+}
+
+/// The encoding of the claim whose signature `verify` accepts, or `None` if it accepts
+/// neither, see [`claim_signing_inputs`]
+pub fn signed_encoding(
+    claim: &ClaimV2,
+    mut verify: impl FnMut(&[u8]) -> Result<bool>,
+) -> Result<Option<ClaimEncoding>> {
+    // A claim whose two encodings are the same counts as deterministically encoded
+    let encodings = [ClaimEncoding::Deterministic, ClaimEncoding::Legacy];
+    for (input, encoding) in claim_signing_inputs(claim)?.into_iter().zip(encodings) {
+        if verify(&input)? {
+            return Ok(Some(encoding));
+        }
+    }
+    Ok(None)
+}
+
+/// Whether `verify` accepts one of the inputs a claim signature may have been made over,
+/// see [`claim_signing_inputs`]
+pub fn verify_claim(claim: &ClaimV2, verify: impl FnMut(&[u8]) -> Result<bool>) -> Result<bool> {
+    Ok(signed_encoding(claim, verify)?.is_some())
+}
+
+fn head(major: u8, argument: u64, out: &mut Vec<u8>) {
+    let major = major << 5;
+    match argument {
+        0..=23 => out.push(major | argument as u8),
+        24..=0xff => out.extend_from_slice(&[major | 24, argument as u8]),
+        0x100..=0xffff => {
+            out.push(major | 25);
+            out.extend_from_slice(&(argument as u16).to_be_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            out.push(major | 26);
+            out.extend_from_slice(&(argument as u32).to_be_bytes());
+        }
+        _ => {
+            out.push(major | 27);
+            out.extend_from_slice(&argument.to_be_bytes());
+        }
+    }
+}
+
+fn encode(value: &Value, out: &mut Vec<u8>) -> Result<()> {
+    match value {
+        Value::Null => out.push(0xf6),
+        Value::Bool(false) => out.push(0xf4),
+        Value::Bool(true) => out.push(0xf5),
+        Value::Integer(n) => {
+            let (major, argument) = if *n >= 0 {
+                (MAJOR_UNSIGNED, u64::try_from(*n))
+            } else {
+                (MAJOR_NEGATIVE, u64::try_from(-1 - *n))
+            };
+            let argument = argument.map_err(|_| {
+                Error::Serialization(format!("Integer {n} is out of the range of CBOR integers"))
+            })?;
+            head(major, argument, out);
+        }
+        Value::Float(f) => encode_float(*f, out),
+        Value::Bytes(bytes) => {
+            head(MAJOR_BYTES, bytes.len() as u64, out);
+            out.extend_from_slice(bytes);
+        }
+        Value::Text(text) => {
+            head(MAJOR_TEXT, text.len() as u64, out);
+            out.extend_from_slice(text.as_bytes());
+        }
+        Value::Array(items) => {
+            head(MAJOR_ARRAY, items.len() as u64, out);
+            for item in items {
+                encode(item, out)?;
+            }
+        }
+        Value::Map(map) => {
+            let mut entries = map
+                .iter()
+                .map(|(key, value)| {
+                    let mut encoded = Vec::new();
+                    encode(key, &mut encoded)?;
+                    Ok((encoded, value))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            head(MAJOR_MAP, entries.len() as u64, out);
+            for (key, value) in entries {
+                out.extend_from_slice(&key);
+                encode(value, out)?;
+            }
+        }
+        Value::Tag(tag, value) => {
+            head(MAJOR_TAG, *tag, out);
+            encode(value, out)?;
+        }
+        _ => {
+            return Err(Error::Serialization("Unsupported CBOR value".to_string()));
+        }
+    }
+    Ok(())
+}
+
+fn encode_float(f: f64, out: &mut Vec<u8>) {
+    if f.is_nan() {
+        out.extend_from_slice(&[0xf9, 0x7e, 0x00]);
+        return;
+    }
+    let single = f as f32;
+    if f64::from(single) != f {
+        out.push(0xfb);
+        out.extend_from_slice(&f.to_be_bytes());
+    } else if let Some(half) = to_half(single) {
+        out.push(0xf9);
+        out.extend_from_slice(&half.to_be_bytes());
+    } else {
+        out.push(0xfa);
+        out.extend_from_slice(&single.to_be_bytes());
+    }
+}
+
+// The bits of the half-precision float with the value of `f`, if there is one
+fn to_half(f: f32) -> Option<u16> {
+    let bits = f.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    if f.is_infinite() {
+        return Some(sign | 0x7c00);
+    }
+    if f == 0.0 {
+        return Some(sign);
+    }
+    let exponent = ((bits >> 23) & 0xff) as i32 - 127;
+    let mantissa = bits & 0x7f_ffff;
+    match exponent {
+        // Normal: the 10 high bits of the mantissa must hold all of it
+        -14..=15 if mantissa & 0x1fff == 0 => {
+            Some(sign | (((exponent + 15) as u16) << 10) | (mantissa >> 13) as u16)
+        }
+        // Subnormal: multiples of 2^-24
+        -24..=-15 => {
+            let significand = mantissa | 0x80_0000;
+            let shift = -(exponent + 1);
+            (significand & ((1 << shift) - 1) == 0).then(|| sign | (significand >> shift) as u16)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn encoded<T: Serialize>(value: T) -> String {
+        hex::encode(to_vec(&value).unwrap())
+    }
+
+    #[test]
+    fn test_rfc_8949_examples() {
+        // Appendix A of RFC 8949
+        assert_eq!(encoded(0), "00");
+        assert_eq!(encoded(23), "17");
+        assert_eq!(encoded(24), "1818");
+        assert_eq!(encoded(1000), "1903e8");
+        assert_eq!(encoded(1_000_000), "1a000f4240");
+        assert_eq!(encoded(1_000_000_000_000u64), "1b000000e8d4a51000");
+        assert_eq!(encoded(u64::MAX), "1bffffffffffffffff");
+        assert_eq!(encoded(-1), "20");
+        assert_eq!(encoded(-1000), "3903e7");
+        assert_eq!(encoded(0.0), "f90000");
+        assert_eq!(encoded(-0.0), "f98000");
+        assert_eq!(encoded(1.5), "f93e00");
+        assert_eq!(encoded(65504.0), "f97bff");
+        assert_eq!(encoded(100000.0), "fa47c35000");
+        assert_eq!(encoded(1.1), "fb3ff199999999999a");
+        assert_eq!(encoded(5.960464477539063e-8), "f90001");
+        assert_eq!(encoded(0.00006103515625), "f90400");
+        assert_eq!(encoded(f64::INFINITY), "f97c00");
+        assert_eq!(encoded(f64::NAN), "f97e00");
+        assert_eq!(encoded("IETF"), "6449455446");
+        assert_eq!(encoded(vec![1, 2, 3]), "83010203");
+        assert_eq!(encoded(Option::<u8>::None), "f6");
+        assert_eq!(encoded(true), "f5");
+
+        let mut out = Vec::new();
+        encode(&Value::Bytes(vec![1, 2, 3, 4]), &mut out).unwrap();
+        assert_eq!(hex::encode(out), "4401020304");
+        let mut out = Vec::new();
+        encode(
+            &Value::Tag(1, Box::new(Value::Integer(1363896240))),
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(hex::encode(out), "c11a514b67b0");
+    }
+
+    #[test]
+    fn test_map_keys_are_sorted() {
+        #[derive(Serialize)]
+        struct Reordered {
+            zz: u8,
+            b: u8,
+            aa: u8,
+        }
+
+        // Shorter keys encode first, then bytewise order
+        let expected = "a361620162616103627a7a00";
+        assert_eq!(encoded(Reordered { zz: 0, b: 1, aa: 3 }), expected);
+
+        let map: BTreeMap<&str, u8> = [("b", 1), ("zz", 0), ("aa", 3)].into_iter().collect();
+        assert_eq!(encoded(&map), expected);
+    }
+}
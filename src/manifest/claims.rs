@@ -0,0 +1,188 @@
+//! The claim of a manifest that readers look at.
+//!
+//! Manifests made by the create commands carry their claim twice: `claim`, which is
+//! signed, and `claim_v2`, a copy made before signing. OMS manifests have only `claim`.
+//! Assertions attached after creation are added to both, but the assertions made by
+//! signing (the signer key, PIV certificate, ML-DSA signature and assertion salts) only to
+//! `claim`.
+//!
+//! Readers resolve the claim through this module rather than picking one themselves, so
+//! that both layouts read the same. Only `claim` is covered by the signature, and nothing
+//! ties `claim_v2` to it, so code making policy or trust decisions reads the
+//! [`signed_claim`]; the [`active_claim`] is for display and export.
+
+use crate::manifest::redaction::ASSERTION_SALTS_ASSERTION_LABEL;
+use crate::manifest::signer::SIGNER_KEY_ASSERTION_LABEL;
+use crate::signing::piv::PIV_CERTIFICATE_ASSERTION_LABEL;
+use crate::signing::pq::PQ_SIGNATURE_ASSERTION_LABEL;
+use atlas_c2pa_lib::assertion::Assertion;
+use atlas_c2pa_lib::claim::ClaimV2;
+use atlas_c2pa_lib::manifest::Manifest;
+
+// Assertions that signing adds to `claim` only
+const SIGNING_ASSERTION_LABELS: [&str; 4] = [
+    SIGNER_KEY_ASSERTION_LABEL,
+    PIV_CERTIFICATE_ASSERTION_LABEL,
+    PQ_SIGNATURE_ASSERTION_LABEL,
+    ASSERTION_SALTS_ASSERTION_LABEL,
+];
+
+/// The active claim of a manifest: `claim_v2` if it has one, otherwise `claim`. For display
+/// and export only, since `claim_v2` is not signed.
+pub fn active_claim(manifest: &Manifest) -> &ClaimV2 {
+    manifest.claim_v2.as_ref().unwrap_or(&manifest.claim)
+}
+
+/// The claim covered by the signature, `claim` in both layouts, which verification and
+/// policy checks read
+pub fn signed_claim(manifest: &Manifest) -> &ClaimV2 {
+    &manifest.claim
+}
+
+/// Whether an assertion is one that signing adds to `claim`
+pub(crate) fn is_signing_assertion(assertion: &Assertion) -> bool {
+    matches!(assertion, Assertion::CustomAssertion(custom) if SIGNING_ASSERTION_LABELS.contains(&custom.label.as_str()))
+}
+
+/// All assertions of a manifest: those of the active claim, followed by the assertions made
+/// by signing if the active claim is `claim_v2`
+pub fn assertions(manifest: &Manifest) -> impl Iterator<Item = &Assertion> {
+    let signing = manifest
+        .claim_v2
+        .is_some()
+        .then(|| {
+            manifest
+                .claim
+                .created_assertions
+                .iter()
+                .filter(|assertion| is_signing_assertion(assertion))
+        })
+        .into_iter()
+        .flatten();
+    active_claim(manifest)
+        .created_assertions
+        .iter()
+        .chain(signing)
+}
+
+/// Whether either claim has a creative work assertion of the given type, such as `Model`
+/// or `Dataset`
+pub fn has_creative_work(manifest: &Manifest, creative_type: &str) -> bool {
+    std::iter::once(&manifest.claim)
+        .chain(&manifest.claim_v2)
+        .flat_map(|claim| &claim.created_assertions)
+        .any(|assertion| {
+            matches!(assertion, Assertion::CreativeWork(creative_work) if creative_work.creative_type == creative_type)
+        })
+}
+
+/// Whether an action assertion of either claim has a parameter named `name`, such as
+/// `software_type`
+pub fn has_action_parameter(manifest: &Manifest, name: &str) -> bool {
+    std::iter::once(&manifest.claim)
+        .chain(&manifest.claim_v2)
+        .flat_map(|claim| &claim.created_assertions)
+        .any(|assertion| {
+            matches!(assertion, Assertion::Action(action_assertion) if action_assertion.actions.iter().any(|action| {
+                action
+                    .parameters
+                    .as_ref()
+                    .is_some_and(|parameters| parameters.get(name).is_some())
+            }))
+        })
+}
+
+/// Whether either claim of the manifest is signed
+pub fn is_signed(manifest: &Manifest) -> bool {
+    manifest.claim.signature.is_some()
+        || manifest
+            .claim_v2
+            .as_ref()
+            .is_some_and(|claim| claim.signature.is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use atlas_c2pa_lib::assertion::{CreativeWorkAssertion, CustomAssertion};
+    use atlas_c2pa_lib::datetime_wrapper::OffsetDateTimeWrapper;
+    use time::OffsetDateTime;
+
+    fn custom(label: &str) -> Assertion {
+        Assertion::CustomAssertion(CustomAssertion {
+            label: label.to_string(),
+            data: serde_json::json!({}),
+        })
+    }
+
+    fn manifest(assertions: Vec<Assertion>, with_claim_v2: bool) -> Manifest {
+        let claim = ClaimV2 {
+            instance_id: "urn:c2pa:claim".to_string(),
+            ingredients: vec![],
+            created_assertions: assertions,
+            claim_generator_info: "test".to_string(),
+            signature: None,
+            created_at: OffsetDateTimeWrapper(OffsetDateTime::now_utc()),
+        };
+        Manifest {
+            claim_generator: "test".to_string(),
+            title: "Claims".to_string(),
+            instance_id: "urn:c2pa:manifest".to_string(),
+            ingredients: vec![],
+            claim_v2: with_claim_v2.then(|| claim.clone()),
+            claim,
+            created_at: OffsetDateTimeWrapper(OffsetDateTime::now_utc()),
+            cross_references: vec![],
+            is_active: true,
+        }
+    }
+
+    fn labels(manifest: &Manifest) -> Vec<String> {
+        assertions(manifest)
+            .filter_map(|assertion| match assertion {
+                Assertion::CustomAssertion(custom) => Some(custom.label.clone()),
+                Assertion::CreativeWork(work) => Some(work.creative_type.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_both_layouts_read_the_same() {
+        let model = Assertion::CreativeWork(CreativeWorkAssertion {
+            context: "http://schema.org/".to_string(),
+            creative_type: "Model".to_string(),
+            author: vec![],
+        });
+
+        // OMS layout: only `claim`
+        let mut oms = manifest(vec![model.clone(), custom("validity")], false);
+        oms.claim
+            .created_assertions
+            .push(custom(SIGNER_KEY_ASSERTION_LABEL));
+        oms.claim.signature = Some("c2lnbmF0dXJl".to_string());
+
+        // Layout of the create commands: signing adds to `claim` only
+        let mut created = manifest(vec![model, custom("validity")], true);
+        created
+            .claim
+            .created_assertions
+            .push(custom(SIGNER_KEY_ASSERTION_LABEL));
+        created.claim.signature = Some("c2lnbmF0dXJl".to_string());
+
+        for manifest in [&oms, &created] {
+            assert_eq!(
+                labels(manifest),
+                ["Model", "validity", SIGNER_KEY_ASSERTION_LABEL]
+            );
+            assert!(has_creative_work(manifest, "Model"));
+            assert!(!has_creative_work(manifest, "Dataset"));
+            assert!(is_signed(manifest));
+        }
+        assert!(std::ptr::eq(active_claim(&oms), &oms.claim));
+        assert!(std::ptr::eq(
+            active_claim(&created),
+            created.claim_v2.as_ref().unwrap()
+        ));
+    }
+}
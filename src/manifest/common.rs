@@ -1,13 +1,53 @@
 use crate::cc_attestation;
+use crate::cc_attestation::event_log;
+use crate::cc_attestation::tpm::{self, TpmQuoteOptions};
+use crate::cli::color;
 use crate::error::{Error, Result};
 use crate::hash;
 use crate::in_toto;
+use crate::in_toto::dsse::Envelope;
+use crate::manifest::archive::{self, ArchiveFormat, ArchiveMember};
+use crate::manifest::attestation;
+use crate::manifest::claims;
 use crate::manifest::config::ManifestCreationConfig;
+use crate::manifest::content_id;
+use crate::manifest::coverage::{CoverageReport, QuorumRule};
+use crate::manifest::credential;
+use crate::manifest::dockerfile;
+use crate::manifest::encryption;
+use crate::manifest::generators;
+use crate::manifest::hooks::{self, HookStage};
+use crate::manifest::ingest;
+use crate::manifest::legacy;
+use crate::manifest::linking;
+use crate::manifest::migration;
+use crate::manifest::naming;
+use crate::manifest::object_store::{
+    self, ObjectClient, ObjectUrl, ProviderChecksum, RemoteObject, StreamOptions,
+};
+use crate::manifest::output::write_encoded;
+use crate::manifest::policy;
+use crate::manifest::presentation::{self, ListOptions, ListRow};
+use crate::manifest::profile;
+use crate::manifest::python_env;
+use crate::manifest::redaction;
+use crate::manifest::relationship::{self, IngredientRelationship};
+use crate::manifest::revocation::{OnRevoked, TrustStore};
+use crate::manifest::schema;
+use crate::manifest::signer::{self, SignatureFilter};
+use crate::manifest::timestamp::to_rfc3339_utc;
 use crate::manifest::utils::{
     determine_dataset_type, determine_format, determine_model_type, determine_software_type,
 };
+use crate::manifest::validity::{self, Validity};
+use crate::manifest::verification::{
+    self, Check, CheckKind, Explanation, Outcome, Progress, VerificationEngine,
+};
+use crate::signing;
 use crate::signing::signable::Signable;
-use crate::storage::traits::{ArtifactLocation, StorageBackend};
+use crate::storage::queue::{self, OfflineQueue};
+use crate::storage::traits::{ArtifactLocation, EnvelopeKind, EnvelopeRecord, StorageBackend};
+use crate::utils::{file_url_to_path, path_to_file_url, relative_file_url, relative_url_to_path};
 use atlas_c2pa_lib::assertion::{
     Action, ActionAssertion, Assertion, Author, CreativeWorkAssertion, CustomAssertion,
 };
@@ -18,15 +58,31 @@ use atlas_c2pa_lib::cross_reference::CrossReference;
 use atlas_c2pa_lib::datetime_wrapper::OffsetDateTimeWrapper;
 use atlas_c2pa_lib::ingredient::{Ingredient, IngredientData};
 use atlas_c2pa_lib::manifest::Manifest;
-use serde_json::{to_string, to_string_pretty};
+use openssl::pkey::{PKey, Public};
+use serde::Deserialize;
+use serde_json::to_string;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
 use std::path::{Path, PathBuf};
 use tdx_workload_attestation::get_platform_name;
 use time::OffsetDateTime;
 use uuid::Uuid;
 
-const CLAIM_GENERATOR: &str = "atlas-cli:0.2.0";
+pub(crate) const CLAIM_GENERATOR: &str = "atlas-cli:0.2.0";
+
+/// Predicate type of the in-toto statements of OMS manifests
+pub const OMS_PREDICATE_TYPE: &str = "https://spec.c2pa.org/specifications/specifications/2.2";
+
+/// Label of the assertion recording ingredient file sizes, keyed by ingredient URL
+pub const INGREDIENT_SIZES_ASSERTION_LABEL: &str = "ingredient_sizes";
+
+/// Label of the assertion recording the license of an asset, see `--license`
+pub const LICENSE_ASSERTION_LABEL: &str = "license";
 
 /// Asset type enum to distinguish between models, datasets, software, and evaluations
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum AssetKind {
     Model,
     Dataset,
@@ -34,15 +90,34 @@ pub enum AssetKind {
     Evaluation,
 }
 
+impl fmt::Display for AssetKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssetKind::Model => write!(f, "model"),
+            AssetKind::Dataset => write!(f, "dataset"),
+            AssetKind::Software => write!(f, "software"),
+            AssetKind::Evaluation => write!(f, "evaluation"),
+        }
+    }
+}
+
 /// Generates C2PA Assertions based on the asset kind and configuration.
 ///
 /// This function creates standardized C2PA assertions including creative work and action assertions
 /// that are tailored to the specific type of asset being attested (model, dataset, software, or evaluation).
-/// It also optionally includes confidential computing (CC) attestations when enabled.
+/// It also optionally includes confidential computing (CC) attestations or TPM quotes when enabled.
 fn generate_c2pa_assertions(
     config: &ManifestCreationConfig,
     asset_kind: AssetKind,
 ) -> Result<Vec<Assertion>> {
+    // Authors may be given as DIDs instead of names
+    for author in [&config.author_org, &config.author_name]
+        .into_iter()
+        .flatten()
+    {
+        credential::check_identity(author)?;
+    }
+
     // Determine asset-specific values
     let (creative_type, digital_source_type) = match asset_kind {
         AssetKind::Model => (
@@ -159,8 +234,11 @@ fn generate_c2pa_assertions(
                         params
                     }
                     // don't need to repeat info for created action assertions that's
-                    // already in the CreativeWork assertion
-                    _ => serde_json::json!({}),
+                    // already in the CreativeWork assertion, which has no description
+                    _ => match &config.description {
+                        Some(description) => serde_json::json!({ "description": description }),
+                        None => serde_json::json!({}),
+                    },
                 }),
                 digital_source_type: Some(digital_source_type),
                 instance_id: None,
@@ -177,6 +255,46 @@ fn generate_c2pa_assertions(
         assertions.push(Assertion::CustomAssertion(cc_assertion));
     }
 
+    // hosts without a TEE can provide a TPM quote instead
+    if let Some(tpm) = &config.tpm {
+        assertions.push(Assertion::CustomAssertion(get_tpm_quote_assertion(tpm)?));
+    }
+
+    if let Some(valid_until) = config.valid_until {
+        assertions.push(Assertion::CustomAssertion(validity::validity_assertion(
+            valid_until,
+        )?));
+    }
+
+    // The license, for software snapshotted from a Python environment or a container build
+    // what it was built from, and for imported legacy artifacts their inventory row
+    for label in [
+        LICENSE_ASSERTION_LABEL,
+        python_env::PYTHON_ENV_ASSERTION_LABEL,
+        dockerfile::CONTAINER_BUILD_ASSERTION_LABEL,
+        legacy::LEGACY_IMPORT_ASSERTION_LABEL,
+    ] {
+        if let Some(data) = config
+            .custom_fields
+            .as_ref()
+            .and_then(|fields| fields.get(label))
+        {
+            assertions.push(Assertion::CustomAssertion(CustomAssertion {
+                label: label.to_string(),
+                data: data.clone(),
+            }));
+        }
+    }
+
+    // Assertions taken from an earlier manifest, unless this one makes its own
+    for inherited in &config.inherited_assertions {
+        if !assertions.iter().any(|assertion| {
+            matches!(assertion, Assertion::CustomAssertion(custom) if custom.label == inherited.label)
+        }) {
+            assertions.push(Assertion::CustomAssertion(inherited.clone()));
+        }
+    }
+
     Ok(assertions)
 }
 
@@ -187,28 +305,172 @@ fn generate_c2pa_assertions(
 /// them with generated assertions. The claim includes metadata such as instance ID, creation timestamp,
 /// and claim generator information.
 fn generate_c2pa_claim(config: &ManifestCreationConfig, asset_kind: AssetKind) -> Result<ClaimV2> {
+    // Names are matched up with their paths before anything else
+    let ingredient_names = naming::ingredient_names(
+        &config.paths,
+        &config.ingredient_names,
+        config.path_base.as_deref(),
+    )?;
+
+    // Refuse manifests violating the organization's policy before hashing anything
+    let policy_override = policy::check_creation(config, asset_kind)?;
+
     // Create ingredients using the helper function
     let mut ingredients = Vec::new();
+    let mut sizes = BTreeMap::new();
+    let mut provider_checksums = BTreeMap::new();
+    let mut profiles = Vec::new();
+    let mut directories_ingested = false;
+    let mut symlinks = BTreeMap::new();
+    let mut empty_dirs = Vec::new();
+
+    // The asset type of an ingredient depends on the asset kind
+    let asset_type = |path: &Path| match asset_kind {
+        AssetKind::Model => determine_model_type(path),
+        AssetKind::Dataset => determine_dataset_type(path),
+        AssetKind::Software => determine_software_type(path),
+        AssetKind::Evaluation => Ok(AssetType::Dataset), // Use Dataset type for evaluation results
+    };
 
-    for (path, ingredient_name) in config.paths.iter().zip(config.ingredient_names.iter()) {
-        // Determine asset type and format based on asset kind
-        let format = determine_format(path)?;
-        let asset_type = match asset_kind {
-            AssetKind::Model => determine_model_type(path)?,
-            AssetKind::Dataset => determine_dataset_type(path)?,
-            AssetKind::Software => determine_software_type(path)?,
-            AssetKind::Evaluation => AssetType::Dataset, // Use Dataset type for evaluation results
-        };
+    relationship::check_relationships(&config.ingredient_relationships, config.paths.len())?;
+
+    for (index, (path, ingredient_name)) in
+        config.paths.iter().zip(ingredient_names.iter()).enumerate()
+    {
+        let relationship = config
+            .ingredient_relationships
+            .get(index)
+            .copied()
+            .unwrap_or_default();
+
+        // A path may select its own hash algorithm, as in `model.onnx:sha256`
+        let (path, hash_alg) = hash::split_path_algorithm(path)?;
+        let hash_alg = hash_alg.unwrap_or_else(|| config.hash_alg.clone());
+        let path = path.as_path();
+
+        // Cloud objects are hashed in place instead of being downloaded
+        if let Some(url) = ObjectUrl::parse(&path.to_string_lossy()) {
+            let client = ObjectClient::from_env(url.store)?;
+            let object = client.stat(&url)?;
+            let options = StreamOptions {
+                connections: config.stream_connections,
+                ..StreamOptions::default()
+            };
+            let key_path = Path::new(&object.url.key);
+            let mut ingredient = create_ingredient_from_remote_object(
+                &object,
+                client.hash_object(&object, &hash_alg, options)?,
+                ingredient_name,
+                asset_type(key_path)?,
+                determine_format(key_path)?,
+                &hash_alg,
+            );
+            ingredient.relationship = relationship.to_string();
+            sizes.insert(ingredient.data.url.clone(), object.size);
+            if let Some(checksum) = object.checksum {
+                provider_checksums.insert(ingredient.data.url.clone(), checksum);
+            }
+            ingredients.push(ingredient);
+            continue;
+        }
+
+        // Files below a directory are ingredients named after their relative path
+        if path.is_dir() {
+            let policy = config.ingest_policy;
+            let contents = policy.walk_directory(path)?;
+            for file in &contents.files {
+                let relative = file.strip_prefix(path).unwrap_or(file);
+                let mut ingredient = create_ingredient_from_directory_entry(
+                    file,
+                    policy.hash_file(file, &hash_alg)?,
+                    &format!("{ingredient_name}/{}", relative.to_string_lossy()),
+                    asset_type(file)?,
+                    determine_format(file)?,
+                    &hash_alg,
+                );
+                ingredient.relationship = relationship.to_string();
+                sizes.insert(ingredient.data.url.clone(), fs::metadata(file)?.len());
+                ingredients.push(ingredient);
+            }
+            directories_ingested = true;
+            symlinks.extend(contents.symlinks);
+            empty_dirs.extend(contents.empty_dirs);
+            continue;
+        }
+
+        if config.expand_archives
+            && let Some(format) = ArchiveFormat::detect(path)
+        {
+            for member in archive::hash_members(path, format, &hash_alg)? {
+                let member_path = Path::new(&member.path);
+                let mut ingredient = create_ingredient_from_archive_member(
+                    path,
+                    &member,
+                    &format!("{ingredient_name}/{}", member.path),
+                    asset_type(member_path)?,
+                    determine_format(member_path)?,
+                    &hash_alg,
+                );
+                ingredient.relationship = relationship.to_string();
+                sizes.insert(ingredient.data.url.clone(), member.size);
+                ingredients.push(ingredient);
+            }
+            continue;
+        }
 
         // Use the helper function to create the ingredient
-        let ingredient = create_ingredient_from_path_with_algorithm(
+        let mut ingredient = create_ingredient_from_path_with_algorithm(
             path,
             ingredient_name,
-            asset_type,
-            format,
-            &config.hash_alg,
+            asset_type(path)?,
+            determine_format(path)?,
+            &hash_alg,
         )?;
+        ingredient.relationship = relationship.to_string();
+        sizes.insert(ingredient.data.url.clone(), fs::metadata(path)?.len());
         ingredients.push(ingredient);
+
+        if config.profile_data
+            && let Some(profile) =
+                profile::profile_file(path, ingredient_name, config.max_sample_bytes)?
+        {
+            profiles.push(profile);
+        }
+    }
+
+    // Names identify ingredients, and order them in OMS manifests
+    naming::check_unique_names(&ingredients)?;
+
+    // A directory or archive ingested as the parent would give the manifest several parents
+    let parents = ingredients
+        .iter()
+        .filter(|i| i.relationship == IngredientRelationship::ParentOf.as_str())
+        .count();
+    if parents > 1 {
+        return Err(Error::Validation(format!(
+            "A manifest has at most one parentOf ingredient, got {parents}"
+        )));
+    }
+
+    // Portable manifests record local ingredients relative to the path base
+    if let Some(base) = &config.path_base {
+        for ingredient in &mut ingredients {
+            let url = relative_ingredient_url(&ingredient.data.url, base)?;
+            if let Some(size) = sizes.remove(&ingredient.data.url) {
+                sizes.insert(url.clone(), size);
+            }
+            ingredient.data.url = url;
+        }
+
+        let relative = |path: &str| relative_file_url(Path::new(path), base);
+        symlinks = symlinks
+            .into_iter()
+            .map(|(link, target)| Ok((relative(&link)?, target)))
+            .collect::<Result<_>>()?;
+        empty_dirs = empty_dirs
+            .iter()
+            .map(|dir| relative(dir))
+            .collect::<Result<_>>()?;
     }
 
     // Per the OMS spec, ingredients must be hashed in alphabetical order of the
@@ -218,7 +480,53 @@ fn generate_c2pa_claim(config: &ManifestCreationConfig, asset_kind: AssetKind) -
     // See https://github.com/sigstore/model-transparency/blob/de2f935ad437218d577a3f39378c482bf3aafcec/src/model_signing/_signing/signing.py#L188-L192
     ingredients.sort_by_key(|ingredient| ingredient.title.to_lowercase());
 
-    let assertions = generate_c2pa_assertions(config, asset_kind)?;
+    let mut assertions = generate_c2pa_assertions(config, asset_kind)?;
+
+    if let Some(policy_override) = &policy_override {
+        assertions.push(Assertion::CustomAssertion(policy_override.to_assertion()?));
+    }
+
+    // Sizes let verification detect truncated files without hashing them
+    if !sizes.is_empty() {
+        assertions.push(Assertion::CustomAssertion(CustomAssertion {
+            label: INGREDIENT_SIZES_ASSERTION_LABEL.to_string(),
+            data: serde_json::to_value(&sizes)?,
+        }));
+    }
+
+    // Provider checksums let `verify --fast-cloud` skip re-reading unchanged objects
+    if !provider_checksums.is_empty() {
+        assertions.push(Assertion::CustomAssertion(CustomAssertion {
+            label: object_store::PROVIDER_CHECKSUMS_ASSERTION_LABEL.to_string(),
+            data: serde_json::to_value(&provider_checksums)?,
+        }));
+    }
+
+    // Verification applies the same policy to the files of ingested directories
+    if directories_ingested {
+        assertions.push(Assertion::CustomAssertion(ingest::ingest_assertion(
+            config.ingest_policy,
+            symlinks,
+            empty_dirs,
+        )?));
+    }
+
+    if !profiles.is_empty() {
+        assertions.push(Assertion::CustomAssertion(profile::profile_assertion(
+            &profiles,
+        )?));
+    }
+
+    // External generators see the manifest as built so far, and may be encrypted too
+    generators::run_generators(config, asset_kind, &ingredients, &mut assertions)?;
+
+    // Custom assertions must match their registered schemas before anything is encrypted
+    schema::check_creation(&assertions)?;
+
+    // Confidential payloads are encrypted before signing, so the signature covers them
+    if let Some(encryption) = &config.encryption {
+        encryption::encrypt_assertions(&mut assertions, encryption)?;
+    }
 
     // Create claim
     Ok(ClaimV2 {
@@ -227,13 +535,26 @@ fn generate_c2pa_claim(config: &ManifestCreationConfig, asset_kind: AssetKind) -
         created_assertions: assertions,
         claim_generator_info: CLAIM_GENERATOR.to_string(),
         signature: None,
-        created_at: OffsetDateTimeWrapper(OffsetDateTime::now_utc()),
+        created_at: OffsetDateTimeWrapper(
+            config.created_at.unwrap_or_else(OffsetDateTime::now_utc),
+        ),
     })
 }
 
 /// Creates a manifest for a model, dataset, software, or evaluation
 pub fn create_manifest(config: ManifestCreationConfig, asset_kind: AssetKind) -> Result<()> {
-    let claim = generate_c2pa_claim(&config, asset_kind)?;
+    let mut manifest = build_manifest(&config, asset_kind)?;
+    finish_manifest(&mut manifest, &config)?;
+    Ok(())
+}
+
+/// Generates the unsigned manifest for a model, dataset, software, or evaluation, with its
+/// dataset links; see [`finish_manifest`] for the rest of [`create_manifest`]
+pub(crate) fn build_manifest(
+    config: &ManifestCreationConfig,
+    asset_kind: AssetKind,
+) -> Result<Manifest> {
+    let claim = generate_c2pa_claim(config, asset_kind)?;
 
     // Create the manifest
     let mut manifest = Manifest {
@@ -242,15 +563,72 @@ pub fn create_manifest(config: ManifestCreationConfig, asset_kind: AssetKind) ->
         instance_id: format!("urn:c2pa:{}", Uuid::new_v4()),
         claim: claim.clone(),
         ingredients: vec![],
-        created_at: OffsetDateTimeWrapper(OffsetDateTime::now_utc()),
+        created_at: OffsetDateTimeWrapper(claim.created_at.0),
         cross_references: vec![],
         claim_v2: Some(claim),
         is_active: true,
     };
 
+    // Datasets are linked before signing, so that the signature covers the links
+    if !config.dataset_ids.is_empty() {
+        let storage_backend = config.storage.ok_or_else(|| {
+            Error::Validation("Linking datasets requires a storage backend".to_string())
+        })?;
+        let datasets = linking::fetch_datasets(&config.dataset_ids, storage_backend)?;
+        linking::add_dataset_links(&mut manifest, &datasets, &config.hash_alg)?;
+    }
+
+    // The ID is derived before signing, since the signature covers the claim ID
+    if config.content_addressed_id {
+        content_id::assign_content_id(&mut manifest)?;
+    }
+
+    Ok(manifest)
+}
+
+/// Signs a built manifest, adds its linked manifests, and outputs and stores it as
+/// configured. Returns the ID of the stored manifest.
+pub(crate) fn finish_manifest(
+    manifest: &mut Manifest,
+    config: &ManifestCreationConfig,
+) -> Result<Option<String>> {
+    // Pre-create hooks may refuse the manifest before it is signed or stored
+    let instance_id = manifest.instance_id.clone();
+    hooks::run_hooks(HookStage::PreCreate, manifest, Some(&instance_id), None)?;
+    let id = sign_and_store(manifest, config)?;
+    hooks::run_hooks(
+        HookStage::PostCreate,
+        manifest,
+        Some(id.as_deref().unwrap_or(&instance_id)),
+        None,
+    )?;
+    Ok(id)
+}
+
+// Signs, links, outputs and stores a manifest for `finish_manifest`
+fn sign_and_store(
+    manifest: &mut Manifest,
+    config: &ManifestCreationConfig,
+) -> Result<Option<String>> {
     // Sign if key is provided
-    if let Some(key_file) = &config.key_path {
-        manifest.sign(key_file.to_path_buf(), config.hash_alg)?;
+    match (&config.key_path, &config.pq_key_path) {
+        (Some(key_file), Some(pq_key_file)) => {
+            signer::sign_hybrid(
+                manifest,
+                key_file.to_path_buf(),
+                pq_key_file,
+                config.hash_alg.clone(),
+            )?;
+        }
+        (Some(key_file), None) => {
+            manifest.sign(key_file.to_path_buf(), config.hash_alg.clone())?;
+        }
+        (None, Some(_)) => {
+            return Err(Error::Validation(
+                "Hybrid signing with --pq-key also requires a classical --key".to_string(),
+            ));
+        }
+        (None, None) => {}
     }
 
     if let Some(manifest_ids) = &config.linked_manifests {
@@ -288,36 +666,49 @@ pub fn create_manifest(config: ManifestCreationConfig, asset_kind: AssetKind) ->
     }
 
     // Output manifest if requested
-    if config.print || config.storage.is_none() {
-        match config.output_encoding.to_lowercase().as_str() {
-            "json" => {
-                let manifest_json =
-                    to_string_pretty(&manifest).map_err(|e| Error::Serialization(e.to_string()))?;
-                println!("{manifest_json}");
-            }
-            "cbor" => {
-                let manifest_cbor = serde_cbor::to_vec(&manifest)
-                    .map_err(|e| Error::Serialization(e.to_string()))?;
-                println!("{}", hex::encode(&manifest_cbor));
-            }
-            _ => {
-                return Err(Error::Validation(format!(
-                    "Invalid output encoding '{}'. Valid options are: json, cbor",
-                    config.output_encoding
-                )));
-            }
-        }
+    if config.print || config.storage.is_none() || config.output_path.is_some() {
+        write_encoded(
+            &manifest,
+            &config.output_encoding,
+            config.output_path.as_deref(),
+        )?;
     }
 
     // Store manifest if storage is provided
     if let Some(storage) = &config.storage {
         if !config.print {
-            let id = storage.store_manifest(&manifest)?;
-            println!("Manifest stored successfully with ID: {id}");
+            let stored = (|| -> Result<String> {
+                if !config.force_new
+                    && let Some(id) = content_id::find_duplicate(*storage, manifest)?
+                {
+                    println!(
+                        "Identical manifest already stored with ID: {id} (use --force-new to store it again)"
+                    );
+                    return Ok(id);
+                }
+                let id = storage.store_manifest(manifest)?;
+                println!("Manifest stored successfully with ID: {id}");
+                Ok(id)
+            })();
+
+            // Unreachable storage is retried later from the offline queue, if enabled
+            return match stored {
+                Err(e) if e.is_retryable() && queue::is_enabled() => {
+                    let queued =
+                        OfflineQueue::open_default()?.enqueue(&storage.get_base_uri(), manifest)?;
+                    println!(
+                        "Storage unreachable ({e}); manifest {} queued as {} for `atlas-cli queue flush`",
+                        queued.manifest_id,
+                        queued.path.display()
+                    );
+                    Ok(Some(queued.manifest_id))
+                }
+                stored => stored.map(Some),
+            };
         }
     }
 
-    Ok(())
+    Ok(None)
 }
 
 /// Creates an OpenSSF Model Signing (OMS) compliant C2PA manifest for a model.
@@ -358,14 +749,31 @@ pub fn create_manifest(config: ManifestCreationConfig, asset_kind: AssetKind) ->
 ///     author_org: Some("Test Org".to_string()),
 ///     paths: vec![PathBuf::from("model.onnx")],
 ///     ingredient_names: vec!["model".to_string()],
+///     ingredient_relationships: vec![],
+///     expand_archives: false,
+///     profile_data: false,
+///     max_sample_bytes: atlas_cli::manifest::profile::DEFAULT_MAX_SAMPLE_BYTES,
+///     stream_connections: atlas_cli::manifest::object_store::DEFAULT_CONNECTIONS,
+///     ingest_policy: atlas_cli::manifest::ingest::IngestPolicy::default(),
+///     path_base: None,
+///     dataset_ids: Vec::new(),
 ///     hash_alg: HashAlgorithm::Sha384,
+///     valid_until: None,
+///     created_at: None,
 ///     key_path: Some(PathBuf::from("private_key.pem")),
+///     pq_key_path: None,
 ///     output_encoding: "json".to_string(),
+///     output_path: None,
 ///     print: true,
 ///     storage: None,
 ///     with_cc: false,
+///     tpm: None,
 ///     linked_manifests: None,
+///     encryption: None,
+///     content_addressed_id: false,
+///     force_new: false,
 ///     custom_fields: None,
+///     inherited_assertions: vec![],
 ///     software_type: None,
 ///     version: None,
 /// };
@@ -382,7 +790,7 @@ pub fn create_oms_manifest(config: ManifestCreationConfig) -> Result<()> {
         instance_id: format!("urn:c2pa:{}", Uuid::new_v4()),
         claim: claim.clone(),
         ingredients: vec![],
-        created_at: OffsetDateTimeWrapper(OffsetDateTime::now_utc()),
+        created_at: OffsetDateTimeWrapper(claim.created_at.0),
         cross_references: vec![],
         claim_v2: None,
         is_active: true,
@@ -422,13 +830,17 @@ pub fn create_oms_manifest(config: ManifestCreationConfig) -> Result<()> {
         }
     }
 
+    if config.content_addressed_id {
+        content_id::assign_content_id(&mut manifest)?;
+    }
+
     // Generate the in-toto format Statement and sign the DSSE
 
     // we need to convert this into a string to serialize into the Struct proto expected by in-toto
     let manifest_json = to_string(&manifest).map_err(|e| Error::Serialization(e.to_string()))?;
     let manifest_proto = in_toto::json_to_struct_proto(&manifest_json)?;
 
-    let subject_hash = generate_oms_subject_hash(&manifest, &config.hash_alg)?;
+    let subject_hash = generate_oms_subject_hash(&manifest.claim.ingredients, &config.hash_alg)?;
 
     let subject = in_toto::make_minimal_resource_descriptor(
         &config.name,
@@ -440,41 +852,47 @@ pub fn create_oms_manifest(config: ManifestCreationConfig) -> Result<()> {
         .key_path
         .ok_or_else(|| Error::Validation("OMS format requires a signing key".to_string()))?;
 
-    let envelope = in_toto::generate_signed_statement_v1(
+    let mut envelope = in_toto::generate_signed_statement_v1(
         &[subject],
-        "https://spec.c2pa.org/specifications/specifications/2.2",
+        OMS_PREDICATE_TYPE,
         &manifest_proto,
         key_path.to_path_buf(),
         config.hash_alg,
     )?;
+    if let Some(pq_key_file) = &config.pq_key_path {
+        envelope.add_pq_signature(pq_key_file)?;
+    }
 
     // Output manifest if requested
-    if config.print || config.storage.is_none() {
-        match config.output_encoding.to_lowercase().as_str() {
-            "json" => {
-                let envelope_json =
-                    to_string_pretty(&envelope).map_err(|e| Error::Serialization(e.to_string()))?;
-                println!("{envelope_json}");
-            }
-            "cbor" => {
-                let envelope_cbor = serde_cbor::to_vec(&envelope)
-                    .map_err(|e| Error::Serialization(e.to_string()))?;
-                println!("{}", hex::encode(&envelope_cbor));
-            }
-            _ => {
-                return Err(Error::Validation(format!(
-                    "Invalid output encoding '{}'. Valid options are: json, cbor",
-                    config.output_encoding
-                )));
-            }
-        }
+    if config.print || config.storage.is_none() || config.output_path.is_some() {
+        write_encoded(
+            &envelope,
+            &config.output_encoding,
+            config.output_path.as_deref(),
+        )?;
     }
 
-    // Store manifest if storage is provided
+    // Store manifest if storage is provided, together with its signed envelope
     if let Some(storage) = &config.storage {
         if !config.print {
+            if !config.force_new
+                && let Some(id) = content_id::find_duplicate(*storage, &manifest)?
+            {
+                println!(
+                    "Identical manifest already stored with ID: {id} (use --force-new to store it again)"
+                );
+                return Ok(());
+            }
             let id = storage.store_manifest(&manifest)?;
             println!("Manifest stored successfully with ID: {id}");
+
+            let record = EnvelopeRecord::new(EnvelopeKind::Oms, &config.name, Some(id), envelope);
+            match storage.store_envelope(&record) {
+                Ok(envelope_id) => {
+                    println!("Envelope stored successfully with ID: {envelope_id}")
+                }
+                Err(e) => println!("Warning: Could not store envelope: {e}"),
+            }
         }
     }
 
@@ -513,6 +931,16 @@ pub fn create_oms_manifest(config: ManifestCreationConfig) -> Result<()> {
 /// list_manifests(&storage_backend, Some(AssetKind::Model)).unwrap();
 /// ```
 pub fn list_manifests(storage: &dyn StorageBackend, asset_kind: Option<AssetKind>) -> Result<()> {
+    list_manifests_with_options(storage, asset_kind, &ListOptions::default())
+}
+
+/// Lists manifests from storage, optionally filtered by asset type, in the format, order
+/// and length given by `options`
+pub fn list_manifests_with_options(
+    storage: &dyn StorageBackend,
+    asset_kind: Option<AssetKind>,
+    options: &ListOptions,
+) -> Result<()> {
     let manifests = storage.list_manifests()?;
 
     // Filter manifests by type if asset_kind is specified
@@ -542,14 +970,34 @@ pub fn list_manifests(storage: &dyn StorageBackend, asset_kind: Option<AssetKind
     };
 
     // Display the manifests
-    for metadata in filtered_manifests {
-        println!(
-            "Manifest: {} (ID: {}, Type: {:?}, Created: {})",
-            metadata.name, metadata.id, metadata.manifest_type, metadata.created_at
-        );
+    let rows = filtered_manifests.into_iter().map(ListRow::from).collect();
+    let rows = filter_by_signature(storage, rows, &options.signature)?;
+    presentation::print_manifest_list(rows, options)
+}
+
+/// Keeps the manifests selected by a signature filter, retrieving them unless the filter
+/// selects every manifest or their metadata records whether they are signed
+pub fn filter_by_signature(
+    storage: &dyn StorageBackend,
+    rows: Vec<ListRow>,
+    filter: &SignatureFilter,
+) -> Result<Vec<ListRow>> {
+    if *filter == SignatureFilter::Any {
+        return Ok(rows);
     }
 
-    Ok(())
+    let mut selected = Vec::new();
+    for row in rows {
+        let matches = match (filter, row.metadata.signed) {
+            (SignatureFilter::Signed, Some(signed)) => signed,
+            (SignatureFilter::Unsigned, Some(signed)) => !signed,
+            _ => filter.matches(&storage.retrieve_manifest(&row.metadata.id)?)?,
+        };
+        if matches {
+            selected.push(row);
+        }
+    }
+    Ok(selected)
 }
 
 /// Performs comprehensive verification of a manifest.
@@ -596,217 +1044,1149 @@ pub fn list_manifests(storage: &dyn StorageBackend, asset_kind: Option<AssetKind
 /// println!("✓ Manifest verification successful");
 /// ```
 pub fn verify_manifest(id: &str, storage: &dyn StorageBackend) -> Result<()> {
+    verify_manifest_with_artifact_root(id, storage, None)
+}
+
+/// Verifies a manifest, resolving relative ingredient URLs against `artifact_root`, or
+/// against the current directory if no root is given
+pub fn verify_manifest_with_artifact_root(
+    id: &str,
+    storage: &dyn StorageBackend,
+    artifact_root: Option<&Path>,
+) -> Result<()> {
+    verify_manifest_with_selection(id, storage, artifact_root, &IngredientSelection::default())
+}
+
+/// Outcome of [`verify_all_manifests`]
+#[derive(Debug, Default)]
+pub struct VerifyAllReport {
+    pub verified: Vec<String>,
+    /// Manifests that failed verification, with the reason
+    pub failed: Vec<(String, String)>,
+}
+
+/// Verifies every stored manifest selected by `filter`, carrying on after failures
+pub fn verify_all_manifests(
+    storage: &dyn StorageBackend,
+    filter: &SignatureFilter,
+    artifact_root: Option<&Path>,
+) -> Result<VerifyAllReport> {
+    let rows = storage
+        .list_manifests()?
+        .into_iter()
+        .map(ListRow::from)
+        .collect();
+
+    let mut report = VerifyAllReport::default();
+    for row in filter_by_signature(storage, rows, filter)? {
+        let id = row.metadata.id;
+        match verify_manifest_with_artifact_root(&id, storage, artifact_root) {
+            Ok(()) => report.verified.push(id),
+            Err(e) => report.failed.push((id, e.to_string())),
+        }
+    }
+    Ok(report)
+}
+
+/// Ingredients whose hashes verification checks
+#[derive(Debug, Clone, Default)]
+pub struct IngredientSelection {
+    /// Only the ingredients with these names; every ingredient if empty
+    pub only: Vec<String>,
+    /// Skip ingredients stored remotely, such as S3 and GCS objects
+    pub skip_remote: bool,
+    /// Accept cloud objects whose store reports the digest recorded at creation without
+    /// re-hashing them
+    pub fast_cloud: bool,
+}
+
+impl IngredientSelection {
+    /// Whether every ingredient is checked
+    pub fn is_all(&self) -> bool {
+        self.only.is_empty() && !self.skip_remote
+    }
+
+    fn includes(&self, ingredient: &Ingredient) -> bool {
+        let url = &ingredient.data.url;
+        let remote = ObjectUrl::parse(url).is_some()
+            || url.starts_with("http://")
+            || url.starts_with("https://");
+
+        (self.only.is_empty() || self.only.contains(&ingredient.title))
+            && !(self.skip_remote && remote)
+    }
+}
+
+/// Verifies a manifest like [`verify_manifest_with_artifact_root`], but only checks the
+/// hashes of the selected ingredients. The signature, cross-references and links to other
+/// manifests are always verified; the file list of ingested directories only when every ingredient is.
+pub fn verify_manifest_with_selection(
+    id: &str,
+    storage: &dyn StorageBackend,
+    artifact_root: Option<&Path>,
+    selection: &IngredientSelection,
+) -> Result<()> {
+    verify_stored_manifest(id, storage, artifact_root, selection, None).map(|_| ())
+}
+
+/// Verifies a manifest in quorum mode, see [`crate::manifest::coverage`].
+///
+/// Ingredients that can't be reached are recorded in the returned report instead of
+/// failing verification; the signature and cross-references must verify as usual. Use
+/// [`CoverageReport::into_result`] to fail when the quorum was not met.
+pub fn verify_manifest_with_quorum(
+    id: &str,
+    storage: &dyn StorageBackend,
+    artifact_root: Option<&Path>,
+    selection: &IngredientSelection,
+    rule: &QuorumRule,
+) -> Result<CoverageReport> {
+    let report = verify_stored_manifest(id, storage, artifact_root, selection, Some(rule))?;
+    Ok(report.expect("quorum mode reports coverage"))
+}
+
+// Verifies a stored manifest, returning the coverage report in quorum mode
+fn verify_stored_manifest(
+    id: &str,
+    storage: &dyn StorageBackend,
+    artifact_root: Option<&Path>,
+    selection: &IngredientSelection,
+    quorum: Option<&QuorumRule>,
+) -> Result<Option<CoverageReport>> {
     let manifest = storage.retrieve_manifest(id)?;
+    let result =
+        verify_retrieved_manifest(id, &manifest, storage, artifact_root, selection, quorum);
+    let verified = result
+        .as_ref()
+        .is_ok_and(|coverage| coverage.as_ref().is_none_or(|coverage| coverage.passed));
+    hooks::run_hooks(HookStage::PostVerify, &manifest, Some(id), Some(verified))?;
+    result
+}
+
+// Verifies a retrieved manifest for `verify_stored_manifest`
+fn verify_retrieved_manifest(
+    id: &str,
+    manifest: &Manifest,
+    storage: &dyn StorageBackend,
+    artifact_root: Option<&Path>,
+    selection: &IngredientSelection,
+    quorum: Option<&QuorumRule>,
+) -> Result<Option<CoverageReport>> {
+    let resolve = |path: PathBuf| match artifact_root {
+        Some(root) if path.is_relative() => root.join(path),
+        _ => path,
+    };
 
     // Step 1: Verify the manifest structure
-    atlas_c2pa_lib::manifest::validate_manifest(&manifest)
+    atlas_c2pa_lib::manifest::validate_manifest(manifest)
         .map_err(|e| crate::error::Error::Validation(e.to_string()))?;
 
     println!("Verifying manifest with ID: {id}");
 
-    // Step 2: Verify each ingredient's hash
-    for ingredient in &manifest.ingredients {
-        println!("Verifying ingredient: {}", ingredient.title);
-
-        if ingredient.data.url.starts_with("file://") {
-            let path = PathBuf::from(ingredient.data.url.trim_start_matches("file://"));
+    if signing::fips::is_enabled() {
+        signing::fips::check_manifest(manifest)?;
+        Explanation::new()
+            .line("policy: FIPS mode, every hash and signature algorithm is FIPS-approved")
+            .print();
+    }
 
-            // Create ArtifactLocation for verification
-            let location = ArtifactLocation {
-                url: ingredient.data.url.clone(),
-                file_path: Some(path),
-                hash: ingredient.data.hash.clone(),
-            };
+    if let Some(message) = check_manifest_validity(manifest)? {
+        println!("{message}");
+    }
+    check_revoked_signatures(manifest)?;
+    redaction::check_redactions(manifest)?;
+    report_pending_migration(id, manifest)?;
+
+    // The ML-DSA signature of a hybrid-signed manifest must hold even when the classical
+    // one is not checked
+    if let Some(variant) = signer::verify_pq_signature(manifest)? {
+        println!("{} {variant} signature verified", color::check_mark());
+        Explanation::new()
+            .line(format!(
+                "{variant}: verified with the public key recorded with the signature"
+            ))
+            .print();
+    }
+    explain_claim_signature(manifest)?;
+    if let Some(certificate) = signer::verify_certificate_signature(manifest)? {
+        let subject = certificate
+            .subject_name()
+            .entries()
+            .filter_map(|entry| entry.data().as_utf8().ok().map(|data| data.to_string()))
+            .collect::<Vec<_>>()
+            .join(", ");
+        println!(
+            "{} Signature verified against embedded certificate of {subject}",
+            color::check_mark()
+        );
+    }
 
-            // Verify the hash and handle the result
-            match location.verify() {
-                Ok(true) => {
-                    println!(
-                        "✓ Successfully verified hash for component: {}",
-                        ingredient.title
-                    );
-                }
-                Ok(false) => {
-                    return Err(Error::Validation(format!(
-                        "Hash verification failed for component: {}. The file may have been modified.",
-                        ingredient.title
-                    )));
-                }
-                Err(e) => {
-                    return Err(Error::Validation(format!(
-                        "Error verifying component {}: {}. The file may be missing or inaccessible.",
-                        ingredient.title, e
-                    )));
-                }
-            }
-        } else {
-            // For non-file URLs, try direct hash verification
-            match hash::calculate_file_hash(PathBuf::from(&ingredient.data.url)) {
-                Ok(calculated_hash) => {
-                    if calculated_hash != ingredient.data.hash {
-                        return Err(Error::Validation(format!(
-                            "Hash mismatch for ingredient: {}",
-                            ingredient.title
-                        )));
-                    }
-                    println!(
-                        "✓ Successfully verified hash for component: {}",
-                        ingredient.title
-                    );
+    // Schema violations are reported without failing verification, since the signature
+    // covers the payloads as they were created
+    report_schema_violations(manifest);
+
+    // Step 2: Verify each ingredient's hash, checking recorded sizes first
+    let sizes = ingredient_sizes(manifest)?;
+    let checksums = selection
+        .fast_cloud
+        .then(|| object_store::provider_checksums(manifest))
+        .transpose()?;
+    let recorded = ingest::ingest_policy(manifest)?;
+    let required = quorum
+        .map(|rule| rule.required.as_slice())
+        .unwrap_or_default();
+    if let Some(unknown) = selection.only.iter().chain(required).find(|name| {
+        !manifest
+            .ingredients
+            .iter()
+            .any(|ingredient| &ingredient.title == *name)
+    }) {
+        return Err(Error::Validation(format!(
+            "Manifest {id} has no ingredient named {unknown}"
+        )));
+    }
+    let (selected, skipped): (Vec<_>, Vec<_>) = manifest
+        .ingredients
+        .iter()
+        .partition(|ingredient| selection.includes(ingredient));
+    let checks: Vec<_> = selected
+        .into_iter()
+        .map(|ingredient| {
+            let (resolve, sizes, checksums, recorded) = (&resolve, &sizes, &checksums, &recorded);
+            let weight = ingredient_weight(ingredient, resolve, sizes);
+            Check::new(CheckKind::Hash, ingredient.title.clone(), move || {
+                // In quorum mode, ingredients that can't be reached are unverifiable
+                if quorum.is_some()
+                    && let Some(reason) = unreachable_ingredient(ingredient, resolve)
+                {
+                    return Err(Error::NotFound(reason));
                 }
-                Err(_) => {
-                    println!(
-                        "⚠ Warning: Component {} does not use file:// URL scheme and could not be verified directly",
-                        ingredient.title
-                    );
+                verify_ingredient(
+                    ingredient,
+                    resolve,
+                    sizes,
+                    checksums.as_ref(),
+                    recorded.as_ref(),
+                )
+                .map(|message| format!("Verifying ingredient: {}\n{message}", ingredient.title))
+            })
+            .with_weight(weight)
+        })
+        .collect();
+    let coverage = match quorum {
+        Some(rule) => {
+            let progress = Progress::new(checks.len());
+            let results = VerificationEngine::new().run_with(checks, |result| {
+                let counter = progress.record(result);
+                match &result.outcome {
+                    Outcome::Passed(message) => println!("{counter}{message}"),
+                    Outcome::Failed(Error::NotFound(reason)) => println!(
+                        "{counter}{} Could not verify {}: {reason}",
+                        color::warning_sign(),
+                        result.subject
+                    ),
+                    Outcome::Failed(e) => println!("{counter}{} {e}", color::cross_mark()),
+                    Outcome::Cancelled => {}
                 }
-            }
+            });
+            progress.summary(&results);
+            Some(CoverageReport::evaluate(id, rule, results))
         }
+        None => {
+            verification::first_failure(
+                VerificationEngine::new()
+                    .fail_fast(true)
+                    .run_with_progress(checks),
+            )?;
+            None
+        }
+    };
+    if !skipped.is_empty() {
+        println!(
+            "{} Skipped {} of {} ingredients",
+            color::warning_sign(),
+            skipped.len(),
+            manifest.ingredients.len()
+        );
+    }
+
+    if let Some(recorded) = &recorded
+        && selection.is_all()
+        && quorum.is_none()
+    {
+        ingest::verify_recorded_entries(recorded, artifact_root)?;
     }
 
     // Step 3: Verify cross-references if present
     if !manifest.cross_references.is_empty() {
         println!("Verifying cross-references...");
 
-        for cross_ref in &manifest.cross_references {
-            let linked_manifest = storage.retrieve_manifest(&cross_ref.manifest_url)?;
-            let manifest_json = serde_json::to_string(&linked_manifest)
-                .map_err(|e| Error::Serialization(e.to_string()))?;
-            let algorithm = hash::detect_hash_algorithm(&cross_ref.manifest_hash);
-            let calculated_hash =
-                hash::calculate_hash_with_algorithm(manifest_json.as_bytes(), &algorithm);
+        let checks = manifest
+            .cross_references
+            .iter()
+            .map(|cross_ref| {
+                Check::new(
+                    CheckKind::CrossReference,
+                    cross_ref.manifest_url.clone(),
+                    move || verify_cross_reference(cross_ref, storage),
+                )
+            })
+            .collect();
+        verification::first_failure(
+            VerificationEngine::new()
+                .fail_fast(true)
+                .run_with_progress(checks),
+        )?;
+    }
 
-            if calculated_hash != cross_ref.manifest_hash {
-                return Err(Error::Validation(format!(
-                    "Cross-reference verification failed for linked manifest: {}. Hash mismatch: stored={}, calculated={}",
-                    cross_ref.manifest_url, cross_ref.manifest_hash, calculated_hash
-                )));
-            }
-            println!(
-                "✓ Verified cross-reference to manifest: {}",
-                cross_ref.manifest_url
-            );
+    // Links to other manifests, added to the claim before signing or to the manifest after
+    let mut links: Vec<&Ingredient> = Vec::new();
+    for ingredient in manifest
+        .ingredients
+        .iter()
+        .chain(&claims::signed_claim(manifest).ingredients)
+    {
+        if linking::is_manifest_link(ingredient)
+            && !links.iter().any(|link| {
+                link.data.url == ingredient.data.url && link.data.hash == ingredient.data.hash
+            })
+        {
+            links.push(ingredient);
         }
     }
+    if !links.is_empty() {
+        println!("Verifying manifest links...");
+
+        let resolve = &resolve;
+        let checks = links
+            .into_iter()
+            .map(|link| {
+                Check::new(CheckKind::ManifestLink, link.title.clone(), move || {
+                    verify_manifest_link(link, storage, resolve)
+                })
+            })
+            .collect();
+        verification::first_failure(
+            VerificationEngine::new()
+                .fail_fast(true)
+                .run_with_progress(checks),
+        )?;
+    }
 
     // Step 4: Verify asset-specific requirements
-    verify_asset_specific_requirements(&manifest)?;
+    verify_asset_specific_requirements(manifest)?;
 
-    println!("✓ Manifest verification successful");
-    Ok(())
+    if coverage.as_ref().is_none_or(|coverage| coverage.passed) {
+        println!("{} Manifest verification successful", color::check_mark());
+    }
+    Ok(coverage)
 }
 
-// Verify asset-specific requirements based on the manifest content
-fn verify_asset_specific_requirements(manifest: &Manifest) -> Result<()> {
-    // Determines the asset type from the manifest contents
-    let is_dataset = is_dataset_manifest(manifest);
-    let is_model = is_model_manifest(manifest);
-    let is_software = is_software_manifest(manifest);
-    let is_evaluation = is_evaluation_manifest(manifest);
+// Prints the custom assertions of a manifest that violate their registered schemas
+fn report_schema_violations(manifest: &Manifest) {
+    let registry = match schema::SchemaRegistry::load(&schema::schemas_file()) {
+        Ok(Some(registry)) => registry,
+        Ok(None) => return,
+        Err(e) => {
+            println!(
+                "{} Assertion schemas not checked: {e}",
+                color::warning_sign()
+            );
+            return;
+        }
+    };
+    let claim = claims::signed_claim(manifest);
+    for (label, violations) in registry.violations(&claim.created_assertions) {
+        for violation in violations {
+            println!(
+                "{} Assertion {label} violates its schema: {violation}",
+                color::warning_sign()
+            );
+        }
+    }
+}
 
-    // Verify that at least one ingredient exists (except for evaluations)
-    if !is_evaluation && manifest.ingredients.is_empty() {
-        return Err(Error::Validation(
-            "Manifest must contain at least one ingredient".to_string(),
-        ));
+// Bytes hashed to verify an ingredient, so that the largest are started first: its
+// recorded size, or the size of its local file
+fn ingredient_weight(
+    ingredient: &Ingredient,
+    resolve: &(dyn Fn(PathBuf) -> PathBuf + Sync),
+    sizes: &BTreeMap<String, u64>,
+) -> u64 {
+    if let Some(size) = sizes.get(&ingredient.data.url) {
+        return *size;
     }
+    file_url_to_path(&ingredient.data.url)
+        .or_else(|| relative_url_to_path(&ingredient.data.url))
+        .and_then(|path| fs::metadata(resolve(path)).ok())
+        .map_or(0, |metadata| metadata.len())
+}
 
-    // Check for dataset, model, software, or evaluation assertion
-    if let Some(claim) = &manifest.claim_v2 {
-        if is_dataset {
-            let has_dataset_assertion = claim.created_assertions.iter().any(|assertion| {
-                matches!(assertion, Assertion::CreativeWork(creative_work) if creative_work.creative_type == "Dataset")
-            });
+// Why an ingredient can't be reached for verification, if it can't
+fn unreachable_ingredient(
+    ingredient: &Ingredient,
+    resolve: &(dyn Fn(PathBuf) -> PathBuf + Sync),
+) -> Option<String> {
+    if linking::is_manifest_link(ingredient) {
+        return None;
+    }
 
-            let has_dataset_assertion_in_claim = if !has_dataset_assertion {
-                manifest.claim.created_assertions.iter().any(|assertion| {
-                    matches!(assertion, Assertion::CreativeWork(creative_work) if creative_work.creative_type == "Dataset")
-                })
-            } else {
-                false
-            };
+    if let Some((archive_path, _)) = archive::parse_member_url(&ingredient.data.url) {
+        let archive_path = resolve(archive_path);
+        return (!archive_path.exists())
+            .then(|| format!("archive {} not found", archive_path.display()));
+    }
+    if let Some(url) = ObjectUrl::parse(&ingredient.data.url) {
+        return ObjectClient::from_env(url.store)
+            .and_then(|client| client.stat(&url))
+            .err()
+            .map(|e| format!("object not reachable: {e}"));
+    }
 
-            if !has_dataset_assertion && !has_dataset_assertion_in_claim {
-                println!(
-                    "WARNING: Dataset manifest doesn't contain a Dataset creative work assertion"
-                );
+    let path = file_url_to_path(&ingredient.data.url)
+        .or_else(|| relative_url_to_path(&ingredient.data.url))
+        .map(resolve)
+        .unwrap_or_else(|| PathBuf::from(&ingredient.data.url));
+    (!path.exists()).then(|| format!("{} not found", path.display()))
+}
 
-                return Err(Error::Validation(
-                    "Dataset manifest must contain a Dataset creative work assertion".to_string(),
-                ));
-            }
-        }
+/// Verifies an OMS manifest, the DSSE envelope written by [`create_oms_manifest`].
+///
+/// The envelope may be JSON or binary CBOR. See [`verify_oms_envelope`].
+pub fn verify_oms_manifest(
+    envelope_path: &Path,
+    public_key: &PKey<Public>,
+    artifact_root: Option<&Path>,
+) -> Result<()> {
+    let envelope = attestation::read_envelope(envelope_path)?;
+
+    println!("Verifying OMS manifest: {}", envelope_path.display());
+    verify_oms_envelope(&envelope, public_key, artifact_root)
+}
 
-        if is_model {
-            let has_model_assertion = claim.created_assertions.iter().any(|assertion| {
-                matches!(assertion, Assertion::CreativeWork(creative_work) if creative_work.creative_type == "Model")
-            });
+/// Verifies the DSSE envelope of an OMS manifest.
+///
+/// The envelope's signature is checked with `public_key`, the embedded C2PA claim is
+/// validated, and the subject digest of the statement is recomputed from the local
+/// artifacts in OMS order. Relative ingredient URLs are resolved against `artifact_root`,
+/// or against the current directory if no root is given. The payload of a detached
+/// envelope is fetched and checked first, see [`attestation::resolve_payload`].
+///
+/// # Errors
+///
+/// Returns a `Validation` error if the envelope is not an OMS statement, no signature
+/// verifies with the key, the claim is invalid or expired, or an artifact doesn't match
+/// its recorded hash or the subject digest.
+pub fn verify_oms_envelope(
+    envelope: &Envelope,
+    public_key: &PKey<Public>,
+    artifact_root: Option<&Path>,
+) -> Result<()> {
+    let envelope = attestation::resolve_payload(envelope, artifact_root)?;
+    for message in check_oms_envelope(&envelope, public_key, artifact_root)? {
+        println!("{message}");
+    }
+    Ok(())
+}
 
-            let has_model_assertion_in_claim = if !has_model_assertion {
-                manifest.claim.created_assertions.iter().any(|assertion| {
-                    matches!(assertion, Assertion::CreativeWork(creative_work) if creative_work.creative_type == "Model")
-                })
-            } else {
-                false
-            };
+/// Verifies the DSSE envelope of an OMS manifest like [`verify_oms_envelope`], returning
+/// the progress messages instead of printing them
+pub(crate) fn check_oms_envelope(
+    envelope: &Envelope,
+    public_key: &PKey<Public>,
+    artifact_root: Option<&Path>,
+) -> Result<Vec<String>> {
+    let mut messages = Vec::new();
+
+    // Step 1: Check the statement and the envelope's signature
+    let statement = in_toto::read_statement(envelope)?;
+    if statement.predicate_type != OMS_PREDICATE_TYPE {
+        return Err(Error::Validation(format!(
+            "Statement predicate type must be {OMS_PREDICATE_TYPE}"
+        )));
+    }
 
-            if !has_model_assertion && !has_model_assertion_in_claim {
-                println!("WARNING: Model manifest doesn't contain a Model creative work assertion");
+    let (alg, subject_digest) = match statement.subject.as_slice() {
+        [subject] if subject.digest.len() == 1 => subject
+            .digest
+            .iter()
+            .next()
+            .map(|(alg, digest)| (alg.clone(), digest.clone()))
+            .expect("digest has one entry"),
+        _ => {
+            return Err(Error::Validation(
+                "Statement must have a single subject with a single digest".to_string(),
+            ));
+        }
+    };
+    let hash_alg = hash::parse_algorithm(&alg)?;
+
+    let explanation = Explanation::new().line(format!(
+        "DSSE: {} signature(s) checked with key {}...",
+        envelope.signatures().len(),
+        verification::digest_prefix(&signer::key_fingerprint(public_key)?)
+    ));
+    if !envelope.verify_signature(public_key, &hash_alg)? {
+        return Err(explanation.error(Error::Validation(
+            "DSSE signature verification failed: no signature matches the public key".to_string(),
+        )));
+    }
+    messages.push(explanation.message(format!("{} DSSE signature verified", color::check_mark())));
+
+    // Step 2: Validate the embedded C2PA claim
+    let mut predicate = statement.predicate;
+    restore_integers(&mut predicate);
+    let manifest: Manifest = serde_json::from_value(predicate)
+        .map_err(|e| Error::Validation(format!("Invalid C2PA manifest in statement: {e}")))?;
+    atlas_c2pa_lib::claim::validate_claim_v2(&manifest.claim)
+        .and_then(|_| {
+            manifest
+                .claim
+                .ingredients
+                .iter()
+                .try_for_each(atlas_c2pa_lib::ingredient::validate_ingredient)
+        })
+        .map_err(Error::Validation)?;
+    if !is_model_manifest(&manifest) {
+        return Err(Error::Validation(
+            "OMS manifest must describe a model".to_string(),
+        ));
+    }
+    if signing::fips::is_enabled() {
+        signing::fips::check_manifest(&manifest)?;
+    }
+    messages.extend(check_manifest_validity(&manifest)?);
+    messages.push(format!("{} C2PA claim is valid", color::check_mark()));
 
-                return Err(Error::Validation(
-                    "Model manifest must contain a Model creative work assertion".to_string(),
-                ));
+    // Step 3: Hash the local artifacts and recompute the subject digest from them
+    let resolve = |path: PathBuf| match artifact_root {
+        Some(root) if path.is_relative() => root.join(path),
+        _ => path,
+    };
+    let sizes = ingredient_sizes(&manifest)?;
+    let mut local_ingredients = Vec::new();
+    for ingredient in &manifest.claim.ingredients {
+        messages.push(format!("Verifying ingredient: {}", ingredient.title));
+
+        let mut local = ingredient.clone();
+        if let Some((archive_path, member)) = archive::parse_member_url(&ingredient.data.url) {
+            messages.push(verify_archive_member(
+                ingredient,
+                &resolve(archive_path),
+                &member,
+            )?);
+        } else if let Some(url) = ObjectUrl::parse(&ingredient.data.url) {
+            messages.push(verify_remote_object(
+                ingredient,
+                &url,
+                sizes.get(&ingredient.data.url).copied(),
+                None,
+            )?);
+        } else {
+            let path = file_url_to_path(&ingredient.data.url)
+                .or_else(|| relative_url_to_path(&ingredient.data.url))
+                .map(resolve)
+                .unwrap_or_else(|| PathBuf::from(&ingredient.data.url));
+            local.data.hash = hash::parse_algorithm(&ingredient.data.alg)
+                .and_then(|alg| hash::calculate_file_hash_with_algorithm(&path, &alg))
+                .map_err(|e| {
+                    Error::Validation(format!(
+                        "Error verifying component {}: {e}. The file may be missing or inaccessible.",
+                        ingredient.title
+                    ))
+                })?;
+            if local.data.hash != ingredient.data.hash {
+                return Err(Error::Validation(format!(
+                    "Hash verification failed for component: {}. The file may have been modified.",
+                    ingredient.title
+                )));
             }
+            messages.push(format!(
+                "{} Successfully verified hash for component: {}",
+                color::check_mark(),
+                ingredient.title
+            ));
         }
+        local_ingredients.push(local);
+    }
 
-        if is_software {
-            let has_software_assertion = claim.created_assertions.iter().any(|assertion| {
-                matches!(assertion, Assertion::CreativeWork(creative_work) if creative_work.creative_type == "Software")
-            });
-
-            let has_software_parameters = claim.created_assertions.iter().any(|assertion| {
-                if let Assertion::Action(action_assertion) = assertion {
-                    action_assertion.actions.iter().any(|action| {
-                        if let Some(params) = &action.parameters {
-                            params.get("software_type").is_some()
-                        } else {
-                            false
-                        }
-                    })
-                } else {
-                    false
-                }
-            });
+    if generate_oms_subject_hash(&local_ingredients, &hash_alg)? != subject_digest {
+        return Err(Error::Validation(format!(
+            "Subject digest mismatch: the artifacts don't match the {alg} digest of the statement"
+        )));
+    }
+    messages.push(format!("{} Subject digest verified", color::check_mark()));
 
-            if !has_software_assertion && !has_software_parameters {
-                println!(
-                    "WARNING: Software manifest doesn't contain a Software creative work assertion or software_type parameter"
-                );
+    messages.push(format!(
+        "{} OMS manifest verification successful",
+        color::check_mark()
+    ));
+    Ok(messages)
+}
 
-                return Err(Error::Validation(
-                    "Software manifest must contain a Software creative work assertion or software_type parameter".to_string(),
-                ));
+// The protobuf Struct of an in-toto predicate stores all numbers as doubles, so integers
+// come back as `2.0`; turn them back into integers for typed deserialization
+fn restore_integers(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Number(number) => {
+            if let Some(float) = number.as_f64()
+                && float.fract() == 0.0
+                && float.abs() < 2f64.powi(53)
+            {
+                *number = (float as i64).into();
             }
         }
+        serde_json::Value::Array(values) => values.iter_mut().for_each(restore_integers),
+        serde_json::Value::Object(map) => map.values_mut().for_each(restore_integers),
+        _ => {}
+    }
+}
 
-        if is_evaluation {
-            let has_evaluation_assertion = claim.created_assertions.iter().any(|assertion| {
-                matches!(assertion, Assertion::CreativeWork(creative_work) if creative_work.creative_type == "EvaluationResult")
-            });
+// Expired manifests fail verification, manifests about to expire are flagged. Returns the
+// message to report, if any.
+fn check_manifest_validity(manifest: &Manifest) -> Result<Option<String>> {
+    let now = OffsetDateTime::now_utc();
+    let explanation = Explanation::new().line(format!("validity: checked at {now}"));
+    match validity::check_validity(manifest, now)? {
+        Validity::Expired(until) => Err(explanation.error(Error::Validation(format!(
+            "Manifest expired at {until}. It needs to be re-attested."
+        )))),
+        Validity::ValidUntil(until) if until - now <= validity::EXPIRY_WARNING_WINDOW => {
+            Ok(Some(explanation.message(format!(
+                "{} Warning: Manifest expires at {until}",
+                color::warning_sign()
+            ))))
+        }
+        Validity::ValidUntil(until) => Ok(Some(explanation.message(format!(
+            "{} Manifest valid until {until}",
+            color::check_mark()
+        )))),
+        Validity::Unbounded => Ok(None),
+    }
+}
 
-            if !has_evaluation_assertion {
+// Signatures made with keys revoked in the trust store fail verification, or are reported
+// with a warning if the trust store says so
+fn check_revoked_signatures(manifest: &Manifest) -> Result<()> {
+    let trust_store = TrustStore::load_default()?;
+    for (signature, revoked) in trust_store.revocations()?.revoked_signatures(manifest)? {
+        let message = format!(
+            "{} by key {}... signed at {}, after its revocation at {}{}",
+            signature.key_use,
+            verification::digest_prefix(&signature.fingerprint),
+            to_rfc3339_utc(signature.signed_at),
+            to_rfc3339_utc(revoked.revoked_at),
+            revoked
+                .reason
+                .map(|reason| format!(" ({reason})"))
+                .unwrap_or_default()
+        );
+        match trust_store.on_revoked {
+            OnRevoked::Fail => {
+                return Err(Error::Validation(format!("Revoked signature: {message}")));
+            }
+            OnRevoked::Warn => {
                 println!(
-                    "WARNING: Evaluation manifest doesn't contain an EvaluationResult creative work assertion"
-                );
+                    "{} Warning: revoked signature: {message}",
+                    color::warning_sign()
+                )
+            }
+        }
+    }
+    Ok(())
+}
+
+// Manifests written by earlier versions keep verifying; the steps migrating them are
+// reported without failing verification
+fn report_pending_migration(id: &str, manifest: &Manifest) -> Result<()> {
+    let steps = migration::pending_steps(manifest)?;
+    if !steps.is_empty() {
+        let steps: Vec<_> = steps.iter().map(ToString::to_string).collect();
+        println!(
+            "{} Manifest {id} is at schema version 1 ({}); `manifest upgrade --id {id}` migrates it",
+            color::warning_sign(),
+            steps.join("; ")
+        );
+    }
+    Ok(())
+}
+
+// Explains which key the claim signature verifies with, see `--explain`
+fn explain_claim_signature(manifest: &Manifest) -> Result<()> {
+    if !verification::is_explaining() {
+        return Ok(());
+    }
+
+    let line = match (
+        &manifest.claim.signature,
+        signer::signing_key_fingerprint(manifest)?,
+    ) {
+        (None, _) => "claim signature: none, the manifest is unsigned".to_string(),
+        (Some(_), Some(fingerprint)) => format!(
+            "claim signature: verified with key {}...",
+            verification::digest_prefix(&fingerprint)
+        ),
+        (Some(_), None) => format!(
+            "claim signature: not matched to a key, no {} assertion or embedded certificate verifies it",
+            signer::SIGNER_KEY_ASSERTION_LABEL
+        ),
+    };
+    Explanation::new().line(line).print();
+    Ok(())
+}
+
+// URL of a local ingredient relative to `base`; other URLs are returned unchanged
+pub(crate) fn relative_ingredient_url(url: &str, base: &Path) -> Result<String> {
+    if let Some((archive_path, member)) = archive::parse_member_url(url) {
+        return Ok(format!(
+            "{}{}{member}",
+            relative_file_url(&archive_path, base)?,
+            archive::MEMBER_SEPARATOR
+        ));
+    }
+
+    match file_url_to_path(url) {
+        Some(path) => relative_file_url(&path, base),
+        None => Ok(url.to_string()),
+    }
+}
+
+// Re-hashes an ingredient of a manifest being verified, returning the message to report
+fn verify_ingredient(
+    ingredient: &Ingredient,
+    resolve: &(dyn Fn(PathBuf) -> PathBuf + Sync),
+    sizes: &BTreeMap<String, u64>,
+    checksums: Option<&BTreeMap<String, ProviderChecksum>>,
+    recorded: Option<&ingest::IngestAssertion>,
+) -> Result<String> {
+    if linking::is_manifest_link(ingredient) {
+        return Ok(format!(
+            "{} Manifest link {} is verified with the manifest links",
+            color::check_mark(),
+            ingredient.title
+        ));
+    }
+
+    if let Some((archive_path, member)) = archive::parse_member_url(&ingredient.data.url) {
+        return verify_archive_member(ingredient, &resolve(archive_path), &member);
+    }
+    if let Some(url) = ObjectUrl::parse(&ingredient.data.url) {
+        return verify_remote_object(
+            ingredient,
+            &url,
+            sizes.get(&ingredient.data.url).copied(),
+            checksums.and_then(|checksums| checksums.get(&ingredient.data.url)),
+        );
+    }
 
-                return Err(Error::Validation(
-                    "Evaluation manifest must contain an EvaluationResult creative work assertion"
-                        .to_string(),
-                ));
+    let Some(path) = file_url_to_path(&ingredient.data.url)
+        .or_else(|| relative_url_to_path(&ingredient.data.url))
+    else {
+        // For non-file URLs, try direct hash verification
+        let calculated = hash::parse_algorithm(&ingredient.data.alg).and_then(|alg| {
+            hash::calculate_file_hash_with_algorithm(PathBuf::from(&ingredient.data.url), &alg)
+        });
+        return match calculated {
+            Ok(calculated_hash) if calculated_hash != ingredient.data.hash => {
+                Err(Explanation::new()
+                    .digests(
+                        &ingredient.data.alg,
+                        &ingredient.data.hash,
+                        &calculated_hash,
+                    )
+                    .error(Error::Validation(format!(
+                        "Hash mismatch for ingredient: {}",
+                        ingredient.title
+                    ))))
             }
+            Ok(calculated_hash) => Ok(Explanation::new()
+                .digests(
+                    &ingredient.data.alg,
+                    &ingredient.data.hash,
+                    &calculated_hash,
+                )
+                .message(format!(
+                    "{} Successfully verified hash for component: {}",
+                    color::check_mark(),
+                    ingredient.title
+                ))),
+            Err(_) => Ok(format!(
+                "{} Warning: Component {} does not use file:// URL scheme and could not be verified directly",
+                color::warning_sign(),
+                ingredient.title
+            )),
+        };
+    };
+
+    // Create ArtifactLocation for verification
+    let path = resolve(path);
+    let location = ArtifactLocation {
+        url: ingredient.data.url.clone(),
+        file_path: Some(path.clone()),
+        hash: ingredient.data.hash.clone(),
+        size: sizes.get(&ingredient.data.url).copied(),
+    };
+    let mut explanation = Explanation::new().line(format!("file: {}", path.display()));
+    if let Some(size) = location.size {
+        explanation = explanation.line(format!("size: {size} bytes recorded"));
+    }
+
+    // Report size changes without hashing; missing files are reported below
+    if let Ok(Some(mismatch)) = location.size_mismatch() {
+        return Err(explanation.error(Error::Validation(format!(
+            "Size check failed for component {}: file was {mismatch}. The file may have been modified.",
+            ingredient.title
+        ))));
+    }
+
+    // Files of ingested directories are hashed under the recorded policy
+    let computed = hash::parse_algorithm(&ingredient.data.alg).and_then(|alg| match recorded {
+        Some(recorded) => recorded.policy.hash_file(&path, &alg),
+        None => hash::calculate_file_hash_with_algorithm(&path, &alg),
+    });
+
+    match computed {
+        Ok(computed) => {
+            let explanation =
+                explanation.digests(&ingredient.data.alg, &ingredient.data.hash, &computed);
+            if computed == ingredient.data.hash {
+                Ok(explanation.message(format!(
+                    "{} Successfully verified hash for component: {}",
+                    color::check_mark(),
+                    ingredient.title
+                )))
+            } else {
+                Err(explanation.error(Error::Validation(format!(
+                    "Hash verification failed for component: {}. The file may have been modified.",
+                    ingredient.title
+                ))))
+            }
+        }
+        Err(e) => Err(explanation.error(Error::Validation(format!(
+            "Error verifying component {}: {}. The file may be missing or inaccessible.",
+            ingredient.title, e
+        )))),
+    }
+}
+
+// Fetches a cross-referenced manifest or envelope and checks its hash, returning the
+// message to report
+fn verify_cross_reference(
+    cross_ref: &CrossReference,
+    storage: &dyn StorageBackend,
+) -> Result<String> {
+    if attestation::is_envelope_reference(cross_ref) {
+        let record = attestation::verify_envelope_reference(cross_ref, storage)?;
+        return Ok(format!(
+            "{} Verified cross-reference to {} envelope: {}",
+            color::check_mark(),
+            record.kind,
+            cross_ref.manifest_url
+        ));
+    }
+
+    let linked_manifest = storage.retrieve_manifest(&cross_ref.manifest_url)?;
+    let manifest_json =
+        serde_json::to_string(&linked_manifest).map_err(|e| Error::Serialization(e.to_string()))?;
+    let algorithm = hash::detect_hash_algorithm(&cross_ref.manifest_hash);
+    let calculated_hash = hash::calculate_hash_with_algorithm(manifest_json.as_bytes(), &algorithm);
+
+    let explanation = Explanation::new().digests(
+        hash::algorithm_to_string(&algorithm),
+        &cross_ref.manifest_hash,
+        &calculated_hash,
+    );
+
+    if calculated_hash != cross_ref.manifest_hash {
+        return Err(explanation.error(Error::Validation(format!(
+            "Cross-reference verification failed for linked manifest: {}. Hash mismatch: stored={}, calculated={}",
+            cross_ref.manifest_url, cross_ref.manifest_hash, calculated_hash
+        ))));
+    }
+    Ok(explanation.message(format!(
+        "{} Verified cross-reference to manifest: {}",
+        color::check_mark(),
+        cross_ref.manifest_url
+    )))
+}
+
+// Verifies a link to another manifest: the URLs and hashes the ingredient records for the
+// linked manifest must agree, the stored manifest must still have that hash, and those of
+// its files found locally must still have theirs
+fn verify_manifest_link(
+    ingredient: &Ingredient,
+    storage: &dyn StorageBackend,
+    resolve: &(dyn Fn(PathBuf) -> PathBuf + Sync),
+) -> Result<String> {
+    let linked = ingredient.linked_ingredient.as_ref().ok_or_else(|| {
+        Error::Validation(format!(
+            "Manifest link {} names no linked manifest",
+            ingredient.title
+        ))
+    })?;
+    let data = &ingredient.data;
+    let urls = [Some(&data.url), data.linked_ingredient_url.as_ref()];
+    let hashes = [Some(&data.hash), data.linked_ingredient_hash.as_ref()];
+    if urls.into_iter().flatten().any(|url| *url != linked.url)
+        || hashes
+            .into_iter()
+            .flatten()
+            .any(|hash| *hash != linked.hash)
+    {
+        return Err(Error::Validation(format!(
+            "Manifest link {} records different URLs or hashes for the manifest it links",
+            ingredient.title
+        )));
+    }
+
+    let linked_manifest = storage.retrieve_manifest(&linked.url).map_err(|e| {
+        Error::Validation(format!(
+            "Failed to retrieve manifest {} linked by {}: {e}",
+            linked.url, ingredient.title
+        ))
+    })?;
+    let algorithm = hash::parse_algorithm(&data.alg)?;
+    let manifest_json =
+        serde_json::to_string(&linked_manifest).map_err(|e| Error::Serialization(e.to_string()))?;
+    let calculated_hash = hash::calculate_hash_with_algorithm(manifest_json.as_bytes(), &algorithm);
+    let mut explanation = Explanation::new().digests(&data.alg, &linked.hash, &calculated_hash);
+    if calculated_hash != linked.hash {
+        return Err(explanation.error(Error::Validation(format!(
+            "Hash verification failed for manifest {} linked by {}. The linked manifest changed since it was linked.",
+            linked.url, ingredient.title
+        ))));
+    }
+
+    // Files of the linked manifest are checked where they can be found, as when a dataset
+    // is verified along with the model trained on it
+    let files: Vec<&Ingredient> = claims::signed_claim(&linked_manifest)
+        .ingredients
+        .iter()
+        .filter(|file| !linking::is_manifest_link(file))
+        .collect();
+    let mut found = 0;
+    for file in &files {
+        let Some(path) = file_url_to_path(&file.data.url)
+            .or_else(|| relative_url_to_path(&file.data.url))
+            .map(resolve)
+            .filter(|path| path.is_file())
+        else {
+            continue;
+        };
+        let calculated = hash::calculate_file_hash_with_algorithm(
+            &path,
+            &hash::parse_algorithm(&file.data.alg)?,
+        )?;
+        explanation = explanation
+            .line(format!("{} of the linked manifest", path.display()))
+            .digests(&file.data.alg, &file.data.hash, &calculated);
+        if calculated != file.data.hash {
+            return Err(explanation.error(Error::Validation(format!(
+                "Hash verification failed for {} of manifest {} linked by {}. The file may have been modified.",
+                file.title, linked.url, ingredient.title
+            ))));
+        }
+        found += 1;
+    }
+
+    let files_checked = if found > 0 {
+        format!(" and {found} of its {} file(s) found locally", files.len())
+    } else {
+        String::new()
+    };
+    Ok(explanation.message(format!(
+        "{} Verified manifest {} linked by {}{files_checked}",
+        color::check_mark(),
+        linked.url,
+        ingredient.title
+    )))
+}
+
+// Re-hash an ingredient from its archive
+fn verify_archive_member(
+    ingredient: &Ingredient,
+    archive_path: &Path,
+    member: &str,
+) -> Result<String> {
+    let algorithm = hash::parse_algorithm(&ingredient.data.alg)?;
+    let current = archive::hash_member(archive_path, member, &algorithm).map_err(|e| {
+        Error::Validation(format!(
+            "Error verifying component {}: {e}. The archive may be missing or inaccessible.",
+            ingredient.title
+        ))
+    })?;
+
+    let explanation = |computed: &str| {
+        Explanation::new()
+            .line(format!("member {member} of {}", archive_path.display()))
+            .digests(&ingredient.data.alg, &ingredient.data.hash, computed)
+    };
+    match current {
+        Some(current) if current.hash == ingredient.data.hash => {
+            Ok(explanation(&current.hash).message(format!(
+                "{} Successfully verified hash for component: {}",
+                color::check_mark(),
+                ingredient.title
+            )))
+        }
+        Some(current) => Err(explanation(&current.hash).error(Error::Validation(format!(
+            "Hash verification failed for component: {}. The archive member may have been modified.",
+            ingredient.title
+        )))),
+        None => Err(Error::Validation(format!(
+            "Component {} not found in archive {}",
+            ingredient.title,
+            archive_path.display()
+        ))),
+    }
+}
+
+// Re-hash an ingredient from the recorded version of a cloud object. With a recorded
+// provider digest, the object is only re-hashed if its store no longer reports the digest.
+fn verify_remote_object(
+    ingredient: &Ingredient,
+    url: &ObjectUrl,
+    size: Option<u64>,
+    provider_checksum: Option<&ProviderChecksum>,
+) -> Result<String> {
+    let algorithm = hash::parse_algorithm(&ingredient.data.alg)?;
+    let inaccessible = |e: Error| {
+        Error::Validation(format!(
+            "Error verifying component {}: {e}. The object may be missing or inaccessible.",
+            ingredient.title
+        ))
+    };
+
+    let client = ObjectClient::from_env(url.store)?;
+    let object = client.stat(url).map_err(inaccessible)?;
+    if let Some(expected) = size
+        && expected != object.size
+    {
+        return Err(Error::Validation(format!(
+            "Size check failed for component {}: expected {expected} bytes, found {}. The object may have been modified.",
+            ingredient.title, object.size
+        )));
+    }
+
+    if let (Some(recorded), Some(reported)) = (
+        provider_checksum.filter(|checksum| checksum.is_digest()),
+        object.checksum.as_ref(),
+    ) && recorded.algorithm == reported.algorithm
+    {
+        let explanation = Explanation::new().digests(
+            &format!("provider {}", recorded.algorithm),
+            &recorded.value,
+            &reported.value,
+        );
+        if recorded.value != reported.value {
+            return Err(explanation.error(Error::Validation(format!(
+                "Provider {} checksum of component {} changed. The object may have been modified.",
+                recorded.algorithm, ingredient.title
+            ))));
+        }
+        return Ok(explanation.message(format!(
+            "{} Verified component {} by its provider {} checksum",
+            color::check_mark(),
+            ingredient.title,
+            recorded.algorithm
+        )));
+    }
+
+    let current = client
+        .hash_object(&object, &algorithm, StreamOptions::default())
+        .map_err(inaccessible)?;
+    let explanation = Explanation::new()
+        .line(format!(
+            "object: {} ({} bytes)",
+            ingredient.data.url, object.size
+        ))
+        .digests(&ingredient.data.alg, &ingredient.data.hash, &current);
+    if current != ingredient.data.hash {
+        return Err(explanation.error(Error::Validation(format!(
+            "Hash verification failed for component: {}. The object may have been modified.",
+            ingredient.title
+        ))));
+    }
+
+    Ok(explanation.message(format!(
+        "{} Successfully verified hash for component: {}",
+        color::check_mark(),
+        ingredient.title
+    )))
+}
+
+/// Returns the ingredient sizes recorded in a manifest, keyed by ingredient URL
+pub fn ingredient_sizes(manifest: &Manifest) -> Result<BTreeMap<String, u64>> {
+    let assertions = &claims::signed_claim(manifest).created_assertions;
+
+    for assertion in assertions {
+        if let Assertion::CustomAssertion(custom) = assertion
+            && custom.label == INGREDIENT_SIZES_ASSERTION_LABEL
+        {
+            return serde_json::from_value(custom.data.clone())
+                .map_err(|e| Error::Manifest(format!("Invalid ingredient sizes assertion: {e}")));
         }
     }
 
+    Ok(BTreeMap::new())
+}
+
+// Verify asset-specific requirements based on the manifest content
+fn verify_asset_specific_requirements(manifest: &Manifest) -> Result<()> {
+    // Determines the asset type from the manifest contents
+    let is_dataset = is_dataset_manifest(manifest);
+    let is_model = is_model_manifest(manifest);
+    let is_software = is_software_manifest(manifest);
+    let is_evaluation = is_evaluation_manifest(manifest);
+
+    // Verify that at least one ingredient exists (except for evaluations)
+    if !is_evaluation && manifest.ingredients.is_empty() {
+        return Err(Error::Validation(
+            "Manifest must contain at least one ingredient".to_string(),
+        ));
+    }
+
+    // Check for dataset, model, software, or evaluation assertion, in either claim layout
+    if is_dataset && !claims::has_creative_work(manifest, "Dataset") {
+        println!("WARNING: Dataset manifest doesn't contain a Dataset creative work assertion");
+
+        return Err(Error::Validation(
+            "Dataset manifest must contain a Dataset creative work assertion".to_string(),
+        ));
+    }
+
+    if is_model && !claims::has_creative_work(manifest, "Model") {
+        println!("WARNING: Model manifest doesn't contain a Model creative work assertion");
+
+        return Err(Error::Validation(
+            "Model manifest must contain a Model creative work assertion".to_string(),
+        ));
+    }
+
+    if is_software
+        && !claims::has_creative_work(manifest, "Software")
+        && !claims::has_action_parameter(manifest, "software_type")
+    {
+        println!(
+            "WARNING: Software manifest doesn't contain a Software creative work assertion or software_type parameter"
+        );
+
+        return Err(Error::Validation(
+            "Software manifest must contain a Software creative work assertion or software_type parameter".to_string(),
+        ));
+    }
+
+    if is_evaluation && !claims::has_creative_work(manifest, "EvaluationResult") {
+        println!(
+            "WARNING: Evaluation manifest doesn't contain an EvaluationResult creative work assertion"
+        );
+
+        return Err(Error::Validation(
+            "Evaluation manifest must contain an EvaluationResult creative work assertion"
+                .to_string(),
+        ));
+    }
+
     Ok(())
 }
 
@@ -818,27 +2198,21 @@ fn is_dataset_manifest(manifest: &Manifest) -> bool {
     }
 
     // Now proceed with the regular dataset checking
+    // Links to training datasets do not make a model a dataset
     let has_dataset_ingredients = manifest.ingredients.iter().any(|ingredient| {
-        ingredient.data.data_types.iter().any(|t| {
-            matches!(
-                t,
-                AssetType::Dataset
-                    | AssetType::DatasetOnnx
-                    | AssetType::DatasetTensorFlow
-                    | AssetType::DatasetPytorch
-            )
-        })
+        !linking::is_dataset_link(ingredient)
+            && ingredient.data.data_types.iter().any(|t| {
+                matches!(
+                    t,
+                    AssetType::Dataset
+                        | AssetType::DatasetOnnx
+                        | AssetType::DatasetTensorFlow
+                        | AssetType::DatasetPytorch
+                )
+            })
     });
 
-    let has_dataset_assertion = if let Some(claim) = &manifest.claim_v2 {
-        claim.created_assertions.iter().any(|assertion| {
-            matches!(assertion, Assertion::CreativeWork(creative_work) if creative_work.creative_type == "Dataset")
-        })
-    } else {
-        false
-    };
-
-    has_dataset_ingredients || has_dataset_assertion
+    has_dataset_ingredients || claims::has_creative_work(manifest, "Dataset")
 }
 
 // Helper function to determine if a manifest is for a model
@@ -857,78 +2231,29 @@ fn is_model_manifest(manifest: &Manifest) -> bool {
         })
     });
 
-    // Check for model assertion
-    let has_model_assertion = if let Some(claim) = &manifest.claim_v2 {
-        claim.created_assertions.iter().any(|assertion| {
-            matches!(assertion, Assertion::CreativeWork(creative_work) if creative_work.creative_type == "Model")
-        })
-    } else if let Some(Assertion::CreativeWork(creative_work)) = manifest
-        .claim
-        .created_assertions
-        .iter()
-        .find(|a| matches!(a, Assertion::CreativeWork(_)))
-    {
-        // Check in the old claim field as a fallback
-        creative_work.creative_type == "Model"
-    } else {
-        false
-    };
-
-    // Returns true if either condition is met
-    has_model_ingredients || has_model_assertion
+    has_model_ingredients || claims::has_creative_work(manifest, "Model")
 }
 
 // Helper function to check if a manifest is a software manifest
 fn is_software_manifest(manifest: &Manifest) -> bool {
-    // Check if any ingredients have software type
+    // Check if any ingredients have software type, other than links to software manifests
     let has_software_ingredients = manifest.ingredients.iter().any(|ingredient| {
-        ingredient
-            .data
-            .data_types
-            .iter()
-            .any(|t| matches!(t, AssetType::Generator))
+        !linking::is_software_link(ingredient)
+            && ingredient
+                .data
+                .data_types
+                .iter()
+                .any(|t| matches!(t, AssetType::Generator))
     });
 
-    // Check for software assertion
-    let has_software_assertion = if let Some(claim) = &manifest.claim_v2 {
-        claim.created_assertions.iter().any(|assertion| {
-            matches!(assertion, Assertion::CreativeWork(creative_work) if creative_work.creative_type == "Software")
-        })
-    } else {
-        false
-    };
-
-    // Check for software parameters in actions
-    let has_software_parameters = if let Some(claim) = &manifest.claim_v2 {
-        claim.created_assertions.iter().any(|assertion| {
-            if let Assertion::Action(action_assertion) = assertion {
-                action_assertion.actions.iter().any(|action| {
-                    if let Some(params) = &action.parameters {
-                        params.get("software_type").is_some()
-                    } else {
-                        false
-                    }
-                })
-            } else {
-                false
-            }
-        })
-    } else {
-        false
-    };
-
-    has_software_ingredients || has_software_assertion || has_software_parameters
+    has_software_ingredients
+        || claims::has_creative_work(manifest, "Software")
+        || claims::has_action_parameter(manifest, "software_type")
 }
 
 // Helper function to check if a manifest is an evaluation manifest
 fn is_evaluation_manifest(manifest: &Manifest) -> bool {
-    if let Some(claim) = &manifest.claim_v2 {
-        claim.created_assertions.iter().any(|assertion| {
-            matches!(assertion, Assertion::CreativeWork(creative_work) if creative_work.creative_type == "EvaluationResult")
-        })
-    } else {
-        false
-    }
+    claims::has_creative_work(manifest, "EvaluationResult")
 }
 
 /// Create a C2PA Ingredient from a path
@@ -956,7 +2281,7 @@ pub fn create_ingredient_from_path_with_algorithm(
     algorithm: &HashAlgorithm,
 ) -> Result<Ingredient> {
     let ingredient_data = IngredientData {
-        url: format!("file://{}", path.to_string_lossy()),
+        url: path_to_file_url(path),
         alg: algorithm.as_str().to_string(),
         hash: hash::calculate_file_hash_with_algorithm(path, algorithm)?,
         data_types: vec![asset_type],
@@ -967,7 +2292,7 @@ pub fn create_ingredient_from_path_with_algorithm(
     Ok(Ingredient {
         title: name.to_string(),
         format,
-        relationship: "componentOf".to_string(),
+        relationship: IngredientRelationship::ComponentOf.to_string(),
         document_id: format!("uuid:{}", Uuid::new_v4()),
         instance_id: format!("uuid:{}", Uuid::new_v4()),
         data: ingredient_data,
@@ -976,6 +2301,96 @@ pub fn create_ingredient_from_path_with_algorithm(
     })
 }
 
+/// Create a C2PA Ingredient from a member of an archive hashed with `algorithm`
+pub fn create_ingredient_from_archive_member(
+    archive_path: &Path,
+    member: &ArchiveMember,
+    name: &str,
+    asset_type: AssetType,
+    format: String,
+    algorithm: &HashAlgorithm,
+) -> Ingredient {
+    let ingredient_data = IngredientData {
+        url: archive::member_url(archive_path, &member.path),
+        alg: algorithm.as_str().to_string(),
+        hash: member.hash.clone(),
+        data_types: vec![asset_type],
+        linked_ingredient_url: None,
+        linked_ingredient_hash: None,
+    };
+
+    Ingredient {
+        title: name.to_string(),
+        format,
+        relationship: IngredientRelationship::ComponentOf.to_string(),
+        document_id: format!("uuid:{}", Uuid::new_v4()),
+        instance_id: format!("uuid:{}", Uuid::new_v4()),
+        data: ingredient_data,
+        linked_ingredient: None,
+        public_key: None,
+    }
+}
+
+/// Create a C2PA Ingredient from a file of an ingested directory hashed with `algorithm`
+pub fn create_ingredient_from_directory_entry(
+    path: &Path,
+    hash: String,
+    name: &str,
+    asset_type: AssetType,
+    format: String,
+    algorithm: &HashAlgorithm,
+) -> Ingredient {
+    let ingredient_data = IngredientData {
+        url: path_to_file_url(path),
+        alg: algorithm.as_str().to_string(),
+        hash,
+        data_types: vec![asset_type],
+        linked_ingredient_url: None,
+        linked_ingredient_hash: None,
+    };
+
+    Ingredient {
+        title: name.to_string(),
+        format,
+        relationship: IngredientRelationship::ComponentOf.to_string(),
+        document_id: format!("uuid:{}", Uuid::new_v4()),
+        instance_id: format!("uuid:{}", Uuid::new_v4()),
+        data: ingredient_data,
+        linked_ingredient: None,
+        public_key: None,
+    }
+}
+
+/// Create a C2PA Ingredient from a cloud object hashed with `algorithm`
+pub fn create_ingredient_from_remote_object(
+    object: &RemoteObject,
+    hash: String,
+    name: &str,
+    asset_type: AssetType,
+    format: String,
+    algorithm: &HashAlgorithm,
+) -> Ingredient {
+    let ingredient_data = IngredientData {
+        url: object.url.to_string(),
+        alg: algorithm.as_str().to_string(),
+        hash,
+        data_types: vec![asset_type],
+        linked_ingredient_url: None,
+        linked_ingredient_hash: None,
+    };
+
+    Ingredient {
+        title: name.to_string(),
+        format,
+        relationship: IngredientRelationship::ComponentOf.to_string(),
+        document_id: format!("uuid:{}", Uuid::new_v4()),
+        instance_id: format!("uuid:{}", Uuid::new_v4()),
+        data: ingredient_data,
+        linked_ingredient: None,
+        public_key: None,
+    }
+}
+
 /// Helper function to generate a CC attestation assertion
 fn get_cc_attestation_assertion() -> Result<CustomAssertion> {
     let report = match cc_attestation::get_report(false) {
@@ -997,18 +2412,97 @@ fn get_cc_attestation_assertion() -> Result<CustomAssertion> {
         }
     };
 
+    // embed the measured boot chain when the platform exposes a CC event log
+    let data = match event_log::read_ccel() {
+        Ok(Some(log)) => serde_json::json!({
+            "report": report,
+            "event_log": log,
+        }),
+        Ok(None) => serde_json::Value::String(report),
+        Err(e) => {
+            println!("Warning: Could not read CC event log: {e}");
+            serde_json::Value::String(report)
+        }
+    };
+
     let cc_assertion = CustomAssertion {
         label: platform,
-        data: serde_json::Value::String(report),
+        data,
     };
 
     Ok(cc_assertion)
 }
 
+/// Attaches a freshly collected CC attestation to an existing manifest.
+///
+/// This is useful when the attestation has to be collected on a different host than the one
+/// that authored the manifest. The report is bound to the hash of the manifest being attested,
+/// appended as an assertion and stored as a new version of the manifest. The updated claim is
+/// re-signed when a key is provided; a signed manifest cannot be updated without one.
+///
+/// # Returns
+///
+/// The ID under which the updated manifest was stored.
+pub fn attach_cc_attestation(
+    manifest_id: &str,
+    storage: &dyn StorageBackend,
+    key_path: Option<PathBuf>,
+    hash_alg: HashAlgorithm,
+) -> Result<String> {
+    let mut manifest = storage.retrieve_manifest(manifest_id)?;
+
+    if key_path.is_none() && manifest.claim.signature.is_some() {
+        return Err(Error::Signing(
+            "Manifest is signed; a key is required to re-sign it after attaching the attestation"
+                .to_string(),
+        ));
+    }
+
+    // bind the report to the manifest version it attests
+    let manifest_json = to_string(&manifest).map_err(|e| Error::Serialization(e.to_string()))?;
+    let manifest_hash = hash::calculate_hash_with_algorithm(manifest_json.as_bytes(), &hash_alg);
+
+    let cc_assertion = get_cc_attestation_assertion()?;
+    let attached = Assertion::CustomAssertion(CustomAssertion {
+        label: cc_assertion.label,
+        data: serde_json::json!({
+            "report": cc_assertion.data,
+            "manifest_id": manifest_id,
+            "manifest_hash": manifest_hash,
+            "alg": hash_alg.as_str(),
+        }),
+    });
+
+    manifest.claim.created_assertions.push(attached.clone());
+    manifest.claim.signature = None;
+    if let Some(claim_v2) = manifest.claim_v2.as_mut() {
+        claim_v2.created_assertions.push(attached);
+    }
+
+    if let Some(key_file) = key_path {
+        manifest.sign(key_file, hash_alg)?;
+    }
+
+    storage.store_manifest(&manifest)
+}
+
+/// Helper function to generate a TPM quote assertion
+fn get_tpm_quote_assertion(tpm: &TpmQuoteOptions) -> Result<CustomAssertion> {
+    let quote = tpm::collect_quote(&tpm.pcr_selection, &tpm.ak, None)?;
+
+    Ok(CustomAssertion {
+        label: tpm::TPM_QUOTE_ASSERTION_LABEL.to_string(),
+        data: serde_json::to_value(quote)?,
+    })
+}
+
 // Compute the OMS subject hash as specified in https://github.com/sigstore/model-transparency/blob/de2f935ad437218d577a3f39378c482bf3aafcec/src/model_signing/_signing/signing.py#L181-L186
-fn generate_oms_subject_hash(manifest: &Manifest, hash_alg: &HashAlgorithm) -> Result<String> {
+fn generate_oms_subject_hash(
+    ingredients: &[Ingredient],
+    hash_alg: &HashAlgorithm,
+) -> Result<String> {
     // generate the hash over all ingredient hashes for the model
-    if manifest.claim.ingredients.is_empty() {
+    if ingredients.is_empty() {
         return Err(Error::Validation(
             "OMS requires at least one ingredient".to_string(),
         ));
@@ -1018,7 +2512,7 @@ fn generate_oms_subject_hash(manifest: &Manifest, hash_alg: &HashAlgorithm) -> R
     // (alphabetical order of artifact name)
     // Since we cannot assume that the ingredients in the manifest are sorted
     // as expected (e.g., during verification), we sort every time we hash.
-    let mut ingredients_to_hash = manifest.claim.ingredients.clone();
+    let mut ingredients_to_hash = ingredients.to_vec();
     ingredients_to_hash.sort_by_key(|ingredient| ingredient.title.to_lowercase());
 
     let mut ingredient_hashes: Vec<u8> = Vec::new();
@@ -1053,14 +2547,31 @@ mod tests {
             author_org: Some("Test Org".to_string()),
             paths: vec![],
             ingredient_names: vec![],
+            ingredient_relationships: vec![],
+            expand_archives: false,
+            profile_data: false,
+            max_sample_bytes: profile::DEFAULT_MAX_SAMPLE_BYTES,
+            stream_connections: crate::manifest::object_store::DEFAULT_CONNECTIONS,
+            ingest_policy: crate::manifest::ingest::IngestPolicy::default(),
+            path_base: None,
+            dataset_ids: Vec::new(),
             hash_alg: HashAlgorithm::Sha384,
+            valid_until: None,
+            created_at: None,
             key_path: Some(tmp_dir.path().join("test_key.pem")),
+            pq_key_path: None,
             output_encoding: "json".to_string(),
+            output_path: None,
             print: false,
             storage: None,
             with_cc: false,
+            tpm: None,
             linked_manifests: None,
+            encryption: None,
+            content_addressed_id: false,
+            force_new: false,
             custom_fields: None,
+            inherited_assertions: vec![],
             software_type: None,
             version: None,
         }
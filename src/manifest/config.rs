@@ -1,10 +1,35 @@
+use crate::cc_attestation::tpm::TpmQuoteOptions;
+use crate::error::{Error, Result};
+use crate::manifest::common::LICENSE_ASSERTION_LABEL;
+use crate::manifest::encryption::AssertionEncryption;
+use crate::manifest::ingest::IngestPolicy;
+use crate::manifest::naming;
+use crate::manifest::relationship::IngredientRelationship;
 use crate::storage::traits::StorageBackend;
+use atlas_c2pa_lib::assertion::CustomAssertion;
 use atlas_c2pa_lib::cose::HashAlgorithm;
 use std::path::PathBuf;
+use time::OffsetDateTime;
 
 pub struct ManifestCreationConfig {
     pub paths: Vec<PathBuf>,
     pub ingredient_names: Vec<String>,
+    // C2PA relationship of each path's ingredients, componentOf for paths without one
+    pub ingredient_relationships: Vec<IngredientRelationship>,
+    // Record the members of tar and zip archives as individual ingredients
+    pub expand_archives: bool,
+    // Record a profile of supported dataset files in an assertion
+    pub profile_data: bool,
+    // Upper bound on the bytes read when profiling text dataset files
+    pub max_sample_bytes: u64,
+    // Concurrent ranged reads per object when hashing s3:// and gs:// ingredients
+    pub stream_connections: usize,
+    // Handling of links, special files and empty directories in directory paths
+    pub ingest_policy: IngestPolicy,
+    // Record local ingredient URLs relative to this directory
+    pub path_base: Option<PathBuf>,
+    // Dataset manifests the model was trained on, linked when the manifest is created
+    pub dataset_ids: Vec<String>,
     pub name: String,
     pub author_org: Option<String>,
     pub author_name: Option<String>,
@@ -13,14 +38,32 @@ pub struct ManifestCreationConfig {
     pub storage: Option<&'static dyn StorageBackend>,
     pub print: bool,
     pub output_encoding: String,
+    // Write output to a file instead of stdout
+    pub output_path: Option<PathBuf>,
     pub key_path: Option<PathBuf>,
+    // ML-DSA key for hybrid signing, alongside key_path
+    pub pq_key_path: Option<PathBuf>,
     pub hash_alg: HashAlgorithm,
+    // Expiry recorded in a validity assertion
+    pub valid_until: Option<OffsetDateTime>,
+    // Creation time recorded instead of the current time, for historical artifacts
+    pub created_at: Option<OffsetDateTime>,
     pub with_cc: bool,
+    // TPM quote collection, for hosts without a TEE
+    pub tpm: Option<TpmQuoteOptions>,
     // Software-specific fields
     pub software_type: Option<String>,
     pub version: Option<String>,
+    // Assertions encrypted to recipients before signing
+    pub encryption: Option<AssertionEncryption>,
+    // Derive the manifest ID from its content instead of a random UUID
+    pub content_addressed_id: bool,
+    // Store the manifest even if an identical one is already stored
+    pub force_new: bool,
     // Generic custom_fields for extensions
     pub custom_fields: Option<serde_json::Value>,
+    // Custom assertions taken from an earlier manifest, see `template`
+    pub inherited_assertions: Vec<CustomAssertion>,
 }
 
 impl ManifestCreationConfig {
@@ -28,6 +71,14 @@ impl ManifestCreationConfig {
         Self {
             paths: self.paths.clone(),
             ingredient_names: self.ingredient_names.clone(),
+            ingredient_relationships: self.ingredient_relationships.clone(),
+            expand_archives: self.expand_archives,
+            profile_data: self.profile_data,
+            max_sample_bytes: self.max_sample_bytes,
+            stream_connections: self.stream_connections,
+            ingest_policy: self.ingest_policy,
+            path_base: self.path_base.clone(),
+            dataset_ids: self.dataset_ids.clone(),
             name: self.name.clone(),
             author_org: self.author_org.clone(),
             author_name: self.author_name.clone(),
@@ -36,12 +87,53 @@ impl ManifestCreationConfig {
             storage: self.storage,
             print: self.print,
             output_encoding: self.output_encoding.clone(),
+            output_path: self.output_path.clone(),
             key_path: self.key_path.clone(),
+            pq_key_path: self.pq_key_path.clone(),
             hash_alg: self.hash_alg.clone(),
+            valid_until: self.valid_until,
+            created_at: self.created_at,
             with_cc: self.with_cc,
+            tpm: self.tpm.clone(),
             software_type: self.software_type.clone(),
             version: self.version.clone(),
+            encryption: self.encryption.clone(),
+            content_addressed_id: self.content_addressed_id,
+            force_new: self.force_new,
             custom_fields: self.custom_fields.clone(),
+            inherited_assertions: self.inherited_assertions.clone(),
         }
     }
+
+    /// Adds a path with its ingredient name. Paths given without a name are named first, so
+    /// that names stay aligned with their paths.
+    pub fn add_ingredient(&mut self, path: PathBuf, name: String) -> Result<()> {
+        self.ingredient_names = naming::ingredient_names(
+            &self.paths,
+            &self.ingredient_names,
+            self.path_base.as_deref(),
+        )?;
+        self.paths.push(path);
+        self.ingredient_names.push(name);
+        Ok(())
+    }
+
+    /// Records the SPDX license identifier of the asset in a license assertion
+    pub fn set_license(&mut self, license: &str) -> Result<()> {
+        self.set_custom_field(
+            LICENSE_ASSERTION_LABEL,
+            serde_json::json!({ "identifier": license }),
+        )
+    }
+
+    /// Sets a field of `custom_fields`, which must be a JSON object if set
+    pub fn set_custom_field(&mut self, key: &str, value: serde_json::Value) -> Result<()> {
+        let fields = self
+            .custom_fields
+            .get_or_insert_with(|| serde_json::json!({}))
+            .as_object_mut()
+            .ok_or_else(|| Error::Validation("Custom fields must be a JSON object".to_string()))?;
+        fields.insert(key.to_string(), value);
+        Ok(())
+    }
 }
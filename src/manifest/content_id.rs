@@ -0,0 +1,391 @@
+//! Content-addressed manifest IDs.
+//!
+//! By default manifests get a random `urn:c2pa:<UUID>` ID. With `--content-addressed-id`,
+//! the ID is derived from the manifest content instead, as `urn:c2pa:sha256-<hex>`, so that
+//! creating the same manifest twice, or in two stores, yields the same ID. The hash is
+//! taken over a canonical form of the manifest that leaves out everything differing
+//! between two creations of the same content: instance and document IDs, creation times,
+//! signatures and cross-references added after creation.
+//!
+//! The same canonical form detects duplicates: a new manifest identical to a stored one is
+//! not stored again unless `--force-new` is given, see [`find_duplicate`].
+
+use crate::error::{Error, Result};
+use crate::manifest::claims;
+use crate::storage::traits::StorageBackend;
+use atlas_c2pa_lib::manifest::Manifest;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+/// Prefix of the content part of a content-addressed ID
+pub const CONTENT_ID_PREFIX: &str = "sha256-";
+
+// Fields left out of the canonical form, at the top level of the manifest, in its claims
+// and in their ingredients. Assertion payloads are kept whole, whatever their keys.
+const MANIFEST_VOLATILE_KEYS: [&str; 3] = ["instance_id", "created_at", "cross_references"];
+const CLAIM_VOLATILE_KEYS: [&str; 3] = ["instance_id", "created_at", "signature"];
+const INGREDIENT_VOLATILE_KEYS: [&str; 2] = ["instance_id", "document_id"];
+
+/// Returns true if `id` (with or without `urn:c2pa:`) has the form `sha256-<hex>`
+///
+/// # Examples
+///
+/// ```
+/// use atlas_cli::manifest::content_id::is_content_id;
+///
+/// let id = format!("urn:c2pa:sha256-{}", "ab".repeat(32));
+/// assert!(is_content_id(&id));
+/// assert!(is_content_id(id.trim_start_matches("urn:c2pa:")));
+/// assert!(!is_content_id("urn:c2pa:sha256-abc"));
+/// ```
+pub fn is_content_id(id: &str) -> bool {
+    id.strip_prefix("urn:c2pa:")
+        .unwrap_or(id)
+        .strip_prefix(CONTENT_ID_PREFIX)
+        .is_some_and(|digest| {
+            digest.len() == 64
+                && digest
+                    .chars()
+                    .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c))
+        })
+}
+
+/// Canonical form of a manifest: its JSON with volatile fields removed and keys sorted
+pub fn canonical_form(manifest: &Manifest) -> Result<Vec<u8>> {
+    fn remove(value: &mut Value, keys: &[&str]) {
+        if let Some(map) = value.as_object_mut() {
+            map.retain(|key, _| !keys.contains(&key.as_str()));
+        }
+    }
+    // Maps keep their keys sorted unless serde_json's `preserve_order` feature is enabled,
+    // so they are sorted here rather than relying on it
+    fn sort_keys(value: Value) -> Value {
+        match value {
+            Value::Object(map) => {
+                let mut entries: Vec<_> = map.into_iter().collect();
+                entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+                Value::Object(
+                    entries
+                        .into_iter()
+                        .map(|(key, value)| (key, sort_keys(value)))
+                        .collect(),
+                )
+            }
+            Value::Array(values) => Value::Array(values.into_iter().map(sort_keys).collect()),
+            value => value,
+        }
+    }
+    fn strip_ingredients(value: &mut Value) {
+        if let Some(ingredients) = value.get_mut("ingredients").and_then(Value::as_array_mut) {
+            ingredients
+                .iter_mut()
+                .for_each(|ingredient| remove(ingredient, &INGREDIENT_VOLATILE_KEYS));
+        }
+    }
+
+    // The assertions made by signing hold signatures too
+    let mut manifest = manifest.clone();
+    manifest
+        .claim
+        .created_assertions
+        .retain(|assertion| !claims::is_signing_assertion(assertion));
+
+    let mut value = serde_json::to_value(&manifest)?;
+    remove(&mut value, &MANIFEST_VOLATILE_KEYS);
+    strip_ingredients(&mut value);
+    for key in ["claim", "claim_v2"] {
+        if let Some(claim) = value.get_mut(key) {
+            remove(claim, &CLAIM_VOLATILE_KEYS);
+            strip_ingredients(claim);
+        }
+    }
+    serde_json::to_vec(&sort_keys(value)).map_err(|e| Error::Serialization(e.to_string()))
+}
+
+/// Derives the content-addressed ID of a manifest
+pub fn content_id(manifest: &Manifest) -> Result<String> {
+    let digest = Sha256::digest(canonical_form(manifest)?);
+    Ok(format!(
+        "urn:c2pa:{CONTENT_ID_PREFIX}{}",
+        hex::encode(digest)
+    ))
+}
+
+/// Replaces the IDs of an unsigned manifest and its claims with its content-addressed ID
+pub fn assign_content_id(manifest: &mut Manifest) -> Result<String> {
+    let id = content_id(manifest)?;
+    manifest.instance_id = id.clone();
+    manifest.claim.instance_id = id.clone();
+    if let Some(claim) = manifest.claim_v2.as_mut() {
+        claim.instance_id = id.clone();
+    }
+    Ok(id)
+}
+
+/// Returns the ID of a stored manifest with the same canonical form as `manifest`.
+///
+/// Only stored manifests with the same title are compared, and a signed manifest is never
+/// a duplicate of an unsigned one. Backends that can't list their manifests have no
+/// duplicates. Entries that are missing or corrupt are skipped, while errors reaching the
+/// backend are returned.
+pub fn find_duplicate(storage: &dyn StorageBackend, manifest: &Manifest) -> Result<Option<String>> {
+    if !storage.supports_listing() {
+        return Ok(None);
+    }
+    let canonical = canonical_form(manifest)?;
+    let signed = claims::is_signed(manifest);
+
+    for metadata in storage.list_manifests()? {
+        // Backends recording the signing state spare fetching manifests that can't match
+        if metadata.name != manifest.title || metadata.signed.is_some_and(|s| s != signed) {
+            continue;
+        }
+        // Entries removed since listing, or that can't be decoded, are left to fsck, but an
+        // unreachable backend must not pass for one without duplicates
+        let existing = match storage.retrieve_manifest(&metadata.id) {
+            Ok(existing) => existing,
+            Err(
+                Error::NotFound(_)
+                | Error::Storage(_)
+                | Error::Serialization(_)
+                | Error::Json(_)
+                | Error::Validation(_)
+                | Error::Manifest(_),
+            ) => continue,
+            Err(e) => return Err(e),
+        };
+        if claims::is_signed(&existing) == signed && canonical_form(&existing)? == canonical {
+            return Ok(Some(metadata.id));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest::common::AssetKind;
+    use crate::tests::common::{
+        ManifestBuilder, MockStorageBackend, create_test_ingredient_internal,
+        create_test_manifest_internal,
+    };
+    use atlas_c2pa_lib::assertion::Assertion;
+    use atlas_c2pa_lib::asset_type::AssetType;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_content_addressed_ids() -> Result<()> {
+        use crate::manifest::common::create_manifest;
+        use crate::manifest::config::ManifestCreationConfig;
+        use crate::manifest::{ensure_c2pa_urn, validate_manifest_id};
+        use atlas_c2pa_lib::cose::HashAlgorithm;
+
+        let dir = tempdir()?;
+        let model_path = dir.path().join("model.onnx");
+        std::fs::write(&model_path, b"weights")?;
+
+        let storage: &'static MockStorageBackend =
+            Box::leak(Box::new(MockStorageBackend::new_empty()));
+        let config = |description: &str| ManifestCreationConfig {
+            paths: vec![model_path.clone()],
+            ingredient_names: vec!["Model".to_string()],
+            ingredient_relationships: Vec::new(),
+            expand_archives: false,
+            profile_data: false,
+            max_sample_bytes: crate::manifest::profile::DEFAULT_MAX_SAMPLE_BYTES,
+            stream_connections: crate::manifest::object_store::DEFAULT_CONNECTIONS,
+            ingest_policy: crate::manifest::ingest::IngestPolicy::default(),
+            path_base: None,
+            dataset_ids: Vec::new(),
+            name: "Model".to_string(),
+            author_org: None,
+            author_name: None,
+            description: Some(description.to_string()),
+            linked_manifests: None,
+            storage: Some(storage),
+            print: false,
+            output_encoding: "json".to_string(),
+            output_path: None,
+            key_path: None,
+            pq_key_path: None,
+            hash_alg: HashAlgorithm::Sha384,
+            valid_until: None,
+            created_at: None,
+            with_cc: false,
+            tpm: None,
+            software_type: None,
+            version: None,
+            encryption: None,
+            content_addressed_id: true,
+            force_new: false,
+            custom_fields: None,
+            inherited_assertions: vec![],
+        };
+
+        // Creating the same manifest twice yields the same ID
+        create_manifest(config("v1"), AssetKind::Model)?;
+        create_manifest(config("v1"), AssetKind::Model)?;
+        let ids = storage.list_manifests()?;
+        assert_eq!(ids.len(), 1);
+        let id = ids[0].id.clone();
+        assert!(is_content_id(&id));
+        validate_manifest_id(&id)?;
+        assert_eq!(ensure_c2pa_urn(id.trim_start_matches("urn:c2pa:")), id);
+
+        let manifest = storage.retrieve_manifest(&id)?;
+        assert_eq!(manifest.claim_v2.as_ref().unwrap().instance_id, id);
+        assert_eq!(content_id(&manifest)?, id);
+
+        // Different content, different ID
+        create_manifest(config("v2"), AssetKind::Model)?;
+        assert_eq!(storage.list_manifests()?.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_content_id_covers_assertion_data() -> Result<()> {
+        let dir = tempdir()?;
+        let model_path = dir.path().join("model.onnx");
+        std::fs::write(&model_path, b"weights")?;
+        let manifest = |id: &str| -> Result<Manifest> {
+            let ingredient =
+                create_test_ingredient_internal(&model_path, "Model", AssetType::Model, "onnx")?;
+            create_test_manifest_internal(
+                id.to_string(),
+                vec![ingredient],
+                "Model",
+                AssetKind::Model,
+            )
+        };
+
+        // IDs and creation times of the manifest, its claims and ingredients are left out
+        let first = manifest("urn:c2pa:first")?;
+        let mut second = manifest("urn:c2pa:second")?;
+        second.claim.signature = Some("signature".to_string());
+        assert_eq!(content_id(&first)?, content_id(&second)?);
+
+        // but assertion payloads count in full, even keys named like those fields
+        for key in ["signature", "created_at", "instance_id"] {
+            let mut changed = first.clone();
+            for claim in std::iter::once(&mut changed.claim).chain(changed.claim_v2.as_mut()) {
+                if let Assertion::Action(actions) = &mut claim.created_assertions[1] {
+                    actions.actions[0].parameters = Some(serde_json::json!({ key: "changed" }));
+                }
+            }
+            assert_ne!(content_id(&first)?, content_id(&changed)?, "{key}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_duplicate_manifests_are_not_stored_again() -> Result<()> {
+        use crate::manifest::common::create_manifest;
+        use crate::manifest::config::ManifestCreationConfig;
+        use atlas_c2pa_lib::cose::HashAlgorithm;
+
+        let dir = tempdir()?;
+        let dataset_path = dir.path().join("data.csv");
+        std::fs::write(&dataset_path, b"x,y\n1,2\n")?;
+
+        let storage: &'static MockStorageBackend =
+            Box::leak(Box::new(MockStorageBackend::new_empty()));
+        let config = |name: &str, force_new: bool| ManifestCreationConfig {
+            paths: vec![dataset_path.clone()],
+            ingredient_names: vec!["data".to_string()],
+            ingredient_relationships: Vec::new(),
+            expand_archives: false,
+            profile_data: false,
+            max_sample_bytes: crate::manifest::profile::DEFAULT_MAX_SAMPLE_BYTES,
+            stream_connections: crate::manifest::object_store::DEFAULT_CONNECTIONS,
+            ingest_policy: crate::manifest::ingest::IngestPolicy::default(),
+            path_base: None,
+            dataset_ids: Vec::new(),
+            name: name.to_string(),
+            author_org: None,
+            author_name: None,
+            description: None,
+            linked_manifests: None,
+            storage: Some(storage),
+            print: false,
+            output_encoding: "json".to_string(),
+            output_path: None,
+            key_path: None,
+            pq_key_path: None,
+            hash_alg: HashAlgorithm::Sha384,
+            valid_until: None,
+            created_at: None,
+            with_cc: false,
+            tpm: None,
+            software_type: None,
+            version: None,
+            encryption: None,
+            content_addressed_id: false,
+            force_new,
+            custom_fields: None,
+            inherited_assertions: vec![],
+        };
+
+        // A rerun creating the same manifest returns the stored one
+        create_manifest(config("CI Dataset", false), AssetKind::Dataset)?;
+        create_manifest(config("CI Dataset", false), AssetKind::Dataset)?;
+        assert_eq!(storage.list_manifests()?.len(), 1);
+
+        create_manifest(config("Other Dataset", false), AssetKind::Dataset)?;
+        assert_eq!(storage.list_manifests()?.len(), 2);
+
+        create_manifest(config("CI Dataset", true), AssetKind::Dataset)?;
+        assert_eq!(storage.list_manifests()?.len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_duplicate_skips_only_unreadable_entries() -> Result<()> {
+        use crate::storage::traits::ManifestMetadata;
+
+        // Lists the manifests of a mock store but fails to retrieve them
+        struct FailingStorage {
+            inner: MockStorageBackend,
+            error: fn() -> Error,
+        }
+        impl StorageBackend for FailingStorage {
+            fn get_base_uri(&self) -> String {
+                self.inner.get_base_uri()
+            }
+            fn store_manifest(&self, manifest: &Manifest) -> Result<String> {
+                self.inner.store_manifest(manifest)
+            }
+            fn retrieve_manifest(&self, _id: &str) -> Result<Manifest> {
+                Err((self.error)())
+            }
+            fn list_manifests(&self) -> Result<Vec<ManifestMetadata>> {
+                self.inner.list_manifests()
+            }
+            fn delete_manifest(&self, id: &str) -> Result<()> {
+                self.inner.delete_manifest(id)
+            }
+            fn as_any(&self) -> &dyn std::any::Any {
+                self
+            }
+        }
+
+        let manifest = ManifestBuilder::new("Duplicate").build();
+        let storage = |error: fn() -> Error| FailingStorage {
+            inner: MockStorageBackend::new(manifest.clone()),
+            error,
+        };
+
+        // Missing and corrupt entries are not duplicates
+        let missing = storage(|| Error::NotFound("gone".to_string()));
+        assert_eq!(find_duplicate(&missing, &manifest)?, None);
+        let corrupt = storage(|| Error::Serialization("truncated".to_string()));
+        assert_eq!(find_duplicate(&corrupt, &manifest)?, None);
+
+        // but an unreachable backend is an error, not a store without duplicates
+        let unreachable = storage(|| Error::StorageUnavailable("timed out".to_string()));
+        assert!(matches!(
+            find_duplicate(&unreachable, &manifest),
+            Err(Error::StorageUnavailable(_))
+        ));
+        Ok(())
+    }
+}
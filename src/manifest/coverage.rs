@@ -0,0 +1,241 @@
+//! Partial verification with quorum rules.
+//!
+//! The shards of a distributed dataset don't all have to be reachable at once, for
+//! example when some live on offline storage tiers. In quorum mode, ingredients that can't
+//! be reached are recorded as unverifiable instead of failing verification, which passes
+//! when a configured fraction of the ingredients, and every explicitly required one,
+//! verified. Ingredients that were reached but don't match their hash always fail it.
+
+use crate::error::{Error, Result};
+use crate::manifest::verification::{CheckResult, Outcome};
+use serde::Serialize;
+use std::fmt::Write as _;
+
+/// When a partially verified manifest passes
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuorumRule {
+    /// Smallest fraction of the ingredients that must verify, between 0 and 1
+    pub min_fraction: f64,
+    /// Names of ingredients that must verify
+    pub required: Vec<String>,
+}
+
+impl QuorumRule {
+    /// Parses a fraction given as a number between 0 and 1 or as a percentage, e.g. `0.9`
+    /// or `90%`
+    pub fn parse_fraction(value: &str) -> Result<f64> {
+        let invalid = || {
+            Error::Validation(format!(
+                "Invalid quorum {value}: expected a fraction between 0 and 1 or a percentage"
+            ))
+        };
+        let fraction = match value.trim().strip_suffix('%') {
+            Some(percent) => percent.trim().parse::<f64>().map_err(|_| invalid())? / 100.0,
+            None => value.trim().parse::<f64>().map_err(|_| invalid())?,
+        };
+
+        if (0.0..=1.0).contains(&fraction) {
+            Ok(fraction)
+        } else {
+            Err(invalid())
+        }
+    }
+}
+
+/// An ingredient that didn't verify, and why
+#[derive(Debug, Clone, Serialize)]
+pub struct IngredientProblem {
+    pub name: String,
+    pub reason: String,
+}
+
+/// Which ingredients of a manifest verified, and whether that meets the quorum rule
+#[derive(Debug, Clone, Serialize)]
+pub struct CoverageReport {
+    pub manifest_id: String,
+    /// Number of ingredients checked
+    pub total: usize,
+    pub verified: Vec<String>,
+    /// Ingredients that couldn't be reached
+    pub unverifiable: Vec<IngredientProblem>,
+    /// Ingredients that were reached but don't match their recorded hash or size
+    pub failed: Vec<IngredientProblem>,
+    /// Fraction of the ingredients that verified
+    pub coverage: f64,
+    pub min_fraction: f64,
+    /// Required ingredients that didn't verify
+    pub missing_required: Vec<String>,
+    pub passed: bool,
+}
+
+impl CoverageReport {
+    /// Evaluates the results of the ingredient checks of a manifest against `rule`.
+    ///
+    /// Checks that failed with [`Error::NotFound`] count as unverifiable, other failures
+    /// as failed.
+    pub fn evaluate(manifest_id: &str, rule: &QuorumRule, results: Vec<CheckResult>) -> Self {
+        let total = results.len();
+        let mut verified = Vec::new();
+        let mut unverifiable = Vec::new();
+        let mut failed = Vec::new();
+
+        for result in results {
+            match result.outcome {
+                Outcome::Passed(_) => verified.push(result.subject),
+                Outcome::Failed(Error::NotFound(reason)) => unverifiable.push(IngredientProblem {
+                    name: result.subject,
+                    reason,
+                }),
+                Outcome::Failed(e) => failed.push(IngredientProblem {
+                    name: result.subject,
+                    reason: e.to_string(),
+                }),
+                Outcome::Cancelled => unverifiable.push(IngredientProblem {
+                    name: result.subject,
+                    reason: "verification was cancelled".to_string(),
+                }),
+            }
+        }
+
+        let coverage = if total == 0 {
+            1.0
+        } else {
+            verified.len() as f64 / total as f64
+        };
+        let missing_required: Vec<String> = rule
+            .required
+            .iter()
+            .filter(|name| !verified.contains(name))
+            .cloned()
+            .collect();
+        let passed =
+            failed.is_empty() && missing_required.is_empty() && coverage >= rule.min_fraction;
+
+        Self {
+            manifest_id: manifest_id.to_string(),
+            total,
+            verified,
+            unverifiable,
+            failed,
+            coverage,
+            min_fraction: rule.min_fraction,
+            missing_required,
+            passed,
+        }
+    }
+
+    /// Renders the report for the terminal
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "Coverage of manifest {}:", self.manifest_id);
+        let _ = writeln!(
+            out,
+            "  {} of {} ingredients verified ({:.1}%, quorum {:.1}%)",
+            self.verified.len(),
+            self.total,
+            self.coverage * 100.0,
+            self.min_fraction * 100.0
+        );
+        for problem in &self.unverifiable {
+            let _ = writeln!(out, "  unverifiable: {}: {}", problem.name, problem.reason);
+        }
+        for problem in &self.failed {
+            let _ = writeln!(out, "  failed: {}: {}", problem.name, problem.reason);
+        }
+        for name in &self.missing_required {
+            let _ = writeln!(out, "  required but not verified: {name}");
+        }
+        let _ = writeln!(
+            out,
+            "  {}",
+            if self.passed {
+                "quorum met"
+            } else {
+                "quorum not met"
+            }
+        );
+        out
+    }
+
+    /// Fails unless the quorum rule was met
+    pub fn into_result(self) -> Result<()> {
+        if self.passed {
+            return Ok(());
+        }
+        Err(Error::Validation(format!(
+            "Verification quorum not met for manifest {}: {} of {} ingredients verified, {} failed, {} required ingredient(s) not verified",
+            self.manifest_id,
+            self.verified.len(),
+            self.total,
+            self.failed.len(),
+            self.missing_required.len()
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest::verification::CheckKind;
+
+    fn result(name: &str, outcome: Outcome) -> CheckResult {
+        CheckResult {
+            kind: CheckKind::Hash,
+            subject: name.to_string(),
+            outcome,
+        }
+    }
+
+    fn results() -> Vec<CheckResult> {
+        vec![
+            result("shard-0", Outcome::Passed(String::new())),
+            result("shard-1", Outcome::Passed(String::new())),
+            result("shard-2", Outcome::Passed(String::new())),
+            result(
+                "shard-3",
+                Outcome::Failed(Error::NotFound("file not found".to_string())),
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_parse_fraction() {
+        assert_eq!(QuorumRule::parse_fraction("0.9").unwrap(), 0.9);
+        assert_eq!(QuorumRule::parse_fraction("75%").unwrap(), 0.75);
+        assert!(QuorumRule::parse_fraction("1.5").is_err());
+        assert!(QuorumRule::parse_fraction("most").is_err());
+    }
+
+    #[test]
+    fn test_quorum_of_fraction() {
+        let rule = |min_fraction| QuorumRule {
+            min_fraction,
+            required: Vec::new(),
+        };
+
+        let report = CoverageReport::evaluate("urn:c2pa:test", &rule(0.75), results());
+        assert!(report.passed);
+        assert_eq!(report.unverifiable[0].name, "shard-3");
+        assert!(!CoverageReport::evaluate("urn:c2pa:test", &rule(0.8), results()).passed);
+    }
+
+    #[test]
+    fn test_required_ingredients_and_failures() {
+        let rule = QuorumRule {
+            min_fraction: 0.0,
+            required: vec!["shard-3".to_string()],
+        };
+        let report = CoverageReport::evaluate("urn:c2pa:test", &rule, results());
+        assert_eq!(report.missing_required, vec!["shard-3".to_string()]);
+        assert!(report.into_result().is_err());
+
+        // Hash mismatches fail verification whatever the quorum
+        let mut failing = results();
+        failing[0].outcome = Outcome::Failed(Error::Validation("Hash mismatch".to_string()));
+        let rule = QuorumRule {
+            min_fraction: 0.0,
+            required: Vec::new(),
+        };
+        assert!(!CoverageReport::evaluate("urn:c2pa:test", &rule, failing).passed);
+    }
+}
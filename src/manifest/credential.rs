@@ -0,0 +1,468 @@
+//! Author identities as DIDs, and manifests as W3C Verifiable Credentials.
+//!
+//! Authors and reviewers may be given as decentralized identifiers (`did:<method>:<id>`)
+//! instead of free-text names; such values are checked against the DID syntax when a
+//! manifest is created or approved. `manifest export-vc` exports the authorship and
+//! approvals of a manifest as a Verifiable Credential (data model 2.0) secured with JOSE,
+//! so that parties outside the Atlas toolchain can check the identity claims with standard
+//! VC tooling. The issuer is a DID; for Ed25519 and P-256 keys it defaults to the
+//! `did:key` of the signing key.
+
+use crate::error::{Error, Result};
+use crate::manifest::{approval, attestation, claims};
+use crate::signing::{self, SecurePrivateKey};
+use crate::storage::traits::StorageBackend;
+use atlas_c2pa_lib::assertion::Assertion;
+use atlas_c2pa_lib::cose::HashAlgorithm;
+use atlas_c2pa_lib::manifest::Manifest;
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use openssl::bn::{BigNum, BigNumContext};
+use openssl::ec::PointConversionForm;
+use openssl::ecdsa::EcdsaSig;
+use openssl::hash::MessageDigest;
+use openssl::nid::Nid;
+use openssl::pkey::{HasPublic, Id, PKey, PKeyRef, Public};
+use openssl::sign::{Signer, Verifier};
+use serde_json::{Value, json};
+use std::path::Path;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+/// Base context of W3C Verifiable Credentials 2.0
+pub const VC_CONTEXT: &str = "https://www.w3.org/ns/credentials/v2";
+
+/// Credential type of exported manifests, besides `VerifiableCredential`
+pub const CREDENTIAL_TYPE: &str = "AtlasProvenanceCredential";
+
+/// JOSE `typ` of credentials secured as JWTs
+pub const VC_JWT_TYPE: &str = "vc+jwt";
+
+const BASE58_ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// Whether a value is meant as a DID rather than a name
+pub fn is_did(value: &str) -> bool {
+    value.starts_with("did:")
+}
+
+/// Checks a DID against the syntax of DID Core: `did:<method>:<method-specific-id>`, with a
+/// method name of lowercase letters and digits
+pub fn validate_did(did: &str) -> Result<()> {
+    let invalid = |reason: &str| Err(Error::Validation(format!("Invalid DID {did}: {reason}")));
+
+    let Some(rest) = did.strip_prefix("did:") else {
+        return invalid("it must start with did:");
+    };
+    let Some((method, id)) = rest.split_once(':') else {
+        return invalid("expected did:<method>:<id>");
+    };
+    if method.is_empty()
+        || !method
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+    {
+        return invalid("the method must be lowercase letters and digits");
+    }
+    if id.is_empty() || id.ends_with(':') {
+        return invalid("the method-specific ID must not be empty or end with ':'");
+    }
+
+    let bytes = id.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                if !bytes
+                    .get(i + 1..i + 3)
+                    .is_some_and(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+                {
+                    return invalid("bad percent-encoding");
+                }
+                i += 3;
+            }
+            c if c.is_ascii_alphanumeric() || matches!(c, b'.' | b'-' | b'_' | b':') => i += 1,
+            c => return invalid(&format!("'{}' is not allowed", c as char)),
+        }
+    }
+    Ok(())
+}
+
+/// Checks the value of an author or reviewer field if it is a DID
+pub fn check_identity(value: &str) -> Result<()> {
+    if is_did(value) {
+        validate_did(value)?;
+    }
+    Ok(())
+}
+
+/// Returns the `did:key` identifier of an Ed25519 or P-256 public key
+pub fn did_key<T: HasPublic>(key: &PKeyRef<T>) -> Result<String> {
+    let unsupported = || {
+        Error::Validation(
+            "did:key can only be derived from Ed25519 and P-256 keys; pass --issuer".to_string(),
+        )
+    };
+
+    // Multicodec prefix of the key type, then the raw or compressed public key
+    let bytes = match key.id() {
+        Id::ED25519 => {
+            let mut bytes = vec![0xed, 0x01];
+            bytes.extend(
+                key.raw_public_key()
+                    .map_err(|e| Error::Signing(e.to_string()))?,
+            );
+            bytes
+        }
+        Id::EC => {
+            let ec = key.ec_key().map_err(|e| Error::Signing(e.to_string()))?;
+            if ec.group().curve_name() != Some(Nid::X9_62_PRIME256V1) {
+                return Err(unsupported());
+            }
+            let mut ctx = BigNumContext::new().map_err(|e| Error::Signing(e.to_string()))?;
+            let point = ec
+                .public_key()
+                .to_bytes(ec.group(), PointConversionForm::COMPRESSED, &mut ctx)
+                .map_err(|e| Error::Signing(e.to_string()))?;
+            let mut bytes = vec![0x80, 0x24];
+            bytes.extend(point);
+            bytes
+        }
+        _ => return Err(unsupported()),
+    };
+
+    Ok(format!("did:key:z{}", base58btc(&bytes)))
+}
+
+fn base58btc(bytes: &[u8]) -> String {
+    let zeros = bytes.iter().take_while(|byte| **byte == 0).count();
+
+    // Base-58 digits, least significant first
+    let mut digits: Vec<u8> = Vec::new();
+    for &byte in bytes {
+        let mut carry = u32::from(byte);
+        for digit in digits.iter_mut() {
+            carry += u32::from(*digit) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+
+    std::iter::repeat_n('1', zeros)
+        .chain(
+            digits
+                .iter()
+                .rev()
+                .map(|digit| BASE58_ALPHABET[usize::from(*digit)] as char),
+        )
+        .collect()
+}
+
+// Authors of the creative work assertion; those given as DIDs are identified by them
+fn authors(manifest: &Manifest) -> Vec<Value> {
+    let assertions = &claims::active_claim(manifest).created_assertions;
+
+    assertions
+        .iter()
+        .filter_map(|assertion| match assertion {
+            Assertion::CreativeWork(work) => Some(&work.author),
+            _ => None,
+        })
+        .flatten()
+        .map(|author| {
+            if is_did(&author.name) {
+                json!({ "type": author.author_type, "id": author.name })
+            } else {
+                json!({ "type": author.author_type, "name": author.name })
+            }
+        })
+        .collect()
+}
+
+/// Builds an unsecured credential stating the authorship and approvals of a manifest,
+/// issued by `issuer`. The manifest is identified by its ID and its subject hash, see
+/// [`attestation::manifest_subject_hash`].
+pub fn manifest_credential(
+    manifest: &Manifest,
+    issuer: &str,
+    hash_alg: &HashAlgorithm,
+) -> Result<Value> {
+    validate_did(issuer)?;
+
+    let approvals = approval::approvals(manifest)?
+        .into_iter()
+        .map(|approval| {
+            let mut value = json!({
+                "role": approval.role,
+                "decision": approval.decision,
+                "date": approval.timestamp.format(&Rfc3339).unwrap_or_default(),
+            });
+            let key = if is_did(&approval.reviewer) {
+                "id"
+            } else {
+                "name"
+            };
+            value["reviewer"] = json!({ key: approval.reviewer });
+            value
+        })
+        .collect::<Vec<_>>();
+
+    let valid_from = OffsetDateTime::now_utc()
+        .format(&Rfc3339)
+        .map_err(|e| Error::Serialization(e.to_string()))?;
+
+    Ok(json!({
+        "@context": [VC_CONTEXT],
+        "type": ["VerifiableCredential", CREDENTIAL_TYPE],
+        "issuer": issuer,
+        "validFrom": valid_from,
+        "credentialSubject": {
+            "id": manifest.instance_id,
+            "name": manifest.title,
+            "digest": {
+                "algorithm": hash_alg.as_str(),
+                "value": attestation::manifest_subject_hash(manifest, hash_alg)?,
+            },
+            "author": authors(manifest),
+            "approval": approvals,
+        },
+    }))
+}
+
+// JOSE algorithm of a key, and the digest it signs with (none for EdDSA)
+fn jose_algorithm<T: HasPublic>(key: &PKeyRef<T>) -> Result<(&'static str, Option<MessageDigest>)> {
+    match key.id() {
+        Id::RSA => Ok(("RS256", Some(MessageDigest::sha256()))),
+        Id::ED25519 | Id::ED448 => Ok(("EdDSA", None)),
+        Id::EC => {
+            let ec = key.ec_key().map_err(|e| Error::Signing(e.to_string()))?;
+            match ec.group().curve_name() {
+                Some(Nid::X9_62_PRIME256V1) => Ok(("ES256", Some(MessageDigest::sha256()))),
+                Some(Nid::SECP384R1) => Ok(("ES384", Some(MessageDigest::sha384()))),
+                Some(Nid::SECP521R1) => Ok(("ES512", Some(MessageDigest::sha512()))),
+                _ => Err(Error::Signing(
+                    "Unsupported curve for JOSE signatures".to_string(),
+                )),
+            }
+        }
+        _ => Err(Error::Signing(
+            "Credentials can only be signed with RSA, EC or EdDSA keys".to_string(),
+        )),
+    }
+}
+
+// Length of each of r and s in a JOSE ECDSA signature
+fn ecdsa_component_len(alg: &str) -> usize {
+    match alg {
+        "ES256" => 32,
+        "ES384" => 48,
+        _ => 66,
+    }
+}
+
+/// Secures a credential as a compact JWS (`vc+jwt`), signed with `key` and identified by
+/// `kid`, usually a DID URL of the issuer's key
+pub fn sign_credential(credential: &Value, key: &SecurePrivateKey, kid: &str) -> Result<String> {
+    let pkey = key.as_pkey();
+    if signing::fips::is_enabled() {
+        signing::fips::check_key(pkey)?;
+    }
+    let (alg, digest) = jose_algorithm(pkey)?;
+
+    let header = json!({ "alg": alg, "typ": VC_JWT_TYPE, "kid": kid });
+    let signing_input = format!(
+        "{}.{}",
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(&header)?),
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(credential)?)
+    );
+
+    let signing_error = |e: openssl::error::ErrorStack| Error::Signing(e.to_string());
+    let signature = match digest {
+        Some(digest) => {
+            let mut signer = Signer::new(digest, pkey).map_err(signing_error)?;
+            signer
+                .update(signing_input.as_bytes())
+                .map_err(signing_error)?;
+            let signature = signer.sign_to_vec().map_err(signing_error)?;
+
+            // JOSE ECDSA signatures are r || s rather than DER
+            if alg.starts_with("ES") {
+                let signature = EcdsaSig::from_der(&signature).map_err(signing_error)?;
+                let len = ecdsa_component_len(alg) as i32;
+                let mut raw = signature.r().to_vec_padded(len).map_err(signing_error)?;
+                raw.extend(signature.s().to_vec_padded(len).map_err(signing_error)?);
+                raw
+            } else {
+                signature
+            }
+        }
+        None => Signer::new_without_digest(pkey)
+            .and_then(|mut signer| signer.sign_oneshot_to_vec(signing_input.as_bytes()))
+            .map_err(signing_error)?,
+    };
+
+    Ok(format!(
+        "{signing_input}.{}",
+        URL_SAFE_NO_PAD.encode(signature)
+    ))
+}
+
+/// Verifies a credential secured with [`sign_credential`] and returns it
+pub fn verify_credential(jws: &str, public_key: &PKey<Public>) -> Result<Value> {
+    let invalid = |reason: &str| Error::Validation(format!("Invalid credential: {reason}"));
+    let mut parts = jws.trim().split('.');
+    let (Some(header), Some(payload), Some(signature), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(invalid("expected a compact JWS"));
+    };
+    let decode = |part: &str| {
+        URL_SAFE_NO_PAD
+            .decode(part)
+            .map_err(|e| invalid(&e.to_string()))
+    };
+
+    let header: Value = serde_json::from_slice(&decode(header)?)?;
+    let (alg, digest) = jose_algorithm(public_key)?;
+    if header["alg"] != alg {
+        return Err(invalid(&format!(
+            "signed with {}, but the key uses {alg}",
+            header["alg"]
+        )));
+    }
+
+    let signing_input = &jws.trim()[..jws.trim().len() - signature.len() - 1];
+    let signature = decode(signature)?;
+    let verification_error = |e: openssl::error::ErrorStack| Error::Signing(e.to_string());
+    let valid = match digest {
+        Some(digest) => {
+            let signature = if alg.starts_with("ES") {
+                let len = ecdsa_component_len(alg);
+                if signature.len() != 2 * len {
+                    return Err(invalid("bad ECDSA signature length"));
+                }
+                let r = BigNum::from_slice(&signature[..len]).map_err(verification_error)?;
+                let s = BigNum::from_slice(&signature[len..]).map_err(verification_error)?;
+                EcdsaSig::from_private_components(r, s)
+                    .and_then(|signature| signature.to_der())
+                    .map_err(verification_error)?
+            } else {
+                signature
+            };
+            let mut verifier = Verifier::new(digest, public_key).map_err(verification_error)?;
+            verifier
+                .update(signing_input.as_bytes())
+                .map_err(verification_error)?;
+            verifier.verify(&signature).map_err(verification_error)?
+        }
+        None => Verifier::new_without_digest(public_key)
+            .and_then(|mut verifier| verifier.verify_oneshot(&signature, signing_input.as_bytes()))
+            .map_err(verification_error)?,
+    };
+    if !valid {
+        return Err(Error::Validation(
+            "Credential signature verification failed".to_string(),
+        ));
+    }
+
+    Ok(serde_json::from_slice(&decode(payload)?)?)
+}
+
+/// The issuer of a credential signed with `key`, and the `kid` naming the key: `issuer`
+/// itself if given, and otherwise the `did:key` of the key
+pub(crate) fn issuer_and_kid(
+    key: &SecurePrivateKey,
+    issuer: Option<String>,
+) -> Result<(String, String)> {
+    match issuer {
+        Some(issuer) => Ok((issuer.clone(), issuer)),
+        None => {
+            let did = did_key(key.as_pkey())?;
+            // A did:key document names its only key after the multibase value
+            let kid = format!("{did}#{}", &did["did:key:".len()..]);
+            Ok((did, kid))
+        }
+    }
+}
+
+/// Exports the authorship and approvals of a stored manifest as a credential signed with
+/// the key at `key_path`.
+///
+/// Without an `issuer`, the credential is issued by the `did:key` of the signing key.
+pub fn export_credential(
+    manifest_id: &str,
+    storage: &dyn StorageBackend,
+    key_path: &Path,
+    issuer: Option<String>,
+    hash_alg: &HashAlgorithm,
+) -> Result<String> {
+    let manifest = storage.retrieve_manifest(manifest_id)?;
+    let private_key = signing::load_private_key(key_path)?;
+    let (issuer, kid) = issuer_and_kid(&private_key, issuer)?;
+
+    let credential = manifest_credential(&manifest, &issuer, hash_alg)?;
+    sign_credential(&credential, &private_key, &kid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::ec::{EcGroup, EcKey};
+
+    #[test]
+    fn test_validate_did() {
+        assert!(validate_did("did:web:example.com").is_ok());
+        assert!(validate_did("did:web:example.com:teams:ml%20platform").is_ok());
+        assert!(validate_did("did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK").is_ok());
+        assert!(validate_did("did:Web:example.com").is_err());
+        assert!(validate_did("did:web:").is_err());
+        assert!(validate_did("did:web:example.com/path").is_err());
+        assert!(validate_did("did:web:bad%2").is_err());
+        assert!(check_identity("Example Org").is_ok());
+    }
+
+    #[test]
+    fn test_base58btc() {
+        assert_eq!(base58btc(b"hello world"), "StV1DL6CwTryKyV");
+        assert_eq!(base58btc(&[0, 0, 1]), "112");
+    }
+
+    #[test]
+    fn test_did_key_of_ed25519() -> Result<()> {
+        let key = PKey::generate_ed25519().map_err(|e| Error::Signing(e.to_string()))?;
+        let did = did_key(&key)?;
+        // The multicodec prefix of Ed25519 keys encodes as z6Mk
+        assert!(did.starts_with("did:key:z6Mk"));
+        validate_did(&did)
+    }
+
+    #[test]
+    fn test_signed_credential_round_trip() -> Result<()> {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)
+            .map_err(|e| Error::Signing(e.to_string()))?;
+        let ec = EcKey::generate(&group).map_err(|e| Error::Signing(e.to_string()))?;
+        let key = PKey::from_ec_key(ec).map_err(|e| Error::Signing(e.to_string()))?;
+        let public_key = PKey::public_key_from_pem(
+            &key.public_key_to_pem()
+                .map_err(|e| Error::Signing(e.to_string()))?,
+        )
+        .map_err(|e| Error::Signing(e.to_string()))?;
+
+        let issuer = did_key(&key)?;
+        assert!(issuer.starts_with("did:key:zDn"));
+        let credential = json!({ "@context": [VC_CONTEXT], "issuer": issuer });
+        let jws = sign_credential(&credential, &signing::pkey_to_secure(key)?, &issuer)?;
+
+        assert_eq!(verify_credential(&jws, &public_key)?, credential);
+
+        // Any change to the payload breaks the signature
+        let mut parts: Vec<&str> = jws.split('.').collect();
+        let tampered =
+            URL_SAFE_NO_PAD.encode(serde_json::to_vec(&json!({ "issuer": "did:web:evil" }))?);
+        parts[1] = &tampered;
+        assert!(verify_credential(&parts.join("."), &public_key).is_err());
+        Ok(())
+    }
+}
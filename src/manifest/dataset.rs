@@ -1,16 +1,333 @@
-use crate::error::Result;
-use crate::manifest::common::{AssetKind, list_manifests, verify_manifest};
+use crate::error::{Error, Result};
+use crate::manifest::common::{
+    AssetKind, build_manifest, finish_manifest, list_manifests, verify_manifest,
+};
 use crate::manifest::config::ManifestCreationConfig;
-use crate::storage::traits::StorageBackend;
+use crate::manifest::{add_cross_reference, extract_uuid_from_urn, linking};
+use crate::storage::traits::{ManifestType, StorageBackend};
 use atlas_c2pa_lib::asset_type::AssetType;
 use atlas_c2pa_lib::ingredient::{Ingredient, IngredientData};
-use std::path::Path;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
 use uuid::Uuid;
 
+/// A split of a dataset, linked to the dataset it was taken from by its relationship
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DatasetSplit {
+    Train,
+    Validation,
+    Test,
+}
+
+impl DatasetSplit {
+    /// Relationship of the ingredient linking the split to its parent dataset
+    pub fn relationship(self) -> &'static str {
+        match self {
+            DatasetSplit::Train => "trainSplitOf",
+            DatasetSplit::Validation => "valSplitOf",
+            DatasetSplit::Test => "testSplitOf",
+        }
+    }
+
+    /// The split whose parent link has this relationship
+    pub fn from_relationship(relationship: &str) -> Option<Self> {
+        [
+            DatasetSplit::Train,
+            DatasetSplit::Validation,
+            DatasetSplit::Test,
+        ]
+        .into_iter()
+        .find(|split| split.relationship() == relationship)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DatasetSplit::Train => "train",
+            DatasetSplit::Validation => "val",
+            DatasetSplit::Test => "test",
+        }
+    }
+}
+
 pub fn create_manifest(config: ManifestCreationConfig) -> Result<()> {
     crate::manifest::common::create_manifest(config, AssetKind::Dataset)
 }
 
+/// Creates a dataset manifest for each split of a stored dataset, and returns their IDs.
+///
+/// Each split manifest links to the parent dataset with an ingredient whose relationship
+/// names the split (`trainSplitOf`, `valSplitOf` or `testSplitOf`), and a cross-reference.
+/// The splits must be disjoint: no file may have the same hash as a file of another split.
+/// This is checked before any manifest is signed or stored.
+///
+/// `config` holds the settings shared by the split manifests; its paths, ingredient names
+/// and name are set for each split, named after the parent dataset unless `config.name` is
+/// set.
+pub fn split_dataset(
+    parent_id: &str,
+    splits: Vec<(DatasetSplit, Vec<PathBuf>)>,
+    config: ManifestCreationConfig,
+) -> Result<Vec<(DatasetSplit, String)>> {
+    let storage = config.storage.ok_or_else(|| {
+        Error::Validation("Splitting a dataset requires a storage backend".to_string())
+    })?;
+    let splits: Vec<_> = splits
+        .into_iter()
+        .filter(|(_, paths)| !paths.is_empty())
+        .collect();
+    if splits.is_empty() {
+        return Err(Error::Validation(
+            "At least one of --train, --val and --test is required".to_string(),
+        ));
+    }
+
+    let (_, parent) = linking::fetch_datasets(&[parent_id.to_string()], storage)?.remove(0);
+    let base_name = if config.name.is_empty() {
+        parent.title.clone()
+    } else {
+        config.name.clone()
+    };
+
+    let mut manifests = Vec::new();
+    for (split, paths) in splits {
+        let mut split_config = config.clone_without_storage();
+        split_config.storage = config.storage;
+        split_config.ingredient_names = paths
+            .iter()
+            .map(|path| {
+                path.file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_else(|| path.display().to_string())
+            })
+            .collect();
+        split_config.paths = paths;
+        split_config.name = format!("{base_name} ({})", split.as_str());
+
+        let manifest = build_manifest(&split_config, AssetKind::Dataset)?;
+        manifests.push((split, split_config, manifest));
+    }
+
+    check_disjoint(
+        manifests
+            .iter()
+            .map(|(split, _, manifest)| (*split, &manifest.claim.ingredients)),
+    )?;
+
+    let algorithm = config.hash_alg.clone();
+    let mut ids = Vec::new();
+    for (split, split_config, mut manifest) in manifests {
+        // The parent link goes into the claim, so that the signature covers it
+        let link =
+            linking::dataset_link_ingredient(parent_id, &parent, split.relationship(), &algorithm)?;
+        manifest.claim.ingredients.push(link.clone());
+        if let Some(claim_v2) = manifest.claim_v2.as_mut() {
+            claim_v2.ingredients.push(link);
+        }
+        add_cross_reference(&mut manifest, parent_id, &parent, &algorithm)?;
+
+        if let Some(id) = finish_manifest(&mut manifest, &split_config)? {
+            println!(
+                "Created {} split {id} of dataset {parent_id}",
+                split.as_str()
+            );
+            ids.push((split, id));
+        }
+    }
+
+    Ok(ids)
+}
+
+// Fails if any content appears in more than one split, naming the first overlap found
+fn check_disjoint<'a>(
+    splits: impl Iterator<Item = (DatasetSplit, &'a Vec<Ingredient>)>,
+) -> Result<()> {
+    let mut seen: HashMap<(String, String), (DatasetSplit, &str)> = HashMap::new();
+
+    for (split, ingredients) in splits {
+        for ingredient in ingredients.iter().filter(|i| !linking::is_dataset_link(i)) {
+            let key = (
+                ingredient.data.alg.to_lowercase(),
+                ingredient.data.hash.to_lowercase(),
+            );
+            match seen.get(&key) {
+                Some((other_split, other_title)) if *other_split != split => {
+                    return Err(Error::Validation(format!(
+                        "Dataset splits are not disjoint: {} in the {} split has the same content as {} in the {} split",
+                        ingredient.title,
+                        split.as_str(),
+                        other_title,
+                        other_split.as_str()
+                    )));
+                }
+                Some(_) => {}
+                None => {
+                    seen.insert(key, (split, &ingredient.title));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A file of a dataset whose content also appears in another dataset
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateFile {
+    pub dataset_id: String,
+    pub dataset_name: String,
+    pub ingredient: String,
+    pub url: String,
+}
+
+/// Content that appears in more than one dataset
+#[derive(Debug, Clone, Serialize)]
+pub struct Overlap {
+    pub alg: String,
+    pub hash: String,
+    pub files: Vec<DuplicateFile>,
+}
+
+/// Result of comparing the ingredient hashes of dataset manifests
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DedupReport {
+    pub datasets_checked: usize,
+    pub files_checked: usize,
+    pub overlaps: Vec<Overlap>,
+}
+
+impl DedupReport {
+    pub fn is_clean(&self) -> bool {
+        self.overlaps.is_empty()
+    }
+}
+
+/// Compares the ingredient hashes of dataset manifests and reports content shared
+/// between datasets, such as test files that also appear in a training set.
+///
+/// With no `ids`, every dataset manifest in the store is compared. Versions of the same
+/// dataset are not compared with each other, nor are splits with the dataset they were
+/// taken from; links to parent datasets are not files. Ingredients are only compared with
+/// ingredients hashed with the same algorithm.
+///
+/// # Errors
+///
+/// Returns an error if a manifest cannot be retrieved or is not a dataset manifest.
+pub fn find_duplicates(ids: &[String], storage: &dyn StorageBackend) -> Result<DedupReport> {
+    let ids: Vec<String> = if ids.is_empty() {
+        storage
+            .list_manifests()?
+            .into_iter()
+            .filter(|metadata| metadata.manifest_type == ManifestType::Dataset)
+            .map(|metadata| metadata.id)
+            .collect()
+    } else {
+        ids.to_vec()
+    };
+    let datasets = linking::fetch_datasets(&ids, storage)?;
+
+    let mut report = DedupReport {
+        datasets_checked: datasets.len(),
+        ..Default::default()
+    };
+    let dataset_of = |id: &str| {
+        extract_uuid_from_urn(id)
+            .map(|uuid| uuid.to_string())
+            .unwrap_or_else(|_| id.to_string())
+    };
+    // Files by content, each with the dataset it belongs to
+    let mut by_content: BTreeMap<(String, String), Vec<(String, DuplicateFile)>> = BTreeMap::new();
+    // Splits and the datasets they were taken from
+    let mut split_of: HashSet<(String, String)> = HashSet::new();
+    for (id, manifest) in &datasets {
+        let dataset = dataset_of(id);
+        let (links, files): (Vec<&Ingredient>, Vec<&Ingredient>) = manifest
+            .ingredients
+            .iter()
+            .chain(manifest.claim.ingredients.iter())
+            .partition(|i| linking::is_dataset_link(i));
+        for link in links {
+            if DatasetSplit::from_relationship(&link.relationship).is_some() {
+                split_of.insert((dataset.clone(), dataset_of(&link.data.url)));
+            }
+        }
+        for ingredient in files {
+            report.files_checked += 1;
+            let key = (
+                ingredient.data.alg.to_lowercase(),
+                ingredient.data.hash.to_lowercase(),
+            );
+            by_content.entry(key).or_default().push((
+                dataset.clone(),
+                DuplicateFile {
+                    dataset_id: id.clone(),
+                    dataset_name: manifest.title.clone(),
+                    ingredient: ingredient.title.clone(),
+                    url: ingredient.data.url.clone(),
+                },
+            ));
+        }
+    }
+
+    let related = |a: &String, b: &String| {
+        a == b
+            || split_of.contains(&(a.clone(), b.clone()))
+            || split_of.contains(&(b.clone(), a.clone()))
+    };
+    for ((alg, hash), files) in by_content {
+        let shared = files
+            .iter()
+            .enumerate()
+            .any(|(i, (a, _))| files[i + 1..].iter().any(|(b, _)| !related(a, b)));
+        if !shared {
+            continue;
+        }
+        report.overlaps.push(Overlap {
+            alg,
+            hash,
+            files: files.into_iter().map(|(_, file)| file).collect(),
+        });
+    }
+    Ok(report)
+}
+
+/// Renders a duplicate report as a table or JSON
+pub fn render_duplicates(report: &DedupReport, output_format: &str) -> Result<String> {
+    let mut out = String::new();
+    match output_format.to_lowercase().as_str() {
+        "table" => {
+            for overlap in &report.overlaps {
+                let _ = writeln!(out, "{}:{}", overlap.alg, overlap.hash);
+                for file in &overlap.files {
+                    let _ = writeln!(
+                        out,
+                        "  {}  {}  {} ({})",
+                        file.dataset_id, file.dataset_name, file.ingredient, file.url
+                    );
+                }
+            }
+            let _ = writeln!(
+                out,
+                "Checked {} file(s) in {} dataset(s)",
+                report.files_checked, report.datasets_checked
+            );
+            if report.is_clean() {
+                out.push_str("No files shared between datasets\n");
+            }
+        }
+        "json" => {
+            out = serde_json::to_string_pretty(report)?;
+            out.push('\n');
+        }
+        _ => {
+            return Err(Error::Validation(format!(
+                "Invalid output format '{output_format}'. Valid options are: table, json"
+            )));
+        }
+    }
+    Ok(out)
+}
+
 /// List dataset manifests
 pub fn list_dataset_manifests(storage: &dyn StorageBackend) -> Result<()> {
     // Call the unified implementation with AssetKind::Dataset
@@ -32,7 +349,7 @@ fn create_ingredient_from_path(
 ) -> Result<Ingredient> {
     let ingredient_data = IngredientData {
         url: path.to_string_lossy().to_string(),
-        alg: "sha256".to_string(),
+        alg: "sha384".to_string(),
         hash: crate::hash::calculate_file_hash(path)?,
         data_types: vec![asset_type],
         linked_ingredient_url: None,
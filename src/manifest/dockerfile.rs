@@ -0,0 +1,424 @@
+//! Container build recipes for software manifests.
+//!
+//! With `--from-dockerfile`, the Dockerfile and the files of its build context become
+//! ingredients of the software manifest, and the base images it builds on are recorded in
+//! a container build assertion. Like `docker build`, files excluded by the `.dockerignore`
+//! of the context (or a `<Dockerfile>.dockerignore` next to the Dockerfile) are left out.
+//!
+//! A base image is only reproducible when pinned by digest (`FROM image@sha256:...`), so
+//! base images referenced by tag are recorded without a digest, with a warning.
+
+use crate::cli::color;
+use crate::error::{Error, Result};
+use crate::manifest::config::ManifestCreationConfig;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Label of the container build assertion
+pub const CONTAINER_BUILD_ASSERTION_LABEL: &str = "software.container_build";
+
+/// Image a build stage starts from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BaseImage {
+    /// Image reference as written in the Dockerfile
+    pub reference: String,
+    /// Digest the image is pinned to, e.g. `sha256:...`
+    pub digest: Option<String>,
+    /// Name of the build stage, if it has one
+    pub stage: Option<String>,
+}
+
+/// Contents of the container build assertion
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContainerBuild {
+    pub dockerfile: String,
+    pub context: String,
+    pub base_images: Vec<BaseImage>,
+    /// Files of the build context recorded as ingredients, after `.dockerignore`
+    pub context_files: usize,
+    #[serde(skip)]
+    pub dockerfile_path: PathBuf,
+    #[serde(skip)]
+    pub files: Vec<(PathBuf, String)>,
+}
+
+/// Joins continued lines and drops comments, returning the instructions of a Dockerfile
+fn instructions(dockerfile: &str) -> Vec<String> {
+    let mut instructions = Vec::new();
+    let mut current = String::new();
+    for line in dockerfile.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('#') {
+            continue;
+        }
+        match trimmed.strip_suffix('\\') {
+            Some(continued) => {
+                current.push_str(continued);
+                current.push(' ');
+            }
+            None => {
+                current.push_str(trimmed);
+                if !current.trim().is_empty() {
+                    instructions.push(current.trim().to_string());
+                }
+                current.clear();
+            }
+        }
+    }
+    if !current.trim().is_empty() {
+        instructions.push(current.trim().to_string());
+    }
+    instructions
+}
+
+/// Returns the base images of the stages of a Dockerfile. Stages built from an earlier
+/// stage and `scratch` have no base image.
+pub fn parse_base_images(dockerfile: &str) -> Result<Vec<BaseImage>> {
+    let mut stages: Vec<String> = Vec::new();
+    let mut base_images = Vec::new();
+
+    for instruction in instructions(dockerfile) {
+        let mut words = instruction.split_whitespace();
+        if !words
+            .next()
+            .is_some_and(|keyword| keyword.eq_ignore_ascii_case("FROM"))
+        {
+            continue;
+        }
+        let words: Vec<&str> = words.filter(|word| !word.starts_with("--")).collect();
+        let reference = words.first().ok_or_else(|| {
+            Error::Validation(format!("Invalid Dockerfile instruction: {instruction}"))
+        })?;
+        let stage = match words.get(1..) {
+            Some([as_keyword, name]) if as_keyword.eq_ignore_ascii_case("AS") => {
+                Some(name.to_string())
+            }
+            _ => None,
+        };
+
+        let is_stage = stages
+            .iter()
+            .any(|name| name.eq_ignore_ascii_case(reference));
+        if !is_stage && !reference.eq_ignore_ascii_case("scratch") {
+            base_images.push(BaseImage {
+                reference: reference.to_string(),
+                digest: reference
+                    .split_once('@')
+                    .map(|(_, digest)| digest.to_string()),
+                stage: stage.clone(),
+            });
+        }
+        if let Some(stage) = stage {
+            stages.push(stage);
+        }
+    }
+
+    Ok(base_images)
+}
+
+/// A `.dockerignore` rule
+#[derive(Debug, Clone)]
+struct IgnoreRule {
+    segments: Vec<String>,
+    exception: bool,
+}
+
+/// Exclusion rules of a `.dockerignore` file. As with Docker, the last matching rule
+/// decides, and a rule matching a directory matches everything below it.
+#[derive(Debug, Clone, Default)]
+pub struct DockerIgnore {
+    rules: Vec<IgnoreRule>,
+}
+
+impl DockerIgnore {
+    pub fn parse(contents: &str) -> Self {
+        let rules = contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| {
+                let (exception, pattern) = match line.strip_prefix('!') {
+                    Some(pattern) => (true, pattern.trim()),
+                    None => (false, line),
+                };
+                let segments: Vec<String> = pattern
+                    .split('/')
+                    .filter(|segment| !segment.is_empty() && *segment != ".")
+                    .map(str::to_string)
+                    .collect();
+                (!segments.is_empty()).then_some(IgnoreRule {
+                    segments,
+                    exception,
+                })
+            })
+            .collect();
+        Self { rules }
+    }
+
+    /// Checks if a path relative to the build context, separated by `/`, is excluded
+    pub fn is_ignored(&self, path: &str) -> bool {
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        let mut ignored = false;
+        for rule in &self.rules {
+            let matches =
+                (1..=segments.len()).any(|len| match_segments(&rule.segments, &segments[..len]));
+            if matches {
+                ignored = !rule.exception;
+            }
+        }
+        ignored
+    }
+}
+
+// Matches path segments against pattern segments, where `**` matches any number of segments
+fn match_segments(pattern: &[String], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((first, rest)) if first == "**" => {
+            (0..=path.len()).any(|skip| match_segments(rest, &path[skip..]))
+        }
+        Some((first, rest)) => match path.split_first() {
+            Some((segment, path_rest)) => {
+                let pattern: Vec<char> = first.chars().collect();
+                let name: Vec<char> = segment.chars().collect();
+                match_segment(&pattern, &name) && match_segments(rest, path_rest)
+            }
+            None => false,
+        },
+    }
+}
+
+// Matches a name against a pattern with `*`, `?`, `[...]` classes and `\` escapes
+fn match_segment(pattern: &[char], name: &[char]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => (0..=name.len()).any(|skip| match_segment(rest, &name[skip..])),
+        Some(('?', rest)) => !name.is_empty() && match_segment(rest, &name[1..]),
+        Some(('[', rest)) => {
+            let Some(end) = rest.iter().position(|c| *c == ']') else {
+                return false;
+            };
+            let Some(c) = name.first() else {
+                return false;
+            };
+            let (negated, class) = match rest[..end].split_first() {
+                Some(('^' | '!', class)) => (true, class),
+                _ => (false, &rest[..end]),
+            };
+            let mut in_class = false;
+            let mut i = 0;
+            while i < class.len() {
+                if i + 2 < class.len() && class[i + 1] == '-' {
+                    in_class |= class[i] <= *c && *c <= class[i + 2];
+                    i += 3;
+                } else {
+                    in_class |= class[i] == *c;
+                    i += 1;
+                }
+            }
+            in_class != negated && match_segment(&rest[end + 1..], &name[1..])
+        }
+        Some(('\\', [escaped, rest @ ..])) => {
+            name.first() == Some(escaped) && match_segment(rest, &name[1..])
+        }
+        Some((c, rest)) => name.first() == Some(c) && match_segment(rest, &name[1..]),
+    }
+}
+
+/// Reads the `.dockerignore` that applies to a build: `<Dockerfile>.dockerignore` next to
+/// the Dockerfile, or else `.dockerignore` in the context
+fn read_dockerignore(dockerfile: &Path, context: &Path) -> Result<DockerIgnore> {
+    let mut specific = dockerfile.as_os_str().to_owned();
+    specific.push(".dockerignore");
+    for path in [PathBuf::from(specific), context.join(".dockerignore")] {
+        if path.is_file() {
+            return Ok(DockerIgnore::parse(&fs::read_to_string(path)?));
+        }
+    }
+    Ok(DockerIgnore::default())
+}
+
+/// Reads a Dockerfile and lists the files of its build context, `context` or else the
+/// directory of the Dockerfile, that are not excluded by `.dockerignore`.
+///
+/// # Errors
+///
+/// Returns an error if the Dockerfile or the build context cannot be read.
+pub fn snapshot(dockerfile: &Path, context: Option<&Path>) -> Result<ContainerBuild> {
+    let context = match context {
+        Some(context) => context.to_path_buf(),
+        None => match dockerfile.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        },
+    };
+    if !context.is_dir() {
+        return Err(Error::Validation(format!(
+            "Build context {} is not a directory",
+            context.display()
+        )));
+    }
+
+    let base_images = parse_base_images(&fs::read_to_string(dockerfile)?)?;
+    for image in base_images.iter().filter(|image| image.digest.is_none()) {
+        println!(
+            "{} Warning: Base image {} is not pinned by digest",
+            color::warning_sign(),
+            image.reference
+        );
+    }
+
+    let ignore = read_dockerignore(dockerfile, &context)?;
+    let dockerfile_path = fs::canonicalize(dockerfile)?;
+    let mut files = Vec::new();
+    for entry in WalkDir::new(&context).sort_by_file_name() {
+        let entry = entry.map_err(|e| {
+            Error::Validation(format!(
+                "Failed to read build context {}: {e}",
+                context.display()
+            ))
+        })?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let relative = entry
+            .path()
+            .strip_prefix(&context)
+            .unwrap_or(entry.path())
+            .to_string_lossy()
+            .replace('\\', "/");
+        // The Dockerfile is recorded on its own
+        if ignore.is_ignored(&relative)
+            || fs::canonicalize(entry.path()).is_ok_and(|path| path == dockerfile_path)
+        {
+            continue;
+        }
+        files.push((entry.path().to_path_buf(), relative));
+    }
+
+    Ok(ContainerBuild {
+        dockerfile: dockerfile.display().to_string(),
+        context: context.display().to_string(),
+        base_images,
+        context_files: files.len(),
+        dockerfile_path: dockerfile.to_path_buf(),
+        files,
+    })
+}
+
+/// Adds a container build to a software manifest configuration: the Dockerfile and the
+/// context files as ingredients, and the container build assertion
+pub fn add_to_config(build: &ContainerBuild, config: &mut ManifestCreationConfig) -> Result<()> {
+    let dockerfile_name = build
+        .dockerfile_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| build.dockerfile.clone());
+    config.add_ingredient(build.dockerfile_path.clone(), dockerfile_name)?;
+    for (path, relative) in &build.files {
+        config.add_ingredient(path.clone(), format!("context/{relative}"))?;
+    }
+
+    config.set_custom_field(
+        CONTAINER_BUILD_ASSERTION_LABEL,
+        serde_json::to_value(build)?,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_parse_base_images() {
+        let dockerfile = r#"
+# syntax=docker/dockerfile:1
+ARG PY=3.11
+FROM --platform=linux/amd64 python:3.11-slim@sha256:abc123 AS builder
+RUN pip install \
+    torch
+FROM builder AS test
+from nvidia/cuda:12.4.1-runtime-ubuntu22.04
+COPY --from=builder /app /app
+FROM scratch
+"#;
+        let images = parse_base_images(dockerfile).unwrap();
+        assert_eq!(
+            images,
+            [
+                BaseImage {
+                    reference: "python:3.11-slim@sha256:abc123".to_string(),
+                    digest: Some("sha256:abc123".to_string()),
+                    stage: Some("builder".to_string()),
+                },
+                BaseImage {
+                    reference: "nvidia/cuda:12.4.1-runtime-ubuntu22.04".to_string(),
+                    digest: None,
+                    stage: None,
+                },
+            ]
+        );
+        assert!(parse_base_images("FROM\n").is_err());
+    }
+
+    #[test]
+    fn test_dockerignore() {
+        let ignore = DockerIgnore::parse(
+            "# comment\n*.md\n!README.md\n/data\n**/__pycache__\n.git\ntmp?\n[ab].log\n",
+        );
+        assert!(ignore.is_ignored("NOTES.md"));
+        assert!(!ignore.is_ignored("README.md"));
+        // Patterns without ** match at the root only
+        assert!(!ignore.is_ignored("docs/NOTES.md"));
+        assert!(ignore.is_ignored("data/train.csv"));
+        assert!(ignore.is_ignored("src/__pycache__/x.pyc"));
+        assert!(ignore.is_ignored(".git/HEAD"));
+        assert!(ignore.is_ignored("tmp1"));
+        assert!(!ignore.is_ignored("tmp12"));
+        assert!(ignore.is_ignored("a.log"));
+        assert!(!ignore.is_ignored("c.log"));
+        assert!(!ignore.is_ignored("train.py"));
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let dir = tempdir().unwrap();
+        let context = dir.path();
+        fs::write(
+            context.join("Dockerfile"),
+            "FROM python:3.11\nCOPY . /app\n",
+        )
+        .unwrap();
+        fs::write(context.join(".dockerignore"), "data\n*.log\n").unwrap();
+        fs::write(context.join("train.py"), "print('train')\n").unwrap();
+        fs::write(context.join("debug.log"), "log\n").unwrap();
+        fs::create_dir_all(context.join("data")).unwrap();
+        fs::write(context.join("data/train.csv"), "x\n").unwrap();
+        fs::create_dir_all(context.join("src")).unwrap();
+        fs::write(context.join("src/model.py"), "model\n").unwrap();
+
+        let build = snapshot(&context.join("Dockerfile"), None).unwrap();
+        let files: Vec<_> = build.files.iter().map(|(_, name)| name.as_str()).collect();
+        assert_eq!(files, [".dockerignore", "src/model.py", "train.py"]);
+        assert_eq!(build.context_files, 3);
+        assert_eq!(build.base_images[0].reference, "python:3.11");
+
+        // A Dockerfile-specific ignore file takes precedence
+        fs::write(context.join("Dockerfile.dockerignore"), "src\n").unwrap();
+        let build = snapshot(&context.join("Dockerfile"), Some(context)).unwrap();
+        let files: Vec<_> = build.files.iter().map(|(_, name)| name.as_str()).collect();
+        assert_eq!(
+            files,
+            [
+                ".dockerignore",
+                "Dockerfile.dockerignore",
+                "data/train.csv",
+                "debug.log",
+                "train.py"
+            ]
+        );
+    }
+}
@@ -0,0 +1,449 @@
+//! Encryption of assertions with confidential metadata.
+//!
+//! Some assertion payloads, such as the packages of a Python environment or the parameters
+//! of an evaluation, should only be readable by some parties. Selected assertions are
+//! encrypted to age X25519 recipients (`age1...`) when the manifest is created, before it is
+//! signed, so the signature and every hash stay verifiable by anyone while only holders of
+//! a matching identity (`AGE-SECRET-KEY-1...`) can read the payloads, e.g. with
+//! `manifest show --identity`.
+//!
+//! Payloads are encrypted with the `age` crate in the age v1 format
+//! (<https://age-encryption.org/v1>), so they can also be decrypted with the `age` tool
+//! after base64-decoding the ciphertext.
+
+use crate::error::{Error, Result};
+use crate::manifest::common::INGREDIENT_SIZES_ASSERTION_LABEL;
+use crate::manifest::{approval, attestation, ingest, policy, redaction, signer, validity};
+use crate::signing::{piv, pq};
+use age::secrecy::ExposeSecret;
+use atlas_c2pa_lib::assertion::Assertion;
+use atlas_c2pa_lib::manifest::Manifest;
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+use zeroize::Zeroizing;
+
+/// Pseudo-label selecting the parameters of the actions in the actions assertion
+pub const ACTION_PARAMETERS_LABEL: &str = "c2pa.actions";
+
+/// Key under which an encrypted payload replaces the original one
+pub const ENCRYPTED_PAYLOAD_KEY: &str = "atlas_encrypted";
+
+/// Assertions that are read during verification or must stay public, and can't be encrypted
+pub const PROTECTED_LABELS: &[&str] = &[
+    INGREDIENT_SIZES_ASSERTION_LABEL,
+    ingest::INGEST_POLICY_ASSERTION_LABEL,
+    validity::VALIDITY_ASSERTION_LABEL,
+    approval::APPROVAL_ASSERTION_LABEL,
+    attestation::ATTESTATION_ASSERTION_LABEL,
+    policy::POLICY_OVERRIDE_ASSERTION_LABEL,
+    piv::PIV_CERTIFICATE_ASSERTION_LABEL,
+    pq::PQ_SIGNATURE_ASSERTION_LABEL,
+    signer::SIGNER_KEY_ASSERTION_LABEL,
+    redaction::ASSERTION_SALTS_ASSERTION_LABEL,
+    crate::cc_attestation::tpm::TPM_QUOTE_ASSERTION_LABEL,
+];
+
+/// An X25519 public key that payloads are encrypted to
+#[derive(Clone, PartialEq, Eq)]
+pub struct Recipient(age::x25519::Recipient);
+
+impl FromStr for Recipient {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        age::x25519::Recipient::from_str(s)
+            .map(Self)
+            .map_err(|_| Error::Validation(format!("Invalid age recipient: {s}")))
+    }
+}
+
+impl fmt::Display for Recipient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl fmt::Debug for Recipient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Recipient").field(&self.to_string()).finish()
+    }
+}
+
+/// An X25519 private key that decrypts payloads encrypted to its recipient
+pub struct Identity {
+    identity: age::x25519::Identity,
+    recipient: Recipient,
+}
+
+impl Identity {
+    /// Generates a new identity
+    pub fn generate() -> Result<Self> {
+        Ok(Self::new(age::x25519::Identity::generate()))
+    }
+
+    fn new(identity: age::x25519::Identity) -> Self {
+        let recipient = Recipient(identity.to_public());
+        Self {
+            identity,
+            recipient,
+        }
+    }
+
+    /// The recipient that payloads are encrypted to for this identity
+    pub fn recipient(&self) -> &Recipient {
+        &self.recipient
+    }
+
+    /// The identity in the `AGE-SECRET-KEY-1...` encoding
+    pub fn to_secret_string(&self) -> Zeroizing<String> {
+        Zeroizing::new(self.identity.to_string().expose_secret().to_string())
+    }
+}
+
+impl FromStr for Identity {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        age::x25519::Identity::from_str(s)
+            .map(Self::new)
+            .map_err(|_| Error::Validation("Invalid age identity".to_string()))
+    }
+}
+
+impl fmt::Debug for Identity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Identity")
+            .field("recipient", &self.recipient)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Reads the identities of an age identity file, as written by `age-keygen`
+pub fn load_identities(path: &Path) -> Result<Vec<Identity>> {
+    let contents = Zeroizing::new(std::fs::read_to_string(path)?);
+    let identities = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(Identity::from_str)
+        .collect::<Result<Vec<_>>>()?;
+
+    if identities.is_empty() {
+        return Err(Error::Validation(format!(
+            "No age identities in {}",
+            path.display()
+        )));
+    }
+    Ok(identities)
+}
+
+/// Assertions to encrypt when a manifest is created, and who can decrypt them
+#[derive(Debug, Clone)]
+pub struct AssertionEncryption {
+    /// Labels of the assertions, or [`ACTION_PARAMETERS_LABEL`] for action parameters
+    pub labels: Vec<String>,
+    pub recipients: Vec<Recipient>,
+}
+
+/// An encrypted payload, stored in place of the original one under
+/// [`ENCRYPTED_PAYLOAD_KEY`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptedPayload {
+    /// Always `age`
+    pub scheme: String,
+    /// Base64-encoded age file of the JSON payload
+    pub ciphertext: String,
+}
+
+fn encrypt_value(value: &serde_json::Value, recipients: &[Recipient]) -> Result<serde_json::Value> {
+    let ciphertext = encrypt(&serde_json::to_vec(value)?, recipients)?;
+    let payload = EncryptedPayload {
+        scheme: "age".to_string(),
+        ciphertext: STANDARD.encode(ciphertext),
+    };
+    Ok(serde_json::json!({ ENCRYPTED_PAYLOAD_KEY: payload }))
+}
+
+fn encrypted_payload(value: &serde_json::Value) -> Option<EncryptedPayload> {
+    serde_json::from_value(value.get(ENCRYPTED_PAYLOAD_KEY)?.clone()).ok()
+}
+
+/// Replaces the payloads of the selected assertions with their encryption.
+///
+/// Fails if a selected assertion is missing or protected, see [`PROTECTED_LABELS`].
+pub fn encrypt_assertions(
+    assertions: &mut [Assertion],
+    encryption: &AssertionEncryption,
+) -> Result<()> {
+    if encryption.recipients.is_empty() {
+        return Err(Error::Validation(
+            "Encrypting assertions requires at least one --recipient".to_string(),
+        ));
+    }
+
+    for label in &encryption.labels {
+        if PROTECTED_LABELS.contains(&label.as_str()) {
+            return Err(Error::Validation(format!(
+                "The {label} assertion is needed for verification and can't be encrypted"
+            )));
+        }
+
+        let mut found = false;
+        for assertion in assertions.iter_mut() {
+            match assertion {
+                Assertion::Action(actions) if label == ACTION_PARAMETERS_LABEL => {
+                    found = true;
+                    for parameters in actions
+                        .actions
+                        .iter_mut()
+                        .filter_map(|action| action.parameters.as_mut())
+                    {
+                        *parameters = encrypt_value(parameters, &encryption.recipients)?;
+                    }
+                }
+                Assertion::CustomAssertion(custom) if &custom.label == label => {
+                    found = true;
+                    custom.data = encrypt_value(&custom.data, &encryption.recipients)?;
+                }
+                _ => {}
+            }
+        }
+        if !found {
+            return Err(Error::Validation(format!(
+                "The manifest has no {label} assertion to encrypt"
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Decrypts the encrypted payloads of a manifest in place, returning how many there were
+pub fn decrypt_manifest(manifest: &mut Manifest, identities: &[Identity]) -> Result<usize> {
+    let mut decrypted = 0;
+    let claims = std::iter::once(&mut manifest.claim.created_assertions).chain(
+        manifest
+            .claim_v2
+            .as_mut()
+            .map(|claim| &mut claim.created_assertions),
+    );
+
+    for assertions in claims {
+        for assertion in assertions.iter_mut() {
+            let values: Vec<&mut serde_json::Value> = match assertion {
+                Assertion::Action(actions) => actions
+                    .actions
+                    .iter_mut()
+                    .filter_map(|action| action.parameters.as_mut())
+                    .collect(),
+                Assertion::CustomAssertion(custom) => vec![&mut custom.data],
+                _ => Vec::new(),
+            };
+
+            for value in values {
+                if let Some(payload) = encrypted_payload(value) {
+                    let ciphertext = STANDARD.decode(&payload.ciphertext).map_err(|e| {
+                        Error::Validation(format!("Invalid encrypted payload: {e}"))
+                    })?;
+                    let plaintext = Zeroizing::new(decrypt(&ciphertext, identities)?);
+                    *value = serde_json::from_slice(&plaintext)?;
+                    decrypted += 1;
+                }
+            }
+        }
+    }
+    Ok(decrypted)
+}
+
+/// Encrypts data to the recipients as an age file
+pub fn encrypt(plaintext: &[u8], recipients: &[Recipient]) -> Result<Vec<u8>> {
+    let encryptor = age::Encryptor::with_recipients(
+        recipients
+            .iter()
+            .map(|recipient| &recipient.0 as &dyn age::Recipient),
+    )
+    .map_err(|e| Error::Signing(format!("Encryption failed: {e}")))?;
+
+    let mut ciphertext = Vec::new();
+    let mut writer = encryptor.wrap_output(&mut ciphertext)?;
+    writer.write_all(plaintext)?;
+    writer.finish()?;
+    Ok(ciphertext)
+}
+
+/// Decrypts an age file with X25519 recipient stanzas using any of the identities
+pub fn decrypt(ciphertext: &[u8], identities: &[Identity]) -> Result<Vec<u8>> {
+    let decryptor = age::Decryptor::new_buffered(ciphertext).map_err(decrypt_error)?;
+    let mut reader = decryptor
+        .decrypt(
+            identities
+                .iter()
+                .map(|identity| &identity.identity as &dyn age::Identity),
+        )
+        .map_err(decrypt_error)?;
+
+    let mut plaintext = Vec::new();
+    reader
+        .read_to_end(&mut plaintext)
+        .map_err(|e| Error::Validation(format!("Invalid age file: {e}")))?;
+    Ok(plaintext)
+}
+
+fn decrypt_error(e: age::DecryptError) -> Error {
+    match e {
+        age::DecryptError::NoMatchingKeys => {
+            Error::Validation("None of the identities can decrypt the payload".to_string())
+        }
+        e => Error::Validation(format!("Invalid age file: {e}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use atlas_c2pa_lib::assertion::CustomAssertion;
+
+    #[test]
+    fn test_identity_encoding() -> Result<()> {
+        let identity = Identity::generate()?;
+        let secret = identity.to_secret_string();
+        assert!(secret.starts_with("AGE-SECRET-KEY-1"));
+
+        let parsed = Identity::from_str(&secret)?;
+        assert_eq!(parsed.recipient(), identity.recipient());
+
+        let recipient = identity.recipient().to_string();
+        assert!(recipient.starts_with("age1"));
+        assert_eq!(&Recipient::from_str(&recipient)?, identity.recipient());
+        assert!(Recipient::from_str(&secret).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_encrypt_and_decrypt() -> Result<()> {
+        const CHUNK_SIZE: usize = 64 * 1024;
+        let alice = Identity::generate()?;
+        let bob = Identity::generate()?;
+        let mallory = Identity::generate()?;
+        let recipients = [alice.recipient().clone(), bob.recipient().clone()];
+
+        // Payloads of several chunks, exactly one chunk and none
+        for size in [3 * CHUNK_SIZE / 2, CHUNK_SIZE, 0] {
+            let plaintext: Vec<u8> = (0..size).map(|i| i as u8).collect();
+            let ciphertext = encrypt(&plaintext, &recipients)?;
+            assert!(ciphertext.starts_with(b"age-encryption.org/v1\n-> X25519 "));
+
+            assert_eq!(decrypt(&ciphertext, std::slice::from_ref(&bob))?, plaintext);
+            assert!(decrypt(&ciphertext, std::slice::from_ref(&mallory)).is_err());
+
+            let mut tampered = ciphertext.clone();
+            *tampered.last_mut().unwrap() ^= 1;
+            assert!(decrypt(&tampered, std::slice::from_ref(&alice)).is_err());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_decrypt_failures() -> Result<()> {
+        let identity = Identity::generate()?;
+        let ciphertext = encrypt(b"payload", &[identity.recipient().clone()])?;
+
+        let err = decrypt(b"not an age file", std::slice::from_ref(&identity)).unwrap_err();
+        assert!(err.to_string().contains("Invalid age file"), "{err}");
+
+        let err = decrypt(&ciphertext, &[Identity::generate()?]).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("None of the identities can decrypt the payload"),
+            "{err}"
+        );
+        Ok(())
+    }
+
+    // Interoperability with other age implementations, on the vectors of the age testkit
+    #[test]
+    fn test_age_testkit() -> Result<()> {
+        use sha2::{Digest, Sha256};
+
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/testdata/age");
+        let mut checked = 0;
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().is_some() {
+                continue;
+            }
+            let contents = std::fs::read(&path)?;
+            let split = contents
+                .windows(2)
+                .position(|window| window == b"\n\n")
+                .expect("vector has a header");
+            let (header, file) = (
+                std::str::from_utf8(&contents[..split]).expect("header is text"),
+                &contents[split + 2..],
+            );
+
+            let mut expect = "";
+            let mut payload = "";
+            let mut identities = Vec::new();
+            for line in header.lines() {
+                match line.split_once(": ") {
+                    Some(("expect", value)) => expect = value,
+                    Some(("payload", value)) => payload = value,
+                    Some(("identity", value)) => identities.push(Identity::from_str(value)?),
+                    _ => {}
+                }
+            }
+
+            let name = path.display();
+            match decrypt(file, &identities) {
+                Ok(plaintext) => {
+                    assert_eq!(expect, "success", "{name} should fail");
+                    assert_eq!(hex::encode(Sha256::digest(&plaintext)), payload, "{name}");
+                }
+                Err(e) => assert_ne!(expect, "success", "{name}: {e}"),
+            }
+            checked += 1;
+        }
+        assert_eq!(checked, 60);
+        Ok(())
+    }
+
+    #[test]
+    fn test_encrypt_selected_assertions() -> Result<()> {
+        let identity = Identity::generate()?;
+        let data = serde_json::json!({ "packages": ["numpy==2.1.0"] });
+        let mut assertions = vec![Assertion::CustomAssertion(CustomAssertion {
+            label: "software.python_environment".to_string(),
+            data: data.clone(),
+        })];
+
+        let encryption = AssertionEncryption {
+            labels: vec!["software.python_environment".to_string()],
+            recipients: vec![identity.recipient().clone()],
+        };
+        encrypt_assertions(&mut assertions, &encryption)?;
+        let Assertion::CustomAssertion(custom) = &assertions[0] else {
+            unreachable!()
+        };
+        let payload = encrypted_payload(&custom.data).expect("payload is encrypted");
+        let plaintext = decrypt(&STANDARD.decode(payload.ciphertext).unwrap(), &[identity])?;
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&plaintext)?,
+            data
+        );
+
+        // Missing and protected assertions are rejected
+        for label in ["dataset.profile", INGREDIENT_SIZES_ASSERTION_LABEL] {
+            let encryption = AssertionEncryption {
+                labels: vec![label.to_string()],
+                ..encryption.clone()
+            };
+            assert!(encrypt_assertions(&mut assertions, &encryption).is_err());
+        }
+        Ok(())
+    }
+}
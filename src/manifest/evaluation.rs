@@ -1,20 +1,45 @@
+use crate::cli::color;
 use crate::error::{Error, Result};
+use crate::manifest::attestation;
+use crate::manifest::claims;
 use crate::manifest::common::{self, AssetKind, list_manifests, verify_manifest};
 use crate::manifest::config::ManifestCreationConfig;
+use crate::manifest::extract_uuid_from_urn;
+use crate::manifest::lineage::{self, LineageDirection, LineageNode, LineageRole};
+use crate::manifest::results::{self, ResultsFormat};
+use crate::manifest::traversal;
 use crate::manifest::{determine_manifest_type, manifest_type_to_str};
 use crate::storage::traits::StorageBackend;
 use atlas_c2pa_lib::assertion::Assertion;
+use serde::Serialize;
 use std::collections::HashMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use uuid::Uuid;
+
+// Levels of base models and fine-tunes searched for versions of a model
+const HISTORY_MAX_DEPTH: u32 = 10;
 
 /// Create a new evaluation result manifest using the standard configuration
+///
+/// With `results_format`, the metrics are read from the results file, the first path of
+/// `config`; metrics given as `key=value` pairs are added and take precedence.
 pub fn create_manifest(
     mut config: ManifestCreationConfig,
     model_id: String,
     dataset_id: String,
     metrics: Vec<String>,
+    results_format: Option<ResultsFormat>,
 ) -> Result<()> {
     // Parse metrics into a map
     let mut metrics_map = HashMap::new();
+    if let Some(format) = results_format {
+        let path = config.paths.first().ok_or_else(|| {
+            Error::Validation("A results file is required to read metrics from".to_string())
+        })?;
+        metrics_map.extend(results::read_metrics(path, format)?);
+    }
     for metric in metrics {
         let parts: Vec<&str> = metric.split('=').collect();
         if parts.len() == 2 {
@@ -83,7 +108,11 @@ pub fn verify_evaluation_manifest(id: &str, storage: &dyn StorageBackend) -> Res
     let mut found_model = false;
     let mut found_dataset = false;
 
-    for cross_ref in &manifest.cross_references {
+    for cross_ref in manifest
+        .cross_references
+        .iter()
+        .filter(|cross_ref| !attestation::is_envelope_reference(cross_ref))
+    {
         match storage.retrieve_manifest(&cross_ref.manifest_url) {
             Ok(ref_manifest) => {
                 if manifest_type_to_str(&determine_manifest_type(&ref_manifest)) == "Model" {
@@ -114,17 +143,280 @@ pub fn verify_evaluation_manifest(id: &str, storage: &dyn StorageBackend) -> Res
         ));
     }
 
-    println!("✓ Evaluation manifest verification successful");
+    println!(
+        "{} Evaluation manifest verification successful",
+        color::check_mark()
+    );
+    Ok(())
+}
+
+/// A metric value of an evaluation, one point in the metric history of a model
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EvaluationPoint {
+    /// Creation time of the evaluation manifest, RFC 3339
+    pub timestamp: String,
+    pub evaluation_id: String,
+    pub evaluation_name: String,
+    /// The evaluated model, the requested one or a base model or fine-tune of it
+    pub model_id: String,
+    pub dataset_id: String,
+    pub metric: String,
+    pub value: f64,
+}
+
+/// Collects the metric values of the evaluations of a model, oldest first
+///
+/// Evaluations of any version of the model count: those linked to its ID or to a versioned
+/// ID of it, and those of its base models and fine-tunes. Only numeric metric values are
+/// collected, and only `metric` if given.
+pub fn evaluation_history(
+    model_id: &str,
+    metric: Option<&str>,
+    storage: &(impl StorageBackend + ?Sized),
+) -> Result<Vec<EvaluationPoint>> {
+    let model_ids = model_versions(model_id, storage)?;
+
+    let ids: Vec<String> = storage
+        .list_manifests()?
+        .into_iter()
+        .map(|metadata| metadata.id)
+        .collect();
+    let fetched = traversal::fetch_manifests(&ids, storage, traversal::MAX_CONCURRENT_FETCHES);
+
+    let mut points = Vec::new();
+    for (evaluation_id, manifest) in ids.into_iter().zip(fetched) {
+        // Manifests that cannot be read are not evaluations of the model, as far as we know
+        let Ok(manifest) = manifest else {
+            continue;
+        };
+        if !is_evaluation_manifest(&manifest) {
+            continue;
+        }
+        let Some(params) = evaluation_parameters(&manifest) else {
+            continue;
+        };
+
+        // Cross-references also name the dataset, so only the evaluated model counts
+        let Some(evaluated) = params
+            .get("model_id")
+            .and_then(|id| id.as_str())
+            .and_then(|id| {
+                model_ids
+                    .iter()
+                    .find(|model_id| same_manifest(id, model_id))
+            })
+        else {
+            continue;
+        };
+
+        let timestamp = manifest
+            .created_at
+            .0
+            .format(&time::format_description::well_known::Rfc3339)
+            .map_err(|e| Error::Serialization(e.to_string()))?;
+        let dataset_id = params
+            .get("dataset_id")
+            .and_then(|id| id.as_str())
+            .unwrap_or_default();
+
+        for (name, value) in params
+            .get("metrics")
+            .and_then(|metrics| metrics.as_object())
+            .into_iter()
+            .flatten()
+        {
+            if metric.is_some_and(|metric| metric != name) {
+                continue;
+            }
+            let value = match value {
+                serde_json::Value::Number(n) => n.as_f64(),
+                serde_json::Value::String(s) => s.trim().parse().ok(),
+                _ => None,
+            };
+            if let Some(value) = value {
+                points.push(EvaluationPoint {
+                    timestamp: timestamp.clone(),
+                    evaluation_id: evaluation_id.clone(),
+                    evaluation_name: manifest.title.clone(),
+                    model_id: evaluated.clone(),
+                    dataset_id: dataset_id.to_string(),
+                    metric: name.clone(),
+                    value,
+                });
+            }
+        }
+    }
+
+    points.sort_by(|a, b| {
+        (&a.timestamp, &a.evaluation_id, &a.metric).cmp(&(
+            &b.timestamp,
+            &b.evaluation_id,
+            &b.metric,
+        ))
+    });
+    Ok(points)
+}
+
+/// Renders a metric history as a table, or as "json" or "csv"
+pub fn render_evaluation_history(
+    points: &[EvaluationPoint],
+    output_format: &str,
+) -> Result<String> {
+    const COLUMNS: [&str; 7] = [
+        "timestamp",
+        "evaluation_id",
+        "evaluation_name",
+        "model_id",
+        "dataset_id",
+        "metric",
+        "value",
+    ];
+    let cells = |point: &EvaluationPoint| {
+        [
+            point.timestamp.clone(),
+            point.evaluation_id.clone(),
+            point.evaluation_name.clone(),
+            point.model_id.clone(),
+            point.dataset_id.clone(),
+            point.metric.clone(),
+            point.value.to_string(),
+        ]
+    };
+
+    let mut out = String::new();
+    match output_format.to_lowercase().as_str() {
+        "table" => {
+            if points.is_empty() {
+                out.push_str("No evaluations found\n");
+                return Ok(out);
+            }
+            let rows: Vec<[String; 7]> = points.iter().map(cells).collect();
+            let widths: Vec<usize> = (0..COLUMNS.len())
+                .map(|i| {
+                    rows.iter()
+                        .map(|row| row[i].chars().count())
+                        .chain([COLUMNS[i].len()])
+                        .max()
+                        .unwrap_or(0)
+                })
+                .collect();
+            let header = COLUMNS.map(str::to_uppercase);
+            for line in std::iter::once(&header).chain(&rows) {
+                let padded: Vec<String> = line
+                    .iter()
+                    .zip(&widths)
+                    .map(|(cell, width)| format!("{cell:<width$}"))
+                    .collect();
+                let _ = writeln!(out, "{}", padded.join("  ").trim_end());
+            }
+        }
+        "csv" => {
+            let csv_error =
+                |e: csv::Error| Error::Serialization(format!("Failed to write CSV: {e}"));
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            writer.write_record(COLUMNS).map_err(csv_error)?;
+            for point in points {
+                writer.write_record(cells(point)).map_err(csv_error)?;
+            }
+            let bytes = writer
+                .into_inner()
+                .map_err(|e| Error::Serialization(format!("Failed to write CSV: {e}")))?;
+            out = String::from_utf8_lossy(&bytes).into_owned();
+        }
+        "json" => {
+            out = serde_json::to_string_pretty(points)?;
+            out.push('\n');
+        }
+        _ => {
+            return Err(Error::Validation(format!(
+                "Invalid output format '{output_format}'. Valid options are: table, json, csv"
+            )));
+        }
+    }
+    Ok(out)
+}
+
+/// Prints the metric history of a model, or writes it to `output_path`
+pub fn print_evaluation_history(
+    model_id: &str,
+    metric: Option<&str>,
+    storage: &(impl StorageBackend + ?Sized),
+    output_format: &str,
+    output_path: Option<&Path>,
+) -> Result<()> {
+    let points = evaluation_history(model_id, metric, storage)?;
+    let rendered = render_evaluation_history(&points, output_format)?;
+
+    match output_path {
+        Some(path) => {
+            fs::write(path, rendered)?;
+            println!(
+                "Wrote {} evaluation results to {}",
+                points.len(),
+                path.display()
+            );
+        }
+        None => print!("{rendered}"),
+    }
     Ok(())
 }
 
+// The model and the versions of it found in its lineage
+fn model_versions(model_id: &str, storage: &(impl StorageBackend + ?Sized)) -> Result<Vec<String>> {
+    fn collect(nodes: &[LineageNode], ids: &mut Vec<String>) {
+        for node in nodes {
+            if matches!(node.role, LineageRole::BaseModel | LineageRole::FineTune)
+                && node.error.is_none()
+            {
+                if !ids.contains(&node.id) {
+                    ids.push(node.id.clone());
+                }
+                collect(&node.children, ids);
+            }
+        }
+    }
+
+    let lineage =
+        lineage::build_lineage(model_id, storage, LineageDirection::Both, HISTORY_MAX_DEPTH)?;
+    let mut ids = vec![model_id.to_string()];
+    for nodes in [&lineage.ancestors, &lineage.descendants]
+        .into_iter()
+        .flatten()
+    {
+        collect(nodes, &mut ids);
+    }
+    Ok(ids)
+}
+
+// Whether two IDs name the same manifest, also if one of them is a versioned ID
+fn same_manifest(a: &str, b: &str) -> bool {
+    let uuid = |id: &str| {
+        extract_uuid_from_urn(id)
+            .ok()
+            .or_else(|| Uuid::parse_str(id).ok())
+    };
+    match (uuid(a), uuid(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+// Parameters of the evaluation action: model_id, dataset_id and metrics
+fn evaluation_parameters(
+    manifest: &atlas_c2pa_lib::manifest::Manifest,
+) -> Option<&serde_json::Value> {
+    let assertions = &claims::active_claim(manifest).created_assertions;
+    assertions.iter().find_map(|assertion| match assertion {
+        Assertion::Action(action_assertion) => action_assertion
+            .actions
+            .iter()
+            .find(|action| action.action == "c2pa.evaluation")
+            .and_then(|action| action.parameters.as_ref()),
+        _ => None,
+    })
+}
+
 /// Check if a manifest is an evaluation result manifest
 fn is_evaluation_manifest(manifest: &atlas_c2pa_lib::manifest::Manifest) -> bool {
-    if let Some(claim) = &manifest.claim_v2 {
-        claim.created_assertions.iter().any(|assertion| {
-            matches!(assertion, Assertion::CreativeWork(creative_work) if creative_work.creative_type == "EvaluationResult")
-        })
-    } else {
-        false
-    }
+    claims::has_creative_work(manifest, "EvaluationResult")
 }
@@ -0,0 +1,322 @@
+//! Integrity checks over a whole manifest store.
+//!
+//! [`check_store`] reports structural problems that per-manifest verification does not
+//! catch because they span several manifests: cross-references to manifests that no
+//! longer exist, cross-reference hashes that no longer match their target, duplicated
+//! instance IDs, malformed hashes and linked ingredients that can't be reached.
+//! [`repair_store`] fixes the issues that can be fixed without human judgement.
+
+use super::object_store::{ObjectClient, ObjectUrl};
+use super::{attestation, linking, traversal, validate_hash_format};
+use crate::error::Result;
+use crate::hash;
+use crate::http;
+use crate::storage::traits::StorageBackend;
+use atlas_c2pa_lib::manifest::Manifest;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::Path;
+
+/// Kind of problem found by [`check_store`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FsckIssueKind {
+    /// A cross-reference points to a manifest that is not in the store
+    DanglingCrossReference,
+    /// A cross-reference hash doesn't match the current target manifest
+    StaleCrossReferenceHash,
+    /// Several stored entries share the same instance ID
+    DuplicateInstanceId,
+    /// A cross-reference or ingredient hash is not a supported hex digest
+    InvalidHashFormat,
+    /// The URL of a linked ingredient can't be resolved
+    UnreachableLinkedIngredient,
+    /// A listed manifest can't be retrieved or parsed
+    UnreadableManifest,
+    /// A referenced envelope doesn't match its cross-reference hash; envelopes don't
+    /// change once stored, so this isn't repaired
+    EnvelopeHashMismatch,
+}
+
+impl fmt::Display for FsckIssueKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            FsckIssueKind::DanglingCrossReference => "dangling-cross-reference",
+            FsckIssueKind::StaleCrossReferenceHash => "stale-cross-reference-hash",
+            FsckIssueKind::DuplicateInstanceId => "duplicate-instance-id",
+            FsckIssueKind::InvalidHashFormat => "invalid-hash-format",
+            FsckIssueKind::UnreachableLinkedIngredient => "unreachable-linked-ingredient",
+            FsckIssueKind::UnreadableManifest => "unreadable-manifest",
+            FsckIssueKind::EnvelopeHashMismatch => "envelope-hash-mismatch",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// A problem found in the store
+#[derive(Debug, Clone)]
+pub struct FsckIssue {
+    pub kind: FsckIssueKind,
+    /// Manifest the issue was found in
+    pub manifest_id: String,
+    /// Cross-reference target, for cross-reference issues
+    pub target_id: Option<String>,
+    pub detail: String,
+}
+
+impl FsckIssue {
+    /// Whether [`repair_store`] can fix this issue
+    pub fn is_repairable(&self) -> bool {
+        self.kind == FsckIssueKind::StaleCrossReferenceHash
+    }
+}
+
+impl fmt::Display for FsckIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}: {}", self.kind, self.manifest_id, self.detail)
+    }
+}
+
+/// Result of checking a store
+#[derive(Debug, Default)]
+pub struct FsckReport {
+    /// Number of manifests checked
+    pub manifests_checked: usize,
+    pub issues: Vec<FsckIssue>,
+}
+
+impl FsckReport {
+    pub fn is_clean(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// Scans every manifest in the store for integrity problems
+pub fn check_store(storage: &(impl StorageBackend + ?Sized)) -> Result<FsckReport> {
+    let listed = storage.list_manifests()?;
+    let mut report = FsckReport::default();
+
+    // Duplicate instance IDs; manifests are checked in ID order
+    let mut occurrences: BTreeMap<&str, usize> = BTreeMap::new();
+    for metadata in &listed {
+        *occurrences.entry(metadata.id.as_str()).or_default() += 1;
+    }
+    for (id, count) in &occurrences {
+        if *count > 1 {
+            report.issues.push(FsckIssue {
+                kind: FsckIssueKind::DuplicateInstanceId,
+                manifest_id: id.to_string(),
+                target_id: None,
+                detail: format!("{count} stored entries share this instance ID"),
+            });
+        }
+    }
+
+    let ids: Vec<String> = occurrences.keys().map(|id| id.to_string()).collect();
+    let fetched = traversal::fetch_manifests(&ids, storage, traversal::MAX_CONCURRENT_FETCHES);
+
+    let mut manifests: HashMap<String, Manifest> = HashMap::new();
+    for (id, result) in ids.iter().zip(fetched) {
+        match result {
+            Ok(manifest) => {
+                manifests.insert(id.clone(), manifest);
+            }
+            Err(e) => report.issues.push(FsckIssue {
+                kind: FsckIssueKind::UnreadableManifest,
+                manifest_id: id.clone(),
+                target_id: None,
+                detail: e.to_string(),
+            }),
+        }
+    }
+
+    for id in &ids {
+        let Some(manifest) = manifests.get(id) else {
+            continue;
+        };
+        report.manifests_checked += 1;
+
+        check_cross_references(id, manifest, &manifests, storage, &mut report.issues);
+        check_ingredients(id, manifest, &mut report.issues);
+    }
+
+    Ok(report)
+}
+
+fn check_cross_references(
+    id: &str,
+    manifest: &Manifest,
+    manifests: &HashMap<String, Manifest>,
+    storage: &(impl StorageBackend + ?Sized),
+    issues: &mut Vec<FsckIssue>,
+) {
+    for cross_ref in &manifest.cross_references {
+        let target_id = &cross_ref.manifest_url;
+        let issue = |kind, detail| FsckIssue {
+            kind,
+            manifest_id: id.to_string(),
+            target_id: Some(target_id.clone()),
+            detail,
+        };
+
+        if let Err(e) = validate_hash_format(&cross_ref.manifest_hash) {
+            issues.push(issue(
+                FsckIssueKind::InvalidHashFormat,
+                format!("cross-reference to {target_id}: {e}"),
+            ));
+            continue;
+        }
+
+        if attestation::is_envelope_reference(cross_ref) {
+            match storage.retrieve_envelope(target_id) {
+                Ok(_) => {
+                    if let Err(e) = attestation::verify_envelope_reference(cross_ref, storage) {
+                        issues.push(issue(FsckIssueKind::EnvelopeHashMismatch, e.to_string()));
+                    }
+                }
+                Err(_) => issues.push(issue(
+                    FsckIssueKind::DanglingCrossReference,
+                    format!("referenced envelope {target_id} not found"),
+                )),
+            }
+            continue;
+        }
+
+        // Targets that aren't listed may still be retrievable, e.g. by base URN
+        let target = match manifests.get(target_id) {
+            Some(target) => target.clone(),
+            None => match storage.retrieve_manifest(target_id) {
+                Ok(target) => target,
+                Err(_) => {
+                    issues.push(issue(
+                        FsckIssueKind::DanglingCrossReference,
+                        format!("referenced manifest {target_id} not found"),
+                    ));
+                    continue;
+                }
+            },
+        };
+
+        match cross_reference_hash(&target, &cross_ref.manifest_hash) {
+            Ok(current_hash) if current_hash != cross_ref.manifest_hash => issues.push(issue(
+                FsckIssueKind::StaleCrossReferenceHash,
+                format!(
+                    "hash of {target_id} changed: stored={}, current={current_hash}",
+                    cross_ref.manifest_hash
+                ),
+            )),
+            Ok(_) => {}
+            Err(e) => issues.push(issue(FsckIssueKind::UnreadableManifest, e.to_string())),
+        }
+    }
+}
+
+fn check_ingredients(id: &str, manifest: &Manifest, issues: &mut Vec<FsckIssue>) {
+    for ingredient in &manifest.ingredients {
+        if let Err(e) = validate_hash_format(&ingredient.data.hash) {
+            issues.push(FsckIssue {
+                kind: FsckIssueKind::InvalidHashFormat,
+                manifest_id: id.to_string(),
+                target_id: None,
+                detail: format!("ingredient {}: {e}", ingredient.title),
+            });
+        }
+
+        // Manifest links point to manifests, which are checked with the cross-references
+        if let Some(linked) = &ingredient.linked_ingredient
+            && !linking::is_manifest_link(ingredient)
+            && !is_reachable(&linked.url)
+        {
+            issues.push(FsckIssue {
+                kind: FsckIssueKind::UnreachableLinkedIngredient,
+                manifest_id: id.to_string(),
+                target_id: None,
+                detail: format!(
+                    "linked ingredient of {} at {} is unreachable",
+                    ingredient.title, linked.url
+                ),
+            });
+        }
+    }
+}
+
+// Hash of a cross-reference target, using the algorithm of the stored hash
+fn cross_reference_hash(target: &Manifest, stored_hash: &str) -> Result<String> {
+    let json = serde_json::to_string(target)?;
+    let algorithm = hash::detect_hash_algorithm(stored_hash);
+    Ok(hash::calculate_hash_with_algorithm(
+        json.as_bytes(),
+        &algorithm,
+    ))
+}
+
+// Local paths must exist, remote URLs and cloud objects must answer a HEAD request
+fn is_reachable(url: &str) -> bool {
+    if let Some(object) = ObjectUrl::parse(url) {
+        return ObjectClient::from_env(object.store)
+            .is_ok_and(|client| client.stat(&object).is_ok());
+    }
+
+    if url.starts_with("http://") || url.starts_with("https://") {
+        return http::client_builder()
+            .build()
+            .and_then(|client| client.head(url).send())
+            .is_ok_and(|response| response.status().is_success());
+    }
+
+    crate::utils::file_url_to_path(url)
+        .unwrap_or_else(|| Path::new(url).to_path_buf())
+        .exists()
+}
+
+/// Repairs the repairable issues of a report, asking `confirm` before each change.
+///
+/// Stale cross-reference hashes are recomputed from the current target manifest. Each
+/// source manifest is stored once with all of its repaired cross-references. Returns the
+/// number of issues repaired.
+pub fn repair_store(
+    report: &FsckReport,
+    storage: &(impl StorageBackend + ?Sized),
+    mut confirm: impl FnMut(&FsckIssue) -> bool,
+) -> Result<usize> {
+    // Confirmed repairs grouped by source manifest, in report order
+    let mut repairs: Vec<(&str, Vec<&str>)> = Vec::new();
+    for issue in report.issues.iter().filter(|issue| issue.is_repairable()) {
+        let Some(target_id) = issue.target_id.as_deref() else {
+            continue;
+        };
+        if !confirm(issue) {
+            continue;
+        }
+
+        match repairs
+            .iter_mut()
+            .find(|(source, _)| *source == issue.manifest_id)
+        {
+            Some((_, targets)) => targets.push(target_id),
+            None => repairs.push((&issue.manifest_id, vec![target_id])),
+        }
+    }
+
+    let mut repaired = 0;
+    for (source_id, targets) in repairs {
+        let mut manifest = storage.retrieve_manifest(source_id)?;
+
+        // Malformed hashes are left alone, they need a new link rather than a rehash
+        for cross_ref in manifest.cross_references.iter_mut().filter(|cross_ref| {
+            targets.contains(&cross_ref.manifest_url.as_str())
+                && validate_hash_format(&cross_ref.manifest_hash).is_ok()
+        }) {
+            let target = storage.retrieve_manifest(&cross_ref.manifest_url)?;
+            let current_hash = cross_reference_hash(&target, &cross_ref.manifest_hash)?;
+            if current_hash != cross_ref.manifest_hash {
+                cross_ref.manifest_hash = current_hash;
+                repaired += 1;
+            }
+        }
+
+        let updated_id = storage.store_manifest(&manifest)?;
+        println!("Repaired cross-references of {source_id} (updated manifest ID: {updated_id})");
+    }
+
+    Ok(repaired)
+}
@@ -0,0 +1,483 @@
+//! Assertions contributed by external generators when manifests are created.
+//!
+//! Organizations often need manifests to carry metadata atlas-cli knows nothing about, such
+//! as internal compliance records or the results of a model scanner. Generators declared in
+//! `ATLAS_GENERATORS_FILE` or `~/.atlas/generators.yaml` are run for every manifest created:
+//!
+//! ```yaml
+//! generators:
+//!   - name: compliance
+//!     command: /opt/compliance/atlas-assertions
+//!     args: [--team, ml-platform]
+//!     kinds: [model, dataset]
+//!   - name: scanner
+//!     wasm: /opt/scanners/pickle-scan.wasm
+//!     optional: true
+//! ```
+//!
+//! A generator is either an executable (`command`) or a WASI module (`wasm`), which is run
+//! with `ATLAS_WASM_RUNTIME` (`wasmtime` by default). It reads a JSON request describing
+//! the manifest from stdin and writes the assertions to add to stdout:
+//!
+//! ```json
+//! {"assertions": [{"label": "org.example.compliance", "data": {"reviewed": true}}]}
+//! ```
+//!
+//! Each manifest records which generators contributed to it, with the SHA-256 of the
+//! executable or module that was run, in an `assertion_generators` assertion.
+
+use crate::error::{Error, Result};
+use crate::manifest::common::AssetKind;
+use crate::manifest::config::ManifestCreationConfig;
+use atlas_c2pa_lib::assertion::{Assertion, CustomAssertion};
+use atlas_c2pa_lib::ingredient::Ingredient;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use time::OffsetDateTime;
+
+/// Environment variable naming the generators file
+pub const GENERATORS_FILE_ENV: &str = "ATLAS_GENERATORS_FILE";
+
+/// Environment variable naming the runtime that runs WASI generators
+pub const WASM_RUNTIME_ENV: &str = "ATLAS_WASM_RUNTIME";
+
+/// Runtime that runs WASI generators unless configured otherwise
+pub const DEFAULT_WASM_RUNTIME: &str = "wasmtime";
+
+/// Label of the assertion recording the generators that contributed to a manifest
+pub const GENERATORS_ASSERTION_LABEL: &str = "assertion_generators";
+
+/// Version of the protocol spoken with generators
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// An external generator of assertions
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GeneratorConfig {
+    pub name: String,
+    /// Executable to run, looked up in `PATH` unless it is a path
+    #[serde(default)]
+    pub command: Option<String>,
+    /// WASI module to run instead of an executable
+    #[serde(default)]
+    pub wasm: Option<PathBuf>,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Asset kinds the generator runs for; all if empty
+    #[serde(default)]
+    pub kinds: Vec<AssetKind>,
+    /// Whether manifests are created without the generator's assertions when it fails
+    #[serde(default)]
+    pub optional: bool,
+}
+
+/// Contents of the generators file
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GeneratorsConfig {
+    pub generators: Vec<GeneratorConfig>,
+}
+
+/// Provenance of the assertions contributed by a generator
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GeneratorRecord {
+    pub name: String,
+    /// Executable or WASI module that was run
+    pub path: String,
+    /// Hex-encoded SHA-256 of the executable or module
+    pub sha256: String,
+    /// Labels of the assertions it contributed
+    pub assertions: Vec<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub timestamp: OffsetDateTime,
+}
+
+#[derive(Deserialize)]
+struct GeneratorResponse {
+    #[serde(default)]
+    assertions: Vec<CustomAssertion>,
+}
+
+/// Location of the generators file: `ATLAS_GENERATORS_FILE`, or `~/.atlas/generators.yaml`
+pub fn generators_file() -> PathBuf {
+    match std::env::var_os(GENERATORS_FILE_ENV) {
+        Some(path) => PathBuf::from(path),
+        None => std::env::var_os("HOME")
+            .or_else(|| std::env::var_os("USERPROFILE"))
+            .map(|home| PathBuf::from(home).join(".atlas"))
+            .unwrap_or_default()
+            .join("generators.yaml"),
+    }
+}
+
+impl GeneratorsConfig {
+    /// Reads a generators file, or returns `None` if there is none
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let config: Self = serde_yaml::from_str(&std::fs::read_to_string(path)?).map_err(|e| {
+            Error::Validation(format!("Invalid generators file {}: {e}", path.display()))
+        })?;
+        for generator in &config.generators {
+            if generator.command.is_some() == generator.wasm.is_some() {
+                return Err(Error::Validation(format!(
+                    "Generator '{}' in {} needs either a command or a wasm module",
+                    generator.name,
+                    path.display()
+                )));
+            }
+        }
+        Ok(Some(config))
+    }
+}
+
+// Finds an executable given by name in PATH
+fn resolve_command(command: &str) -> Result<PathBuf> {
+    let path = Path::new(command);
+    if path.components().count() > 1 {
+        return Ok(path.to_path_buf());
+    }
+    std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter()
+        .map(|directory| directory.join(command))
+        .find(|candidate| candidate.is_file())
+        .ok_or_else(|| Error::Validation(format!("Generator command {command} not found in PATH")))
+}
+
+impl GeneratorConfig {
+    /// Whether the generator runs for manifests of `asset_kind`
+    pub fn applies_to(&self, asset_kind: AssetKind) -> bool {
+        self.kinds.is_empty() || self.kinds.contains(&asset_kind)
+    }
+
+    // The program to run with its arguments, and the file whose digest is recorded
+    fn invocation(&self) -> Result<(Command, PathBuf)> {
+        match (&self.command, &self.wasm) {
+            (Some(command), _) => {
+                let path = resolve_command(command)?;
+                let mut invocation = Command::new(&path);
+                invocation.args(&self.args);
+                Ok((invocation, path))
+            }
+            (None, Some(module)) => {
+                let runtime = std::env::var(WASM_RUNTIME_ENV)
+                    .unwrap_or_else(|_| DEFAULT_WASM_RUNTIME.to_string());
+                let mut invocation = Command::new(runtime);
+                invocation.arg("run").arg(module).args(&self.args);
+                Ok((invocation, module.clone()))
+            }
+            (None, None) => Err(Error::Validation(format!(
+                "Generator '{}' needs either a command or a wasm module",
+                self.name
+            ))),
+        }
+    }
+
+    /// Runs the generator, returning its assertions and their provenance
+    pub fn run(
+        &self,
+        request: &serde_json::Value,
+    ) -> Result<(Vec<CustomAssertion>, GeneratorRecord)> {
+        let (mut invocation, path) = self.invocation()?;
+        let sha256 = hex::encode(Sha256::digest(std::fs::read(&path).map_err(|e| {
+            Error::Validation(format!(
+                "Cannot read generator '{}' at {}: {e}",
+                self.name,
+                path.display()
+            ))
+        })?));
+
+        let input = serde_json::to_vec(request)?;
+        let mut child = invocation
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| {
+                Error::Validation(format!("Failed to run generator '{}': {e}", self.name))
+            })?;
+        // Written from another thread, so a generator writing a large response before it
+        // read the whole request can't block on a full stdout pipe
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let writer = std::thread::spawn(move || stdin.write_all(&input));
+        let output = child.wait_with_output()?;
+        let _ = writer.join();
+
+        if !output.status.success() {
+            return Err(Error::Validation(format!(
+                "Generator '{}' failed ({}): {}",
+                self.name,
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        let response: GeneratorResponse = serde_json::from_slice(&output.stdout).map_err(|e| {
+            Error::Validation(format!(
+                "Invalid response of generator '{}': {e}",
+                self.name
+            ))
+        })?;
+
+        let record = GeneratorRecord {
+            name: self.name.clone(),
+            path: path.display().to_string(),
+            sha256,
+            assertions: response
+                .assertions
+                .iter()
+                .map(|assertion| assertion.label.clone())
+                .collect(),
+            timestamp: OffsetDateTime::now_utc(),
+        };
+        Ok((response.assertions, record))
+    }
+}
+
+/// Request sent to generators, describing the manifest being created
+pub fn generator_request(
+    config: &ManifestCreationConfig,
+    asset_kind: AssetKind,
+    ingredients: &[Ingredient],
+    assertions: &[Assertion],
+) -> Result<serde_json::Value> {
+    let labels: Vec<_> = assertions.iter().filter_map(assertion_label).collect();
+    Ok(json!({
+        "version": PROTOCOL_VERSION,
+        "asset_kind": asset_kind.to_string(),
+        "name": config.name,
+        "author_org": config.author_org,
+        "description": config.description,
+        "ingredients": serde_json::to_value(ingredients)?,
+        "assertions": labels,
+    }))
+}
+
+fn assertion_label(assertion: &Assertion) -> Option<String> {
+    match assertion {
+        Assertion::CustomAssertion(custom) => Some(custom.label.clone()),
+        _ => None,
+    }
+}
+
+/// Runs the generators of the generators file for a manifest being created, adding their
+/// assertions and the record of where they came from to `assertions`.
+///
+/// Fails if a generator fails, unless it is optional, or contributes an assertion whose
+/// label the manifest already has.
+pub fn run_generators(
+    config: &ManifestCreationConfig,
+    asset_kind: AssetKind,
+    ingredients: &[Ingredient],
+    assertions: &mut Vec<Assertion>,
+) -> Result<()> {
+    let Some(generators) = GeneratorsConfig::load(&generators_file())? else {
+        return Ok(());
+    };
+    apply_generators(&generators, config, asset_kind, ingredients, assertions)
+}
+
+fn apply_generators(
+    generators: &GeneratorsConfig,
+    config: &ManifestCreationConfig,
+    asset_kind: AssetKind,
+    ingredients: &[Ingredient],
+    assertions: &mut Vec<Assertion>,
+) -> Result<()> {
+    let request = generator_request(config, asset_kind, ingredients, assertions)?;
+    let mut records = Vec::new();
+    for generator in generators
+        .generators
+        .iter()
+        .filter(|generator| generator.applies_to(asset_kind))
+    {
+        let (generated, record) = match generator.run(&request) {
+            Ok(result) => result,
+            Err(e) if generator.optional => {
+                eprintln!("Warning: skipping optional generator: {e}");
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+
+        for assertion in generated {
+            let label = assertion.label.clone();
+            if label == GENERATORS_ASSERTION_LABEL
+                || assertions
+                    .iter()
+                    .any(|existing| assertion_label(existing).as_deref() == Some(&label))
+            {
+                return Err(Error::Validation(format!(
+                    "Generator '{}' contributed assertion '{label}', which the manifest already has",
+                    generator.name
+                )));
+            }
+            assertions.push(Assertion::CustomAssertion(assertion));
+        }
+        records.push(record);
+    }
+
+    if !records.is_empty() {
+        assertions.push(Assertion::CustomAssertion(CustomAssertion {
+            label: GENERATORS_ASSERTION_LABEL.to_string(),
+            data: serde_json::to_value(&records)?,
+        }));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_generators() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("generators.yaml");
+        assert!(GeneratorsConfig::load(&path)?.is_none());
+
+        std::fs::write(
+            &path,
+            "generators:\n  - name: scanner\n    wasm: scan.wasm\n    kinds: [model]\n    optional: true\n",
+        )?;
+        let config = GeneratorsConfig::load(&path)?.unwrap();
+        assert_eq!(config.generators.len(), 1);
+        assert!(config.generators[0].applies_to(AssetKind::Model));
+        assert!(!config.generators[0].applies_to(AssetKind::Dataset));
+
+        // A generator is either a command or a module
+        std::fs::write(&path, "generators:\n  - name: scanner\n")?;
+        assert!(GeneratorsConfig::load(&path).is_err());
+        std::fs::write(
+            &path,
+            "generators:\n  - name: scanner\n    command: scan\n    wasm: scan.wasm\n",
+        )?;
+        assert!(GeneratorsConfig::load(&path).is_err());
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_apply_generators() -> Result<()> {
+        use crate::manifest::ingest::IngestPolicy;
+        use atlas_c2pa_lib::cose::HashAlgorithm;
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir()?;
+        let script = |name: &str, body: &str| -> Result<String> {
+            let path = dir.path().join(name);
+            std::fs::write(&path, format!("#!/bin/sh\n{body}\n"))?;
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))?;
+            Ok(path.display().to_string())
+        };
+        let request = dir.path().join("request.json");
+        let compliance = script(
+            "compliance",
+            &format!(
+                "cat > {}\necho '{{\"assertions\": [{{\"label\": \"org.example.compliance\", \"data\": {{\"reviewed\": true}}}}]}}'",
+                request.display()
+            ),
+        )?;
+        let failing = script("failing", "echo 'scanner unavailable' >&2\nexit 1")?;
+        let duplicate = script(
+            "duplicate",
+            "echo '{\"assertions\": [{\"label\": \"org.example.compliance\", \"data\": {}}]}'",
+        )?;
+        let generator = |name: &str, command: &str, optional: bool| GeneratorConfig {
+            name: name.to_string(),
+            command: Some(command.to_string()),
+            wasm: None,
+            args: Vec::new(),
+            kinds: Vec::new(),
+            optional,
+        };
+
+        let config = ManifestCreationConfig {
+            paths: Vec::new(),
+            ingredient_names: Vec::new(),
+            ingredient_relationships: Vec::new(),
+            expand_archives: false,
+            profile_data: false,
+            max_sample_bytes: crate::manifest::profile::DEFAULT_MAX_SAMPLE_BYTES,
+            stream_connections: crate::manifest::object_store::DEFAULT_CONNECTIONS,
+            ingest_policy: IngestPolicy::default(),
+            path_base: None,
+            dataset_ids: Vec::new(),
+            name: "Model".to_string(),
+            author_org: None,
+            author_name: None,
+            description: None,
+            linked_manifests: None,
+            storage: None,
+            print: true,
+            output_encoding: "json".to_string(),
+            output_path: None,
+            key_path: None,
+            pq_key_path: None,
+            hash_alg: HashAlgorithm::Sha384,
+            valid_until: None,
+            created_at: None,
+            with_cc: false,
+            tpm: None,
+            software_type: None,
+            version: None,
+            encryption: None,
+            content_addressed_id: false,
+            force_new: false,
+            custom_fields: None,
+            inherited_assertions: vec![],
+        };
+
+        // Optional generators that fail are skipped; the others are recorded
+        let generators = GeneratorsConfig {
+            generators: vec![
+                generator("compliance", &compliance, false),
+                generator("scanner", &failing, true),
+            ],
+        };
+        let mut assertions = Vec::new();
+        apply_generators(&generators, &config, AssetKind::Model, &[], &mut assertions)?;
+        let labels: Vec<_> = assertions.iter().filter_map(assertion_label).collect();
+        assert_eq!(
+            labels,
+            vec!["org.example.compliance", GENERATORS_ASSERTION_LABEL]
+        );
+        let Assertion::CustomAssertion(provenance) = &assertions[1] else {
+            panic!("expected the generators assertion");
+        };
+        let records: Vec<GeneratorRecord> = serde_json::from_value(provenance.data.clone())?;
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].name, "compliance");
+        assert_eq!(
+            records[0].sha256,
+            hex::encode(Sha256::digest(std::fs::read(&compliance)?))
+        );
+        let sent: serde_json::Value = serde_json::from_slice(&std::fs::read(&request)?)?;
+        assert_eq!(sent["asset_kind"], "model");
+        assert_eq!(sent["name"], "Model");
+
+        // Required generators that fail, and duplicate labels, fail the creation
+        let required = GeneratorsConfig {
+            generators: vec![generator("scanner", &failing, false)],
+        };
+        let error = apply_generators(&required, &config, AssetKind::Model, &[], &mut Vec::new())
+            .unwrap_err();
+        assert!(error.to_string().contains("scanner unavailable"));
+        let duplicates = GeneratorsConfig {
+            generators: vec![
+                generator("compliance", &compliance, false),
+                generator("duplicate", &duplicate, false),
+            ],
+        };
+        assert!(
+            apply_generators(&duplicates, &config, AssetKind::Model, &[], &mut Vec::new()).is_err()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,220 @@
+//! Commands run before and after manifests are created and verified.
+//!
+//! Hooks integrate atlas-cli with systems it has no first-class support for, such as
+//! notifying a model registry or uploading artifacts once a manifest is stored. They are
+//! declared in `ATLAS_HOOKS_FILE` or `~/.atlas/hooks.yaml`:
+//!
+//! ```yaml
+//! pre-create:
+//!   - command: /opt/registry/check-name
+//! post-create:
+//!   - command: curl
+//!     args: [-sf, -X, POST, --data-binary, "@-", https://registry.example.com/notify]
+//! post-verify:
+//!   - command: /opt/registry/record-verification
+//! ```
+//!
+//! Each hook gets the manifest JSON on stdin, and its stage, the manifest ID and, after
+//! verification, its outcome in the `ATLAS_HOOK`, `ATLAS_MANIFEST_ID` and
+//! `ATLAS_VERIFY_OUTCOME` environment variables. A failing `pre-create` hook stops the
+//! manifest from being signed and stored; failing post hooks only print a warning, since
+//! the operation they follow already happened.
+
+use crate::error::{Error, Result};
+use atlas_c2pa_lib::manifest::Manifest;
+use serde::Deserialize;
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Environment variable naming the hooks file
+pub const HOOKS_FILE_ENV: &str = "ATLAS_HOOKS_FILE";
+
+/// Point at which hooks run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookStage {
+    /// Before a created manifest is signed, output and stored
+    PreCreate,
+    /// After a manifest was created, and stored if a storage backend was given
+    PostCreate,
+    /// After a stored manifest was verified, successfully or not
+    PostVerify,
+}
+
+impl fmt::Display for HookStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HookStage::PreCreate => write!(f, "pre-create"),
+            HookStage::PostCreate => write!(f, "post-create"),
+            HookStage::PostVerify => write!(f, "post-verify"),
+        }
+    }
+}
+
+/// A command run as a hook
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Hook {
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+/// Contents of the hooks file
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct HooksConfig {
+    pub pre_create: Vec<Hook>,
+    pub post_create: Vec<Hook>,
+    pub post_verify: Vec<Hook>,
+}
+
+/// Location of the hooks file: `ATLAS_HOOKS_FILE`, or `~/.atlas/hooks.yaml`
+pub fn hooks_file() -> PathBuf {
+    match std::env::var_os(HOOKS_FILE_ENV) {
+        Some(path) => PathBuf::from(path),
+        None => std::env::var_os("HOME")
+            .or_else(|| std::env::var_os("USERPROFILE"))
+            .map(|home| PathBuf::from(home).join(".atlas"))
+            .unwrap_or_default()
+            .join("hooks.yaml"),
+    }
+}
+
+impl HooksConfig {
+    /// Reads a hooks file, or returns `None` if there is none
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        serde_yaml::from_str(&std::fs::read_to_string(path)?)
+            .map(Some)
+            .map_err(|e| Error::Validation(format!("Invalid hooks file {}: {e}", path.display())))
+    }
+
+    /// The hooks run at `stage`
+    pub fn hooks(&self, stage: HookStage) -> &[Hook] {
+        match stage {
+            HookStage::PreCreate => &self.pre_create,
+            HookStage::PostCreate => &self.post_create,
+            HookStage::PostVerify => &self.post_verify,
+        }
+    }
+
+    /// Runs the hooks of `stage` in order, stopping at the first that fails
+    pub fn run(
+        &self,
+        stage: HookStage,
+        manifest: &Manifest,
+        id: Option<&str>,
+        verified: Option<bool>,
+    ) -> Result<()> {
+        let hooks = self.hooks(stage);
+        if hooks.is_empty() {
+            return Ok(());
+        }
+        let input = serde_json::to_vec(manifest)?;
+        for hook in hooks {
+            hook.run(stage, &input, id, verified)?;
+        }
+        Ok(())
+    }
+}
+
+impl Hook {
+    fn run(
+        &self,
+        stage: HookStage,
+        input: &[u8],
+        id: Option<&str>,
+        verified: Option<bool>,
+    ) -> Result<()> {
+        let mut command = Command::new(&self.command);
+        command
+            .args(&self.args)
+            .env("ATLAS_HOOK", stage.to_string())
+            .stdin(Stdio::piped())
+            // Hooks report to the user like the command they run in
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::inherit());
+        if let Some(id) = id {
+            command.env("ATLAS_MANIFEST_ID", id);
+        }
+        if let Some(verified) = verified {
+            let outcome = if verified { "verified" } else { "failed" };
+            command.env("ATLAS_VERIFY_OUTCOME", outcome);
+        }
+
+        let mut child = command.spawn().map_err(|e| {
+            Error::Validation(format!("Failed to run {stage} hook {}: {e}", self.command))
+        })?;
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        // Hooks may exit without reading the manifest
+        if let Err(e) = stdin.write_all(input)
+            && e.kind() != std::io::ErrorKind::BrokenPipe
+        {
+            return Err(e.into());
+        }
+        drop(stdin);
+
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(Error::Validation(format!(
+                "{stage} hook {} failed ({status})",
+                self.command
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Runs the hooks of the hooks file for `stage`.
+///
+/// A failing `pre-create` hook is an error; failing post hooks print a warning.
+pub fn run_hooks(
+    stage: HookStage,
+    manifest: &Manifest,
+    id: Option<&str>,
+    verified: Option<bool>,
+) -> Result<()> {
+    let result = HooksConfig::load(&hooks_file()).and_then(|config| match config {
+        Some(config) => config.run(stage, manifest, id, verified),
+        None => Ok(()),
+    });
+    match result {
+        Err(e) if stage != HookStage::PreCreate => {
+            eprintln!("Warning: {e}");
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_hooks() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("hooks.yaml");
+        assert!(HooksConfig::load(&path)?.is_none());
+
+        std::fs::write(
+            &path,
+            "pre-create:\n  - command: check\npost-verify:\n  - command: notify\n    args: [--verified]\n",
+        )?;
+        let config = HooksConfig::load(&path)?.unwrap();
+        assert_eq!(config.hooks(HookStage::PreCreate)[0].command, "check");
+        assert!(config.hooks(HookStage::PostCreate).is_empty());
+        assert_eq!(
+            config.hooks(HookStage::PostVerify)[0].args,
+            vec!["--verified"]
+        );
+
+        std::fs::write(&path, "post-store:\n  - command: notify\n")?;
+        assert!(HooksConfig::load(&path).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,353 @@
+//! Directory ingestion policy.
+//!
+//! When a path given to `create` is a directory, every regular file below it becomes an
+//! ingredient. What happens to symlinks, hard links, special files (sockets, FIFOs,
+//! devices) and empty directories is decided by an [`IngestPolicy`] rather than by the
+//! platform. The policy, the symlinks it recorded and the empty directories it recorded are
+//! stored in an assertion, and verification applies the same policy.
+
+use crate::cli::color;
+use crate::error::{Error, Result};
+use crate::hash;
+use crate::manifest::claims;
+use crate::utils::{long_path, relative_url_to_path};
+use atlas_c2pa_lib::assertion::{Assertion, CustomAssertion};
+use atlas_c2pa_lib::cose::HashAlgorithm;
+use atlas_c2pa_lib::manifest::Manifest;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Label of the ingest policy assertion
+pub const INGEST_POLICY_ASSERTION_LABEL: &str = "ingest_policy";
+
+/// Handling of symlinks inside ingested directories
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SymlinkPolicy {
+    /// Hash the link target as if it were at the link path
+    Follow,
+    /// Record the link and its target without hashing the target
+    Record,
+    /// Leave links out of the manifest
+    #[default]
+    Skip,
+}
+
+/// Handling of files with more than one hard link
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HardlinkPolicy {
+    /// Hash each link as a separate file
+    Hash,
+    /// Fail on files with several hard links
+    #[default]
+    Reject,
+}
+
+/// Handling of sockets, FIFOs and device files
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpecialFilePolicy {
+    Skip,
+    #[default]
+    Reject,
+}
+
+/// Handling of empty directories
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmptyDirPolicy {
+    /// Record empty directories so that verification checks they still exist
+    Record,
+    #[default]
+    Skip,
+}
+
+/// How directory entries other than regular files are handled
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IngestPolicy {
+    pub symlinks: SymlinkPolicy,
+    pub hardlinks: HardlinkPolicy,
+    pub special_files: SpecialFilePolicy,
+    pub empty_dirs: EmptyDirPolicy,
+}
+
+/// Contents of the ingest policy assertion
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IngestAssertion {
+    pub policy: IngestPolicy,
+    /// Recorded symlinks and their targets
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub symlinks: BTreeMap<String, String>,
+    /// Recorded empty directories
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub empty_dirs: Vec<String>,
+}
+
+/// Result of walking a directory
+#[derive(Debug, Default)]
+pub struct DirectoryContents {
+    /// Regular files (and followed symlinks) to hash, in path order
+    pub files: Vec<PathBuf>,
+    pub symlinks: BTreeMap<String, String>,
+    pub empty_dirs: Vec<String>,
+}
+
+impl IngestPolicy {
+    /// Walks a directory, sorting its entries by the policy
+    pub fn walk_directory(&self, root: &Path) -> Result<DirectoryContents> {
+        let mut contents = DirectoryContents::default();
+        let walker = WalkDir::new(root)
+            .follow_links(self.symlinks == SymlinkPolicy::Follow)
+            .sort_by_file_name();
+
+        for entry in walker {
+            let entry = entry.map_err(|e| {
+                Error::Validation(format!("Failed to read directory {}: {e}", root.display()))
+            })?;
+            let path = entry.path();
+            let file_type = entry.file_type();
+
+            if file_type.is_symlink() {
+                // Followed links report the type of their target, so these are not followed
+                if self.symlinks == SymlinkPolicy::Record {
+                    let target = fs::read_link(path)?;
+                    contents.symlinks.insert(
+                        path.to_string_lossy().into_owned(),
+                        target.to_string_lossy().into_owned(),
+                    );
+                }
+            } else if file_type.is_dir() {
+                if self.empty_dirs == EmptyDirPolicy::Record && fs::read_dir(path)?.next().is_none()
+                {
+                    contents
+                        .empty_dirs
+                        .push(path.to_string_lossy().into_owned());
+                }
+            } else if file_type.is_file() {
+                self.check_hardlinks(path)?;
+                contents.files.push(path.to_path_buf());
+            } else if self.special_files == SpecialFilePolicy::Reject {
+                return Err(Error::Validation(format!(
+                    "{} is not a regular file. Use --special-files=skip to leave it out.",
+                    path.display()
+                )));
+            }
+        }
+
+        Ok(contents)
+    }
+
+    /// Hashes a file found in a directory, enforcing the policy again so that
+    /// verification treats the file the same way creation did
+    pub fn hash_file(&self, path: &Path, algorithm: &HashAlgorithm) -> Result<String> {
+        if fs::symlink_metadata(path)?.file_type().is_symlink()
+            && self.symlinks != SymlinkPolicy::Follow
+        {
+            return Err(Error::Validation(format!(
+                "{} is a symlink, which the ingest policy does not follow",
+                path.display()
+            )));
+        }
+        if !fs::metadata(path)?.is_file() {
+            return Err(Error::Validation(format!(
+                "{} is not a regular file",
+                path.display()
+            )));
+        }
+        self.check_hardlinks(path)?;
+
+        hash::calculate_reader_hash_with_algorithm(File::open(long_path(path))?, algorithm)
+    }
+
+    #[cfg(unix)]
+    fn check_hardlinks(&self, path: &Path) -> Result<()> {
+        use std::os::unix::fs::MetadataExt;
+
+        let links = fs::metadata(path)?.nlink();
+        if links > 1 && self.hardlinks == HardlinkPolicy::Reject {
+            return Err(Error::Validation(format!(
+                "{} has multiple hard links ({links}). Use --hardlinks=hash to hash it anyway.",
+                path.display()
+            )));
+        }
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    fn check_hardlinks(&self, _path: &Path) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Creates the ingest policy assertion
+pub fn ingest_assertion(
+    policy: IngestPolicy,
+    symlinks: BTreeMap<String, String>,
+    empty_dirs: Vec<String>,
+) -> Result<CustomAssertion> {
+    Ok(CustomAssertion {
+        label: INGEST_POLICY_ASSERTION_LABEL.to_string(),
+        data: serde_json::to_value(IngestAssertion {
+            policy,
+            symlinks,
+            empty_dirs,
+        })?,
+    })
+}
+
+/// Returns the ingest policy assertion of a manifest, if any
+pub fn ingest_policy(manifest: &Manifest) -> Result<Option<IngestAssertion>> {
+    let assertions = &claims::signed_claim(manifest).created_assertions;
+
+    for assertion in assertions {
+        if let Assertion::CustomAssertion(custom) = assertion
+            && custom.label == INGEST_POLICY_ASSERTION_LABEL
+        {
+            return serde_json::from_value(custom.data.clone())
+                .map(Some)
+                .map_err(|e| Error::Manifest(format!("Invalid ingest policy assertion: {e}")));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Checks that recorded symlinks still point to their targets and that recorded empty
+/// directories still exist. Entries recorded relative to a path base are resolved against
+/// `artifact_root`.
+pub fn verify_recorded_entries(
+    recorded: &IngestAssertion,
+    artifact_root: Option<&Path>,
+) -> Result<()> {
+    let resolve = |entry: &str| {
+        let path = relative_url_to_path(entry).unwrap_or_else(|| PathBuf::from(entry));
+        match artifact_root {
+            Some(root) => root.join(path),
+            None => path,
+        }
+    };
+
+    for (link, target) in &recorded.symlinks {
+        match fs::read_link(resolve(link)) {
+            Ok(current) if current == Path::new(target) => {
+                println!(
+                    "{} Verified symlink: {link} -> {target}",
+                    color::check_mark()
+                );
+            }
+            Ok(current) => {
+                return Err(Error::Validation(format!(
+                    "Symlink {link} points to {} instead of {target}",
+                    current.display()
+                )));
+            }
+            Err(e) => {
+                return Err(Error::Validation(format!(
+                    "Recorded symlink {link} could not be read: {e}"
+                )));
+            }
+        }
+    }
+
+    for dir in &recorded.empty_dirs {
+        if !resolve(dir).is_dir() {
+            return Err(Error::Validation(format!(
+                "Recorded directory {dir} no longer exists"
+            )));
+        }
+        println!("{} Verified directory: {dir}", color::check_mark());
+    }
+
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+    use std::os::unix::net::UnixListener;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_walk_directory_policies() -> Result<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+        fs::write(root.join("a.txt"), b"a")?;
+        fs::create_dir(root.join("empty"))?;
+        symlink(root.join("a.txt"), root.join("link.txt"))?;
+        let _socket = UnixListener::bind(root.join("socket"))?;
+
+        // Special files are rejected by default
+        assert!(IngestPolicy::default().walk_directory(root).is_err());
+
+        let policy = IngestPolicy {
+            special_files: SpecialFilePolicy::Skip,
+            ..IngestPolicy::default()
+        };
+        let contents = policy.walk_directory(root)?;
+        assert_eq!(contents.files, vec![root.join("a.txt")]);
+        assert!(contents.symlinks.is_empty() && contents.empty_dirs.is_empty());
+
+        let policy = IngestPolicy {
+            symlinks: SymlinkPolicy::Record,
+            empty_dirs: EmptyDirPolicy::Record,
+            special_files: SpecialFilePolicy::Skip,
+            ..IngestPolicy::default()
+        };
+        let contents = policy.walk_directory(root)?;
+        let link = root.join("link.txt").to_string_lossy().into_owned();
+        assert_eq!(
+            contents.symlinks.get(&link).map(String::as_str),
+            Some(root.join("a.txt").to_str().unwrap())
+        );
+        assert_eq!(contents.empty_dirs.len(), 1);
+        verify_recorded_entries(
+            &IngestAssertion {
+                policy,
+                symlinks: contents.symlinks,
+                empty_dirs: contents.empty_dirs,
+            },
+            None,
+        )?;
+
+        let policy = IngestPolicy {
+            symlinks: SymlinkPolicy::Follow,
+            special_files: SpecialFilePolicy::Skip,
+            ..IngestPolicy::default()
+        };
+        let contents = policy.walk_directory(root)?;
+        assert_eq!(contents.files.len(), 2);
+        assert_eq!(
+            policy.hash_file(&root.join("link.txt"), &HashAlgorithm::Sha256)?,
+            hash::calculate_hash_with_algorithm(b"a", &HashAlgorithm::Sha256)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_hardlink_policy() -> Result<()> {
+        let dir = tempdir()?;
+        let file = dir.path().join("a.txt");
+        fs::write(&file, b"a")?;
+        fs::hard_link(&file, dir.path().join("b.txt"))?;
+
+        assert!(IngestPolicy::default().walk_directory(dir.path()).is_err());
+
+        let policy = IngestPolicy {
+            hardlinks: HardlinkPolicy::Hash,
+            ..IngestPolicy::default()
+        };
+        assert_eq!(policy.walk_directory(dir.path())?.files.len(), 2);
+        assert!(policy.hash_file(&file, &HashAlgorithm::Sha256).is_ok());
+        assert!(
+            IngestPolicy::default()
+                .hash_file(&file, &HashAlgorithm::Sha256)
+                .is_err()
+        );
+        Ok(())
+    }
+}
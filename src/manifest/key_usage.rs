@@ -0,0 +1,197 @@
+//! Key usage audit.
+//!
+//! When a signing key is compromised or due for rotation, the question is what it signed.
+//! `key usage` scans every manifest and envelope of a store for signatures made with a
+//! public key: claim signatures, reviewer approvals and DSSE envelope signatures. Only
+//! signatures that verify with the key are reported, so a recorded key that didn't make a
+//! signature is not mistaken for a use of it.
+
+use crate::error::Result;
+use crate::manifest::approval;
+use crate::manifest::signer::{key_fingerprint, signing_key_fingerprint};
+use crate::manifest::timestamp::to_rfc3339_utc;
+use crate::storage::traits::StorageBackend;
+use atlas_c2pa_lib::cose::HashAlgorithm;
+use atlas_c2pa_lib::manifest::Manifest;
+use openssl::pkey::{PKey, Public};
+use serde::Serialize;
+use std::fmt;
+use time::OffsetDateTime;
+
+/// How a key was used
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeyUse {
+    /// Signed the claim of a manifest
+    ManifestSignature,
+    /// Signed a reviewer approval recorded in a manifest
+    Approval,
+    /// Signed a stored DSSE envelope
+    EnvelopeSignature,
+}
+
+impl fmt::Display for KeyUse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            KeyUse::ManifestSignature => "manifest-signature",
+            KeyUse::Approval => "approval",
+            KeyUse::EnvelopeSignature => "envelope-signature",
+        })
+    }
+}
+
+/// A signature made with the audited key
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyUsage {
+    #[serde(rename = "use")]
+    pub key_use: KeyUse,
+    /// ID of the manifest or envelope
+    pub id: String,
+    pub name: String,
+    /// When the signature was made, RFC 3339: the creation time of the manifest or
+    /// envelope, or the time of the approval
+    pub used_at: String,
+    /// Decision and role of approvals, kind of envelopes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// Uses of a key across a store
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyUsageReport {
+    /// SHA-256 fingerprint of the key, see [`key_fingerprint`]
+    pub fingerprint: String,
+    /// Uses of the key, oldest first
+    pub usages: Vec<KeyUsage>,
+    pub manifests_scanned: usize,
+    pub envelopes_scanned: usize,
+    /// Manifests whose signatures could not be checked, with the reason
+    pub unchecked: Vec<(String, String)>,
+}
+
+impl KeyUsageReport {
+    /// Time of the first use of the key, RFC 3339
+    pub fn first_used(&self) -> Option<&str> {
+        self.usages.first().map(|usage| usage.used_at.as_str())
+    }
+
+    /// Time of the last use of the key, RFC 3339
+    pub fn last_used(&self) -> Option<&str> {
+        self.usages.last().map(|usage| usage.used_at.as_str())
+    }
+}
+
+/// A verified signature in a manifest
+#[derive(Debug, Clone)]
+pub struct ManifestSignature {
+    /// Fingerprint of the signing key, see [`key_fingerprint`]
+    pub fingerprint: String,
+    pub key_use: KeyUse,
+    /// Creation time of the manifest for its claim signature, or the time of an approval
+    pub signed_at: OffsetDateTime,
+    /// Decision and role of approvals
+    pub detail: Option<String>,
+}
+
+/// Returns the signatures of a manifest that verify with the key they record: the claim
+/// signature and the signatures of approvals
+pub fn manifest_signatures(manifest: &Manifest) -> Result<Vec<ManifestSignature>> {
+    let mut signatures = Vec::new();
+    if let Some(fingerprint) = signing_key_fingerprint(manifest)? {
+        signatures.push(ManifestSignature {
+            fingerprint,
+            key_use: KeyUse::ManifestSignature,
+            signed_at: manifest.created_at.0,
+            detail: None,
+        });
+    }
+
+    for approval in approval::approvals(manifest)? {
+        let Ok(public_key) = PKey::public_key_from_pem(approval.public_key.as_bytes()) else {
+            continue;
+        };
+        if approval.verify_signature()? {
+            signatures.push(ManifestSignature {
+                fingerprint: key_fingerprint(&public_key)?,
+                key_use: KeyUse::Approval,
+                signed_at: approval.timestamp,
+                detail: Some(format!("{} as {}", approval.decision, approval.role)),
+            });
+        }
+    }
+    Ok(signatures)
+}
+
+// Uses of the key with fingerprint `fingerprint` in a manifest
+fn manifest_usages(id: &str, manifest: &Manifest, fingerprint: &str) -> Result<Vec<KeyUsage>> {
+    Ok(manifest_signatures(manifest)?
+        .into_iter()
+        .filter(|signature| signature.fingerprint == fingerprint)
+        .map(|signature| KeyUsage {
+            key_use: signature.key_use,
+            id: id.to_string(),
+            name: manifest.title.clone(),
+            used_at: to_rfc3339_utc(signature.signed_at),
+            detail: signature.detail,
+        })
+        .collect())
+}
+
+/// Finds the signatures made with `public_key` in the manifests and envelopes of `storage`
+pub fn scan_key_usage(
+    storage: &dyn StorageBackend,
+    public_key: &PKey<Public>,
+) -> Result<KeyUsageReport> {
+    let fingerprint = key_fingerprint(public_key)?;
+    let mut report = KeyUsageReport {
+        fingerprint: fingerprint.clone(),
+        usages: Vec::new(),
+        manifests_scanned: 0,
+        envelopes_scanned: 0,
+        unchecked: Vec::new(),
+    };
+
+    for metadata in storage.list_manifests()? {
+        let manifest = storage.retrieve_manifest(&metadata.id)?;
+        report.manifests_scanned += 1;
+        match manifest_usages(&metadata.id, &manifest, &fingerprint) {
+            Ok(usages) => report.usages.extend(usages),
+            Err(e) => report.unchecked.push((metadata.id, e.to_string())),
+        }
+    }
+
+    if storage.supports_envelopes() {
+        for metadata in storage.list_envelopes()? {
+            let record = storage.retrieve_envelope(&metadata.id)?;
+            report.envelopes_scanned += 1;
+
+            // Envelopes don't record the hash algorithm of their signatures, so each is tried
+            let mut signed = false;
+            for hash_alg in [
+                HashAlgorithm::Sha256,
+                HashAlgorithm::Sha384,
+                HashAlgorithm::Sha512,
+            ] {
+                if record.envelope.verify_signature(public_key, &hash_alg)? {
+                    signed = true;
+                    break;
+                }
+            }
+            if signed {
+                report.usages.push(KeyUsage {
+                    key_use: KeyUse::EnvelopeSignature,
+                    id: metadata.id,
+                    name: metadata.name,
+                    used_at: record.created_at,
+                    detail: Some(record.kind.to_string()),
+                });
+            }
+        }
+    }
+
+    // RFC 3339 UTC timestamps sort chronologically as text
+    report
+        .usages
+        .sort_by(|a, b| a.used_at.cmp(&b.used_at).then_with(|| a.id.cmp(&b.id)));
+    Ok(report)
+}
@@ -0,0 +1,313 @@
+//! Import of legacy artifacts from an inventory.
+//!
+//! `import legacy` bootstraps provenance for models, datasets and software that predate
+//! atlas-cli. The inventory is a CSV file with a header row and these columns:
+//!
+//! | Column | Required | Contents |
+//! |---|---|---|
+//! | `path` | yes | File, directory or `s3://`/`gs://` object; relative paths are relative to the inventory |
+//! | `name` | yes | Name of the manifest and its ingredient |
+//! | `type` | no | `model`, `dataset` or `software`; `--type` when empty |
+//! | `author` | no | Person recorded as the author |
+//! | `date` | no | Creation date: RFC 3339 timestamp or `YYYY-MM-DD` |
+//! | `description` | no | Description of the artifact |
+//!
+//! Artifacts are hashed several at a time, then signed and stored in inventory order. Each
+//! manifest records where it was imported from in a `legacy_import` assertion, together
+//! with the inventory date and the time of the import, so a backdated creation time is
+//! never mistaken for the time the manifest was made.
+
+use crate::error::{Error, Result};
+use crate::manifest::common::{AssetKind, build_manifest, finish_manifest};
+use crate::manifest::config::ManifestCreationConfig;
+use crate::manifest::ingest::IngestPolicy;
+use crate::manifest::object_store::{self, ObjectUrl};
+use crate::manifest::{profile, timestamp, traversal};
+use crate::storage::traits::StorageBackend;
+use atlas_c2pa_lib::cose::HashAlgorithm;
+use atlas_c2pa_lib::manifest::Manifest;
+use std::path::{Path, PathBuf};
+use time::OffsetDateTime;
+
+/// Label of the assertion recording the inventory row a manifest was imported from
+pub const LEGACY_IMPORT_ASSERTION_LABEL: &str = "legacy_import";
+
+/// Default number of artifacts hashed at the same time
+pub const DEFAULT_IMPORT_JOBS: usize = 4;
+
+const COLUMNS: [&str; 6] = ["path", "name", "type", "author", "date", "description"];
+
+/// An artifact listed in the inventory
+#[derive(Debug, Clone, PartialEq)]
+pub struct InventoryRow {
+    /// Line of the row in the inventory file
+    pub line: u64,
+    pub path: PathBuf,
+    pub name: String,
+    pub kind: Option<AssetKind>,
+    pub author: Option<String>,
+    pub date: Option<String>,
+    pub description: Option<String>,
+}
+
+/// Settings shared by all manifests of an import
+#[derive(Debug, Clone)]
+pub struct ImportOptions {
+    /// Asset kind of rows without a type
+    pub default_kind: AssetKind,
+    /// Record the inventory date as the creation time of the manifest
+    pub backdate: bool,
+    /// Number of artifacts hashed at the same time
+    pub jobs: usize,
+    pub author_org: Option<String>,
+    pub path_base: Option<PathBuf>,
+    pub key_path: Option<PathBuf>,
+    pub hash_alg: HashAlgorithm,
+}
+
+/// Outcome of importing one inventory row
+#[derive(Debug)]
+pub struct ImportResult {
+    pub line: u64,
+    pub name: String,
+    /// ID of the stored manifest
+    pub id: Result<String>,
+}
+
+/// Parses the asset type of an inventory row or of `--type`
+pub fn parse_asset_kind(kind: &str) -> Result<AssetKind> {
+    match kind {
+        "model" => Ok(AssetKind::Model),
+        "dataset" => Ok(AssetKind::Dataset),
+        "software" => Ok(AssetKind::Software),
+        _ => Err(Error::Validation(format!(
+            "Unknown asset type '{kind}', expected model, dataset or software"
+        ))),
+    }
+}
+
+/// Reads an inventory. Relative paths are resolved against the directory of the inventory,
+/// and dates are checked up front so that a bad row fails before anything is hashed.
+pub fn read_inventory(path: &Path) -> Result<Vec<InventoryRow>> {
+    let invalid =
+        |e: csv::Error| Error::Validation(format!("Failed to parse {}: {e}", path.display()));
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_path(path)
+        .map_err(invalid)?;
+
+    let headers: Vec<String> = reader
+        .headers()
+        .map_err(invalid)?
+        .iter()
+        .map(|header| header.to_lowercase())
+        .collect();
+    if let Some(unknown) = headers.iter().find(|h| !COLUMNS.contains(&h.as_str())) {
+        return Err(Error::Validation(format!(
+            "Unknown column '{unknown}' in {}, expected {}",
+            path.display(),
+            COLUMNS.join(", ")
+        )));
+    }
+    for required in ["path", "name"] {
+        if !headers.iter().any(|h| h == required) {
+            return Err(Error::Validation(format!(
+                "Inventory {} has no '{required}' column",
+                path.display()
+            )));
+        }
+    }
+
+    let base = path.parent().unwrap_or(Path::new(""));
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(invalid)?;
+        let line = record.position().map_or(0, |position| position.line());
+        let field = |column: &str| {
+            headers
+                .iter()
+                .position(|h| h == column)
+                .and_then(|index| record.get(index))
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+        let row_error =
+            |message: String| Error::Validation(format!("{}:{line}: {message}", path.display()));
+
+        let (Some(artifact), Some(name)) = (field("path"), field("name")) else {
+            return Err(row_error("path and name are required".to_string()));
+        };
+        let kind = field("type")
+            .map(|kind| parse_asset_kind(&kind.to_lowercase()))
+            .transpose()
+            .map_err(|e| row_error(e.to_string()))?;
+        let date = field("date");
+        if let Some(date) = &date {
+            timestamp::parse_created_at(date).map_err(|e| row_error(e.to_string()))?;
+        }
+
+        let artifact = PathBuf::from(artifact);
+        let artifact =
+            if artifact.is_absolute() || ObjectUrl::parse(&artifact.to_string_lossy()).is_some() {
+                artifact
+            } else {
+                base.join(artifact)
+            };
+
+        rows.push(InventoryRow {
+            line,
+            path: artifact,
+            name,
+            kind,
+            author: field("author"),
+            date,
+            description: field("description"),
+        });
+    }
+    Ok(rows)
+}
+
+impl ImportOptions {
+    /// The creation config of the manifest of an inventory row
+    pub fn row_config(
+        &self,
+        inventory: &Path,
+        row: &InventoryRow,
+        imported_at: OffsetDateTime,
+        storage: Option<&'static dyn StorageBackend>,
+    ) -> Result<ManifestCreationConfig> {
+        let created_at = match (&row.date, self.backdate) {
+            (Some(date), true) => Some(timestamp::parse_created_at(date)?),
+            _ => None,
+        };
+
+        let mut config = ManifestCreationConfig {
+            paths: vec![row.path.clone()],
+            ingredient_names: vec![row.name.clone()],
+            ingredient_relationships: Vec::new(),
+            expand_archives: false,
+            profile_data: false,
+            max_sample_bytes: profile::DEFAULT_MAX_SAMPLE_BYTES,
+            stream_connections: object_store::DEFAULT_CONNECTIONS,
+            ingest_policy: IngestPolicy::default(),
+            path_base: self.path_base.clone(),
+            dataset_ids: Vec::new(),
+            name: row.name.clone(),
+            author_org: self.author_org.clone(),
+            author_name: row.author.clone(),
+            description: row.description.clone(),
+            linked_manifests: None,
+            storage,
+            print: false,
+            output_encoding: "json".to_string(),
+            output_path: None,
+            key_path: self.key_path.clone(),
+            pq_key_path: None,
+            hash_alg: self.hash_alg.clone(),
+            valid_until: None,
+            created_at,
+            with_cc: false,
+            tpm: None,
+            software_type: None,
+            version: None,
+            encryption: None,
+            content_addressed_id: false,
+            force_new: false,
+            custom_fields: None,
+            inherited_assertions: vec![],
+        };
+        config.set_custom_field(
+            LEGACY_IMPORT_ASSERTION_LABEL,
+            serde_json::json!({
+                "inventory": inventory.file_name().map(|name| name.to_string_lossy()),
+                "line": row.line,
+                "date": row.date,
+                "backdated": created_at.is_some(),
+                "imported_at": timestamp::to_rfc3339_utc(imported_at),
+            }),
+        )?;
+        Ok(config)
+    }
+}
+
+/// Creates and stores a manifest for every inventory row, returning the outcome of each
+/// row in inventory order. A failing row does not stop the others.
+pub fn import_inventory(
+    inventory: &Path,
+    rows: &[InventoryRow],
+    options: &ImportOptions,
+    storage: &'static dyn StorageBackend,
+) -> Result<Vec<ImportResult>> {
+    // Each manifest is signed separately, and standard input can only be read once
+    if options
+        .key_path
+        .as_deref()
+        .is_some_and(|key| key == Path::new(crate::signing::KEY_STDIN))
+    {
+        return Err(Error::Validation(
+            "import legacy signs a manifest per row and can't read the key from standard input; use a key file or env:<VAR>".to_string(),
+        ));
+    }
+
+    let imported_at = OffsetDateTime::now_utc();
+
+    // Hashing dominates, so the unsigned manifests are built concurrently
+    let built: Vec<Result<Manifest>> = traversal::map_concurrent(rows, options.jobs, |row| {
+        let config = options.row_config(inventory, row, imported_at, None)?;
+        build_manifest(&config, row.kind.unwrap_or(options.default_kind))
+    });
+
+    let mut results = Vec::new();
+    for (row, manifest) in rows.iter().zip(built) {
+        let id = manifest.and_then(|mut manifest| {
+            let config = options.row_config(inventory, row, imported_at, Some(storage))?;
+            finish_manifest(&mut manifest, &config)?
+                .ok_or_else(|| Error::Manifest(format!("Manifest for {} was not stored", row.name)))
+        });
+        results.push(ImportResult {
+            line: row.line,
+            name: row.name.clone(),
+            id,
+        });
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_inventory() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("inventory.csv");
+        std::fs::write(
+            &path,
+            "path,name,type,author,date\n\
+             models/resnet.onnx,ResNet,model,Jane Doe,2019-11-05\n\
+             /data/imagenet, ImageNet ,dataset,,\n",
+        )?;
+
+        let rows = read_inventory(&path)?;
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].path, dir.path().join("models/resnet.onnx"));
+        assert_eq!(rows[0].kind, Some(AssetKind::Model));
+        assert_eq!(rows[0].author.as_deref(), Some("Jane Doe"));
+        assert_eq!(rows[0].date.as_deref(), Some("2019-11-05"));
+        assert_eq!(rows[0].line, 2);
+        assert_eq!(rows[1].path, PathBuf::from("/data/imagenet"));
+        assert_eq!(rows[1].name, "ImageNet");
+        assert_eq!(rows[1].author, None);
+
+        std::fs::write(&path, "path,name,owner\nmodel.onnx,Model,me\n")?;
+        assert!(read_inventory(&path).is_err());
+        std::fs::write(&path, "path,type\nmodel.onnx,model\n")?;
+        assert!(read_inventory(&path).is_err());
+        std::fs::write(&path, "path,name,type\nmodel.onnx,Model,checkpoint\n")?;
+        assert!(read_inventory(&path).is_err());
+        std::fs::write(&path, "path,name,date\nmodel.onnx,Model,last year\n")?;
+        let error = read_inventory(&path).unwrap_err().to_string();
+        assert!(error.contains("inventory.csv:2"), "{error}");
+        Ok(())
+    }
+}
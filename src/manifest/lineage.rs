@@ -0,0 +1,345 @@
+//! Model lineage derived from the cross-reference graph.
+//!
+//! A manifest's cross-references point to what it was derived from: a model references its
+//! base model, its datasets and the software used to build it. Walking cross-references
+//! gives the ancestry of a manifest; walking them backwards over the whole store gives its
+//! descendants, such as fine-tunes and deployments.
+
+use super::utils::{determine_manifest_type, manifest_type_to_str};
+use super::{attestation, traversal};
+use crate::error::{Error, Result};
+use crate::storage::traits::{ManifestType, StorageBackend};
+use atlas_c2pa_lib::manifest::Manifest;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+/// Which side of the lineage to walk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineageDirection {
+    /// Ancestors: what the manifest was derived from
+    Up,
+    /// Descendants: what was derived from the manifest
+    Down,
+    Both,
+}
+
+impl LineageDirection {
+    fn includes_up(self) -> bool {
+        matches!(self, LineageDirection::Up | LineageDirection::Both)
+    }
+
+    fn includes_down(self) -> bool {
+        matches!(self, LineageDirection::Down | LineageDirection::Both)
+    }
+}
+
+/// Role of a manifest relative to the manifest it is shown under
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LineageRole {
+    Root,
+    BaseModel,
+    FineTune,
+    Model,
+    Dataset,
+    Software,
+    Deployment,
+    Manifest,
+}
+
+impl LineageRole {
+    fn as_str(self) -> &'static str {
+        match self {
+            LineageRole::Root => "root",
+            LineageRole::BaseModel => "base model",
+            LineageRole::FineTune => "fine-tune",
+            LineageRole::Model => "model",
+            LineageRole::Dataset => "dataset",
+            LineageRole::Software => "software",
+            LineageRole::Deployment => "deployment",
+            LineageRole::Manifest => "manifest",
+        }
+    }
+
+    /// Role of `child`, reached from a manifest of type `parent` by walking up or down
+    fn of(parent: &ManifestType, child: &ManifestType, up: bool) -> Self {
+        match (parent, child, up) {
+            (ManifestType::Model, ManifestType::Model, true) => LineageRole::BaseModel,
+            (ManifestType::Model, ManifestType::Model, false) => LineageRole::FineTune,
+            (ManifestType::Model, ManifestType::Software, false) => LineageRole::Deployment,
+            (_, ManifestType::Model, _) => LineageRole::Model,
+            (_, ManifestType::Dataset, _) => LineageRole::Dataset,
+            (_, ManifestType::Software, _) => LineageRole::Software,
+            (_, ManifestType::Unknown, _) => LineageRole::Manifest,
+        }
+    }
+}
+
+/// A manifest in the lineage tree
+#[derive(Debug, Clone, Serialize)]
+pub struct LineageNode {
+    pub id: String,
+    pub name: String,
+    pub manifest_type: String,
+    pub role: LineageRole,
+    /// Set when the manifest already appears above this node, which ends the branch
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub cycle: bool,
+    /// Set when the node has further relatives beyond the depth limit
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+    /// Why the manifest could not be retrieved
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<LineageNode>,
+}
+
+/// Ancestors and descendants of a manifest
+#[derive(Debug, Clone, Serialize)]
+pub struct Lineage {
+    pub root: LineageNode,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ancestors: Option<Vec<LineageNode>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub descendants: Option<Vec<LineageNode>>,
+}
+
+/// Manifests and the edges between them, in both directions
+struct LineageGraph {
+    manifests: HashMap<String, Result<Manifest>>,
+    /// Cross-reference sources for each target, in ID order
+    referrers: BTreeMap<String, Vec<String>>,
+}
+
+impl LineageGraph {
+    fn node(&self, id: &str, role: LineageRole) -> (LineageNode, Option<&Manifest>) {
+        let mut node = LineageNode {
+            id: id.to_string(),
+            name: String::new(),
+            manifest_type: manifest_type_to_str(&ManifestType::Unknown).to_string(),
+            role,
+            cycle: false,
+            truncated: false,
+            error: None,
+            children: Vec::new(),
+        };
+
+        match self.manifests.get(id) {
+            Some(Ok(manifest)) => {
+                node.name = manifest.title.clone();
+                node.manifest_type =
+                    manifest_type_to_str(&determine_manifest_type(manifest)).to_string();
+                (node, Some(manifest))
+            }
+            Some(Err(e)) => {
+                node.error = Some(e.to_string());
+                (node, None)
+            }
+            None => {
+                node.error = Some("manifest not found".to_string());
+                (node, None)
+            }
+        }
+    }
+
+    fn relatives(&self, manifest: &Manifest, up: bool) -> Vec<String> {
+        if up {
+            let mut ids: Vec<String> = Vec::new();
+            for cross_ref in manifest
+                .cross_references
+                .iter()
+                .filter(|cross_ref| !attestation::is_envelope_reference(cross_ref))
+            {
+                if !ids.contains(&cross_ref.manifest_url) {
+                    ids.push(cross_ref.manifest_url.clone());
+                }
+            }
+            ids
+        } else {
+            self.referrers
+                .get(&manifest.instance_id)
+                .cloned()
+                .unwrap_or_default()
+        }
+    }
+
+    /// Builds the subtree below `manifest`, walking up or down
+    fn branch(
+        &self,
+        manifest: &Manifest,
+        up: bool,
+        path: &mut Vec<String>,
+        depth: u32,
+        max_depth: u32,
+    ) -> (Vec<LineageNode>, bool) {
+        let relatives = self.relatives(manifest, up);
+        if depth >= max_depth {
+            return (Vec::new(), !relatives.is_empty());
+        }
+
+        let parent_type = determine_manifest_type(manifest);
+        let mut children = Vec::new();
+        for id in relatives {
+            let child_type = match self.manifests.get(&id) {
+                Some(Ok(child)) => determine_manifest_type(child),
+                _ => ManifestType::Unknown,
+            };
+            let (mut node, child) = self.node(&id, LineageRole::of(&parent_type, &child_type, up));
+
+            if path.contains(&id) {
+                node.cycle = true;
+            } else if let Some(child) = child {
+                path.push(id.clone());
+                (node.children, node.truncated) =
+                    self.branch(child, up, path, depth + 1, max_depth);
+                path.pop();
+            }
+            children.push(node);
+        }
+        (children, false)
+    }
+}
+
+/// Builds the lineage of a manifest, up to `max_depth` levels in each direction
+pub fn build_lineage(
+    id: &str,
+    storage: &(impl StorageBackend + ?Sized),
+    direction: LineageDirection,
+    max_depth: u32,
+) -> Result<Lineage> {
+    let root = storage
+        .retrieve_manifest(id)
+        .map_err(|e| Error::Manifest(format!("Failed to retrieve manifest {id}: {e}")))?;
+
+    let mut graph = LineageGraph {
+        manifests: HashMap::new(),
+        referrers: BTreeMap::new(),
+    };
+
+    if direction.includes_down() {
+        // Descendants are only known from the manifests that reference them
+        let ids: Vec<String> = storage
+            .list_manifests()?
+            .into_iter()
+            .map(|metadata| metadata.id)
+            .collect();
+        let fetched = traversal::fetch_manifests(&ids, storage, traversal::MAX_CONCURRENT_FETCHES);
+        for (source, result) in ids.into_iter().zip(fetched) {
+            if let Ok(manifest) = &result {
+                for cross_ref in manifest
+                    .cross_references
+                    .iter()
+                    .filter(|cross_ref| !attestation::is_envelope_reference(cross_ref))
+                {
+                    let sources = graph
+                        .referrers
+                        .entry(cross_ref.manifest_url.clone())
+                        .or_default();
+                    if !sources.contains(&source) {
+                        sources.push(source.clone());
+                    }
+                }
+            }
+            graph.manifests.insert(source, result);
+        }
+        for sources in graph.referrers.values_mut() {
+            sources.sort();
+        }
+    }
+
+    if direction.includes_up() {
+        for (linked_id, result) in traversal::prefetch_linked_manifests(
+            id,
+            storage,
+            max_depth,
+            traversal::MAX_CONCURRENT_FETCHES,
+        ) {
+            graph.manifests.entry(linked_id).or_insert(result);
+        }
+    }
+    graph.manifests.insert(id.to_string(), Ok(root.clone()));
+
+    let (root_node, _) = graph.node(id, LineageRole::Root);
+    let mut path = vec![id.to_string()];
+    let walk = |up: bool, path: &mut Vec<String>| graph.branch(&root, up, path, 0, max_depth).0;
+
+    Ok(Lineage {
+        ancestors: direction.includes_up().then(|| walk(true, &mut path)),
+        descendants: direction.includes_down().then(|| walk(false, &mut path)),
+        root: root_node,
+    })
+}
+
+/// Renders the lineage as an indented tree
+pub fn format_lineage_tree(lineage: &Lineage) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "{}", node_label(&lineage.root));
+
+    let sections = [
+        ("Ancestors", &lineage.ancestors),
+        ("Descendants", &lineage.descendants),
+    ];
+    for (title, nodes) in sections {
+        if let Some(nodes) = nodes {
+            let _ = writeln!(out, "\n{title}:");
+            if nodes.is_empty() {
+                let _ = writeln!(out, "  (none)");
+            }
+            for (index, node) in nodes.iter().enumerate() {
+                write_tree(&mut out, node, "  ", index + 1 == nodes.len());
+            }
+        }
+    }
+    out
+}
+
+fn write_tree(out: &mut String, node: &LineageNode, prefix: &str, last: bool) {
+    let branch = if last { "└── " } else { "├── " };
+    let _ = writeln!(out, "{prefix}{branch}{}", node_label(node));
+
+    let child_prefix = format!("{prefix}{}", if last { "    " } else { "│   " });
+    for (index, child) in node.children.iter().enumerate() {
+        write_tree(out, child, &child_prefix, index + 1 == node.children.len());
+    }
+}
+
+fn node_label(node: &LineageNode) -> String {
+    let mut label = format!("[{}] {}", node.role.as_str(), node.id);
+    if !node.name.is_empty() {
+        let _ = write!(label, " \"{}\"", node.name);
+    }
+    if let Some(error) = &node.error {
+        let _ = write!(label, " (unavailable: {error})");
+    }
+    if node.cycle {
+        label.push_str(" (cycle)");
+    }
+    if node.truncated {
+        label.push_str(" ...");
+    }
+    label
+}
+
+/// Prints the lineage of a manifest as a tree, or as JSON with `output_format` "json"
+pub fn print_lineage(
+    id: &str,
+    storage: &(impl StorageBackend + ?Sized),
+    direction: LineageDirection,
+    max_depth: u32,
+    output_format: &str,
+) -> Result<()> {
+    let lineage = build_lineage(id, storage, direction, max_depth)?;
+
+    match output_format.to_lowercase().as_str() {
+        "tree" => print!("{}", format_lineage_tree(&lineage)),
+        "json" => println!("{}", serde_json::to_string_pretty(&lineage)?),
+        _ => {
+            return Err(Error::Validation(format!(
+                "Invalid output format '{output_format}'. Valid options are: tree, json"
+            )));
+        }
+    }
+    Ok(())
+}
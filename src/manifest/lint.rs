@@ -0,0 +1,440 @@
+//! Lint rules for stored manifests.
+//!
+//! Manifests can be valid and verify, yet carry metadata that makes them less useful: no
+//! description, placeholder authors, ingredient URLs only meaningful on the machine that
+//! created them. `manifest lint` reports such findings at the severity configured for each
+//! rule in `ATLAS_LINT_FILE` or `~/.atlas/lint.yaml`:
+//!
+//! ```yaml
+//! rules:
+//!   missing-description: error
+//!   mixed-hash-algorithms: off
+//! ```
+//!
+//! Some findings can be fixed with `--fix`. Fixes change the claim, so they are only applied
+//! to unsigned manifests; a signed manifest has to be created again instead.
+
+use crate::error::{Error, Result};
+use crate::manifest::claims;
+use crate::manifest::common::{INGREDIENT_SIZES_ASSERTION_LABEL, relative_ingredient_url};
+use crate::manifest::ingest;
+use crate::utils::file_url_to_path;
+use atlas_c2pa_lib::assertion::Assertion;
+use atlas_c2pa_lib::claim::ClaimV2;
+use atlas_c2pa_lib::ingredient::Ingredient;
+use atlas_c2pa_lib::manifest::Manifest;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Environment variable naming the lint configuration file
+pub const LINT_FILE_ENV: &str = "ATLAS_LINT_FILE";
+
+// Author names recorded when none was given
+const PLACEHOLDER_AUTHORS: &[&str] = &["Unknown", "Organization"];
+
+/// Severity of the findings of a rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The rule is not checked
+    Off,
+    Info,
+    Warning,
+    /// Findings make `manifest lint` fail
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Off => write!(f, "off"),
+            Severity::Info => write!(f, "info"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A lint rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Rule {
+    /// The manifest has no description
+    MissingDescription,
+    /// An author is the placeholder recorded when none was given
+    PlaceholderAuthor,
+    /// An ingredient URL is an absolute local path
+    AbsolutePath,
+    /// Ingredients are hashed with different algorithms
+    MixedHashAlgorithms,
+    /// A cross-reference is not covered by a signature
+    UnsignedCrossReference,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 5] = [
+        Rule::MissingDescription,
+        Rule::PlaceholderAuthor,
+        Rule::AbsolutePath,
+        Rule::MixedHashAlgorithms,
+        Rule::UnsignedCrossReference,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Rule::MissingDescription => "missing-description",
+            Rule::PlaceholderAuthor => "placeholder-author",
+            Rule::AbsolutePath => "absolute-path",
+            Rule::MixedHashAlgorithms => "mixed-hash-algorithms",
+            Rule::UnsignedCrossReference => "unsigned-cross-reference",
+        }
+    }
+
+    /// Severity of the rule unless configured otherwise
+    pub fn default_severity(self) -> Severity {
+        match self {
+            Rule::MissingDescription | Rule::MixedHashAlgorithms => Severity::Info,
+            Rule::PlaceholderAuthor | Rule::AbsolutePath | Rule::UnsignedCrossReference => {
+                Severity::Warning
+            }
+        }
+    }
+
+    /// Returns the findings of the rule in `manifest`
+    pub fn check(self, manifest: &Manifest) -> Vec<String> {
+        let claim = claims::active_claim(manifest);
+        match self {
+            Rule::MissingDescription => {
+                let described = action_parameters(claim).any(|parameters| {
+                    parameters
+                        .get("description")
+                        .and_then(|description| description.as_str())
+                        .is_some_and(|description| !description.trim().is_empty())
+                });
+                if described {
+                    Vec::new()
+                } else {
+                    vec!["the manifest has no description (--description)".to_string()]
+                }
+            }
+            Rule::PlaceholderAuthor => placeholder_authors(claim)
+                .map(|(author_type, name)| {
+                    format!("{author_type} author is the placeholder \"{name}\"")
+                })
+                .collect(),
+            Rule::AbsolutePath => ingredients(manifest)
+                .filter(|ingredient| is_absolute_url(&ingredient.data.url))
+                .map(|ingredient| {
+                    format!(
+                        "ingredient {} has the absolute URL {} (use --path-base)",
+                        ingredient.title, ingredient.data.url
+                    )
+                })
+                .collect(),
+            Rule::MixedHashAlgorithms => {
+                let algorithms: BTreeSet<_> = ingredients(manifest)
+                    .map(|ingredient| ingredient.data.alg.as_str())
+                    .collect();
+                if algorithms.len() > 1 {
+                    vec![format!(
+                        "ingredients are hashed with {}",
+                        algorithms.into_iter().collect::<Vec<_>>().join(", ")
+                    )]
+                } else {
+                    Vec::new()
+                }
+            }
+            Rule::UnsignedCrossReference => {
+                let signed = claims::is_signed(manifest);
+                manifest
+                    .cross_references
+                    .iter()
+                    .filter(|cross_ref| {
+                        // Signed links are recorded by an ingredient of the signed claim
+                        !(signed
+                            && claim.ingredients.iter().any(|ingredient| {
+                                ingredient.linked_ingredient.is_some()
+                                    && ingredient.data.url == cross_ref.manifest_url
+                            }))
+                    })
+                    .map(|cross_ref| {
+                        format!(
+                            "cross-reference to {} is not covered by a signature",
+                            cross_ref.manifest_url
+                        )
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// Whether `--fix` can fix the findings of the rule in `manifest`
+    pub fn is_fixable(self, manifest: &Manifest, options: &LintOptions) -> bool {
+        !claims::is_signed(manifest)
+            && match self {
+                Rule::PlaceholderAuthor => true,
+                // The files of ingested directories are recorded by URL as well
+                Rule::AbsolutePath => {
+                    options.path_base.is_some()
+                        && !has_assertion(manifest, ingest::INGEST_POLICY_ASSERTION_LABEL)
+                }
+                _ => false,
+            }
+    }
+
+    // Fixes the findings of the rule in an unsigned manifest
+    fn fix(self, manifest: &mut Manifest, options: &LintOptions) -> Result<()> {
+        match self {
+            Rule::PlaceholderAuthor => {
+                for claim in std::iter::once(&mut manifest.claim).chain(manifest.claim_v2.as_mut())
+                {
+                    for assertion in &mut claim.created_assertions {
+                        if let Assertion::CreativeWork(creative_work) = assertion {
+                            creative_work.author.retain(|author| {
+                                !PLACEHOLDER_AUTHORS.contains(&author.name.as_str())
+                            });
+                        }
+                    }
+                }
+            }
+            Rule::AbsolutePath => {
+                let Some(base) = &options.path_base else {
+                    return Ok(());
+                };
+                let mut renamed = BTreeMap::new();
+                let ingredients = std::iter::once(&mut manifest.claim)
+                    .chain(manifest.claim_v2.as_mut())
+                    .flat_map(|claim| claim.ingredients.iter_mut())
+                    .chain(manifest.ingredients.iter_mut());
+                for ingredient in ingredients {
+                    if is_absolute_url(&ingredient.data.url) {
+                        let url = relative_ingredient_url(&ingredient.data.url, base)?;
+                        renamed.insert(
+                            std::mem::replace(&mut ingredient.data.url, url.clone()),
+                            url,
+                        );
+                    }
+                }
+
+                // Recorded sizes are keyed by ingredient URL
+                for claim in std::iter::once(&mut manifest.claim).chain(manifest.claim_v2.as_mut())
+                {
+                    for assertion in &mut claim.created_assertions {
+                        if let Assertion::CustomAssertion(custom) = assertion
+                            && custom.label == INGREDIENT_SIZES_ASSERTION_LABEL
+                            && let Some(sizes) = custom.data.as_object_mut()
+                        {
+                            *sizes = std::mem::take(sizes)
+                                .into_iter()
+                                .map(|(url, size)| {
+                                    (renamed.get(&url).cloned().unwrap_or(url), size)
+                                })
+                                .collect();
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl std::str::FromStr for Rule {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        Rule::ALL
+            .into_iter()
+            .find(|rule| rule.name() == name)
+            .ok_or_else(|| {
+                let names: Vec<_> = Rule::ALL.iter().map(|rule| rule.name()).collect();
+                Error::Validation(format!(
+                    "Unknown lint rule {name}; rules are {}",
+                    names.join(", ")
+                ))
+            })
+    }
+}
+
+// The ingredients of the claim and of the manifest, without links to other manifests
+fn ingredients(manifest: &Manifest) -> impl Iterator<Item = &Ingredient> {
+    let mut seen = BTreeSet::new();
+    claims::active_claim(manifest)
+        .ingredients
+        .iter()
+        .chain(&manifest.ingredients)
+        .filter(|ingredient| ingredient.linked_ingredient.is_none())
+        .filter(move |ingredient| seen.insert(&ingredient.data.url))
+}
+
+fn action_parameters(claim: &ClaimV2) -> impl Iterator<Item = &serde_json::Value> {
+    claim
+        .created_assertions
+        .iter()
+        .filter_map(|assertion| match assertion {
+            Assertion::Action(actions) => Some(actions),
+            _ => None,
+        })
+        .flat_map(|actions| &actions.actions)
+        .filter_map(|action| action.parameters.as_ref())
+}
+
+fn placeholder_authors(claim: &ClaimV2) -> impl Iterator<Item = (&str, &str)> {
+    claim
+        .created_assertions
+        .iter()
+        .filter_map(|assertion| match assertion {
+            Assertion::CreativeWork(creative_work) => Some(creative_work),
+            _ => None,
+        })
+        .flat_map(|creative_work| &creative_work.author)
+        .filter(|author| PLACEHOLDER_AUTHORS.contains(&author.name.as_str()))
+        .map(|author| (author.author_type.as_str(), author.name.as_str()))
+}
+
+fn has_assertion(manifest: &Manifest, label: &str) -> bool {
+    claims::active_claim(manifest).created_assertions.iter().any(|assertion| {
+        matches!(assertion, Assertion::CustomAssertion(custom) if custom.label == label)
+    })
+}
+
+fn is_absolute_url(url: &str) -> bool {
+    file_url_to_path(url).is_some() || Path::new(url).is_absolute()
+}
+
+/// Severities of the rules
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LintConfig {
+    severities: BTreeMap<Rule, Severity>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct LintFile {
+    rules: BTreeMap<String, Severity>,
+}
+
+/// Location of the lint configuration: `ATLAS_LINT_FILE`, or `~/.atlas/lint.yaml`
+pub fn lint_file() -> PathBuf {
+    match std::env::var_os(LINT_FILE_ENV) {
+        Some(path) => PathBuf::from(path),
+        None => std::env::var_os("HOME")
+            .or_else(|| std::env::var_os("USERPROFILE"))
+            .map(|home| PathBuf::from(home).join(".atlas"))
+            .unwrap_or_default()
+            .join("lint.yaml"),
+    }
+}
+
+impl LintConfig {
+    /// Reads a lint configuration, or returns the default severities if there is none
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let file: LintFile = serde_yaml::from_str(&std::fs::read_to_string(path)?)
+            .map_err(|e| Error::Validation(format!("Invalid lint file {}: {e}", path.display())))?;
+        let severities = file
+            .rules
+            .into_iter()
+            .map(|(name, severity)| Ok((name.parse()?, severity)))
+            .collect::<Result<_>>()?;
+        Ok(LintConfig { severities })
+    }
+
+    /// Sets the severity of `rule`
+    pub fn with_severity(mut self, rule: Rule, severity: Severity) -> Self {
+        self.severities.insert(rule, severity);
+        self
+    }
+
+    pub fn severity(&self, rule: Rule) -> Severity {
+        self.severities
+            .get(&rule)
+            .copied()
+            .unwrap_or_else(|| rule.default_severity())
+    }
+}
+
+/// Options of `manifest lint`
+#[derive(Debug, Clone, Default)]
+pub struct LintOptions {
+    /// Directory that absolute ingredient paths are made relative to when fixing them
+    pub path_base: Option<PathBuf>,
+}
+
+/// A finding of a lint rule
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Finding {
+    pub manifest_id: String,
+    pub rule: Rule,
+    pub severity: Severity,
+    pub message: String,
+    /// Whether `--fix` can fix it
+    pub fixable: bool,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} [{}] {}",
+            self.manifest_id, self.severity, self.rule, self.message
+        )?;
+        if self.fixable {
+            write!(f, " (fixable)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks a manifest against every rule that isn't turned off
+pub fn lint_manifest(
+    id: &str,
+    manifest: &Manifest,
+    config: &LintConfig,
+    options: &LintOptions,
+) -> Vec<Finding> {
+    Rule::ALL
+        .into_iter()
+        .filter(|rule| config.severity(*rule) != Severity::Off)
+        .flat_map(|rule| {
+            let fixable = rule.is_fixable(manifest, options);
+            rule.check(manifest)
+                .into_iter()
+                .map(move |message| Finding {
+                    manifest_id: id.to_string(),
+                    rule,
+                    severity: config.severity(rule),
+                    message,
+                    fixable,
+                })
+        })
+        .collect()
+}
+
+/// Fixes the fixable findings of a manifest, returning the rules that were fixed
+pub fn fix_manifest(
+    manifest: &mut Manifest,
+    findings: &[Finding],
+    options: &LintOptions,
+) -> Result<Vec<Rule>> {
+    let rules: BTreeSet<_> = findings
+        .iter()
+        .filter(|finding| finding.fixable)
+        .map(|finding| finding.rule)
+        .collect();
+    for rule in &rules {
+        rule.fix(manifest, options)?;
+    }
+    Ok(rules.into_iter().collect())
+}
@@ -0,0 +1,314 @@
+//! Migration of stored manifests to the current schema.
+//!
+//! Manifests written by earlier versions of atlas-cli differ from current ones in ways that
+//! still verify but that readers have to allow for:
+//!
+//! - `claim_v2` holds assertions or ingredients that were added to it but not to the
+//!   claim, so the signature doesn't cover them
+//! - the claim was signed over its `serde_cbor` encoding rather than its deterministic
+//!   one, see [`crate::manifest::canonical`]
+//! - the claim is signed but doesn't record the signer's key, so the signature can't be
+//!   attributed to a key
+//!
+//! Such manifests are at schema version 1; manifests without any of these are at
+//! [`CURRENT_SCHEMA_VERSION`]. Verification keeps accepting version 1 manifests and points
+//! at `manifest upgrade`, which applies the pending [`MigrationStep`]s, records them in a
+//! `schema_migration` assertion together with the hash of the manifest before the
+//! migration, re-signs the manifest and stores it again.
+
+use crate::error::{Error, Result};
+use crate::hash;
+use crate::manifest::canonical::ClaimEncoding;
+use crate::manifest::signer::{self, SIGNER_KEY_ASSERTION_LABEL};
+use crate::signing::piv::PIV_CERTIFICATE_ASSERTION_LABEL;
+use crate::signing::signable::Signable;
+use crate::storage::traits::StorageBackend;
+use atlas_c2pa_lib::assertion::{Assertion, CustomAssertion};
+use atlas_c2pa_lib::cose::HashAlgorithm;
+use atlas_c2pa_lib::ingredient::Ingredient;
+use atlas_c2pa_lib::manifest::Manifest;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::PathBuf;
+use time::OffsetDateTime;
+
+/// Label of the assertion recording a migration of the manifest
+pub const SCHEMA_MIGRATION_ASSERTION_LABEL: &str = "schema_migration";
+
+/// Schema version of manifests written by this version of atlas-cli
+pub const CURRENT_SCHEMA_VERSION: u32 = 2;
+
+/// A change bringing a manifest to the current schema
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MigrationStep {
+    /// Adds the assertions and ingredients only `claim_v2` has to the claim
+    SyncClaimV2,
+    /// Re-signs a claim signed over its legacy encoding
+    DeterministicEncoding,
+    /// Re-signs a claim that doesn't record its signer's key
+    RecordSignerKey,
+}
+
+impl fmt::Display for MigrationStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            MigrationStep::SyncClaimV2 => {
+                "claim_v2 has assertions or ingredients the signed claim doesn't"
+            }
+            MigrationStep::DeterministicEncoding => {
+                "the claim is signed over its legacy CBOR encoding"
+            }
+            MigrationStep::RecordSignerKey => "the claim doesn't record the key that signed it",
+        })
+    }
+}
+
+/// Contents of the schema migration assertion
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SchemaMigration {
+    pub from_version: u32,
+    pub to_version: u32,
+    pub steps: Vec<MigrationStep>,
+    /// Hash of the JSON-encoded manifest before the migration
+    pub previous_hash: String,
+    pub hash_algorithm: String,
+    /// Fingerprint of the key that signed the manifest before the migration, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_signer: Option<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub migrated_at: OffsetDateTime,
+}
+
+// Items of `theirs` missing from `ours`, compared by their JSON
+fn missing<T: Serialize + Clone>(ours: &[T], theirs: &[T]) -> Result<Vec<T>> {
+    let ours = ours
+        .iter()
+        .map(serde_json::to_value)
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    let mut missing = Vec::new();
+    for item in theirs {
+        if !ours.contains(&serde_json::to_value(item)?) {
+            missing.push(item.clone());
+        }
+    }
+    Ok(missing)
+}
+
+// Assertions and ingredients of claim_v2 that the claim doesn't have
+fn claim_v2_extras(manifest: &Manifest) -> Result<(Vec<Assertion>, Vec<Ingredient>)> {
+    let Some(claim_v2) = &manifest.claim_v2 else {
+        return Ok(Default::default());
+    };
+    Ok((
+        missing(
+            &manifest.claim.created_assertions,
+            &claim_v2.created_assertions,
+        )?,
+        missing(&manifest.claim.ingredients, &claim_v2.ingredients)?,
+    ))
+}
+
+fn has_assertion(manifest: &Manifest, label: &str) -> bool {
+    manifest.claim.created_assertions.iter().any(
+        |assertion| matches!(assertion, Assertion::CustomAssertion(custom) if custom.label == label),
+    )
+}
+
+/// The steps bringing a manifest to the current schema, empty if it is current
+pub fn pending_steps(manifest: &Manifest) -> Result<Vec<MigrationStep>> {
+    let mut steps = Vec::new();
+
+    // The claim holding more than claim_v2, such as the signer's key, is normal
+    let (assertions, ingredients) = claim_v2_extras(manifest)?;
+    if !assertions.is_empty() || !ingredients.is_empty() {
+        steps.push(MigrationStep::SyncClaimV2);
+    }
+
+    if manifest.claim.signature.is_some() {
+        if signer::claim_signature_encoding(manifest)? == Some(ClaimEncoding::Legacy) {
+            steps.push(MigrationStep::DeterministicEncoding);
+        }
+        if !has_assertion(manifest, SIGNER_KEY_ASSERTION_LABEL)
+            && !has_assertion(manifest, PIV_CERTIFICATE_ASSERTION_LABEL)
+        {
+            steps.push(MigrationStep::RecordSignerKey);
+        }
+    }
+    Ok(steps)
+}
+
+/// Schema version of a manifest: [`CURRENT_SCHEMA_VERSION`], or 1 if it has pending
+/// migration steps
+pub fn schema_version(manifest: &Manifest) -> Result<u32> {
+    Ok(if pending_steps(manifest)?.is_empty() {
+        CURRENT_SCHEMA_VERSION
+    } else {
+        1
+    })
+}
+
+/// Returns the migrations recorded in a manifest, oldest first
+pub fn migrations(manifest: &Manifest) -> Result<Vec<SchemaMigration>> {
+    manifest
+        .claim
+        .created_assertions
+        .iter()
+        .filter_map(|assertion| match assertion {
+            Assertion::CustomAssertion(custom)
+                if custom.label == SCHEMA_MIGRATION_ASSERTION_LABEL =>
+            {
+                Some(serde_json::from_value(custom.data.clone()).map_err(|e| {
+                    Error::Manifest(format!("Invalid schema migration assertion: {e}"))
+                }))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Applies migration steps to a manifest and records them, leaving it unsigned. Steps that
+/// re-sign the claim take effect when the manifest is signed again.
+pub fn migrate(
+    manifest: &mut Manifest,
+    steps: &[MigrationStep],
+    hash_alg: &HashAlgorithm,
+) -> Result<()> {
+    let previous_hash =
+        hash::calculate_hash_with_algorithm(&serde_json::to_vec(&*manifest)?, hash_alg);
+    let previous_signer = signer::signing_key_fingerprint(manifest)?;
+
+    if steps.contains(&MigrationStep::SyncClaimV2) {
+        let (assertions, ingredients) = claim_v2_extras(manifest)?;
+        manifest.claim.created_assertions.extend(assertions);
+        manifest.claim.ingredients.extend(ingredients);
+    }
+
+    let record = Assertion::CustomAssertion(CustomAssertion {
+        label: SCHEMA_MIGRATION_ASSERTION_LABEL.to_string(),
+        data: serde_json::to_value(SchemaMigration {
+            from_version: 1,
+            to_version: CURRENT_SCHEMA_VERSION,
+            steps: steps.to_vec(),
+            previous_hash,
+            hash_algorithm: hash::algorithm_to_string(hash_alg).to_string(),
+            previous_signer,
+            migrated_at: OffsetDateTime::now_utc(),
+        })?,
+    });
+    manifest.claim.created_assertions.push(record.clone());
+    manifest.claim.signature = None;
+    if let Some(claim_v2) = manifest.claim_v2.as_mut() {
+        claim_v2.created_assertions.push(record);
+    }
+    Ok(())
+}
+
+/// Migrates a stored manifest to the current schema, re-signs it with `key_path` and stores
+/// it again, returning the ID of the migrated manifest, or `None` if it was current.
+///
+/// A signed manifest can only be migrated with a key to re-sign it.
+pub fn upgrade_manifest(
+    manifest_id: &str,
+    storage: &dyn StorageBackend,
+    key_path: Option<PathBuf>,
+    hash_alg: HashAlgorithm,
+) -> Result<Option<(String, Vec<MigrationStep>)>> {
+    let mut manifest = storage.retrieve_manifest(manifest_id)?;
+    let steps = pending_steps(&manifest)?;
+    if steps.is_empty() {
+        return Ok(None);
+    }
+    if manifest.claim.signature.is_some() && key_path.is_none() {
+        return Err(Error::Signing(format!(
+            "Manifest {manifest_id} is signed; a key is required to re-sign it after the migration"
+        )));
+    }
+
+    migrate(&mut manifest, &steps, &hash_alg)?;
+    if let Some(key_path) = key_path {
+        manifest.sign(key_path, hash_alg)?;
+    }
+    let id = storage.store_manifest(&manifest)?;
+    Ok(Some((id, steps)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing::pkey_to_secure;
+    use atlas_c2pa_lib::claim::ClaimV2;
+    use atlas_c2pa_lib::datetime_wrapper::OffsetDateTimeWrapper;
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::nid::Nid;
+    use openssl::pkey::PKey;
+
+    fn manifest() -> Manifest {
+        let claim = ClaimV2 {
+            instance_id: "urn:c2pa:claim".to_string(),
+            ingredients: vec![],
+            created_assertions: vec![],
+            claim_generator_info: "test".to_string(),
+            signature: None,
+            created_at: OffsetDateTimeWrapper(OffsetDateTime::now_utc()),
+        };
+        Manifest {
+            claim_generator: "test".to_string(),
+            title: "Migrated".to_string(),
+            instance_id: "urn:c2pa:manifest".to_string(),
+            ingredients: vec![],
+            claim: claim.clone(),
+            created_at: OffsetDateTimeWrapper(OffsetDateTime::now_utc()),
+            cross_references: vec![],
+            claim_v2: Some(claim),
+            is_active: true,
+        }
+    }
+
+    fn assertion(label: &str) -> Assertion {
+        Assertion::CustomAssertion(CustomAssertion {
+            label: label.to_string(),
+            data: serde_json::json!({}),
+        })
+    }
+
+    #[test]
+    fn test_sync_claim_v2() -> Result<()> {
+        let mut manifest = manifest();
+        assert_eq!(schema_version(&manifest)?, CURRENT_SCHEMA_VERSION);
+
+        // An assertion an earlier version attached to claim_v2 only
+        if let Some(claim_v2) = manifest.claim_v2.as_mut() {
+            claim_v2.created_assertions.push(assertion("only_in_v2"));
+        }
+        let steps = pending_steps(&manifest)?;
+        assert_eq!(steps, vec![MigrationStep::SyncClaimV2]);
+        assert_eq!(schema_version(&manifest)?, 1);
+
+        migrate(&mut manifest, &steps, &HashAlgorithm::Sha256)?;
+        assert!(has_assertion(&manifest, "only_in_v2"));
+        assert!(pending_steps(&manifest)?.is_empty());
+        let migrations = migrations(&manifest)?;
+        assert_eq!(migrations.len(), 1);
+        assert_eq!(migrations[0].steps, steps);
+        assert_eq!(migrations[0].to_version, CURRENT_SCHEMA_VERSION);
+        Ok(())
+    }
+
+    #[test]
+    fn test_signed_without_signer_key() -> Result<()> {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        let key = pkey_to_secure(PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap())?;
+        let mut manifest = manifest();
+        signer::sign_with_key(&mut manifest, &key, &HashAlgorithm::Sha256)?;
+        assert!(pending_steps(&manifest)?.is_empty());
+
+        manifest.claim.created_assertions.clear();
+        manifest.claim.signature = Some("c2lnbmF0dXJl".to_string());
+        assert_eq!(
+            pending_steps(&manifest)?,
+            vec![MigrationStep::RecordSignerKey]
+        );
+        Ok(())
+    }
+}
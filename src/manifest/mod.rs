@@ -1,6 +1,8 @@
 use crate::cc_attestation::mock::MockReport;
+use crate::cli::color;
 use crate::error::{Error, Result};
 use crate::hash;
+use crate::manifest::presentation::ShowOptions;
 use crate::storage::traits::StorageBackend;
 use atlas_c2pa_lib::cose::HashAlgorithm;
 use atlas_c2pa_lib::cross_reference::CrossReference;
@@ -9,14 +11,52 @@ use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::Write;
 use uuid::Uuid;
+pub mod approval;
+pub mod archive;
+pub mod attestation;
+pub mod badge;
+pub mod canonical;
+pub mod claims;
 pub mod common;
 pub mod config;
+pub mod content_id;
+pub mod coverage;
+pub mod credential;
 pub mod dataset;
+pub mod dockerfile;
+pub mod encryption;
 pub mod evaluation;
+pub mod fsck;
+pub mod generators;
+pub mod hooks;
+pub mod ingest;
+pub mod key_usage;
+pub mod legacy;
+pub mod lineage;
+pub mod lint;
+pub mod migration;
 pub mod model;
+pub mod naming;
+pub mod object_store;
+pub mod output;
+pub mod policy;
+pub mod presentation;
+pub mod profile;
+pub mod python_env;
+pub mod redaction;
+pub mod relationship;
+pub mod results;
+pub mod retention;
+pub mod revocation;
+pub mod schema;
 pub mod signer;
 pub mod software;
+pub mod template;
+pub mod timestamp;
+pub mod traversal;
 pub mod utils;
+pub mod validity;
+pub mod verification;
 pub use dataset::create_manifest as create_dataset_manifest;
 pub use dataset::list_dataset_manifests as list_dataset_manifest;
 pub use dataset::verify_dataset_manifest;
@@ -99,18 +139,201 @@ pub fn link_manifests(
         }
     };
 
-    // Detect the hash algorithm used in the source manifest
-    let algorithm = if let Some(first_ingredient) = source_manifest.ingredients.first() {
-        hash::parse_algorithm(first_ingredient.data.alg.as_str())?
-    } else {
-        // If no ingredients, check if source manifest has any cross-references
-        if let Some(first_cross_ref) = source_manifest.cross_references.first() {
-            // Detect algorithm from existing cross-reference hash length
-            hash::detect_hash_algorithm(&first_cross_ref.manifest_hash) // This already returns HashAlgorithm
-        } else {
-            HashAlgorithm::Sha384 // Default if no ingredients or cross-references
+    let algorithm = link_hash_algorithm(&source_manifest)?;
+
+    let outcome = add_cross_reference(
+        &mut source_manifest,
+        target_id,
+        &target_manifest,
+        &algorithm,
+    )?;
+
+    if let LinkOutcome::Unchanged = outcome {
+        println!("Existing cross-reference is identical, no changes needed");
+        return Ok(());
+    }
+
+    // Update the source manifest in storage
+    let updated_id = storage.store_manifest(&source_manifest)?;
+
+    match outcome {
+        LinkOutcome::Versioned(versioned_id) => println!(
+            "Successfully linked manifest {source_id} to {target_id} (versioned as {versioned_id})"
+        ),
+        _ => println!("Successfully linked manifest {source_id} to {target_id}"),
+    }
+    println!("Updated manifest ID: {updated_id}");
+    println!("Using hash algorithm: {}", algorithm.as_str());
+
+    Ok(())
+}
+
+/// Links in a link batch, one source manifest with any number of targets
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkBatchEntry {
+    pub source: String,
+    pub targets: Vec<String>,
+}
+
+/// Specification of a link batch
+///
+/// # Examples
+///
+/// ```
+/// use atlas_cli::manifest::LinkBatchSpec;
+///
+/// let spec: LinkBatchSpec = serde_yaml::from_str(
+///     "links:\n  - source: model-1\n    targets: [dataset-1, dataset-2]\n",
+/// )
+/// .unwrap();
+///
+/// assert_eq!(spec.links[0].targets.len(), 2);
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkBatchSpec {
+    pub links: Vec<LinkBatchEntry>,
+}
+
+impl LinkBatchSpec {
+    /// Loads a link batch specification from a YAML (or JSON) file
+    pub fn from_file(path: &std::path::Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        serde_yaml::from_str(&contents)
+            .map_err(|e| Error::Validation(format!("Invalid link batch specification: {e}")))
+    }
+
+    /// Merges the entries for the same source manifest, keeping the order in which
+    /// sources and targets first appear
+    fn grouped(&self) -> Vec<LinkBatchEntry> {
+        let mut grouped: Vec<LinkBatchEntry> = Vec::new();
+
+        for entry in &self.links {
+            let index = match grouped.iter().position(|g| g.source == entry.source) {
+                Some(index) => index,
+                None => {
+                    grouped.push(LinkBatchEntry {
+                        source: entry.source.clone(),
+                        targets: Vec::new(),
+                    });
+                    grouped.len() - 1
+                }
+            };
+
+            for target in &entry.targets {
+                if !grouped[index].targets.contains(target) {
+                    grouped[index].targets.push(target.clone());
+                }
+            }
         }
-    };
+
+        grouped
+    }
+}
+
+/// Creates all cross-references of a link batch, storing each source manifest once
+/// with all of its new links instead of once per link.
+///
+/// Returns the updated manifest IDs, in the order the sources appear in the batch.
+pub fn link_manifests_batch(
+    spec: &LinkBatchSpec,
+    storage: &(impl StorageBackend + ?Sized),
+) -> Result<Vec<String>> {
+    let mut updated_ids = Vec::new();
+
+    for entry in spec.grouped() {
+        let source_id = entry.source.as_str();
+
+        validate_manifest_id(source_id)?;
+        for target_id in &entry.targets {
+            validate_manifest_id(target_id)?;
+        }
+
+        let mut source_manifest = storage.retrieve_manifest(source_id).map_err(|e| {
+            Error::Manifest(format!(
+                "Failed to retrieve source manifest {source_id}: {e}"
+            ))
+        })?;
+
+        let target_manifests =
+            traversal::fetch_manifests(&entry.targets, storage, traversal::MAX_CONCURRENT_FETCHES);
+
+        let algorithm = link_hash_algorithm(&source_manifest)?;
+        let mut linked = 0;
+
+        for (target_id, target_manifest) in entry.targets.iter().zip(target_manifests) {
+            let target_manifest = target_manifest.map_err(|e| {
+                Error::Manifest(format!(
+                    "Failed to retrieve target manifest {target_id}: {e}"
+                ))
+            })?;
+
+            match add_cross_reference(
+                &mut source_manifest,
+                target_id,
+                &target_manifest,
+                &algorithm,
+            )? {
+                LinkOutcome::Unchanged => {
+                    println!("Existing cross-reference to {target_id} is identical, skipping")
+                }
+                LinkOutcome::Linked => linked += 1,
+                LinkOutcome::Versioned(versioned_id) => {
+                    println!("Linking {target_id} as {versioned_id}");
+                    linked += 1;
+                }
+            }
+        }
+
+        if linked == 0 {
+            println!("No new links for manifest {source_id}");
+            continue;
+        }
+
+        // Store the source manifest once with all of its new links
+        let updated_id = storage.store_manifest(&source_manifest)?;
+        println!("Successfully linked manifest {source_id} to {linked} target(s)");
+        println!("Updated manifest ID: {updated_id}");
+        updated_ids.push(updated_id);
+    }
+
+    Ok(updated_ids)
+}
+
+/// Result of adding a cross-reference to a source manifest
+enum LinkOutcome {
+    /// A new cross-reference was added
+    Linked,
+    /// The target changed since it was linked, a versioned cross-reference was added
+    Versioned(String),
+    /// An identical cross-reference already exists
+    Unchanged,
+}
+
+// Detect the hash algorithm used in the source manifest
+fn link_hash_algorithm(
+    source_manifest: &atlas_c2pa_lib::manifest::Manifest,
+) -> Result<HashAlgorithm> {
+    if let Some(first_ingredient) = source_manifest.ingredients.first() {
+        hash::parse_algorithm(first_ingredient.data.alg.as_str())
+    } else if let Some(first_cross_ref) = source_manifest.cross_references.first() {
+        // Detect algorithm from existing cross-reference hash length
+        Ok(hash::detect_hash_algorithm(&first_cross_ref.manifest_hash))
+    } else {
+        Ok(HashAlgorithm::Sha384) // Default if no ingredients or cross-references
+    }
+}
+
+// Adds a cross-reference to the target to the source manifest, without storing it
+fn add_cross_reference(
+    source_manifest: &mut atlas_c2pa_lib::manifest::Manifest,
+    target_id: &str,
+    target_manifest: &atlas_c2pa_lib::manifest::Manifest,
+    algorithm: &HashAlgorithm,
+) -> Result<LinkOutcome> {
+    // Create a hash of the target manifest using the detected algorithm
+    let target_json =
+        serde_json::to_string(target_manifest).map_err(|e| Error::Serialization(e.to_string()))?;
+    let target_hash = hash::calculate_hash_with_algorithm(target_json.as_bytes(), algorithm);
 
     // Check if a cross-reference to this target already exists
     let duplicate_ref = source_manifest
@@ -122,60 +345,39 @@ pub fn link_manifests(
         println!("Warning: A cross-reference to {target_id} already exists");
 
         // Check if hash matches (if it doesn't, this could indicate a conflict)
-        let target_json = serde_json::to_string(&target_manifest)
-            .map_err(|e| Error::Serialization(e.to_string()))?;
-        let target_hash = hash::calculate_hash_with_algorithm(target_json.as_bytes(), &algorithm);
-
-        if existing_ref.manifest_hash != target_hash {
-            // Handle conflict by creating a versioned reference
-            println!("Manifest hash conflict detected, creating versioned reference");
-            return create_versioned_link(
-                source_manifest,
-                target_manifest,
-                source_id,
-                target_id,
-                storage,
-                &algorithm,
-            );
-        } else {
-            println!("Existing cross-reference is identical, no changes needed");
-            return Ok(());
+        if existing_ref.manifest_hash == target_hash {
+            return Ok(LinkOutcome::Unchanged);
         }
-    }
 
-    // Create a hash of the target manifest using the detected algorithm
-    let target_json =
-        serde_json::to_string(&target_manifest).map_err(|e| Error::Serialization(e.to_string()))?;
-    let target_hash = hash::calculate_hash_with_algorithm(target_json.as_bytes(), &algorithm);
+        // Handle conflict by creating a versioned reference
+        println!("Manifest hash conflict detected, creating versioned reference");
+        let versioned_id = versioned_link_id(source_manifest, target_id, target_manifest);
+
+        // Create a cross-reference with the versioned ID
+        source_manifest
+            .cross_references
+            .push(CrossReference::new(versioned_id.clone(), target_hash));
+
+        return Ok(LinkOutcome::Versioned(versioned_id));
+    }
 
     // Convert IDs to proper C2PA URNs if they're not already
     let target_urn = ensure_c2pa_urn(target_id);
 
     // Create a cross-reference from source to target
-    let cross_reference = CrossReference::new(target_urn, target_hash);
-
-    // Add the cross-reference to the source manifest
-    source_manifest.cross_references.push(cross_reference);
-
-    // Update the source manifest in storage
-    let updated_id = storage.store_manifest(&source_manifest)?;
-
-    println!("Successfully linked manifest {source_id} to {target_id}");
-    println!("Updated manifest ID: {updated_id}");
-    println!("Using hash algorithm: {}", algorithm.as_str());
+    source_manifest
+        .cross_references
+        .push(CrossReference::new(target_urn, target_hash));
 
-    Ok(())
+    Ok(LinkOutcome::Linked)
 }
 
-// Create a versioned link when there's a conflict
-fn create_versioned_link(
-    mut source_manifest: atlas_c2pa_lib::manifest::Manifest,
-    target_manifest: atlas_c2pa_lib::manifest::Manifest,
-    source_id: &str,
+// Generate a versioned ID for a link when there's a conflict
+fn versioned_link_id(
+    source_manifest: &atlas_c2pa_lib::manifest::Manifest,
     target_id: &str,
-    storage: &(impl StorageBackend + ?Sized),
-    algorithm: &HashAlgorithm,
-) -> Result<()> {
+    target_manifest: &atlas_c2pa_lib::manifest::Manifest,
+) -> String {
     // Generate a versioned ID following C2PA spec section 8.2
     // Format: original_urn:claim_generator:version_reason
     // where version_reason is version_number_reason_code
@@ -213,203 +415,358 @@ fn create_versioned_link(
 
     // Create new versioned ID
     // Reason code 1 = Conflict with another C2PA Manifest
-    let versioned_id = format!(
+    format!(
         "urn:c2pa:{}:{}:{}_{}",
         uuid_part,
         claim_generator,
         max_version + 1,
         1
-    );
-
-    // Create a hash of the target manifest using the specified algorithm
-    let target_json =
-        serde_json::to_string(&target_manifest).map_err(|e| Error::Serialization(e.to_string()))?;
-    let target_hash = hash::calculate_hash_with_algorithm(target_json.as_bytes(), algorithm);
-
-    // Create a cross-reference with the versioned ID
-    let cross_reference = CrossReference::new(versioned_id.clone(), target_hash);
-
-    // Add the cross-reference to the source manifest
-    source_manifest.cross_references.push(cross_reference);
-
-    // Update the source manifest in storage
-    let updated_id = storage.store_manifest(&source_manifest)?;
-
-    println!(
-        "Successfully linked manifest {source_id} to {target_id} (versioned as {versioned_id})"
-    );
-    println!("Updated manifest ID: {updated_id}");
-    println!("Using hash algorithm: {}", algorithm.as_str());
-
-    Ok(())
+    )
 }
 
 pub fn show_manifest(id: &str, storage: &(impl StorageBackend + ?Sized)) -> Result<()> {
-    let manifest = storage.retrieve_manifest(id)?;
-
-    println!("============ Manifest Details ============");
-    println!("ID: {}", manifest.instance_id);
-    println!("Title: {}", manifest.title);
-    println!("Created: {}", manifest.created_at.0);
-    println!("Claim Generator: {}", manifest.claim_generator);
-    println!("Active: {}", manifest.is_active);
-
-    // Display claim details
-    println!("\n------------ Claim Details -------------");
-    println!("Claim ID: {}", manifest.claim.instance_id);
-    println!("Claim Generated: {}", manifest.claim.created_at.0);
-    println!("Claim Generator: {}", manifest.claim.claim_generator_info);
-
-    if let Some(signature) = &manifest.claim.signature {
-        println!("\nSignature: {signature}");
-    } else {
-        println!("\nSignature: None (unsigned)");
-    }
-
-    // Display assertions
-    println!("\n------------ Assertions -------------");
-    for (i, assertion) in manifest.claim.created_assertions.iter().enumerate() {
-        println!("\nAssertion #{}", i + 1);
-        match assertion {
-            atlas_c2pa_lib::assertion::Assertion::CreativeWork(creative) => {
-                println!("  Type: CreativeWork");
-                println!("  Context: {}", creative.context);
-                println!("  Creative Type: {}", creative.creative_type);
-
-                println!("  Authors:");
-                for author in &creative.author {
-                    println!("    - {} ({})", author.name, author.author_type);
-                }
-            }
-            atlas_c2pa_lib::assertion::Assertion::Action(action) => {
-                println!("  Type: Action");
-                println!("  Actions:");
-                for action in &action.actions {
-                    println!("    - Action: {}", action.action);
-                    if let Some(agent) = &action.software_agent {
-                        println!("      Software Agent: {agent}");
-                    }
-                    if let Some(source_type) = &action.digital_source_type {
-                        println!("      Digital Source Type: {source_type}");
-                    }
-                    if let Some(params) = &action.parameters {
-                        println!(
-                            "      Parameters: {}",
-                            serde_json::to_string_pretty(params)
-                                .unwrap_or_else(|_| format!("{params:?}"))
-                        );
-                    }
-                }
-            }
-            _ => println!("  Unknown assertion type"),
-        }
-    }
-
-    // Display ingredients
-    println!("\n------------ Ingredients -------------");
-    for (i, ingredient) in manifest.ingredients.iter().enumerate() {
-        println!("\nIngredient #{}: {}", i + 1, ingredient.title);
-        println!("  Document ID: {}", ingredient.document_id);
-        println!("  Instance ID: {}", ingredient.instance_id);
-        println!("  Format: {}", ingredient.format);
-        println!("  Relationship: {}", ingredient.relationship);
-
-        println!("  Data:");
-        println!("    URL: {}", ingredient.data.url);
-        println!("    Hash Algorithm: {}", ingredient.data.alg);
-        println!("    Hash: {}", ingredient.data.hash);
-
-        println!("    Data Types:");
-        for data_type in &ingredient.data.data_types {
-            println!("      - {data_type:?}");
-        }
-
-        if let Some(linked) = &ingredient.linked_ingredient {
-            println!("  Linked Ingredient: {linked:?}");
-        }
-
-        if let Some(key) = &ingredient.public_key {
-            println!("  Public Key: {key:?}");
-        }
-    }
+    show_manifest_with_options(id, storage, &ShowOptions::default(), &[])
+}
 
-    // Display cross-references if any
-    if !manifest.cross_references.is_empty() {
-        println!("\n------------ Cross References -------------");
-        for (i, cross_ref) in manifest.cross_references.iter().enumerate() {
-            println!("\nReference #{}", i + 1);
-            println!("  URL: {}", cross_ref.manifest_url);
-            println!("  Hash: {}", cross_ref.manifest_hash);
-        }
+/// Shows a manifest in the format given by `options`, or only the selected fields of it.
+///
+/// Encrypted assertion payloads are shown decrypted if one of `identities` can decrypt
+/// them, see [`encryption`].
+pub fn show_manifest_with_options(
+    id: &str,
+    storage: &(impl StorageBackend + ?Sized),
+    options: &ShowOptions,
+    identities: &[encryption::Identity],
+) -> Result<()> {
+    let mut manifest = storage.retrieve_manifest(id)?;
+    if !identities.is_empty() {
+        encryption::decrypt_manifest(&mut manifest, identities)?;
     }
-
+    print!("{}", presentation::render_manifest(&manifest, options)?);
     Ok(())
 }
 
 pub mod linking {
+    use super::dataset::DatasetSplit;
+    use super::relationship::IngredientRelationship;
+    use super::{LinkOutcome, add_cross_reference, claims, traversal};
     use crate::error::{Error, Result};
+    use crate::hash;
     use crate::storage::traits::StorageBackend;
-    use atlas_c2pa_lib::ingredient::{Ingredient, LinkedIngredient};
+    use atlas_c2pa_lib::asset_type::AssetType;
+    use atlas_c2pa_lib::cose::HashAlgorithm;
+    use atlas_c2pa_lib::ingredient::{Ingredient, IngredientData, LinkedIngredient};
     use atlas_c2pa_lib::manifest::Manifest;
+    use uuid::Uuid;
+
+    /// Relationship of the ingredients that link a model to its training datasets
+    pub const DATASET_LINK_RELATIONSHIP: &str = IngredientRelationship::InputTo.as_str();
 
-    /// Links a dataset ingredient to a model ingredient
+    /// Relationship of the ingredients that link a model to the software it was trained with
+    pub const SOFTWARE_LINK_RELATIONSHIP: &str = IngredientRelationship::InputTo.as_str();
+
+    /// Links a dataset manifest to a model manifest
     pub fn link_dataset_to_model(
         model_manifest_id: &str,
         dataset_manifest_id: &str,
         storage: &dyn StorageBackend,
     ) -> Result<Manifest> {
-        // Retrieve both manifests
+        link_datasets_to_model(
+            model_manifest_id,
+            &[dataset_manifest_id.to_string()],
+            storage,
+        )
+    }
+
+    /// Links any number of dataset manifests to a model manifest, storing the model
+    /// manifest once with all of the links
+    pub fn link_datasets_to_model(
+        model_manifest_id: &str,
+        dataset_manifest_ids: &[String],
+        storage: &dyn StorageBackend,
+    ) -> Result<Manifest> {
         let mut model_manifest = storage.retrieve_manifest(model_manifest_id)?;
-        let dataset_manifest = storage.retrieve_manifest(dataset_manifest_id)?;
+        let datasets = fetch_datasets(dataset_manifest_ids, storage)?;
+
+        let algorithm = super::link_hash_algorithm(&model_manifest)?;
+        add_dataset_links(&mut model_manifest, &datasets, &algorithm)?;
 
-        // Verify the dataset manifest type
-        if !is_dataset_manifest(&dataset_manifest) {
+        // Store updated model manifest
+        storage.store_manifest(&model_manifest)?;
+
+        Ok(model_manifest)
+    }
+
+    /// Links a software manifest to a model manifest as an input to its training: an
+    /// `inputTo` ingredient whose linked ingredient is the software manifest, and a
+    /// cross-reference to it
+    pub fn link_software_to_model(
+        model_manifest_id: &str,
+        software_manifest_id: &str,
+        storage: &dyn StorageBackend,
+    ) -> Result<Manifest> {
+        super::validate_manifest_id(model_manifest_id)?;
+        super::validate_manifest_id(software_manifest_id)?;
+
+        let mut model_manifest = storage.retrieve_manifest(model_manifest_id)?;
+        let software_manifest = storage
+            .retrieve_manifest(software_manifest_id)
+            .map_err(|e| {
+                Error::Manifest(format!(
+                    "Failed to retrieve software manifest {software_manifest_id}: {e}"
+                ))
+            })?;
+        if !own_ingredients(&software_manifest).any(is_software_ingredient) {
             return Err(Error::Validation(format!(
-                "Manifest {dataset_manifest_id} is not a dataset manifest"
+                "Manifest {software_manifest_id} is not a software manifest"
             )));
         }
 
-        // Get all dataset ingredients
-        let dataset_ingredients = dataset_manifest.ingredients;
-
-        // Update each model ingredient with dataset links
-        for model_ingredient in &mut model_manifest.ingredients {
-            for dataset_ingredient in &dataset_ingredients {
-                // Create linked ingredient
-                let linked_ingredient = create_linked_ingredient(dataset_ingredient)?;
-                model_ingredient.data.linked_ingredient_url =
-                    Some(dataset_ingredient.data.url.clone());
-                model_ingredient.data.linked_ingredient_hash =
-                    Some(dataset_ingredient.data.hash.clone());
-                model_ingredient.linked_ingredient = Some(linked_ingredient);
+        let algorithm = super::link_hash_algorithm(&model_manifest)?;
+        let ingredient = manifest_link_ingredient(
+            software_manifest_id,
+            &software_manifest,
+            SOFTWARE_LINK_RELATIONSHIP,
+            is_software_type,
+            AssetType::Generator,
+            &algorithm,
+        )?;
+        add_link_ingredient(&mut model_manifest, ingredient, is_software_link);
+
+        match add_cross_reference(
+            &mut model_manifest,
+            software_manifest_id,
+            &software_manifest,
+            &algorithm,
+        )? {
+            LinkOutcome::Unchanged => {
+                println!("Software {software_manifest_id} is already linked")
+            }
+            LinkOutcome::Linked | LinkOutcome::Versioned(_) => {
+                println!("Linked software {software_manifest_id}")
             }
         }
 
-        // Store updated model manifest
         storage.store_manifest(&model_manifest)?;
 
         Ok(model_manifest)
     }
 
+    /// Retrieves dataset manifests, failing if any of them is not a dataset manifest
+    pub fn fetch_datasets(
+        dataset_manifest_ids: &[String],
+        storage: &dyn StorageBackend,
+    ) -> Result<Vec<(String, Manifest)>> {
+        let manifests = traversal::fetch_manifests(
+            dataset_manifest_ids,
+            storage,
+            traversal::MAX_CONCURRENT_FETCHES,
+        );
+
+        dataset_manifest_ids
+            .iter()
+            .zip(manifests)
+            .map(|(id, manifest)| {
+                let manifest = manifest.map_err(|e| {
+                    Error::Manifest(format!("Failed to retrieve dataset manifest {id}: {e}"))
+                })?;
+                if !is_dataset_manifest(&manifest) {
+                    return Err(Error::Validation(format!(
+                        "Manifest {id} is not a dataset manifest"
+                    )));
+                }
+                Ok((id.clone(), manifest))
+            })
+            .collect()
+    }
+
+    /// Adds a dataset-typed link for each dataset to a model manifest: an ingredient whose
+    /// linked ingredient is the dataset manifest, and a cross-reference to it.
+    ///
+    /// While the claim is unsigned the link ingredients are added to the claim, so that
+    /// signing covers them. Signed claims cannot change, so the links are then added to
+    /// the manifest ingredients. Returns the number of new or changed links.
+    pub fn add_dataset_links(
+        model_manifest: &mut Manifest,
+        datasets: &[(String, Manifest)],
+        algorithm: &HashAlgorithm,
+    ) -> Result<usize> {
+        let mut linked = 0;
+
+        for (dataset_id, dataset_manifest) in datasets {
+            let ingredient = dataset_link_ingredient(
+                dataset_id,
+                dataset_manifest,
+                DATASET_LINK_RELATIONSHIP,
+                algorithm,
+            )?;
+            add_link_ingredient(model_manifest, ingredient, is_dataset_link);
+
+            match add_cross_reference(model_manifest, dataset_id, dataset_manifest, algorithm)? {
+                LinkOutcome::Unchanged => {
+                    println!("Dataset {dataset_id} is already linked, skipping")
+                }
+                LinkOutcome::Linked | LinkOutcome::Versioned(_) => {
+                    println!("Linked dataset {dataset_id}");
+                    linked += 1;
+                }
+            }
+        }
+
+        Ok(linked)
+    }
+
+    /// Adds a link ingredient to the claim while it is unsigned, or else to the manifest
+    /// ingredients, replacing a previous link of the same kind to the same manifest
+    fn add_link_ingredient(
+        manifest: &mut Manifest,
+        ingredient: Ingredient,
+        is_same_kind: fn(&Ingredient) -> bool,
+    ) {
+        let in_claim = manifest.claim.signature.is_none();
+        let mut ingredient_lists = vec![];
+        if in_claim {
+            ingredient_lists.push(&mut manifest.claim.ingredients);
+            if let Some(claim_v2) = manifest.claim_v2.as_mut() {
+                ingredient_lists.push(&mut claim_v2.ingredients);
+            }
+        } else {
+            ingredient_lists.push(&mut manifest.ingredients);
+        }
+
+        for ingredients in ingredient_lists {
+            ingredients.retain(|i| !(is_same_kind(i) && i.data.url == ingredient.data.url));
+            ingredients.push(ingredient.clone());
+        }
+    }
+
+    /// Checks if an ingredient links to another manifest rather than hashing a file: a
+    /// training dataset or training software of a model, or the dataset a split was taken
+    /// from
+    pub fn is_manifest_link(ingredient: &Ingredient) -> bool {
+        (ingredient.relationship == DATASET_LINK_RELATIONSHIP
+            || ingredient.relationship == SOFTWARE_LINK_RELATIONSHIP
+            || DatasetSplit::from_relationship(&ingredient.relationship).is_some())
+            && ingredient.linked_ingredient.is_some()
+    }
+
+    /// Checks if an ingredient is a link to a dataset manifest: a training dataset of a
+    /// model, or the dataset a split was taken from
+    pub fn is_dataset_link(ingredient: &Ingredient) -> bool {
+        is_manifest_link(ingredient)
+            && ingredient
+                .data
+                .data_types
+                .first()
+                .is_none_or(is_dataset_type)
+    }
+
+    /// Checks if an ingredient is a link to the software manifest a model was trained with
+    pub fn is_software_link(ingredient: &Ingredient) -> bool {
+        ingredient.relationship == SOFTWARE_LINK_RELATIONSHIP
+            && is_manifest_link(ingredient)
+            && ingredient
+                .data
+                .data_types
+                .first()
+                .is_some_and(is_software_type)
+    }
+
+    /// The ingredients of a manifest other than its links to other manifests
+    fn own_ingredients(manifest: &Manifest) -> impl Iterator<Item = &Ingredient> {
+        manifest
+            .ingredients
+            .iter()
+            .chain(&claims::active_claim(manifest).ingredients)
+            .filter(|i| !is_manifest_link(i))
+    }
+
     /// Checks if a manifest is a dataset manifest
     fn is_dataset_manifest(manifest: &Manifest) -> bool {
-        manifest.ingredients.iter().any(|i| {
-            matches!(
-                i.data.data_types[0],
-                atlas_c2pa_lib::asset_type::AssetType::Dataset
-                    | atlas_c2pa_lib::asset_type::AssetType::DatasetOnnx
-                    | atlas_c2pa_lib::asset_type::AssetType::DatasetTensorFlow
-                    | atlas_c2pa_lib::asset_type::AssetType::DatasetPytorch
-            )
-        })
+        own_ingredients(manifest).any(|i| i.data.data_types.first().is_some_and(is_dataset_type))
+    }
+
+    fn is_software_ingredient(ingredient: &Ingredient) -> bool {
+        ingredient
+            .data
+            .data_types
+            .first()
+            .is_some_and(is_software_type)
+    }
+
+    fn is_dataset_type(asset_type: &AssetType) -> bool {
+        matches!(
+            asset_type,
+            AssetType::Dataset
+                | AssetType::DatasetOnnx
+                | AssetType::DatasetTensorFlow
+                | AssetType::DatasetPytorch
+        )
     }
 
-    /// Creates linked ingredient from a dataset ingredient
-    fn create_linked_ingredient(dataset_ingredient: &Ingredient) -> Result<LinkedIngredient> {
-        Ok(LinkedIngredient {
-            url: dataset_ingredient.data.url.clone(),
-            hash: dataset_ingredient.data.hash.clone(),
-            media_type: dataset_ingredient.format.clone(),
+    fn is_software_type(asset_type: &AssetType) -> bool {
+        matches!(asset_type, AssetType::Generator)
+    }
+
+    /// Creates the ingredient linking to a dataset manifest with the given relationship
+    pub(crate) fn dataset_link_ingredient(
+        dataset_id: &str,
+        dataset_manifest: &Manifest,
+        relationship: &str,
+        algorithm: &HashAlgorithm,
+    ) -> Result<Ingredient> {
+        manifest_link_ingredient(
+            dataset_id,
+            dataset_manifest,
+            relationship,
+            is_dataset_type,
+            AssetType::Dataset,
+            algorithm,
+        )
+    }
+
+    /// Creates the ingredient linking to a manifest with the given relationship, typed
+    /// after the first of its ingredients of an accepted type
+    fn manifest_link_ingredient(
+        linked_id: &str,
+        linked_manifest: &Manifest,
+        relationship: &str,
+        accepts_type: fn(&AssetType) -> bool,
+        default_type: AssetType,
+        algorithm: &HashAlgorithm,
+    ) -> Result<Ingredient> {
+        let linked_json = serde_json::to_string(linked_manifest)?;
+        let linked_hash = hash::calculate_hash_with_algorithm(linked_json.as_bytes(), algorithm);
+
+        let data_type = linked_manifest
+            .ingredients
+            .iter()
+            .chain(&claims::active_claim(linked_manifest).ingredients)
+            .filter_map(|i| i.data.data_types.first())
+            .find(|t| accepts_type(t))
+            .cloned()
+            .unwrap_or(default_type);
+
+        Ok(Ingredient {
+            title: linked_manifest.title.clone(),
+            format: "application/json".to_string(),
+            relationship: relationship.to_string(),
+            document_id: format!("uuid:{}", Uuid::new_v4()),
+            instance_id: format!("uuid:{}", Uuid::new_v4()),
+            data: IngredientData {
+                url: linked_id.to_string(),
+                alg: algorithm.as_str().to_string(),
+                hash: linked_hash.clone(),
+                data_types: vec![data_type],
+                linked_ingredient_url: Some(linked_id.to_string()),
+                linked_ingredient_hash: Some(linked_hash.clone()),
+            },
+            linked_ingredient: Some(LinkedIngredient {
+                url: linked_id.to_string(),
+                hash: linked_hash,
+                media_type: "application/json".to_string(),
+            }),
+            public_key: None,
         })
     }
 }
@@ -431,67 +788,28 @@ pub fn validate_linked_manifests(
 
     let mut validation_errors = Vec::new();
 
-    for (index, cross_ref) in manifest.cross_references.iter().enumerate() {
+    // Retrieve and verify the referenced manifests concurrently, then report in order
+    let checks = traversal::map_concurrent(
+        &manifest.cross_references,
+        traversal::MAX_CONCURRENT_FETCHES,
+        |cross_ref| check_cross_reference(cross_ref, storage),
+    );
+
+    for (index, (cross_ref, results)) in manifest.cross_references.iter().zip(checks).enumerate() {
         println!(
             "\nValidating cross-reference #{}: {}",
             index + 1,
             cross_ref.manifest_url
         );
 
-        // Validate the hash format first
-        if let Err(hash_err) = validate_hash_format(&cross_ref.manifest_hash) {
-            let error = format!("Invalid hash format: {hash_err}");
-            validation_errors.push(error.clone());
-            println!("  ❌ {error}");
-            continue;
-        }
-
-        // Try to retrieve the referenced manifest
-        match storage.retrieve_manifest(&cross_ref.manifest_url) {
-            Ok(referenced_manifest) => {
-                // Calculate hash of the referenced manifest
-                let ref_json = match serde_json::to_string(&referenced_manifest) {
-                    Ok(json) => json,
-                    Err(e) => {
-                        let error = format!("Failed to serialize referenced manifest: {e}");
-                        validation_errors.push(error.clone());
-                        println!("  ❌ {error}");
-                        continue;
-                    }
-                };
-
-                let algorithm = hash::detect_hash_algorithm(&cross_ref.manifest_hash);
-
-                let calculated_hash =
-                    hash::calculate_hash_with_algorithm(ref_json.as_bytes(), &algorithm);
-
-                // Compare calculated hash with stored hash
-                if calculated_hash == cross_ref.manifest_hash {
-                    println!("  ✓ Hash verification successful");
-                } else {
-                    let error = format!(
-                        "Hash mismatch for manifest {}: stored={}, calculated={}",
-                        cross_ref.manifest_url, cross_ref.manifest_hash, calculated_hash
-                    );
-                    validation_errors.push(error.clone());
-                    println!("  ❌ {error}");
-                }
-
-                // Check manifest structure
-                match atlas_c2pa_lib::manifest::validate_manifest(&referenced_manifest) {
-                    Ok(_) => println!("  ✓ Manifest structure validation successful"),
-                    Err(e) => {
-                        let error = format!("Manifest structure validation failed: {e}");
-                        validation_errors.push(error.clone());
-                        println!("  ❌ {error}");
-                    }
+        for result in results {
+            match result {
+                Ok(message) => println!("  {} {message}", color::check_mark()),
+                Err(error) => {
+                    println!("  {} {error}", color::cross_mark());
+                    validation_errors.push(error);
                 }
             }
-            Err(e) => {
-                let error = format!("Failed to retrieve referenced manifest: {e}");
-                validation_errors.push(error.clone());
-                println!("  ❌ {error}");
-            }
         }
     }
 
@@ -513,6 +831,64 @@ pub fn validate_linked_manifests(
     }
 }
 
+/// Runs the checks for a single cross-reference, returning the success or error message
+/// of each check
+fn check_cross_reference(
+    cross_ref: &CrossReference,
+    storage: &(impl StorageBackend + ?Sized),
+) -> Vec<std::result::Result<String, String>> {
+    // Validate the hash format first
+    if let Err(hash_err) = validate_hash_format(&cross_ref.manifest_hash) {
+        return vec![Err(format!("Invalid hash format: {hash_err}"))];
+    }
+
+    if attestation::is_envelope_reference(cross_ref) {
+        return vec![
+            attestation::verify_envelope_reference(cross_ref, storage)
+                .map(|record| format!("Hash verification successful for {} envelope", record.kind))
+                .map_err(|e| e.to_string()),
+        ];
+    }
+
+    // Try to retrieve the referenced manifest
+    let referenced_manifest = match storage.retrieve_manifest(&cross_ref.manifest_url) {
+        Ok(manifest) => manifest,
+        Err(e) => return vec![Err(format!("Failed to retrieve referenced manifest: {e}"))],
+    };
+
+    let mut results = Vec::new();
+
+    // Calculate hash of the referenced manifest
+    let ref_json = match serde_json::to_string(&referenced_manifest) {
+        Ok(json) => json,
+        Err(e) => {
+            return vec![Err(format!("Failed to serialize referenced manifest: {e}"))];
+        }
+    };
+
+    let algorithm = hash::detect_hash_algorithm(&cross_ref.manifest_hash);
+
+    let calculated_hash = hash::calculate_hash_with_algorithm(ref_json.as_bytes(), &algorithm);
+
+    // Compare calculated hash with stored hash
+    if calculated_hash == cross_ref.manifest_hash {
+        results.push(Ok("Hash verification successful".to_string()));
+    } else {
+        results.push(Err(format!(
+            "Hash mismatch for manifest {}: stored={}, calculated={}",
+            cross_ref.manifest_url, cross_ref.manifest_hash, calculated_hash
+        )));
+    }
+
+    // Check manifest structure
+    match atlas_c2pa_lib::manifest::validate_manifest(&referenced_manifest) {
+        Ok(_) => results.push(Ok("Manifest structure validation successful".to_string())),
+        Err(e) => results.push(Err(format!("Manifest structure validation failed: {e}"))),
+    }
+
+    results
+}
+
 /// Check whether a given hash (hex-encoded) length matches one of the
 /// C2PA-supported algorithms (must be one of SHA-256, SHA-384, SHA-512).
 fn is_supported_c2pa_hash_length(hash_len: usize) -> bool {
@@ -583,6 +959,11 @@ pub fn verify_manifest_link(
 ///
 /// // Valid: alphanumeric ID
 /// assert!(validate_manifest_id("model-123").is_ok());
+///
+/// // Valid: content-addressed ID
+/// let digest = "ab".repeat(32);
+/// assert!(validate_manifest_id(&format!("urn:c2pa:sha256-{digest}")).is_ok());
+/// assert!(validate_manifest_id("urn:c2pa:sha256-abc").is_err());
 /// ```
 pub fn validate_manifest_id(id: &str) -> Result<()> {
     // Basic validation
@@ -602,10 +983,10 @@ pub fn validate_manifest_id(id: &str) -> Result<()> {
             ));
         }
 
-        // Validate UUID part
-        if Uuid::parse_str(parts[2]).is_err() {
+        // Validate UUID part, or the digest of a content-addressed ID
+        if Uuid::parse_str(parts[2]).is_err() && !content_id::is_content_id(parts[2]) {
             return Err(Error::Validation(format!(
-                "Invalid UUID in C2PA URN: '{}'",
+                "Invalid UUID or content address in C2PA URN: '{}'",
                 parts[2]
             )));
         }
@@ -638,7 +1019,7 @@ pub fn validate_manifest_id(id: &str) -> Result<()> {
         }
     } else {
         // If not a URN, try to validate as UUID or other format
-        if Uuid::parse_str(id).is_ok() {
+        if Uuid::parse_str(id).is_ok() || content_id::is_content_id(id) {
             // Valid UUID, which is good
         } else if !id
             .chars()
@@ -671,6 +1052,10 @@ pub fn validate_manifest_id(id: &str) -> Result<()> {
 /// let existing_urn = "urn:c2pa:12345678-1234-1234-1234-123456789012";
 /// assert_eq!(ensure_c2pa_urn(existing_urn), existing_urn);
 ///
+/// // Content-addressed IDs keep their digest
+/// let digest = format!("sha256-{}", "ab".repeat(32));
+/// assert_eq!(ensure_c2pa_urn(&digest), format!("urn:c2pa:{digest}"));
+///
 /// // Non-UUID gets new UUID generated
 /// let result = ensure_c2pa_urn("custom-id");
 /// assert!(result.starts_with("urn:c2pa:"));
@@ -678,8 +1063,8 @@ pub fn validate_manifest_id(id: &str) -> Result<()> {
 pub fn ensure_c2pa_urn(id: &str) -> String {
     if id.starts_with("urn:c2pa:") {
         id.to_string() // Already in correct format
-    } else if Uuid::parse_str(id).is_ok() {
-        // It's a valid UUID, convert to URN
+    } else if Uuid::parse_str(id).is_ok() || content_id::is_content_id(id) {
+        // It's a valid UUID or content address, convert to URN
         format!("urn:c2pa:{id}")
     } else {
         // Not a UUID, generate a new one
@@ -788,8 +1173,24 @@ pub fn export_provenance(
     // Keep track of visited manifests to avoid cycles
     let mut visited = HashSet::new();
 
+    // Fetch the reachable manifests concurrently, then build the graph from them in order
+    let mut prefetched = traversal::prefetch_linked_manifests(
+        id,
+        storage,
+        max_depth,
+        traversal::MAX_CONCURRENT_FETCHES,
+    );
+
     // Build the graph recursively starting from the root manifest
-    build_provenance_graph(id, storage, &mut graph, &mut visited, max_depth, 0)?;
+    build_provenance_graph(
+        id,
+        storage,
+        &mut prefetched,
+        &mut graph,
+        &mut visited,
+        max_depth,
+        0,
+    )?;
 
     // Serialize the graph based on the requested format
     let serialized = match format.to_lowercase().as_str() {
@@ -831,6 +1232,7 @@ pub fn export_provenance(
 fn build_provenance_graph(
     id: &str,
     storage: &(impl StorageBackend + ?Sized),
+    prefetched: &mut HashMap<String, Result<atlas_c2pa_lib::manifest::Manifest>>,
     graph: &mut ProvenanceGraph,
     visited: &mut HashSet<String>,
     max_depth: u32,
@@ -844,8 +1246,11 @@ fn build_provenance_graph(
     // Mark as visited
     visited.insert(id.to_string());
 
-    // Retrieve the manifest
-    let manifest = match storage.retrieve_manifest(id) {
+    // Retrieve the manifest, unless it has been fetched already
+    let manifest = match prefetched
+        .remove(id)
+        .unwrap_or_else(|| storage.retrieve_manifest(id))
+    {
         Ok(manifest) => manifest,
         Err(e) => {
             return Err(Error::Manifest(format!(
@@ -859,21 +1264,19 @@ fn build_provenance_graph(
 
     // Extract assertions
     let mut assertions = Vec::new();
-    if let Some(claim) = &manifest.claim_v2 {
-        for assertion in &claim.created_assertions {
-            let details = extract_assertion_details(assertion);
-            let type_name = match assertion {
-                atlas_c2pa_lib::assertion::Assertion::CreativeWork(_) => "CreativeWork",
-                atlas_c2pa_lib::assertion::Assertion::Action(_) => "Action",
-                atlas_c2pa_lib::assertion::Assertion::DoNotTrain(_) => "DoNotTrain",
-                atlas_c2pa_lib::assertion::Assertion::CustomAssertion(_) => "TrustedHardware",
-                _ => "Other",
-            };
-            assertions.push(AssertionInfo {
-                type_name: type_name.to_string(),
-                details,
-            });
-        }
+    for assertion in claims::assertions(&manifest) {
+        let details = extract_assertion_details(assertion);
+        let type_name = match assertion {
+            atlas_c2pa_lib::assertion::Assertion::CreativeWork(_) => "CreativeWork",
+            atlas_c2pa_lib::assertion::Assertion::Action(_) => "Action",
+            atlas_c2pa_lib::assertion::Assertion::DoNotTrain(_) => "DoNotTrain",
+            atlas_c2pa_lib::assertion::Assertion::CustomAssertion(_) => "TrustedHardware",
+            _ => "Other",
+        };
+        assertions.push(AssertionInfo {
+            type_name: type_name.to_string(),
+            details,
+        });
     }
 
     // Extract ingredient IDs
@@ -888,11 +1291,11 @@ fn build_provenance_graph(
         id: id.to_string(),
         title: manifest.title.clone(),
         manifest_type: manifest_type_to_string(&manifest_type),
-        created_at: manifest.created_at.0.to_string(),
+        created_at: timestamp::to_rfc3339_utc(manifest.created_at.0),
         ingredients: ingredient_ids,
         assertions,
         references: Vec::new(), // Will populate below
-        signature: manifest.claim_v2.as_ref().map(|c| c.signature.is_some()),
+        signature: Some(claims::is_signed(&manifest)),
     };
 
     // Add node to graph
@@ -902,6 +1305,11 @@ fn build_provenance_graph(
     for cross_ref in &manifest.cross_references {
         let target_id = &cross_ref.manifest_url;
 
+        if attestation::is_envelope_reference(cross_ref) {
+            add_envelope_node(id, cross_ref, storage, graph)?;
+            continue;
+        }
+
         // Add references to the node
         if let Some(node) = graph.nodes.get_mut(id) {
             node.references.push(ReferenceInfo {
@@ -921,6 +1329,7 @@ fn build_provenance_graph(
         build_provenance_graph(
             target_id,
             storage,
+            prefetched,
             graph,
             visited,
             max_depth,
@@ -946,6 +1355,58 @@ fn build_provenance_graph(
     Ok(())
 }
 
+/// Adds a stored envelope referenced by a manifest to the graph, as a leaf node
+fn add_envelope_node(
+    source_id: &str,
+    cross_ref: &CrossReference,
+    storage: &(impl StorageBackend + ?Sized),
+    graph: &mut ProvenanceGraph,
+) -> Result<()> {
+    let target_id = &cross_ref.manifest_url;
+    let record = storage
+        .retrieve_envelope(target_id)
+        .map_err(|e| Error::Manifest(format!("Failed to retrieve envelope {target_id}: {e}")))?;
+    let statement = crate::in_toto::read_statement(&record.envelope)?;
+
+    if !graph.nodes.contains_key(target_id) {
+        graph.nodes.insert(
+            target_id.clone(),
+            ManifestNode {
+                id: target_id.clone(),
+                title: record.name.clone(),
+                manifest_type: format!("{} Attestation", record.kind),
+                created_at: record.created_at.clone(),
+                ingredients: statement.subject.into_iter().map(|s| s.name).collect(),
+                assertions: vec![AssertionInfo {
+                    type_name: "Predicate".to_string(),
+                    details: serde_json::json!({ "predicate_type": statement.predicate_type }),
+                }],
+                references: Vec::new(),
+                signature: Some(!record.envelope.signatures().is_empty()),
+            },
+        );
+    }
+
+    for (source, target, relation_type) in [
+        (source_id, target_id.as_str(), "attestedBy"),
+        (target_id.as_str(), source_id, "attests"),
+    ] {
+        if let Some(node) = graph.nodes.get_mut(source) {
+            node.references.push(ReferenceInfo {
+                target_id: target.to_string(),
+                relation_type: relation_type.to_string(),
+            });
+        }
+        graph.edges.push(Edge {
+            source: source.to_string(),
+            target: target.to_string(),
+            relation_type: relation_type.to_string(),
+        });
+    }
+
+    Ok(())
+}
+
 /// Extract details from an assertion in a simplified form
 fn extract_assertion_details(
     assertion: &atlas_c2pa_lib::assertion::Assertion,
@@ -994,11 +1455,18 @@ fn extract_assertion_details(
             })
         }
         atlas_c2pa_lib::assertion::Assertion::CustomAssertion(custom) => {
-            let r_str = custom.data.as_str().unwrap();
-            let r: MockReport = serde_json::from_str(r_str).unwrap();
+            // CC reports are stored as JSON strings, other custom assertions as values
+            let data = match custom
+                .data
+                .as_str()
+                .and_then(|r_str| serde_json::from_str::<MockReport>(r_str).ok())
+            {
+                Some(r) => serde_json::to_value(r).unwrap_or_default(),
+                None => custom.data.clone(),
+            };
             serde_json::json!({
                 "label": custom.label,
-                "data": r,
+                "data": data,
             })
         }
         _ => serde_json::json!({"type": "Unknown"}),
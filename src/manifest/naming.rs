@@ -0,0 +1,155 @@
+//! Names of the ingredients of a manifest.
+//!
+//! Ingredients are named with `--ingredient-names`, in the order of `--paths`. Paths without
+//! a name are named after their relative path: below `--path-base` if one is given, or as
+//! given if they are relative, or else by their file name. Cloud objects are named after
+//! their key.
+//!
+//! Names must be unique, ignoring case: OMS orders ingredients by name, and two ingredients
+//! of the same name could be hashed in either order.
+
+use crate::error::{Error, Result};
+use crate::hash;
+use crate::manifest::object_store::ObjectUrl;
+use atlas_c2pa_lib::ingredient::Ingredient;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+/// The ingredient name of each path: the given name, or one derived from the path for
+/// paths beyond the given names
+pub fn ingredient_names(
+    paths: &[PathBuf],
+    names: &[String],
+    path_base: Option<&Path>,
+) -> Result<Vec<String>> {
+    if names.len() > paths.len() {
+        let unmatched = names[paths.len()..]
+            .iter()
+            .map(|name| format!("'{name}'"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        return Err(Error::Validation(format!(
+            "Got {} ingredient names for {} path(s): no path for {unmatched}",
+            names.len(),
+            paths.len()
+        )));
+    }
+
+    let mut resolved = names.to_vec();
+    for path in &paths[names.len()..] {
+        resolved.push(derive_ingredient_name(path, path_base)?);
+    }
+    Ok(resolved)
+}
+
+/// Derives the name of an ingredient from its path, see the module documentation
+pub fn derive_ingredient_name(path: &Path, path_base: Option<&Path>) -> Result<String> {
+    // A path may select its own hash algorithm, as in `model.onnx:sha256`
+    let (path, _) = hash::split_path_algorithm(path)?;
+    if let Some(url) = ObjectUrl::parse(&path.to_string_lossy()) {
+        return Ok(url.key);
+    }
+
+    let relative = match path_base {
+        Some(base) => std::path::absolute(&path)?
+            .strip_prefix(std::path::absolute(base)?)
+            .ok()
+            .map(Path::to_path_buf),
+        None => None,
+    }
+    .or_else(|| path.is_relative().then(|| path.clone()));
+
+    // Paths leaving their directory are named by their file name
+    let name = relative
+        .filter(|relative| {
+            !relative
+                .components()
+                .any(|component| component == Component::ParentDir)
+        })
+        .map(|relative| {
+            relative
+                .components()
+                .filter(|component| *component != Component::CurDir)
+                .map(|component| component.as_os_str().to_string_lossy().to_string())
+                .collect::<Vec<_>>()
+                .join("/")
+        })
+        .filter(|name| !name.is_empty());
+    if let Some(name) = name {
+        return Ok(name);
+    }
+
+    std::path::absolute(&path)?
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .ok_or_else(|| {
+            Error::Validation(format!(
+                "Can't derive an ingredient name from {}, name it with --ingredient-names",
+                path.display()
+            ))
+        })
+}
+
+/// Checks that no two ingredients have the same name, ignoring case
+pub fn check_unique_names(ingredients: &[Ingredient]) -> Result<()> {
+    let mut seen: HashMap<String, &str> = HashMap::new();
+    for ingredient in ingredients {
+        if let Some(other) = seen.insert(ingredient.title.to_lowercase(), &ingredient.title) {
+            return Err(Error::Validation(format!(
+                "Ingredient names must be unique, ignoring case, but '{other}' and '{}' are both used; \
+                 name the paths with --ingredient-names",
+                ingredient.title
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn derived(path: &str, base: Option<&str>) -> String {
+        derive_ingredient_name(Path::new(path), base.map(Path::new)).unwrap()
+    }
+
+    #[test]
+    fn test_derive_ingredient_name() {
+        assert_eq!(derived("model.onnx", None), "model.onnx");
+        assert_eq!(derived("./weights/model.onnx", None), "weights/model.onnx");
+        assert_eq!(
+            derived("weights/model.onnx:sha256", None),
+            "weights/model.onnx"
+        );
+        assert_eq!(derived("../model.onnx", None), "model.onnx");
+        assert_eq!(derived("/srv/models/model.onnx", None), "model.onnx");
+        assert_eq!(
+            derived("/srv/models/v2/model.onnx", Some("/srv/models")),
+            "v2/model.onnx"
+        );
+        assert_eq!(
+            derived("s3://bucket/models/model.onnx", None),
+            "models/model.onnx"
+        );
+    }
+
+    #[test]
+    fn test_ingredient_names() -> Result<()> {
+        let paths = [
+            PathBuf::from("model.onnx"),
+            PathBuf::from("config/tokenizer.json"),
+        ];
+        assert_eq!(
+            ingredient_names(&paths, &["weights".to_string()], None)?,
+            ["weights", "config/tokenizer.json"]
+        );
+        assert_eq!(
+            ingredient_names(&paths, &[], None)?,
+            ["model.onnx", "config/tokenizer.json"]
+        );
+        let names = ["a", "b", "c"].map(String::from);
+        let err = ingredient_names(&paths, &names, None).unwrap_err();
+        assert!(err.to_string().contains("no path for 'c'"));
+        Ok(())
+    }
+}
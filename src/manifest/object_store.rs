@@ -0,0 +1,1032 @@
+//! Cloud object storage ingredients.
+//!
+//! Objects in S3 (`s3://bucket/key`) and Google Cloud Storage (`gs://bucket/key`) are
+//! hashed without a local copy: the object is read with ranged GET requests over several
+//! connections and the chunks are fed to the hasher in order, so memory use is bounded by
+//! the chunk size times the number of connections. Ingredients record the canonical
+//! object URL including the version read, `s3://bucket/key?versionId=...` or
+//! `gs://bucket/key#generation`, so verification re-reads exactly that version.
+//!
+//! Ingredients also record the checksum the store reports for the object: the MD5 or
+//! SHA-256 digest where the store has one for the whole object, or else its ETag. With
+//! `verify --fast-cloud`, an object whose store still reports the recorded digest is not
+//! downloaded and re-hashed, see [`ProviderChecksum`].
+//!
+//! Requests go to the public endpoints unless `AWS_ENDPOINT_URL` or
+//! `STORAGE_EMULATOR_HOST` is set. S3 requests are signed with SigV4 when
+//! `AWS_ACCESS_KEY_ID` and `AWS_SECRET_ACCESS_KEY` are set, and GCS requests carry
+//! `GOOGLE_OAUTH_ACCESS_TOKEN` as a bearer token; otherwise objects are read anonymously.
+
+use crate::error::{Error, Result};
+use crate::hash;
+use crate::http;
+use crate::manifest::claims;
+use atlas_c2pa_lib::assertion::Assertion;
+use atlas_c2pa_lib::cose::HashAlgorithm;
+use atlas_c2pa_lib::manifest::Manifest;
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use openssl::hash::MessageDigest;
+use openssl::pkey::PKey;
+use openssl::sign::Signer;
+use reqwest::StatusCode;
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use reqwest::header::{
+    CONTENT_LENGTH, CONTENT_TYPE, ETAG, HeaderMap, IF_MATCH, IF_NONE_MATCH, RANGE,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Read;
+use time::OffsetDateTime;
+
+/// Bytes requested per ranged read
+pub const DEFAULT_CHUNK_SIZE: u64 = 8 * 1024 * 1024;
+
+/// Default number of concurrent ranged reads per object
+pub const DEFAULT_CONNECTIONS: usize = 8;
+
+/// Label of the assertion recording the provider checksums of cloud ingredients, keyed by
+/// ingredient URL
+pub const PROVIDER_CHECKSUMS_ASSERTION_LABEL: &str = "provider_checksums";
+
+/// Algorithm of a [`ProviderChecksum`] that is an opaque ETag rather than a digest
+pub const ETAG_CHECKSUM: &str = "etag";
+
+const S3_ENDPOINT: &str = "https://s3.amazonaws.com";
+const GCS_ENDPOINT: &str = "https://storage.googleapis.com";
+
+// SHA-256 of an empty payload, S3 GET, HEAD and DELETE requests have no body
+const EMPTY_PAYLOAD_HASH: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+/// Supported object stores
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectStore {
+    S3,
+    Gcs,
+}
+
+impl ObjectStore {
+    // Prefix of the headers carrying user-defined object metadata
+    fn metadata_prefix(self) -> &'static str {
+        match self {
+            ObjectStore::S3 => "x-amz-meta-",
+            ObjectStore::Gcs => "x-goog-meta-",
+        }
+    }
+}
+
+/// Location of an object, optionally pinned to a version
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectUrl {
+    pub store: ObjectStore,
+    pub bucket: String,
+    pub key: String,
+    /// S3 version ID or GCS generation
+    pub version: Option<String>,
+}
+
+impl ObjectUrl {
+    /// Parses an `s3://` or `gs://` URL, returning `None` for other URLs
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use atlas_cli::manifest::object_store::{ObjectStore, ObjectUrl};
+    ///
+    /// let url = ObjectUrl::parse("s3://datasets/train/part-0.parquet?versionId=3HL4kqtJ").unwrap();
+    /// assert_eq!(url.store, ObjectStore::S3);
+    /// assert_eq!(url.key, "train/part-0.parquet");
+    /// assert_eq!(url.version.as_deref(), Some("3HL4kqtJ"));
+    ///
+    /// let url = ObjectUrl::parse("gs://datasets/train.csv#1712345678901234").unwrap();
+    /// assert_eq!(url.to_string(), "gs://datasets/train.csv#1712345678901234");
+    ///
+    /// assert!(ObjectUrl::parse("file:///data/train.csv").is_none());
+    /// ```
+    pub fn parse(url: &str) -> Option<Self> {
+        let (store, rest, version_separator) = if let Some(rest) = url.strip_prefix("s3://") {
+            (ObjectStore::S3, rest, "?versionId=")
+        } else if let Some(rest) = url.strip_prefix("gs://") {
+            (ObjectStore::Gcs, rest, "#")
+        } else {
+            return None;
+        };
+
+        let (location, version) = match rest.split_once(version_separator) {
+            Some((location, version)) => (location, Some(version.to_string())),
+            None => (rest, None),
+        };
+        let (bucket, key) = location.split_once('/')?;
+        if bucket.is_empty() || key.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            store,
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+            version,
+        })
+    }
+
+    /// The same object pinned to `version`
+    pub fn with_version(&self, version: Option<String>) -> Self {
+        Self {
+            version,
+            ..self.clone()
+        }
+    }
+}
+
+impl fmt::Display for ObjectUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.store, &self.version) {
+            (ObjectStore::S3, Some(version)) => {
+                write!(f, "s3://{}/{}?versionId={version}", self.bucket, self.key)
+            }
+            (ObjectStore::S3, None) => write!(f, "s3://{}/{}", self.bucket, self.key),
+            (ObjectStore::Gcs, Some(version)) => {
+                write!(f, "gs://{}/{}#{version}", self.bucket, self.key)
+            }
+            (ObjectStore::Gcs, None) => write!(f, "gs://{}/{}", self.bucket, self.key),
+        }
+    }
+}
+
+/// Precondition of [`ObjectClient::put_object`] on the object it replaces
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PutCondition {
+    /// Only write if no object has the key
+    Absent,
+    /// Only write if the current object has this ETag
+    Matches(String),
+}
+
+/// An object as found by [`ObjectClient::stat`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteObject {
+    /// URL of the object, pinned to the current version if the store reported one
+    pub url: ObjectUrl,
+    pub size: u64,
+    pub etag: Option<String>,
+    pub checksum: Option<ProviderChecksum>,
+    /// User-defined metadata of the object, by name without the store's prefix
+    pub metadata: BTreeMap<String, String>,
+}
+
+/// Checksum of an object as reported by its store.
+///
+/// S3 reports the SHA-256 of objects uploaded with a full-object checksum, and its ETag
+/// is the MD5 of objects uploaded in one part without KMS or customer-key encryption. GCS
+/// reports the MD5 of objects that were not composed from others. Any other ETag only
+/// tells that the object changed, so it can't stand in for re-hashing the object.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProviderChecksum {
+    /// `md5` or `sha256` for a digest of the whole object, or [`ETAG_CHECKSUM`]
+    pub algorithm: String,
+    /// Hex-encoded digest, or the ETag as reported
+    pub value: String,
+}
+
+impl ProviderChecksum {
+    /// Whether the checksum is a digest of the whole object
+    pub fn is_digest(&self) -> bool {
+        self.algorithm != ETAG_CHECKSUM
+    }
+
+    /// Checksum reported with an object, preferring digests over opaque ETags
+    pub fn from_headers(store: ObjectStore, headers: &HeaderMap) -> Option<Self> {
+        let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+        let digest = |algorithm: &str, encoded: &str| {
+            STANDARD.decode(encoded.trim()).ok().map(|digest| Self {
+                algorithm: algorithm.to_string(),
+                value: hex::encode(digest),
+            })
+        };
+        let etag = || {
+            header(ETAG.as_str()).map(|etag| Self {
+                algorithm: ETAG_CHECKSUM.to_string(),
+                value: etag.to_string(),
+            })
+        };
+
+        match store {
+            ObjectStore::S3 => {
+                // Checksums of multipart uploads are composed from the checksums of the parts
+                if header("x-amz-checksum-type") != Some("COMPOSITE")
+                    && let Some(encoded) = header("x-amz-checksum-sha256")
+                    && !encoded.contains('-')
+                    && let Some(checksum) = digest("sha256", encoded)
+                {
+                    return Some(checksum);
+                }
+
+                let encrypted = header("x-amz-server-side-encryption")
+                    .is_some_and(|sse| sse.starts_with("aws:kms"))
+                    || header("x-amz-server-side-encryption-customer-algorithm").is_some();
+                let md5 = header(ETAG.as_str())
+                    .map(|etag| etag.trim_matches('"'))
+                    .filter(|etag| etag.len() == 32 && etag.chars().all(|c| c.is_ascii_hexdigit()));
+                match md5 {
+                    Some(md5) if !encrypted => Some(Self {
+                        algorithm: "md5".to_string(),
+                        value: md5.to_ascii_lowercase(),
+                    }),
+                    _ => etag(),
+                }
+            }
+            ObjectStore::Gcs => headers
+                .get_all("x-goog-hash")
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(|value| value.split(','))
+                .find_map(|hash| hash.trim().strip_prefix("md5="))
+                .and_then(|encoded| digest("md5", encoded))
+                .or_else(etag),
+        }
+    }
+}
+
+/// Returns the provider checksums recorded in a manifest, keyed by ingredient URL
+pub fn provider_checksums(manifest: &Manifest) -> Result<BTreeMap<String, ProviderChecksum>> {
+    let assertions = &claims::signed_claim(manifest).created_assertions;
+
+    for assertion in assertions {
+        if let Assertion::CustomAssertion(custom) = assertion
+            && custom.label == PROVIDER_CHECKSUMS_ASSERTION_LABEL
+        {
+            return serde_json::from_value(custom.data.clone()).map_err(|e| {
+                Error::Manifest(format!("Invalid provider checksums assertion: {e}"))
+            });
+        }
+    }
+
+    Ok(BTreeMap::new())
+}
+
+/// Bounds on the memory and connections used to hash an object
+#[derive(Debug, Clone, Copy)]
+pub struct StreamOptions {
+    pub chunk_size: u64,
+    pub connections: usize,
+}
+
+impl Default for StreamOptions {
+    fn default() -> Self {
+        Self {
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            connections: DEFAULT_CONNECTIONS,
+        }
+    }
+}
+
+struct AwsCredentials {
+    access_key_id: String,
+    secret_access_key: String,
+    session_token: Option<String>,
+    region: String,
+}
+
+/// Client for reading objects from one object store
+pub struct ObjectClient {
+    http: Client,
+    store: ObjectStore,
+    endpoint: String,
+    aws: Option<AwsCredentials>,
+    bearer_token: Option<String>,
+}
+
+impl ObjectClient {
+    /// Creates a client configured from the environment
+    pub fn from_env(store: ObjectStore) -> Result<Self> {
+        let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+
+        let mut client = match store {
+            ObjectStore::S3 => {
+                let endpoint = var("AWS_ENDPOINT_URL").unwrap_or(S3_ENDPOINT.to_string());
+                let mut client = Self::with_endpoint(store, &endpoint)?;
+                if let (Some(access_key_id), Some(secret_access_key)) =
+                    (var("AWS_ACCESS_KEY_ID"), var("AWS_SECRET_ACCESS_KEY"))
+                {
+                    client.aws = Some(AwsCredentials {
+                        access_key_id,
+                        secret_access_key,
+                        session_token: var("AWS_SESSION_TOKEN"),
+                        region: var("AWS_REGION")
+                            .or_else(|| var("AWS_DEFAULT_REGION"))
+                            .unwrap_or("us-east-1".to_string()),
+                    });
+                }
+                client
+            }
+            ObjectStore::Gcs => {
+                let endpoint = var("STORAGE_EMULATOR_HOST").unwrap_or(GCS_ENDPOINT.to_string());
+                Self::with_endpoint(store, &endpoint)?
+            }
+        };
+        client.bearer_token =
+            var("GOOGLE_OAUTH_ACCESS_TOKEN").filter(|_| store == ObjectStore::Gcs);
+
+        Ok(client)
+    }
+
+    /// Creates an anonymous client for an endpoint, e.g. `http://localhost:9000`
+    pub fn with_endpoint(store: ObjectStore, endpoint: &str) -> Result<Self> {
+        let http = http::client_builder()
+            .build()
+            .map_err(|e| Error::Storage(format!("Failed to create HTTP client: {e}")))?;
+
+        Ok(Self {
+            http,
+            store,
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            aws: None,
+            bearer_token: None,
+        })
+    }
+
+    /// Looks up the size, current version and checksum of an object
+    pub fn stat(&self, url: &ObjectUrl) -> Result<RemoteObject> {
+        let response = self.send("HEAD", url, |request| match self.store {
+            // S3 only reports additional checksums when asked to
+            ObjectStore::S3 => request.header("x-amz-checksum-mode", "ENABLED"),
+            ObjectStore::Gcs => request,
+        })?;
+        let headers = response.headers();
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+
+        let size = header(CONTENT_LENGTH.as_str())
+            .and_then(|length| length.parse().ok())
+            .ok_or_else(|| Error::Storage(format!("No content length reported for {url}")))?;
+        let version = match self.store {
+            ObjectStore::S3 => header("x-amz-version-id").filter(|version| version != "null"),
+            ObjectStore::Gcs => header("x-goog-generation"),
+        };
+        let metadata = headers
+            .iter()
+            .filter_map(|(name, value)| {
+                let name = name.as_str().strip_prefix(self.store.metadata_prefix())?;
+                Some((name.to_string(), value.to_str().ok()?.to_string()))
+            })
+            .collect();
+
+        Ok(RemoteObject {
+            url: url.with_version(version.or_else(|| url.version.clone())),
+            size,
+            etag: header(ETAG.as_str()),
+            checksum: ProviderChecksum::from_headers(self.store, headers),
+            metadata,
+        })
+    }
+
+    /// Hashes an object with ranged reads, without storing more than
+    /// `chunk_size * connections` bytes of it at a time
+    pub fn hash_object(
+        &self,
+        object: &RemoteObject,
+        algorithm: &HashAlgorithm,
+        options: StreamOptions,
+    ) -> Result<String> {
+        let reader = ObjectReader {
+            client: self,
+            object,
+            options: StreamOptions {
+                chunk_size: options.chunk_size.max(1),
+                connections: options.connections.max(1),
+            },
+            offset: 0,
+            chunks: Vec::new(),
+            position: 0,
+        };
+
+        hash::calculate_reader_hash_with_algorithm(reader, algorithm)
+    }
+
+    // Reads `length` bytes of an object starting at `offset`
+    fn read_range(&self, object: &RemoteObject, offset: u64, length: u64) -> Result<Vec<u8>> {
+        let last = offset + length - 1;
+        let response = self.send("GET", &object.url, |mut request| {
+            request = request.header(RANGE, format!("bytes={offset}-{last}"));
+            // Unversioned objects must not change between ranges
+            if object.url.version.is_none()
+                && let Some(etag) = &object.etag
+            {
+                request = request.header(IF_MATCH, etag);
+            }
+            request
+        })?;
+
+        let body = response
+            .bytes()
+            .map_err(|e| Error::Storage(format!("Failed to read {}: {e}", object.url)))?;
+        if body.len() as u64 != length {
+            return Err(Error::Storage(format!(
+                "Expected {length} bytes at offset {offset} of {}, received {}",
+                object.url,
+                body.len()
+            )));
+        }
+
+        Ok(body.to_vec())
+    }
+
+    /// Reads a whole object
+    pub fn get_object(&self, url: &ObjectUrl) -> Result<Vec<u8>> {
+        Ok(self.get_object_with_etag(url)?.0)
+    }
+
+    /// Reads a whole object, with its ETag if the store reports one
+    pub fn get_object_with_etag(&self, url: &ObjectUrl) -> Result<(Vec<u8>, Option<String>)> {
+        let response = self.send("GET", url, |request| request)?;
+        let etag = response
+            .headers()
+            .get(ETAG)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let body = response
+            .bytes()
+            .map_err(|e| Error::StorageUnavailable(format!("Failed to read {url}: {e}")))?;
+        Ok((body.to_vec(), etag))
+    }
+
+    /// Writes an object with user-defined `metadata` if `condition` holds, returning
+    /// whether it was written. Metadata values must be ASCII.
+    pub fn put_object(
+        &self,
+        url: &ObjectUrl,
+        body: Vec<u8>,
+        content_type: &str,
+        metadata: &[(&str, String)],
+        condition: &PutCondition,
+    ) -> Result<bool> {
+        let payload_hash = hash::calculate_hash_with_algorithm(&body, &HashAlgorithm::Sha256);
+        let (path, query) = self.object_path(url);
+        let what = url.to_string();
+        let headers: Vec<(String, String)> = metadata
+            .iter()
+            .map(|(name, value)| {
+                (
+                    format!("{}{name}", self.store.metadata_prefix()),
+                    value.clone(),
+                )
+            })
+            .collect();
+        let response = self.send_request(
+            "PUT",
+            &path,
+            &query,
+            &payload_hash,
+            &headers,
+            &what,
+            |request| {
+                let request = match condition {
+                    PutCondition::Absent => request.header(IF_NONE_MATCH, "*"),
+                    PutCondition::Matches(etag) => request.header(IF_MATCH, etag),
+                };
+                request.header(CONTENT_TYPE, content_type).body(body)
+            },
+        )?;
+
+        match response.status() {
+            status if status.is_success() => Ok(true),
+            // S3 answers a conditional write racing another one with 409 Conflict
+            StatusCode::PRECONDITION_FAILED | StatusCode::CONFLICT => Ok(false),
+            status => Err(Error::storage_status(
+                status,
+                format!("Request for {what} failed with status {status}"),
+            )),
+        }
+    }
+
+    /// Deletes an object. Stores answer the deletion of a missing object as a success.
+    pub fn delete_object(&self, url: &ObjectUrl) -> Result<()> {
+        self.send("DELETE", url, |request| request)?;
+        Ok(())
+    }
+
+    /// Lists the keys of the objects of an S3 bucket that start with `prefix`, following
+    /// continuation tokens until the listing is complete
+    pub fn list_objects(&self, bucket: &str, prefix: &str) -> Result<Vec<String>> {
+        let path = format!("/{}", uri_encode(bucket, true));
+        let what = format!("s3://{bucket}/{prefix}");
+        let mut keys = Vec::new();
+        let mut continuation = None;
+        loop {
+            // Query parameters in the sorted order of the canonical request
+            let mut query = Vec::new();
+            if let Some(token) = &continuation {
+                query.push(format!("continuation-token={}", uri_encode(token, true)));
+            }
+            query.push("list-type=2".to_string());
+            query.push(format!("prefix={}", uri_encode(prefix, true)));
+            let query = query.join("&");
+
+            let response = self.request("GET", &path, &query, EMPTY_PAYLOAD_HASH, &what, |r| r)?;
+            let body = response
+                .text()
+                .map_err(|e| Error::StorageUnavailable(format!("Failed to list {what}: {e}")))?;
+            keys.extend(xml_values(&body, "Key"));
+
+            continuation = xml_values(&body, "NextContinuationToken")
+                .into_iter()
+                .next();
+            let truncated =
+                xml_values(&body, "IsTruncated").first().map(String::as_str) == Some("true");
+            if !truncated || continuation.is_none() {
+                return Ok(keys);
+            }
+        }
+    }
+
+    fn send(
+        &self,
+        method: &str,
+        url: &ObjectUrl,
+        customize: impl FnOnce(RequestBuilder) -> RequestBuilder,
+    ) -> Result<Response> {
+        let (path, query) = self.object_path(url);
+        self.request(
+            method,
+            &path,
+            &query,
+            EMPTY_PAYLOAD_HASH,
+            &url.to_string(),
+            customize,
+        )
+    }
+
+    // Path and query of the requests for an object, as they are signed
+    fn object_path(&self, url: &ObjectUrl) -> (String, String) {
+        let path = format!(
+            "/{}/{}",
+            uri_encode(&url.bucket, true),
+            uri_encode(&url.key, false)
+        );
+        let query = match (self.store, &url.version) {
+            (ObjectStore::S3, Some(version)) => format!("versionId={}", uri_encode(version, true)),
+            (ObjectStore::Gcs, Some(generation)) => {
+                format!("generation={}", uri_encode(generation, true))
+            }
+            (_, None) => String::new(),
+        };
+        (path, query)
+    }
+
+    // Sends a request for `path` and `query`, which must already be encoded. `what` names
+    // the requested resource in errors.
+    fn request(
+        &self,
+        method: &str,
+        path: &str,
+        query: &str,
+        payload_hash: &str,
+        what: &str,
+        customize: impl FnOnce(RequestBuilder) -> RequestBuilder,
+    ) -> Result<Response> {
+        let response =
+            self.send_request(method, path, query, payload_hash, &[], what, customize)?;
+        if !response.status().is_success() {
+            return Err(Error::storage_status(
+                response.status(),
+                format!(
+                    "Request for {what} failed with status {}",
+                    response.status()
+                ),
+            ));
+        }
+
+        Ok(response)
+    }
+
+    // Sends a request as `request` does, returning the response whatever its status.
+    // `headers` are signed along with the request, as S3 requires of `x-amz-` headers.
+    #[allow(clippy::too_many_arguments)]
+    fn send_request(
+        &self,
+        method: &str,
+        path: &str,
+        query: &str,
+        payload_hash: &str,
+        headers: &[(String, String)],
+        what: &str,
+        customize: impl FnOnce(RequestBuilder) -> RequestBuilder,
+    ) -> Result<Response> {
+        let mut request_url = format!("{}{path}", self.endpoint);
+        if !query.is_empty() {
+            request_url = format!("{request_url}?{query}");
+        }
+
+        let method = reqwest::Method::from_bytes(method.as_bytes())
+            .map_err(|e| Error::Storage(e.to_string()))?;
+        let mut request = self.http.request(method.clone(), &request_url);
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+
+        if let Some(aws) = &self.aws {
+            for (name, value) in
+                self.sigv4_headers(aws, method.as_str(), path, query, payload_hash, headers)?
+            {
+                request = request.header(name, value);
+            }
+        } else if let Some(token) = &self.bearer_token {
+            request = request.bearer_auth(token);
+        }
+
+        customize(request)
+            .send()
+            .map_err(|e| Error::storage_request(&format!("Failed to request {what}"), e))
+    }
+
+    // Headers authenticating a request with AWS Signature Version 4, also signing the
+    // `extra` headers the request carries
+    fn sigv4_headers(
+        &self,
+        aws: &AwsCredentials,
+        method: &str,
+        path: &str,
+        query: &str,
+        payload_hash: &str,
+        extra: &[(String, String)],
+    ) -> Result<Vec<(String, String)>> {
+        let endpoint = reqwest::Url::parse(&self.endpoint)
+            .map_err(|e| Error::Storage(format!("Invalid endpoint {}: {e}", self.endpoint)))?;
+        let host = match (endpoint.host_str(), endpoint.port()) {
+            (Some(host), Some(port)) => format!("{host}:{port}"),
+            (Some(host), None) => host.to_string(),
+            (None, _) => {
+                return Err(Error::Storage(format!(
+                    "Invalid endpoint {}: no host",
+                    self.endpoint
+                )));
+            }
+        };
+
+        let now = OffsetDateTime::now_utc();
+        let date = format!(
+            "{:04}{:02}{:02}",
+            now.year(),
+            u8::from(now.month()),
+            now.day()
+        );
+        let timestamp = format!(
+            "{date}T{:02}{:02}{:02}Z",
+            now.hour(),
+            now.minute(),
+            now.second()
+        );
+
+        let mut headers = vec![
+            ("host".to_string(), host),
+            ("x-amz-content-sha256".to_string(), payload_hash.to_string()),
+            ("x-amz-date".to_string(), timestamp.clone()),
+        ];
+        if let Some(token) = &aws.session_token {
+            headers.push(("x-amz-security-token".to_string(), token.clone()));
+        }
+        headers.extend(extra.iter().cloned());
+        // Canonical headers are sorted by name
+        headers.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let canonical_headers: String = headers
+            .iter()
+            .map(|(name, value)| format!("{name}:{}\n", value.trim()))
+            .collect();
+        let signed_headers = headers
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>()
+            .join(";");
+        let canonical_request = format!(
+            "{method}\n{path}\n{query}\n{canonical_headers}\n{signed_headers}\n{payload_hash}"
+        );
+
+        let scope = format!("{date}/{}/s3/aws4_request", aws.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{timestamp}\n{scope}\n{}",
+            hash::calculate_hash_with_algorithm(
+                canonical_request.as_bytes(),
+                &HashAlgorithm::Sha256
+            )
+        );
+
+        let mut key = format!("AWS4{}", aws.secret_access_key).into_bytes();
+        for part in [date.as_str(), aws.region.as_str(), "s3", "aws4_request"] {
+            key = hmac_sha256(&key, part.as_bytes())?;
+        }
+        let signature = hex::encode(hmac_sha256(&key, string_to_sign.as_bytes())?);
+
+        // The host header is set by the HTTP client, and the extra headers by the caller
+        headers.retain(|(name, _)| name != "host" && !extra.iter().any(|(extra, _)| extra == name));
+        headers.push((
+            "authorization".to_string(),
+            format!(
+                "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_headers}, Signature={signature}",
+                aws.access_key_id
+            ),
+        ));
+
+        Ok(headers)
+    }
+}
+
+// Reads an object in order, fetching up to `connections` chunks at a time in parallel
+struct ObjectReader<'a> {
+    client: &'a ObjectClient,
+    object: &'a RemoteObject,
+    options: StreamOptions,
+    // Offset of the first byte not yet fetched
+    offset: u64,
+    // Fetched chunks not yet fully read, in reverse order
+    chunks: Vec<Vec<u8>>,
+    // Bytes of the last chunk already read
+    position: usize,
+}
+
+impl ObjectReader<'_> {
+    fn fetch_batch(&mut self) -> Result<()> {
+        let ranges: Vec<(u64, u64)> = (0..self.options.connections)
+            .map_while(|index| {
+                let start = self.offset + index as u64 * self.options.chunk_size;
+                (start < self.object.size)
+                    .then(|| (start, self.options.chunk_size.min(self.object.size - start)))
+            })
+            .collect();
+
+        let (client, object) = (self.client, self.object);
+        let chunks = std::thread::scope(|scope| {
+            let handles: Vec<_> = ranges
+                .iter()
+                .map(|&(start, length)| {
+                    scope.spawn(move || client.read_range(object, start, length))
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .map(|handle| {
+                    handle
+                        .join()
+                        .map_err(|_| Error::Storage("Ranged read panicked".to_string()))?
+                })
+                .collect::<Result<Vec<_>>>()
+        })?;
+
+        self.offset += ranges.iter().map(|(_, length)| length).sum::<u64>();
+        self.chunks = chunks.into_iter().rev().collect();
+        self.position = 0;
+        Ok(())
+    }
+}
+
+impl Read for ObjectReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        loop {
+            if let Some(chunk) = self.chunks.last() {
+                let remaining = &chunk[self.position..];
+                if !remaining.is_empty() {
+                    let n = remaining.len().min(buf.len());
+                    buf[..n].copy_from_slice(&remaining[..n]);
+                    self.position += n;
+                    return Ok(n);
+                }
+                self.chunks.pop();
+                self.position = 0;
+                continue;
+            }
+
+            if self.offset >= self.object.size {
+                return Ok(0);
+            }
+            self.fetch_batch().map_err(std::io::Error::other)?;
+        }
+    }
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+    let key = PKey::hmac(key).map_err(|e| Error::Signing(e.to_string()))?;
+    let mut signer =
+        Signer::new(MessageDigest::sha256(), &key).map_err(|e| Error::Signing(e.to_string()))?;
+    signer
+        .sign_oneshot_to_vec(data)
+        .map_err(|e| Error::Signing(e.to_string()))
+}
+
+// The text of every `<tag>` element of an XML document, unescaped. S3 listings are flat
+// enough not to need an XML parser.
+fn xml_values(xml: &str, tag: &str) -> Vec<String> {
+    let (open, close) = (format!("<{tag}>"), format!("</{tag}>"));
+    let mut values = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        let after = &rest[start + open.len()..];
+        let Some(end) = after.find(&close) else {
+            break;
+        };
+        values.push(
+            after[..end]
+                .replace("&lt;", "<")
+                .replace("&gt;", ">")
+                .replace("&quot;", "\"")
+                .replace("&apos;", "'")
+                .replace("&amp;", "&"),
+        );
+        rest = &after[end + close.len()..];
+    }
+    values
+}
+
+// Percent-encodes everything but RFC 3986 unreserved characters (and `/` unless
+// `encode_slash`), as required for SigV4 canonical requests
+fn uri_encode(s: &str, encode_slash: bool) -> String {
+    s.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            b'/' if !encode_slash => "/".to_string(),
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // Serves one object with HEAD and ranged GET requests, counting the GETs
+    fn serve(data: Vec<u8>) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let gets = Arc::new(AtomicUsize::new(0));
+
+        let counter = gets.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { continue };
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+
+                let mut range = None;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    if let Some(value) = line.to_ascii_lowercase().strip_prefix("range: bytes=") {
+                        let (start, end) = value.trim().split_once('-').unwrap();
+                        range = Some((
+                            start.parse::<usize>().unwrap(),
+                            end.parse::<usize>().unwrap(),
+                        ));
+                    }
+                }
+
+                let response = if request_line.starts_with("HEAD") {
+                    format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nx-amz-version-id: v1\r\nConnection: close\r\n\r\n",
+                        data.len()
+                    )
+                    .into_bytes()
+                } else {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    let (start, end) = range.unwrap();
+                    let body = &data[start..=end];
+                    let mut response = format!(
+                        "HTTP/1.1 206 Partial Content\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                        body.len()
+                    )
+                    .into_bytes();
+                    response.extend_from_slice(body);
+                    response
+                };
+                stream.write_all(&response).unwrap();
+            }
+        });
+
+        (endpoint, gets)
+    }
+
+    #[test]
+    fn test_hash_object_with_ranged_reads() -> Result<()> {
+        let data: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+        let (endpoint, gets) = serve(data.clone());
+
+        let client = ObjectClient::with_endpoint(ObjectStore::S3, &endpoint)?;
+        let object = client.stat(&ObjectUrl::parse("s3://bucket/data/train.bin").unwrap())?;
+        assert_eq!(object.size, data.len() as u64);
+        assert_eq!(
+            object.url.to_string(),
+            "s3://bucket/data/train.bin?versionId=v1"
+        );
+
+        let options = StreamOptions {
+            chunk_size: 1024,
+            connections: 3,
+        };
+        let hash = client.hash_object(&object, &HashAlgorithm::Sha384, options)?;
+        assert_eq!(
+            hash,
+            hash::calculate_hash_with_algorithm(&data, &HashAlgorithm::Sha384)
+        );
+        assert_eq!(gets.load(Ordering::SeqCst), 10);
+        Ok(())
+    }
+
+    #[test]
+    fn test_provider_checksums() {
+        let headers = |pairs: &[(&'static str, &str)]| {
+            let mut headers = HeaderMap::new();
+            for (name, value) in pairs {
+                headers.append(*name, value.parse().unwrap());
+            }
+            headers
+        };
+        let md5 = "d41d8cd98f00b204e9800998ecf8427e";
+
+        let single_part = headers(&[("etag", "\"D41D8CD98F00B204E9800998ECF8427E\"")]);
+        let checksum = ProviderChecksum::from_headers(ObjectStore::S3, &single_part).unwrap();
+        assert_eq!(
+            (checksum.algorithm.as_str(), checksum.value.as_str()),
+            ("md5", md5)
+        );
+        assert!(checksum.is_digest());
+
+        let multipart = headers(&[("etag", "\"9b2cf535f27731c974343645a3985328-12\"")]);
+        assert!(
+            !ProviderChecksum::from_headers(ObjectStore::S3, &multipart)
+                .unwrap()
+                .is_digest()
+        );
+        let kms = headers(&[("etag", md5), ("x-amz-server-side-encryption", "aws:kms")]);
+        assert!(
+            !ProviderChecksum::from_headers(ObjectStore::S3, &kms)
+                .unwrap()
+                .is_digest()
+        );
+
+        let sha256 = headers(&[
+            ("etag", "\"9b2cf535f27731c974343645a3985328-12\""),
+            (
+                "x-amz-checksum-sha256",
+                "47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=",
+            ),
+            ("x-amz-checksum-type", "FULL_OBJECT"),
+        ]);
+        let checksum = ProviderChecksum::from_headers(ObjectStore::S3, &sha256).unwrap();
+        assert_eq!(checksum.algorithm, "sha256");
+        assert_eq!(checksum.value, EMPTY_PAYLOAD_HASH);
+
+        let gcs = headers(&[
+            ("x-goog-hash", "crc32c=AAAAAA=="),
+            ("x-goog-hash", "md5=1B2M2Y8AsgTpgAmY7PhCfg=="),
+            ("etag", "CJ+g3aXy/oADEAE="),
+        ]);
+        let checksum = ProviderChecksum::from_headers(ObjectStore::Gcs, &gcs).unwrap();
+        assert_eq!(
+            (checksum.algorithm.as_str(), checksum.value.as_str()),
+            ("md5", md5)
+        );
+        let composite = headers(&[("x-goog-hash", "crc32c=AAAAAA=="), ("etag", "CJ+g3aXy")]);
+        assert_eq!(
+            ProviderChecksum::from_headers(ObjectStore::Gcs, &composite)
+                .unwrap()
+                .algorithm,
+            ETAG_CHECKSUM
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_urls() {
+        assert!(ObjectUrl::parse("s3://bucket").is_none());
+        assert!(ObjectUrl::parse("s3:///key").is_none());
+        assert!(ObjectUrl::parse("gs://bucket/").is_none());
+        assert_eq!(uri_encode("a b/c+d", false), "a%20b/c%2Bd");
+    }
+
+    #[test]
+    fn test_xml_values() {
+        let listing = "<ListBucketResult><IsTruncated>true</IsTruncated>\
+            <Contents><Key>manifests/a.json</Key></Contents>\
+            <Contents><Key>manifests/R&amp;D.json</Key></Contents>\
+            <NextContinuationToken>1/ab+c=</NextContinuationToken></ListBucketResult>";
+        assert_eq!(
+            xml_values(listing, "Key"),
+            ["manifests/a.json", "manifests/R&D.json"]
+        );
+        assert_eq!(xml_values(listing, "IsTruncated"), ["true"]);
+        assert_eq!(xml_values(listing, "NextContinuationToken"), ["1/ab+c="]);
+        assert!(xml_values(listing, "Prefix").is_empty());
+    }
+}
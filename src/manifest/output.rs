@@ -0,0 +1,175 @@
+//! Streaming output of manifests and envelopes.
+//!
+//! Manifests for large datasets can reach hundreds of megabytes. Rather than building the
+//! whole JSON document or hex string in memory, values are serialized directly into a
+//! buffered writer. CBOR written to a file is binary; CBOR written to stdout is hex-encoded
+//! in chunks as it is produced.
+
+use crate::error::{Error, Result};
+use crate::utils::safe_create_file;
+use serde::Serialize;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+const HEX_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Supported output encodings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputEncoding {
+    Json,
+    Cbor,
+}
+
+impl std::str::FromStr for OutputEncoding {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(OutputEncoding::Json),
+            "cbor" => Ok(OutputEncoding::Cbor),
+            _ => Err(Error::Validation(format!(
+                "Invalid output encoding '{s}'. Valid options are: json, cbor"
+            ))),
+        }
+    }
+}
+
+/// Serializes `value` with the given encoding to `output`, or to stdout if no path is given.
+///
+/// # Examples
+///
+/// ```
+/// use atlas_cli::manifest::output::write_encoded;
+/// use serde_json::json;
+///
+/// let dir = tempfile::tempdir().unwrap();
+/// let path = dir.path().join("value.cbor");
+///
+/// write_encoded(&json!({"title": "example"}), "cbor", Some(&path)).unwrap();
+/// let bytes = std::fs::read(&path).unwrap();
+/// let decoded: serde_json::Value = serde_cbor::from_slice(&bytes).unwrap();
+/// assert_eq!(decoded["title"], "example");
+/// ```
+pub fn write_encoded<T: Serialize>(value: &T, encoding: &str, output: Option<&Path>) -> Result<()> {
+    let encoding: OutputEncoding = encoding.parse()?;
+
+    match output {
+        Some(path) => {
+            let mut writer = BufWriter::new(safe_create_file(path, false)?);
+            serialize_to(&mut writer, value, encoding)?;
+            writer.flush()?;
+            println!("Output written to {}", path.display());
+        }
+        None => {
+            let stdout = io::stdout();
+            let mut writer = BufWriter::new(stdout.lock());
+            match encoding {
+                OutputEncoding::Json => serialize_to(&mut writer, value, encoding)?,
+                OutputEncoding::Cbor => {
+                    let mut hex_writer = HexWriter::new(&mut writer);
+                    serialize_to(&mut hex_writer, value, encoding)?;
+                    hex_writer.flush()?;
+                }
+            }
+            writeln!(writer)?;
+            writer.flush()?;
+        }
+    }
+
+    Ok(())
+}
+
+fn serialize_to<W: Write, T: Serialize>(
+    writer: &mut W,
+    value: &T,
+    encoding: OutputEncoding,
+) -> Result<()> {
+    match encoding {
+        OutputEncoding::Json => serde_json::to_writer_pretty(writer, value)
+            .map_err(|e| Error::Serialization(e.to_string())),
+        OutputEncoding::Cbor => {
+            serde_cbor::to_writer(writer, value).map_err(|e| Error::Serialization(e.to_string()))
+        }
+    }
+}
+
+/// Hex-encodes everything written to it, in bounded chunks
+struct HexWriter<W: Write> {
+    inner: W,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> HexWriter<W> {
+    fn new(inner: W) -> Self {
+        Self {
+            inner,
+            buffer: Vec::with_capacity(HEX_CHUNK_SIZE),
+        }
+    }
+
+    fn flush_buffer(&mut self) -> io::Result<()> {
+        if !self.buffer.is_empty() {
+            self.inner.write_all(hex::encode(&self.buffer).as_bytes())?;
+            self.buffer.clear();
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for HexWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = buf.len().min(HEX_CHUNK_SIZE - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..n]);
+        if self.buffer.len() == HEX_CHUNK_SIZE {
+            self.flush_buffer()?;
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.flush_buffer()?;
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_hex_writer_matches_hex_encode() {
+        let data: Vec<u8> = (0..(HEX_CHUNK_SIZE * 2 + 17)).map(|i| i as u8).collect();
+
+        let mut out = Vec::new();
+        {
+            let mut writer = HexWriter::new(&mut out);
+            for chunk in data.chunks(1000) {
+                writer.write_all(chunk).unwrap();
+            }
+            writer.flush().unwrap();
+        }
+
+        assert_eq!(String::from_utf8(out).unwrap(), hex::encode(&data));
+    }
+
+    #[test]
+    fn test_write_json_to_file() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("manifest.json");
+        let value = json!({"title": "big", "items": (0..1000).collect::<Vec<_>>()});
+
+        write_encoded(&value, "JSON", Some(&path))?;
+
+        let written: serde_json::Value = serde_json::from_slice(&std::fs::read(&path)?)?;
+        assert_eq!(written, value);
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_encoding() {
+        let result = write_encoded(&json!({}), "xml", None);
+        assert!(matches!(result, Err(Error::Validation(_))));
+    }
+}
@@ -0,0 +1,274 @@
+//! Organization signing policy, enforced when manifests are created.
+//!
+//! An organization can refuse manifests that break its rules, such as unsigned model
+//! manifests, manifests without a license or hash algorithms weaker than it mandates. The
+//! policy is read from `ATLAS_POLICY_FILE` or `~/.atlas/policy.yaml`:
+//!
+//! ```yaml
+//! require_signature: [model, software]
+//! require_license: [model, dataset]
+//! hash_algorithms: [sha384, sha512]
+//! ```
+//!
+//! A manifest violating the policy is only created with `--override-policy=<REASON>` and
+//! `--acknowledge-policy-override`. The override, its reason and the violations are then
+//! recorded in a `policy_override` assertion, so that reviewers can find such manifests.
+
+use crate::error::{Error, Result};
+use crate::hash;
+use crate::manifest::common::{AssetKind, LICENSE_ASSERTION_LABEL};
+use crate::manifest::config::ManifestCreationConfig;
+use atlas_c2pa_lib::assertion::CustomAssertion;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use time::OffsetDateTime;
+
+/// Environment variable naming the policy file
+pub const POLICY_FILE_ENV: &str = "ATLAS_POLICY_FILE";
+
+/// Label of the assertion recording an override of the policy
+pub const POLICY_OVERRIDE_ASSERTION_LABEL: &str = "policy_override";
+
+// Reason given with --override-policy, if any
+static OVERRIDE: Mutex<Option<String>> = Mutex::new(None);
+
+/// Creates manifests violating the policy, recording `reason` in them
+pub fn set_override(reason: Option<String>) {
+    *OVERRIDE.lock().unwrap() = reason;
+}
+
+fn override_reason() -> Option<String> {
+    OVERRIDE.lock().unwrap().clone()
+}
+
+/// Rules that manifests must follow when they are created
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CreationPolicy {
+    /// Asset kinds whose manifests must be signed
+    pub require_signature: Vec<AssetKind>,
+    /// Asset kinds whose manifests must record a license (`--license`)
+    pub require_license: Vec<AssetKind>,
+    /// Hash algorithms that may be used, for signing and for each ingredient; any if empty
+    pub hash_algorithms: Vec<String>,
+}
+
+/// Contents of the policy override assertion
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyOverride {
+    pub reason: String,
+    /// The rules the manifest violates
+    pub violations: Vec<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub timestamp: OffsetDateTime,
+}
+
+impl PolicyOverride {
+    pub fn to_assertion(&self) -> Result<CustomAssertion> {
+        Ok(CustomAssertion {
+            label: POLICY_OVERRIDE_ASSERTION_LABEL.to_string(),
+            data: serde_json::to_value(self)?,
+        })
+    }
+}
+
+/// Location of the policy file: `ATLAS_POLICY_FILE`, or `~/.atlas/policy.yaml`
+pub fn policy_file() -> PathBuf {
+    match std::env::var_os(POLICY_FILE_ENV) {
+        Some(path) => PathBuf::from(path),
+        None => std::env::var_os("HOME")
+            .or_else(|| std::env::var_os("USERPROFILE"))
+            .map(|home| PathBuf::from(home).join(".atlas"))
+            .unwrap_or_default()
+            .join("policy.yaml"),
+    }
+}
+
+impl CreationPolicy {
+    /// Reads a policy file, or returns `None` if there is none
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let policy: Self = serde_yaml::from_str(&std::fs::read_to_string(path)?).map_err(|e| {
+            Error::Validation(format!("Invalid policy file {}: {e}", path.display()))
+        })?;
+        for algorithm in &policy.hash_algorithms {
+            hash::parse_algorithm(algorithm)?;
+        }
+        Ok(Some(policy))
+    }
+
+    /// Returns the rules a manifest created with `config` would violate
+    pub fn violations(
+        &self,
+        config: &ManifestCreationConfig,
+        asset_kind: AssetKind,
+    ) -> Result<Vec<String>> {
+        let mut violations = Vec::new();
+
+        if self.require_signature.contains(&asset_kind) && config.key_path.is_none() {
+            violations.push(format!("{asset_kind} manifests must be signed (--key)"));
+        }
+
+        let has_license = config
+            .custom_fields
+            .as_ref()
+            .is_some_and(|fields| fields.get(LICENSE_ASSERTION_LABEL).is_some());
+        if self.require_license.contains(&asset_kind) && !has_license {
+            violations.push(format!(
+                "{asset_kind} manifests must record a license (--license)"
+            ));
+        }
+
+        if !self.hash_algorithms.is_empty() {
+            let allowed = |algorithm: &str| self.hash_algorithms.iter().any(|a| a == algorithm);
+            let signing_algorithm = hash::algorithm_to_string(&config.hash_alg);
+            if !allowed(signing_algorithm) {
+                violations.push(format!(
+                    "hash algorithm {signing_algorithm} is not allowed (allowed: {})",
+                    self.hash_algorithms.join(", ")
+                ));
+            }
+            for path in &config.paths {
+                if let (path, Some(algorithm)) = hash::split_path_algorithm(path)?
+                    && !allowed(algorithm.as_str())
+                {
+                    violations.push(format!(
+                        "hash algorithm {} of {} is not allowed",
+                        algorithm.as_str(),
+                        path.display()
+                    ));
+                }
+            }
+        }
+
+        Ok(violations)
+    }
+}
+
+/// Checks a manifest about to be created against the policy file.
+///
+/// Fails if the manifest would violate the policy, unless the policy was overridden, in
+/// which case the returned override is to be recorded in the manifest.
+pub fn check_creation(
+    config: &ManifestCreationConfig,
+    asset_kind: AssetKind,
+) -> Result<Option<PolicyOverride>> {
+    let path = policy_file();
+    let Some(policy) = CreationPolicy::load(&path)? else {
+        return Ok(None);
+    };
+
+    let violations = policy.violations(config, asset_kind)?;
+    if violations.is_empty() {
+        return Ok(None);
+    }
+
+    match override_reason() {
+        Some(reason) => {
+            for violation in &violations {
+                eprintln!("Warning: policy overridden: {violation}");
+            }
+            Ok(Some(PolicyOverride {
+                reason,
+                violations,
+                timestamp: OffsetDateTime::now_utc(),
+            }))
+        }
+        None => Err(Error::Validation(format!(
+            "Manifest violates the signing policy in {}: {}. Use --override-policy=<REASON> --acknowledge-policy-override to create it anyway",
+            path.display(),
+            violations.join("; ")
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest::ingest::IngestPolicy;
+    use atlas_c2pa_lib::cose::HashAlgorithm;
+
+    fn config(key_path: Option<PathBuf>, hash_alg: HashAlgorithm) -> ManifestCreationConfig {
+        ManifestCreationConfig {
+            paths: vec![PathBuf::from("model.onnx")],
+            ingredient_names: vec!["Model".to_string()],
+            ingredient_relationships: Vec::new(),
+            expand_archives: false,
+            profile_data: false,
+            max_sample_bytes: crate::manifest::profile::DEFAULT_MAX_SAMPLE_BYTES,
+            stream_connections: crate::manifest::object_store::DEFAULT_CONNECTIONS,
+            ingest_policy: IngestPolicy::default(),
+            path_base: None,
+            dataset_ids: Vec::new(),
+            name: "Model".to_string(),
+            author_org: None,
+            author_name: None,
+            description: None,
+            linked_manifests: None,
+            storage: None,
+            print: true,
+            output_encoding: "json".to_string(),
+            output_path: None,
+            key_path,
+            pq_key_path: None,
+            hash_alg,
+            valid_until: None,
+            created_at: None,
+            with_cc: false,
+            tpm: None,
+            software_type: None,
+            version: None,
+            encryption: None,
+            content_addressed_id: false,
+            force_new: false,
+            custom_fields: None,
+            inherited_assertions: vec![],
+        }
+    }
+
+    #[test]
+    fn test_load_policy() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("policy.yaml");
+        assert!(CreationPolicy::load(&path)?.is_none());
+
+        std::fs::write(
+            &path,
+            "require_signature: [model]\nhash_algorithms: [sha384, sha512]\n",
+        )?;
+        let policy = CreationPolicy::load(&path)?.unwrap();
+        assert_eq!(policy.require_signature, vec![AssetKind::Model]);
+        assert!(policy.require_license.is_empty());
+
+        std::fs::write(&path, "hash_algorithms: [md5]\n")?;
+        assert!(CreationPolicy::load(&path).is_err());
+        std::fs::write(&path, "require_signatures: [model]\n")?;
+        assert!(CreationPolicy::load(&path).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_policy_violations() -> Result<()> {
+        let policy = CreationPolicy {
+            require_signature: vec![AssetKind::Model],
+            require_license: vec![AssetKind::Model],
+            hash_algorithms: vec!["sha384".to_string()],
+        };
+
+        let unsigned = config(None, HashAlgorithm::Sha256);
+        assert_eq!(policy.violations(&unsigned, AssetKind::Model)?.len(), 3);
+        // Datasets only have to use an allowed hash algorithm
+        assert_eq!(policy.violations(&unsigned, AssetKind::Dataset)?.len(), 1);
+
+        let mut compliant = config(Some(PathBuf::from("key.pem")), HashAlgorithm::Sha384);
+        compliant.set_custom_field(
+            LICENSE_ASSERTION_LABEL,
+            serde_json::json!({ "identifier": "Apache-2.0" }),
+        )?;
+        assert!(policy.violations(&compliant, AssetKind::Model)?.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,632 @@
+//! Rendering of manifests and manifest lists.
+//!
+//! Every `list` command goes through [`print_manifest_list`], so they all share the same
+//! output formats, column selection, sorting and limits. `manifest show` goes through
+//! [`render_manifest`].
+
+use super::signer::SignatureFilter;
+use super::timestamp::display_local;
+use super::utils::manifest_type_to_str;
+use crate::error::{Error, Result};
+use crate::storage::traits::ManifestMetadata;
+use atlas_c2pa_lib::assertion::Assertion;
+use atlas_c2pa_lib::manifest::Manifest;
+use serde_json::{Map, Value};
+use std::fmt::Write;
+use time::OffsetDateTime;
+
+/// Output format of a manifest list
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ListFormat {
+    /// One descriptive line per manifest
+    #[default]
+    Text,
+    /// Aligned columns with a header
+    Table,
+    Csv,
+    Json,
+}
+
+/// A column of a manifest list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListColumn {
+    Id,
+    Name,
+    Type,
+    CreatedAt,
+    /// Expiry of the manifest, for lists of expiring manifests
+    ValidUntil,
+}
+
+impl ListColumn {
+    /// Columns shown when none are selected
+    pub const DEFAULT: &[ListColumn] = &[
+        ListColumn::Id,
+        ListColumn::Name,
+        ListColumn::Type,
+        ListColumn::CreatedAt,
+    ];
+
+    /// Name of the column in headers and JSON keys
+    pub fn name(self) -> &'static str {
+        match self {
+            ListColumn::Id => "id",
+            ListColumn::Name => "name",
+            ListColumn::Type => "type",
+            ListColumn::CreatedAt => "created_at",
+            ListColumn::ValidUntil => "valid_until",
+        }
+    }
+
+    fn value(self, row: &ListRow) -> String {
+        match self {
+            ListColumn::Id => row.metadata.id.clone(),
+            ListColumn::Name => row.metadata.name.clone(),
+            ListColumn::Type => manifest_type_to_str(&row.metadata.manifest_type).to_string(),
+            ListColumn::CreatedAt => row.metadata.created_at.clone(),
+            ListColumn::ValidUntil => row
+                .valid_until
+                .map(|until| until.to_string())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Sort key of a manifest list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListSort {
+    CreatedAt,
+    Name,
+    Type,
+}
+
+/// How a manifest list is rendered
+#[derive(Debug, Clone, Default)]
+pub struct ListOptions {
+    pub format: ListFormat,
+    /// Columns of table, CSV and JSON output, [`ListColumn::DEFAULT`] if empty
+    pub columns: Vec<ListColumn>,
+    /// Order of the list, the storage order if `None`
+    pub sort: Option<ListSort>,
+    pub reverse: bool,
+    /// Maximum number of manifests, applied after sorting
+    pub limit: Option<usize>,
+    /// Only manifests selected by this filter are listed
+    pub signature: SignatureFilter,
+}
+
+/// A manifest in a list
+#[derive(Debug, Clone)]
+pub struct ListRow {
+    pub metadata: ManifestMetadata,
+    pub valid_until: Option<OffsetDateTime>,
+}
+
+impl From<ManifestMetadata> for ListRow {
+    fn from(metadata: ManifestMetadata) -> Self {
+        ListRow {
+            metadata,
+            valid_until: None,
+        }
+    }
+}
+
+/// Sorts, limits and renders a manifest list
+pub fn render_manifest_list(mut rows: Vec<ListRow>, options: &ListOptions) -> Result<String> {
+    if let Some(sort) = options.sort {
+        // Stable, so manifests with equal keys keep their storage order
+        match sort {
+            ListSort::CreatedAt => rows.sort_by(|a, b| {
+                created_at_key(&a.metadata.created_at).cmp(&created_at_key(&b.metadata.created_at))
+            }),
+            ListSort::Name => rows.sort_by(|a, b| a.metadata.name.cmp(&b.metadata.name)),
+            ListSort::Type => {
+                rows.sort_by_key(|row| manifest_type_to_str(&row.metadata.manifest_type))
+            }
+        }
+    }
+    if options.reverse {
+        rows.reverse();
+    }
+    if let Some(limit) = options.limit {
+        rows.truncate(limit);
+    }
+
+    let columns = if options.columns.is_empty() {
+        ListColumn::DEFAULT
+    } else {
+        &options.columns
+    };
+
+    let mut out = String::new();
+    match options.format {
+        ListFormat::Text => {
+            for row in &rows {
+                let metadata = &row.metadata;
+                let _ = match row.valid_until {
+                    Some(until) => {
+                        let now = OffsetDateTime::now_utc();
+                        let status = if until <= now { "expired" } else { "expires" };
+                        writeln!(
+                            out,
+                            "Manifest: {} (ID: {}, Type: {:?}, {status} {until})",
+                            metadata.name, metadata.id, metadata.manifest_type
+                        )
+                    }
+                    None => writeln!(
+                        out,
+                        "Manifest: {} (ID: {}, Type: {:?}, Created: {})",
+                        metadata.name, metadata.id, metadata.manifest_type, metadata.created_at
+                    ),
+                };
+            }
+        }
+        ListFormat::Table => {
+            let cells: Vec<Vec<String>> = rows
+                .iter()
+                .map(|row| columns.iter().map(|column| column.value(row)).collect())
+                .collect();
+            let widths: Vec<usize> = columns
+                .iter()
+                .enumerate()
+                .map(|(i, column)| {
+                    cells
+                        .iter()
+                        .map(|row| row[i].chars().count())
+                        .chain([column.name().len()])
+                        .max()
+                        .unwrap_or(0)
+                })
+                .collect();
+
+            let header: Vec<String> = columns
+                .iter()
+                .map(|column| column.name().to_uppercase())
+                .collect();
+            for line in std::iter::once(&header).chain(&cells) {
+                let padded: Vec<String> = line
+                    .iter()
+                    .zip(&widths)
+                    .map(|(cell, width)| format!("{cell:<width$}"))
+                    .collect();
+                let _ = writeln!(out, "{}", padded.join("  ").trim_end());
+            }
+        }
+        ListFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            let csv_error =
+                |e: csv::Error| Error::Serialization(format!("Failed to write CSV: {e}"));
+            writer
+                .write_record(columns.iter().map(|column| column.name()))
+                .map_err(csv_error)?;
+            for row in &rows {
+                writer
+                    .write_record(columns.iter().map(|column| column.value(row)))
+                    .map_err(csv_error)?;
+            }
+            let bytes = writer
+                .into_inner()
+                .map_err(|e| Error::Serialization(format!("Failed to write CSV: {e}")))?;
+            out = String::from_utf8_lossy(&bytes).into_owned();
+        }
+        ListFormat::Json => {
+            let objects: Vec<Value> = rows
+                .iter()
+                .map(|row| {
+                    let object: Map<String, Value> = columns
+                        .iter()
+                        .map(|column| (column.name().to_string(), Value::String(column.value(row))))
+                        .collect();
+                    Value::Object(object)
+                })
+                .collect();
+            out = serde_json::to_string_pretty(&objects)?;
+            out.push('\n');
+        }
+    }
+    Ok(out)
+}
+
+/// Prints a manifest list, see [`render_manifest_list`]
+pub fn print_manifest_list(rows: Vec<ListRow>, options: &ListOptions) -> Result<()> {
+    print!("{}", render_manifest_list(rows, options)?);
+    Ok(())
+}
+
+/// Output format of a single manifest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ShowFormat {
+    /// Sectioned, human-readable layout
+    #[default]
+    Text,
+    /// The exact manifest content
+    Json,
+    /// The exact manifest content, needs the `yaml` feature
+    Yaml,
+    /// A few lines with the identity and counts of the manifest's parts
+    Summary,
+}
+
+/// How a manifest is shown
+#[derive(Debug, Clone, Default)]
+pub struct ShowOptions {
+    pub format: ShowFormat,
+    /// Field selectors, see [`select_field`]. When given, only the selected values are shown
+    pub fields: Vec<String>,
+}
+
+/// Renders a manifest, or the fields selected by `options`
+///
+/// With fields, text and summary output have one value per line (prefixed with the selector
+/// when there are several), while JSON and YAML output map each selector to its values.
+pub fn render_manifest(manifest: &Manifest, options: &ShowOptions) -> Result<String> {
+    if !options.fields.is_empty() {
+        let value = serde_json::to_value(manifest)?;
+        let mut selected = Vec::new();
+        for field in &options.fields {
+            let values = select_field(&value, field)?;
+            if values.is_empty() {
+                return Err(Error::Validation(format!(
+                    "Field '{field}' does not match anything in the manifest"
+                )));
+            }
+            selected.push((field, values));
+        }
+
+        return match options.format {
+            // Selectors are printed in the order they were given
+            ShowFormat::Text | ShowFormat::Summary => {
+                let mut out = String::new();
+                for (field, values) in &selected {
+                    for value in values {
+                        let text = match value {
+                            Value::String(text) => text.clone(),
+                            other => other.to_string(),
+                        };
+                        let _ = if selected.len() > 1 {
+                            writeln!(out, "{field}: {text}")
+                        } else {
+                            writeln!(out, "{text}")
+                        };
+                    }
+                }
+                Ok(out)
+            }
+            format => {
+                let object: Map<String, Value> = selected
+                    .into_iter()
+                    .map(|(field, values)| (field.clone(), Value::Array(values)))
+                    .collect();
+                serialize_document(&Value::Object(object), format)
+            }
+        };
+    }
+
+    match options.format {
+        ShowFormat::Text => render_details(manifest)
+            .map_err(|e| Error::Serialization(format!("Failed to render manifest: {e}"))),
+        ShowFormat::Summary => render_summary(manifest)
+            .map_err(|e| Error::Serialization(format!("Failed to render manifest: {e}"))),
+        format => serialize_document(manifest, format),
+    }
+}
+
+/// Selects values from a JSON document with a dotted path
+///
+/// Each segment names an object key and may end with `[]` to select every element of an
+/// array or `[N]` to select one. Paths that are missing in some elements select nothing
+/// for them.
+///
+/// # Examples
+///
+/// ```
+/// use atlas_cli::manifest::presentation::select_field;
+/// use serde_json::json;
+///
+/// let manifest = json!({
+///     "ingredients": [
+///         {"data": {"hash": "aa"}},
+///         {"data": {"hash": "bb"}}
+///     ]
+/// });
+/// assert_eq!(
+///     select_field(&manifest, "ingredients[].data.hash").unwrap(),
+///     vec![json!("aa"), json!("bb")]
+/// );
+/// assert_eq!(
+///     select_field(&manifest, "ingredients[1].data.hash").unwrap(),
+///     vec![json!("bb")]
+/// );
+/// ```
+pub fn select_field(value: &Value, path: &str) -> Result<Vec<Value>> {
+    let invalid = || Error::Validation(format!("Invalid field selector '{path}'"));
+
+    let mut current = vec![value];
+    for segment in path.split('.') {
+        let (key, mut indexes) = match segment.find('[') {
+            Some(start) => (&segment[..start], &segment[start..]),
+            None => (segment, ""),
+        };
+        if key.is_empty() && indexes.is_empty() {
+            return Err(invalid());
+        }
+        if !key.is_empty() {
+            current = current.into_iter().filter_map(|v| v.get(key)).collect();
+        }
+
+        while !indexes.is_empty() {
+            let end = indexes.find(']').ok_or_else(invalid)?;
+            let index = indexes.get(1..end).ok_or_else(invalid)?;
+            current = if index.is_empty() {
+                current
+                    .into_iter()
+                    .filter_map(Value::as_array)
+                    .flatten()
+                    .collect()
+            } else {
+                let index: usize = index.parse().map_err(|_| invalid())?;
+                current.into_iter().filter_map(|v| v.get(index)).collect()
+            };
+            indexes = &indexes[end + 1..];
+            if !indexes.is_empty() && !indexes.starts_with('[') {
+                return Err(invalid());
+            }
+        }
+    }
+    Ok(current.into_iter().cloned().collect())
+}
+
+fn serialize_document<T: serde::Serialize>(value: &T, format: ShowFormat) -> Result<String> {
+    match format {
+        ShowFormat::Yaml => {
+            #[cfg(feature = "yaml")]
+            {
+                serde_yaml::to_string(value)
+                    .map_err(|e| Error::Serialization(format!("Failed to serialize to YAML: {e}")))
+            }
+
+            #[cfg(not(feature = "yaml"))]
+            {
+                let _ = value;
+                Err(Error::Validation(
+                    "YAML output is not supported. Enable the 'yaml' feature.".to_string(),
+                ))
+            }
+        }
+        _ => {
+            let mut out = serde_json::to_string_pretty(value)?;
+            out.push('\n');
+            Ok(out)
+        }
+    }
+}
+
+fn render_summary(manifest: &Manifest) -> std::result::Result<String, std::fmt::Error> {
+    let mut out = String::new();
+    let assertions: Vec<&str> = manifest
+        .claim
+        .created_assertions
+        .iter()
+        .map(|assertion| match assertion {
+            Assertion::Action(_) => "Action",
+            Assertion::Ingredient(_) => "Ingredient",
+            Assertion::Hash(_) => "Hash",
+            Assertion::Metadata(_) => "Metadata",
+            Assertion::CreativeWork(_) => "CreativeWork",
+            Assertion::CustomAssertion(custom) => custom.label.as_str(),
+            Assertion::DoNotTrain(_) => "DoNotTrain",
+        })
+        .collect();
+
+    writeln!(out, "ID: {}", manifest.instance_id)?;
+    writeln!(out, "Title: {}", manifest.title)?;
+    writeln!(out, "Created: {}", display_local(manifest.created_at.0))?;
+    writeln!(
+        out,
+        "Signed: {}",
+        if manifest.claim.signature.is_some() {
+            "yes"
+        } else {
+            "no"
+        }
+    )?;
+    writeln!(
+        out,
+        "Assertions: {} ({})",
+        assertions.len(),
+        assertions.join(", ")
+    )?;
+    writeln!(out, "Ingredients: {}", manifest.ingredients.len())?;
+    writeln!(out, "Cross References: {}", manifest.cross_references.len())?;
+    Ok(out)
+}
+
+fn render_details(manifest: &Manifest) -> std::result::Result<String, std::fmt::Error> {
+    let mut out = String::new();
+
+    writeln!(out, "============ Manifest Details ============")?;
+    writeln!(out, "ID: {}", manifest.instance_id)?;
+    writeln!(out, "Title: {}", manifest.title)?;
+    writeln!(out, "Created: {}", display_local(manifest.created_at.0))?;
+    writeln!(out, "Claim Generator: {}", manifest.claim_generator)?;
+    writeln!(out, "Active: {}", manifest.is_active)?;
+
+    // Display claim details
+    writeln!(out, "\n------------ Claim Details -------------")?;
+    writeln!(out, "Claim ID: {}", manifest.claim.instance_id)?;
+    writeln!(
+        out,
+        "Claim Generated: {}",
+        display_local(manifest.claim.created_at.0)
+    )?;
+    writeln!(
+        out,
+        "Claim Generator: {}",
+        manifest.claim.claim_generator_info
+    )?;
+
+    if let Some(signature) = &manifest.claim.signature {
+        writeln!(out, "\nSignature: {signature}")?;
+    } else {
+        writeln!(out, "\nSignature: None (unsigned)")?;
+    }
+
+    // Display assertions
+    writeln!(out, "\n------------ Assertions -------------")?;
+    for (i, assertion) in manifest.claim.created_assertions.iter().enumerate() {
+        writeln!(out, "\nAssertion #{}", i + 1)?;
+        match assertion {
+            Assertion::CreativeWork(creative) => {
+                writeln!(out, "  Type: CreativeWork")?;
+                writeln!(out, "  Context: {}", creative.context)?;
+                writeln!(out, "  Creative Type: {}", creative.creative_type)?;
+
+                writeln!(out, "  Authors:")?;
+                for author in &creative.author {
+                    writeln!(out, "    - {} ({})", author.name, author.author_type)?;
+                }
+            }
+            Assertion::Action(action) => {
+                writeln!(out, "  Type: Action")?;
+                writeln!(out, "  Actions:")?;
+                for action in &action.actions {
+                    writeln!(out, "    - Action: {}", action.action)?;
+                    if let Some(agent) = &action.software_agent {
+                        writeln!(out, "      Software Agent: {agent}")?;
+                    }
+                    if let Some(source_type) = &action.digital_source_type {
+                        writeln!(out, "      Digital Source Type: {source_type}")?;
+                    }
+                    if let Some(params) = &action.parameters {
+                        writeln!(
+                            out,
+                            "      Parameters: {}",
+                            serde_json::to_string_pretty(params)
+                                .unwrap_or_else(|_| format!("{params:?}"))
+                        )?;
+                    }
+                }
+            }
+            _ => writeln!(out, "  Unknown assertion type")?,
+        }
+    }
+
+    // Display ingredients
+    writeln!(out, "\n------------ Ingredients -------------")?;
+    for (i, ingredient) in manifest.ingredients.iter().enumerate() {
+        writeln!(out, "\nIngredient #{}: {}", i + 1, ingredient.title)?;
+        writeln!(out, "  Document ID: {}", ingredient.document_id)?;
+        writeln!(out, "  Instance ID: {}", ingredient.instance_id)?;
+        writeln!(out, "  Format: {}", ingredient.format)?;
+        writeln!(out, "  Relationship: {}", ingredient.relationship)?;
+
+        writeln!(out, "  Data:")?;
+        writeln!(out, "    URL: {}", ingredient.data.url)?;
+        writeln!(out, "    Hash Algorithm: {}", ingredient.data.alg)?;
+        writeln!(out, "    Hash: {}", ingredient.data.hash)?;
+
+        writeln!(out, "    Data Types:")?;
+        for data_type in &ingredient.data.data_types {
+            writeln!(out, "      - {data_type:?}")?;
+        }
+
+        if let Some(linked) = &ingredient.linked_ingredient {
+            writeln!(out, "  Linked Ingredient: {linked:?}")?;
+        }
+
+        if let Some(key) = &ingredient.public_key {
+            writeln!(out, "  Public Key: {key:?}")?;
+        }
+    }
+
+    // Display cross-references if any
+    if !manifest.cross_references.is_empty() {
+        writeln!(out, "\n------------ Cross References -------------")?;
+        for (i, cross_ref) in manifest.cross_references.iter().enumerate() {
+            writeln!(out, "\nReference #{}", i + 1)?;
+            writeln!(out, "  URL: {}", cross_ref.manifest_url)?;
+            writeln!(out, "  Hash: {}", cross_ref.manifest_hash)?;
+        }
+    }
+
+    Ok(out)
+}
+
+// Backends record creation times as RFC 3339 or in the `time` crate's display format, so
+// they are compared as instants when they parse, and as text otherwise
+fn created_at_key(created_at: &str) -> (Option<OffsetDateTime>, &str) {
+    const DISPLAY_FORMAT: &[time::format_description::BorrowedFormatItem<'_>] = time::macros::format_description!(
+        "[year]-[month]-[day] [hour padding:none]:[minute]:[second].[subsecond] [offset_hour sign:mandatory]:[offset_minute]:[offset_second]"
+    );
+
+    let parsed = OffsetDateTime::parse(created_at, &time::format_description::well_known::Rfc3339)
+        .or_else(|_| OffsetDateTime::parse(created_at, DISPLAY_FORMAT))
+        .ok();
+    (parsed, created_at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::traits::ManifestType;
+
+    fn row(id: &str, name: &str, manifest_type: ManifestType, created_at: &str) -> ListRow {
+        ListRow::from(ManifestMetadata {
+            id: id.to_string(),
+            name: name.to_string(),
+            manifest_type,
+            created_at: created_at.to_string(),
+            signed: None,
+        })
+    }
+
+    #[test]
+    fn test_render_manifest_list() -> Result<()> {
+        let rows = vec![
+            row("m1", "Zeta", ManifestType::Model, "2024-03-01T00:00:00Z"),
+            row("d1", "Alpha", ManifestType::Dataset, "2024-01-01T00:00:00Z"),
+            row(
+                "m2",
+                "Beta, v2",
+                ManifestType::Model,
+                "2024-02-01T00:00:00+01:00",
+            ),
+        ];
+
+        let options = ListOptions {
+            format: ListFormat::Csv,
+            columns: vec![ListColumn::Id, ListColumn::Name],
+            sort: Some(ListSort::CreatedAt),
+            reverse: true,
+            limit: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(
+            render_manifest_list(rows.clone(), &options)?,
+            "id,name\nm1,Zeta\nm2,\"Beta, v2\"\n"
+        );
+
+        let options = ListOptions {
+            format: ListFormat::Table,
+            sort: Some(ListSort::Name),
+            ..Default::default()
+        };
+        let table = render_manifest_list(rows.clone(), &options)?;
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines[0], "ID  NAME      TYPE     CREATED_AT");
+        assert_eq!(lines[1], "d1  Alpha     Dataset  2024-01-01T00:00:00Z");
+        assert_eq!(lines.len(), 4);
+
+        let options = ListOptions {
+            format: ListFormat::Json,
+            columns: vec![ListColumn::Type],
+            sort: Some(ListSort::Type),
+            ..Default::default()
+        };
+        let json: Value = serde_json::from_str(&render_manifest_list(rows, &options)?)?;
+        assert_eq!(json[0]["type"], "Dataset");
+        assert!(json[0].get("id").is_none());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,618 @@
+//! Dataset profiles recorded as assertions.
+//!
+//! With `--profile-data`, dataset ingredients in supported formats are inspected at
+//! creation time and a summary of their contents (row counts, schema and column
+//! statistics) is recorded in a dataset profile assertion. This makes dataset manifests
+//! useful for data governance without having to open the data itself.
+//!
+//! Parquet files are profiled from their footer only, so profiling is cheap even for
+//! very large files. CSV, TSV and JSON Lines files have no such metadata; column names,
+//! types and null ratios are inferred from a sample of at most `max_sample_bytes` from
+//! the start of the file.
+
+use crate::error::{Error, Result};
+use crate::utils::long_path;
+use atlas_c2pa_lib::assertion::CustomAssertion;
+use parquet::basic::ConvertedType;
+use parquet::file::metadata::ParquetMetaDataReader;
+use parquet::file::statistics::Statistics;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// Label of the dataset profile assertion
+pub const DATASET_PROFILE_ASSERTION_LABEL: &str = "dataset.profile";
+
+/// Default bound on the bytes sampled from text dataset files
+pub const DEFAULT_MAX_SAMPLE_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Profile of a single dataset ingredient
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DatasetProfile {
+    /// Title of the profiled ingredient
+    pub ingredient: String,
+    /// Data format, e.g. `parquet`
+    pub format: String,
+    /// Number of rows, or of rows in the sample if only a sample was profiled
+    pub row_count: u64,
+    /// Number of bytes profiled, if the file was larger than the sample bound
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sampled_bytes: Option<u64>,
+    pub columns: Vec<ColumnProfile>,
+}
+
+/// Schema and statistics of a column
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColumnProfile {
+    pub name: String,
+    pub data_type: String,
+    pub nullable: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub null_count: Option<u64>,
+    /// Fraction of rows in which the column is null
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub null_ratio: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub distinct_count: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<Value>,
+}
+
+/// Profiles a dataset file if its format is supported, returning `None` otherwise.
+///
+/// Text formats are profiled from at most `max_sample_bytes` bytes of the file.
+pub fn profile_file(
+    path: &Path,
+    ingredient: &str,
+    max_sample_bytes: u64,
+) -> Result<Option<DatasetProfile>> {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("parquet") => profile_parquet(path, ingredient).map(Some),
+        Some("csv") => profile_delimited(path, ingredient, b',', max_sample_bytes).map(Some),
+        Some("tsv") => profile_delimited(path, ingredient, b'\t', max_sample_bytes).map(Some),
+        Some("jsonl") | Some("ndjson") => {
+            profile_json_lines(path, ingredient, max_sample_bytes).map(Some)
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Profiles a Parquet file from its footer metadata.
+///
+/// Column statistics are merged over all row groups; a statistic is only reported if
+/// every row group records it.
+pub fn profile_parquet(path: &Path, ingredient: &str) -> Result<DatasetProfile> {
+    let file = File::open(long_path(path))?;
+    let metadata = ParquetMetaDataReader::new()
+        .parse_and_finish(&file)
+        .map_err(|e| {
+            Error::Validation(format!(
+                "Failed to read Parquet footer of {}: {e}",
+                path.display()
+            ))
+        })?;
+
+    let file_metadata = metadata.file_metadata();
+    let mut columns: Vec<ColumnProfile> = file_metadata
+        .schema_descr()
+        .columns()
+        .iter()
+        .map(|column| ColumnProfile {
+            name: column.path().string(),
+            data_type: match column.converted_type() {
+                ConvertedType::NONE => column.physical_type().to_string(),
+                converted => converted.to_string(),
+            },
+            nullable: column.max_def_level() > 0,
+            null_count: Some(0),
+            null_ratio: None,
+            distinct_count: None,
+            min: None,
+            max: None,
+        })
+        .collect();
+
+    for (index, column) in columns.iter_mut().enumerate() {
+        let mut min_max_known = true;
+
+        for row_group in metadata.row_groups() {
+            let Some(statistics) = row_group.column(index).statistics() else {
+                column.null_count = None;
+                min_max_known = false;
+                continue;
+            };
+
+            column.null_count = column
+                .null_count
+                .zip(statistics.null_count_opt())
+                .map(|(total, count)| total + count);
+
+            // Distinct counts can't be merged across row groups
+            column.distinct_count = match metadata.num_row_groups() {
+                1 => statistics.distinct_count_opt(),
+                _ => None,
+            };
+
+            match statistic_values(statistics) {
+                Some((min, max)) if min_max_known => {
+                    column.min = Some(pick(column.min.take(), min, Ordering::Less));
+                    column.max = Some(pick(column.max.take(), max, Ordering::Greater));
+                }
+                _ => min_max_known = false,
+            }
+        }
+
+        if !min_max_known {
+            column.min = None;
+            column.max = None;
+        }
+    }
+
+    let row_count = u64::try_from(file_metadata.num_rows()).unwrap_or_default();
+    for column in &mut columns {
+        column.null_ratio = column
+            .null_count
+            .and_then(|nulls| null_ratio(nulls, row_count));
+    }
+
+    Ok(DatasetProfile {
+        ingredient: ingredient.to_string(),
+        format: "parquet".to_string(),
+        row_count,
+        sampled_bytes: None,
+        columns,
+    })
+}
+
+/// Profiles a CSV (or other delimited) file with a header row from a sample of the file
+pub fn profile_delimited(
+    path: &Path,
+    ingredient: &str,
+    delimiter: u8,
+    max_sample_bytes: u64,
+) -> Result<DatasetProfile> {
+    let (sample, sampled_bytes) = read_sample(path, max_sample_bytes)?;
+    let invalid =
+        |e: csv::Error| Error::Validation(format!("Failed to parse {}: {e}", path.display()));
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .from_reader(sample.as_slice());
+    let mut columns: Vec<ColumnStats> = reader
+        .headers()
+        .map_err(invalid)?
+        .iter()
+        .map(ColumnStats::new)
+        .collect();
+
+    let mut row_count = 0;
+    for record in reader.records() {
+        let record = record.map_err(invalid)?;
+        row_count += 1;
+        for (index, column) in columns.iter_mut().enumerate() {
+            column.observe(
+                record
+                    .get(index)
+                    .map_or(ValueType::Null, ValueType::of_text),
+            );
+        }
+    }
+
+    let format = if delimiter == b'\t' { "tsv" } else { "csv" };
+    Ok(text_profile(
+        ingredient,
+        format,
+        row_count,
+        sampled_bytes,
+        columns,
+    ))
+}
+
+/// Profiles a JSON Lines file of objects from a sample of the file. Columns are the
+/// top-level keys, in order of first appearance.
+pub fn profile_json_lines(
+    path: &Path,
+    ingredient: &str,
+    max_sample_bytes: u64,
+) -> Result<DatasetProfile> {
+    let (sample, sampled_bytes) = read_sample(path, max_sample_bytes)?;
+
+    let mut columns: Vec<ColumnStats> = Vec::new();
+    let mut row_count = 0;
+    for (number, line) in sample.split(|byte| *byte == b'\n').enumerate() {
+        if line.iter().all(u8::is_ascii_whitespace) {
+            continue;
+        }
+
+        let row: serde_json::Map<String, Value> = serde_json::from_slice(line).map_err(|e| {
+            Error::Validation(format!(
+                "Line {} of {} is not a JSON object: {e}",
+                number + 1,
+                path.display()
+            ))
+        })?;
+
+        // Keys first seen in this row were missing, i.e. null, in all earlier rows
+        for key in row.keys() {
+            if !columns.iter().any(|column| column.name == *key) {
+                let mut column = ColumnStats::new(key);
+                column.nulls = row_count;
+                columns.push(column);
+            }
+        }
+
+        row_count += 1;
+        for column in &mut columns {
+            column.observe(
+                row.get(&column.name)
+                    .map_or(ValueType::Null, ValueType::of_json),
+            );
+        }
+    }
+
+    Ok(text_profile(
+        ingredient,
+        "jsonl",
+        row_count,
+        sampled_bytes,
+        columns,
+    ))
+}
+
+/// Creates the dataset profile assertion
+pub fn profile_assertion(profiles: &[DatasetProfile]) -> Result<CustomAssertion> {
+    Ok(CustomAssertion {
+        label: DATASET_PROFILE_ASSERTION_LABEL.to_string(),
+        data: serde_json::json!({ "profiles": profiles }),
+    })
+}
+
+// Reads at most `max_bytes` from the start of a file. If the file is longer, the sample
+// ends at the last complete line and its length is returned along with it.
+fn read_sample(path: &Path, max_bytes: u64) -> Result<(Vec<u8>, Option<u64>)> {
+    let mut sample = Vec::new();
+    File::open(long_path(path))?
+        .take(max_bytes.saturating_add(1))
+        .read_to_end(&mut sample)?;
+
+    if sample.len() as u64 <= max_bytes {
+        return Ok((sample, None));
+    }
+
+    sample.truncate(max_bytes as usize);
+    let end = sample
+        .iter()
+        .rposition(|byte| *byte == b'\n')
+        .map_or(0, |newline| newline + 1);
+    sample.truncate(end);
+    Ok((sample, Some(end as u64)))
+}
+
+fn null_ratio(nulls: u64, rows: u64) -> Option<f64> {
+    (rows > 0).then(|| nulls as f64 / rows as f64)
+}
+
+fn text_profile(
+    ingredient: &str,
+    format: &str,
+    row_count: u64,
+    sampled_bytes: Option<u64>,
+    columns: Vec<ColumnStats>,
+) -> DatasetProfile {
+    DatasetProfile {
+        ingredient: ingredient.to_string(),
+        format: format.to_string(),
+        row_count,
+        sampled_bytes,
+        columns: columns
+            .into_iter()
+            .map(|column| ColumnProfile {
+                data_type: column.value_type.name().to_string(),
+                nullable: column.nulls > 0,
+                null_count: Some(column.nulls),
+                null_ratio: null_ratio(column.nulls, row_count),
+                distinct_count: None,
+                min: None,
+                max: None,
+                name: column.name,
+            })
+            .collect(),
+    }
+}
+
+// Type inferred for the values of a text column
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ValueType {
+    Null,
+    Boolean,
+    Integer,
+    Float,
+    String,
+    Array,
+    Object,
+    Mixed,
+}
+
+impl ValueType {
+    fn of_text(value: &str) -> Self {
+        let value = value.trim();
+        if value.is_empty() {
+            ValueType::Null
+        } else if value.parse::<i64>().is_ok() {
+            ValueType::Integer
+        } else if value.parse::<f64>().is_ok() {
+            ValueType::Float
+        } else if value.eq_ignore_ascii_case("true") || value.eq_ignore_ascii_case("false") {
+            ValueType::Boolean
+        } else {
+            ValueType::String
+        }
+    }
+
+    fn of_json(value: &Value) -> Self {
+        match value {
+            Value::Null => ValueType::Null,
+            Value::Bool(_) => ValueType::Boolean,
+            Value::Number(number) if number.is_f64() => ValueType::Float,
+            Value::Number(_) => ValueType::Integer,
+            Value::String(_) => ValueType::String,
+            Value::Array(_) => ValueType::Array,
+            Value::Object(_) => ValueType::Object,
+        }
+    }
+
+    fn merge(self, other: Self) -> Self {
+        match (self, other) {
+            (ValueType::Null, other) | (other, ValueType::Null) => other,
+            (a, b) if a == b => a,
+            (ValueType::Integer, ValueType::Float) | (ValueType::Float, ValueType::Integer) => {
+                ValueType::Float
+            }
+            _ => ValueType::Mixed,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            ValueType::Null => "null",
+            ValueType::Boolean => "boolean",
+            ValueType::Integer => "integer",
+            ValueType::Float => "float",
+            ValueType::String => "string",
+            ValueType::Array => "array",
+            ValueType::Object => "object",
+            ValueType::Mixed => "mixed",
+        }
+    }
+}
+
+struct ColumnStats {
+    name: String,
+    value_type: ValueType,
+    nulls: u64,
+}
+
+impl ColumnStats {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            value_type: ValueType::Null,
+            nulls: 0,
+        }
+    }
+
+    fn observe(&mut self, value_type: ValueType) {
+        if value_type == ValueType::Null {
+            self.nulls += 1;
+        }
+        self.value_type = self.value_type.merge(value_type);
+    }
+}
+
+// Min and max of a row group as JSON values, for types with a meaningful order
+fn statistic_values(statistics: &Statistics) -> Option<(Value, Value)> {
+    fn values<T: Clone + Into<Value>>(min: Option<&T>, max: Option<&T>) -> Option<(Value, Value)> {
+        Some((min?.clone().into(), max?.clone().into()))
+    }
+
+    match statistics {
+        Statistics::Boolean(s) => values(s.min_opt(), s.max_opt()),
+        Statistics::Int32(s) => values(s.min_opt(), s.max_opt()),
+        Statistics::Int64(s) => values(s.min_opt(), s.max_opt()),
+        Statistics::Float(s) => values(s.min_opt(), s.max_opt()),
+        Statistics::Double(s) => values(s.min_opt(), s.max_opt()),
+        Statistics::ByteArray(s) => {
+            let text = |value: &parquet::data_type::ByteArray| {
+                value
+                    .as_utf8()
+                    .ok()
+                    .map(|text| Value::from(text.to_string()))
+            };
+            Some((text(s.min_opt()?)?, text(s.max_opt()?)?))
+        }
+        // Legacy timestamps and fixed-length binaries (e.g. decimals) aren't reported
+        Statistics::Int96(_) | Statistics::FixedLenByteArray(_) => None,
+    }
+}
+
+// Keeps `current` unless `candidate` compares as `wanted` against it
+fn pick(current: Option<Value>, candidate: Value, wanted: Ordering) -> Value {
+    match current {
+        Some(current) if compare(&candidate, &current) != Some(wanted) => current,
+        _ => candidate,
+    }
+}
+
+fn compare(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => match (a.as_i64(), b.as_i64()) {
+            (Some(a), Some(b)) => Some(a.cmp(&b)),
+            _ => a.as_f64()?.partial_cmp(&b.as_f64()?),
+        },
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parquet::data_type::{ByteArray, ByteArrayType, Int64Type};
+    use parquet::file::properties::WriterProperties;
+    use parquet::file::writer::SerializedFileWriter;
+    use parquet::schema::parser::parse_message_type;
+    use std::sync::Arc;
+    use tempfile::tempdir;
+
+    // Writes one row group per entry of `row_groups`, each a list of (id, name) rows
+    fn write_parquet(path: &Path, row_groups: &[Vec<(i64, Option<&str>)>]) {
+        let schema = Arc::new(
+            parse_message_type(
+                "message schema { REQUIRED INT64 id; OPTIONAL BYTE_ARRAY name (UTF8); }",
+            )
+            .unwrap(),
+        );
+        let props = Arc::new(WriterProperties::builder().build());
+        let mut writer =
+            SerializedFileWriter::new(File::create(path).unwrap(), schema, props).unwrap();
+
+        for rows in row_groups {
+            let mut row_group = writer.next_row_group().unwrap();
+
+            let ids: Vec<i64> = rows.iter().map(|(id, _)| *id).collect();
+            let mut column = row_group.next_column().unwrap().unwrap();
+            column
+                .typed::<Int64Type>()
+                .write_batch(&ids, None, None)
+                .unwrap();
+            column.close().unwrap();
+
+            let names: Vec<ByteArray> = rows
+                .iter()
+                .filter_map(|(_, name)| name.map(ByteArray::from))
+                .collect();
+            let def_levels: Vec<i16> = rows.iter().map(|(_, name)| name.is_some() as i16).collect();
+            let mut column = row_group.next_column().unwrap().unwrap();
+            column
+                .typed::<ByteArrayType>()
+                .write_batch(&names, Some(&def_levels), None)
+                .unwrap();
+            column.close().unwrap();
+
+            row_group.close().unwrap();
+        }
+        writer.close().unwrap();
+    }
+
+    #[test]
+    fn test_profile_parquet() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("data.parquet");
+        write_parquet(
+            &path,
+            &[
+                vec![(5, Some("mango")), (2, None)],
+                vec![(9, Some("apple")), (7, Some("pear")), (3, None)],
+            ],
+        );
+
+        let profile = profile_file(&path, "data", DEFAULT_MAX_SAMPLE_BYTES)?.unwrap();
+        assert_eq!(profile.row_count, 5);
+        assert_eq!(profile.format, "parquet");
+
+        let id = &profile.columns[0];
+        assert_eq!(id.name, "id");
+        assert_eq!(id.data_type, "INT64");
+        assert!(!id.nullable);
+        assert_eq!(id.null_count, Some(0));
+        assert_eq!(id.min, Some(Value::from(2)));
+        assert_eq!(id.max, Some(Value::from(9)));
+
+        let name = &profile.columns[1];
+        assert_eq!(name.data_type, "UTF8");
+        assert!(name.nullable);
+        assert_eq!(name.null_count, Some(2));
+        assert_eq!(name.null_ratio, Some(0.4));
+        assert_eq!(name.min, Some(Value::from("apple")));
+        assert_eq!(name.max, Some(Value::from("pear")));
+
+        assert!(profile_file(&dir.path().join("data.bin"), "data", 1024)?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_profile_invalid_parquet() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("broken.parquet");
+        std::fs::write(&path, b"not a parquet file")?;
+
+        assert!(matches!(
+            profile_file(&path, "broken", DEFAULT_MAX_SAMPLE_BYTES),
+            Err(Error::Validation(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_profile_csv() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("data.csv");
+        std::fs::write(
+            &path,
+            "id,score,label,flag\n1,0.5,cat,true\n2,1,,false\n3,,dog\n",
+        )?;
+
+        let profile = profile_file(&path, "data", DEFAULT_MAX_SAMPLE_BYTES)?.unwrap();
+        assert_eq!(profile.row_count, 3);
+        assert_eq!(profile.sampled_bytes, None);
+
+        let summary: Vec<(&str, &str, Option<u64>)> = profile
+            .columns
+            .iter()
+            .map(|c| (c.name.as_str(), c.data_type.as_str(), c.null_count))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("id", "integer", Some(0)),
+                ("score", "float", Some(1)),
+                ("label", "string", Some(1)),
+                ("flag", "boolean", Some(1)),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_profile_json_lines_sample() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("data.jsonl");
+        let line = r#"{"text": "hello", "tokens": 2}"#;
+        let mut content = format!("{line}\n").repeat(10);
+        content.push_str(r#"{"text": null, "tokens": 1.5, "source": "web"}"#);
+        std::fs::write(&path, &content)?;
+
+        let profile = profile_file(&path, "data", DEFAULT_MAX_SAMPLE_BYTES)?.unwrap();
+        assert_eq!(profile.row_count, 11);
+        let source = &profile.columns[2];
+        assert_eq!(source.name, "source");
+        assert_eq!(source.null_count, Some(10));
+        assert_eq!(profile.columns[1].data_type, "float");
+
+        // A bound in the middle of the third line samples the first two lines only
+        let bound = (line.len() * 2 + 10) as u64;
+        let profile = profile_file(&path, "data", bound)?.unwrap();
+        assert_eq!(profile.row_count, 2);
+        assert_eq!(profile.sampled_bytes, Some((line.len() * 2 + 2) as u64));
+        assert_eq!(profile.columns.len(), 2);
+        assert_eq!(profile.columns[1].data_type, "integer");
+        Ok(())
+    }
+}
@@ -0,0 +1,467 @@
+//! Snapshots of Python environments for software manifests.
+//!
+//! With `--from-python-env`, every package installed in a virtualenv or conda environment
+//! becomes an ingredient of the software manifest, named `name==version`, and the pinned
+//! package list is recorded in a Python environment assertion.
+//!
+//! Where possible an ingredient is the package archive itself: a wheel from the pip wheel
+//! cache, or a package from the conda package cache. Otherwise it is the record of the
+//! installed package, the `RECORD` file of its `.dist-info` directory (which lists the hash
+//! of every installed file) or its `conda-meta` entry.
+
+use crate::error::{Error, Result};
+use crate::manifest::config::ManifestCreationConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Label of the Python environment assertion
+pub const PYTHON_ENV_ASSERTION_LABEL: &str = "software.python_environment";
+
+/// Kind of a Python environment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EnvironmentKind {
+    Venv,
+    Conda,
+}
+
+/// File hashed for a package
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PackageSource {
+    /// Wheel from the pip wheel cache
+    Wheel,
+    /// `RECORD` file of the installed package
+    Record,
+    /// Package archive from the conda package cache
+    CondaPackage,
+    /// `conda-meta` entry of the installed package
+    CondaMeta,
+}
+
+/// A package installed in a Python environment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PythonPackage {
+    pub name: String,
+    pub version: String,
+    /// Tool that installed the package, e.g. pip, uv or conda
+    pub installer: String,
+    pub source: PackageSource,
+    /// File hashed as the ingredient of the package
+    #[serde(skip)]
+    pub path: PathBuf,
+}
+
+/// Contents of the Python environment assertion
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PythonEnvironment {
+    pub prefix: String,
+    pub kind: EnvironmentKind,
+    pub python_version: Option<String>,
+    pub packages: Vec<PythonPackage>,
+}
+
+/// Returns the active Python environment: `VIRTUAL_ENV`, or else `CONDA_PREFIX`
+pub fn active_environment() -> Result<PathBuf> {
+    ["VIRTUAL_ENV", "CONDA_PREFIX"]
+        .iter()
+        .filter_map(|var| env::var_os(var))
+        .find(|prefix| !prefix.is_empty())
+        .map(PathBuf::from)
+        .ok_or_else(|| {
+            Error::Validation(
+                "No active Python environment: activate a virtualenv or conda environment, or pass its directory to --from-python-env".to_string(),
+            )
+        })
+}
+
+/// Returns the pip wheel cache: `$PIP_CACHE_DIR/wheels`, or `wheels` in the pip directory
+/// of the user cache
+pub fn default_wheel_cache() -> Option<PathBuf> {
+    let pip_cache = env::var_os("PIP_CACHE_DIR")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("XDG_CACHE_HOME").map(|dir| PathBuf::from(dir).join("pip")))
+        .or_else(|| env::var_os("HOME").map(|dir| PathBuf::from(dir).join(".cache/pip")))
+        .or_else(|| {
+            env::var_os("LOCALAPPDATA").map(|dir| PathBuf::from(dir).join("pip").join("Cache"))
+        })?;
+    Some(pip_cache.join("wheels"))
+}
+
+/// Normalizes a package name as in wheel file names: lowercase, with runs of `-`, `_` and
+/// `.` replaced by `_`
+fn normalize_name(name: &str) -> String {
+    let mut normalized = String::with_capacity(name.len());
+    for c in name.chars() {
+        if matches!(c, '-' | '_' | '.') {
+            if !normalized.ends_with('_') {
+                normalized.push('_');
+            }
+        } else {
+            normalized.push(c.to_ascii_lowercase());
+        }
+    }
+    normalized
+}
+
+/// Reads the name and version from the headers of a package `METADATA` file
+fn parse_metadata(metadata: &str) -> Option<(String, String)> {
+    let mut name = None;
+    let mut version = None;
+    for line in metadata.lines() {
+        // The headers end at the first empty line, followed by the description
+        if line.is_empty() {
+            break;
+        }
+        if let Some(value) = line.strip_prefix("Name:") {
+            name = Some(value.trim().to_string());
+        } else if let Some(value) = line.strip_prefix("Version:") {
+            version = Some(value.trim().to_string());
+        }
+    }
+    Some((name?, version?))
+}
+
+/// Finds the `site-packages` directories of an environment
+fn site_packages(prefix: &Path) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    for lib in ["lib", "lib64"] {
+        let Ok(entries) = fs::read_dir(prefix.join(lib)) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let dir = entry.path().join("site-packages");
+            if entry.file_name().to_string_lossy().starts_with("python") && dir.is_dir() {
+                dirs.push(dir);
+            }
+        }
+    }
+    // Windows environments
+    let dir = prefix.join("Lib").join("site-packages");
+    if dir.is_dir() {
+        dirs.push(dir);
+    }
+    dirs.sort();
+    dirs.dedup();
+    dirs
+}
+
+/// Indexes the wheels in a wheel cache by normalized name and version
+fn index_wheels(cache: &Path) -> HashMap<(String, String), PathBuf> {
+    let mut wheels = HashMap::new();
+    for entry in WalkDir::new(cache).into_iter().flatten() {
+        let file_name = entry.file_name().to_string_lossy();
+        let Some(stem) = file_name.strip_suffix(".whl") else {
+            continue;
+        };
+        let mut parts = stem.split('-');
+        if let (Some(name), Some(version)) = (parts.next(), parts.next()) {
+            wheels
+                .entry((normalize_name(name), version.to_string()))
+                .or_insert_with(|| entry.path().to_path_buf());
+        }
+    }
+    wheels
+}
+
+/// Lists the packages installed with pip or another Python installer, except those
+/// installed by conda
+fn dist_info_packages(
+    site_packages: &Path,
+    wheels: &HashMap<(String, String), PathBuf>,
+) -> Result<Vec<PythonPackage>> {
+    let mut packages = Vec::new();
+    for entry in fs::read_dir(site_packages)? {
+        let dir = entry?.path();
+        if dir.extension().is_none_or(|ext| ext != "dist-info") {
+            continue;
+        }
+        let Ok(metadata) = fs::read_to_string(dir.join("METADATA")) else {
+            continue;
+        };
+        let Some((name, version)) = parse_metadata(&metadata) else {
+            continue;
+        };
+        let installer = fs::read_to_string(dir.join("INSTALLER"))
+            .map(|installer| installer.trim().to_string())
+            .unwrap_or_else(|_| "unknown".to_string());
+        // Packages installed by conda are listed from conda-meta
+        if installer == "conda" {
+            continue;
+        }
+
+        let (source, path) = match wheels.get(&(normalize_name(&name), version.clone())) {
+            Some(wheel) => (PackageSource::Wheel, wheel.clone()),
+            None if dir.join("RECORD").is_file() => (PackageSource::Record, dir.join("RECORD")),
+            None => {
+                return Err(Error::Validation(format!(
+                    "Package {name}=={version} has no RECORD file in {}",
+                    dir.display()
+                )));
+            }
+        };
+        packages.push(PythonPackage {
+            name,
+            version,
+            installer,
+            source,
+            path,
+        });
+    }
+    Ok(packages)
+}
+
+/// Conda package caches: `CONDA_PKGS_DIRS`, and `pkgs` in the environment and in the conda
+/// installation it belongs to
+fn conda_package_dirs(prefix: &Path) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = env::var("CONDA_PKGS_DIRS")
+        .map(|dirs| dirs.split(',').map(PathBuf::from).collect())
+        .unwrap_or_default();
+    dirs.push(prefix.join("pkgs"));
+    // Named environments live in <installation>/envs/<name>
+    if let Some(root) = prefix
+        .parent()
+        .filter(|envs| envs.file_name().is_some_and(|name| name == "envs"))
+        .and_then(Path::parent)
+    {
+        dirs.push(root.join("pkgs"));
+    }
+    dirs
+}
+
+/// Lists the packages installed by conda
+fn conda_packages(prefix: &Path) -> Result<Vec<PythonPackage>> {
+    #[derive(Deserialize)]
+    struct CondaMeta {
+        name: String,
+        version: String,
+        #[serde(rename = "fn")]
+        file_name: Option<String>,
+    }
+
+    let package_dirs = conda_package_dirs(prefix);
+    let mut packages = Vec::new();
+    for entry in fs::read_dir(prefix.join("conda-meta"))? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+        let meta: CondaMeta = serde_json::from_str(&fs::read_to_string(&path)?).map_err(|e| {
+            Error::Validation(format!("Invalid conda metadata {}: {e}", path.display()))
+        })?;
+
+        let archive = meta.file_name.as_ref().and_then(|file_name| {
+            package_dirs
+                .iter()
+                .map(|dir| dir.join(file_name))
+                .find(|archive| archive.is_file())
+        });
+        let (source, path) = match archive {
+            Some(archive) => (PackageSource::CondaPackage, archive),
+            None => (PackageSource::CondaMeta, path),
+        };
+        packages.push(PythonPackage {
+            name: meta.name,
+            version: meta.version,
+            installer: "conda".to_string(),
+            source,
+            path,
+        });
+    }
+    Ok(packages)
+}
+
+/// Lists the packages installed in a virtualenv or conda environment, sorted by name.
+///
+/// Packages installed with pip are hashed from `wheel_cache` where it holds their wheel.
+///
+/// # Errors
+///
+/// Returns an error if `prefix` is neither a virtualenv nor a conda environment, or if
+/// its package metadata cannot be read.
+pub fn snapshot(prefix: &Path, wheel_cache: Option<&Path>) -> Result<PythonEnvironment> {
+    let kind = if prefix.join("conda-meta").is_dir() {
+        EnvironmentKind::Conda
+    } else if prefix.join("pyvenv.cfg").is_file() {
+        EnvironmentKind::Venv
+    } else {
+        return Err(Error::Validation(format!(
+            "{} is not a virtualenv or conda environment",
+            prefix.display()
+        )));
+    };
+
+    let site_packages = site_packages(prefix);
+    let python_version = site_packages.iter().find_map(|dir| {
+        dir.parent()?
+            .file_name()?
+            .to_str()?
+            .strip_prefix("python")
+            .map(str::to_string)
+    });
+
+    let wheels = wheel_cache.map(index_wheels).unwrap_or_default();
+    let mut packages = Vec::new();
+    for dir in &site_packages {
+        packages.extend(dist_info_packages(dir, &wheels)?);
+    }
+    if kind == EnvironmentKind::Conda {
+        packages.extend(conda_packages(prefix)?);
+    }
+    packages.sort_by_key(|package| (normalize_name(&package.name), package.version.clone()));
+
+    Ok(PythonEnvironment {
+        prefix: prefix.display().to_string(),
+        kind,
+        python_version,
+        packages,
+    })
+}
+
+/// Adds the packages of a Python environment to a software manifest configuration: one
+/// ingredient per package, and the environment assertion
+pub fn add_to_config(
+    environment: &PythonEnvironment,
+    config: &mut ManifestCreationConfig,
+) -> Result<()> {
+    for package in &environment.packages {
+        config.add_ingredient(
+            package.path.clone(),
+            format!("{}=={}", package.name, package.version),
+        )?;
+    }
+
+    config.set_custom_field(
+        PYTHON_ENV_ASSERTION_LABEL,
+        serde_json::to_value(environment)?,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn install(site_packages: &Path, name: &str, version: &str, installer: &str) {
+        let dir = site_packages.join(format!("{}-{version}.dist-info", normalize_name(name)));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("METADATA"),
+            format!(
+                "Metadata-Version: 2.1\nName: {name}\nVersion: {version}\n\nName: not a header\n"
+            ),
+        )
+        .unwrap();
+        fs::write(dir.join("INSTALLER"), format!("{installer}\n")).unwrap();
+        fs::write(
+            dir.join("RECORD"),
+            format!("{name}/__init__.py,sha256=abc,10\n"),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_normalize_name() {
+        assert_eq!(normalize_name("Typing-Extensions"), "typing_extensions");
+        assert_eq!(normalize_name("zope.interface"), "zope_interface");
+        assert_eq!(normalize_name("a-_-b"), "a_b");
+    }
+
+    #[test]
+    fn test_parse_metadata() {
+        let metadata = "Metadata-Version: 2.1\nName: numpy\nVersion: 1.26.4\nSummary: x\n";
+        assert_eq!(
+            parse_metadata(metadata),
+            Some(("numpy".to_string(), "1.26.4".to_string()))
+        );
+        assert_eq!(parse_metadata("Name: numpy\n\nVersion: 1.0\n"), None);
+    }
+
+    #[test]
+    fn test_snapshot_venv() {
+        let dir = tempdir().unwrap();
+        let prefix = dir.path().join("venv");
+        let site_packages = prefix.join("lib/python3.11/site-packages");
+        fs::create_dir_all(&site_packages).unwrap();
+        fs::write(prefix.join("pyvenv.cfg"), "version = 3.11.4\n").unwrap();
+        install(&site_packages, "Requests", "2.31.0", "pip");
+        install(&site_packages, "numpy", "1.26.4", "uv");
+
+        let cache = dir.path().join("wheels/ab/cd");
+        fs::create_dir_all(&cache).unwrap();
+        let wheel = cache.join("requests-2.31.0-py3-none-any.whl");
+        fs::write(&wheel, "wheel").unwrap();
+        // A different version in the cache is not used
+        fs::write(cache.join("numpy-1.0.0-cp311-linux_x86_64.whl"), "wheel").unwrap();
+
+        let environment = snapshot(&prefix, Some(&dir.path().join("wheels"))).unwrap();
+        assert_eq!(environment.kind, EnvironmentKind::Venv);
+        assert_eq!(environment.python_version.as_deref(), Some("3.11"));
+
+        let packages = &environment.packages;
+        assert_eq!(packages.len(), 2);
+        assert_eq!(packages[0].name, "numpy");
+        assert_eq!(packages[0].installer, "uv");
+        assert_eq!(packages[0].source, PackageSource::Record);
+        assert!(packages[0].path.ends_with("numpy-1.26.4.dist-info/RECORD"));
+        assert_eq!(packages[1].name, "Requests");
+        assert_eq!(packages[1].source, PackageSource::Wheel);
+        assert_eq!(packages[1].path, wheel);
+    }
+
+    #[test]
+    fn test_snapshot_conda() {
+        let dir = tempdir().unwrap();
+        let prefix = dir.path().join("miniconda/envs/train");
+        let site_packages = prefix.join("lib/python3.12/site-packages");
+        fs::create_dir_all(&site_packages).unwrap();
+        fs::create_dir_all(prefix.join("conda-meta")).unwrap();
+        install(&site_packages, "numpy", "2.0.0", "conda");
+        install(&site_packages, "wandb", "0.17.0", "pip");
+        for (name, version) in [("numpy", "2.0.0"), ("python", "3.12.4")] {
+            fs::write(
+                prefix.join(format!("conda-meta/{name}-{version}-0.json")),
+                format!(
+                    r#"{{"name": "{name}", "version": "{version}", "fn": "{name}-{version}-0.conda"}}"#
+                ),
+            )
+            .unwrap();
+        }
+        let pkgs = dir.path().join("miniconda/pkgs");
+        fs::create_dir_all(&pkgs).unwrap();
+        fs::write(pkgs.join("numpy-2.0.0-0.conda"), "package").unwrap();
+
+        let environment = snapshot(&prefix, None).unwrap();
+        assert_eq!(environment.kind, EnvironmentKind::Conda);
+
+        let sources: Vec<_> = environment
+            .packages
+            .iter()
+            .map(|package| {
+                (
+                    package.name.as_str(),
+                    package.installer.as_str(),
+                    package.source,
+                )
+            })
+            .collect();
+        assert_eq!(
+            sources,
+            [
+                ("numpy", "conda", PackageSource::CondaPackage),
+                ("python", "conda", PackageSource::CondaMeta),
+                ("wandb", "pip", PackageSource::Record),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_snapshot_not_an_environment() {
+        let dir = tempdir().unwrap();
+        assert!(snapshot(dir.path(), None).is_err());
+    }
+}
@@ -0,0 +1,649 @@
+//! Redaction of assertions.
+//!
+//! Before a manifest is shared with external partners, assertions holding internal details
+//! can be redacted. `manifest redact` replaces the content of each selected assertion with
+//! a placeholder holding only a salted hash of the removed content, so whoever has the
+//! original can still show it belongs to the manifest, while the hash reveals nothing about
+//! content that could be guessed. A redaction record lists the redacted assertions and
+//! their placeholders.
+//!
+//! Signing gives each redactable assertion a random salt, recorded in the assertion salts
+//! assertion, and the signature covers the placeholder of each salted assertion rather than
+//! its content, see [`commit_assertions`]. Redacting therefore keeps the original
+//! signature. Manifests signed before assertions were salted have to be re-signed when
+//! redacted.
+//!
+//! Redacting changes the reviewed content, so approvals given before the redaction no
+//! longer count towards release readiness.
+
+use crate::error::{Error, Result};
+use crate::hash;
+use crate::manifest::approval;
+use crate::manifest::canonical;
+use crate::manifest::claims;
+use crate::manifest::encryption::PROTECTED_LABELS;
+use crate::manifest::signer;
+use crate::signing;
+use crate::storage::traits::StorageBackend;
+use atlas_c2pa_lib::assertion::{Assertion, CustomAssertion};
+use atlas_c2pa_lib::claim::ClaimV2;
+use atlas_c2pa_lib::cose::HashAlgorithm;
+use atlas_c2pa_lib::manifest::Manifest;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use time::OffsetDateTime;
+
+/// Label of the assertion recording the redactions of a manifest
+pub const REDACTION_ASSERTION_LABEL: &str = "redaction";
+
+/// Label of the assertion holding the salts of the redactable assertions of a claim
+pub const ASSERTION_SALTS_ASSERTION_LABEL: &str = "assertion_salts";
+
+/// Key under which the placeholder replaces the content of a redacted assertion
+pub const REDACTED_PLACEHOLDER_KEY: &str = "atlas_redacted";
+
+// Length of the salt of each assertion, in bytes
+const SALT_LENGTH: usize = 16;
+
+/// Placeholder left in place of the content of a redacted assertion
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RedactedPlaceholder {
+    /// Hash algorithm of `hash`
+    pub alg: String,
+    /// Hash of the salt followed by the deterministic CBOR encoding of the removed content
+    pub hash: String,
+}
+
+/// Contents of the redaction record
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedactionRecord {
+    /// Labels of the redacted assertions
+    pub labels: Vec<String>,
+    /// Placeholders left by the redaction, empty for redactions made before assertions
+    /// were salted
+    #[serde(default)]
+    pub placeholders: Vec<RedactedPlaceholder>,
+    pub reason: Option<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub timestamp: OffsetDateTime,
+}
+
+/// Contents of the assertion salts assertion
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssertionSalts {
+    /// Hash algorithm of the placeholders committed to by the signature
+    pub alg: String,
+    /// Hex-encoded salt of the assertions with each label, dropped when they are redacted
+    pub salts: BTreeMap<String, String>,
+}
+
+/// Returns the placeholder of a redacted assertion, if the content was redacted
+pub fn placeholder(data: &serde_json::Value) -> Option<RedactedPlaceholder> {
+    data.get(REDACTED_PLACEHOLDER_KEY)
+        .and_then(|value| serde_json::from_value(value.clone()).ok())
+}
+
+/// Computes the placeholder of content with its hex-encoded salt
+pub fn salted_placeholder(
+    salt: &str,
+    content: &serde_json::Value,
+    hash_alg: &HashAlgorithm,
+) -> Result<RedactedPlaceholder> {
+    let mut input =
+        hex::decode(salt).map_err(|e| Error::Manifest(format!("Invalid assertion salt: {e}")))?;
+    // Deterministic CBOR, so that the hash doesn't depend on the order of JSON object keys
+    input.extend(canonical::to_vec(content)?);
+    Ok(RedactedPlaceholder {
+        alg: hash::algorithm_to_string(hash_alg).to_string(),
+        hash: hash::calculate_hash_with_algorithm(&input, hash_alg),
+    })
+}
+
+/// Checks that `content` is the content that was removed from a redacted assertion, given
+/// the salt of the assertion in the original manifest, see [`assertion_salts`]
+pub fn matches_placeholder(
+    placeholder: &RedactedPlaceholder,
+    salt: &str,
+    content: &serde_json::Value,
+) -> Result<bool> {
+    let hash_alg = hash::parse_algorithm(&placeholder.alg)?;
+    Ok(salted_placeholder(salt, content, &hash_alg)?.hash == placeholder.hash)
+}
+
+/// Returns the assertion salts of a claim, or `None` if it was signed before assertions
+/// were salted
+pub fn assertion_salts(claim: &ClaimV2) -> Result<Option<AssertionSalts>> {
+    claim
+        .created_assertions
+        .iter()
+        .find_map(|assertion| match assertion {
+            Assertion::CustomAssertion(custom)
+                if custom.label == ASSERTION_SALTS_ASSERTION_LABEL =>
+            {
+                Some(custom.data.clone())
+            }
+            _ => None,
+        })
+        .map(|data| {
+            serde_json::from_value(data)
+                .map_err(|e| Error::Manifest(format!("Invalid assertion salts assertion: {e}")))
+        })
+        .transpose()
+}
+
+// Replaces the assertion salts assertion of a claim in place, or adds it
+fn set_assertion_salts(claim: &mut ClaimV2, salts: &AssertionSalts) -> Result<()> {
+    let assertion = Assertion::CustomAssertion(CustomAssertion {
+        label: ASSERTION_SALTS_ASSERTION_LABEL.to_string(),
+        data: serde_json::to_value(salts)?,
+    });
+    let assertions = &mut claim.created_assertions;
+    match assertions.iter_mut().find(|assertion| {
+        matches!(assertion, Assertion::CustomAssertion(custom) if custom.label == ASSERTION_SALTS_ASSERTION_LABEL)
+    }) {
+        Some(existing) => *existing = assertion,
+        None => assertions.push(assertion),
+    }
+    Ok(())
+}
+
+fn is_redactable(label: &str) -> bool {
+    !PROTECTED_LABELS.contains(&label) && label != REDACTION_ASSERTION_LABEL
+}
+
+/// Gives each unredacted, redactable assertion of a claim without a salt a random one.
+///
+/// Called before signing, so that the claim keeps a single salts assertion and signing
+/// twice leaves salts that were already given unchanged.
+pub fn add_assertion_salts(claim: &mut ClaimV2, hash_alg: &HashAlgorithm) -> Result<()> {
+    let existing = assertion_salts(claim)?;
+    let mut changed = existing.is_none();
+    let mut salts = existing.unwrap_or_else(|| AssertionSalts {
+        alg: hash::algorithm_to_string(hash_alg).to_string(),
+        salts: BTreeMap::new(),
+    });
+
+    for assertion in &claim.created_assertions {
+        let Assertion::CustomAssertion(custom) = assertion else {
+            continue;
+        };
+        if !is_redactable(&custom.label)
+            || placeholder(&custom.data).is_some()
+            || salts.salts.contains_key(&custom.label)
+        {
+            continue;
+        }
+        let mut salt = [0u8; SALT_LENGTH];
+        openssl::rand::rand_bytes(&mut salt)
+            .map_err(|e| Error::Signing(format!("Failed to generate assertion salt: {e}")))?;
+        salts.salts.insert(custom.label.clone(), hex::encode(salt));
+        changed = true;
+    }
+
+    if changed {
+        set_assertion_salts(claim, &salts)?;
+    }
+    Ok(())
+}
+
+/// Prepares a claim for encoding as a signing input: the content of each salted assertion
+/// is replaced with its placeholder, and the assertion salts and redaction records are left
+/// out, so that redacting doesn't change the signing input.
+///
+/// Returns false, leaving the claim unchanged, for claims signed before assertions were
+/// salted.
+pub(crate) fn commit_assertions(claim: &mut ClaimV2) -> Result<bool> {
+    let Some(salts) = assertion_salts(claim)? else {
+        return Ok(false);
+    };
+    let hash_alg = hash::parse_algorithm(&salts.alg)?;
+
+    claim.created_assertions.retain(|assertion| {
+        !matches!(assertion, Assertion::CustomAssertion(custom)
+            if custom.label == ASSERTION_SALTS_ASSERTION_LABEL || custom.label == REDACTION_ASSERTION_LABEL)
+    });
+    for assertion in claim.created_assertions.iter_mut() {
+        let Assertion::CustomAssertion(custom) = assertion else {
+            continue;
+        };
+        if placeholder(&custom.data).is_some() {
+            continue;
+        }
+        if let Some(salt) = salts.salts.get(&custom.label) {
+            let placeholder = salted_placeholder(salt, &custom.data, &hash_alg)?;
+            custom.data = serde_json::json!({ REDACTED_PLACEHOLDER_KEY: placeholder });
+        }
+    }
+    Ok(true)
+}
+
+/// Returns the redaction records of a manifest, in the order the redactions were made
+pub fn redactions(manifest: &Manifest) -> Result<Vec<RedactionRecord>> {
+    let assertions = &claims::signed_claim(manifest).created_assertions;
+
+    assertions
+        .iter()
+        .filter_map(|assertion| match assertion {
+            Assertion::CustomAssertion(custom) if custom.label == REDACTION_ASSERTION_LABEL => {
+                Some(
+                    serde_json::from_value(custom.data.clone())
+                        .map_err(|e| Error::Manifest(format!("Invalid redaction assertion: {e}"))),
+                )
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Checks the redacted assertions of a manifest against its redaction records: each
+/// placeholder in the signed claim must be recorded, and each recorded placeholder must
+/// still be in the claim. Records of redactions made before assertions were salted are
+/// checked by label only.
+pub fn check_redactions(manifest: &Manifest) -> Result<()> {
+    let records = redactions(manifest)?;
+    let redacted: Vec<(&str, RedactedPlaceholder)> = claims::signed_claim(manifest)
+        .created_assertions
+        .iter()
+        .filter_map(|assertion| match assertion {
+            Assertion::CustomAssertion(custom) => {
+                placeholder(&custom.data).map(|placeholder| (custom.label.as_str(), placeholder))
+            }
+            _ => None,
+        })
+        .collect();
+
+    for (label, placeholder) in &redacted {
+        let recorded = records.iter().any(|record| {
+            record.labels.iter().any(|recorded| recorded == label)
+                && (record.placeholders.is_empty() || record.placeholders.contains(placeholder))
+        });
+        if !recorded {
+            return Err(Error::Validation(format!(
+                "The {label} assertion of manifest {} is redacted without a redaction record",
+                manifest.instance_id
+            )));
+        }
+    }
+    for placeholder in records.iter().flat_map(|record| &record.placeholders) {
+        if !redacted.iter().any(|(_, redacted)| redacted == placeholder) {
+            return Err(Error::Validation(format!(
+                "A redaction record of manifest {} lists a placeholder ({}) missing from the claim",
+                manifest.instance_id, placeholder.hash
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Replaces the content of the assertions labelled `labels` with their salted placeholders,
+/// returning the placeholders.
+///
+/// Fails if a label is missing, already redacted, protected (see [`PROTECTED_LABELS`]) or
+/// has no salt.
+pub fn redact_assertions(
+    assertions: &mut [Assertion],
+    labels: &[String],
+    salts: &AssertionSalts,
+) -> Result<Vec<RedactedPlaceholder>> {
+    let hash_alg = hash::parse_algorithm(&salts.alg)?;
+    let mut placeholders = Vec::new();
+    for label in labels {
+        if !is_redactable(label) {
+            return Err(Error::Validation(format!(
+                "The {label} assertion is needed for verification and can't be redacted"
+            )));
+        }
+
+        let mut found = false;
+        for assertion in assertions.iter_mut() {
+            let Assertion::CustomAssertion(custom) = assertion else {
+                continue;
+            };
+            if &custom.label != label {
+                continue;
+            }
+            if placeholder(&custom.data).is_some() {
+                return Err(Error::Validation(format!(
+                    "The {label} assertion is already redacted"
+                )));
+            }
+            let Some(salt) = salts.salts.get(label) else {
+                return Err(Error::Validation(format!(
+                    "The {label} assertion has no salt and can't be redacted"
+                )));
+            };
+            found = true;
+            let placeholder = salted_placeholder(salt, &custom.data, &hash_alg)?;
+            custom.data = serde_json::json!({ REDACTED_PLACEHOLDER_KEY: placeholder });
+            placeholders.push(placeholder);
+        }
+        if !found {
+            return Err(Error::Validation(format!(
+                "The manifest has no {label} assertion to redact"
+            )));
+        }
+    }
+    Ok(placeholders)
+}
+
+/// Redacts assertions of a stored manifest and records the redaction.
+///
+/// The original signature is kept, since it covers the placeholders of the salted
+/// assertions. Manifests signed before assertions were salted are re-signed with
+/// `key_path`, and can't be redacted without it. Returns the ID under which the redacted
+/// manifest was stored.
+pub fn redact_manifest(
+    manifest_id: &str,
+    storage: &dyn StorageBackend,
+    labels: &[String],
+    reason: Option<String>,
+    key_path: Option<PathBuf>,
+    hash_alg: HashAlgorithm,
+) -> Result<String> {
+    if labels.is_empty() {
+        return Err(Error::Validation(
+            "Select at least one assertion to redact with --assertion".to_string(),
+        ));
+    }
+    let mut manifest = storage.retrieve_manifest(manifest_id)?;
+    if !approval::approvals(&manifest)?.is_empty() {
+        eprintln!("Warning: approvals of manifest {manifest_id} don't cover the redacted content");
+    }
+
+    let salted = assertion_salts(&manifest.claim)?.is_some();
+    let private_key = match (manifest.claim.signature.is_some() && !salted, key_path) {
+        (false, _) => None,
+        (true, Some(key_path)) => {
+            eprintln!(
+                "Warning: manifest {manifest_id} was signed before its assertions were salted, so it is re-signed with {}",
+                key_path.display()
+            );
+            manifest.claim.signature = None;
+            // Signed with the loaded key, since a key from stdin can only be read once
+            Some(signing::load_private_key(&key_path)?)
+        }
+        (true, None) => {
+            return Err(Error::Validation(format!(
+                "Manifest {manifest_id} was signed before its assertions were salted, so redacting it breaks its signature; re-sign it with --key"
+            )));
+        }
+    };
+    if manifest.claim.signature.is_none() {
+        add_assertion_salts(&mut manifest.claim, &hash_alg)?;
+    }
+    let mut salts = assertion_salts(&manifest.claim)?.expect("added above");
+
+    let placeholders = redact_assertions(&mut manifest.claim.created_assertions, labels, &salts)?;
+    if let Some(claim_v2) = manifest.claim_v2.as_mut() {
+        redact_assertions(&mut claim_v2.created_assertions, labels, &salts)?;
+    }
+
+    // The salts of redacted assertions would only help guess their content
+    for label in labels {
+        salts.salts.remove(label);
+    }
+    set_assertion_salts(&mut manifest.claim, &salts)?;
+
+    let record = Assertion::CustomAssertion(CustomAssertion {
+        label: REDACTION_ASSERTION_LABEL.to_string(),
+        data: serde_json::to_value(RedactionRecord {
+            labels: labels.to_vec(),
+            placeholders,
+            reason,
+            timestamp: OffsetDateTime::now_utc(),
+        })?,
+    });
+    manifest.claim.created_assertions.push(record.clone());
+    if let Some(claim_v2) = manifest.claim_v2.as_mut() {
+        claim_v2.created_assertions.push(record);
+    }
+
+    if let Some(private_key) = private_key {
+        signer::sign_with_key(&mut manifest, &private_key, &hash_alg)?;
+    }
+    storage.store_manifest(&manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest::common::AssetKind;
+    use crate::tests::common::{
+        MockStorageBackend, create_test_ingredient_internal, create_test_manifest_internal,
+    };
+    use crate::utils::safe_create_file;
+    use atlas_c2pa_lib::asset_type::AssetType;
+    use atlas_c2pa_lib::datetime_wrapper::OffsetDateTimeWrapper;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    fn claim() -> ClaimV2 {
+        ClaimV2 {
+            instance_id: "urn:c2pa:claim".to_string(),
+            ingredients: vec![],
+            created_assertions: vec![
+                Assertion::CustomAssertion(CustomAssertion {
+                    label: "training_config".to_string(),
+                    data: serde_json::json!({ "cluster": "internal-a100-pool" }),
+                }),
+                Assertion::CustomAssertion(CustomAssertion {
+                    label: approval::APPROVAL_ASSERTION_LABEL.to_string(),
+                    data: serde_json::json!({}),
+                }),
+            ],
+            claim_generator_info: "test".to_string(),
+            signature: None,
+            created_at: OffsetDateTimeWrapper(OffsetDateTime::now_utc()),
+        }
+    }
+
+    #[test]
+    fn test_redact_assertions() -> Result<()> {
+        let mut claim = claim();
+        add_assertion_salts(&mut claim, &HashAlgorithm::Sha384)?;
+        let salts = assertion_salts(&claim)?.unwrap();
+        let salt = &salts.salts["training_config"];
+        assert!(!salts.salts.contains_key(approval::APPROVAL_ASSERTION_LABEL));
+
+        let original = serde_json::json!({ "cluster": "internal-a100-pool" });
+        let placeholders = redact_assertions(
+            &mut claim.created_assertions,
+            &["training_config".to_string()],
+            &salts,
+        )?;
+
+        let Assertion::CustomAssertion(custom) = &claim.created_assertions[0] else {
+            panic!("expected a custom assertion");
+        };
+        assert!(custom.data.get("cluster").is_none());
+        let placeholder = placeholder(&custom.data).unwrap();
+        assert_eq!(placeholder.alg, "sha384");
+        assert_eq!(placeholders, [placeholder.clone()]);
+        assert!(matches_placeholder(&placeholder, salt, &original)?);
+        assert!(!matches_placeholder(
+            &placeholder,
+            salt,
+            &serde_json::json!({ "cluster": "other" })
+        )?);
+
+        // Without the salt, guessing the content doesn't reveal it
+        assert!(!matches_placeholder(
+            &placeholder,
+            &hex::encode([0u8; SALT_LENGTH]),
+            &original
+        )?);
+
+        // Redacting twice would lose the hash of the original content
+        assert!(
+            redact_assertions(
+                &mut claim.created_assertions,
+                &["training_config".to_string()],
+                &salts
+            )
+            .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_redact_missing_or_protected_assertions() -> Result<()> {
+        let mut claim = claim();
+        add_assertion_salts(&mut claim, &HashAlgorithm::Sha384)?;
+        let salts = assertion_salts(&claim)?.unwrap();
+        for label in [
+            "missing",
+            approval::APPROVAL_ASSERTION_LABEL,
+            ASSERTION_SALTS_ASSERTION_LABEL,
+        ] {
+            assert!(
+                redact_assertions(&mut claim.created_assertions, &[label.to_string()], &salts)
+                    .is_err()
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_redaction_keeps_signing_input() -> Result<()> {
+        let mut claim = claim();
+        add_assertion_salts(&mut claim, &HashAlgorithm::Sha256)?;
+        let input = canonical::claim_signing_input(&claim)?;
+
+        // Salting again keeps the salts already given
+        add_assertion_salts(&mut claim, &HashAlgorithm::Sha256)?;
+        assert_eq!(canonical::claim_signing_input(&claim)?, input);
+
+        let mut salts = assertion_salts(&claim)?.unwrap();
+        let mut redacted = claim.clone();
+        let placeholders = redact_assertions(
+            &mut redacted.created_assertions,
+            &["training_config".to_string()],
+            &salts,
+        )?;
+        salts.salts.remove("training_config");
+        set_assertion_salts(&mut redacted, &salts)?;
+        redacted
+            .created_assertions
+            .push(Assertion::CustomAssertion(CustomAssertion {
+                label: REDACTION_ASSERTION_LABEL.to_string(),
+                data: serde_json::to_value(RedactionRecord {
+                    labels: vec!["training_config".to_string()],
+                    placeholders,
+                    reason: None,
+                    timestamp: OffsetDateTime::now_utc(),
+                })?,
+            }));
+        assert_eq!(canonical::claim_signing_input(&redacted)?, input);
+
+        // Editing the content of a salted assertion changes the signing input
+        let Assertion::CustomAssertion(custom) = &mut claim.created_assertions[0] else {
+            panic!("expected a custom assertion");
+        };
+        custom.data = serde_json::json!({ "cluster": "other" });
+        assert_ne!(canonical::claim_signing_input(&claim)?, input);
+        Ok(())
+    }
+
+    #[test]
+    fn test_redaction_keeps_original_signature() -> Result<()> {
+        use crate::manifest::common::verify_manifest;
+        use crate::manifest::signer::{key_fingerprint, signing_key_fingerprint};
+        use crate::signing::test_utils::generate_temp_key;
+
+        let dir = tempdir()?;
+        let model_path = dir.path().join("model.onnx");
+        safe_create_file(&model_path, false)?.write_all(b"mock model data")?;
+        let ingredient = create_test_ingredient_internal(
+            &model_path,
+            "Test Model",
+            AssetType::ModelOnnx,
+            "application/onnx",
+        )?;
+        let mut manifest = create_test_manifest_internal(
+            "redacted".to_string(),
+            vec![ingredient],
+            "Model",
+            AssetKind::Model,
+        )?;
+        let original = serde_json::json!({ "cluster": "internal-a100-pool" });
+        let config = Assertion::CustomAssertion(CustomAssertion {
+            label: "training_config".to_string(),
+            data: original.clone(),
+        });
+        manifest.claim.created_assertions.push(config.clone());
+        if let Some(claim) = manifest.claim_v2.as_mut() {
+            claim.created_assertions.push(config);
+        }
+        let (key, _key_dir) = generate_temp_key()?;
+        crate::manifest::signer::sign_with_key(&mut manifest, &key, &HashAlgorithm::Sha384)?;
+        let signature = manifest.claim.signature.clone();
+        let salt = assertion_salts(&manifest.claim)?.unwrap().salts["training_config"].clone();
+
+        let storage = MockStorageBackend::new(manifest);
+        redact_manifest(
+            "redacted",
+            &storage,
+            &["training_config".to_string()],
+            Some("external release".to_string()),
+            None,
+            HashAlgorithm::Sha384,
+        )?;
+
+        // The original signature still verifies with the original signer's key
+        let redacted = storage.retrieve_manifest("redacted")?;
+        assert_eq!(redacted.claim.signature, signature);
+        assert_eq!(
+            signing_key_fingerprint(&redacted)?,
+            Some(key_fingerprint(key.as_pkey())?)
+        );
+        check_redactions(&redacted)?;
+        verify_manifest("redacted", &storage)?;
+
+        // The salt of the redacted assertion is dropped, and only the original salt matches
+        assert!(
+            !assertion_salts(&redacted.claim)?
+                .unwrap()
+                .salts
+                .contains_key("training_config")
+        );
+        let placeholder = redacted
+            .claim
+            .created_assertions
+            .iter()
+            .find_map(|assertion| match assertion {
+                Assertion::CustomAssertion(custom) if custom.label == "training_config" => {
+                    placeholder(&custom.data)
+                }
+                _ => None,
+            })
+            .unwrap();
+        assert!(matches_placeholder(&placeholder, &salt, &original)?);
+
+        // A redaction record that doesn't match the claim fails the check
+        let mut tampered = redacted.clone();
+        for assertion in tampered.claim.created_assertions.iter_mut() {
+            match assertion {
+                Assertion::CustomAssertion(custom) if custom.label == REDACTION_ASSERTION_LABEL => {
+                    custom.data["placeholders"][0]["hash"] = serde_json::json!("00");
+                }
+                _ => {}
+            }
+        }
+        assert!(check_redactions(&tampered).is_err());
+
+        // Restoring other content under the placeholder breaks the signature
+        let mut forged = redacted;
+        for assertion in forged.claim.created_assertions.iter_mut() {
+            match assertion {
+                Assertion::CustomAssertion(custom) if custom.label == "training_config" => {
+                    custom.data = serde_json::json!({ "cluster": "public" });
+                }
+                _ => {}
+            }
+        }
+        assert_eq!(signing_key_fingerprint(&forged)?, None);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,113 @@
+//! Relationships between a manifest and its ingredients.
+//!
+//! C2PA defines three: the asset an asset was derived from (`parentOf`), an asset that is
+//! part of it (`componentOf`), and an asset consumed by the process that created it
+//! (`inputTo`). Ingredients are components unless another relationship is selected.
+
+use crate::error::{Error, Result};
+use std::fmt;
+use std::str::FromStr;
+
+/// C2PA relationship of an ingredient to the asset of its manifest
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum IngredientRelationship {
+    /// The asset was derived from the ingredient, such as a fine-tune from its base model.
+    /// A manifest has at most one parent.
+    ParentOf,
+    /// The ingredient is part of the asset, such as a weights file of a model
+    #[default]
+    ComponentOf,
+    /// The ingredient was consumed by the process that created the asset, such as the
+    /// training data or training code of a model
+    InputTo,
+}
+
+impl IngredientRelationship {
+    pub const ALL: [IngredientRelationship; 3] = [
+        IngredientRelationship::ParentOf,
+        IngredientRelationship::ComponentOf,
+        IngredientRelationship::InputTo,
+    ];
+
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            IngredientRelationship::ParentOf => "parentOf",
+            IngredientRelationship::ComponentOf => "componentOf",
+            IngredientRelationship::InputTo => "inputTo",
+        }
+    }
+}
+
+impl fmt::Display for IngredientRelationship {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for IngredientRelationship {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|relationship| relationship.as_str() == s)
+            .ok_or_else(|| {
+                Error::Validation(format!(
+                    "Unknown ingredient relationship '{s}', expected parentOf, componentOf or inputTo"
+                ))
+            })
+    }
+}
+
+/// Checks the relationships selected for the paths of a manifest: there may be no more
+/// relationships than paths, and at most one path may be the parent
+pub fn check_relationships(relationships: &[IngredientRelationship], paths: usize) -> Result<()> {
+    if relationships.len() > paths {
+        return Err(Error::Validation(format!(
+            "Got {} ingredient relationships for {paths} path(s)",
+            relationships.len()
+        )));
+    }
+    let parents = relationships
+        .iter()
+        .filter(|r| **r == IngredientRelationship::ParentOf)
+        .count();
+    if parents > 1 {
+        return Err(Error::Validation(format!(
+            "A manifest has at most one parentOf ingredient, got {parents}"
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_relationship() -> Result<()> {
+        for relationship in IngredientRelationship::ALL {
+            assert_eq!(
+                relationship.as_str().parse::<IngredientRelationship>()?,
+                relationship
+            );
+        }
+        assert_eq!(
+            IngredientRelationship::default(),
+            IngredientRelationship::ComponentOf
+        );
+        assert!("ComponentOf".parse::<IngredientRelationship>().is_err());
+        assert!("trainSplitOf".parse::<IngredientRelationship>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_relationships() {
+        use IngredientRelationship::*;
+
+        assert!(check_relationships(&[], 2).is_ok());
+        assert!(check_relationships(&[ParentOf, InputTo], 2).is_ok());
+        assert!(check_relationships(&[ComponentOf, InputTo], 1).is_err());
+        assert!(check_relationships(&[ParentOf, ParentOf], 2).is_err());
+    }
+}
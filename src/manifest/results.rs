@@ -0,0 +1,263 @@
+//! Evaluation metrics read from the reports of evaluation harnesses.
+//!
+//! With `--results-format`, `evaluation create` reads the metrics from the results file
+//! instead of requiring them as `key=value` pairs. The results file itself remains the
+//! ingredient of the evaluation manifest, so the raw report is hashed and signed as well.
+//!
+//! Supported reports:
+//! - **lm-eval**: The results JSON of the EleutherAI LM Evaluation Harness. Metrics are
+//!   named `<task>/<metric>`; the default `none` filter is dropped from metric names and
+//!   other filters are kept as `<metric>:<filter>`.
+//! - **openai-evals**: The JSON Lines record of OpenAI Evals. The numbers in the final
+//!   report are named `<eval>/<metric>`.
+//! - **mlflow**: An MLflow run as JSON, either from the REST API (`runs/get`) or from
+//!   `Run.to_dictionary()`. Only the latest value of each metric is recorded.
+//!
+//! Only numeric values are recorded.
+
+use crate::error::{Error, Result};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+/// Report formats of evaluation harnesses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResultsFormat {
+    LmEval,
+    OpenAiEvals,
+    Mlflow,
+}
+
+/// Reads the metrics from an evaluation report
+pub fn read_metrics(path: &Path, format: ResultsFormat) -> Result<BTreeMap<String, String>> {
+    let report = fs::read_to_string(path)?;
+    let metrics = match format {
+        ResultsFormat::LmEval => parse_lm_eval(&report)?,
+        ResultsFormat::OpenAiEvals => parse_openai_evals(&report)?,
+        ResultsFormat::Mlflow => parse_mlflow(&report)?,
+    };
+
+    if metrics.is_empty() {
+        return Err(Error::Validation(format!(
+            "No metrics found in {}",
+            path.display()
+        )));
+    }
+    Ok(metrics)
+}
+
+/// Parses the results JSON of the LM Evaluation Harness
+pub fn parse_lm_eval(report: &str) -> Result<BTreeMap<String, String>> {
+    let report: Value = serde_json::from_str(report)
+        .map_err(|e| Error::Validation(format!("Invalid lm-eval results: {e}")))?;
+    let results = report
+        .get("results")
+        .and_then(Value::as_object)
+        .ok_or_else(|| Error::Validation("lm-eval results have no \"results\"".to_string()))?;
+
+    let mut metrics = BTreeMap::new();
+    for (task, task_results) in results {
+        for (metric, value) in task_results.as_object().into_iter().flatten() {
+            let Some(value) = number(value) else {
+                continue;
+            };
+            // Metrics are keyed "<metric>,<filter>" since lm-eval 0.4
+            let metric = match metric.split_once(',') {
+                Some((metric, "none")) => metric.to_string(),
+                Some((metric, filter)) => format!("{metric}:{filter}"),
+                None => metric.clone(),
+            };
+            metrics.insert(format!("{task}/{metric}"), value);
+        }
+    }
+    Ok(metrics)
+}
+
+/// Parses the JSON Lines record of OpenAI Evals
+pub fn parse_openai_evals(report: &str) -> Result<BTreeMap<String, String>> {
+    let mut eval_name = None;
+    let mut final_report = None;
+    for line in report.lines().filter(|line| !line.trim().is_empty()) {
+        let event: Value = serde_json::from_str(line)
+            .map_err(|e| Error::Validation(format!("Invalid OpenAI Evals record: {e}")))?;
+        if let Some(name) = event.pointer("/spec/eval_name").and_then(Value::as_str) {
+            eval_name = Some(name.to_string());
+        }
+        if let Some(report) = event.get("final_report") {
+            final_report = Some(report.clone());
+        }
+    }
+
+    let final_report = final_report
+        .ok_or_else(|| Error::Validation("OpenAI Evals record has no final report".to_string()))?;
+
+    let mut metrics = BTreeMap::new();
+    for (metric, value) in final_report.as_object().into_iter().flatten() {
+        if let Some(value) = number(value) {
+            let name = match &eval_name {
+                Some(eval_name) => format!("{eval_name}/{metric}"),
+                None => metric.clone(),
+            };
+            metrics.insert(name, value);
+        }
+    }
+    Ok(metrics)
+}
+
+/// Parses an MLflow run, as returned by the REST API or `Run.to_dictionary()`
+pub fn parse_mlflow(report: &str) -> Result<BTreeMap<String, String>> {
+    let report: Value = serde_json::from_str(report)
+        .map_err(|e| Error::Validation(format!("Invalid MLflow run: {e}")))?;
+    let run = report.get("run").unwrap_or(&report);
+    let run_metrics = run
+        .pointer("/data/metrics")
+        .ok_or_else(|| Error::Validation("MLflow run has no metrics".to_string()))?;
+
+    let mut metrics = BTreeMap::new();
+    match run_metrics {
+        // REST API: a list of measurements, possibly several per metric
+        Value::Array(measurements) => {
+            let mut latest: HashMap<&str, (i64, i64)> = HashMap::new();
+            for measurement in measurements {
+                let Some(key) = measurement.get("key").and_then(Value::as_str) else {
+                    continue;
+                };
+                let Some(value) = measurement.get("value").and_then(number) else {
+                    continue;
+                };
+                let order = (
+                    integer(measurement.get("step")),
+                    integer(measurement.get("timestamp")),
+                );
+                if latest.get(key).is_none_or(|latest| order >= *latest) {
+                    latest.insert(key, order);
+                    metrics.insert(key.to_string(), value);
+                }
+            }
+        }
+        // Run.to_dictionary(): the latest value of each metric
+        Value::Object(values) => {
+            for (key, value) in values {
+                if let Some(value) = number(value) {
+                    metrics.insert(key.clone(), value);
+                }
+            }
+        }
+        _ => {
+            return Err(Error::Validation(
+                "MLflow run metrics must be a list or an object".to_string(),
+            ));
+        }
+    }
+    Ok(metrics)
+}
+
+fn number(value: &Value) -> Option<String> {
+    value.as_number().map(|n| n.to_string())
+}
+
+// MLflow's REST API encodes 64-bit integers as strings
+fn integer(value: Option<&Value>) -> i64 {
+    match value {
+        Some(Value::Number(n)) => n.as_i64().unwrap_or_default(),
+        Some(Value::String(s)) => s.parse().unwrap_or_default(),
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_lm_eval() {
+        let report = json!({
+            "results": {
+                "hellaswag": {
+                    "alias": "hellaswag",
+                    "acc,none": 0.5612,
+                    "acc_stderr,none": 0.0049,
+                    "acc_norm,none": 0.7534
+                },
+                "gsm8k": {
+                    "exact_match,strict-match": 0.32,
+                    "exact_match_stderr,strict-match": "N/A"
+                },
+                "arc_easy": { "acc": 0.8 }
+            },
+            "config": { "model": "hf" }
+        });
+
+        let metrics = parse_lm_eval(&report.to_string()).unwrap();
+
+        assert_eq!(metrics.len(), 5);
+        assert_eq!(metrics["hellaswag/acc"], "0.5612");
+        assert_eq!(metrics["hellaswag/acc_norm"], "0.7534");
+        assert_eq!(metrics["gsm8k/exact_match:strict-match"], "0.32");
+        assert_eq!(metrics["arc_easy/acc"], "0.8");
+        assert!(!metrics.contains_key("hellaswag/alias"));
+    }
+
+    #[test]
+    fn test_parse_lm_eval_without_results() {
+        assert!(parse_lm_eval(r#"{"config": {}}"#).is_err());
+        assert!(parse_lm_eval("not json").is_err());
+    }
+
+    #[test]
+    fn test_parse_openai_evals() {
+        let report = [
+            json!({"spec": {"eval_name": "test-match.s1.simple-v0", "run_id": "abc"}}),
+            json!({"final_report": {"accuracy": 0.75, "boostrap_std": 0.1, "note": "x"}}),
+            json!({"run_id": "abc", "event_id": 0, "type": "match", "data": {"correct": true}}),
+        ]
+        .iter()
+        .map(Value::to_string)
+        .collect::<Vec<_>>()
+        .join("\n");
+
+        let metrics = parse_openai_evals(&report).unwrap();
+
+        assert_eq!(metrics.len(), 2);
+        assert_eq!(metrics["test-match.s1.simple-v0/accuracy"], "0.75");
+        assert!(parse_openai_evals(&json!({"spec": {}}).to_string()).is_err());
+    }
+
+    #[test]
+    fn test_parse_mlflow_rest() {
+        let report = json!({
+            "run": {
+                "info": { "run_id": "abc" },
+                "data": {
+                    "metrics": [
+                        { "key": "loss", "value": 0.9, "timestamp": "1700000000000", "step": "1" },
+                        { "key": "loss", "value": 0.4, "timestamp": "1700000001000", "step": "2" },
+                        { "key": "accuracy", "value": 0.91, "timestamp": "1700000001000", "step": "0" }
+                    ],
+                    "params": [{ "key": "lr", "value": "0.001" }]
+                }
+            }
+        });
+
+        let metrics = parse_mlflow(&report.to_string()).unwrap();
+
+        assert_eq!(metrics.len(), 2);
+        assert_eq!(metrics["loss"], "0.4");
+        assert_eq!(metrics["accuracy"], "0.91");
+    }
+
+    #[test]
+    fn test_parse_mlflow_dictionary() {
+        let report = json!({
+            "info": { "run_id": "abc" },
+            "data": { "metrics": { "f1": 0.88 }, "params": { "lr": "0.001" } }
+        });
+
+        let metrics = parse_mlflow(&report.to_string()).unwrap();
+
+        assert_eq!(metrics.len(), 1);
+        assert_eq!(metrics["f1"], "0.88");
+    }
+}
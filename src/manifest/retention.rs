@@ -0,0 +1,213 @@
+//! Evidence of the deletion or retention of datasets.
+//!
+//! `dataset retire` creates a signed manifest recording what was done with a dataset, for
+//! example to meet a GDPR erasure request. The evidence of the action, such as a deletion
+//! log, is hashed as the ingredient of the manifest, and the manifest cross-references the
+//! retired dataset manifest, which itself is left unchanged.
+
+use crate::error::{Error, Result};
+use crate::manifest::common::{
+    CLAIM_GENERATOR, create_ingredient_from_path_with_algorithm, finish_manifest,
+};
+use crate::manifest::config::ManifestCreationConfig;
+use crate::manifest::utils::determine_format;
+use crate::manifest::{add_cross_reference, claims, linking};
+use crate::storage::traits::StorageBackend;
+use atlas_c2pa_lib::assertion::{
+    Action, ActionAssertion, Assertion, Author, CreativeWorkAssertion, CustomAssertion,
+};
+use atlas_c2pa_lib::asset_type::AssetType;
+use atlas_c2pa_lib::claim::ClaimV2;
+use atlas_c2pa_lib::datetime_wrapper::OffsetDateTimeWrapper;
+use atlas_c2pa_lib::manifest::Manifest;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+/// Label of the retirement assertion
+pub const RETIREMENT_ASSERTION_LABEL: &str = "dataset_retirement";
+
+/// What was done with a retired dataset
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RetirementAction {
+    /// The data was deleted
+    Deletion,
+    /// The data is no longer used, but kept until the end of its retention period
+    Retention,
+}
+
+impl fmt::Display for RetirementAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RetirementAction::Deletion => write!(f, "deletion"),
+            RetirementAction::Retention => write!(f, "retention"),
+        }
+    }
+}
+
+/// Contents of the retirement assertion
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetirementAssertion {
+    /// ID of the retired dataset manifest
+    pub dataset_id: String,
+    pub dataset_name: String,
+    pub action: RetirementAction,
+    /// Why the dataset was retired, e.g. the reference of an erasure request
+    pub reason: Option<String>,
+    /// End of the retention period of retained data
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub retain_until: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub timestamp: OffsetDateTime,
+}
+
+/// Returns the retirement recorded in a manifest, if it is a retirement manifest
+pub fn retirement(manifest: &Manifest) -> Result<Option<RetirementAssertion>> {
+    let assertions = &claims::signed_claim(manifest).created_assertions;
+
+    assertions
+        .iter()
+        .find_map(|assertion| match assertion {
+            Assertion::CustomAssertion(custom) if custom.label == RETIREMENT_ASSERTION_LABEL => {
+                Some(
+                    serde_json::from_value(custom.data.clone())
+                        .map_err(|e| Error::Manifest(format!("Invalid retirement assertion: {e}"))),
+                )
+            }
+            _ => None,
+        })
+        .transpose()
+}
+
+/// Creates a manifest recording the deletion or retention of a stored dataset, and
+/// returns its ID.
+///
+/// The evidence file is the ingredient of the manifest, and the manifest cross-references
+/// the dataset. `retain_until` is required for [`RetirementAction::Retention`] and not
+/// allowed otherwise. `config` holds the signing, output and storage settings; the
+/// manifest is named after the dataset unless `config.name` is set.
+pub fn retire_dataset(
+    dataset_id: &str,
+    action: RetirementAction,
+    evidence: &Path,
+    reason: Option<String>,
+    retain_until: Option<OffsetDateTime>,
+    config: ManifestCreationConfig,
+) -> Result<Option<String>> {
+    let storage: &dyn StorageBackend = config.storage.ok_or_else(|| {
+        Error::Validation("Retiring a dataset requires a storage backend".to_string())
+    })?;
+    match (action, retain_until) {
+        (RetirementAction::Retention, None) => {
+            return Err(Error::Validation(
+                "Retention requires the end of the retention period (--retain-until)".to_string(),
+            ));
+        }
+        (RetirementAction::Deletion, Some(_)) => {
+            return Err(Error::Validation(
+                "--retain-until only applies to retention".to_string(),
+            ));
+        }
+        _ => {}
+    }
+
+    let (_, dataset) = linking::fetch_datasets(&[dataset_id.to_string()], storage)?.remove(0);
+    let name = if config.name.is_empty() {
+        format!("{} ({action})", dataset.title)
+    } else {
+        config.name.clone()
+    };
+
+    let evidence_name = evidence
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| evidence.display().to_string());
+    let ingredient = create_ingredient_from_path_with_algorithm(
+        evidence,
+        &evidence_name,
+        AssetType::Generator,
+        determine_format(evidence).unwrap_or_else(|_| "application/octet-stream".to_string()),
+        &config.hash_alg,
+    )?;
+
+    let timestamp = OffsetDateTime::now_utc();
+    let retirement = RetirementAssertion {
+        dataset_id: dataset_id.to_string(),
+        dataset_name: dataset.title.clone(),
+        action,
+        reason,
+        retain_until,
+        timestamp,
+    };
+    let assertions = vec![
+        Assertion::CreativeWork(CreativeWorkAssertion {
+            context: "http://schema.org/".to_string(),
+            creative_type: "DigitalDocument".to_string(),
+            author: vec![
+                Author {
+                    author_type: "Organization".to_string(),
+                    name: config
+                        .author_org
+                        .clone()
+                        .unwrap_or_else(|| "Organization".to_string()),
+                },
+                Author {
+                    author_type: "Person".to_string(),
+                    name: config
+                        .author_name
+                        .clone()
+                        .unwrap_or_else(|| "Unknown".to_string()),
+                },
+            ],
+        }),
+        Assertion::Action(ActionAssertion {
+            actions: vec![Action {
+                action: match action {
+                    RetirementAction::Deletion => "c2pa.deleted".to_string(),
+                    RetirementAction::Retention => "c2pa.retained".to_string(),
+                },
+                software_agent: Some(CLAIM_GENERATOR.to_string()),
+                parameters: Some(serde_json::json!({
+                    "dataset_id": dataset_id,
+                    "description": config.description,
+                })),
+                digital_source_type: None,
+                instance_id: None,
+            }],
+        }),
+        Assertion::CustomAssertion(CustomAssertion {
+            label: RETIREMENT_ASSERTION_LABEL.to_string(),
+            data: serde_json::to_value(&retirement)?,
+        }),
+    ];
+
+    let claim = ClaimV2 {
+        instance_id: format!("urn:c2pa:{}", Uuid::new_v4()),
+        ingredients: vec![ingredient],
+        created_assertions: assertions,
+        claim_generator_info: CLAIM_GENERATOR.to_string(),
+        signature: None,
+        created_at: OffsetDateTimeWrapper(timestamp),
+    };
+    let mut manifest = Manifest {
+        claim_generator: CLAIM_GENERATOR.to_string(),
+        title: name,
+        instance_id: format!("urn:c2pa:{}", Uuid::new_v4()),
+        claim: claim.clone(),
+        ingredients: vec![],
+        created_at: OffsetDateTimeWrapper(timestamp),
+        cross_references: vec![],
+        claim_v2: Some(claim),
+        is_active: true,
+    };
+    add_cross_reference(&mut manifest, dataset_id, &dataset, &config.hash_alg)?;
+
+    let id = finish_manifest(&mut manifest, &config)?;
+    if let Some(id) = &id {
+        println!("Recorded {action} of dataset {dataset_id} in manifest {id}");
+    }
+    Ok(id)
+}
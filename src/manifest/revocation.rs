@@ -0,0 +1,324 @@
+//! Revocation of compromised signing keys.
+//!
+//! A revocation list names keys by fingerprint, each with the time it was revoked. The list
+//! is a credential signed by a revocation authority, so that it can be distributed without
+//! being tampered with; the trust store names the list, the public key of the authority and
+//! what verification does with revoked signatures. It is read from `ATLAS_TRUST_STORE` or
+//! `~/.atlas/trust.yaml`, with paths relative to the file:
+//!
+//! ```yaml
+//! revocation_authority: revocation-authority.pem
+//! revocation_list: revocations.jwt
+//! on_revoked: fail   # or warn
+//! ```
+//!
+//! A signature made with a revoked key at or after its revocation time, whether of a claim
+//! or of an approval, fails verification or is reported with a warning. Signatures made
+//! before the revocation keep verifying. Signing times are those recorded in manifests,
+//! which a holder of the key can backdate, so keys are best revoked from the earliest time
+//! they may have been compromised.
+
+use crate::error::{Error, Result};
+use crate::manifest::credential::{self, VC_CONTEXT};
+use crate::manifest::key_usage::{ManifestSignature, manifest_signatures};
+use crate::manifest::timestamp::to_rfc3339_utc;
+use crate::signing::SecurePrivateKey;
+use atlas_c2pa_lib::manifest::Manifest;
+use openssl::pkey::{PKey, Public};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use time::OffsetDateTime;
+
+/// Environment variable naming the trust store file
+pub const TRUST_STORE_ENV: &str = "ATLAS_TRUST_STORE";
+
+/// Credential type of revocation lists, besides `VerifiableCredential`
+pub const REVOCATION_LIST_CREDENTIAL_TYPE: &str = "AtlasKeyRevocationList";
+
+/// A revoked key
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RevokedKey {
+    /// SHA-256 fingerprint of the key, see [`crate::manifest::signer::key_fingerprint`]
+    pub fingerprint: String,
+    /// Signatures made from this time on are revoked
+    #[serde(with = "time::serde::rfc3339")]
+    pub revoked_at: OffsetDateTime,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// Keys revoked by a revocation authority
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RevocationList {
+    pub revoked: Vec<RevokedKey>,
+}
+
+impl RevocationList {
+    /// The revocation of the key with fingerprint `fingerprint`, if it is revoked
+    pub fn revocation(&self, fingerprint: &str) -> Option<&RevokedKey> {
+        self.revoked
+            .iter()
+            .find(|revoked| revoked.fingerprint.eq_ignore_ascii_case(fingerprint))
+    }
+
+    /// Revokes a key, replacing an earlier revocation of it
+    pub fn revoke(&mut self, revoked: RevokedKey) {
+        self.revoked.retain(|existing| {
+            !existing
+                .fingerprint
+                .eq_ignore_ascii_case(&revoked.fingerprint)
+        });
+        self.revoked.push(revoked);
+        self.revoked
+            .sort_by(|a, b| a.fingerprint.cmp(&b.fingerprint));
+    }
+
+    /// Signatures of a manifest made with a revoked key at or after its revocation, with
+    /// the revocation
+    pub fn revoked_signatures(
+        &self,
+        manifest: &Manifest,
+    ) -> Result<Vec<(ManifestSignature, RevokedKey)>> {
+        if self.revoked.is_empty() {
+            return Ok(Vec::new());
+        }
+        Ok(manifest_signatures(manifest)?
+            .into_iter()
+            .filter_map(|signature| {
+                let revoked = self.revocation(&signature.fingerprint)?;
+                (signature.signed_at >= revoked.revoked_at).then(|| (signature, revoked.clone()))
+            })
+            .collect())
+    }
+
+    /// Signs the list with the key of the revocation authority. Without an `issuer`, the
+    /// list is issued by the `did:key` of the key.
+    pub fn sign(&self, key: &SecurePrivateKey, issuer: Option<String>) -> Result<String> {
+        let (issuer, kid) = credential::issuer_and_kid(key, issuer)?;
+        let list = json!({
+            "@context": [VC_CONTEXT],
+            "type": ["VerifiableCredential", REVOCATION_LIST_CREDENTIAL_TYPE],
+            "issuer": issuer,
+            "validFrom": to_rfc3339_utc(OffsetDateTime::now_utc()),
+            "credentialSubject": {
+                "revoked": self.revoked,
+            },
+        });
+        credential::sign_credential(&list, key, &kid)
+    }
+
+    /// Reads a signed list, failing unless it verifies with the authority's key
+    pub fn verify(jws: &str, authority: &PKey<Public>) -> Result<Self> {
+        let list = credential::verify_credential(jws.trim(), authority)
+            .map_err(|e| Error::Validation(format!("Invalid key revocation list: {e}")))?;
+        let is_list = list["type"]
+            .as_array()
+            .is_some_and(|types| types.iter().any(|t| t == REVOCATION_LIST_CREDENTIAL_TYPE));
+        if !is_list {
+            return Err(Error::Validation(
+                "The credential is not a key revocation list".to_string(),
+            ));
+        }
+        let revoked = serde_json::from_value(list["credentialSubject"]["revoked"].clone())
+            .map_err(|e| Error::Validation(format!("Invalid key revocation list: {e}")))?;
+        Ok(RevocationList { revoked })
+    }
+}
+
+/// Adds a revocation to the list at `path`, or starts one, and writes it signed with the
+/// key of the revocation authority. An existing list must verify with that key.
+pub fn revoke_key(
+    path: &Path,
+    authority: &SecurePrivateKey,
+    issuer: Option<String>,
+    revoked: RevokedKey,
+) -> Result<RevocationList> {
+    let mut list = if path.exists() {
+        let public_key = authority
+            .as_pkey()
+            .public_key_to_der()
+            .and_then(|der| PKey::public_key_from_der(&der))
+            .map_err(|e| Error::Signing(format!("Failed to encode public key: {e}")))?;
+        RevocationList::verify(&std::fs::read_to_string(path)?, &public_key)?
+    } else {
+        RevocationList::default()
+    };
+    list.revoke(revoked);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, list.sign(authority, issuer)?)?;
+    Ok(list)
+}
+
+/// What verification does with signatures made with revoked keys
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OnRevoked {
+    #[default]
+    Fail,
+    Warn,
+}
+
+/// Trust settings of the verifier
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TrustStore {
+    /// Public key (PEM) of the authority signing the revocation list
+    pub revocation_authority: Option<PathBuf>,
+    /// Signed revocation list
+    pub revocation_list: Option<PathBuf>,
+    pub on_revoked: OnRevoked,
+}
+
+/// Location of the trust store: `ATLAS_TRUST_STORE`, or `~/.atlas/trust.yaml`
+pub fn trust_store_file() -> PathBuf {
+    match std::env::var_os(TRUST_STORE_ENV) {
+        Some(path) => PathBuf::from(path),
+        None => std::env::var_os("HOME")
+            .or_else(|| std::env::var_os("USERPROFILE"))
+            .map(|home| PathBuf::from(home).join(".atlas"))
+            .unwrap_or_default()
+            .join("trust.yaml"),
+    }
+}
+
+impl TrustStore {
+    /// Reads a trust store, or returns an empty one if there is none. Relative paths are
+    /// resolved against the directory of the file.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let mut store: TrustStore =
+            serde_yaml::from_str(&std::fs::read_to_string(path)?).map_err(|e| {
+                Error::Validation(format!("Invalid trust store {}: {e}", path.display()))
+            })?;
+        let base = path.parent().unwrap_or(Path::new(""));
+        for file in [&mut store.revocation_authority, &mut store.revocation_list]
+            .into_iter()
+            .flatten()
+        {
+            *file = base.join(&*file);
+        }
+        Ok(store)
+    }
+
+    /// Reads the trust store at [`trust_store_file`]
+    pub fn load_default() -> Result<Self> {
+        Self::load(&trust_store_file())
+    }
+
+    /// Public key of the revocation authority, if one is configured
+    pub fn authority_key(&self) -> Result<Option<PKey<Public>>> {
+        let Some(path) = &self.revocation_authority else {
+            return Ok(None);
+        };
+        let pem = std::fs::read(path).map_err(|e| {
+            Error::Validation(format!(
+                "Failed to read revocation authority key {}: {e}",
+                path.display()
+            ))
+        })?;
+        PKey::public_key_from_pem(&pem)
+            .map(Some)
+            .map_err(|e| Error::Signing(format!("Invalid revocation authority key: {e}")))
+    }
+
+    /// The revocation list, verified with the authority's key. A configured list that is
+    /// missing or doesn't verify is an error, so that revocations can't be dropped
+    /// silently.
+    pub fn revocations(&self) -> Result<RevocationList> {
+        let Some(path) = &self.revocation_list else {
+            return Ok(RevocationList::default());
+        };
+        let authority = self.authority_key()?.ok_or_else(|| {
+            Error::Validation(
+                "The trust store names a revocation list but no revocation_authority".to_string(),
+            )
+        })?;
+        let jws = std::fs::read_to_string(path).map_err(|e| {
+            Error::Validation(format!(
+                "Failed to read key revocation list {}: {e}",
+                path.display()
+            ))
+        })?;
+        RevocationList::verify(&jws, &authority)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing::pkey_to_secure;
+    use time::macros::datetime;
+
+    fn revoked(fingerprint: &str, revoked_at: OffsetDateTime) -> RevokedKey {
+        RevokedKey {
+            fingerprint: fingerprint.to_string(),
+            revoked_at,
+            reason: None,
+        }
+    }
+
+    #[test]
+    fn test_sign_and_verify_list() -> Result<()> {
+        let authority = pkey_to_secure(PKey::generate_ed25519().unwrap())?;
+        let public_key =
+            PKey::public_key_from_pem(&authority.as_pkey().public_key_to_pem().unwrap()).unwrap();
+        let mut list = RevocationList::default();
+        list.revoke(revoked("BB", datetime!(2024-01-01 00:00 UTC)));
+        list.revoke(revoked("aa", datetime!(2024-02-01 00:00 UTC)));
+        list.revoke(revoked("bb", datetime!(2024-03-01 00:00 UTC)));
+        assert_eq!(list.revoked.len(), 2);
+        assert_eq!(
+            list.revocation("AA").map(|revoked| revoked.revoked_at),
+            Some(datetime!(2024-02-01 00:00 UTC))
+        );
+        assert_eq!(
+            list.revocation("BB").map(|revoked| revoked.revoked_at),
+            Some(datetime!(2024-03-01 00:00 UTC))
+        );
+
+        let jws = list.sign(&authority, None)?;
+        assert_eq!(RevocationList::verify(&jws, &public_key)?, list);
+
+        // A list is only trusted with the key of its authority
+        let other = PKey::generate_ed25519().unwrap();
+        let other = PKey::public_key_from_pem(&other.public_key_to_pem().unwrap()).unwrap();
+        assert!(RevocationList::verify(&jws, &other).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_trust_store() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("trust.yaml");
+        assert_eq!(TrustStore::load(&path)?, TrustStore::default());
+        assert!(TrustStore::default().revocations()?.revoked.is_empty());
+
+        std::fs::write(
+            &path,
+            "revocation_authority: authority.pem\nrevocation_list: revocations.jwt\non_revoked: warn\n",
+        )?;
+        let store = TrustStore::load(&path)?;
+        assert_eq!(
+            store.revocation_authority,
+            Some(dir.path().join("authority.pem"))
+        );
+        assert_eq!(
+            store.revocation_list,
+            Some(dir.path().join("revocations.jwt"))
+        );
+        assert_eq!(store.on_revoked, OnRevoked::Warn);
+
+        // A configured list that is missing doesn't mean nothing is revoked
+        assert!(store.revocations().is_err());
+
+        std::fs::write(&path, "on_revoked: ignore\n")?;
+        assert!(TrustStore::load(&path).is_err());
+        Ok(())
+    }
+}
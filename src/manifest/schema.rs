@@ -0,0 +1,540 @@
+//! JSON Schemas of custom assertions.
+//!
+//! Custom assertions carry whatever JSON their creator chose, so metadata that is malformed
+//! or missing fields accumulates in the store unnoticed. Schemas registered for assertion
+//! labels in `ATLAS_SCHEMAS_FILE` or `~/.atlas/schemas.yaml` are checked when manifests are
+//! created, which fails on a violation, and reported when they are verified:
+//!
+//! ```yaml
+//! schemas:
+//!   org.example.compliance: schemas/compliance.json   # relative to this file
+//!   license:
+//!     type: object
+//!     required: [spdx_id]
+//! ```
+//!
+//! Schemas are validated with the commonly used subset of JSON Schema: `type`, `enum`,
+//! `const`, `properties`, `required`, `additionalProperties`, `items`, `minItems`,
+//! `maxItems`, `uniqueItems`, `minimum`, `maximum`, `exclusiveMinimum`,
+//! `exclusiveMaximum`, `minLength`, `maxLength`, `allOf`, `anyOf`, `oneOf` and `not`.
+//! Annotations such as `title` or `format` are ignored; schemas using any other keyword are
+//! refused rather than silently checked less strictly than they say.
+
+use crate::error::{Error, Result};
+use crate::manifest::encryption::ENCRYPTED_PAYLOAD_KEY;
+use atlas_c2pa_lib::assertion::Assertion;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Environment variable naming the schemas file
+pub const SCHEMAS_FILE_ENV: &str = "ATLAS_SCHEMAS_FILE";
+
+// Keywords that are checked
+const VALIDATION_KEYWORDS: &[&str] = &[
+    "type",
+    "enum",
+    "const",
+    "properties",
+    "required",
+    "additionalProperties",
+    "items",
+    "minItems",
+    "maxItems",
+    "uniqueItems",
+    "minimum",
+    "maximum",
+    "exclusiveMinimum",
+    "exclusiveMaximum",
+    "minLength",
+    "maxLength",
+    "allOf",
+    "anyOf",
+    "oneOf",
+    "not",
+];
+
+// Keywords that don't constrain payloads
+const ANNOTATION_KEYWORDS: &[&str] = &[
+    "$schema",
+    "$id",
+    "$comment",
+    "title",
+    "description",
+    "default",
+    "examples",
+    "format",
+    "deprecated",
+    "readOnly",
+    "writeOnly",
+];
+
+/// A schema as registered: a JSON Schema file, or the schema itself
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum SchemaSource {
+    File(PathBuf),
+    Inline(Value),
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct SchemasFile {
+    schemas: BTreeMap<String, SchemaSource>,
+}
+
+/// Schemas of custom assertions, by label
+#[derive(Debug, Clone, Default)]
+pub struct SchemaRegistry {
+    schemas: BTreeMap<String, Value>,
+}
+
+/// Location of the schemas file: `ATLAS_SCHEMAS_FILE`, or `~/.atlas/schemas.yaml`
+pub fn schemas_file() -> PathBuf {
+    match std::env::var_os(SCHEMAS_FILE_ENV) {
+        Some(path) => PathBuf::from(path),
+        None => std::env::var_os("HOME")
+            .or_else(|| std::env::var_os("USERPROFILE"))
+            .map(|home| PathBuf::from(home).join(".atlas"))
+            .unwrap_or_default()
+            .join("schemas.yaml"),
+    }
+}
+
+impl SchemaRegistry {
+    /// Reads a schemas file, or returns `None` if there is none
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let invalid = |e: &dyn std::fmt::Display| {
+            Error::Validation(format!("Invalid schemas file {}: {e}", path.display()))
+        };
+        let file: SchemasFile =
+            serde_yaml::from_str(&std::fs::read_to_string(path)?).map_err(|e| invalid(&e))?;
+
+        let mut registry = SchemaRegistry::default();
+        for (label, source) in file.schemas {
+            let schema = match source {
+                SchemaSource::File(schema_path) => {
+                    let schema_path = path.parent().unwrap_or(Path::new("")).join(schema_path);
+                    serde_json::from_str(&std::fs::read_to_string(&schema_path)?).map_err(|e| {
+                        invalid(&format!("schema {} of {label}: {e}", schema_path.display()))
+                    })?
+                }
+                SchemaSource::Inline(schema) => schema,
+            };
+            check_schema(&schema, "#").map_err(|e| invalid(&format!("schema of {label}: {e}")))?;
+            registry.schemas.insert(label, schema);
+        }
+        Ok(Some(registry))
+    }
+
+    /// Registers `schema` for assertions labeled `label`, checking that it only uses
+    /// supported keywords
+    pub fn register(&mut self, label: &str, schema: Value) -> Result<()> {
+        check_schema(&schema, "#")
+            .map_err(|e| Error::Validation(format!("Invalid schema of {label}: {e}")))?;
+        self.schemas.insert(label.to_string(), schema);
+        Ok(())
+    }
+
+    /// Checks the payloads of `assertions` that have a registered schema, returning the
+    /// violations by label. Encrypted payloads are skipped.
+    pub fn violations(&self, assertions: &[Assertion]) -> Vec<(String, Vec<String>)> {
+        assertions
+            .iter()
+            .filter_map(|assertion| match assertion {
+                Assertion::CustomAssertion(custom) => Some(custom),
+                _ => None,
+            })
+            .filter(|custom| custom.data.get(ENCRYPTED_PAYLOAD_KEY).is_none())
+            .filter_map(|custom| {
+                let schema = self.schemas.get(&custom.label)?;
+                let mut violations = Vec::new();
+                validate(schema, &custom.data, "", &mut violations);
+                (!violations.is_empty()).then(|| (custom.label.clone(), violations))
+            })
+            .collect()
+    }
+}
+
+// Refuses schemas with keywords that aren't checked
+fn check_schema(schema: &Value, location: &str) -> std::result::Result<(), String> {
+    let keywords = match schema {
+        Value::Bool(_) => return Ok(()),
+        Value::Object(keywords) => keywords,
+        _ => {
+            return Err(format!("{location} is not a schema"));
+        }
+    };
+    for (keyword, value) in keywords {
+        let location = format!("{location}/{keyword}");
+        match keyword.as_str() {
+            "properties" => {
+                for (name, schema) in value.as_object().into_iter().flatten() {
+                    check_schema(schema, &format!("{location}/{name}"))?;
+                }
+            }
+            "additionalProperties" | "items" | "not" => check_schema(value, &location)?,
+            "allOf" | "anyOf" | "oneOf" => {
+                for (index, schema) in value.as_array().into_iter().flatten().enumerate() {
+                    check_schema(schema, &format!("{location}/{index}"))?;
+                }
+            }
+            keyword
+                if VALIDATION_KEYWORDS.contains(&keyword)
+                    || ANNOTATION_KEYWORDS.contains(&keyword) => {}
+            _ => {
+                return Err(format!("unsupported keyword {location}"));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(number) if number.is_i64() || number.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn has_type(value: &Value, expected: &str) -> bool {
+    let actual = type_name(value);
+    actual == expected
+        || (expected == "number" && actual == "integer")
+        // 1.0 is an integer as far as JSON Schema is concerned
+        || (expected == "integer" && value.as_f64().is_some_and(|n| n.fract() == 0.0))
+}
+
+// Appends the violations of `value` against `schema`, found at the JSON pointer `path`
+fn validate(schema: &Value, value: &Value, path: &str, violations: &mut Vec<String>) {
+    let keywords: &Map<String, Value> = match schema {
+        Value::Bool(true) => return,
+        Value::Bool(false) => {
+            violations.push(format!("{}: not allowed", display_path(path)));
+            return;
+        }
+        Value::Object(keywords) => keywords,
+        _ => return,
+    };
+    let mut fail = |message: String| violations.push(format!("{}: {message}", display_path(path)));
+
+    if let Some(expected) = keywords.get("type") {
+        let types: Vec<&str> = match expected {
+            Value::String(name) => vec![name.as_str()],
+            Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !types.iter().any(|expected| has_type(value, expected)) {
+            fail(format!(
+                "expected {}, got {}",
+                types.join(" or "),
+                type_name(value)
+            ));
+        }
+    }
+    if let Some(allowed) = keywords.get("enum").and_then(Value::as_array)
+        && !allowed.contains(value)
+    {
+        fail(format!(
+            "{value} is not one of {}",
+            Value::Array(allowed.clone())
+        ));
+    }
+    if let Some(expected) = keywords.get("const")
+        && expected != value
+    {
+        fail(format!("expected {expected}"));
+    }
+
+    if let Some(number) = value.as_f64() {
+        let bound = |keyword: &str| keywords.get(keyword).and_then(Value::as_f64);
+        if let Some(minimum) = bound("minimum")
+            && number < minimum
+        {
+            fail(format!("{number} is less than {minimum}"));
+        }
+        if let Some(maximum) = bound("maximum")
+            && number > maximum
+        {
+            fail(format!("{number} is greater than {maximum}"));
+        }
+        if let Some(minimum) = bound("exclusiveMinimum")
+            && number <= minimum
+        {
+            fail(format!("{number} is not greater than {minimum}"));
+        }
+        if let Some(maximum) = bound("exclusiveMaximum")
+            && number >= maximum
+        {
+            fail(format!("{number} is not less than {maximum}"));
+        }
+    }
+
+    if let Value::String(text) = value {
+        let length = text.chars().count() as u64;
+        if let Some(min) = keywords.get("minLength").and_then(Value::as_u64)
+            && length < min
+        {
+            fail(format!("shorter than {min} characters"));
+        }
+        if let Some(max) = keywords.get("maxLength").and_then(Value::as_u64)
+            && length > max
+        {
+            fail(format!("longer than {max} characters"));
+        }
+    }
+
+    if let Value::Array(items) = value {
+        let count = items.len() as u64;
+        if let Some(min) = keywords.get("minItems").and_then(Value::as_u64)
+            && count < min
+        {
+            fail(format!("fewer than {min} items"));
+        }
+        if let Some(max) = keywords.get("maxItems").and_then(Value::as_u64)
+            && count > max
+        {
+            fail(format!("more than {max} items"));
+        }
+        if keywords.get("uniqueItems") == Some(&Value::Bool(true))
+            && items
+                .iter()
+                .enumerate()
+                .any(|(index, item)| items[..index].contains(item))
+        {
+            fail("items are not unique".to_string());
+        }
+    }
+
+    if let Value::Object(properties) = value {
+        for name in keywords
+            .get("required")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+        {
+            if !properties.contains_key(name) {
+                fail(format!("missing required property {name}"));
+            }
+        }
+    }
+
+    // Subschemas report their own violations
+    match value {
+        Value::Object(properties) => {
+            let declared = keywords.get("properties").and_then(Value::as_object);
+            for (name, property) in properties {
+                let path = format!("{path}/{}", escape_pointer(name));
+                match declared.and_then(|declared| declared.get(name)) {
+                    Some(schema) => validate(schema, property, &path, violations),
+                    None => {
+                        if let Some(schema) = keywords.get("additionalProperties") {
+                            validate(schema, property, &path, violations);
+                        }
+                    }
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(schema) = keywords.get("items") {
+                for (index, item) in items.iter().enumerate() {
+                    validate(schema, item, &format!("{path}/{index}"), violations);
+                }
+            }
+        }
+        _ => {}
+    }
+
+    let matches = |schema: &Value| {
+        let mut nested = Vec::new();
+        validate(schema, value, path, &mut nested);
+        nested.is_empty()
+    };
+    if let Some(schemas) = keywords.get("allOf").and_then(Value::as_array) {
+        for schema in schemas {
+            validate(schema, value, path, violations);
+        }
+    }
+    if let Some(schemas) = keywords.get("anyOf").and_then(Value::as_array)
+        && !schemas.iter().any(matches)
+    {
+        violations.push(format!(
+            "{}: matches none of the anyOf schemas",
+            display_path(path)
+        ));
+    }
+    if let Some(schemas) = keywords.get("oneOf").and_then(Value::as_array) {
+        let matched = schemas.iter().filter(|schema| matches(schema)).count();
+        if matched != 1 {
+            violations.push(format!(
+                "{}: matches {matched} of the oneOf schemas instead of one",
+                display_path(path)
+            ));
+        }
+    }
+    if let Some(schema) = keywords.get("not")
+        && matches(schema)
+    {
+        violations.push(format!(
+            "{}: matches the schema it must not match",
+            display_path(path)
+        ));
+    }
+}
+
+fn escape_pointer(name: &str) -> String {
+    name.replace('~', "~0").replace('/', "~1")
+}
+
+fn display_path(path: &str) -> &str {
+    if path.is_empty() { "/" } else { path }
+}
+
+/// Checks the custom assertions of a manifest being created against the schemas file.
+///
+/// Fails listing every violation, so that malformed metadata never reaches the store.
+pub fn check_creation(assertions: &[Assertion]) -> Result<()> {
+    let path = schemas_file();
+    let Some(registry) = SchemaRegistry::load(&path)? else {
+        return Ok(());
+    };
+    let violations = registry.violations(assertions);
+    if violations.is_empty() {
+        return Ok(());
+    }
+    Err(Error::Validation(format!(
+        "Assertions violate their schemas in {}: {}",
+        path.display(),
+        format_violations(&violations)
+    )))
+}
+
+/// Formats violations as returned by [`SchemaRegistry::violations`]
+pub fn format_violations(violations: &[(String, Vec<String>)]) -> String {
+    violations
+        .iter()
+        .flat_map(|(label, violations)| {
+            violations
+                .iter()
+                .map(move |violation| format!("{label} {violation}"))
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use atlas_c2pa_lib::assertion::CustomAssertion;
+    use serde_json::json;
+
+    fn assertion(label: &str, data: Value) -> Assertion {
+        Assertion::CustomAssertion(CustomAssertion {
+            label: label.to_string(),
+            data,
+        })
+    }
+
+    #[test]
+    fn test_schema_violations() -> Result<()> {
+        let mut registry = SchemaRegistry::default();
+        registry.register(
+            "org.example.scan",
+            json!({
+                "type": "object",
+                "required": ["scanner", "findings"],
+                "properties": {
+                    "scanner": {"type": "string", "minLength": 1},
+                    "severity": {"enum": ["low", "high"]},
+                    "findings": {"type": "array", "items": {"type": "integer", "minimum": 0}},
+                },
+                "additionalProperties": false,
+            }),
+        )?;
+
+        let valid = assertion(
+            "org.example.scan",
+            json!({"scanner": "picklescan", "findings": [0, 2.0]}),
+        );
+        let invalid = assertion(
+            "org.example.scan",
+            json!({"scanner": "", "severity": "medium", "findings": [-1], "extra": true}),
+        );
+        let unregistered = assertion("org.example.other", json!("anything"));
+        let encrypted = assertion("org.example.scan", json!({ENCRYPTED_PAYLOAD_KEY: "..."}));
+        assert!(
+            registry
+                .violations(&[valid, unregistered, encrypted])
+                .is_empty()
+        );
+
+        let mut violations = registry.violations(&[invalid]);
+        assert_eq!(violations.len(), 1);
+        violations[0].1.sort();
+        assert_eq!(
+            violations[0].1,
+            vec![
+                "/extra: not allowed",
+                "/findings/0: -1 is less than 0",
+                "/scanner: shorter than 1 characters",
+                "/severity: \"medium\" is not one of [\"low\",\"high\"]",
+            ]
+        );
+
+        let missing = assertion("org.example.scan", json!({"scanner": "picklescan"}));
+        assert_eq!(
+            format_violations(&registry.violations(&[missing])),
+            "org.example.scan /: missing required property findings"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_schemas() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("schemas.yaml");
+        assert!(SchemaRegistry::load(&path)?.is_none());
+
+        std::fs::write(
+            dir.path().join("compliance.json"),
+            r#"{"type": "object", "required": ["reviewed"], "anyOf": [{"required": ["team"]}, {"required": ["owner"]}]}"#,
+        )?;
+        std::fs::write(
+            &path,
+            "schemas:\n  org.example.compliance: compliance.json\n  license:\n    type: object\n",
+        )?;
+        let registry = SchemaRegistry::load(&path)?.unwrap();
+        let violations = registry.violations(&[
+            assertion("org.example.compliance", json!({"reviewed": true})),
+            assertion("license", json!("MIT")),
+        ]);
+        assert_eq!(violations.len(), 2);
+        assert_eq!(
+            violations[0].1,
+            vec!["/: matches none of the anyOf schemas"]
+        );
+
+        // Schemas with keywords that aren't checked are refused
+        std::fs::write(
+            &path,
+            "schemas:\n  license:\n    properties:\n      id:\n        pattern: '^[A-Z]'\n",
+        )?;
+        let error = SchemaRegistry::load(&path).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("unsupported keyword #/properties/id/pattern")
+        );
+        Ok(())
+    }
+}
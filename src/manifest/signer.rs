@@ -1,26 +1,389 @@
 use crate::error::{Error, Result};
-use crate::signing;
+use crate::hash;
+use crate::manifest::canonical;
+use crate::manifest::redaction;
+use crate::signing::piv::{
+    self, PIV_CERTIFICATE_ASSERTION_LABEL, PivCertificateAssertion, PivSlot,
+};
+use crate::signing::pq::{self, PQ_SIGNATURE_ASSERTION_LABEL, PqSignatureAssertion};
 use crate::signing::signable::Signable;
+use crate::signing::{self, SecurePrivateKey};
+use atlas_c2pa_lib::assertion::{Assertion, CustomAssertion};
 use atlas_c2pa_lib::cose::HashAlgorithm;
 use atlas_c2pa_lib::manifest::Manifest;
 use base64::Engine;
 use base64::engine::general_purpose::STANDARD;
-use std::path::PathBuf;
+use openssl::pkey::{HasPublic, PKey, PKeyRef, Public};
+use openssl::x509::X509;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+/// Label of the assertion recording the public key that signed the claim
+pub const SIGNER_KEY_ASSERTION_LABEL: &str = "signer_key";
+
+/// Contents of the signer key assertion
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignerKeyAssertion {
+    /// PEM-encoded public key of the signer
+    pub public_key: String,
+    pub hash_algorithm: String,
+}
+
+/// Which manifests to select by signature
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum SignatureFilter {
+    #[default]
+    Any,
+    Signed,
+    Unsigned,
+    /// Manifests whose signature verifies with a key with one of these fingerprints, see
+    /// [`key_fingerprint`]
+    SignedBy(Vec<String>),
+    /// Manifests with a signature made by a key revoked in the trust store, see
+    /// [`crate::manifest::revocation`]
+    SignedByRevoked,
+}
+
+impl SignatureFilter {
+    /// Whether a manifest is selected by the filter
+    pub fn matches(&self, manifest: &Manifest) -> Result<bool> {
+        Ok(match self {
+            SignatureFilter::Any => true,
+            SignatureFilter::Signed => manifest.claim.signature.is_some(),
+            SignatureFilter::Unsigned => manifest.claim.signature.is_none(),
+            SignatureFilter::SignedBy(fingerprints) => signing_key_fingerprint(manifest)?
+                .is_some_and(|fingerprint| {
+                    fingerprints
+                        .iter()
+                        .any(|expected| expected.eq_ignore_ascii_case(&fingerprint))
+                }),
+            SignatureFilter::SignedByRevoked => {
+                let revocations =
+                    crate::manifest::revocation::TrustStore::load_default()?.revocations()?;
+                !revocations.revoked_signatures(manifest)?.is_empty()
+            }
+        })
+    }
+}
+
+/// SHA-256 fingerprint of a public key: the hex-encoded hash of its DER encoding, as
+/// printed by `openssl pkey -pubin -outform DER | sha256sum`
+pub fn key_fingerprint<T: HasPublic>(key: &PKeyRef<T>) -> Result<String> {
+    let der = key
+        .public_key_to_der()
+        .map_err(|e| Error::Signing(format!("Failed to encode public key: {e}")))?;
+    Ok(hex::encode(Sha256::digest(der)))
+}
+
+// Records the signer's public key in the claim, replacing the key of an earlier signature
+// in place, so that signing twice with the same key leaves the claim unchanged
+fn record_signer_key(
+    manifest: &mut Manifest,
+    private_key: &SecurePrivateKey,
+    hash_alg: &HashAlgorithm,
+) -> Result<()> {
+    let public_key = private_key
+        .as_pkey()
+        .public_key_to_pem()
+        .map_err(|e| Error::Signing(format!("Failed to export signer public key: {e}")))?;
+    let assertion = Assertion::CustomAssertion(CustomAssertion {
+        label: SIGNER_KEY_ASSERTION_LABEL.to_string(),
+        data: serde_json::to_value(SignerKeyAssertion {
+            public_key: String::from_utf8_lossy(&public_key).into_owned(),
+            hash_algorithm: hash::algorithm_to_string(hash_alg).to_string(),
+        })?,
+    });
+
+    let assertions = &mut manifest.claim.created_assertions;
+    match assertions.iter_mut().find(|assertion| {
+        matches!(assertion, Assertion::CustomAssertion(custom) if custom.label == SIGNER_KEY_ASSERTION_LABEL)
+    }) {
+        Some(existing) => *existing = assertion,
+        None => assertions.push(assertion),
+    }
+    Ok(())
+}
+
+/// Returns the fingerprint of the key whose signature of the claim verifies, taken from the
+/// signer key or the embedded certificate, or `None` if the manifest is unsigned or doesn't
+/// record its signer
+pub fn signing_key_fingerprint(manifest: &Manifest) -> Result<Option<String>> {
+    if let Some(certificate) = verify_certificate_signature(manifest)? {
+        let public_key = certificate
+            .public_key()
+            .map_err(|e| Error::Signing(format!("Invalid embedded certificate: {e}")))?;
+        return key_fingerprint(&public_key).map(Some);
+    }
+
+    let Some(signature) = &manifest.claim.signature else {
+        return Ok(None);
+    };
+    let Some((public_key, hash_alg)) = recorded_signer_key(manifest)? else {
+        return Ok(None);
+    };
+    let signature = decode_signature(signature)?;
+    if !canonical::verify_claim(&manifest.claim, |claim| {
+        signing::verify_signature_with_algorithm(claim, &signature, &public_key, &hash_alg)
+    })? {
+        // A recorded key that didn't make the signature says nothing about the signer
+        return Ok(None);
+    }
+    key_fingerprint(&public_key).map(Some)
+}
+
+/// Returns the encoding of the claim the manifest signature was made over, checked with the
+/// embedded certificate or the signer key, or `None` if the manifest is unsigned or its
+/// signature doesn't verify with either
+pub fn claim_signature_encoding(manifest: &Manifest) -> Result<Option<canonical::ClaimEncoding>> {
+    let Some(signature) = &manifest.claim.signature else {
+        return Ok(None);
+    };
+    let (public_key, hash_alg) = match embedded_certificate(manifest)? {
+        Some((certificate, hash_alg)) => (
+            certificate
+                .public_key()
+                .map_err(|e| Error::Signing(format!("Invalid embedded certificate: {e}")))?,
+            hash_alg,
+        ),
+        None => match recorded_signer_key(manifest)? {
+            Some(key) => key,
+            None => return Ok(None),
+        },
+    };
+    let signature = decode_signature(signature)?;
+    canonical::signed_encoding(&manifest.claim, |claim| {
+        signing::verify_signature_with_algorithm(claim, &signature, &public_key, &hash_alg)
+    })
+}
+
+fn decode_signature(signature: &str) -> Result<Vec<u8>> {
+    STANDARD
+        .decode(signature)
+        .map_err(|e| Error::Signing(format!("Invalid signature encoding: {e}")))
+}
+
+// The public key and hash algorithm recorded in the signer key assertion, if there is one
+fn recorded_signer_key(manifest: &Manifest) -> Result<Option<(PKey<Public>, HashAlgorithm)>> {
+    let Some(data) =
+        manifest
+            .claim
+            .created_assertions
+            .iter()
+            .find_map(|assertion| match assertion {
+                Assertion::CustomAssertion(custom)
+                    if custom.label == SIGNER_KEY_ASSERTION_LABEL =>
+                {
+                    Some(custom.data.clone())
+                }
+                _ => None,
+            })
+    else {
+        return Ok(None);
+    };
+    let assertion: SignerKeyAssertion = serde_json::from_value(data)
+        .map_err(|e| Error::Manifest(format!("Invalid signer key assertion: {e}")))?;
+    let public_key: PKey<Public> = PKey::public_key_from_pem(assertion.public_key.as_bytes())
+        .map_err(|e| Error::Signing(format!("Invalid signer public key: {e}")))?;
+    let hash_alg = hash::parse_algorithm(&assertion.hash_algorithm)?;
+    Ok(Some((public_key, hash_alg)))
+}
 
 impl Signable for Manifest {
     fn sign(&mut self, key_path: PathBuf, hash_alg: HashAlgorithm) -> Result<()> {
+        if let Some(slot) = piv::slot_from_key_spec(&key_path) {
+            return sign_with_piv(self, slot?, hash_alg);
+        }
         let private_key = signing::load_private_key(&key_path)?;
+        sign_with_key(self, &private_key, &hash_alg)
+    }
+}
+
+/// Signs a manifest with an already loaded key, for callers that also use the key otherwise.
+///
+/// The public key is recorded in the claim, see [`SIGNER_KEY_ASSERTION_LABEL`].
+pub fn sign_with_key(
+    manifest: &mut Manifest,
+    private_key: &SecurePrivateKey,
+    hash_alg: &HashAlgorithm,
+) -> Result<()> {
+    record_signer_key(manifest, private_key, hash_alg)?;
+    redaction::add_assertion_salts(&mut manifest.claim, hash_alg)?;
+
+    // Encode the claim deterministically for signing, see `canonical`
+    let claim_cbor = canonical::claim_signing_input(&manifest.claim)?;
+
+    // Use the signing module with the specified algorithm
+    let signature = signing::sign_data_with_algorithm(&claim_cbor, private_key, hash_alg)?;
+
+    // Add signature to claim
+    manifest.claim.signature = Some(STANDARD.encode(&signature));
+
+    Ok(())
+}
+
+/// Signs a manifest with the key in a PIV slot, embedding the slot's certificate.
+///
+/// The certificate is added as an assertion before signing, so the signature covers it. A
+/// certificate from an earlier PIV signature is replaced.
+fn sign_with_piv(manifest: &mut Manifest, slot: PivSlot, hash_alg: HashAlgorithm) -> Result<()> {
+    let certificate = piv::read_certificate(slot)?;
+    let certificate_pem = certificate
+        .to_pem()
+        .map_err(|e| Error::Signing(format!("Failed to encode certificate: {e}")))?;
+
+    let assertion = PivCertificateAssertion {
+        slot: slot.to_string(),
+        certificate: String::from_utf8_lossy(&certificate_pem).into_owned(),
+        hash_algorithm: hash::algorithm_to_string(&hash_alg).to_string(),
+    };
+    manifest.claim.created_assertions.retain(|assertion| {
+        !matches!(assertion, Assertion::CustomAssertion(custom) if custom.label == PIV_CERTIFICATE_ASSERTION_LABEL)
+    });
+    manifest
+        .claim
+        .created_assertions
+        .push(Assertion::CustomAssertion(CustomAssertion {
+            label: PIV_CERTIFICATE_ASSERTION_LABEL.to_string(),
+            data: serde_json::to_value(assertion)?,
+        }));
+
+    manifest.claim.signature = None;
+    redaction::add_assertion_salts(&mut manifest.claim, &hash_alg)?;
+    let claim_cbor = canonical::claim_signing_input(&manifest.claim)?;
+    let signature = piv::sign(&claim_cbor, slot, &certificate, &hash_alg)?;
+    manifest.claim.signature = Some(STANDARD.encode(&signature));
+
+    Ok(())
+}
 
-        // Serialize claim to CBOR for signing
-        let claim_cbor =
-            serde_cbor::to_vec(&self.claim).map_err(|e| Error::Serialization(e.to_string()))?;
+/// Verifies the claim signature of a PIV-signed manifest against its embedded certificate,
+/// returning the certificate, or `None` if the manifest has no embedded certificate
+pub fn verify_certificate_signature(manifest: &Manifest) -> Result<Option<X509>> {
+    let Some(signature) = &manifest.claim.signature else {
+        return Ok(None);
+    };
+    let Some((certificate, hash_alg)) = embedded_certificate(manifest)? else {
+        return Ok(None);
+    };
+    let signature = decode_signature(signature)?;
+    let public_key = certificate
+        .public_key()
+        .map_err(|e| Error::Signing(format!("Invalid embedded certificate: {e}")))?;
+    if !canonical::verify_claim(&manifest.claim, |claim| {
+        signing::verify_signature_with_algorithm(claim, &signature, &public_key, &hash_alg)
+    })? {
+        return Err(Error::Signing(format!(
+            "Signature of manifest {} does not match its embedded certificate",
+            manifest.instance_id
+        )));
+    }
+    Ok(Some(certificate))
+}
+
+// The embedded PIV certificate and the hash algorithm of the signature, if there is one
+fn embedded_certificate(manifest: &Manifest) -> Result<Option<(X509, HashAlgorithm)>> {
+    let Some(data) =
+        manifest
+            .claim
+            .created_assertions
+            .iter()
+            .find_map(|assertion| match assertion {
+                Assertion::CustomAssertion(custom)
+                    if custom.label == PIV_CERTIFICATE_ASSERTION_LABEL =>
+                {
+                    Some(custom.data.clone())
+                }
+                _ => None,
+            })
+    else {
+        return Ok(None);
+    };
+    let assertion: PivCertificateAssertion = serde_json::from_value(data)
+        .map_err(|e| Error::Manifest(format!("Invalid certificate assertion: {e}")))?;
+
+    let certificate = X509::from_pem(assertion.certificate.as_bytes())
+        .map_err(|e| Error::Signing(format!("Invalid embedded certificate: {e}")))?;
+    let hash_alg = hash::parse_algorithm(&assertion.hash_algorithm)?;
+    Ok(Some((certificate, hash_alg)))
+}
+
+/// Signs a manifest with both a classical and an ML-DSA key.
+///
+/// The ML-DSA signature over the claim is added to the claim as an assertion, and the
+/// classical signature then covers the claim including it, binding the two together.
+pub fn sign_hybrid(
+    manifest: &mut Manifest,
+    key_path: PathBuf,
+    pq_key_path: &Path,
+    hash_alg: HashAlgorithm,
+) -> Result<()> {
+    let pq_key = pq::load_ml_dsa_key(pq_key_path)?;
+    let algorithm = pq::ml_dsa_variant(pq_key.as_pkey()).expect("checked when loading");
+
+    // The classical signer is recorded first, so that the ML-DSA signature covers it too
+    let classical_key = match piv::slot_from_key_spec(&key_path) {
+        Some(_) => None,
+        None => {
+            let private_key = signing::load_private_key(&key_path)?;
+            record_signer_key(manifest, &private_key, &hash_alg)?;
+            Some(private_key)
+        }
+    };
+
+    // Salted before the ML-DSA signature, which the salts would otherwise invalidate
+    manifest.claim.signature = None;
+    redaction::add_assertion_salts(&mut manifest.claim, &hash_alg)?;
+    let claim_cbor = canonical::claim_signing_input(&manifest.claim)?;
+    let signature = signing::sign_data_with_algorithm(&claim_cbor, &pq_key, &hash_alg)?;
+    let public_key = pq_key
+        .as_pkey()
+        .public_key_to_pem()
+        .map_err(|e| Error::Signing(format!("Failed to export ML-DSA public key: {e}")))?;
+
+    let assertion = PqSignatureAssertion {
+        algorithm,
+        public_key: String::from_utf8_lossy(&public_key).into_owned(),
+        signature: STANDARD.encode(&signature),
+    };
+    manifest
+        .claim
+        .created_assertions
+        .push(Assertion::CustomAssertion(CustomAssertion {
+            label: PQ_SIGNATURE_ASSERTION_LABEL.to_string(),
+            data: serde_json::to_value(assertion)?,
+        }));
+
+    // A key from stdin can only be read once
+    match classical_key {
+        Some(private_key) => sign_with_key(manifest, &private_key, &hash_alg),
+        None => manifest.sign(key_path, hash_alg),
+    }
+}
 
-        // Use the signing module with the specified algorithm
-        let signature = signing::sign_data_with_algorithm(&claim_cbor, &private_key, &hash_alg)?;
+/// Verifies the ML-DSA signature of a hybrid-signed manifest, returning its parameter set,
+/// or `None` if the manifest has no ML-DSA signature
+pub fn verify_pq_signature(manifest: &Manifest) -> Result<Option<pq::MlDsaVariant>> {
+    let mut claim = manifest.claim.clone();
+    claim.signature = None;
+    let Some(index) = claim.created_assertions.iter().position(|assertion| {
+        matches!(assertion, Assertion::CustomAssertion(custom) if custom.label == PQ_SIGNATURE_ASSERTION_LABEL)
+    }) else {
+        return Ok(None);
+    };
 
-        // Add signature to claim
-        self.claim.signature = Some(STANDARD.encode(&signature));
+    // The ML-DSA signature was made before its own assertion was added
+    let Assertion::CustomAssertion(custom) = claim.created_assertions.remove(index) else {
+        unreachable!("matched above");
+    };
+    let assertion: PqSignatureAssertion = serde_json::from_value(custom.data)
+        .map_err(|e| Error::Manifest(format!("Invalid ML-DSA signature assertion: {e}")))?;
 
-        Ok(())
+    if !canonical::verify_claim(&claim, |claim| pq::verify_assertion(claim, &assertion))? {
+        return Err(Error::Signing(format!(
+            "{} signature of manifest {} is invalid",
+            assertion.algorithm, manifest.instance_id
+        )));
     }
+    Ok(Some(assertion.algorithm))
 }
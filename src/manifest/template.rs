@@ -0,0 +1,169 @@
+//! Manifests created after an existing one.
+//!
+//! A new version of a model usually differs from the previous one only in its files and its
+//! name. `model create --like <id>` takes everything else from the manifest of the previous
+//! version:
+//!
+//! - the authors and the description, unless they are given
+//! - the links to training datasets and other manifests, besides those given
+//! - the license and other custom assertions, unless the new manifest makes its own
+//!
+//! Assertions about one version rather than the asset are not copied: those describing
+//! its files or how it was signed, attested, approved or migrated, and its validity.
+//! Neither are assertions contributed by generators, which run again, nor encrypted
+//! assertions.
+
+use crate::cc_attestation::mock::MockReport;
+use crate::cc_attestation::tpm::TPM_QUOTE_ASSERTION_LABEL;
+use crate::error::{Error, Result};
+use crate::manifest::common::INGREDIENT_SIZES_ASSERTION_LABEL;
+use crate::manifest::config::ManifestCreationConfig;
+use crate::manifest::encryption::ENCRYPTED_PAYLOAD_KEY;
+use crate::manifest::generators::{GENERATORS_ASSERTION_LABEL, GeneratorRecord};
+use crate::manifest::utils::determine_manifest_type;
+use crate::manifest::{
+    approval, attestation, claims, dockerfile, ensure_c2pa_urn, ingest, legacy, linking, migration,
+    object_store, policy, profile, python_env, redaction, retention, signer, validity,
+};
+use crate::signing::{piv, pq};
+use crate::storage::traits::ManifestType;
+use atlas_c2pa_lib::assertion::{Assertion, CustomAssertion};
+use atlas_c2pa_lib::manifest::Manifest;
+
+// Assertions about one version of an asset, which are made anew for each version
+const VERSION_ASSERTION_LABELS: &[&str] = &[
+    INGREDIENT_SIZES_ASSERTION_LABEL,
+    object_store::PROVIDER_CHECKSUMS_ASSERTION_LABEL,
+    ingest::INGEST_POLICY_ASSERTION_LABEL,
+    profile::DATASET_PROFILE_ASSERTION_LABEL,
+    python_env::PYTHON_ENV_ASSERTION_LABEL,
+    dockerfile::CONTAINER_BUILD_ASSERTION_LABEL,
+    legacy::LEGACY_IMPORT_ASSERTION_LABEL,
+    validity::VALIDITY_ASSERTION_LABEL,
+    policy::POLICY_OVERRIDE_ASSERTION_LABEL,
+    GENERATORS_ASSERTION_LABEL,
+    TPM_QUOTE_ASSERTION_LABEL,
+    attestation::ATTESTATION_ASSERTION_LABEL,
+    approval::APPROVAL_ASSERTION_LABEL,
+    redaction::REDACTION_ASSERTION_LABEL,
+    redaction::ASSERTION_SALTS_ASSERTION_LABEL,
+    retention::RETIREMENT_ASSERTION_LABEL,
+    migration::SCHEMA_MIGRATION_ASSERTION_LABEL,
+    signer::SIGNER_KEY_ASSERTION_LABEL,
+    piv::PIV_CERTIFICATE_ASSERTION_LABEL,
+    pq::PQ_SIGNATURE_ASSERTION_LABEL,
+];
+
+// Names recorded for authors that were not given
+const PLACEHOLDER_ORGANIZATION: &str = "Organization";
+const PLACEHOLDER_PERSON: &str = "Unknown";
+
+/// Fills `config` from the model manifest `template`, see the module documentation
+pub fn apply_template(config: &mut ManifestCreationConfig, template: &Manifest) -> Result<()> {
+    if determine_manifest_type(template) != ManifestType::Model {
+        return Err(Error::Validation(format!(
+            "Manifest {} is not a model manifest",
+            template.instance_id
+        )));
+    }
+
+    for assertion in claims::assertions(template) {
+        match assertion {
+            Assertion::CreativeWork(work) => {
+                for author in &work.author {
+                    let (field, placeholder) = match author.author_type.as_str() {
+                        "Organization" => (&mut config.author_org, PLACEHOLDER_ORGANIZATION),
+                        "Person" => (&mut config.author_name, PLACEHOLDER_PERSON),
+                        _ => continue,
+                    };
+                    if field.is_none() && author.name != placeholder {
+                        *field = Some(author.name.clone());
+                    }
+                }
+            }
+            Assertion::Action(actions) if config.description.is_none() => {
+                config.description = actions
+                    .actions
+                    .iter()
+                    .filter(|action| action.action == "c2pa.created")
+                    .find_map(|action| action.parameters.as_ref()?.get("description")?.as_str())
+                    .map(str::to_string);
+            }
+            _ => {}
+        }
+    }
+
+    let dataset_ids: Vec<&str> = template
+        .ingredients
+        .iter()
+        .chain(&claims::active_claim(template).ingredients)
+        .filter(|ingredient| linking::is_dataset_link(ingredient))
+        .filter_map(|ingredient| Some(ingredient.linked_ingredient.as_ref()?.url.as_str()))
+        .collect();
+    for id in &dataset_ids {
+        if !config.dataset_ids.iter().any(|given| given == id) {
+            config.dataset_ids.push(id.to_string());
+        }
+    }
+
+    // Versioned references (urn:c2pa:<uuid>:<generator>:<version>) record an earlier state
+    // of a manifest that is also linked by its plain ID
+    let linked: Vec<&str> = template
+        .cross_references
+        .iter()
+        .map(|reference| reference.manifest_url.as_str())
+        .filter(|id| id.split(':').count() <= 3)
+        .filter(|id| {
+            !dataset_ids
+                .iter()
+                .any(|dataset| ensure_c2pa_urn(dataset) == *id)
+        })
+        .collect();
+    if !linked.is_empty() {
+        let linked_manifests = config.linked_manifests.get_or_insert_with(Vec::new);
+        for id in linked {
+            if !linked_manifests.iter().any(|given| given == id) {
+                linked_manifests.push(id.to_string());
+            }
+        }
+    }
+
+    config.inherited_assertions = inherited_assertions(template)?;
+    Ok(())
+}
+
+/// The custom assertions of `template` that describe the asset rather than one version of it
+pub fn inherited_assertions(template: &Manifest) -> Result<Vec<CustomAssertion>> {
+    let mut generated = Vec::new();
+    for assertion in claims::assertions(template) {
+        if let Assertion::CustomAssertion(custom) = assertion
+            && custom.label == GENERATORS_ASSERTION_LABEL
+        {
+            let records: Vec<GeneratorRecord> = serde_json::from_value(custom.data.clone())?;
+            generated.extend(records.into_iter().flat_map(|record| record.assertions));
+        }
+    }
+
+    Ok(claims::assertions(template)
+        .filter_map(|assertion| match assertion {
+            Assertion::CustomAssertion(custom) => Some(custom),
+            _ => None,
+        })
+        .filter(|custom| {
+            !VERSION_ASSERTION_LABELS.contains(&custom.label.as_str())
+                && !generated.contains(&custom.label)
+                && custom.data.get(ENCRYPTED_PAYLOAD_KEY).is_none()
+                && !is_cc_attestation(&custom.data)
+        })
+        .cloned()
+        .collect())
+}
+
+// CC attestations are labelled after their platform, and hold the report either as a JSON
+// string or, with the event log, in an object
+fn is_cc_attestation(data: &serde_json::Value) -> bool {
+    data.get("report").is_some()
+        || data
+            .as_str()
+            .is_some_and(|report| serde_json::from_str::<MockReport>(report).is_ok())
+}
@@ -0,0 +1,101 @@
+//! Manifest timestamps.
+//!
+//! Timestamps are recorded in UTC and written as RFC 3339, whatever offset they were given
+//! in. A manifest created for a historical artifact can carry its original creation time
+//! with `--created-at`; `manifest show` displays it in local time next to the recorded value.
+
+use crate::error::{Error, Result};
+use chrono::TimeZone;
+use time::format_description::well_known::Rfc3339;
+use time::{Date, OffsetDateTime, Time, UtcOffset};
+
+/// Formats a timestamp as RFC 3339 in UTC, e.g. `2024-03-01T09:30:00Z`
+///
+/// # Examples
+///
+/// ```
+/// use atlas_cli::manifest::timestamp::to_rfc3339_utc;
+/// use time::macros::datetime;
+///
+/// assert_eq!(
+///     to_rfc3339_utc(datetime!(2024-03-01 10:30 +01:00)),
+///     "2024-03-01T09:30:00Z"
+/// );
+/// ```
+pub fn to_rfc3339_utc(timestamp: OffsetDateTime) -> String {
+    timestamp
+        .to_offset(UtcOffset::UTC)
+        .format(&Rfc3339)
+        // Only years outside 0..=9999 fail to format
+        .unwrap_or_else(|_| timestamp.to_string())
+}
+
+/// Parses a `--created-at` value: an RFC 3339 timestamp with any offset, or a date
+/// (`YYYY-MM-DD`, midnight UTC). The result is in UTC and may not be in the future.
+pub fn parse_created_at(s: &str) -> Result<OffsetDateTime> {
+    let s = s.trim();
+    let parsed = OffsetDateTime::parse(s, &Rfc3339).or_else(|_| {
+        Date::parse(s, time::macros::format_description!("[year]-[month]-[day]"))
+            .map(|date| date.with_time(Time::MIDNIGHT).assume_utc())
+    });
+    let timestamp = parsed
+        .map_err(|_| {
+            Error::Validation(format!(
+                "Invalid creation time '{s}'. Expected an RFC 3339 timestamp (e.g. 2021-06-01T12:00:00+02:00) or a date (YYYY-MM-DD)"
+            ))
+        })?
+        .to_offset(UtcOffset::UTC);
+
+    if timestamp > OffsetDateTime::now_utc() {
+        return Err(Error::Validation(format!(
+            "Creation time {} is in the future",
+            to_rfc3339_utc(timestamp)
+        )));
+    }
+    Ok(timestamp)
+}
+
+/// Formats a timestamp in the local time zone, followed by the recorded UTC value, e.g.
+/// `2024-03-01 10:30:00 +01:00 (2024-03-01T09:30:00Z)`
+pub fn display_local(timestamp: OffsetDateTime) -> String {
+    let utc = to_rfc3339_utc(timestamp);
+    let local = chrono::Local
+        .timestamp_opt(timestamp.unix_timestamp(), timestamp.nanosecond())
+        .single();
+    match local {
+        Some(local) => format!("{} ({utc})", local.format("%Y-%m-%d %H:%M:%S %:z")),
+        None => utc,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    #[test]
+    fn test_parse_created_at() -> Result<()> {
+        assert_eq!(
+            parse_created_at("2021-06-01T12:00:00+02:00")?,
+            datetime!(2021-06-01 10:00 UTC)
+        );
+        assert_eq!(
+            parse_created_at("2019-11-05")?,
+            datetime!(2019-11-05 00:00 UTC)
+        );
+        assert_eq!(parse_created_at("2019-11-05")?.offset(), UtcOffset::UTC);
+
+        assert!(parse_created_at("05/11/2019").is_err());
+        assert!(parse_created_at("2019-11-05 12:00").is_err());
+        let tomorrow = OffsetDateTime::now_utc() + time::Duration::days(1);
+        assert!(parse_created_at(&to_rfc3339_utc(tomorrow)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_display_local() {
+        let timestamp = datetime!(2024-03-01 10:30 +01:00);
+        let shown = display_local(timestamp);
+        assert!(shown.ends_with(" (2024-03-01T09:30:00Z)"), "{shown}");
+    }
+}
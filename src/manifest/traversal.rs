@@ -0,0 +1,165 @@
+//! Concurrent retrieval of linked manifests.
+//!
+//! Walking a provenance graph one manifest at a time is slow against remote backends
+//! such as Rekor. These helpers fetch manifests on a bounded number of worker threads
+//! and always return results in the order they were requested, so that output built
+//! from them does not depend on scheduling.
+
+use crate::error::Result;
+use crate::manifest::attestation;
+use crate::storage::traits::StorageBackend;
+use atlas_c2pa_lib::manifest::Manifest;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/// Maximum number of manifests fetched at the same time
+pub const MAX_CONCURRENT_FETCHES: usize = 8;
+
+/// Applies `f` to every item on at most `parallelism` threads, returning the results in
+/// the order of `items`
+///
+/// # Examples
+///
+/// ```
+/// use atlas_cli::manifest::traversal::map_concurrent;
+///
+/// let squares = map_concurrent(&[1, 2, 3, 4], 2, |n| n * n);
+/// assert_eq!(squares, vec![1, 4, 9, 16]);
+/// ```
+pub fn map_concurrent<T, R, F>(items: &[T], parallelism: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let workers = parallelism.clamp(1, items.len().max(1));
+    if workers == 1 {
+        return items.iter().map(f).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<R>>> = Mutex::new(items.iter().map(|_| None).collect());
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(item) = items.get(index) else {
+                        break;
+                    };
+                    let result = f(item);
+                    results.lock().unwrap()[index] = Some(result);
+                }
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|result| result.expect("every item is processed"))
+        .collect()
+}
+
+/// Retrieves the given manifests concurrently, in the order of `ids`
+pub fn fetch_manifests(
+    ids: &[String],
+    storage: &(impl StorageBackend + ?Sized),
+    parallelism: usize,
+) -> Vec<Result<Manifest>> {
+    map_concurrent(ids, parallelism, |id| storage.retrieve_manifest(id))
+}
+
+/// Retrieves every manifest reachable from `root_id` through cross-references within
+/// `max_depth` hops, one level of the graph at a time.
+///
+/// Retrieval errors are kept in the returned map rather than aborting the walk, so the
+/// caller can report them at the point where the manifest is needed.
+pub fn prefetch_linked_manifests(
+    root_id: &str,
+    storage: &(impl StorageBackend + ?Sized),
+    max_depth: u32,
+    parallelism: usize,
+) -> HashMap<String, Result<Manifest>> {
+    let mut fetched = HashMap::new();
+    let mut seen = HashSet::from([root_id.to_string()]);
+    let mut level = vec![root_id.to_string()];
+
+    for depth in 0..=max_depth {
+        if level.is_empty() {
+            break;
+        }
+
+        let results = fetch_manifests(&level, storage, parallelism);
+        let mut next_level = Vec::new();
+
+        for (id, result) in level.into_iter().zip(results) {
+            if depth < max_depth
+                && let Ok(manifest) = &result
+            {
+                // Envelopes are leaves of the graph, see `attestation::link_envelope`
+                for cross_ref in manifest
+                    .cross_references
+                    .iter()
+                    .filter(|cross_ref| !attestation::is_envelope_reference(cross_ref))
+                {
+                    if seen.insert(cross_ref.manifest_url.clone()) {
+                        next_level.push(cross_ref.manifest_url.clone());
+                    }
+                }
+            }
+            fetched.insert(id, result);
+        }
+
+        level = next_level;
+    }
+
+    fetched
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use std::time::Duration;
+
+    #[test]
+    fn test_map_concurrent_preserves_order() {
+        let items: Vec<u64> = (0..50).collect();
+
+        // later items finish first
+        let results = map_concurrent(&items, 8, |n| {
+            thread::sleep(Duration::from_millis(50 - n));
+            n * 2
+        });
+
+        assert_eq!(results, items.iter().map(|n| n * 2).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_map_concurrent_is_bounded() {
+        let active = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+
+        map_concurrent(&[(); 32], 4, |_| {
+            let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+            peak.fetch_max(now, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(5));
+            active.fetch_sub(1, Ordering::SeqCst);
+        });
+
+        assert!(peak.load(Ordering::SeqCst) <= 4);
+    }
+
+    #[test]
+    fn test_map_concurrent_empty() {
+        let results: Vec<Result<()>> = map_concurrent(&[] as &[u8], 8, |_| {
+            Err(Error::Validation("unreachable".to_string()))
+        });
+        assert!(results.is_empty());
+    }
+}
@@ -1,4 +1,5 @@
 use crate::error::{Error, Result};
+use crate::manifest::claims;
 use crate::storage::traits::ManifestType;
 use atlas_c2pa_lib::assertion::Assertion;
 use atlas_c2pa_lib::asset_type::AssetType;
@@ -186,35 +187,20 @@ pub fn determine_dataset_type(path: &Path) -> Result<AssetType> {
 /// to determine whether it's a Dataset, Model, Software, or other type.
 pub fn determine_manifest_type(manifest: &Manifest) -> ManifestType {
     // Check for Dataset assertion
-    let has_dataset_assertion = (if let Some(claim) = &manifest.claim_v2 {
-        claim.created_assertions.iter().any(|assertion| {
-            if let Assertion::CreativeWork(creative_work) = assertion {
-                creative_work.creative_type == "Dataset"
-            } else {
-                false
-            }
-        })
-    } else {
-        false
-    }) || manifest.claim.created_assertions.iter().any(|assertion| {
-        if let Assertion::CreativeWork(creative_work) = assertion {
-            creative_work.creative_type == "Dataset"
-        } else {
-            false
-        }
-    });
+    let has_dataset_assertion = claims::has_creative_work(manifest, "Dataset");
 
-    // Check for Dataset ingredients
+    // Check for Dataset ingredients, other than links to dataset manifests
     let has_dataset_ingredients = manifest.ingredients.iter().any(|ingredient| {
-        ingredient.data.data_types.iter().any(|t| {
-            matches!(
-                t,
-                AssetType::Dataset
-                    | AssetType::DatasetOnnx
-                    | AssetType::DatasetTensorFlow
-                    | AssetType::DatasetPytorch
-            )
-        })
+        !super::linking::is_dataset_link(ingredient)
+            && ingredient.data.data_types.iter().any(|t| {
+                matches!(
+                    t,
+                    AssetType::Dataset
+                        | AssetType::DatasetOnnx
+                        | AssetType::DatasetTensorFlow
+                        | AssetType::DatasetPytorch
+                )
+            })
     });
 
     // If we have a Dataset assertion OR Dataset ingredients, return Dataset
@@ -223,54 +209,10 @@ pub fn determine_manifest_type(manifest: &Manifest) -> ManifestType {
     }
 
     // Check for Software assertion
-    let has_software_assertion = (if let Some(claim) = &manifest.claim_v2 {
-        claim.created_assertions.iter().any(|assertion| {
-            if let Assertion::CreativeWork(creative_work) = assertion {
-                creative_work.creative_type == "Software"
-            } else {
-                false
-            }
-        })
-    } else {
-        false
-    }) || manifest.claim.created_assertions.iter().any(|assertion| {
-        if let Assertion::CreativeWork(creative_work) = assertion {
-            creative_work.creative_type == "Software"
-        } else {
-            false
-        }
-    });
+    let has_software_assertion = claims::has_creative_work(manifest, "Software");
 
     // Check for Software parameters in Action assertions
-    let has_software_parameters = (if let Some(claim) = &manifest.claim_v2 {
-        claim.created_assertions.iter().any(|assertion| {
-            if let Assertion::Action(action_assertion) = assertion {
-                action_assertion.actions.iter().any(|action| {
-                    if let Some(params) = &action.parameters {
-                        params.get("software_type").is_some()
-                    } else {
-                        false
-                    }
-                })
-            } else {
-                false
-            }
-        })
-    } else {
-        false
-    }) || manifest.claim.created_assertions.iter().any(|assertion| {
-        if let Assertion::Action(action_assertion) = assertion {
-            action_assertion.actions.iter().any(|action| {
-                if let Some(params) = &action.parameters {
-                    params.get("software_type").is_some()
-                } else {
-                    false
-                }
-            })
-        } else {
-            false
-        }
-    });
+    let has_software_parameters = claims::has_action_parameter(manifest, "software_type");
 
     // Check for Software ingredients
     let has_software_ingredients = manifest.ingredients.iter().any(|ingredient| {
@@ -287,23 +229,7 @@ pub fn determine_manifest_type(manifest: &Manifest) -> ManifestType {
     }
 
     // Check for Model assertion
-    let has_model_assertion = (if let Some(claim) = &manifest.claim_v2 {
-        claim.created_assertions.iter().any(|assertion| {
-            if let Assertion::CreativeWork(creative_work) = assertion {
-                creative_work.creative_type == "Model"
-            } else {
-                false
-            }
-        })
-    } else {
-        false
-    }) || manifest.claim.created_assertions.iter().any(|assertion| {
-        if let Assertion::CreativeWork(creative_work) = assertion {
-            creative_work.creative_type == "Model"
-        } else {
-            false
-        }
-    });
+    let has_model_assertion = claims::has_creative_work(manifest, "Model");
 
     // Check for Model ingredients
     let has_model_ingredients = manifest.ingredients.iter().any(|ingredient| {
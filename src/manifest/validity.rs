@@ -0,0 +1,299 @@
+//! Validity windows for manifests.
+//!
+//! A manifest created with `--valid-until` carries a validity assertion with its expiry
+//! time. Verification fails once a manifest has expired and warns when it is about to,
+//! so that models can be re-attested on a schedule.
+
+use crate::error::{Error, Result};
+use crate::manifest::claims;
+use crate::manifest::traversal;
+use crate::storage::traits::{ManifestMetadata, StorageBackend};
+use atlas_c2pa_lib::assertion::{Assertion, CustomAssertion};
+use atlas_c2pa_lib::manifest::Manifest;
+use serde::{Deserialize, Serialize};
+use time::format_description::well_known::Rfc3339;
+use time::{Date, Duration, OffsetDateTime, Time};
+
+/// Label of the validity assertion
+pub const VALIDITY_ASSERTION_LABEL: &str = "validity";
+
+/// Verification warns about manifests expiring within this window
+pub const EXPIRY_WARNING_WINDOW: Duration = Duration::days(30);
+
+/// Contents of the validity assertion
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidityAssertion {
+    #[serde(with = "time::serde::rfc3339")]
+    pub valid_until: OffsetDateTime,
+}
+
+/// Validity of a manifest at a point in time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Validity {
+    /// The manifest has no expiry
+    Unbounded,
+    /// The manifest is valid until the given time
+    ValidUntil(OffsetDateTime),
+    /// The manifest expired at the given time
+    Expired(OffsetDateTime),
+}
+
+/// Parses a duration such as `30d`, `12h` or `2w`
+///
+/// # Examples
+///
+/// ```
+/// use atlas_cli::manifest::validity::parse_duration;
+/// use time::Duration;
+///
+/// assert_eq!(parse_duration("30d").unwrap(), Duration::days(30));
+/// assert_eq!(parse_duration("2w").unwrap(), Duration::weeks(2));
+/// assert!(parse_duration("soon").is_err());
+/// ```
+pub fn parse_duration(s: &str) -> Result<Duration> {
+    let invalid = || {
+        Error::Validation(format!(
+            "Invalid duration '{s}'. Expected a number followed by s, m, h, d or w (e.g. 30d)"
+        ))
+    };
+
+    let s = s.trim();
+    let unit_start = s.find(|c: char| !c.is_ascii_digit()).ok_or_else(invalid)?;
+    let (value, unit) = s.split_at(unit_start);
+    let value: i64 = value.parse().map_err(|_| invalid())?;
+
+    match unit {
+        "s" => Ok(Duration::seconds(value)),
+        "m" => Ok(Duration::minutes(value)),
+        "h" => Ok(Duration::hours(value)),
+        "d" => Ok(Duration::days(value)),
+        "w" => Ok(Duration::weeks(value)),
+        _ => Err(invalid()),
+    }
+}
+
+/// Parses a `--valid-until` value: an RFC 3339 timestamp, a date (`YYYY-MM-DD`, valid
+/// until the end of that day in UTC) or a duration from now (e.g. `90d`)
+///
+/// # Examples
+///
+/// ```
+/// use atlas_cli::manifest::validity::parse_valid_until;
+///
+/// let until = parse_valid_until("2030-06-30").unwrap();
+/// assert_eq!(until.to_string(), "2030-06-30 23:59:59.0 +00:00:00");
+///
+/// assert!(parse_valid_until("2030-06-30T12:00:00Z").is_ok());
+/// assert!(parse_valid_until("90d").is_ok());
+/// ```
+pub fn parse_valid_until(s: &str) -> Result<OffsetDateTime> {
+    if let Ok(timestamp) = OffsetDateTime::parse(s, &Rfc3339) {
+        return Ok(timestamp);
+    }
+
+    let date_format = time::macros::format_description!("[year]-[month]-[day]");
+    if let Ok(date) = Date::parse(s, &date_format) {
+        let end_of_day = Time::from_hms(23, 59, 59).expect("valid time");
+        return Ok(date.with_time(end_of_day).assume_utc());
+    }
+
+    parse_duration(s)
+        .map(|duration| OffsetDateTime::now_utc() + duration)
+        .map_err(|_| {
+            Error::Validation(format!(
+                "Invalid validity '{s}'. Expected an RFC 3339 timestamp, a date (YYYY-MM-DD) or a duration (e.g. 90d)"
+            ))
+        })
+}
+
+/// Creates the validity assertion for a manifest expiring at `valid_until`
+pub fn validity_assertion(valid_until: OffsetDateTime) -> Result<CustomAssertion> {
+    Ok(CustomAssertion {
+        label: VALIDITY_ASSERTION_LABEL.to_string(),
+        data: serde_json::to_value(ValidityAssertion { valid_until })?,
+    })
+}
+
+/// Returns the expiry time recorded in a manifest, if any
+pub fn valid_until(manifest: &Manifest) -> Result<Option<OffsetDateTime>> {
+    let assertions = &claims::signed_claim(manifest).created_assertions;
+
+    for assertion in assertions {
+        if let Assertion::CustomAssertion(custom) = assertion
+            && custom.label == VALIDITY_ASSERTION_LABEL
+        {
+            let validity: ValidityAssertion = serde_json::from_value(custom.data.clone())
+                .map_err(|e| Error::Manifest(format!("Invalid validity assertion: {e}")))?;
+            return Ok(Some(validity.valid_until));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Checks the validity of a manifest at time `now`
+pub fn check_validity(manifest: &Manifest, now: OffsetDateTime) -> Result<Validity> {
+    Ok(match valid_until(manifest)? {
+        None => Validity::Unbounded,
+        Some(until) if until <= now => Validity::Expired(until),
+        Some(until) => Validity::ValidUntil(until),
+    })
+}
+
+/// Returns the stored manifests that expire within `within` from now, including those
+/// that have already expired, ordered by expiry
+pub fn expiring_manifests(
+    storage: &(impl StorageBackend + ?Sized),
+    within: Duration,
+) -> Result<Vec<(ManifestMetadata, OffsetDateTime)>> {
+    let listed = storage.list_manifests()?;
+    let ids: Vec<String> = listed.iter().map(|metadata| metadata.id.clone()).collect();
+    let manifests = traversal::fetch_manifests(&ids, storage, traversal::MAX_CONCURRENT_FETCHES);
+    let deadline = OffsetDateTime::now_utc() + within;
+
+    let mut expiring = Vec::new();
+    for (metadata, manifest) in listed.into_iter().zip(manifests) {
+        let until = match manifest.and_then(|manifest| valid_until(&manifest)) {
+            Ok(Some(until)) => until,
+            Ok(None) => continue,
+            Err(e) => {
+                // Log but don't fail on unreadable manifests
+                eprintln!("Error reading manifest {}: {e}", metadata.id);
+                continue;
+            }
+        };
+
+        if until <= deadline {
+            expiring.push((metadata, until));
+        }
+    }
+
+    expiring.sort_by_key(|(_, until)| *until);
+    Ok(expiring)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest::common::AssetKind;
+    use crate::tests::common::{
+        ManifestBuilder, MockStorageBackend, create_test_ingredient_internal,
+        create_test_manifest_internal,
+    };
+    use crate::utils::safe_create_file;
+    use atlas_c2pa_lib::asset_type::AssetType;
+    use atlas_c2pa_lib::claim::ClaimV2;
+    use atlas_c2pa_lib::datetime_wrapper::OffsetDateTimeWrapper;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    fn manifest_with(assertions: Vec<Assertion>) -> Manifest {
+        let claim = ClaimV2 {
+            instance_id: "urn:c2pa:test".to_string(),
+            ingredients: vec![],
+            created_assertions: assertions,
+            claim_generator_info: "test".to_string(),
+            signature: None,
+            created_at: OffsetDateTimeWrapper(OffsetDateTime::now_utc()),
+        };
+        ManifestBuilder::new("Test Manifest")
+            .id("test")
+            .claim(claim.clone())
+            .claim_v2(Some(claim))
+            .build()
+    }
+
+    #[test]
+    fn test_check_validity() -> Result<()> {
+        let now = OffsetDateTime::now_utc();
+        let until = now + Duration::days(10);
+        let manifest = manifest_with(vec![Assertion::CustomAssertion(validity_assertion(until)?)]);
+
+        assert_eq!(check_validity(&manifest, now)?, Validity::ValidUntil(until));
+        assert_eq!(
+            check_validity(&manifest, now + Duration::days(11))?,
+            Validity::Expired(until)
+        );
+        assert_eq!(
+            check_validity(&manifest_with(vec![]), now)?,
+            Validity::Unbounded
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_values() {
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("10y").is_err());
+        assert!(parse_valid_until("next tuesday").is_err());
+
+        let manifest = manifest_with(vec![Assertion::CustomAssertion(CustomAssertion {
+            label: VALIDITY_ASSERTION_LABEL.to_string(),
+            data: serde_json::json!({"valid_until": "never"}),
+        })]);
+        assert!(valid_until(&manifest).is_err());
+    }
+
+    #[test]
+    fn test_manifest_validity_window() -> Result<()> {
+        use crate::manifest::common::verify_manifest;
+
+        let dir = tempdir()?;
+        let model_path = dir.path().join("model.onnx");
+        safe_create_file(&model_path, false)?.write_all(b"mock model data")?;
+        let ingredient = create_test_ingredient_internal(
+            &model_path,
+            "Test Model",
+            AssetType::ModelOnnx,
+            "application/onnx",
+        )?;
+
+        let manifest_valid_until =
+            |id: &str, valid_until: Option<OffsetDateTime>| -> Result<Manifest> {
+                let mut manifest = create_test_manifest_internal(
+                    id.to_string(),
+                    vec![ingredient.clone()],
+                    id,
+                    AssetKind::Model,
+                )?;
+                let Some(valid_until) = valid_until else {
+                    return Ok(manifest);
+                };
+                let assertion = Assertion::CustomAssertion(validity_assertion(valid_until)?);
+                manifest.claim.created_assertions.push(assertion.clone());
+                if let Some(claim) = manifest.claim_v2.as_mut() {
+                    claim.created_assertions.push(assertion);
+                }
+                Ok(manifest)
+            };
+
+        let now = OffsetDateTime::now_utc();
+        let mut storage = MockStorageBackend::new_empty();
+        storage.add_manifest(manifest_valid_until(
+            "expired",
+            Some(now - Duration::days(1)),
+        )?);
+        storage.add_manifest(manifest_valid_until(
+            "soon",
+            Some(now + Duration::days(10)),
+        )?);
+        storage.add_manifest(manifest_valid_until(
+            "later",
+            Some(now + Duration::days(365)),
+        )?);
+        storage.add_manifest(manifest_valid_until("unbounded", None)?);
+
+        assert!(verify_manifest("expired", &storage).is_err());
+        assert!(verify_manifest("soon", &storage).is_ok());
+        assert!(verify_manifest("later", &storage).is_ok());
+        assert!(verify_manifest("unbounded", &storage).is_ok());
+
+        let expiring: Vec<String> = expiring_manifests(&storage, Duration::days(30))?
+            .into_iter()
+            .map(|(metadata, _)| metadata.id)
+            .collect();
+        assert_eq!(expiring, vec!["expired", "soon"]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,697 @@
+//! Verification engine.
+//!
+//! Verifying a manifest means many independent checks: re-hashing each ingredient,
+//! checking signatures and fetching and hashing every cross-referenced manifest. The engine
+//! runs such checks from a work queue on a bounded number of threads, and returns their
+//! results in the order they were submitted, so that reports built from them do not depend
+//! on scheduling. Checks that have not started are skipped once the run is cancelled,
+//! either through a [`CancellationToken`] or, in fail-fast mode, by the first failure.
+//!
+//! Checks can be weighted by the number of bytes they hash. The heaviest checks are started
+//! first, so that a few huge files among thousands of tiny ones are not left to hash on
+//! their own at the end of the run, and threads share the work by bytes rather than by
+//! number of files.
+//!
+//! Results are shown as soon as their check completes, followed by a summary, so that
+//! verifying a large manifest shows progress. With `--progress=ndjson`, each result and the
+//! summary are also written to stderr as JSON events, one per line, see [`Progress`].
+//!
+//! With `--explain`, checks also describe what they compared, such as the algorithm and the
+//! stored and computed digests of an artifact or the key a signature verified with, see
+//! [`Explanation`].
+
+use crate::cli::color;
+use crate::error::{Error, Result};
+use std::cmp::Reverse;
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+/// Most checks run at the same time by default
+pub const MAX_PARALLELISM: usize = 8;
+
+/// Number of hex digits of a digest shown in explanations
+pub const DIGEST_PREFIX_LEN: usize = 16;
+
+// Whether checks explain what they compared, set with --explain
+static EXPLAIN: AtomicBool = AtomicBool::new(false);
+
+// Whether progress events are written to stderr, set with --progress=ndjson
+static NDJSON_PROGRESS: AtomicBool = AtomicBool::new(false);
+
+/// How the progress of a verification run is shown
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ProgressFormat {
+    /// Each result on stdout as its check completes, then a summary
+    #[default]
+    Text,
+    /// As text, and also one JSON event per result and summary on stderr
+    Ndjson,
+}
+
+/// Sets how progress is shown, for the rest of the process
+pub fn set_progress_format(format: ProgressFormat) {
+    NDJSON_PROGRESS.store(format == ProgressFormat::Ndjson, Ordering::Relaxed);
+}
+
+pub fn progress_format() -> ProgressFormat {
+    if NDJSON_PROGRESS.load(Ordering::Relaxed) {
+        ProgressFormat::Ndjson
+    } else {
+        ProgressFormat::Text
+    }
+}
+
+/// Makes verification explain what each check compared
+pub fn set_explain(enabled: bool) {
+    EXPLAIN.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_explaining() -> bool {
+    EXPLAIN.load(Ordering::Relaxed)
+}
+
+/// First [`DIGEST_PREFIX_LEN`] hex digits of a digest
+pub fn digest_prefix(digest: &str) -> &str {
+    digest.get(..DIGEST_PREFIX_LEN).unwrap_or(digest)
+}
+
+/// What a check compared, appended to its message or error with `--explain`
+#[derive(Debug, Clone, Default)]
+pub struct Explanation {
+    lines: Vec<String>,
+}
+
+impl Explanation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn line(mut self, line: impl Into<String>) -> Self {
+        self.lines.push(line.into());
+        self
+    }
+
+    /// Records a comparison of a stored digest with one computed with `algorithm`
+    pub fn digests(self, algorithm: &str, stored: &str, computed: &str) -> Self {
+        let verdict = if stored == computed {
+            "match"
+        } else {
+            "differ"
+        };
+        self.line(format!(
+            "{algorithm}: stored {}..., computed {}... ({verdict})",
+            digest_prefix(stored),
+            digest_prefix(computed)
+        ))
+    }
+
+    /// Appends the explanation to the message of a passed check
+    pub fn message(&self, message: String) -> String {
+        if !is_explaining() {
+            return message;
+        }
+        format!("{message}{self}")
+    }
+
+    /// Appends the explanation to the error of a failed check
+    pub fn error(&self, error: Error) -> Error {
+        if !is_explaining() || self.lines.is_empty() {
+            return error;
+        }
+        match error {
+            Error::Validation(message) => Error::Validation(format!("{message}{self}")),
+            Error::NotFound(message) => Error::NotFound(format!("{message}{self}")),
+            Error::Signing(message) => Error::Signing(format!("{message}{self}")),
+            error => error,
+        }
+    }
+
+    /// Prints the explanation of a step that is not run as a check
+    pub fn print(&self) {
+        if is_explaining() && !self.lines.is_empty() {
+            println!("{}", self.to_string().trim_start_matches('\n'));
+        }
+    }
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in &self.lines {
+            write!(f, "\n    - {line}")?;
+        }
+        Ok(())
+    }
+}
+
+/// What a check verifies
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckKind {
+    /// Re-hashing an artifact
+    Hash,
+    /// Verifying a signature
+    Signature,
+    /// Fetching and hashing a cross-referenced manifest
+    CrossReference,
+    /// Fetching and hashing a linked manifest and its local files
+    ManifestLink,
+}
+
+impl fmt::Display for CheckKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckKind::Hash => write!(f, "hash"),
+            CheckKind::Signature => write!(f, "signature"),
+            CheckKind::CrossReference => write!(f, "cross-reference"),
+            CheckKind::ManifestLink => write!(f, "manifest link"),
+        }
+    }
+}
+
+/// A unit of verification work. It returns a message describing what was verified.
+pub struct Check<'a> {
+    pub kind: CheckKind,
+    /// What is checked, such as an ingredient title or manifest ID
+    pub subject: String,
+    /// Cost of the check, such as the number of bytes it hashes
+    pub weight: u64,
+    run: Box<dyn FnOnce() -> Result<String> + Send + 'a>,
+}
+
+impl<'a> Check<'a> {
+    pub fn new(
+        kind: CheckKind,
+        subject: impl Into<String>,
+        run: impl FnOnce() -> Result<String> + Send + 'a,
+    ) -> Self {
+        Self {
+            kind,
+            subject: subject.into(),
+            weight: 0,
+            run: Box::new(run),
+        }
+    }
+
+    /// Sets the cost of the check; heavier checks are started first
+    pub fn with_weight(mut self, weight: u64) -> Self {
+        self.weight = weight;
+        self
+    }
+}
+
+impl fmt::Debug for Check<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Check")
+            .field("kind", &self.kind)
+            .field("subject", &self.subject)
+            .field("weight", &self.weight)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Outcome of a check
+#[derive(Debug)]
+pub enum Outcome {
+    /// The check passed, with its message
+    Passed(String),
+    Failed(Error),
+    /// The run was cancelled before the check started
+    Cancelled,
+}
+
+/// Result of a check, see [`VerificationEngine::run`]
+#[derive(Debug)]
+pub struct CheckResult {
+    pub kind: CheckKind,
+    pub subject: String,
+    pub outcome: Outcome,
+}
+
+impl CheckResult {
+    pub fn passed(&self) -> bool {
+        matches!(self.outcome, Outcome::Passed(_))
+    }
+}
+
+/// Shared flag that stops a verification run from starting further checks
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Runs checks with bounded parallelism
+#[derive(Debug, Clone)]
+pub struct VerificationEngine {
+    parallelism: usize,
+    fail_fast: bool,
+    cancellation: CancellationToken,
+}
+
+impl Default for VerificationEngine {
+    fn default() -> Self {
+        let parallelism = thread::available_parallelism()
+            .map(usize::from)
+            .unwrap_or(1)
+            .min(MAX_PARALLELISM);
+
+        Self {
+            parallelism,
+            fail_fast: false,
+            cancellation: CancellationToken::new(),
+        }
+    }
+}
+
+impl VerificationEngine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runs at most `parallelism` checks at the same time (at least one)
+    pub fn with_parallelism(mut self, parallelism: usize) -> Self {
+        self.parallelism = parallelism.max(1);
+        self
+    }
+
+    /// Cancels the rest of the run when a check fails
+    pub fn fail_fast(mut self, fail_fast: bool) -> Self {
+        self.fail_fast = fail_fast;
+        self
+    }
+
+    /// Stops the run when `cancellation` is cancelled, for example by another thread
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
+    /// Token cancelling runs of this engine
+    pub fn cancellation(&self) -> &CancellationToken {
+        &self.cancellation
+    }
+
+    /// Runs the checks and returns their results in the order of `checks`.
+    ///
+    /// Checks are started heaviest first, and in the order of `checks` among checks of the
+    /// same weight, so when the run is cancelled by a failure, every check started before
+    /// the failed one has run.
+    pub fn run(&self, checks: Vec<Check<'_>>) -> Vec<CheckResult> {
+        self.run_with(checks, |_| {})
+    }
+
+    /// Runs the checks like [`run`](Self::run), showing each result as its check completes
+    /// and a summary at the end, see [`Progress`]
+    pub fn run_with_progress(&self, checks: Vec<Check<'_>>) -> Vec<CheckResult> {
+        let progress = Progress::new(checks.len());
+        let results = self.run_with(checks, |result| progress.show(result));
+        progress.summary(&results);
+        results
+    }
+
+    /// Runs the checks like [`run`](Self::run), calling `on_result` with each result as
+    /// soon as its check completes. Calls to `on_result` never overlap.
+    pub fn run_with(
+        &self,
+        checks: Vec<Check<'_>>,
+        on_result: impl Fn(&CheckResult) + Sync,
+    ) -> Vec<CheckResult> {
+        let count = checks.len();
+        let mut checks = checks.into_iter().enumerate().collect::<VecDeque<_>>();
+        // A stable sort, so checks of the same weight start in the order they were given
+        checks
+            .make_contiguous()
+            .sort_by_key(|(_, check)| Reverse(check.weight));
+        let queue = Mutex::new(checks);
+        let results: Mutex<Vec<Option<CheckResult>>> =
+            Mutex::new((0..count).map(|_| None).collect());
+
+        let work = || {
+            loop {
+                let Some((index, check)) = queue.lock().unwrap().pop_front() else {
+                    break;
+                };
+                let outcome = if self.cancellation.is_cancelled() {
+                    Outcome::Cancelled
+                } else {
+                    match (check.run)() {
+                        Ok(message) => Outcome::Passed(message),
+                        Err(e) => {
+                            if self.fail_fast {
+                                self.cancellation.cancel();
+                            }
+                            Outcome::Failed(e)
+                        }
+                    }
+                };
+                let result = CheckResult {
+                    kind: check.kind,
+                    subject: check.subject,
+                    outcome,
+                };
+                let mut results = results.lock().unwrap();
+                on_result(&result);
+                results[index] = Some(result);
+            }
+        };
+
+        let workers = self.parallelism.clamp(1, count.max(1));
+        if workers == 1 {
+            work();
+        } else {
+            thread::scope(|scope| {
+                for _ in 0..workers {
+                    scope.spawn(&work);
+                }
+            });
+        }
+
+        results
+            .into_inner()
+            .unwrap()
+            .into_iter()
+            .map(|result| result.expect("every check is run or cancelled"))
+            .collect()
+    }
+}
+
+/// Progress of a verification run: shows results as they complete and sums them up
+#[derive(Debug)]
+pub struct Progress {
+    total: usize,
+    completed: AtomicUsize,
+    started: Instant,
+}
+
+impl Progress {
+    /// Progress of a run of `total` checks, starting now
+    pub fn new(total: usize) -> Self {
+        Self {
+            total,
+            completed: AtomicUsize::new(0),
+            started: Instant::now(),
+        }
+    }
+
+    /// Counts a completed check and writes its event in NDJSON mode. Returns the
+    /// `[completed/total] ` counter to prefix the result with, empty for a single check.
+    pub fn record(&self, result: &CheckResult) -> String {
+        let completed = self.completed.fetch_add(1, Ordering::Relaxed) + 1;
+        if progress_format() == ProgressFormat::Ndjson {
+            let (status, error) = match &result.outcome {
+                Outcome::Passed(_) => ("passed", None),
+                Outcome::Failed(e) => ("failed", Some(e.to_string())),
+                Outcome::Cancelled => ("cancelled", None),
+            };
+            eprintln!(
+                "{}",
+                serde_json::json!({
+                    "event": "check",
+                    "kind": result.kind.to_string(),
+                    "subject": result.subject,
+                    "status": status,
+                    "error": error,
+                    "completed": completed,
+                    "total": self.total,
+                    "elapsed_ms": self.started.elapsed().as_millis() as u64,
+                })
+            );
+        }
+
+        if self.total > 1 {
+            format!("[{completed}/{}] ", self.total)
+        } else {
+            String::new()
+        }
+    }
+
+    /// Records a completed check and prints its message or error
+    pub fn show(&self, result: &CheckResult) {
+        let counter = self.record(result);
+        match &result.outcome {
+            Outcome::Passed(message) => println!("{counter}{message}"),
+            Outcome::Failed(e) => println!("{counter}{} {e}", color::cross_mark()),
+            Outcome::Cancelled => {}
+        }
+    }
+
+    /// Prints how many checks passed, failed or were cancelled, and how long they took
+    pub fn summary(&self, results: &[CheckResult]) {
+        let Some(first) = results.first() else {
+            return;
+        };
+        let count =
+            |f: fn(&Outcome) -> bool| results.iter().filter(|result| f(&result.outcome)).count();
+        let passed = count(|outcome| matches!(outcome, Outcome::Passed(_)));
+        let failed = count(|outcome| matches!(outcome, Outcome::Failed(_)));
+        let cancelled = count(|outcome| matches!(outcome, Outcome::Cancelled));
+        let elapsed = self.started.elapsed();
+
+        if progress_format() == ProgressFormat::Ndjson {
+            eprintln!(
+                "{}",
+                serde_json::json!({
+                    "event": "summary",
+                    "kind": first.kind.to_string(),
+                    "total": results.len(),
+                    "passed": passed,
+                    "failed": failed,
+                    "cancelled": cancelled,
+                    "elapsed_ms": elapsed.as_millis() as u64,
+                })
+            );
+        }
+
+        if results.len() > 1 {
+            let mut summary = format!(
+                "{passed} of {} {} checks passed in {:.1}s",
+                results.len(),
+                first.kind,
+                elapsed.as_secs_f64()
+            );
+            if failed > 0 {
+                summary.push_str(&format!(", {failed} failed"));
+            }
+            if cancelled > 0 {
+                summary.push_str(&format!(", {cancelled} not run after a failure"));
+            }
+            println!("{summary}");
+        }
+    }
+}
+
+/// Prints the message of every passed check in order, and returns the error of the first
+/// failed one
+pub fn report(results: Vec<CheckResult>) -> Result<()> {
+    for result in &results {
+        match &result.outcome {
+            Outcome::Passed(message) => println!("{message}"),
+            _ => break,
+        }
+    }
+    first_failure(results)
+}
+
+/// Returns the error of the first failed check, for results already shown by [`Progress`].
+///
+/// A failure is returned before any cancellation, since heavier checks given later may
+/// have failed and cancelled the checks given before them.
+pub fn first_failure(results: Vec<CheckResult>) -> Result<()> {
+    let mut cancelled = None;
+    for result in results {
+        match result.outcome {
+            Outcome::Passed(_) => {}
+            Outcome::Failed(e) => return Err(e),
+            Outcome::Cancelled => {
+                cancelled.get_or_insert(result.subject);
+            }
+        }
+    }
+    match cancelled {
+        Some(subject) => Err(Error::Validation(format!(
+            "Verification was cancelled before checking {subject}"
+        ))),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(index: usize, fail: bool) -> Check<'static> {
+        Check::new(CheckKind::Hash, format!("item {index}"), move || {
+            if fail {
+                Err(Error::Validation(format!("item {index} failed")))
+            } else {
+                Ok(format!("item {index} verified"))
+            }
+        })
+    }
+
+    #[test]
+    fn test_results_keep_submission_order() {
+        let engine = VerificationEngine::new().with_parallelism(4);
+        let results = engine.run((0..20).map(|index| check(index, index == 7)).collect());
+
+        assert_eq!(results.len(), 20);
+        for (index, result) in results.iter().enumerate() {
+            assert_eq!(result.subject, format!("item {index}"));
+            assert_eq!(result.passed(), index != 7);
+        }
+    }
+
+    #[test]
+    fn test_parallelism_is_bounded() {
+        let running = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        let checks = (0..16)
+            .map(|index| {
+                let (running, peak) = (&running, &peak);
+                Check::new(CheckKind::Hash, format!("item {index}"), move || {
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    thread::sleep(std::time::Duration::from_millis(5));
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    Ok(String::new())
+                })
+            })
+            .collect();
+
+        VerificationEngine::new().with_parallelism(3).run(checks);
+        assert!(peak.load(Ordering::SeqCst) <= 3);
+    }
+
+    #[test]
+    fn test_fail_fast_cancels_remaining_checks() {
+        let engine = VerificationEngine::new()
+            .with_parallelism(1)
+            .fail_fast(true);
+        let results = engine.run((0..5).map(|index| check(index, index == 1)).collect());
+
+        assert!(results[0].passed());
+        assert!(matches!(results[1].outcome, Outcome::Failed(_)));
+        assert!(
+            results[2..]
+                .iter()
+                .all(|result| matches!(result.outcome, Outcome::Cancelled))
+        );
+
+        let error = report(results).unwrap_err();
+        assert!(error.to_string().contains("item 1 failed"));
+    }
+
+    #[test]
+    fn test_results_are_passed_on_as_checks_complete() {
+        let seen = Mutex::new(Vec::new());
+        let engine = VerificationEngine::new().with_parallelism(4);
+        let results = engine.run_with(
+            (0..10).map(|index| check(index, false)).collect(),
+            |result| seen.lock().unwrap().push(result.subject.clone()),
+        );
+
+        let mut seen = seen.into_inner().unwrap();
+        seen.sort();
+        let mut subjects: Vec<_> = results.into_iter().map(|result| result.subject).collect();
+        subjects.sort();
+        assert_eq!(seen, subjects);
+    }
+
+    #[test]
+    fn test_progress_counter() {
+        let result = |index| CheckResult {
+            kind: CheckKind::Hash,
+            subject: format!("item {index}"),
+            outcome: Outcome::Passed(String::new()),
+        };
+        let progress = Progress::new(2);
+        assert_eq!(progress.record(&result(0)), "[1/2] ");
+        assert_eq!(progress.record(&result(1)), "[2/2] ");
+        assert_eq!(Progress::new(1).record(&result(0)), "");
+    }
+
+    #[test]
+    fn test_explanation_lines() {
+        let stored = "ab".repeat(32);
+        let explanation = Explanation::new()
+            .line("size: 12 bytes recorded, 12 bytes found")
+            .digests("sha256", &stored, &"cd".repeat(32));
+
+        assert_eq!(
+            explanation.to_string(),
+            format!(
+                "\n    - size: 12 bytes recorded, 12 bytes found\n    - sha256: stored {}..., computed {}... (differ)",
+                "ab".repeat(8),
+                "cd".repeat(8)
+            )
+        );
+        assert!(
+            Explanation::new()
+                .digests("sha256", &stored, &stored)
+                .to_string()
+                .ends_with("(match)")
+        );
+    }
+
+    #[test]
+    fn test_heaviest_checks_start_first() {
+        let started = Mutex::new(Vec::new());
+        let checks = [1, 5, 3, 5]
+            .into_iter()
+            .enumerate()
+            .map(|(index, weight)| {
+                let started = &started;
+                Check::new(CheckKind::Hash, format!("item {index}"), move || {
+                    started.lock().unwrap().push(index);
+                    Ok(String::new())
+                })
+                .with_weight(weight)
+            })
+            .collect();
+
+        let results = VerificationEngine::new().with_parallelism(1).run(checks);
+        assert_eq!(started.into_inner().unwrap(), vec![1, 3, 2, 0]);
+        // Results keep the order the checks were given in
+        assert_eq!(results[0].subject, "item 0");
+    }
+
+    #[test]
+    fn test_failure_is_reported_before_cancellations() {
+        let checks = vec![
+            check(0, false).with_weight(1),
+            check(1, true).with_weight(10),
+        ];
+        let results = VerificationEngine::new()
+            .with_parallelism(1)
+            .fail_fast(true)
+            .run(checks);
+
+        assert!(matches!(results[0].outcome, Outcome::Cancelled));
+        let error = first_failure(results).unwrap_err();
+        assert!(error.to_string().contains("item 1 failed"));
+    }
+
+    #[test]
+    fn test_cancellation_token() {
+        let token = CancellationToken::new();
+        let engine = VerificationEngine::new().with_cancellation(token.clone());
+        token.cancel();
+
+        let results = engine.run(vec![check(0, false)]);
+        assert!(matches!(results[0].outcome, Outcome::Cancelled));
+    }
+}
@@ -0,0 +1,409 @@
+//! Prometheus metrics of operations, verifications and storage requests.
+//!
+//! Metrics are collected for the whole process and exposed in the Prometheus text format,
+//! either served over HTTP while a command runs (`--metrics-addr`), which suits long-running
+//! operations, or written to a file when it ends (`--metrics-file`), for the node_exporter
+//! textfile collector.
+
+use crate::error::{Error, Result};
+use reqwest::StatusCode;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+/// Upper bounds, in seconds, of the buckets of the latency histograms
+pub const LATENCY_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 300.0,
+];
+
+static REGISTRY: Mutex<Registry> = Mutex::new(Registry::new());
+
+/// Outcome of a request to a storage backend
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum StorageOutcome {
+    Success,
+    /// The service answered, but refused or could not find what was asked for
+    ClientError,
+    /// The service was unreachable, overloaded (429) or failing (5xx)
+    Unavailable,
+}
+
+impl StorageOutcome {
+    pub fn from_status(status: StatusCode) -> Self {
+        if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+            StorageOutcome::Unavailable
+        } else if status.is_client_error() {
+            StorageOutcome::ClientError
+        } else {
+            StorageOutcome::Success
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            StorageOutcome::Success => "success",
+            StorageOutcome::ClientError => "client_error",
+            StorageOutcome::Unavailable => "unavailable",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Histogram {
+    // Count of observations in each bucket of LATENCY_BUCKETS, not cumulative
+    buckets: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        if self.buckets.is_empty() {
+            self.buckets = vec![0; LATENCY_BUCKETS.len()];
+        }
+        if let Some(bucket) = LATENCY_BUCKETS.iter().position(|bound| seconds <= *bound) {
+            self.buckets[bucket] += 1;
+        }
+        self.sum += seconds;
+        self.count += 1;
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let mut cumulative = 0;
+        for (bound, count) in LATENCY_BUCKETS.iter().zip(&self.buckets) {
+            cumulative += count;
+            let _ = writeln!(out, "{name}_bucket{{{labels},le=\"{bound}\"}} {cumulative}");
+        }
+        let _ = writeln!(out, "{name}_bucket{{{labels},le=\"+Inf\"}} {}", self.count);
+        let _ = writeln!(out, "{name}_sum{{{labels}}} {}", self.sum);
+        let _ = writeln!(out, "{name}_count{{{labels}}} {}", self.count);
+    }
+}
+
+#[derive(Debug)]
+struct Registry {
+    operations: BTreeMap<(String, bool), u64>,
+    operation_latency: BTreeMap<String, Histogram>,
+    verifications: BTreeMap<(String, bool), u64>,
+    storage_requests: BTreeMap<(String, StorageOutcome), u64>,
+    storage_latency: BTreeMap<String, Histogram>,
+    storage_up: BTreeMap<String, bool>,
+}
+
+impl Registry {
+    const fn new() -> Self {
+        Self {
+            operations: BTreeMap::new(),
+            operation_latency: BTreeMap::new(),
+            verifications: BTreeMap::new(),
+            storage_requests: BTreeMap::new(),
+            storage_latency: BTreeMap::new(),
+            storage_up: BTreeMap::new(),
+        }
+    }
+
+    fn render(&self) -> String {
+        let mut out = String::new();
+
+        header(
+            &mut out,
+            "atlas_operations_total",
+            "counter",
+            "Operations run, by outcome",
+        );
+        for ((operation, success), count) in &self.operations {
+            let _ = writeln!(
+                out,
+                "atlas_operations_total{{operation=\"{}\",outcome=\"{}\"}} {count}",
+                escape(operation),
+                if *success { "success" } else { "failure" }
+            );
+        }
+
+        header(
+            &mut out,
+            "atlas_operation_duration_seconds",
+            "histogram",
+            "Duration of operations",
+        );
+        for (operation, histogram) in &self.operation_latency {
+            histogram.render(
+                &mut out,
+                "atlas_operation_duration_seconds",
+                &format!("operation=\"{}\"", escape(operation)),
+            );
+        }
+
+        header(
+            &mut out,
+            "atlas_verifications_total",
+            "counter",
+            "Verifications, by result",
+        );
+        for ((kind, passed), count) in &self.verifications {
+            let _ = writeln!(
+                out,
+                "atlas_verifications_total{{kind=\"{}\",result=\"{}\"}} {count}",
+                escape(kind),
+                if *passed { "pass" } else { "fail" }
+            );
+        }
+
+        header(
+            &mut out,
+            "atlas_storage_requests_total",
+            "counter",
+            "Requests to storage backends, by outcome",
+        );
+        for ((backend, outcome), count) in &self.storage_requests {
+            let _ = writeln!(
+                out,
+                "atlas_storage_requests_total{{backend=\"{}\",outcome=\"{}\"}} {count}",
+                escape(backend),
+                outcome.label()
+            );
+        }
+
+        header(
+            &mut out,
+            "atlas_storage_request_duration_seconds",
+            "histogram",
+            "Duration of requests to storage backends",
+        );
+        for (backend, histogram) in &self.storage_latency {
+            histogram.render(
+                &mut out,
+                "atlas_storage_request_duration_seconds",
+                &format!("backend=\"{}\"", escape(backend)),
+            );
+        }
+
+        header(
+            &mut out,
+            "atlas_storage_up",
+            "gauge",
+            "Whether the last request to a storage backend found it available",
+        );
+        for (backend, up) in &self.storage_up {
+            let _ = writeln!(
+                out,
+                "atlas_storage_up{{backend=\"{}\"}} {}",
+                escape(backend),
+                u8::from(*up)
+            );
+        }
+
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+// Escapes a label value of the text format
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn registry() -> std::sync::MutexGuard<'static, Registry> {
+    // Metrics stay usable even if a thread panicked while updating them
+    REGISTRY
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Records a completed operation, such as a command, and how long it took
+pub fn record_operation(operation: &str, duration: Duration, success: bool) {
+    let mut registry = registry();
+    *registry
+        .operations
+        .entry((operation.to_string(), success))
+        .or_default() += 1;
+    registry
+        .operation_latency
+        .entry(operation.to_string())
+        .or_default()
+        .observe(duration);
+}
+
+/// Records whether a verification of `kind` (for example `model verify`) passed
+pub fn record_verification(kind: &str, passed: bool) {
+    *registry()
+        .verifications
+        .entry((kind.to_string(), passed))
+        .or_default() += 1;
+}
+
+/// Records a request to a storage backend. The backend counts as up unless the request
+/// found it unavailable.
+pub fn record_storage_request(backend: &str, duration: Duration, outcome: StorageOutcome) {
+    let mut registry = registry();
+    *registry
+        .storage_requests
+        .entry((backend.to_string(), outcome))
+        .or_default() += 1;
+    registry
+        .storage_latency
+        .entry(backend.to_string())
+        .or_default()
+        .observe(duration);
+    registry
+        .storage_up
+        .insert(backend.to_string(), outcome != StorageOutcome::Unavailable);
+}
+
+/// Returns the metrics in the Prometheus text exposition format
+pub fn render() -> String {
+    registry().render()
+}
+
+/// Writes the metrics to a file, replacing it atomically so that collectors never read a
+/// partial file
+pub fn write_file(path: &Path) -> Result<()> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    fs::write(&temporary, render())?;
+    fs::rename(&temporary, path)?;
+    Ok(())
+}
+
+// Time a scrape connection may wait on each read or write of the client
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Serves the metrics at `/metrics` on `addr` from a background thread, for as long as the
+/// process runs. Returns the address listened on, useful when `addr` has port 0.
+pub fn serve(addr: &str) -> Result<SocketAddr> {
+    let listener = TcpListener::bind(addr).map_err(|e| {
+        Error::InitializationError(format!("Failed to listen for metrics on {addr}: {e}"))
+    })?;
+    let local = listener.local_addr()?;
+
+    // Each connection has its own thread, so a slow or stalled client can't hold up scrapes
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            thread::spawn(move || {
+                if let Err(e) = respond(stream) {
+                    log::debug!("Failed to answer metrics request: {e}");
+                }
+            });
+        }
+    });
+    Ok(local)
+}
+
+fn respond(mut stream: TcpStream) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut request_line = String::new();
+    {
+        // Read the whole request head, as closing with unread data resets the connection
+        let mut reader = BufReader::new(&stream);
+        reader.read_line(&mut request_line)?;
+        let mut line = String::new();
+        while reader.read_line(&mut line)? > 0 && !line.trim().is_empty() {
+            line.clear();
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (status, content_type, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => (
+            "200 OK",
+            "text/plain; version=0.0.4; charset=utf-8",
+            render(),
+        ),
+        (Some("GET"), Some(_)) => ("404 Not Found", "text/plain", "Not found\n".to_string()),
+        _ => (
+            "405 Method Not Allowed",
+            "text/plain",
+            "Method not allowed\n".to_string(),
+        ),
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn test_histogram_buckets_are_cumulative() {
+        let mut histogram = Histogram::default();
+        histogram.observe(Duration::from_millis(20));
+        histogram.observe(Duration::from_millis(200));
+        histogram.observe(Duration::from_secs(600));
+
+        let mut out = String::new();
+        histogram.render(&mut out, "latency", "operation=\"test\"");
+        assert!(out.contains("latency_bucket{operation=\"test\",le=\"0.025\"} 1\n"));
+        assert!(out.contains("latency_bucket{operation=\"test\",le=\"0.25\"} 2\n"));
+        assert!(out.contains("latency_bucket{operation=\"test\",le=\"300\"} 2\n"));
+        assert!(out.contains("latency_bucket{operation=\"test\",le=\"+Inf\"} 3\n"));
+        assert!(out.contains("latency_count{operation=\"test\"} 3\n"));
+    }
+
+    #[test]
+    fn test_storage_outcome() {
+        assert_eq!(
+            StorageOutcome::from_status(StatusCode::OK),
+            StorageOutcome::Success
+        );
+        assert_eq!(
+            StorageOutcome::from_status(StatusCode::NOT_FOUND),
+            StorageOutcome::ClientError
+        );
+        assert_eq!(
+            StorageOutcome::from_status(StatusCode::TOO_MANY_REQUESTS),
+            StorageOutcome::Unavailable
+        );
+    }
+
+    #[test]
+    fn test_serve_metrics() -> Result<()> {
+        record_operation("metrics \"test\"", Duration::from_millis(5), true);
+        record_verification("metrics test", false);
+        record_storage_request(
+            "metrics-test",
+            Duration::from_millis(5),
+            StorageOutcome::Unavailable,
+        );
+
+        let addr = serve("127.0.0.1:0")?;
+        // A client that never sends its request doesn't hold up the scrape
+        let _stalled = TcpStream::connect(addr)?;
+        let mut stream = TcpStream::connect(addr)?;
+        stream.set_read_timeout(Some(Duration::from_secs(2)))?;
+        stream.write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n")?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains(
+            "atlas_operations_total{operation=\"metrics \\\"test\\\"\",outcome=\"success\"} 1"
+        ));
+        assert!(
+            response.contains("atlas_verifications_total{kind=\"metrics test\",result=\"fail\"} 1")
+        );
+        assert!(response.contains("atlas_storage_up{backend=\"metrics-test\"} 0"));
+        Ok(())
+    }
+}
@@ -0,0 +1,192 @@
+//! Hugging Face Hub.
+//!
+//! Summaries are committed to the model repository as `atlas-provenance.json`, next to the
+//! model card the repository already has. Entries are repositories: a name with a `/` is a
+//! full repository ID, and other names are looked up in the namespace given with
+//! `--namespace`, the user or organization owning the models. Requests authenticate with
+//! `HF_TOKEN` when it is set; pushing needs a token with write access.
+
+use super::{ManifestSummary, Registry, RegistryEntry, read_json, send};
+use crate::error::{Error, Result};
+use crate::http;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use reqwest::blocking::{Client, RequestBuilder};
+use serde::Deserialize;
+use serde_json::json;
+
+/// Environment variable holding the access token
+pub const TOKEN_ENV: &str = "HF_TOKEN";
+
+/// File of the model repository holding the summary
+pub const PROVENANCE_FILE: &str = "atlas-provenance.json";
+
+const DEFAULT_URL: &str = "https://huggingface.co";
+
+// Models requested per page
+const PAGE_SIZE: usize = 1000;
+
+#[derive(Deserialize)]
+struct Model {
+    id: String,
+    #[serde(default)]
+    siblings: Vec<Sibling>,
+}
+
+#[derive(Deserialize)]
+struct Sibling {
+    rfilename: String,
+}
+
+pub struct HuggingFaceRegistry {
+    client: Client,
+    base_url: String,
+    namespace: Option<String>,
+}
+
+impl HuggingFaceRegistry {
+    pub fn new(url: Option<String>, namespace: Option<String>) -> Result<Self> {
+        let client = http::client_builder()
+            .build()
+            .map_err(|e| Error::Storage(format!("Failed to create HTTP client: {e}")))?;
+
+        Ok(HuggingFaceRegistry {
+            client,
+            base_url: url
+                .as_deref()
+                .unwrap_or(DEFAULT_URL)
+                .trim_end_matches('/')
+                .to_string(),
+            namespace,
+        })
+    }
+
+    fn namespace(&self) -> Result<&str> {
+        self.namespace.as_deref().ok_or_else(|| {
+            Error::Validation(
+                "Hugging Face needs --namespace, the user or organization owning the models"
+                    .to_string(),
+            )
+        })
+    }
+
+    // Full ID of the repository of an entry
+    fn repo_id(&self, entry: &str) -> Result<String> {
+        if entry.contains('/') {
+            Ok(entry.to_string())
+        } else {
+            Ok(format!("{}/{entry}", self.namespace()?))
+        }
+    }
+
+    fn request(&self, method: reqwest::Method, url: impl reqwest::IntoUrl) -> RequestBuilder {
+        let request = self.client.request(method, url);
+        match std::env::var(TOKEN_ENV) {
+            Ok(token) => request.bearer_auth(token),
+            Err(_) => request,
+        }
+    }
+
+    // Manifest ID recorded in the provenance file of a repository
+    fn recorded_manifest_id(&self, repo_id: &str) -> Result<Option<String>> {
+        let context = format!("Failed to read {PROVENANCE_FILE} of {repo_id}");
+        let url = format!("{}/{repo_id}/resolve/main/{PROVENANCE_FILE}", self.base_url);
+        let summary: serde_json::Value = read_json(
+            send(self.request(reqwest::Method::GET, url), &context)?,
+            &context,
+        )?;
+        Ok(summary["manifest_id"].as_str().map(str::to_string))
+    }
+}
+
+// URL of the next page from a `Link` header, if any
+fn next_page(response: &reqwest::blocking::Response) -> Option<String> {
+    let link = response
+        .headers()
+        .get(reqwest::header::LINK)?
+        .to_str()
+        .ok()?;
+    link.split(',').find_map(|link| {
+        let (url, params) = link.split_once(';')?;
+        params
+            .split(';')
+            .any(|param| param.trim() == r#"rel="next""#)
+            .then(|| url.trim().trim_matches(['<', '>']).to_string())
+    })
+}
+
+impl Registry for HuggingFaceRegistry {
+    fn push(&self, entry: &str, summary: &ManifestSummary) -> Result<()> {
+        let repo_id = self.repo_id(entry)?;
+        let content = format!("{}\n", serde_json::to_string_pretty(summary)?);
+
+        // The commit API takes a header line followed by one line per file
+        let body = [
+            json!({
+                "key": "header",
+                "value": {
+                    "summary": format!("Update Atlas provenance of {}", summary.manifest_id),
+                },
+            }),
+            json!({
+                "key": "file",
+                "value": {
+                    "path": PROVENANCE_FILE,
+                    "encoding": "base64",
+                    "content": STANDARD.encode(content),
+                },
+            }),
+        ]
+        .iter()
+        .map(|line| format!("{line}\n"))
+        .collect::<String>();
+
+        send(
+            self.request(
+                reqwest::Method::POST,
+                format!("{}/api/models/{repo_id}/commit/main", self.base_url),
+            )
+            .header(reqwest::header::CONTENT_TYPE, "application/x-ndjson")
+            .body(body),
+            &format!("Failed to commit {PROVENANCE_FILE} to {repo_id}"),
+        )?;
+        Ok(())
+    }
+
+    fn entries(&self) -> Result<Vec<RegistryEntry>> {
+        let context = "Failed to list models";
+        let mut url = reqwest::Url::parse(&format!("{}/api/models", self.base_url))
+            .map_err(|e| Error::Validation(format!("Invalid Hugging Face URL: {e}")))?;
+        url.query_pairs_mut()
+            .append_pair("author", self.namespace()?)
+            .append_pair("full", "true")
+            .append_pair("limit", &PAGE_SIZE.to_string());
+
+        let mut models: Vec<Model> = Vec::new();
+        let mut next = Some(url.to_string());
+        while let Some(url) = next {
+            let response = send(self.request(reqwest::Method::GET, url), context)?;
+            next = next_page(&response);
+            models.extend(read_json::<Vec<Model>>(response, context)?);
+        }
+
+        models
+            .into_iter()
+            .map(|model| {
+                let has_provenance = model
+                    .siblings
+                    .iter()
+                    .any(|sibling| sibling.rfilename == PROVENANCE_FILE);
+                let manifest_id = if has_provenance {
+                    self.recorded_manifest_id(&model.id)?
+                } else {
+                    None
+                };
+                Ok(RegistryEntry {
+                    name: model.id,
+                    manifest_id,
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,156 @@
+//! MLflow Model Registry.
+//!
+//! Summaries are recorded as tags of the registered model, and the model card replaces
+//! its description. The tracking server is read from `MLFLOW_TRACKING_URI` unless a URL is
+//! given, and requests authenticate with `MLFLOW_TRACKING_TOKEN` as a bearer token, or
+//! `MLFLOW_TRACKING_USERNAME` and `MLFLOW_TRACKING_PASSWORD`, when they are set.
+
+use super::{ManifestSummary, Registry, RegistryEntry, read_json, send};
+use crate::error::{Error, Result};
+use crate::http;
+use reqwest::blocking::{Client, RequestBuilder};
+use serde::Deserialize;
+use serde_json::json;
+
+/// Environment variable holding the URL of the tracking server
+pub const TRACKING_URI_ENV: &str = "MLFLOW_TRACKING_URI";
+pub const TRACKING_TOKEN_ENV: &str = "MLFLOW_TRACKING_TOKEN";
+pub const TRACKING_USERNAME_ENV: &str = "MLFLOW_TRACKING_USERNAME";
+pub const TRACKING_PASSWORD_ENV: &str = "MLFLOW_TRACKING_PASSWORD";
+
+/// Tag holding the manifest ID
+pub const MANIFEST_ID_TAG: &str = "atlas.manifest_id";
+/// Tag holding the ingredient digests as a JSON object
+pub const DIGESTS_TAG: &str = "atlas.digests";
+/// Tag holding the verification status
+pub const VERIFICATION_TAG: &str = "atlas.verification";
+
+// Registered models requested per page
+const PAGE_SIZE: usize = 1000;
+
+#[derive(Deserialize)]
+struct SearchResponse {
+    #[serde(default)]
+    registered_models: Vec<RegisteredModel>,
+    next_page_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RegisteredModel {
+    name: String,
+    #[serde(default)]
+    tags: Vec<Tag>,
+}
+
+#[derive(Deserialize)]
+struct Tag {
+    key: String,
+    value: String,
+}
+
+pub struct MlflowRegistry {
+    client: Client,
+    base_url: String,
+}
+
+impl MlflowRegistry {
+    pub fn new(url: Option<String>) -> Result<Self> {
+        let base_url = url
+            .or_else(|| std::env::var(TRACKING_URI_ENV).ok())
+            .ok_or_else(|| {
+                Error::Validation(format!(
+                    "No MLflow tracking server; pass --registry-url or set {TRACKING_URI_ENV}"
+                ))
+            })?;
+        let client = http::client_builder()
+            .build()
+            .map_err(|e| Error::Storage(format!("Failed to create HTTP client: {e}")))?;
+
+        Ok(MlflowRegistry {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        })
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/api/2.0/mlflow/registered-models/{path}", self.base_url)
+    }
+
+    fn request(&self, method: reqwest::Method, url: impl reqwest::IntoUrl) -> RequestBuilder {
+        let request = self.client.request(method, url);
+        if let Ok(token) = std::env::var(TRACKING_TOKEN_ENV) {
+            request.bearer_auth(token)
+        } else if let Ok(username) = std::env::var(TRACKING_USERNAME_ENV) {
+            request.basic_auth(username, std::env::var(TRACKING_PASSWORD_ENV).ok())
+        } else {
+            request
+        }
+    }
+}
+
+impl Registry for MlflowRegistry {
+    fn push(&self, entry: &str, summary: &ManifestSummary) -> Result<()> {
+        let context = format!("Failed to update registered model {entry}");
+        let tags = [
+            (MANIFEST_ID_TAG, summary.manifest_id.clone()),
+            (DIGESTS_TAG, serde_json::to_string(&summary.digests)?),
+            (VERIFICATION_TAG, summary.verification.to_string()),
+        ];
+        for (key, value) in tags {
+            send(
+                self.request(reqwest::Method::POST, self.url("set-tag"))
+                    .json(&json!({
+                        "name": entry,
+                        "key": key,
+                        "value": value,
+                    })),
+                &context,
+            )?;
+        }
+        send(
+            self.request(reqwest::Method::PATCH, self.url("update"))
+                .json(&json!({
+                    "name": entry,
+                    "description": summary.model_card,
+                })),
+            &context,
+        )?;
+        Ok(())
+    }
+
+    fn entries(&self) -> Result<Vec<RegistryEntry>> {
+        let context = "Failed to list registered models";
+        let mut entries = Vec::new();
+        let mut page_token: Option<String> = None;
+        loop {
+            let mut url = reqwest::Url::parse(&self.url("search"))
+                .map_err(|e| Error::Validation(format!("Invalid MLflow URL: {e}")))?;
+            url.query_pairs_mut()
+                .append_pair("max_results", &PAGE_SIZE.to_string());
+            if let Some(token) = &page_token {
+                url.query_pairs_mut().append_pair("page_token", token);
+            }
+            let page: SearchResponse = read_json(
+                send(self.request(reqwest::Method::GET, url), context)?,
+                context,
+            )?;
+
+            entries.extend(page.registered_models.into_iter().map(|model| {
+                let manifest_id = model
+                    .tags
+                    .into_iter()
+                    .find(|tag| tag.key == MANIFEST_ID_TAG)
+                    .map(|tag| tag.value);
+                RegistryEntry {
+                    name: model.name,
+                    manifest_id,
+                }
+            }));
+
+            page_token = page.next_page_token.filter(|token| !token.is_empty());
+            if page_token.is_none() {
+                return Ok(entries);
+            }
+        }
+    }
+}
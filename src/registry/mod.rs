@@ -0,0 +1,359 @@
+//! # Model Registry Sync
+//!
+//! Model registries are where teams look for models, so `atlas-cli sync` pushes a summary
+//! of each model manifest into the metadata of the matching registry entry: the manifest
+//! ID, the digests of its ingredients, the outcome of verifying it and a model card
+//! rendered from the manifest. It can also pull the registry's entries and report those
+//! without a manifest.
+//!
+//! ## Supported Registries
+//!
+//! - [`mlflow`] - MLflow Model Registry: registered model tags and description
+//! - [`huggingface`] - Hugging Face Hub: an `atlas-provenance.json` file in the model repo
+//! - [`vertex`] - Vertex AI Model Registry: model labels and description
+//!
+//! A manifest is pushed to the entry named like the manifest, unless another entry is
+//! given. Entries only learn about manifests through sync, so an entry without a recorded
+//! manifest ID is a gap, unless a stored model manifest has its name and just wasn't
+//! pushed yet.
+
+pub mod huggingface;
+pub mod mlflow;
+pub mod vertex;
+
+use crate::error::{Error, Result};
+use crate::manifest::claims;
+use crate::manifest::common::verify_manifest_with_artifact_root;
+use crate::storage::traits::{ManifestType, StorageBackend};
+use atlas_c2pa_lib::assertion::Assertion;
+use atlas_c2pa_lib::manifest::Manifest;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Write as _};
+use std::path::Path;
+
+/// Registry that manifests are synced with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegistryTarget {
+    Mlflow,
+    HuggingFace,
+    Vertex,
+}
+
+/// Outcome of verifying a manifest before its summary was pushed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum VerificationStatus {
+    Verified,
+    Failed,
+    /// The manifest was pushed with `--skip-verify`
+    NotChecked,
+}
+
+impl VerificationStatus {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            VerificationStatus::Verified => "verified",
+            VerificationStatus::Failed => "failed",
+            VerificationStatus::NotChecked => "not-checked",
+        }
+    }
+}
+
+impl fmt::Display for VerificationStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// What a registry entry records about a manifest
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ManifestSummary {
+    pub manifest_id: String,
+    pub name: String,
+    /// Digests of the ingredients, as `<alg>:<hex>` by ingredient name
+    pub digests: BTreeMap<String, String>,
+    pub verification: VerificationStatus,
+    /// Markdown model card rendered from the manifest
+    pub model_card: String,
+}
+
+impl ManifestSummary {
+    /// Summarizes a manifest verified with the outcome `verification`
+    pub fn new(manifest: &Manifest, verification: VerificationStatus) -> Self {
+        let digests: BTreeMap<String, String> = manifest
+            .ingredients
+            .iter()
+            .filter(|ingredient| !ingredient.data.hash.is_empty())
+            .map(|ingredient| {
+                (
+                    ingredient.title.clone(),
+                    format!("{}:{}", ingredient.data.alg, ingredient.data.hash),
+                )
+            })
+            .collect();
+
+        let mut summary = ManifestSummary {
+            manifest_id: manifest.instance_id.clone(),
+            name: manifest.title.clone(),
+            digests,
+            verification,
+            model_card: String::new(),
+        };
+        summary.model_card = render_model_card(manifest, &summary);
+        summary
+    }
+}
+
+// Description and authors from the assertions of the claim
+fn description_and_authors(manifest: &Manifest) -> (Option<String>, Vec<String>) {
+    let claim = claims::active_claim(manifest);
+    let mut description = None;
+    let mut authors = Vec::new();
+    for assertion in &claim.created_assertions {
+        match assertion {
+            Assertion::CreativeWork(work) => {
+                authors.extend(work.author.iter().map(|author| author.name.clone()))
+            }
+            Assertion::Action(actions) => {
+                description = description.or_else(|| {
+                    actions
+                        .actions
+                        .iter()
+                        .filter_map(|action| action.parameters.as_ref()?.get("description"))
+                        .find_map(|description| description.as_str())
+                        .filter(|description| !description.trim().is_empty())
+                        .map(str::to_string)
+                })
+            }
+            _ => {}
+        }
+    }
+    (description, authors)
+}
+
+fn render_model_card(manifest: &Manifest, summary: &ManifestSummary) -> String {
+    let (description, authors) = description_and_authors(manifest);
+
+    let mut card = format!("# {}\n\n", summary.name);
+    if let Some(description) = description {
+        let _ = writeln!(card, "{description}\n");
+    }
+    if !authors.is_empty() {
+        let _ = writeln!(card, "Authors: {}\n", authors.join(", "));
+    }
+    let _ = writeln!(
+        card,
+        "Provenance: Atlas manifest `{}` ({})",
+        summary.manifest_id, summary.verification
+    );
+    if !summary.digests.is_empty() {
+        card.push_str("\n| Ingredient | Digest |\n|---|---|\n");
+        for (name, digest) in &summary.digests {
+            let _ = writeln!(card, "| {} | `{digest}` |", name.replace('|', "\\|"));
+        }
+    }
+    card
+}
+
+/// An entry of a registry, with the manifest ID it records if any
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct RegistryEntry {
+    pub name: String,
+    pub manifest_id: Option<String>,
+}
+
+/// A model registry that manifest summaries are pushed to
+pub trait Registry {
+    /// Records `summary` in the metadata of the entry `entry`
+    fn push(&self, entry: &str, summary: &ManifestSummary) -> Result<()>;
+
+    /// Lists the entries of the registry
+    fn entries(&self) -> Result<Vec<RegistryEntry>>;
+}
+
+/// Connects to a registry. `url` overrides the registry's default or configured endpoint,
+/// and `namespace` is the Hugging Face user or organization, or the Vertex AI
+/// `projects/<PROJECT>/locations/<LOCATION>`.
+pub fn connect(
+    target: RegistryTarget,
+    url: Option<String>,
+    namespace: Option<String>,
+) -> Result<Box<dyn Registry>> {
+    Ok(match target {
+        RegistryTarget::Mlflow => Box::new(mlflow::MlflowRegistry::new(url)?),
+        RegistryTarget::HuggingFace => {
+            Box::new(huggingface::HuggingFaceRegistry::new(url, namespace)?)
+        }
+        RegistryTarget::Vertex => Box::new(vertex::VertexRegistry::new(url, namespace)?),
+    })
+}
+
+/// Outcome of pushing the summary of one manifest
+#[derive(Debug)]
+pub struct PushResult {
+    pub manifest_id: String,
+    pub entry: String,
+    pub verification: VerificationStatus,
+    pub outcome: Result<()>,
+}
+
+/// Verifies the manifests `ids`, or every stored model manifest if none are given, and
+/// pushes their summaries to the entries named like them. `entry` names the entry of a
+/// single manifest. A failing manifest does not stop the others.
+pub fn push_summaries(
+    registry: &dyn Registry,
+    storage: &dyn StorageBackend,
+    ids: &[String],
+    entry: Option<&str>,
+    verify: bool,
+    artifact_root: Option<&Path>,
+) -> Result<Vec<PushResult>> {
+    let ids = if ids.is_empty() {
+        storage
+            .list_manifests()?
+            .into_iter()
+            .filter(|metadata| metadata.manifest_type == ManifestType::Model)
+            .map(|metadata| metadata.id)
+            .collect()
+    } else {
+        ids.to_vec()
+    };
+    if entry.is_some() && ids.len() != 1 {
+        return Err(Error::Validation(
+            "A registry entry can only be given for a single manifest".to_string(),
+        ));
+    }
+
+    let mut results = Vec::new();
+    for id in ids {
+        let manifest = storage.retrieve_manifest(&id)?;
+        let verification = if !verify {
+            VerificationStatus::NotChecked
+        } else if verify_manifest_with_artifact_root(&id, storage, artifact_root).is_ok() {
+            VerificationStatus::Verified
+        } else {
+            VerificationStatus::Failed
+        };
+
+        let summary = ManifestSummary::new(&manifest, verification);
+        let entry = entry.unwrap_or(&summary.name).to_string();
+        let outcome = registry.push(&entry, &summary);
+        results.push(PushResult {
+            manifest_id: id,
+            entry,
+            verification,
+            outcome,
+        });
+    }
+    Ok(results)
+}
+
+/// Registry entries lacking a manifest in the store
+#[derive(Debug, Default, Serialize)]
+pub struct GapReport {
+    /// Entries without a manifest ID, and no stored model manifest of the same name
+    pub missing: Vec<RegistryEntry>,
+    /// Entries without a manifest ID, named like stored model manifests that were not
+    /// pushed yet, with the IDs of those manifests
+    pub unsynced: Vec<(RegistryEntry, Vec<String>)>,
+    /// Entries recording a manifest ID that is not in the store
+    pub stale: Vec<RegistryEntry>,
+}
+
+impl GapReport {
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.unsynced.is_empty() && self.stale.is_empty()
+    }
+
+    /// Renders the report as text, one entry per line
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for entry in &self.missing {
+            let _ = writeln!(out, "missing   {}", entry.name);
+        }
+        for (entry, ids) in &self.unsynced {
+            let _ = writeln!(out, "unsynced  {} ({})", entry.name, ids.join(", "));
+        }
+        for entry in &self.stale {
+            let _ = writeln!(
+                out,
+                "stale     {} ({} is not stored)",
+                entry.name,
+                entry.manifest_id.as_deref().unwrap_or_default()
+            );
+        }
+        out
+    }
+}
+
+/// Pulls the entries of a registry and reports those lacking a stored manifest
+pub fn find_gaps(registry: &dyn Registry, storage: &dyn StorageBackend) -> Result<GapReport> {
+    let stored = storage.list_manifests()?;
+    let stored_ids: BTreeSet<&str> = stored.iter().map(|metadata| metadata.id.as_str()).collect();
+
+    let mut entries = registry.entries()?;
+    entries.sort();
+
+    let mut report = GapReport::default();
+    for entry in entries {
+        match &entry.manifest_id {
+            Some(id) if stored_ids.contains(id.as_str()) => {}
+            Some(_) => report.stale.push(entry),
+            None => {
+                let named: Vec<String> = stored
+                    .iter()
+                    .filter(|metadata| {
+                        metadata.manifest_type == ManifestType::Model
+                            && is_named(&entry.name, &metadata.name)
+                    })
+                    .map(|metadata| metadata.id.clone())
+                    .collect();
+                if named.is_empty() {
+                    report.missing.push(entry);
+                } else {
+                    report.unsynced.push((entry, named));
+                }
+            }
+        }
+    }
+    Ok(report)
+}
+
+// Whether a registry entry is named like a manifest; Hugging Face entries are prefixed
+// with their namespace
+fn is_named(entry: &str, name: &str) -> bool {
+    entry == name
+        || entry
+            .rsplit_once('/')
+            .is_some_and(|(_, entry)| entry == name)
+}
+
+// Sends a registry request, failing on unsuccessful statuses with the registry's answer
+fn send(
+    request: reqwest::blocking::RequestBuilder,
+    context: &str,
+) -> Result<reqwest::blocking::Response> {
+    let response = request
+        .send()
+        .map_err(|e| Error::storage_request(context, e))?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().unwrap_or_default();
+        return Err(Error::storage_status(
+            status,
+            format!("{context}: {status} {}", body.trim()),
+        ));
+    }
+    Ok(response)
+}
+
+// Parses the JSON body of a registry response
+fn read_json<T: serde::de::DeserializeOwned>(
+    response: reqwest::blocking::Response,
+    context: &str,
+) -> Result<T> {
+    response
+        .json()
+        .map_err(|e| Error::Storage(format!("{context}: invalid response: {e}")))
+}
@@ -0,0 +1,212 @@
+//! Vertex AI Model Registry.
+//!
+//! Summaries are recorded as labels of the model with the entry's display name, and the
+//! model card, which also lists the digests, replaces its description. Label values are
+//! limited to 63 lowercase characters, so the manifest ID is recorded without its
+//! `urn:c2pa:` prefix, and manifests with content-addressed IDs can't be pushed.
+//!
+//! Models are looked up in `--namespace`, given as `projects/<PROJECT>/locations/<LOCATION>`,
+//! through the regional endpoint of the location unless a URL is given. Requests
+//! authenticate with `GOOGLE_OAUTH_ACCESS_TOKEN` as a bearer token.
+
+use super::{ManifestSummary, Registry, RegistryEntry, read_json, send};
+use crate::error::{Error, Result};
+use crate::http;
+use reqwest::blocking::{Client, RequestBuilder};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::BTreeMap;
+
+/// Environment variable holding the access token
+pub const TOKEN_ENV: &str = "GOOGLE_OAUTH_ACCESS_TOKEN";
+
+/// Label holding the manifest ID, without `urn:c2pa:`
+pub const MANIFEST_ID_LABEL: &str = "atlas-manifest";
+/// Label holding the verification status
+pub const VERIFICATION_LABEL: &str = "atlas-verification";
+
+const MAX_LABEL_LEN: usize = 63;
+
+// Models requested per page
+const PAGE_SIZE: usize = 100;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListResponse {
+    #[serde(default)]
+    models: Vec<Model>,
+    next_page_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Model {
+    /// Resource name, `projects/<PROJECT>/locations/<LOCATION>/models/<ID>`
+    name: String,
+    display_name: String,
+    #[serde(default)]
+    labels: BTreeMap<String, String>,
+}
+
+pub struct VertexRegistry {
+    client: Client,
+    base_url: String,
+    namespace: String,
+}
+
+impl VertexRegistry {
+    pub fn new(url: Option<String>, namespace: Option<String>) -> Result<Self> {
+        let namespace = namespace.ok_or_else(|| {
+            Error::Validation(
+                "Vertex AI needs --namespace=projects/<PROJECT>/locations/<LOCATION>".to_string(),
+            )
+        })?;
+        let location = match namespace.split('/').collect::<Vec<_>>()[..] {
+            ["projects", project, "locations", location]
+                if !project.is_empty() && !location.is_empty() =>
+            {
+                location.to_string()
+            }
+            _ => {
+                return Err(Error::Validation(format!(
+                    "Invalid Vertex AI namespace {namespace}, expected projects/<PROJECT>/locations/<LOCATION>"
+                )));
+            }
+        };
+        let base_url = url
+            .unwrap_or_else(|| format!("https://{location}-aiplatform.googleapis.com"))
+            .trim_end_matches('/')
+            .to_string();
+        let client = http::client_builder()
+            .build()
+            .map_err(|e| Error::Storage(format!("Failed to create HTTP client: {e}")))?;
+
+        Ok(VertexRegistry {
+            client,
+            base_url,
+            namespace,
+        })
+    }
+
+    fn request(&self, method: reqwest::Method, url: impl reqwest::IntoUrl) -> RequestBuilder {
+        let request = self.client.request(method, url);
+        match std::env::var(TOKEN_ENV) {
+            Ok(token) => request.bearer_auth(token),
+            Err(_) => request,
+        }
+    }
+
+    fn models(&self) -> Result<Vec<Model>> {
+        let context = "Failed to list Vertex AI models";
+        let mut models = Vec::new();
+        let mut page_token: Option<String> = None;
+        loop {
+            let mut url =
+                reqwest::Url::parse(&format!("{}/v1/{}/models", self.base_url, self.namespace))
+                    .map_err(|e| Error::Validation(format!("Invalid Vertex AI URL: {e}")))?;
+            url.query_pairs_mut()
+                .append_pair("pageSize", &PAGE_SIZE.to_string());
+            if let Some(token) = &page_token {
+                url.query_pairs_mut().append_pair("pageToken", token);
+            }
+            let page: ListResponse = read_json(
+                send(self.request(reqwest::Method::GET, url), context)?,
+                context,
+            )?;
+
+            models.extend(page.models);
+            page_token = page.next_page_token.filter(|token| !token.is_empty());
+            if page_token.is_none() {
+                return Ok(models);
+            }
+        }
+    }
+}
+
+/// Value of the manifest ID label of a manifest
+pub fn manifest_id_label(manifest_id: &str) -> Result<String> {
+    let value = manifest_id
+        .strip_prefix("urn:c2pa:")
+        .unwrap_or(manifest_id)
+        .to_lowercase();
+    let fits = value.len() <= MAX_LABEL_LEN
+        && value
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+    if !fits {
+        return Err(Error::Validation(format!(
+            "Manifest ID {manifest_id} doesn't fit in a Vertex AI label"
+        )));
+    }
+    Ok(value)
+}
+
+impl Registry for VertexRegistry {
+    fn push(&self, entry: &str, summary: &ManifestSummary) -> Result<()> {
+        let manifest_label = manifest_id_label(&summary.manifest_id)?;
+        let model = self
+            .models()?
+            .into_iter()
+            .find(|model| model.display_name == entry)
+            .ok_or_else(|| {
+                Error::NotFound(format!(
+                    "No Vertex AI model named {entry} in {}",
+                    self.namespace
+                ))
+            })?;
+
+        // The update replaces all labels, so the model's other labels are kept
+        let mut labels = model.labels;
+        labels.insert(MANIFEST_ID_LABEL.to_string(), manifest_label);
+        labels.insert(
+            VERIFICATION_LABEL.to_string(),
+            summary.verification.to_string(),
+        );
+
+        let mut url = reqwest::Url::parse(&format!("{}/v1/{}", self.base_url, model.name))
+            .map_err(|e| Error::Validation(format!("Invalid Vertex AI URL: {e}")))?;
+        url.query_pairs_mut()
+            .append_pair("updateMask", "labels,description");
+        send(
+            self.request(reqwest::Method::PATCH, url).json(&json!({
+                "labels": labels,
+                "description": summary.model_card,
+            })),
+            &format!("Failed to update Vertex AI model {entry}"),
+        )?;
+        Ok(())
+    }
+
+    fn entries(&self) -> Result<Vec<RegistryEntry>> {
+        Ok(self
+            .models()?
+            .into_iter()
+            .map(|model| RegistryEntry {
+                manifest_id: model
+                    .labels
+                    .get(MANIFEST_ID_LABEL)
+                    .map(|id| format!("urn:c2pa:{id}")),
+                name: model.display_name,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_id_label() -> Result<()> {
+        assert_eq!(
+            manifest_id_label("urn:c2pa:6F1C2A0E-8B7D-4C3E-9A1F-2D3E4F5A6B7C")?,
+            "6f1c2a0e-8b7d-4c3e-9a1f-2d3e4f5a6b7c"
+        );
+        assert!(manifest_id_label(&format!("urn:c2pa:sha256-{}", "ab".repeat(32))).is_err());
+        assert!(VertexRegistry::new(None, Some("projects/p/locations/".to_string())).is_err());
+        assert!(
+            VertexRegistry::new(None, Some("projects/p/locations/us-central1".to_string())).is_ok()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,199 @@
+//! Restricted-algorithm (FIPS) mode.
+//!
+//! When enabled with `--fips` or `ATLAS_FIPS=1`, signing and signature verification only
+//! accept approved keys (RSA of at least 3072 bits, the NIST P-256, P-384 and P-521 curves,
+//! Ed25519, Ed448 and ML-DSA), and verification rejects manifests that record hashes or keys outside
+//! the approved set. The mode applies to the whole process and is set once at startup.
+
+use crate::cli::color;
+use crate::error::{Error, Result};
+use crate::manifest::approval::APPROVAL_ASSERTION_LABEL;
+use crate::manifest::claims;
+use atlas_c2pa_lib::assertion::Assertion;
+use atlas_c2pa_lib::manifest::Manifest;
+use openssl::nid::Nid;
+use openssl::pkey::{HasPublic, Id, PKey, PKeyRef};
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Environment variable that enables restricted-algorithm mode
+pub const FIPS_ENV: &str = "ATLAS_FIPS";
+
+/// Hash algorithms allowed in restricted-algorithm mode
+pub const APPROVED_HASH_ALGORITHMS: &[&str] = &["sha256", "sha384", "sha512"];
+
+/// Smallest RSA modulus allowed in restricted-algorithm mode
+pub const MIN_RSA_BITS: u32 = 3072;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Turns restricted-algorithm mode on for the rest of the process
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Whether restricted-algorithm mode is on, through [`enable`] or `ATLAS_FIPS`
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+        || std::env::var(FIPS_ENV).is_ok_and(|value| matches!(value.as_str(), "1" | "true"))
+}
+
+/// Checks that a hash algorithm name is in the approved set
+///
+/// # Examples
+///
+/// ```
+/// use atlas_cli::signing::fips::check_hash_algorithm;
+///
+/// assert!(check_hash_algorithm("SHA384").is_ok());
+/// assert!(check_hash_algorithm("blake3").is_err());
+/// ```
+pub fn check_hash_algorithm(algorithm: &str) -> Result<()> {
+    if APPROVED_HASH_ALGORITHMS.contains(&algorithm.to_lowercase().as_str()) {
+        Ok(())
+    } else {
+        Err(Error::Validation(format!(
+            "Hash algorithm {algorithm} is not approved in FIPS mode"
+        )))
+    }
+}
+
+/// Checks that a signing or verification key is in the approved set
+pub fn check_key<T: HasPublic>(key: &PKeyRef<T>) -> Result<()> {
+    let reason = match key.id() {
+        Id::RSA if key.bits() >= MIN_RSA_BITS => return Ok(()),
+        Id::RSA => format!("RSA-{} keys are below {MIN_RSA_BITS} bits", key.bits()),
+        Id::EC => {
+            let curve = key.ec_key().ok().and_then(|ec| ec.group().curve_name());
+            match curve {
+                Some(Nid::X9_62_PRIME256V1 | Nid::SECP384R1 | Nid::SECP521R1) => return Ok(()),
+                Some(nid) => format!(
+                    "curve {} is not approved",
+                    nid.short_name().unwrap_or("unknown")
+                ),
+                None => "the curve can't be identified".to_string(),
+            }
+        }
+        Id::ED25519 | Id::ED448 => return Ok(()),
+        _ if super::pq::ml_dsa_variant(key).is_some() => return Ok(()),
+        _ => "the key type is not approved".to_string(),
+    };
+
+    Err(Error::Signing(format!(
+        "Key not allowed in FIPS mode: {reason}"
+    )))
+}
+
+/// An algorithm recorded in a manifest that is outside the approved set
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FipsViolation {
+    /// Where the algorithm was found, e.g. the ingredient title
+    pub location: String,
+    pub detail: String,
+}
+
+impl fmt::Display for FipsViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.location, self.detail)
+    }
+}
+
+/// Lists the hash algorithms and keys recorded in a manifest that are not approved
+pub fn manifest_violations(manifest: &Manifest) -> Vec<FipsViolation> {
+    let mut violations = Vec::new();
+    let claim_ingredients = manifest
+        .claim_v2
+        .iter()
+        .flat_map(|claim| claim.ingredients.iter());
+
+    for ingredient in manifest.ingredients.iter().chain(claim_ingredients) {
+        if check_hash_algorithm(&ingredient.data.alg).is_err() {
+            violations.push(FipsViolation {
+                location: format!("ingredient {}", ingredient.title),
+                detail: format!("hash algorithm {}", ingredient.data.alg),
+            });
+        }
+    }
+
+    // Cross-references don't name their algorithm, so it is inferred from the digest length
+    for cross_ref in &manifest.cross_references {
+        if !matches!(cross_ref.manifest_hash.len(), 64 | 96 | 128) {
+            violations.push(FipsViolation {
+                location: format!("cross-reference to {}", cross_ref.manifest_url),
+                detail: format!(
+                    "{}-bit digest is not SHA-256, SHA-384 or SHA-512",
+                    cross_ref.manifest_hash.len() * 4
+                ),
+            });
+        }
+    }
+
+    let assertions = &claims::signed_claim(manifest).created_assertions;
+    for assertion in assertions {
+        if let Assertion::CustomAssertion(custom) = assertion
+            && custom.label == APPROVAL_ASSERTION_LABEL
+            && let Some(public_key) = custom.data["public_key"].as_str()
+        {
+            let reviewer = custom.data["reviewer"].as_str().unwrap_or("unknown");
+            let result = PKey::public_key_from_pem(public_key.as_bytes())
+                .map_err(|e| Error::Signing(e.to_string()))
+                .and_then(|key| check_key(&key));
+            if let Err(e) = result {
+                violations.push(FipsViolation {
+                    location: format!("approval by {reviewer}"),
+                    detail: e.to_string(),
+                });
+            }
+        }
+    }
+
+    violations
+}
+
+/// Reports the non-approved algorithms of a manifest, failing if there are any
+pub fn check_manifest(manifest: &Manifest) -> Result<()> {
+    let violations = manifest_violations(manifest);
+    if violations.is_empty() {
+        println!(
+            "{} Manifest uses only FIPS-approved algorithms",
+            color::check_mark()
+        );
+        return Ok(());
+    }
+
+    for violation in &violations {
+        println!(
+            "{} Non-compliant algorithm in {violation}",
+            color::ballot_x()
+        );
+    }
+    Err(Error::Validation(format!(
+        "Manifest uses {} algorithm(s) not approved in FIPS mode",
+        violations.len()
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::rsa::Rsa;
+
+    #[test]
+    fn test_check_key() -> Result<()> {
+        let rsa = |bits| PKey::from_rsa(Rsa::generate(bits).unwrap()).unwrap();
+        assert!(check_key(&rsa(2048)).is_err());
+        assert!(check_key(&rsa(3072)).is_ok());
+
+        let ec = |nid| {
+            let group = EcGroup::from_curve_name(nid).unwrap();
+            PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap()
+        };
+        assert!(check_key(&ec(Nid::X9_62_PRIME256V1)).is_ok());
+        assert!(check_key(&ec(Nid::SECP384R1)).is_ok());
+        assert!(check_key(&ec(Nid::SECP256K1)).is_err());
+
+        assert!(check_key(&PKey::generate_ed25519().unwrap()).is_ok());
+        Ok(())
+    }
+}
@@ -0,0 +1,276 @@
+//! Signing with keys held on a PIV smartcard, such as a YubiKey.
+//!
+//! A key on the card is selected with `--key piv:<SLOT>` (`piv:` alone uses the Digital
+//! Signature slot, 9c). The private key never leaves the card: data is signed with
+//! `yubico-piv-tool`, after verifying the PIN from `ATLAS_PIV_PIN` or an interactive prompt.
+//! The certificate stored in the slot is read from the card so that it can be embedded next
+//! to the signature.
+
+use crate::error::{Error, Result};
+use atlas_c2pa_lib::cose::HashAlgorithm;
+use openssl::nid::Nid;
+use openssl::pkey::Id;
+use openssl::x509::X509;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+use uuid::Uuid;
+use zeroize::Zeroizing;
+
+/// Prefix of `--key` values that select a PIV slot instead of a key file
+pub const PIV_KEY_PREFIX: &str = "piv:";
+
+/// Environment variable holding the PIV PIN, for non-interactive use
+pub const PIV_PIN_ENV: &str = "ATLAS_PIV_PIN";
+
+/// Label of the assertion embedding the certificate of a PIV-signed manifest
+pub const PIV_CERTIFICATE_ASSERTION_LABEL: &str = "piv_certificate";
+
+/// Contents of the certificate assertion
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PivCertificateAssertion {
+    pub slot: String,
+    /// PEM-encoded certificate of the signing key
+    pub certificate: String,
+    /// Hash algorithm of the claim signature
+    pub hash_algorithm: String,
+}
+
+/// A PIV key slot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PivSlot(u8);
+
+impl PivSlot {
+    /// PIV Authentication
+    pub const AUTHENTICATION: PivSlot = PivSlot(0x9a);
+    /// Digital Signature, the slot meant for signing documents
+    pub const SIGNATURE: PivSlot = PivSlot(0x9c);
+    /// Key Management
+    pub const KEY_MANAGEMENT: PivSlot = PivSlot(0x9d);
+    /// Card Authentication
+    pub const CARD_AUTHENTICATION: PivSlot = PivSlot(0x9e);
+}
+
+impl Default for PivSlot {
+    fn default() -> Self {
+        PivSlot::SIGNATURE
+    }
+}
+
+impl FromStr for PivSlot {
+    type Err = Error;
+
+    /// Parses a slot number in hex: 9a, 9c, 9d, 9e or a retired key slot (82-95)
+    fn from_str(s: &str) -> Result<Self> {
+        let slot = u8::from_str_radix(s.trim(), 16)
+            .ok()
+            .filter(|slot| matches!(slot, 0x9a | 0x9c | 0x9d | 0x9e | 0x82..=0x95))
+            .ok_or_else(|| {
+                Error::Validation(format!(
+                    "Invalid PIV slot '{s}'. Valid slots are 9a, 9c, 9d, 9e and 82-95"
+                ))
+            })?;
+        Ok(PivSlot(slot))
+    }
+}
+
+impl fmt::Display for PivSlot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02x}", self.0)
+    }
+}
+
+/// Returns the PIV slot selected by a `--key` value, or `None` for key files
+///
+/// # Examples
+///
+/// ```
+/// use atlas_cli::signing::piv::{PivSlot, slot_from_key_spec};
+/// use std::path::Path;
+///
+/// assert_eq!(slot_from_key_spec(Path::new("piv:")).unwrap().unwrap(), PivSlot::SIGNATURE);
+/// assert_eq!(slot_from_key_spec(Path::new("piv:9a")).unwrap().unwrap(), PivSlot::AUTHENTICATION);
+/// assert!(slot_from_key_spec(Path::new("private.pem")).is_none());
+/// ```
+pub fn slot_from_key_spec(key_path: &Path) -> Option<Result<PivSlot>> {
+    let slot = key_path.to_str()?.strip_prefix(PIV_KEY_PREFIX)?;
+    if slot.is_empty() {
+        Some(Ok(PivSlot::default()))
+    } else {
+        Some(slot.parse())
+    }
+}
+
+/// Reads the certificate stored in a slot
+pub fn read_certificate(slot: PivSlot) -> Result<X509> {
+    let pem = run_piv_tool(
+        Command::new("yubico-piv-tool").args([
+            "-a",
+            "read-certificate",
+            "-s",
+            &slot.to_string(),
+            "-K",
+            "PEM",
+        ]),
+        None,
+    )?;
+    X509::from_pem(&pem).map_err(|e| {
+        Error::Signing(format!(
+            "No usable certificate in PIV slot {slot}: {e}. Import the certificate of the key \
+             with `yubico-piv-tool -a import-certificate -s {slot}`"
+        ))
+    })
+}
+
+/// Signs data with the key in a slot, checking the signature against the slot's certificate
+///
+/// The signature has the same form as one made by [`super::sign_data_with_algorithm`] with the
+/// certificate's key, so it verifies the same way.
+pub fn sign(
+    data: &[u8],
+    slot: PivSlot,
+    certificate: &X509,
+    hash_alg: &HashAlgorithm,
+) -> Result<Vec<u8>> {
+    let public_key = certificate
+        .public_key()
+        .map_err(|e| Error::Signing(format!("Invalid certificate in PIV slot {slot}: {e}")))?;
+    if super::fips::is_enabled() {
+        super::fips::check_key(&public_key)?;
+    }
+
+    let algorithm = match (public_key.id(), public_key.bits()) {
+        (Id::RSA, bits @ (1024 | 2048 | 3072 | 4096)) => format!("RSA{bits}"),
+        (Id::EC, _) => {
+            let curve = public_key
+                .ec_key()
+                .ok()
+                .and_then(|ec| ec.group().curve_name());
+            match curve {
+                Some(Nid::X9_62_PRIME256V1) => "ECCP256".to_string(),
+                Some(Nid::SECP384R1) => "ECCP384".to_string(),
+                _ => {
+                    return Err(Error::Signing(format!(
+                        "Unsupported curve for the key in PIV slot {slot}"
+                    )));
+                }
+            }
+        }
+        _ => {
+            return Err(Error::Signing(format!(
+                "Unsupported key type in PIV slot {slot}"
+            )));
+        }
+    };
+    let hash = match hash_alg {
+        HashAlgorithm::Sha256 => "SHA256",
+        HashAlgorithm::Sha384 => "SHA384",
+        HashAlgorithm::Sha512 => "SHA512",
+    };
+
+    let pin = read_pin()?;
+
+    // yubico-piv-tool reads the data from a file, since stdin carries the PIN
+    let work_dir = std::env::temp_dir();
+    let input_path = work_dir.join(format!("atlas-piv-{}.in", Uuid::new_v4()));
+    let output_path = input_path.with_extension("sig");
+    fs::write(&input_path, data)?;
+
+    let mut cmd = Command::new("yubico-piv-tool");
+    cmd.args([
+        "--stdin-input",
+        "-a",
+        "verify-pin",
+        "-a",
+        "sign-data",
+        "-s",
+        &slot.to_string(),
+        "-A",
+        &algorithm,
+        "-H",
+        hash,
+    ])
+    .arg("-i")
+    .arg(&input_path)
+    .arg("-o")
+    .arg(&output_path);
+    let result = run_piv_tool(&mut cmd, Some(&pin)).and_then(|_| Ok(fs::read(&output_path)?));
+
+    let _ = fs::remove_file(&input_path);
+    let _ = fs::remove_file(&output_path);
+    let signature = result?;
+
+    // A signature that doesn't match the certificate means the certificate belongs to
+    // another key, so embedding it would make the manifest unverifiable
+    if !super::verify_signature_with_algorithm(data, &signature, &public_key, hash_alg)? {
+        return Err(Error::Signing(format!(
+            "The certificate in PIV slot {slot} does not match its key"
+        )));
+    }
+
+    Ok(signature)
+}
+
+fn read_pin() -> Result<Zeroizing<String>> {
+    if let Ok(pin) = std::env::var(PIV_PIN_ENV) {
+        return Ok(Zeroizing::new(pin));
+    }
+    rpassword::prompt_password("PIV PIN: ")
+        .map(Zeroizing::new)
+        .map_err(|e| {
+            Error::Signing(format!(
+                "Failed to read the PIV PIN (set {PIV_PIN_ENV} when there is no terminal): {e}"
+            ))
+        })
+}
+
+fn run_piv_tool(cmd: &mut Command, pin: Option<&Zeroizing<String>>) -> Result<Vec<u8>> {
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| {
+            Error::Signing(format!(
+                "Failed to run yubico-piv-tool (is it installed?): {e}"
+            ))
+        })?;
+
+    if let Some(pin) = pin
+        && let Some(mut stdin) = child.stdin.take()
+    {
+        let line = Zeroizing::new(format!("{}\n", pin.as_str()));
+        stdin.write_all(line.as_bytes())?;
+    }
+    // Closing stdin keeps the tool from waiting for input that will never come
+    drop(child.stdin.take());
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(Error::Signing(format!(
+            "yubico-piv-tool failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_piv_slot() -> Result<()> {
+        assert_eq!("9c".parse::<PivSlot>()?, PivSlot::SIGNATURE);
+        assert_eq!("9D".parse::<PivSlot>()?, PivSlot::KEY_MANAGEMENT);
+        assert_eq!("82".parse::<PivSlot>()?.to_string(), "82");
+        assert!("9b".parse::<PivSlot>().is_err());
+        assert!("96".parse::<PivSlot>().is_err());
+        assert!(slot_from_key_spec(Path::new("piv:zz")).unwrap().is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,140 @@
+//! Post-quantum ML-DSA (FIPS 204) signatures.
+//!
+//! ML-DSA keys are used like any other PEM key: [`super::sign_data_with_algorithm`] and
+//! [`super::verify_signature_with_algorithm`] detect them and sign the data directly, since
+//! ML-DSA does not sign a pre-computed digest. Signing and verifying needs the experimental
+//! `pq` feature and OpenSSL 3.5 or newer.
+//!
+//! In hybrid mode, manifests and DSSE envelopes carry both a classical and an ML-DSA
+//! signature, so they stay verifiable if either algorithm is broken.
+
+use crate::error::{Error, Result};
+use openssl::pkey::{HasPrivate, HasPublic, PKey, PKeyRef};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+
+/// Label of the assertion holding the ML-DSA signature of a hybrid-signed manifest
+pub const PQ_SIGNATURE_ASSERTION_LABEL: &str = "pq_signature";
+
+/// ML-DSA parameter sets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MlDsaVariant {
+    #[serde(rename = "ML-DSA-44")]
+    MlDsa44,
+    #[serde(rename = "ML-DSA-65")]
+    MlDsa65,
+    #[serde(rename = "ML-DSA-87")]
+    MlDsa87,
+}
+
+impl fmt::Display for MlDsaVariant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            MlDsaVariant::MlDsa44 => "ML-DSA-44",
+            MlDsaVariant::MlDsa65 => "ML-DSA-65",
+            MlDsaVariant::MlDsa87 => "ML-DSA-87",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Contents of the ML-DSA signature assertion
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PqSignatureAssertion {
+    pub algorithm: MlDsaVariant,
+    /// PEM-encoded ML-DSA public key
+    pub public_key: String,
+    /// Base64-encoded ML-DSA signature
+    pub signature: String,
+}
+
+/// Returns the ML-DSA parameter set of a key, or `None` for other key types
+pub fn ml_dsa_variant<T: HasPublic>(key: &PKeyRef<T>) -> Option<MlDsaVariant> {
+    // OpenSSL only knows ML-DSA keys through its provider, so they are recognized by the
+    // algorithm OID (2.16.840.1.101.3.4.3.17-19) of their SubjectPublicKeyInfo
+    const OID_PREFIX: [u8; 10] = [0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x03];
+
+    let der = key.public_key_to_der().ok()?;
+    let position = der
+        .windows(OID_PREFIX.len())
+        .position(|window| window == OID_PREFIX)?;
+    match der.get(position + OID_PREFIX.len())? {
+        0x11 => Some(MlDsaVariant::MlDsa44),
+        0x12 => Some(MlDsaVariant::MlDsa65),
+        0x13 => Some(MlDsaVariant::MlDsa87),
+        _ => None,
+    }
+}
+
+/// Loads an ML-DSA private key, failing for other key types
+pub fn load_ml_dsa_key(path: &Path) -> Result<super::SecurePrivateKey> {
+    let key = super::load_private_key(path)?;
+    if ml_dsa_variant(key.as_pkey()).is_none() {
+        return Err(Error::Signing(format!(
+            "{} is not an ML-DSA private key",
+            path.display()
+        )));
+    }
+    Ok(key)
+}
+
+/// Signs data with an ML-DSA key
+#[cfg(feature = "pq")]
+pub(crate) fn sign<T: HasPrivate>(data: &[u8], key: &PKeyRef<T>) -> Result<Vec<u8>> {
+    openssl::sign::Signer::new_without_digest(key)
+        .and_then(|mut signer| signer.sign_oneshot_to_vec(data))
+        .map_err(|e| Error::Signing(format!("ML-DSA signing failed: {e}")))
+}
+
+#[cfg(not(feature = "pq"))]
+pub(crate) fn sign<T: HasPrivate>(_data: &[u8], _key: &PKeyRef<T>) -> Result<Vec<u8>> {
+    Err(not_supported())
+}
+
+/// Verifies an ML-DSA signature
+#[cfg(feature = "pq")]
+pub(crate) fn verify<T: HasPublic>(
+    data: &[u8],
+    signature: &[u8],
+    key: &PKeyRef<T>,
+) -> Result<bool> {
+    openssl::sign::Verifier::new_without_digest(key)
+        .and_then(|mut verifier| verifier.verify_oneshot(signature, data))
+        .map_err(|e| Error::Signing(format!("ML-DSA verification failed: {e}")))
+}
+
+#[cfg(not(feature = "pq"))]
+pub(crate) fn verify<T: HasPublic>(
+    _data: &[u8],
+    _signature: &[u8],
+    _key: &PKeyRef<T>,
+) -> Result<bool> {
+    Err(not_supported())
+}
+
+#[cfg(not(feature = "pq"))]
+fn not_supported() -> Error {
+    Error::Signing(
+        "ML-DSA keys need atlas-cli built with the experimental `pq` feature".to_string(),
+    )
+}
+
+/// Verifies a PEM-encoded ML-DSA public key and signature recorded in an assertion
+pub fn verify_assertion(data: &[u8], assertion: &PqSignatureAssertion) -> Result<bool> {
+    use base64::Engine;
+
+    let key = PKey::public_key_from_pem(assertion.public_key.as_bytes())
+        .map_err(|e| Error::Signing(format!("Invalid ML-DSA public key: {e}")))?;
+    if ml_dsa_variant(&key) != Some(assertion.algorithm) {
+        return Err(Error::Signing(format!(
+            "Public key is not an {} key",
+            assertion.algorithm
+        )));
+    }
+    let signature = base64::engine::general_purpose::STANDARD
+        .decode(&assertion.signature)
+        .map_err(|e| Error::Signing(format!("Invalid ML-DSA signature encoding: {e}")))?;
+
+    verify(data, &signature, &key)
+}
@@ -0,0 +1,281 @@
+//! Signing of data read from a stream.
+//!
+//! RSA and ECDSA sign a digest of the data, so the data can be hashed as it is read, a chunk
+//! at a time, rather than first being gathered into one buffer. The signatures are the same
+//! as those of [`super::sign_data_with_algorithm`], so verifiers don't need to know how a
+//! signature was made.
+//!
+//! Memory use is only bounded if the reader is. DSSE envelopes hold their payload, so
+//! signing one this way spares the copy of the payload that signing it in one piece makes,
+//! not the payload itself; detached envelopes are signed from a file instead, see
+//! [`crate::in_toto::dsse::Envelope::sign_detached`], which [`DigestingReader`] also
+//! hashes for the envelope's payload digest as it is read.
+//!
+//! Ed25519, Ed448 and ML-DSA hash the message more than once while signing it, so they need
+//! all of it at once, and streamed data is rejected for them. Ed25519ph, the prehash
+//! variant of Ed25519, is not offered: OpenSSL provides it only through parameters the
+//! `openssl` bindings don't expose, and its signatures differ from Ed25519 signatures, so
+//! verifiers would have to be told which variant was used. Large payloads are signed with
+//! an RSA or ECDSA key.
+
+use super::SecurePrivateKey;
+use crate::error::{Error, Result};
+use atlas_c2pa_lib::cose::HashAlgorithm;
+use openssl::hash::{Hasher, MessageDigest};
+use openssl::pkey::{HasPublic, Id, PKeyRef, Public};
+use openssl::sign::{Signer, Verifier};
+use std::io::Read;
+
+/// Bytes hashed at a time
+pub const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Whether a key can sign and verify streamed data, see [`sign_reader`]
+pub fn supports_streaming<T: HasPublic>(key: &PKeyRef<T>) -> bool {
+    matches!(key.id(), Id::RSA | Id::EC)
+}
+
+fn message_digest(algorithm: &HashAlgorithm) -> MessageDigest {
+    match algorithm {
+        HashAlgorithm::Sha256 => MessageDigest::sha256(),
+        HashAlgorithm::Sha384 => MessageDigest::sha384(),
+        HashAlgorithm::Sha512 => MessageDigest::sha512(),
+    }
+}
+
+fn check_streaming<T: HasPublic>(key: &PKeyRef<T>) -> Result<()> {
+    if super::fips::is_enabled() {
+        super::fips::check_key(key)?;
+    }
+    if supports_streaming(key) {
+        return Ok(());
+    }
+    let key_type = match key.id() {
+        Id::ED25519 => "Ed25519 (Ed25519ph is not supported)".to_string(),
+        Id::ED448 => "Ed448".to_string(),
+        _ => match super::pq::ml_dsa_variant(key) {
+            Some(variant) => variant.to_string(),
+            None => "These".to_string(),
+        },
+    };
+    Err(Error::Signing(format!(
+        "{key_type} keys sign the whole payload at once and can't sign streamed data; use an RSA or ECDSA key for large payloads"
+    )))
+}
+
+// Feeds `reader` to `update` a chunk at a time
+fn stream(
+    reader: &mut dyn Read,
+    mut update: impl FnMut(&[u8]) -> std::result::Result<(), openssl::error::ErrorStack>,
+) -> Result<()> {
+    let mut buffer = vec![0u8; CHUNK_SIZE];
+    loop {
+        let n = match reader.read(&mut buffer) {
+            Ok(0) => return Ok(()),
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+        update(&buffer[..n]).map_err(|e| Error::Signing(format!("Failed to hash data: {e}")))?;
+    }
+}
+
+/// Signs the data read from `reader`, hashing it a chunk at a time.
+///
+/// # Errors
+///
+/// Returns a `Signing` error for keys that can't sign streamed data, see
+/// [`supports_streaming`].
+pub fn sign_reader(
+    reader: &mut dyn Read,
+    private_key: &SecurePrivateKey,
+    algorithm: &HashAlgorithm,
+) -> Result<Vec<u8>> {
+    check_streaming(private_key.as_pkey())?;
+
+    let mut signer = Signer::new(message_digest(algorithm), private_key.as_pkey())
+        .map_err(|e| Error::Signing(format!("Failed to create signer: {e}")))?;
+    stream(reader, |chunk| signer.update(chunk))?;
+    signer
+        .sign_to_vec()
+        .map_err(|e| Error::Signing(format!("Failed to sign data: {e}")))
+}
+
+/// Verifies a signature of the data read from `reader`, hashing it a chunk at a time
+pub fn verify_reader(
+    reader: &mut dyn Read,
+    signature: &[u8],
+    public_key: &PKeyRef<Public>,
+    algorithm: &HashAlgorithm,
+) -> Result<bool> {
+    check_streaming(public_key)?;
+
+    let mut verifier = Verifier::new(message_digest(algorithm), public_key)
+        .map_err(|e| Error::Signing(e.to_string()))?;
+    stream(reader, |chunk| verifier.update(chunk))?;
+    verifier
+        .verify(signature)
+        .map_err(|e| Error::Signing(e.to_string()))
+}
+
+/// Verifies several signatures of the data read from `reader` in one pass, returning
+/// whether any of them verifies. Malformed signatures don't verify.
+pub fn verify_reader_any(
+    reader: &mut dyn Read,
+    signatures: &[&[u8]],
+    public_key: &PKeyRef<Public>,
+    algorithm: &HashAlgorithm,
+) -> Result<bool> {
+    check_streaming(public_key)?;
+
+    let mut verifiers = signatures
+        .iter()
+        .map(|_| Verifier::new(message_digest(algorithm), public_key))
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| Error::Signing(e.to_string()))?;
+    stream(reader, |chunk| {
+        verifiers
+            .iter_mut()
+            .try_for_each(|verifier| verifier.update(chunk))
+    })?;
+    Ok(verifiers
+        .iter()
+        .zip(signatures)
+        .any(|(verifier, signature)| verifier.verify(signature).unwrap_or(false)))
+}
+
+/// Reader passing the data of another reader through while hashing it, so that data can be
+/// signed and its digest taken in one pass
+pub struct DigestingReader<R> {
+    inner: R,
+    hashers: Vec<Hasher>,
+}
+
+impl<R: Read> DigestingReader<R> {
+    /// Hashes the data read through it with each of `algorithms`
+    pub fn new(inner: R, algorithms: &[HashAlgorithm]) -> Result<Self> {
+        let hashers = algorithms
+            .iter()
+            .map(|algorithm| Hasher::new(message_digest(algorithm)))
+            .collect::<std::result::Result<_, _>>()
+            .map_err(|e| Error::Signing(format!("Failed to create hasher: {e}")))?;
+        Ok(Self { inner, hashers })
+    }
+
+    /// Hex-encoded digests of the data read, in the order of the algorithms
+    pub fn finish(self) -> Result<Vec<String>> {
+        self.hashers
+            .into_iter()
+            .map(|mut hasher| {
+                hasher
+                    .finish()
+                    .map(hex::encode)
+                    .map_err(|e| Error::Signing(format!("Failed to hash data: {e}")))
+            })
+            .collect()
+    }
+}
+
+impl<R: Read> Read for DigestingReader<R> {
+    fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buffer)?;
+        for hasher in &mut self.hashers {
+            hasher.update(&buffer[..n]).map_err(std::io::Error::other)?;
+        }
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing::test_utils::generate_temp_key;
+    use crate::signing::{pkey_to_secure, sign_data_with_algorithm};
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::nid::Nid;
+    use openssl::pkey::PKey;
+
+    fn public_key(key: &SecurePrivateKey) -> PKey<Public> {
+        PKey::public_key_from_pem(&key.as_pkey().public_key_to_pem().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_streamed_signature_matches() -> Result<()> {
+        // Spans several chunks
+        let data = vec![0x5a; 2 * CHUNK_SIZE + 17];
+
+        let (rsa, _dir) = generate_temp_key()?;
+        let streamed = sign_reader(&mut data.as_slice(), &rsa, &HashAlgorithm::Sha384)?;
+        // RSA PKCS#1 v1.5 signatures are deterministic
+        assert_eq!(
+            streamed,
+            sign_data_with_algorithm(&data, &rsa, &HashAlgorithm::Sha384)?
+        );
+
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        let ec = pkey_to_secure(PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap())?;
+        let signature = sign_reader(&mut data.as_slice(), &ec, &HashAlgorithm::Sha256)?;
+        assert!(crate::signing::verify_signature_with_algorithm(
+            &data,
+            &signature,
+            &public_key(&ec),
+            &HashAlgorithm::Sha256
+        )?);
+        assert!(verify_reader(
+            &mut data.as_slice(),
+            &signature,
+            &public_key(&ec),
+            &HashAlgorithm::Sha256
+        )?);
+        assert!(!verify_reader(
+            &mut &data[1..],
+            &signature,
+            &public_key(&ec),
+            &HashAlgorithm::Sha256
+        )?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_any_and_digest_in_one_pass() -> Result<()> {
+        let data = vec![0xa5; CHUNK_SIZE + 3];
+        let (rsa, _dir) = generate_temp_key()?;
+        let signature = sign_data_with_algorithm(&data, &rsa, &HashAlgorithm::Sha384)?;
+
+        let mut reader = DigestingReader::new(
+            data.as_slice(),
+            &[HashAlgorithm::Sha256, HashAlgorithm::Sha384],
+        )?;
+        assert!(verify_reader_any(
+            &mut reader,
+            &[b"malformed".as_slice(), &signature],
+            &public_key(&rsa),
+            &HashAlgorithm::Sha384
+        )?);
+        assert_eq!(
+            reader.finish()?,
+            [
+                crate::hash::calculate_hash_with_algorithm(&data, &HashAlgorithm::Sha256),
+                crate::hash::calculate_hash_with_algorithm(&data, &HashAlgorithm::Sha384),
+            ]
+        );
+
+        assert!(!verify_reader_any(
+            &mut &data[1..],
+            &[&signature],
+            &public_key(&rsa),
+            &HashAlgorithm::Sha384
+        )?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_eddsa_keys_are_not_streamed() -> Result<()> {
+        let key = pkey_to_secure(PKey::generate_ed25519().unwrap())?;
+        assert!(!supports_streaming(key.as_pkey()));
+        let error = sign_reader(&mut b"data".as_slice(), &key, &HashAlgorithm::Sha512)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("Ed25519"), "{error}");
+        Ok(())
+    }
+}
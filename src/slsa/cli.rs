@@ -31,6 +31,7 @@
 //!     Some(PathBuf::from("signing_key.pem")),     // signing key
 //!     HashAlgorithm::Sha384,                      // hash algorithm
 //!     "json".to_string(),                         // output format
+//!     None,                                       // no output file
 //!     true,                                       // print to console
 //!     None,                                       // no storage backend
 //!     false,                                      // no TDX support
@@ -40,16 +41,18 @@
 use crate::cli::{CLI_NAME, CLI_VERSION};
 use crate::error::{Error, Result};
 use crate::in_toto;
+use crate::in_toto::dsse::Envelope;
+use crate::manifest::attestation;
+use crate::manifest::output::write_encoded;
 use crate::slsa;
-use crate::storage::traits::StorageBackend;
+use crate::storage::traits::{EnvelopeKind, EnvelopeRecord, StorageBackend};
 
 use atlas_c2pa_lib::cose::HashAlgorithm;
 use in_toto_attestation::to_struct;
 use in_toto_attestation::v1::resource_descriptor::ResourceDescriptor;
 use protobuf::well_known_types::struct_::{ListValue, Struct, Value};
 use protobuf::well_known_types::timestamp::Timestamp;
-use serde_json::to_string_pretty;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// The Atlas CLI builder identifier for SLSA provenance.
 pub const ATLAS_CLI_BUILDER_ID: &str = "https://github.com/IntelLabs/atlas-cli";
@@ -129,8 +132,10 @@ impl ExternalParameters {
 /// * `key_path` - Optional path to private key for signing (required for valid attestations)
 /// * `hash_alg` - Hash algorithm to use for file integrity and signing operations
 /// * `output_encoding` - Output format: "json" or "cbor"
+/// * `output_path` - Optional file to write the attestation to (CBOR is written as binary)
 /// * `print` - Whether to print the attestation to stdout
-/// * `storage` - Optional storage backend for persisting the attestation
+/// * `storage` - Optional storage backend the signed envelope is stored in; the stored
+///   model and software manifests with a product as ingredient get a cross-reference to it
 /// * `_with_tdx` - TDX (Intel Trust Domain Extensions) support flag (reserved for future use)
 ///
 /// # Returns
@@ -152,10 +157,16 @@ pub fn generate_build_provenance(
     key_path: Option<PathBuf>,
     hash_alg: HashAlgorithm,
     output_encoding: String,
+    output_path: Option<PathBuf>,
     print: bool,
     storage: Option<&'static dyn StorageBackend>,
     _with_tdx: bool,
 ) -> Result<()> {
+    let pipeline_name = pipeline_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| pipeline_path.display().to_string());
+
     // Generate the SLSA BuildDefinition.externalParameters
     let external_params = ExternalParameters::new(inputs_path, pipeline_path, &hash_alg)?;
     let external_params_proto = external_params.to_struct()?;
@@ -199,40 +210,60 @@ pub fn generate_build_provenance(
         hash_alg,
     )?;
 
+    emit_provenance(
+        envelope,
+        &pipeline_name,
+        &output_encoding,
+        output_path.as_deref(),
+        print,
+        storage,
+    )
+}
+
+/// Writes signed provenance out and stores it, linking it from the manifests of its products.
+///
+/// The envelope is written if `print` is set, no storage is given or `output_path` is set.
+/// It is stored under `name` unless `print` is set.
+pub(crate) fn emit_provenance(
+    envelope: Envelope,
+    name: &str,
+    output_encoding: &str,
+    output_path: Option<&Path>,
+    print: bool,
+    storage: Option<&'static dyn StorageBackend>,
+) -> Result<()> {
     // Output manifest if requested
-    if print || storage.is_none() {
-        match output_encoding.to_lowercase().as_str() {
-            "json" => {
-                let envelope_json =
-                    to_string_pretty(&envelope).map_err(|e| Error::Serialization(e.to_string()))?;
-                println!("{envelope_json}");
-            }
-            "cbor" => {
-                let envelope_cbor = serde_cbor::to_vec(&envelope)
-                    .map_err(|e| Error::Serialization(e.to_string()))?;
-                println!("{}", hex::encode(&envelope_cbor));
-            }
-            _ => {
-                return Err(Error::Validation(format!(
-                    "Invalid output encoding '{}'. Valid options are: json, cbor",
-                    output_encoding
-                )));
-            }
-        }
+    if print || storage.is_none() || output_path.is_some() {
+        write_encoded(&envelope, output_encoding, output_path)?;
     }
 
-    // Store manifest if storage is provided
-    // FIXME: Add support for SLSA storage in backend
-    if let Some(_storage) = &storage {
-        if !print {
-            let id = 0;
-            println!("Manifest stored successfully with ID: {id}");
+    // Store the envelope if storage is provided, and link it from the manifests of its products
+    if let Some(storage) = storage
+        && !print
+    {
+        let record = EnvelopeRecord::new(EnvelopeKind::Slsa, name, None, envelope);
+        let id = storage.store_envelope(&record)?;
+        println!("Provenance stored successfully with ID: {id}");
+
+        if let Err(e) = link_product_manifests(&record, storage) {
+            println!("Warning: Could not link provenance to manifests: {e}");
         }
     }
 
     Ok(())
 }
 
+// Cross-references the provenance from the model and software manifests of its products
+fn link_product_manifests(record: &EnvelopeRecord, storage: &dyn StorageBackend) -> Result<()> {
+    let statement = in_toto::read_statement(&record.envelope)?;
+
+    for manifest_id in attestation::find_subject_manifests(&statement, storage)? {
+        let updated_id = attestation::link_envelope(&manifest_id, record, storage)?;
+        println!("Linked provenance to manifest {manifest_id} (updated manifest ID: {updated_id})");
+    }
+    Ok(())
+}
+
 fn generate_file_list_resource_descriptors(
     file_paths: Vec<PathBuf>,
     algorithm: &HashAlgorithm,
@@ -392,6 +423,7 @@ mod tests {
             Some(tmp_dir.path().join("test_key.pem")),
             HashAlgorithm::Sha256,
             "json".to_string(),
+            None,
             true,
             None,
             false,
@@ -0,0 +1,617 @@
+//! # SLSA Provenance Import from Build Systems
+//!
+//! This module converts the build metadata that container and artifact build systems
+//! already record into SLSA v1 Build Provenance signed by Atlas CLI, so that the
+//! provenance logic does not have to be duplicated in each build system.
+//!
+//! ## Supported Build Systems
+//!
+//! - **BuildKit**: The metadata file written by `docker buildx build --metadata-file`.
+//!   Provenance recorded with `--provenance=mode=max` (SLSA v0.2 or v1) is carried over,
+//!   and the built image becomes the statement subject.
+//! - **ko**: The image references written by `ko build --image-refs`, one
+//!   `name@sha256:...` reference per line.
+//! - **Bazel**: The execution log written by `--execution_log_json_file`. Outputs that
+//!   no other spawn consumes become the subjects, and inputs that no spawn produces
+//!   become the resolved dependencies.
+//!
+//! The builder of the imported provenance is the build system, not Atlas CLI, which
+//! only converts and signs the recorded metadata.
+//!
+//! ## Examples
+//!
+//! ```no_run
+//! use atlas_cli::slsa::import::{BuildSystem, import_build_provenance};
+//! use atlas_c2pa_lib::cose::HashAlgorithm;
+//! use std::path::PathBuf;
+//!
+//! import_build_provenance(
+//!     BuildSystem::Buildkit,
+//!     PathBuf::from("metadata.json"),        // buildx metadata file
+//!     Some(PathBuf::from("signing_key.pem")), // signing key
+//!     HashAlgorithm::Sha384,                 // hash algorithm
+//!     "json".to_string(),                    // output format
+//!     None,                                  // no output file
+//!     true,                                  // print to console
+//!     None,                                  // no storage backend
+//! ).unwrap();
+//! ```
+
+use crate::error::{Error, Result};
+use crate::in_toto;
+use crate::slsa;
+use crate::storage::traits::StorageBackend;
+
+use atlas_c2pa_lib::cose::HashAlgorithm;
+use in_toto_attestation::to_struct;
+use in_toto_attestation::v1::resource_descriptor::ResourceDescriptor;
+use protobuf::well_known_types::struct_::Struct;
+use protobuf::well_known_types::timestamp::Timestamp;
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::PathBuf;
+
+/// Builder identifier of BuildKit, used when its provenance does not name a builder.
+pub const BUILDKIT_BUILDER_ID: &str = "https://mobyproject.org/buildkit";
+
+/// Build type of provenance recorded by BuildKit.
+pub const BUILDKIT_BUILD_TYPE: &str = "https://mobyproject.org/buildkit@v1";
+
+/// Builder identifier and build type of provenance imported from ko.
+pub const KO_BUILDER_ID: &str = "https://ko.build";
+
+/// Builder identifier and build type of provenance imported from Bazel.
+pub const BAZEL_BUILDER_ID: &str = "https://bazel.build";
+
+const BUILDKIT_PROVENANCE_KEY: &str = "buildx.build.provenance";
+const BUILDKIT_IMAGE_DIGEST_KEY: &str = "containerimage.digest";
+const BUILDKIT_IMAGE_NAME_KEY: &str = "image.name";
+
+/// Build systems whose metadata can be imported as SLSA provenance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildSystem {
+    Buildkit,
+    Ko,
+    Bazel,
+}
+
+/// A build as recorded by a build system, before it is turned into SLSA provenance.
+#[derive(Debug, Clone)]
+pub struct ImportedBuild {
+    pub builder_id: String,
+    pub build_type: String,
+    pub external_parameters: Value,
+    pub internal_parameters: Option<Value>,
+    pub resolved_dependencies: Vec<ResourceDescriptor>,
+    pub subjects: Vec<ResourceDescriptor>,
+    pub invocation_id: String,
+    pub started_on: Option<Timestamp>,
+    pub finished_on: Option<Timestamp>,
+}
+
+/// Imports the metadata of a build as SLSA v1 build provenance signed by Atlas CLI.
+///
+/// The metadata file is parsed according to `from` (see the [module documentation](self)),
+/// and the resulting provenance is written out and stored like the provenance of
+/// [`generate_build_provenance`](crate::slsa::cli::generate_build_provenance).
+///
+/// # Arguments
+///
+/// * `from` - Build system that wrote the metadata
+/// * `metadata_path` - Path to the metadata file
+/// * `key_path` - Optional path to private key for signing (required for valid attestations)
+/// * `hash_alg` - Hash algorithm to use for signing
+/// * `output_encoding` - Output format: "json" or "cbor"
+/// * `output_path` - Optional file to write the attestation to (CBOR is written as binary)
+/// * `print` - Whether to print the attestation to stdout
+/// * `storage` - Optional storage backend the signed envelope is stored in
+///
+/// # Errors
+///
+/// Returns an error if the metadata file cannot be read or does not describe any
+/// artifact, if no signing key is given, or if signing, output or storage fails.
+#[allow(clippy::too_many_arguments)]
+pub fn import_build_provenance(
+    from: BuildSystem,
+    metadata_path: PathBuf,
+    key_path: Option<PathBuf>,
+    hash_alg: HashAlgorithm,
+    output_encoding: String,
+    output_path: Option<PathBuf>,
+    print: bool,
+    storage: Option<&'static dyn StorageBackend>,
+) -> Result<()> {
+    let metadata = fs::read_to_string(&metadata_path)?;
+    let build = match from {
+        BuildSystem::Buildkit => parse_buildkit_metadata(&metadata)?,
+        BuildSystem::Ko => parse_ko_image_refs(&metadata)?,
+        BuildSystem::Bazel => parse_bazel_execution_log(&metadata)?,
+    };
+
+    let key_path = key_path.ok_or_else(|| {
+        Error::Validation("Signing key is required for SLSA provenance".to_string())
+    })?;
+
+    let provenance = make_provenance(&build)?;
+    let envelope = in_toto::generate_signed_statement_v1(
+        &build.subjects,
+        slsa::BUILD_PROVENANCE_PREDICATE_TYPE_V1,
+        &provenance,
+        key_path,
+        hash_alg,
+    )?;
+
+    let name = metadata_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| metadata_path.display().to_string());
+
+    slsa::cli::emit_provenance(
+        envelope,
+        &name,
+        &output_encoding,
+        output_path.as_deref(),
+        print,
+        storage,
+    )
+}
+
+/// Converts an imported build into a SLSA v1 provenance predicate.
+pub fn make_provenance(build: &ImportedBuild) -> Result<Struct> {
+    let external_parameters = value_to_struct(&build.external_parameters)?;
+    let internal_parameters = build
+        .internal_parameters
+        .as_ref()
+        .map(value_to_struct)
+        .transpose()?;
+
+    let resolved_dependencies =
+        (!build.resolved_dependencies.is_empty()).then_some(build.resolved_dependencies.as_slice());
+    let build_def = slsa::generators::make_build_definition_v1(
+        &build.build_type,
+        &external_parameters,
+        internal_parameters.as_ref(),
+        resolved_dependencies,
+    );
+
+    let builder = slsa::generators::make_builder_v1(&build.builder_id, None, None);
+    let build_metadata = slsa::generators::make_build_metadata_v1(
+        &build.invocation_id,
+        build.started_on.as_ref(),
+        build.finished_on.as_ref(),
+    );
+    let run_details = slsa::generators::make_run_details_v1(&builder, Some(&build_metadata), None);
+
+    let provenance = slsa::generators::generate_build_provenance_v1(&build_def, &run_details);
+    to_struct(&provenance).map_err(|e| Error::Serialization(e.to_string()))
+}
+
+/// Parses a BuildKit metadata file, as written by `docker buildx build --metadata-file`.
+///
+/// Both SLSA v0.2 and v1 provenance recorded by BuildKit are accepted. Without recorded
+/// provenance, only the built image and the BuildKit builder are known.
+pub fn parse_buildkit_metadata(metadata: &str) -> Result<ImportedBuild> {
+    let metadata: Value = serde_json::from_str(metadata)
+        .map_err(|e| Error::Validation(format!("Invalid BuildKit metadata: {e}")))?;
+
+    let digest = metadata
+        .get(BUILDKIT_IMAGE_DIGEST_KEY)
+        .and_then(Value::as_str)
+        .ok_or_else(|| {
+            Error::Validation(format!(
+                "BuildKit metadata has no {BUILDKIT_IMAGE_DIGEST_KEY}; was an image built?"
+            ))
+        })?;
+    let (alg, hex) = split_digest(digest)?;
+    let image_name = metadata
+        .get(BUILDKIT_IMAGE_NAME_KEY)
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    // Several tags of the same image are comma-separated
+    let subjects = image_name
+        .split(',')
+        .map(|name| in_toto::make_minimal_resource_descriptor(name.trim(), alg, hex))
+        .collect();
+
+    let mut build = ImportedBuild {
+        builder_id: BUILDKIT_BUILDER_ID.to_string(),
+        build_type: BUILDKIT_BUILD_TYPE.to_string(),
+        external_parameters: json!({}),
+        internal_parameters: None,
+        resolved_dependencies: Vec::new(),
+        subjects,
+        invocation_id: String::new(),
+        started_on: None,
+        finished_on: None,
+    };
+
+    let Some(provenance) = metadata.get(BUILDKIT_PROVENANCE_KEY) else {
+        return Ok(build);
+    };
+
+    if let Some(build_definition) = provenance.get("buildDefinition") {
+        // SLSA v1 provenance
+        if let Some(build_type) = build_definition.get("buildType").and_then(Value::as_str) {
+            build.build_type = build_type.to_string();
+        }
+        if let Some(params) = build_definition.get("externalParameters") {
+            build.external_parameters = params.clone();
+        }
+        build.internal_parameters = build_definition.get("internalParameters").cloned();
+        build.resolved_dependencies =
+            parse_materials(build_definition.get("resolvedDependencies"))?;
+
+        let run_details = provenance.get("runDetails");
+        if let Some(id) = run_details
+            .and_then(|r| r.pointer("/builder/id"))
+            .and_then(Value::as_str)
+            .filter(|id| !id.is_empty())
+        {
+            build.builder_id = id.to_string();
+        }
+        let metadata = run_details.and_then(|r| r.get("metadata"));
+        build.invocation_id = string_field(metadata, "invocationID");
+        build.started_on = parse_timestamp(metadata, "startedOn")?;
+        build.finished_on = parse_timestamp(metadata, "finishedOn")?;
+    } else if provenance.get("buildType").is_some() {
+        // SLSA v0.2 provenance
+        if let Some(build_type) = provenance.get("buildType").and_then(Value::as_str) {
+            build.build_type = build_type.to_string();
+        }
+        if let Some(id) = provenance
+            .pointer("/builder/id")
+            .and_then(Value::as_str)
+            .filter(|id| !id.is_empty())
+        {
+            build.builder_id = id.to_string();
+        }
+        let invocation = provenance.get("invocation");
+        build.external_parameters = json!({
+            "configSource": invocation.and_then(|i| i.get("configSource")).cloned().unwrap_or(json!({})),
+            "request": invocation.and_then(|i| i.get("parameters")).cloned().unwrap_or(json!({})),
+        });
+        build.internal_parameters = invocation
+            .and_then(|i| i.get("environment"))
+            .map(|environment| json!({ "environment": environment }));
+        build.resolved_dependencies = parse_materials(provenance.get("materials"))?;
+
+        let metadata = provenance.get("metadata");
+        build.invocation_id = string_field(metadata, "buildInvocationID");
+        build.started_on = parse_timestamp(metadata, "buildStartedOn")?;
+        build.finished_on = parse_timestamp(metadata, "buildFinishedOn")?;
+    } else {
+        return Err(Error::Validation(
+            "Unsupported BuildKit provenance: expected SLSA v0.2 or v1 provenance for a single platform"
+                .to_string(),
+        ));
+    }
+
+    Ok(build)
+}
+
+/// Parses the image references written by `ko build --image-refs`.
+pub fn parse_ko_image_refs(image_refs: &str) -> Result<ImportedBuild> {
+    let mut refs = Vec::new();
+    let mut subjects = Vec::new();
+    for line in image_refs.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        let (name, digest) = line
+            .split_once('@')
+            .ok_or_else(|| Error::Validation(format!("Image reference {line} has no digest")))?;
+        let (alg, hex) = split_digest(digest)?;
+        subjects.push(in_toto::make_minimal_resource_descriptor(name, alg, hex));
+        refs.push(line.to_string());
+    }
+
+    if subjects.is_empty() {
+        return Err(Error::Validation(
+            "No image references found in ko output".to_string(),
+        ));
+    }
+
+    Ok(ImportedBuild {
+        builder_id: KO_BUILDER_ID.to_string(),
+        build_type: KO_BUILDER_ID.to_string(),
+        external_parameters: json!({ "imageRefs": refs }),
+        internal_parameters: None,
+        resolved_dependencies: Vec::new(),
+        subjects,
+        invocation_id: String::new(),
+        started_on: None,
+        finished_on: None,
+    })
+}
+
+/// Parses a Bazel execution log, as written by `--execution_log_json_file`.
+///
+/// The log is a stream of JSON spawn records. Outputs that no spawn consumes are the
+/// products of the build, and inputs that no spawn produces are its dependencies.
+pub fn parse_bazel_execution_log(log: &str) -> Result<ImportedBuild> {
+    let mut inputs = BTreeMap::new();
+    let mut outputs = BTreeMap::new();
+    let mut targets = BTreeSet::new();
+    let mut mnemonics = BTreeSet::new();
+
+    for spawn in serde_json::Deserializer::from_str(log).into_iter::<Value>() {
+        let spawn =
+            spawn.map_err(|e| Error::Validation(format!("Invalid Bazel execution log: {e}")))?;
+
+        for input in spawn
+            .get("inputs")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            if let Some((path, rd)) = bazel_file_descriptor(input)? {
+                inputs.insert(path, rd);
+            }
+        }
+        for output in spawn
+            .get("actualOutputs")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            if let Some((path, rd)) = bazel_file_descriptor(output)? {
+                outputs.insert(path, rd);
+            }
+        }
+        if let Some(target) = spawn.get("targetLabel").and_then(Value::as_str) {
+            targets.insert(target.to_string());
+        }
+        if let Some(mnemonic) = spawn.get("mnemonic").and_then(Value::as_str) {
+            mnemonics.insert(mnemonic.to_string());
+        }
+    }
+
+    let subjects: Vec<_> = outputs
+        .iter()
+        .filter(|(path, _)| !inputs.contains_key(*path))
+        .map(|(_, rd)| rd.clone())
+        .collect();
+    if subjects.is_empty() {
+        return Err(Error::Validation(
+            "No build outputs found in Bazel execution log".to_string(),
+        ));
+    }
+    let resolved_dependencies = inputs
+        .iter()
+        .filter(|(path, _)| !outputs.contains_key(*path))
+        .map(|(_, rd)| rd.clone())
+        .collect();
+
+    Ok(ImportedBuild {
+        builder_id: BAZEL_BUILDER_ID.to_string(),
+        build_type: BAZEL_BUILDER_ID.to_string(),
+        external_parameters: json!({ "targets": targets }),
+        internal_parameters: Some(json!({ "mnemonics": mnemonics })),
+        resolved_dependencies,
+        subjects,
+        invocation_id: String::new(),
+        started_on: None,
+        finished_on: None,
+    })
+}
+
+// A file of a Bazel spawn, keyed by its path; directories without a digest are skipped
+fn bazel_file_descriptor(file: &Value) -> Result<Option<(String, ResourceDescriptor)>> {
+    let Some(path) = file.get("path").and_then(Value::as_str) else {
+        return Ok(None);
+    };
+    let Some(hash) = file.pointer("/digest/hash").and_then(Value::as_str) else {
+        return Ok(None);
+    };
+    // Bazel names hash functions like "SHA-256"
+    let alg = file
+        .pointer("/digest/hashFunctionName")
+        .and_then(Value::as_str)
+        .unwrap_or("SHA-256")
+        .replace('-', "")
+        .to_lowercase();
+
+    Ok(Some((
+        path.to_string(),
+        in_toto::make_minimal_resource_descriptor(path, &alg, hash),
+    )))
+}
+
+// SLSA v0.2 materials and v1 resolved dependencies, both as {uri, digest}
+fn parse_materials(materials: Option<&Value>) -> Result<Vec<ResourceDescriptor>> {
+    let mut descriptors = Vec::new();
+    for material in materials.and_then(Value::as_array).into_iter().flatten() {
+        let mut rd = ResourceDescriptor::new();
+        rd.uri = string_field(Some(material), "uri");
+        if let Some(digest) = material.get("digest").and_then(Value::as_object) {
+            for (alg, value) in digest {
+                let value = value.as_str().ok_or_else(|| {
+                    Error::Validation(format!("Digest of material {} is not a string", rd.uri))
+                })?;
+                rd.digest.insert(alg.clone(), value.to_string());
+            }
+        }
+        descriptors.push(rd);
+    }
+    Ok(descriptors)
+}
+
+// Splits an OCI digest such as "sha256:abc..." into its algorithm and hex value
+fn split_digest(digest: &str) -> Result<(&str, &str)> {
+    digest
+        .split_once(':')
+        .filter(|(alg, hex)| !alg.is_empty() && !hex.is_empty())
+        .ok_or_else(|| Error::Validation(format!("Invalid digest: {digest}")))
+}
+
+fn string_field(value: Option<&Value>, field: &str) -> String {
+    value
+        .and_then(|v| v.get(field))
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn parse_timestamp(value: Option<&Value>, field: &str) -> Result<Option<Timestamp>> {
+    let Some(time) = value.and_then(|v| v.get(field)).and_then(Value::as_str) else {
+        return Ok(None);
+    };
+    let time = chrono::DateTime::parse_from_rfc3339(time)
+        .map_err(|e| Error::Validation(format!("Invalid {field} timestamp {time}: {e}")))?;
+
+    let mut timestamp = Timestamp::new();
+    timestamp.seconds = time.timestamp();
+    timestamp.nanos = time.timestamp_subsec_nanos() as i32;
+    Ok(Some(timestamp))
+}
+
+fn value_to_struct(value: &Value) -> Result<Struct> {
+    in_toto::json_to_struct_proto(&value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IMAGE_DIGEST: &str =
+        "sha256:1f2d3c4b5a6978685746352413020100f1e2d3c4b5a69788796a5b4c3d2e1f00";
+
+    #[test]
+    fn test_parse_buildkit_metadata_v02() {
+        let metadata = json!({
+            "buildx.build.provenance": {
+                "builder": { "id": "" },
+                "buildType": "https://mobyproject.org/buildkit@v1",
+                "materials": [{
+                    "uri": "pkg:docker/alpine@3.19",
+                    "digest": { "sha256": "abc123" }
+                }],
+                "invocation": {
+                    "configSource": { "entryPoint": "Dockerfile" },
+                    "parameters": { "frontend": "dockerfile.v0" },
+                    "environment": { "platform": "linux/amd64" }
+                },
+                "metadata": {
+                    "buildInvocationID": "build-42",
+                    "buildStartedOn": "2024-05-01T10:00:00.5Z",
+                    "buildFinishedOn": "2024-05-01T10:02:00Z"
+                }
+            },
+            "containerimage.digest": IMAGE_DIGEST,
+            "image.name": "registry.example.com/app:1.0,registry.example.com/app:latest"
+        });
+
+        let build = parse_buildkit_metadata(&metadata.to_string()).unwrap();
+
+        assert_eq!(build.builder_id, BUILDKIT_BUILDER_ID);
+        assert_eq!(build.build_type, BUILDKIT_BUILD_TYPE);
+        assert_eq!(build.invocation_id, "build-42");
+        assert_eq!(build.subjects.len(), 2);
+        assert_eq!(build.subjects[1].name, "registry.example.com/app:latest");
+        assert_eq!(build.subjects[0].digest["sha256"], &IMAGE_DIGEST[7..]);
+        assert_eq!(build.resolved_dependencies.len(), 1);
+        assert_eq!(build.resolved_dependencies[0].uri, "pkg:docker/alpine@3.19");
+        assert_eq!(
+            build.external_parameters["configSource"]["entryPoint"],
+            "Dockerfile"
+        );
+        assert_eq!(build.started_on.as_ref().unwrap().nanos, 500_000_000);
+        assert!(build.finished_on.is_some());
+        assert!(make_provenance(&build).is_ok());
+    }
+
+    #[test]
+    fn test_parse_buildkit_metadata_v1() {
+        let metadata = json!({
+            "buildx.build.provenance": {
+                "buildDefinition": {
+                    "buildType": "https://mobyproject.org/buildkit@v1",
+                    "externalParameters": { "request": { "frontend": "dockerfile.v0" } },
+                    "resolvedDependencies": [{
+                        "uri": "pkg:docker/alpine@3.19",
+                        "digest": { "sha256": "abc123" }
+                    }]
+                },
+                "runDetails": {
+                    "builder": { "id": "https://github.com/actions/runner" },
+                    "metadata": { "invocationID": "build-43" }
+                }
+            },
+            "containerimage.digest": IMAGE_DIGEST,
+            "image.name": "registry.example.com/app:1.0"
+        });
+
+        let build = parse_buildkit_metadata(&metadata.to_string()).unwrap();
+
+        assert_eq!(build.builder_id, "https://github.com/actions/runner");
+        assert_eq!(build.invocation_id, "build-43");
+        assert_eq!(build.resolved_dependencies.len(), 1);
+        assert_eq!(
+            build.external_parameters["request"]["frontend"],
+            "dockerfile.v0"
+        );
+    }
+
+    #[test]
+    fn test_parse_buildkit_metadata_without_image() {
+        let result = parse_buildkit_metadata(r#"{"buildx.build.ref": "builder/builder0/abc"}"#);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_parse_ko_image_refs() {
+        let refs = format!(
+            "ghcr.io/example/app@{IMAGE_DIGEST}\n\nghcr.io/example/worker:v1@{IMAGE_DIGEST}\n"
+        );
+
+        let build = parse_ko_image_refs(&refs).unwrap();
+
+        assert_eq!(build.builder_id, KO_BUILDER_ID);
+        assert_eq!(build.subjects.len(), 2);
+        assert_eq!(build.subjects[1].name, "ghcr.io/example/worker:v1");
+        assert_eq!(
+            build.external_parameters["imageRefs"][0],
+            format!("ghcr.io/example/app@{IMAGE_DIGEST}")
+        );
+        assert!(parse_ko_image_refs("ghcr.io/example/app:latest").is_err());
+        assert!(parse_ko_image_refs("").is_err());
+    }
+
+    #[test]
+    fn test_parse_bazel_execution_log() {
+        let log = r#"
+            {
+              "commandArgs": ["gcc", "-c", "main.c"],
+              "inputs": [{"path": "main.c", "digest": {"hash": "aa11", "sizeBytes": "10", "hashFunctionName": "SHA-256"}}],
+              "actualOutputs": [{"path": "bazel-out/main.o", "digest": {"hash": "bb22", "sizeBytes": "20", "hashFunctionName": "SHA-256"}}],
+              "mnemonic": "CppCompile",
+              "targetLabel": "//:app"
+            }
+            {
+              "commandArgs": ["gcc", "-o", "app", "main.o"],
+              "inputs": [{"path": "bazel-out/main.o", "digest": {"hash": "bb22", "sizeBytes": "20", "hashFunctionName": "SHA-256"}}],
+              "actualOutputs": [{"path": "bazel-out/app", "digest": {"hash": "cc33", "sizeBytes": "30", "hashFunctionName": "SHA-256"}}],
+              "mnemonic": "CppLink",
+              "targetLabel": "//:app"
+            }
+        "#;
+
+        let build = parse_bazel_execution_log(log).unwrap();
+
+        assert_eq!(build.builder_id, BAZEL_BUILDER_ID);
+        assert_eq!(build.subjects.len(), 1);
+        assert_eq!(build.subjects[0].name, "bazel-out/app");
+        assert_eq!(build.subjects[0].digest["sha256"], "cc33");
+        assert_eq!(build.resolved_dependencies.len(), 1);
+        assert_eq!(build.resolved_dependencies[0].name, "main.c");
+        assert_eq!(build.external_parameters["targets"], json!(["//:app"]));
+        assert_eq!(
+            build.internal_parameters.unwrap()["mnemonics"],
+            json!(["CppCompile", "CppLink"])
+        );
+    }
+
+    #[test]
+    fn test_parse_bazel_execution_log_without_outputs() {
+        assert!(parse_bazel_execution_log("").is_err());
+        assert!(parse_bazel_execution_log("{\"inputs\": [").is_err());
+    }
+}
@@ -23,6 +23,8 @@
 //!
 //! - [`cli`] - An Atlas CLI specific generator for SLSA Build Provenance
 //! - [`generators`] - Core functions for creating SLSA provenance structures
+//! - [`import`] - SLSA Build Provenance from the metadata of other build systems
+//! - [`run`] - SLSA Build Provenance for a command run by Atlas CLI
 //! - [`BUILD_PROVENANCE_PREDICATE_TYPE_V1`] - Standard SLSA v1 predicate type URI
 //!
 //! ## Examples
@@ -42,6 +44,7 @@
 //!     Some(PathBuf::from("signing_key.pem")),       // signing key
 //!     HashAlgorithm::Sha384,                        // hash algorithm
 //!     "json".to_string(),                           // output format
+//!     None,                                         // output file
 //!     true,                                         // print to stdout
 //!     None,                                         // storage backend
 //!     false,                                        // TDX support
@@ -49,6 +52,8 @@
 //! ```
 pub mod cli;
 pub mod generators;
+pub mod import;
+pub mod run;
 
 /// The standard SLSA v1 build provenance in-toto predicate type URI.
 ///
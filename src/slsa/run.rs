@@ -0,0 +1,237 @@
+//! # SLSA Provenance from Captured Invocations
+//!
+//! This module runs a build or training command and records SLSA v1 Build Provenance
+//! for it, so that any command — a Makefile target, a training script — can be wrapped
+//! without describing the build separately.
+//!
+//! The provenance records:
+//! - **External Parameters**: The command line and the working directory
+//! - **Internal Parameters**: The values of the environment variables selected for recording
+//! - **Resolved Dependencies**: The inputs, hashed before the command runs
+//! - **Subjects**: The products, hashed after the command exits successfully
+//! - **Build Metadata**: The wall-clock start and finish of the command
+//!
+//! Atlas CLI is the builder, as for [`generate_build_provenance`](crate::slsa::cli::generate_build_provenance).
+//!
+//! ## Examples
+//!
+//! ```no_run
+//! use atlas_cli::slsa::run::{RunConfig, run_build};
+//! use atlas_c2pa_lib::cose::HashAlgorithm;
+//! use std::path::PathBuf;
+//!
+//! run_build(RunConfig {
+//!     command: vec!["make".to_string(), "model".to_string()],
+//!     inputs: vec![PathBuf::from("train.py")],
+//!     products: vec![PathBuf::from("model.onnx")],
+//!     env: vec!["CUDA_VISIBLE_DEVICES".to_string()],
+//!     key_path: Some(PathBuf::from("signing_key.pem")),
+//!     hash_alg: HashAlgorithm::Sha384,
+//!     output_encoding: "json".to_string(),
+//!     output_path: None,
+//!     print: true,
+//!     storage: None,
+//! })
+//! .unwrap();
+//! ```
+
+use crate::cli::{CLI_NAME, CLI_VERSION};
+use crate::error::{Error, Result};
+use crate::in_toto;
+use crate::slsa;
+use crate::slsa::import::ImportedBuild;
+use crate::storage::traits::StorageBackend;
+
+use atlas_c2pa_lib::cose::HashAlgorithm;
+use in_toto_attestation::v1::resource_descriptor::ResourceDescriptor;
+use protobuf::well_known_types::timestamp::Timestamp;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::process::Command;
+
+pub struct RunConfig {
+    // Program and arguments to run
+    pub command: Vec<String>,
+    // Files the command reads, hashed before it runs
+    pub inputs: Vec<PathBuf>,
+    // Files the command writes, hashed after it exits
+    pub products: Vec<PathBuf>,
+    // Environment variables whose values are recorded, if set
+    pub env: Vec<String>,
+    pub key_path: Option<PathBuf>,
+    pub hash_alg: HashAlgorithm,
+    pub output_encoding: String,
+    // Write output to a file instead of stdout
+    pub output_path: Option<PathBuf>,
+    pub print: bool,
+    pub storage: Option<&'static dyn StorageBackend>,
+}
+
+/// Runs a command and emits signed SLSA v1 build provenance for it.
+///
+/// The command inherits the standard streams and environment of Atlas CLI. Provenance is
+/// only emitted if it exits successfully; it is then written out and stored like the
+/// provenance of [`generate_build_provenance`](crate::slsa::cli::generate_build_provenance).
+///
+/// # Errors
+///
+/// Returns an error if no command or signing key is given, an input or product cannot be
+/// hashed, the command cannot be started or exits unsuccessfully, or if signing, output
+/// or storage fails.
+pub fn run_build(config: RunConfig) -> Result<()> {
+    let key_path = config.key_path.clone().ok_or_else(|| {
+        Error::Validation("Signing key is required for SLSA provenance".to_string())
+    })?;
+
+    let build = capture_build(&config)?;
+
+    let provenance = slsa::import::make_provenance(&build)?;
+    let envelope = in_toto::generate_signed_statement_v1(
+        &build.subjects,
+        slsa::BUILD_PROVENANCE_PREDICATE_TYPE_V1,
+        &provenance,
+        key_path,
+        config.hash_alg,
+    )?;
+
+    slsa::cli::emit_provenance(
+        envelope,
+        &config.command[0],
+        &config.output_encoding,
+        config.output_path.as_deref(),
+        config.print,
+        config.storage,
+    )
+}
+
+/// Runs the command of `config` and records it as a build, without signing it.
+pub fn capture_build(config: &RunConfig) -> Result<ImportedBuild> {
+    let Some((program, args)) = config.command.split_first() else {
+        return Err(Error::Validation("No command to run".to_string()));
+    };
+
+    let resolved_dependencies = hash_files(&config.inputs, &config.hash_alg)?;
+    let environment: BTreeMap<&str, String> = config
+        .env
+        .iter()
+        .filter_map(|name| std::env::var(name).ok().map(|value| (name.as_str(), value)))
+        .collect();
+    let working_directory = std::env::current_dir()?;
+
+    let started_on = Timestamp::now();
+    let status = Command::new(program)
+        .args(args)
+        .status()
+        .map_err(|e| Error::Validation(format!("Could not run {program}: {e}")))?;
+    let finished_on = Timestamp::now();
+
+    if !status.success() {
+        return Err(Error::Validation(format!(
+            "{program} failed ({status}); no provenance was generated"
+        )));
+    }
+
+    let subjects = hash_files(&config.products, &config.hash_alg)?;
+
+    Ok(ImportedBuild {
+        builder_id: slsa::cli::ATLAS_CLI_BUILDER_ID.to_string(),
+        build_type: format!("{}:{}", CLI_NAME, CLI_VERSION),
+        external_parameters: json!({
+            "command": config.command,
+            "workingDirectory": working_directory.display().to_string(),
+        }),
+        internal_parameters: Some(json!({ "environment": environment })),
+        resolved_dependencies,
+        subjects,
+        invocation_id: uuid::Uuid::new_v4().urn().to_string(),
+        started_on: Some(started_on),
+        finished_on: Some(finished_on),
+    })
+}
+
+fn hash_files(paths: &[PathBuf], hash_alg: &HashAlgorithm) -> Result<Vec<ResourceDescriptor>> {
+    paths
+        .iter()
+        .map(|path| in_toto::generate_file_resource_descriptor_from_path(path, hash_alg))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn config(command: &[&str], inputs: Vec<PathBuf>, products: Vec<PathBuf>) -> RunConfig {
+        RunConfig {
+            command: command.iter().map(|arg| arg.to_string()).collect(),
+            inputs,
+            products,
+            env: vec![
+                "ATLAS_TEST_RUN_VAR".to_string(),
+                "ATLAS_TEST_UNSET_VAR".to_string(),
+            ],
+            key_path: None,
+            hash_alg: HashAlgorithm::Sha256,
+            output_encoding: "json".to_string(),
+            output_path: None,
+            print: true,
+            storage: None,
+        }
+    }
+
+    #[test]
+    fn test_capture_build() {
+        let temp_dir = TempDir::new().unwrap();
+        let input = temp_dir.path().join("input.txt");
+        let product = temp_dir.path().join("output.txt");
+        fs::write(&input, "input").unwrap();
+        // SAFETY: the variable is only read by this test
+        unsafe { std::env::set_var("ATLAS_TEST_RUN_VAR", "recorded") };
+
+        let script = format!("cp {} {}", input.display(), product.display());
+        let build = capture_build(&config(
+            &["sh", "-c", &script],
+            vec![input],
+            vec![product.clone()],
+        ))
+        .unwrap();
+
+        assert_eq!(build.builder_id, slsa::cli::ATLAS_CLI_BUILDER_ID);
+        assert_eq!(build.external_parameters["command"][0], "sh");
+        assert_eq!(build.external_parameters["command"][2], script);
+        let environment = &build.internal_parameters.unwrap()["environment"];
+        assert_eq!(environment["ATLAS_TEST_RUN_VAR"], "recorded");
+        assert!(environment.get("ATLAS_TEST_UNSET_VAR").is_none());
+        assert_eq!(build.resolved_dependencies.len(), 1);
+        assert_eq!(build.subjects.len(), 1);
+        // The product is a copy of the input, so both have the same digest
+        assert_eq!(
+            build.subjects[0].digest,
+            build.resolved_dependencies[0].digest
+        );
+        assert!(build.invocation_id.starts_with("urn:uuid:"));
+        assert!(build.started_on.unwrap().seconds <= build.finished_on.unwrap().seconds);
+    }
+
+    #[test]
+    fn test_capture_build_failing_command() {
+        let result = capture_build(&config(&["sh", "-c", "exit 3"], vec![], vec![]));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_capture_build_missing_product() {
+        let temp_dir = TempDir::new().unwrap();
+        let product = temp_dir.path().join("never-written.bin");
+        let result = capture_build(&config(&["true"], vec![], vec![product]));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_run_build_requires_key() {
+        let result = run_build(config(&["true"], vec![], vec![]));
+        assert!(result.is_err());
+    }
+}
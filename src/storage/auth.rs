@@ -0,0 +1,329 @@
+//! Authentication to database storage servers.
+//!
+//! Multi-team provenance servers authenticate clients with bearer tokens and authorize
+//! them by role. Requests carry either a static API token from `ATLAS_API_TOKEN`, or an
+//! OIDC access token obtained with the client credentials grant from
+//! `ATLAS_OIDC_TOKEN_URL`, `ATLAS_OIDC_CLIENT_ID` and `ATLAS_OIDC_CLIENT_SECRET`. OIDC
+//! tokens are requested on first use and renewed shortly before they expire.
+//!
+//! The server decides what a token may do; requests answered with 401 or 403 fail with
+//! an [`Error::Unauthorized`] naming the [`Role`] the operation needs.
+
+use crate::error::{Error, Result};
+use reqwest::blocking::{Client, RequestBuilder};
+use reqwest::header::CONTENT_TYPE;
+use reqwest::{Method, StatusCode};
+use serde::Deserialize;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Environment variable holding a static API token
+pub const API_TOKEN_ENV: &str = "ATLAS_API_TOKEN";
+/// Environment variable holding the token endpoint of the OIDC provider
+pub const OIDC_TOKEN_URL_ENV: &str = "ATLAS_OIDC_TOKEN_URL";
+pub const OIDC_CLIENT_ID_ENV: &str = "ATLAS_OIDC_CLIENT_ID";
+pub const OIDC_CLIENT_SECRET_ENV: &str = "ATLAS_OIDC_CLIENT_SECRET";
+/// Environment variable holding the space-separated scopes to request, if any
+pub const OIDC_SCOPE_ENV: &str = "ATLAS_OIDC_SCOPE";
+/// Environment variable holding the audience to request, if any
+pub const OIDC_AUDIENCE_ENV: &str = "ATLAS_OIDC_AUDIENCE";
+
+// Tokens are renewed this long before they expire
+const EXPIRY_MARGIN: Duration = Duration::from_secs(30);
+
+/// Role a server grants to a token, from least to most privileged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Role {
+    /// Lists and retrieves manifests and envelopes
+    Reader,
+    /// Also stores manifests and envelopes
+    Publisher,
+    /// Also deletes and rewrites stored manifests
+    Admin,
+}
+
+impl Role {
+    /// Role needed for a request with this method
+    pub fn required_for(method: &Method) -> Role {
+        match *method {
+            Method::GET | Method::HEAD | Method::OPTIONS => Role::Reader,
+            Method::DELETE => Role::Admin,
+            _ => Role::Publisher,
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Role::Reader => write!(f, "reader"),
+            Role::Publisher => write!(f, "publisher"),
+            Role::Admin => write!(f, "admin"),
+        }
+    }
+}
+
+/// Client credentials for the OAuth 2.0 client credentials grant
+#[derive(Debug)]
+pub struct OidcClient {
+    pub token_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    pub scope: Option<String>,
+    pub audience: Option<String>,
+    // Current access token and when it must be renewed
+    token: Mutex<Option<(String, Instant)>>,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: Option<u64>,
+}
+
+impl OidcClient {
+    pub fn new(token_url: String, client_id: String, client_secret: String) -> Self {
+        Self {
+            token_url,
+            client_id,
+            client_secret,
+            scope: None,
+            audience: None,
+            token: Mutex::new(None),
+        }
+    }
+
+    /// Returns the current access token, requesting a new one if there is none or it is
+    /// about to expire
+    pub fn access_token(&self, client: &Client) -> Result<String> {
+        let mut token = self.token.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((access_token, renew_at)) = token.as_ref()
+            && Instant::now() < *renew_at
+        {
+            return Ok(access_token.clone());
+        }
+
+        let mut form = vec![
+            ("grant_type", "client_credentials"),
+            ("client_id", self.client_id.as_str()),
+            ("client_secret", self.client_secret.as_str()),
+        ];
+        if let Some(scope) = &self.scope {
+            form.push(("scope", scope.as_str()));
+        }
+        if let Some(audience) = &self.audience {
+            form.push(("audience", audience.as_str()));
+        }
+
+        let body = form
+            .iter()
+            .map(|(key, value)| format!("{key}={}", form_urlencode(value)))
+            .collect::<Vec<_>>()
+            .join("&");
+        let response = client
+            .post(&self.token_url)
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(body)
+            .send()
+            .map_err(|e| Error::storage_request("Failed to request an OIDC access token", e))?;
+        if !response.status().is_success() {
+            return Err(Error::Unauthorized(format!(
+                "The OIDC provider refused the client credentials. Status: {}",
+                response.status()
+            )));
+        }
+        let response: TokenResponse = response
+            .json()
+            .map_err(|e| Error::Storage(format!("Invalid OIDC token response: {e}")))?;
+
+        // Tokens without an expiry are renewed every few minutes
+        let lifetime = Duration::from_secs(response.expires_in.unwrap_or(300));
+        let renew_at = Instant::now() + lifetime.saturating_sub(EXPIRY_MARGIN);
+        *token = Some((response.access_token.clone(), renew_at));
+        Ok(response.access_token)
+    }
+}
+
+// Encodes a form value, keeping only unreserved characters as they are
+fn form_urlencode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+/// How requests to a storage server are authenticated
+#[derive(Debug, Default)]
+pub enum Credentials {
+    #[default]
+    Anonymous,
+    /// Static API token
+    Token(String),
+    /// OIDC client credentials
+    Oidc(OidcClient),
+}
+
+impl Credentials {
+    /// Reads the credentials from the environment: a static token takes precedence over
+    /// OIDC client credentials, and without either requests are anonymous.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if OIDC client credentials are incomplete.
+    pub fn from_env() -> Result<Self> {
+        let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+
+        if let Some(token) = var(API_TOKEN_ENV) {
+            return Ok(Credentials::Token(token));
+        }
+        let Some(token_url) = var(OIDC_TOKEN_URL_ENV) else {
+            return Ok(Credentials::Anonymous);
+        };
+        let (Some(client_id), Some(client_secret)) =
+            (var(OIDC_CLIENT_ID_ENV), var(OIDC_CLIENT_SECRET_ENV))
+        else {
+            return Err(Error::Validation(format!(
+                "{OIDC_TOKEN_URL_ENV} requires {OIDC_CLIENT_ID_ENV} and {OIDC_CLIENT_SECRET_ENV}"
+            )));
+        };
+
+        let mut oidc = OidcClient::new(token_url, client_id, client_secret);
+        oidc.scope = var(OIDC_SCOPE_ENV);
+        oidc.audience = var(OIDC_AUDIENCE_ENV);
+        Ok(Credentials::Oidc(oidc))
+    }
+
+    /// Adds the bearer token, if any, to a request
+    pub fn authorize(&self, client: &Client, request: RequestBuilder) -> Result<RequestBuilder> {
+        Ok(match self {
+            Credentials::Anonymous => request,
+            Credentials::Token(token) => request.bearer_auth(token),
+            Credentials::Oidc(oidc) => request.bearer_auth(oidc.access_token(client)?),
+        })
+    }
+
+    pub fn is_anonymous(&self) -> bool {
+        matches!(self, Credentials::Anonymous)
+    }
+}
+
+/// Error for a request refused with 401 or 403, naming the role the request needs
+pub fn refused(status: StatusCode, method: &Method, credentials: &Credentials) -> Option<Error> {
+    let role = Role::required_for(method);
+    match status {
+        StatusCode::UNAUTHORIZED if credentials.is_anonymous() => {
+            Some(Error::Unauthorized(format!(
+                "The server requires authentication; set {API_TOKEN_ENV} or OIDC client credentials ({OIDC_TOKEN_URL_ENV}, {OIDC_CLIENT_ID_ENV}, {OIDC_CLIENT_SECRET_ENV})"
+            )))
+        }
+        StatusCode::UNAUTHORIZED => Some(Error::Unauthorized(
+            "The server rejected the credentials; the token may be invalid or expired".to_string(),
+        )),
+        StatusCode::FORBIDDEN => Some(Error::Unauthorized(format!(
+            "The credentials are not authorized for this operation, which needs the {role} role"
+        ))),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_required_roles() {
+        assert_eq!(Role::required_for(&Method::GET), Role::Reader);
+        assert_eq!(Role::required_for(&Method::POST), Role::Publisher);
+        assert_eq!(Role::required_for(&Method::DELETE), Role::Admin);
+        assert!(Role::Reader < Role::Publisher && Role::Publisher < Role::Admin);
+    }
+
+    #[test]
+    fn test_refused() {
+        let token = Credentials::Token("t".to_string());
+        let error = refused(StatusCode::FORBIDDEN, &Method::POST, &token).unwrap();
+        assert_eq!(error.code(), "ATLAS-STOR-004");
+        assert!(error.to_string().contains("publisher role"));
+
+        let error = refused(
+            StatusCode::UNAUTHORIZED,
+            &Method::GET,
+            &Credentials::Anonymous,
+        );
+        assert!(error.unwrap().to_string().contains(API_TOKEN_ENV));
+        assert!(refused(StatusCode::NOT_FOUND, &Method::GET, &token).is_none());
+    }
+
+    // Serves OIDC token requests, counting them
+    fn serve_tokens(expires_in: u64) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/token", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line == "\r\n" || line.is_empty() {
+                        break;
+                    }
+                    if let Some(value) = line.to_lowercase().strip_prefix("content-length:") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+                let body = String::from_utf8(body).unwrap();
+                assert!(body.contains("grant_type=client_credentials"));
+                assert!(body.contains("scope=atlas.write%20atlas.read"));
+
+                let n = counter.fetch_add(1, Ordering::SeqCst);
+                let response =
+                    format!(r#"{{"access_token": "token-{n}", "expires_in": {expires_in}}}"#);
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{response}",
+                    response.len()
+                )
+                .unwrap();
+            }
+        });
+
+        (url, requests)
+    }
+
+    #[test]
+    fn test_oidc_token_caching() {
+        let client = Client::new();
+
+        let (url, requests) = serve_tokens(3600);
+        let mut oidc = OidcClient::new(url, "ci".to_string(), "secret".to_string());
+        oidc.scope = Some("atlas.write atlas.read".to_string());
+        assert_eq!(oidc.access_token(&client).unwrap(), "token-0");
+        assert_eq!(oidc.access_token(&client).unwrap(), "token-0");
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+        // Tokens expiring within the margin are renewed on every use
+        let (url, requests) = serve_tokens(10);
+        let mut oidc = OidcClient::new(url, "ci".to_string(), "secret".to_string());
+        oidc.scope = Some("atlas.write atlas.read".to_string());
+        oidc.access_token(&client).unwrap();
+        assert_eq!(oidc.access_token(&client).unwrap(), "token-1");
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,215 @@
+//! Chunked, resumable transfers of large manifests to and from the database backend.
+//!
+//! Manifests of large datasets can run to gigabytes, and a single request carrying one
+//! fails as soon as the link drops. Bodies larger than the chunk size are instead uploaded
+//! to `/uploads/<upload ID>` one chunk at a time, each with its SHA-256 so the server can
+//! reject chunks corrupted in transit. The upload ID is derived from the manifest ID: when
+//! an upload is interrupted, the next attempt asks the server which chunks it already
+//! holds and only sends those that are missing or differ. A final request has the server
+//! assemble the chunks, check the digest of the whole body and store the manifest.
+//!
+//! Downloads use `Range` requests of the same size, checking each range against the
+//! digests the server sends with it. Every chunk and range is retried on its own, so a
+//! dropped connection only costs the chunk in flight.
+
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::ops::Range;
+use std::time::Duration;
+
+/// Size of uploaded chunks and downloaded ranges
+pub const DEFAULT_CHUNK_SIZE: usize = 8 * 1024 * 1024;
+
+/// Header carrying the hex-encoded SHA-256 of an uploaded chunk or downloaded range
+pub const CHUNK_SHA256_HEADER: &str = "x-atlas-chunk-sha256";
+
+/// Header carrying the hex-encoded SHA-256 of a whole downloaded entry
+pub const CONTENT_SHA256_HEADER: &str = "x-atlas-content-sha256";
+
+/// Number of times a chunk or range is tried before the transfer fails
+pub const CHUNK_ATTEMPTS: u32 = 3;
+
+// Delay before the first retry, growing with each further one
+const RETRY_DELAY: Duration = Duration::from_millis(250);
+
+/// A chunk of an upload
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    pub index: usize,
+    /// Byte range of the chunk in the uploaded body
+    pub range: Range<usize>,
+    /// Hex-encoded SHA-256 of the chunk
+    pub sha256: String,
+}
+
+/// A chunk the server holds, as reported by the upload status
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReceivedChunk {
+    pub index: usize,
+    pub sha256: String,
+}
+
+/// Upload status returned by `GET /uploads/<upload ID>`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UploadStatus {
+    #[serde(default)]
+    pub chunks: Vec<ReceivedChunk>,
+}
+
+impl UploadStatus {
+    /// Whether the server holds `chunk` with the same content
+    pub fn has(&self, chunk: &Chunk) -> bool {
+        self.chunks
+            .iter()
+            .any(|received| received.index == chunk.index && received.sha256 == chunk.sha256)
+    }
+}
+
+/// Request completing an upload, sent to `POST /uploads/<upload ID>/complete`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompleteUpload {
+    pub manifest_id: String,
+    /// Content encoding of the assembled body, if it is compressed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_encoding: Option<String>,
+    pub size: usize,
+    /// Hex-encoded SHA-256 of the assembled body
+    pub sha256: String,
+    /// Number of chunks making up the body
+    pub chunks: usize,
+}
+
+/// Hex-encoded SHA-256 of `data`
+pub fn sha256(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+/// ID under which the chunks of a manifest are uploaded
+///
+/// # Examples
+///
+/// ```
+/// use atlas_cli::storage::chunked::upload_id;
+///
+/// assert_eq!(upload_id("urn:c2pa:123"), upload_id("urn:c2pa:123"));
+/// assert_ne!(upload_id("urn:c2pa:123"), upload_id("urn:c2pa:456"));
+/// ```
+pub fn upload_id(manifest_id: &str) -> String {
+    sha256(manifest_id.as_bytes())
+}
+
+/// Splits `body` into chunks of at most `chunk_size` bytes
+pub fn chunks(body: &[u8], chunk_size: usize) -> Vec<Chunk> {
+    body.chunks(chunk_size.max(1))
+        .enumerate()
+        .map(|(index, data)| {
+            let start = index * chunk_size.max(1);
+            Chunk {
+                index,
+                range: start..start + data.len(),
+                sha256: sha256(data),
+            }
+        })
+        .collect()
+}
+
+/// Parses a `Content-Range: bytes <first>-<last>/<total>` header value
+///
+/// # Examples
+///
+/// ```
+/// use atlas_cli::storage::chunked::parse_content_range;
+///
+/// assert_eq!(parse_content_range("bytes 0-99/1000"), Some((0, 99, 1000)));
+/// assert_eq!(parse_content_range("bytes */1000"), None);
+/// ```
+pub fn parse_content_range(value: &str) -> Option<(u64, u64, u64)> {
+    let (range, total) = value.trim().strip_prefix("bytes ")?.split_once('/')?;
+    let (first, last) = range.split_once('-')?;
+    let (first, last, total) = (first.parse().ok()?, last.parse().ok()?, total.parse().ok()?);
+    (first <= last && last < total).then_some((first, last, total))
+}
+
+/// Checks `data` against the hex-encoded SHA-256 sent with it.
+///
+/// A mismatch is reported as a storage outage, so that the transfer is retried.
+pub fn check_digest(data: &[u8], expected: Option<&str>, what: &str) -> Result<()> {
+    match expected {
+        Some(expected) if !sha256(data).eq_ignore_ascii_case(expected) => Err(
+            Error::StorageUnavailable(format!("{what} was corrupted in transit")),
+        ),
+        _ => Ok(()),
+    }
+}
+
+/// Runs `attempt` until it succeeds, fails with an error that isn't retryable, or has been
+/// tried [`CHUNK_ATTEMPTS`] times
+pub fn with_retries<T>(mut attempt: impl FnMut() -> Result<T>) -> Result<T> {
+    let mut tries = 1;
+    loop {
+        match attempt() {
+            Err(e) if e.is_retryable() && tries < CHUNK_ATTEMPTS => {
+                std::thread::sleep(RETRY_DELAY * tries);
+                tries += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn test_chunks() {
+        let body = vec![7u8; 25];
+        let chunks = chunks(&body, 10);
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[2].range, 20..25);
+        assert_eq!(chunks[0].sha256, chunks[1].sha256);
+        assert_eq!(chunks[2].sha256, sha256(&[7u8; 5]));
+
+        let status = UploadStatus {
+            chunks: vec![ReceivedChunk {
+                index: 1,
+                sha256: chunks[0].sha256.clone(),
+            }],
+        };
+        assert!(status.has(&chunks[1]));
+        assert!(!status.has(&chunks[0]));
+        assert!(!status.has(&chunks[2]));
+    }
+
+    #[test]
+    fn test_with_retries() {
+        let tries = Cell::new(0);
+        let result = with_retries(|| {
+            tries.set(tries.get() + 1);
+            if tries.get() < CHUNK_ATTEMPTS {
+                Err(Error::StorageUnavailable("flaky".to_string()))
+            } else {
+                Ok(tries.get())
+            }
+        });
+        assert_eq!(result.unwrap(), CHUNK_ATTEMPTS);
+
+        // Errors that aren't retryable fail at once
+        tries.set(0);
+        let result: Result<()> = with_retries(|| {
+            tries.set(tries.get() + 1);
+            Err(Error::Storage("bad request".to_string()))
+        });
+        assert!(result.is_err());
+        assert_eq!(tries.get(), 1);
+
+        assert!(check_digest(b"chunk", Some(&sha256(b"chunk")), "chunk 0").is_ok());
+        assert!(
+            check_digest(b"chunk", Some(&sha256(b"other")), "chunk 0")
+                .unwrap_err()
+                .is_retryable()
+        );
+    }
+}
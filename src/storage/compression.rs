@@ -0,0 +1,82 @@
+//! Transparent zstd compression of stored manifests.
+//!
+//! Compressed data is recognized by the zstd frame magic number, so entries written
+//! before compression was introduced are still read as plain JSON. The filesystem
+//! backend marks compressed files with the [`COMPRESSED_EXTENSION`] extension, the
+//! database backend negotiates with the `Content-Encoding` header.
+
+use crate::error::{Error, Result};
+
+/// Magic number at the start of every zstd frame
+pub const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+
+/// Content encoding name used in HTTP headers
+pub const ZSTD_ENCODING: &str = "zstd";
+
+/// File extension of compressed manifests (appended to `.json`)
+pub const COMPRESSED_EXTENSION: &str = "zst";
+
+pub(crate) const COMPRESSION_LEVEL: i32 = 3;
+
+/// Returns true if `data` starts with a zstd frame
+///
+/// # Examples
+///
+/// ```
+/// use atlas_cli::storage::compression::{compress, is_compressed};
+///
+/// assert!(!is_compressed(b"{\"title\": \"manifest\"}"));
+/// assert!(is_compressed(&compress(b"{\"title\": \"manifest\"}").unwrap()));
+/// ```
+pub fn is_compressed(data: &[u8]) -> bool {
+    data.starts_with(&ZSTD_MAGIC)
+}
+
+/// Compresses `data` with zstd
+pub fn compress(data: &[u8]) -> Result<Vec<u8>> {
+    zstd::encode_all(data, COMPRESSION_LEVEL)
+        .map_err(|e| Error::Storage(format!("Failed to compress manifest: {e}")))
+}
+
+/// Decompresses `data` if it is zstd-compressed, otherwise returns it unchanged
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>> {
+    if !is_compressed(data) {
+        return Ok(data.to_vec());
+    }
+
+    zstd::decode_all(data)
+        .map_err(|e| Error::Storage(format!("Failed to decompress manifest: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() -> Result<()> {
+        let data = serde_json::to_vec(&serde_json::json!({
+            "title": "dataset",
+            "ingredients": vec!["file.csv"; 1000],
+        }))?;
+
+        let compressed = compress(&data)?;
+        assert!(is_compressed(&compressed));
+        assert!(compressed.len() < data.len());
+        assert_eq!(decompress(&compressed)?, data);
+        Ok(())
+    }
+
+    #[test]
+    fn test_uncompressed_passthrough() -> Result<()> {
+        let data = br#"{"title": "legacy"}"#;
+        assert_eq!(decompress(data)?, data);
+        Ok(())
+    }
+
+    #[test]
+    fn test_corrupt_frame() {
+        let mut data = ZSTD_MAGIC.to_vec();
+        data.extend_from_slice(b"garbage");
+        assert!(matches!(decompress(&data), Err(Error::Storage(_))));
+    }
+}
@@ -1,3 +1,5 @@
+use crate::storage::rate_limit::{DEFAULT_REKOR_REQUESTS_PER_SECOND, RateLimit};
+
 #[derive(Debug, Clone)]
 pub enum StorageType {
     Rekor,
@@ -9,6 +11,8 @@ pub struct StorageConfig {
     pub rekor_url: String,
     pub enable_verification: bool,
     pub filesystem_path: Option<String>,
+    /// How fast requests may be sent to Rekor
+    pub rekor_rate_limit: RateLimit,
 }
 
 impl Default for StorageConfig {
@@ -17,6 +21,7 @@ impl Default for StorageConfig {
             rekor_url: "https://rekor.sigstore.dev".to_string(),
             enable_verification: true,
             filesystem_path: None,
+            rekor_rate_limit: RateLimit::per_second(DEFAULT_REKOR_REQUESTS_PER_SECOND),
         }
     }
 }
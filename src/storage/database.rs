@@ -1,16 +1,40 @@
 use crate::error::{Error, Result};
+use crate::manifest::content_id;
+use crate::manifest::timestamp::to_rfc3339_utc;
 use crate::manifest::utils::{determine_manifest_type, manifest_type_to_string};
-use crate::storage::traits::{ManifestMetadata, ManifestType, StorageBackend};
+use crate::metrics::{self, StorageOutcome};
+use crate::storage::auth::{self, Credentials};
+use crate::storage::chunked::{
+    self, CHUNK_SHA256_HEADER, CONTENT_SHA256_HEADER, CompleteUpload, DEFAULT_CHUNK_SIZE,
+    UploadStatus,
+};
+use crate::storage::compression::{self, ZSTD_ENCODING};
+use crate::storage::pinning::{self, SendPinned};
+use crate::storage::traits::{
+    EnvelopeMetadata, EnvelopeRecord, ManifestMetadata, ManifestType, StorageBackend,
+};
 use atlas_c2pa_lib::manifest::Manifest;
-use reqwest::blocking::Client;
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use reqwest::header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_RANGE, CONTENT_TYPE, RANGE};
+use reqwest::{Method, StatusCode};
+use serde::de::{DeserializeOwned, IgnoredAny};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::time::Duration;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Header carrying the idempotency key of a request storing a manifest, see
+/// [`idempotency_key`]
+pub const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
 
 #[derive(Debug, Clone)]
 pub struct DatabaseStorage {
     base_url: String,
     client: Client,
+    compression: bool,
+    chunk_size: usize,
+    credentials: Arc<Credentials>,
 }
 
 impl DatabaseStorage {
@@ -43,6 +67,40 @@ struct StoredManifest {
     created_at: String,
 }
 
+// An entry of the manifest list, with only the fields listing reads. The rest of each
+// stored manifest is skipped while parsing rather than built up in memory.
+#[derive(Debug, Deserialize)]
+struct ListedManifest {
+    manifest_id: String,
+    manifest_type: String,
+    created_at: String,
+    #[serde(default)]
+    manifest: Option<ListedLayer<ListedLayer<ListedFields>>>,
+}
+
+// One level of the wrapping the server stores manifests in
+#[derive(Debug, Deserialize)]
+struct ListedLayer<T> {
+    #[serde(default)]
+    manifest: Option<T>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListedFields {
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    claim: Option<ListedClaim>,
+    #[serde(default)]
+    claim_v2: Option<ListedClaim>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListedClaim {
+    #[serde(default)]
+    signature: Option<IgnoredAny>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct ManifestWrapper {
     manifest_id: String,
@@ -54,7 +112,7 @@ struct ManifestWrapper {
 
 impl DatabaseStorage {
     pub fn new(url: String) -> Result<Self> {
-        let client = Client::builder()
+        let client = pinning::client_builder(&url)?
             .timeout(Duration::from_secs(30))
             .build()
             .map_err(|e| Error::Storage(format!("Failed to create HTTP client: {e}")))?;
@@ -62,15 +120,471 @@ impl DatabaseStorage {
         Ok(Self {
             base_url: url.trim_end_matches('/').to_string(),
             client,
+            compression: true,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            credentials: Arc::new(Credentials::from_env()?),
         })
     }
 
+    /// Authenticates requests with `credentials` instead of those from the environment
+    pub fn with_credentials(mut self, credentials: Credentials) -> Self {
+        self.credentials = Arc::new(credentials);
+        self
+    }
+
+    /// Enables or disables zstd compression of uploaded manifests (enabled by default).
+    /// Compressed and uncompressed responses are always accepted.
+    pub fn with_compression(mut self, enabled: bool) -> Self {
+        self.compression = enabled;
+        self
+    }
+
+    /// Sets the size above which manifests are uploaded in chunks, and the size of each
+    /// chunk and of each range of downloads
+    pub fn with_chunk_size(mut self, bytes: usize) -> Self {
+        self.chunk_size = bytes.max(1);
+        self
+    }
+
     fn manifest_url(&self, id: Option<&str>) -> String {
         match id {
             Some(id) => format!("{}/manifests/{}", self.base_url, id),
             None => format!("{}/manifests", self.base_url),
         }
     }
+
+    fn envelope_url(&self, id: Option<&str>) -> String {
+        match id {
+            Some(id) => format!("{}/envelopes/{}", self.base_url, id),
+            None => format!("{}/envelopes", self.base_url),
+        }
+    }
+
+    fn upload_url(&self, upload_id: &str) -> String {
+        format!("{}/uploads/{}", self.base_url, upload_id)
+    }
+
+    // Sends a request with the configured credentials, GET requests advertising zstd
+    // support. Requests the server refuses fail with an error naming the role they need.
+    fn send(
+        &self,
+        method: Method,
+        url: String,
+        context: &str,
+        prepare: impl FnOnce(RequestBuilder) -> RequestBuilder,
+    ) -> Result<Response> {
+        let request = self.client.request(method.clone(), url);
+        let request = if method == Method::GET {
+            request.header(ACCEPT_ENCODING, ZSTD_ENCODING)
+        } else {
+            request
+        };
+
+        let started = Instant::now();
+        let response = self
+            .credentials
+            .authorize(&self.client, prepare(request))?
+            .send_pinned(context);
+        let outcome = match &response {
+            Ok(response) => StorageOutcome::from_status(response.status()),
+            Err(e) if e.is_retryable() => StorageOutcome::Unavailable,
+            Err(_) => StorageOutcome::ClientError,
+        };
+        metrics::record_storage_request("database", started.elapsed(), outcome);
+
+        let response = response?;
+        match auth::refused(response.status(), &method, &self.credentials) {
+            Some(error) => Err(error),
+            None => Ok(response),
+        }
+    }
+
+    fn get(&self, url: String, context: &str) -> Result<Response> {
+        self.send(Method::GET, url, context, |request| request)
+    }
+
+    // Uploads an encoded manifest in a single request
+    fn send_manifest(
+        &self,
+        id: &str,
+        body: Vec<u8>,
+        encoding: Option<&str>,
+        key: &str,
+    ) -> Result<Response> {
+        self.send(
+            Method::POST,
+            self.manifest_url(Some(id)),
+            "Failed to store manifest",
+            |request| {
+                let request = request
+                    .header(CONTENT_TYPE, "application/json")
+                    .header(IDEMPOTENCY_KEY_HEADER, key);
+                match encoding {
+                    Some(encoding) => request.header(CONTENT_ENCODING, encoding).body(body),
+                    None => request.body(body),
+                }
+            },
+        )
+    }
+
+    // Uploads a manifest, compressed if enabled. Servers that don't accept the encoding
+    // answer 415, in which case the manifest is sent uncompressed.
+    fn post_manifest(
+        &self,
+        id: &str,
+        stored_manifest: &StoredManifest,
+        manifest: &Manifest,
+    ) -> Result<()> {
+        let body =
+            serde_json::to_vec(stored_manifest).map_err(|e| Error::Serialization(e.to_string()))?;
+        let key = idempotency_key(id, manifest)?;
+
+        let mut status = StatusCode::UNSUPPORTED_MEDIA_TYPE;
+        if self.compression {
+            status =
+                self.upload_manifest(id, compression::compress(&body)?, Some(ZSTD_ENCODING), &key)?;
+        }
+        if status == StatusCode::UNSUPPORTED_MEDIA_TYPE {
+            status = self.upload_manifest(id, body, None, &key)?;
+        }
+
+        if !status.is_success() {
+            return Err(Error::storage_status(
+                status,
+                format!("Failed to store manifest. Status: {status}"),
+            ));
+        }
+        Ok(())
+    }
+
+    // Uploads an encoded manifest, in chunks if it is larger than the chunk size and the
+    // server supports it. Returns the status of the request that stored the manifest.
+    // Requests that fail on the way are sent again with the same idempotency key.
+    fn upload_manifest(
+        &self,
+        id: &str,
+        body: Vec<u8>,
+        encoding: Option<&str>,
+        key: &str,
+    ) -> Result<StatusCode> {
+        if body.len() > self.chunk_size
+            && let Some(status) = self.upload_chunked(id, &body, encoding, key)?
+        {
+            return Ok(status);
+        }
+        let response = chunked::with_retries(|| {
+            let response = self.send_manifest(id, body.clone(), encoding, key)?;
+            if response.status().is_server_error() {
+                return Err(Error::storage_status(
+                    response.status(),
+                    format!("Failed to store manifest. Status: {}", response.status()),
+                ));
+            }
+            Ok(response)
+        })?;
+        Ok(response.status())
+    }
+
+    // Uploads an encoded manifest in chunks, skipping those the server already holds from
+    // an earlier attempt. Returns the status of the request completing the upload, or None
+    // if the server has no upload API.
+    fn upload_chunked(
+        &self,
+        id: &str,
+        body: &[u8],
+        encoding: Option<&str>,
+        key: &str,
+    ) -> Result<Option<StatusCode>> {
+        let upload_id = chunked::upload_id(id);
+        let Some(status) = chunked::with_retries(|| self.upload_status(&upload_id))? else {
+            return Ok(None);
+        };
+
+        let chunks = chunked::chunks(body, self.chunk_size);
+        let resume_hint = |e: Error| match e {
+            Error::StorageUnavailable(message) => Error::StorageUnavailable(format!(
+                "{message}; the chunks uploaded so far are kept, run the command again to resume"
+            )),
+            e => e,
+        };
+        for chunk in chunks.iter().filter(|chunk| !status.has(chunk)) {
+            let url = format!("{}/chunks/{}", self.upload_url(&upload_id), chunk.index);
+            let context = format!(
+                "Failed to upload chunk {} of {}",
+                chunk.index + 1,
+                chunks.len()
+            );
+            let response = chunked::with_retries(|| {
+                let data = body[chunk.range.clone()].to_vec();
+                let response = self.send(Method::PUT, url.clone(), &context, |request| {
+                    request
+                        .header(CONTENT_TYPE, "application/octet-stream")
+                        .header(CHUNK_SHA256_HEADER, &chunk.sha256)
+                        .header(
+                            CONTENT_RANGE,
+                            format!(
+                                "bytes {}-{}/{}",
+                                chunk.range.start,
+                                chunk.range.end - 1,
+                                body.len()
+                            ),
+                        )
+                        .body(data)
+                })?;
+                // The server rejects chunks whose digest doesn't match what it received
+                if response.status() == StatusCode::UNPROCESSABLE_ENTITY {
+                    return Err(Error::StorageUnavailable(format!(
+                        "Chunk {} of {} was corrupted in transit",
+                        chunk.index + 1,
+                        chunks.len()
+                    )));
+                }
+                if response.status().is_server_error() {
+                    return Err(Error::storage_status(
+                        response.status(),
+                        format!("{context}. Status: {}", response.status()),
+                    ));
+                }
+                Ok(response)
+            })
+            .map_err(resume_hint)?;
+
+            // Servers without the upload API may answer the status request, but not this
+            if status.chunks.is_empty() && chunk.index == 0 && upload_unsupported(response.status())
+            {
+                return Ok(None);
+            }
+            if !response.status().is_success() {
+                return Err(Error::storage_status(
+                    response.status(),
+                    format!("{context}. Status: {}", response.status()),
+                ));
+            }
+        }
+
+        let complete = CompleteUpload {
+            manifest_id: id.to_string(),
+            content_encoding: encoding.map(str::to_string),
+            size: body.len(),
+            sha256: chunked::sha256(body),
+            chunks: chunks.len(),
+        };
+        let response = chunked::with_retries(|| {
+            let response = self.send(
+                Method::POST,
+                format!("{}/complete", self.upload_url(&upload_id)),
+                "Failed to complete manifest upload",
+                |request| request.header(IDEMPOTENCY_KEY_HEADER, key).json(&complete),
+            )?;
+            if response.status().is_server_error() {
+                return Err(Error::storage_status(
+                    response.status(),
+                    format!(
+                        "Failed to complete manifest upload. Status: {}",
+                        response.status()
+                    ),
+                ));
+            }
+            Ok(response)
+        })
+        .map_err(resume_hint)?;
+
+        Ok(Some(response.status()))
+    }
+
+    // Asks which chunks of an upload the server holds. Returns None if the server has no
+    // upload API.
+    fn upload_status(&self, upload_id: &str) -> Result<Option<UploadStatus>> {
+        let response = self.get(
+            self.upload_url(upload_id),
+            "Failed to query manifest upload",
+        )?;
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(Some(UploadStatus::default())),
+            status if upload_unsupported(status) => Ok(None),
+            status if status.is_success() => Ok(Some(parse_response(response, "upload status")?)),
+            status => Err(Error::storage_status(
+                status,
+                format!("Failed to query manifest upload. Status: {status}"),
+            )),
+        }
+    }
+
+    // Downloads a stored entry in ranges of the chunk size, retrying each range on its own.
+    // Servers that ignore the Range header send the whole entry. Returns None if the
+    // server has no entry at `url`.
+    fn download(&self, url: &str, context: &str) -> Result<Option<Vec<u8>>> {
+        let mut body = Vec::new();
+        let mut expected = None;
+        loop {
+            let start = body.len() as u64;
+            let Some(part) = chunked::with_retries(|| self.get_range(url, context, start))? else {
+                if start == 0 {
+                    return Ok(None);
+                }
+                return Err(Error::Storage(format!(
+                    "{context}: the entry disappeared during the download"
+                )));
+            };
+
+            if start == 0 {
+                expected = part.content_sha256.clone();
+            } else if part.total.is_none() || part.content_sha256 != expected {
+                return Err(Error::Storage(format!(
+                    "{context}: the entry changed during the download"
+                )));
+            }
+            body.extend_from_slice(&part.data);
+
+            match part.total {
+                Some(total) if (body.len() as u64) < total => continue,
+                _ => break,
+            }
+        }
+
+        chunked::check_digest(&body, expected.as_deref(), "The downloaded entry").map_err(
+            |_| {
+                Error::Storage(format!(
+                    "{context}: the downloaded entry does not match its digest"
+                ))
+            },
+        )?;
+        Ok(Some(body))
+    }
+
+    // Fetches the range of an entry starting at `start`
+    fn get_range(&self, url: &str, context: &str, start: u64) -> Result<Option<RangePart>> {
+        let end = start + self.chunk_size as u64 - 1;
+        let response = self.send(Method::GET, url.to_string(), context, |request| {
+            request.header(RANGE, format!("bytes={start}-{end}"))
+        })?;
+
+        let status = response.status();
+        if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+            return Err(Error::storage_status(
+                status,
+                format!("{context}. Status: {status}"),
+            ));
+        }
+        if !status.is_success() {
+            return Ok(None);
+        }
+
+        let headers = response.headers().clone();
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        let content_sha256 = header(CONTENT_SHA256_HEADER);
+        let chunk_sha256 = header(CHUNK_SHA256_HEADER);
+        let content_range = header(CONTENT_RANGE.as_str());
+        // An interrupted body is worth another try
+        let data = response
+            .bytes()
+            .map_err(|e| Error::StorageUnavailable(format!("{context}: {e}")))?
+            .to_vec();
+
+        let total = if status == StatusCode::PARTIAL_CONTENT {
+            let (first, last, total) = content_range
+                .as_deref()
+                .and_then(chunked::parse_content_range)
+                .ok_or_else(|| {
+                    Error::Storage(format!("{context}: invalid Content-Range in response"))
+                })?;
+            if first != start || last - first + 1 != data.len() as u64 {
+                return Err(Error::StorageUnavailable(format!(
+                    "{context}: received bytes {first}-{last} instead of a range from {start}"
+                )));
+            }
+            chunked::check_digest(
+                &data,
+                chunk_sha256.as_deref(),
+                &format!("Range {first}-{last}"),
+            )?;
+            Some(total)
+        } else {
+            // The whole entry, sent in one response
+            chunked::check_digest(&data, content_sha256.as_deref(), "The downloaded entry")?;
+            None
+        };
+
+        Ok(Some(RangePart {
+            data,
+            total,
+            content_sha256,
+        }))
+    }
+}
+
+// A range of a downloaded entry
+struct RangePart {
+    data: Vec<u8>,
+    /// Size of the whole entry, if only part of it was sent
+    total: Option<u64>,
+    content_sha256: Option<String>,
+}
+
+// Whether a status shows the server has no upload API
+fn upload_unsupported(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::NOT_FOUND | StatusCode::METHOD_NOT_ALLOWED | StatusCode::NOT_IMPLEMENTED
+    )
+}
+
+/// Idempotency key of a request storing `manifest` as `id`.
+///
+/// Every attempt to store the same content under the same ID sends the same key, so that a
+/// server which already stored the manifest answers a retry with the outcome of the first
+/// attempt instead of storing it again. The key is the SHA-256 of the ID and the digest of
+/// the content of the manifest: its canonical form (see [`content_id::canonical_form`]),
+/// whether it is signed, and its cross-references.
+pub fn idempotency_key(id: &str, manifest: &Manifest) -> Result<String> {
+    let mut hasher = Sha256::new();
+    hasher.update(id.as_bytes());
+    hasher.update([0]);
+    hasher.update(content_digest(manifest)?);
+    Ok(hex::encode(hasher.finalize()))
+}
+
+// Digest of what storing a manifest again leaves unchanged: its canonical form, whether it
+// is signed, and its cross-references, which the canonical form leaves out but linking
+// changes. Retries differing only in instance IDs or creation times have the same digest.
+fn content_digest(manifest: &Manifest) -> Result<[u8; 32]> {
+    let cross_references = serde_json::to_vec(&manifest.cross_references)
+        .map_err(|e| Error::Serialization(e.to_string()))?;
+    let canonical = content_id::canonical_form(manifest)?;
+    let mut hasher = Sha256::new();
+    hasher.update([manifest.claim.signature.is_some() as u8]);
+    hasher.update((canonical.len() as u64).to_be_bytes());
+    hasher.update(canonical);
+    hasher.update(cross_references);
+    Ok(hasher.finalize().into())
+}
+
+// Whether a stored entry holds content with the digest `digest`, as it does when an earlier
+// attempt to store the manifest succeeded but its answer was lost
+fn holds_content(stored: &Value, digest: &[u8; 32]) -> bool {
+    stored
+        .get("manifest")
+        .and_then(|stored| serde_json::from_value::<Manifest>(stored.clone()).ok())
+        .is_some_and(|existing| content_digest(&existing).is_ok_and(|d| &d == digest))
+}
+
+// Parses a JSON response body, decompressing it if it is zstd-encoded
+fn parse_response<T: DeserializeOwned>(response: Response, what: &str) -> Result<T> {
+    let body = response
+        .bytes()
+        .map_err(|e| Error::Storage(format!("Failed to read {what}: {e}")))?;
+
+    parse_body(&body, what)
+}
+
+fn parse_body<T: DeserializeOwned>(body: &[u8], what: &str) -> Result<T> {
+    serde_json::from_slice(&compression::decompress(body)?)
+        .map_err(|e| Error::Storage(format!("Failed to parse {what}: {e}")))
 }
 
 impl StorageBackend for DatabaseStorage {
@@ -79,17 +593,22 @@ impl StorageBackend for DatabaseStorage {
     }
 
     fn store_manifest(&self, manifest: &Manifest) -> Result<String> {
+        let digest = content_digest(manifest)?;
+
         // Check if this ID already exists
-        let existing = self
-            .client
-            .get(format!(
-                "{}/manifests/{}",
-                self.base_url, &manifest.instance_id
-            ))
-            .send()
-            .map_err(|e| Error::Storage(format!("Failed to check existing manifest: {e}")))?;
-
-        if existing.status().is_success() {
+        let existing = self.download(
+            &self.manifest_url(Some(&manifest.instance_id)),
+            "Failed to check existing manifest",
+        )?;
+
+        if let Some(existing) = existing {
+            // Storing the same content again, as a retry does, yields the ID it is stored as
+            if parse_body::<StoredManifest>(&existing, "manifest")
+                .is_ok_and(|existing| holds_content(&existing.manifest, &digest))
+            {
+                return Ok(manifest.instance_id.clone());
+            }
+
             // Manifest exists - create a new version
 
             // Parse the existing ID
@@ -104,21 +623,26 @@ impl StorageBackend for DatabaseStorage {
             let claim_generator = manifest.claim_generator.replace('/', "_");
 
             // Get all manifests to find highest version
-            let all_manifests_response = self
-                .client
-                .get(format!("{}/manifests", self.base_url))
-                .send()
-                .map_err(|e| Error::Storage(format!("Failed to list manifests: {e}")))?;
+            let all_manifests_response = self.get(
+                format!("{}/manifests", self.base_url),
+                "Failed to list manifests",
+            )?;
 
-            let all_manifests: Vec<serde_json::Value> = all_manifests_response
-                .json()
-                .map_err(|e| Error::Storage(format!("Failed to parse manifests list: {e}")))?;
+            let all_manifests: Vec<serde_json::Value> =
+                parse_response(all_manifests_response, "manifests list")?;
 
             // Find highest version for this ID
             let mut max_version = 0;
             for manifest_entry in all_manifests {
                 if let Some(id) = manifest_entry.get("manifest_id").and_then(|v| v.as_str()) {
                     if id.starts_with(&format!("urn:c2pa:{uuid_part}:")) {
+                        // A version with this content was stored by an earlier attempt
+                        if manifest_entry
+                            .get("manifest")
+                            .is_some_and(|stored| holds_content(stored, &digest))
+                        {
+                            return Ok(id.to_string());
+                        }
                         let id_parts: Vec<&str> = id.split(':').collect();
                         if id_parts.len() >= 5 {
                             if let Some(version_reason) = id_parts.get(4) {
@@ -156,14 +680,10 @@ impl StorageBackend for DatabaseStorage {
                 manifest_type,
                 manifest: serde_json::to_value(&updated_manifest)
                     .map_err(|e| Error::Serialization(e.to_string()))?,
-                created_at: time::OffsetDateTime::now_utc().to_string(),
+                created_at: to_rfc3339_utc(manifest.created_at.0),
             };
 
-            self.client
-                .post(self.manifest_url(Some(&versioned_id)))
-                .json(&stored_manifest)
-                .send()
-                .map_err(|e| Error::Storage(format!("Failed to store manifest: {e}")))?;
+            self.post_manifest(&versioned_id, &stored_manifest, &updated_manifest)?;
 
             Ok(versioned_id)
         } else {
@@ -176,14 +696,10 @@ impl StorageBackend for DatabaseStorage {
                 manifest_type,
                 manifest: serde_json::to_value(manifest)
                     .map_err(|e| Error::Serialization(e.to_string()))?,
-                created_at: time::OffsetDateTime::now_utc().to_string(),
+                created_at: to_rfc3339_utc(manifest.created_at.0),
             };
 
-            self.client
-                .post(self.manifest_url(Some(&manifest.instance_id)))
-                .json(&stored_manifest)
-                .send()
-                .map_err(|e| Error::Storage(format!("Failed to store manifest: {e}")))?;
+            self.post_manifest(&manifest.instance_id, &stored_manifest, manifest)?;
 
             Ok(manifest.instance_id.clone())
         }
@@ -199,17 +715,11 @@ impl StorageBackend for DatabaseStorage {
         };
 
         // First try direct retrieval with the given ID
-        let response = self
-            .client
-            .get(format!("{}/manifests/{}", self.base_url, id))
-            .send()
-            .map_err(|e| Error::Storage(format!("Failed to retrieve manifest: {e}")))?;
-
-        if response.status().is_success() {
+        if let Some(body) =
+            self.download(&self.manifest_url(Some(id)), "Failed to retrieve manifest")?
+        {
             // Found the manifest, parse it
-            let stored_manifest: StoredManifest = response
-                .json()
-                .map_err(|e| Error::Storage(format!("Failed to parse manifest: {e}")))?;
+            let stored_manifest: StoredManifest = parse_body(&body, "manifest")?;
 
             // Extract the inner manifest
             let manifest_value = stored_manifest
@@ -222,23 +732,23 @@ impl StorageBackend for DatabaseStorage {
         }
 
         // If direct lookup failed, try to find all versions
-        let list_response = self
-            .client
-            .get(format!("{}/manifests", self.base_url))
-            .send()
-            .map_err(|e| Error::Storage(format!("Failed to list manifests: {e}")))?;
+        let list_response = self.get(
+            format!("{}/manifests", self.base_url),
+            "Failed to list manifests",
+        )?;
 
         if !list_response.status().is_success() {
-            return Err(Error::Storage(format!(
-                "Failed to list manifests. Status: {}",
-                list_response.status()
-            )));
+            return Err(Error::storage_status(
+                list_response.status(),
+                format!(
+                    "Failed to list manifests. Status: {}",
+                    list_response.status()
+                ),
+            ));
         }
 
         // Parse the manifest list
-        let manifests: Vec<StoredManifest> = list_response
-            .json()
-            .map_err(|e| Error::Storage(format!("Failed to parse manifests list: {e}")))?;
+        let manifests: Vec<StoredManifest> = parse_response(list_response, "manifests list")?;
 
         // Find all versions of this manifest
         let mut versions: Vec<StoredManifest> = manifests
@@ -247,7 +757,7 @@ impl StorageBackend for DatabaseStorage {
             .collect();
 
         if versions.is_empty() {
-            return Err(Error::Storage(format!("Manifest not found for ID: {id}")));
+            return Err(Error::NotFound(format!("Manifest not found for ID: {id}")));
         }
 
         // Sort by created_at timestamp (newest first)
@@ -267,34 +777,35 @@ impl StorageBackend for DatabaseStorage {
     }
 
     fn list_manifests(&self) -> Result<Vec<ManifestMetadata>> {
-        let response = self
-            .client
-            .get(self.manifest_url(None))
-            .send()
-            .map_err(|e| Error::Storage(format!("Failed to list manifests: {e}")))?;
+        let response = self.get(self.manifest_url(None), "Failed to list manifests")?;
 
         if !response.status().is_success() {
-            return Err(Error::Storage(format!(
-                "Failed to list manifests. Status: {}",
-                response.status()
-            )));
+            return Err(Error::storage_status(
+                response.status(),
+                format!("Failed to list manifests. Status: {}", response.status()),
+            ));
         }
 
-        let stored_manifests: Vec<StoredManifest> = response
-            .json()
-            .map_err(|e| Error::Storage(format!("Failed to parse manifests list: {e}")))?;
+        let listed: Vec<ListedManifest> = parse_response(response, "manifests list")?;
 
-        Ok(stored_manifests
+        Ok(listed
             .into_iter()
             .map(|m| {
-                let title = m
+                let fields = m
                     .manifest
-                    .get("manifest")
-                    .and_then(|inner| inner.get("manifest"))
-                    .and_then(|manifest| manifest.get("title"))
-                    .and_then(|t| t.as_str())
-                    .unwrap_or("Unknown")
-                    .to_string();
+                    .and_then(|inner| inner.manifest)
+                    .and_then(|inner| inner.manifest);
+                let title = fields
+                    .as_ref()
+                    .and_then(|fields| fields.title.clone())
+                    .unwrap_or_else(|| "Unknown".to_string());
+                // Signed if either claim is, see `claims::is_signed`
+                let signed = fields.and_then(|fields| {
+                    let claims: Vec<ListedClaim> =
+                        fields.claim.into_iter().chain(fields.claim_v2).collect();
+                    (!claims.is_empty())
+                        .then(|| claims.iter().any(|claim| claim.signature.is_some()))
+                });
 
                 ManifestMetadata {
                     id: m.manifest_id,
@@ -304,28 +815,130 @@ impl StorageBackend for DatabaseStorage {
                         _ => ManifestType::Model,
                     },
                     created_at: m.created_at,
+                    signed,
                 }
             })
             .collect())
     }
 
     fn delete_manifest(&self, id: &str) -> Result<()> {
-        let response = self
-            .client
-            .delete(self.manifest_url(Some(id)))
-            .send()
-            .map_err(|e| Error::Storage(format!("Failed to delete manifest: {e}")))?;
+        let response = self.send(
+            Method::DELETE,
+            self.manifest_url(Some(id)),
+            "Failed to delete manifest",
+            |request| request,
+        )?;
 
         if !response.status().is_success() {
-            return Err(Error::Storage(format!(
-                "Failed to delete manifest. Status: {}",
-                response.status()
-            )));
+            return Err(Error::storage_status(
+                response.status(),
+                format!("Failed to delete manifest. Status: {}", response.status()),
+            ));
         }
 
         Ok(())
     }
 
+    fn migrate_compression(&self) -> Result<usize> {
+        let response = self.get(self.manifest_url(None), "Failed to list manifests")?;
+
+        if !response.status().is_success() {
+            return Err(Error::storage_status(
+                response.status(),
+                format!("Failed to list manifests. Status: {}", response.status()),
+            ));
+        }
+
+        let stored_manifests: Vec<StoredManifest> = parse_response(response, "manifests list")?;
+        let mut migrated = 0;
+
+        // Re-upload every entry compressed; the server replaces the uncompressed copy
+        for stored_manifest in stored_manifests {
+            let body = serde_json::to_vec(&stored_manifest)
+                .map_err(|e| Error::Serialization(e.to_string()))?;
+            let body = compression::compress(&body)?;
+            // Keyed by the compressed body, so the server doesn't take the re-upload for a
+            // retry of the request that stored the entry
+            let key = chunked::sha256(&body);
+            let response = self.send_manifest(
+                &stored_manifest.manifest_id,
+                body,
+                Some(ZSTD_ENCODING),
+                &key,
+            )?;
+            if response.status() == StatusCode::UNSUPPORTED_MEDIA_TYPE {
+                return Err(Error::Storage(
+                    "The database server does not accept zstd-encoded manifests".to_string(),
+                ));
+            }
+            if !response.status().is_success() {
+                return Err(Error::storage_status(
+                    response.status(),
+                    format!(
+                        "Failed to migrate manifest {}. Status: {}",
+                        stored_manifest.manifest_id,
+                        response.status()
+                    ),
+                ));
+            }
+            migrated += 1;
+        }
+
+        Ok(migrated)
+    }
+
+    fn supports_envelopes(&self) -> bool {
+        true
+    }
+
+    fn store_envelope(&self, record: &EnvelopeRecord) -> Result<String> {
+        let response = self.send(
+            Method::POST,
+            self.envelope_url(Some(&record.id)),
+            "Failed to store envelope",
+            |request| request.json(record),
+        )?;
+
+        if !response.status().is_success() {
+            return Err(Error::storage_status(
+                response.status(),
+                format!("Failed to store envelope. Status: {}", response.status()),
+            ));
+        }
+
+        Ok(record.id.clone())
+    }
+
+    fn retrieve_envelope(&self, id: &str) -> Result<EnvelopeRecord> {
+        let response = self.get(self.envelope_url(Some(id)), "Failed to retrieve envelope")?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            return Err(Error::NotFound(format!("Envelope not found: {id}")));
+        }
+        if !response.status().is_success() {
+            return Err(Error::storage_status(
+                response.status(),
+                format!("Failed to retrieve envelope. Status: {}", response.status()),
+            ));
+        }
+
+        parse_response(response, "envelope")
+    }
+
+    fn list_envelopes(&self) -> Result<Vec<EnvelopeMetadata>> {
+        let response = self.get(self.envelope_url(None), "Failed to list envelopes")?;
+
+        if !response.status().is_success() {
+            return Err(Error::storage_status(
+                response.status(),
+                format!("Failed to list envelopes. Status: {}", response.status()),
+            ));
+        }
+
+        let records: Vec<EnvelopeRecord> = parse_response(response, "envelopes list")?;
+        Ok(records.iter().map(EnvelopeRecord::metadata).collect())
+    }
+
     // Added for test suite
     fn as_any(&self) -> &dyn std::any::Any {
         self
@@ -0,0 +1,423 @@
+//! Export and import of a whole provenance store.
+//!
+//! `store export` writes every manifest and attestation envelope of a store to a
+//! zstd-compressed tar archive, for backups, replication to partners or promotion from one
+//! environment to another. The archive starts with an index listing each entry with its
+//! ID and SHA-256 checksum. `store import` checks the index and every checksum before
+//! storing anything, so a corrupted or tampered archive leaves the target store untouched.
+//!
+//! For periodic replication, such as from an edge training site to a central store, an
+//! export can write a checkpoint with the checksum of every entry it saw. A later export
+//! `--since` that checkpoint only holds the entries created or changed since, and
+//! `store import --dedupe` leaves entries the target already holds unchanged alone.
+
+use crate::error::{Error, Result};
+use crate::storage::compression::COMPRESSION_LEVEL;
+use crate::storage::traits::{EnvelopeRecord, StorageBackend};
+use atlas_c2pa_lib::manifest::Manifest;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use time::OffsetDateTime;
+
+/// Format of the archive index
+pub const EXPORT_FORMAT: &str = "atlas-store-export/v1";
+
+/// Path of the index in the archive
+pub const INDEX_PATH: &str = "index.json";
+
+/// Format of export checkpoints
+pub const CHECKPOINT_FORMAT: &str = "atlas-store-checkpoint/v1";
+
+/// What an archive entry holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryKind {
+    Manifest,
+    Envelope,
+}
+
+/// An entry of the archive index
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexEntry {
+    /// Path of the entry in the archive
+    pub path: String,
+    pub kind: EntryKind,
+    /// ID of the manifest or envelope in the exported store
+    pub id: String,
+    /// Hex-encoded SHA-256 of the entry
+    pub sha256: String,
+}
+
+/// Index of an exported store
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportIndex {
+    pub format: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    /// Base URI of the exported store
+    pub source: String,
+    /// Start of a differential export, which only holds entries created or changed since
+    #[serde(
+        default,
+        with = "time::serde::rfc3339::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub since: Option<OffsetDateTime>,
+    pub entries: Vec<IndexEntry>,
+}
+
+/// Checksums of the entries of a store at the time of an export
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub format: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    /// Base URI of the exported store
+    pub source: String,
+    /// Hex-encoded SHA-256 of each manifest, by ID
+    pub manifests: BTreeMap<String, String>,
+    /// Hex-encoded SHA-256 of each envelope, by ID
+    pub envelopes: BTreeMap<String, String>,
+}
+
+impl Checkpoint {
+    pub fn load(path: &Path) -> Result<Self> {
+        let checkpoint: Checkpoint =
+            serde_json::from_slice(&std::fs::read(path)?).map_err(|e| {
+                Error::Validation(format!("Invalid checkpoint {}: {e}", path.display()))
+            })?;
+        if checkpoint.format != CHECKPOINT_FORMAT {
+            return Err(Error::Validation(format!(
+                "Unsupported checkpoint format {} in {}",
+                checkpoint.format,
+                path.display()
+            )));
+        }
+        Ok(checkpoint)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Start of a differential export
+#[derive(Debug, Clone)]
+pub enum ExportSince {
+    /// Entries created after this time. Manifests changed since without being created
+    /// again, such as by linking, are not exported.
+    Timestamp(OffsetDateTime),
+    /// Entries new or changed since the checkpoint was written
+    Checkpoint(Checkpoint),
+}
+
+impl ExportSince {
+    /// Parses a `--since` value: an RFC 3339 timestamp or date, or the path of a checkpoint
+    pub fn parse(value: &str) -> Result<Self> {
+        if let Ok(timestamp) = crate::manifest::timestamp::parse_created_at(value) {
+            return Ok(ExportSince::Timestamp(timestamp));
+        }
+        let path = Path::new(value);
+        if path.is_file() {
+            return Checkpoint::load(path).map(ExportSince::Checkpoint);
+        }
+        Err(Error::Validation(format!(
+            "'{value}' is neither a timestamp (RFC 3339 or YYYY-MM-DD) nor a checkpoint file"
+        )))
+    }
+
+    fn start(&self) -> OffsetDateTime {
+        match self {
+            ExportSince::Timestamp(timestamp) => *timestamp,
+            ExportSince::Checkpoint(checkpoint) => checkpoint.created_at,
+        }
+    }
+
+    // Whether an entry with checksum `sha256` created at `created_at` is exported
+    fn includes(
+        &self,
+        kind: EntryKind,
+        id: &str,
+        sha256: &str,
+        created_at: Option<OffsetDateTime>,
+    ) -> bool {
+        match self {
+            // Entries without a readable creation time are exported to be safe
+            ExportSince::Timestamp(since) => created_at.is_none_or(|at| at > *since),
+            ExportSince::Checkpoint(checkpoint) => {
+                let seen = match kind {
+                    EntryKind::Manifest => &checkpoint.manifests,
+                    EntryKind::Envelope => &checkpoint.envelopes,
+                };
+                seen.get(id).is_none_or(|seen| seen != sha256)
+            }
+        }
+    }
+}
+
+/// What [`import_store`] does with entries whose ID is already in the target store
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OnExisting {
+    /// Store them again
+    #[default]
+    Store,
+    /// Leave them as they are
+    Skip,
+    /// Leave them as they are if they are identical, and store them again if they changed
+    Dedupe,
+}
+
+/// Number of entries exported or imported
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct TransferSummary {
+    pub manifests: usize,
+    pub envelopes: usize,
+    /// Entries left out: on export, those unchanged since `--since`; on import, those the
+    /// target store already has
+    pub skipped: usize,
+}
+
+fn archive_error(e: impl std::fmt::Display) -> Error {
+    Error::Storage(format!("Invalid store archive: {e}"))
+}
+
+/// Writes every manifest and envelope of `storage` to a `.tar.zst` archive at `output`
+pub fn export_store(storage: &dyn StorageBackend, output: &Path) -> Result<TransferSummary> {
+    export_store_since(storage, output, None).map(|(summary, _)| summary)
+}
+
+/// Writes the manifests and envelopes of `storage` created or changed since `since`, or all
+/// of them, to a `.tar.zst` archive at `output`. Returns a checkpoint of the whole store for
+/// the next differential export.
+pub fn export_store_since(
+    storage: &dyn StorageBackend,
+    output: &Path,
+    since: Option<&ExportSince>,
+) -> Result<(TransferSummary, Checkpoint)> {
+    let source = storage.get_base_uri();
+    if let Some(ExportSince::Checkpoint(checkpoint)) = since
+        && checkpoint.source != source
+    {
+        return Err(Error::Validation(format!(
+            "The checkpoint is of {}, not of {source}",
+            checkpoint.source
+        )));
+    }
+
+    let created_at = OffsetDateTime::now_utc();
+    let mut checkpoint = Checkpoint {
+        format: CHECKPOINT_FORMAT.to_string(),
+        created_at,
+        source: source.clone(),
+        manifests: BTreeMap::new(),
+        envelopes: BTreeMap::new(),
+    };
+    let mut summary = TransferSummary::default();
+    let mut entries = Vec::new();
+    let mut files = Vec::new();
+    let mut add = |kind: EntryKind, id: String, data: Vec<u8>, at: Option<OffsetDateTime>| {
+        let sha256 = hex::encode(Sha256::digest(&data));
+        let included = since.is_none_or(|since| since.includes(kind, &id, &sha256, at));
+        let (directory, seen, count) = match kind {
+            EntryKind::Manifest => (
+                "manifests",
+                &mut checkpoint.manifests,
+                &mut summary.manifests,
+            ),
+            EntryKind::Envelope => (
+                "envelopes",
+                &mut checkpoint.envelopes,
+                &mut summary.envelopes,
+            ),
+        };
+        seen.insert(id.clone(), sha256.clone());
+        if !included {
+            summary.skipped += 1;
+            return;
+        }
+        *count += 1;
+
+        let path = format!("{directory}/{:06}.json", files.len());
+        entries.push(IndexEntry {
+            path: path.clone(),
+            kind,
+            id,
+            sha256,
+        });
+        files.push((path, data));
+    };
+
+    for metadata in storage.list_manifests()? {
+        let manifest = storage.retrieve_manifest(&metadata.id)?;
+        add(
+            EntryKind::Manifest,
+            metadata.id,
+            serde_json::to_vec(&manifest)?,
+            Some(manifest.created_at.0),
+        );
+    }
+    if storage.supports_envelopes() {
+        for metadata in storage.list_envelopes()? {
+            let record = storage.retrieve_envelope(&metadata.id)?;
+            let at = OffsetDateTime::parse(
+                &record.created_at,
+                &time::format_description::well_known::Rfc3339,
+            )
+            .ok();
+            add(
+                EntryKind::Envelope,
+                metadata.id,
+                serde_json::to_vec(&record)?,
+                at,
+            );
+        }
+    }
+
+    let index = ExportIndex {
+        format: EXPORT_FORMAT.to_string(),
+        created_at,
+        source,
+        since: since.map(ExportSince::start),
+        entries,
+    };
+
+    let encoder = zstd::stream::Encoder::new(File::create(output)?, COMPRESSION_LEVEL)?;
+    let mut builder = tar::Builder::new(encoder);
+    let index = serde_json::to_vec_pretty(&index)?;
+    for (path, data) in std::iter::once((INDEX_PATH.to_string(), index)).chain(files) {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, &path, data.as_slice())?;
+    }
+    builder.into_inner()?.finish()?;
+
+    Ok((summary, checkpoint))
+}
+
+/// Reads an exported store, checking every entry against the checksums of the index
+pub fn read_export(input: &Path) -> Result<(ExportIndex, Vec<Manifest>, Vec<EnvelopeRecord>)> {
+    let decoder = zstd::stream::Decoder::new(File::open(input)?)?;
+    let mut archive = tar::Archive::new(decoder);
+    let mut files = HashMap::new();
+    for entry in archive.entries().map_err(archive_error)? {
+        let mut entry = entry.map_err(archive_error)?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry
+            .path()
+            .map_err(archive_error)?
+            .to_string_lossy()
+            .into_owned();
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data)?;
+        files.insert(path, data);
+    }
+
+    let index: ExportIndex = serde_json::from_slice(
+        &files
+            .remove(INDEX_PATH)
+            .ok_or_else(|| archive_error(format!("missing {INDEX_PATH}")))?,
+    )
+    .map_err(archive_error)?;
+    if index.format != EXPORT_FORMAT {
+        return Err(archive_error(format!(
+            "unsupported format {}",
+            index.format
+        )));
+    }
+
+    let mut manifests = Vec::new();
+    let mut envelopes = Vec::new();
+    for entry in &index.entries {
+        let data = files
+            .remove(&entry.path)
+            .ok_or_else(|| archive_error(format!("missing entry {}", entry.path)))?;
+        if hex::encode(Sha256::digest(&data)) != entry.sha256 {
+            return Err(Error::Validation(format!(
+                "Checksum mismatch for {} ({}) in store archive",
+                entry.path, entry.id
+            )));
+        }
+        match entry.kind {
+            EntryKind::Manifest => manifests.push(
+                serde_json::from_slice(&data)
+                    .map_err(|e| archive_error(format!("{}: {e}", entry.path)))?,
+            ),
+            EntryKind::Envelope => envelopes.push(
+                serde_json::from_slice(&data)
+                    .map_err(|e| archive_error(format!("{}: {e}", entry.path)))?,
+            ),
+        }
+    }
+    if let Some(path) = files.keys().next() {
+        return Err(archive_error(format!("{path} is not listed in the index")));
+    }
+
+    Ok((index, manifests, envelopes))
+}
+
+/// Imports an exported store into `storage`.
+///
+/// The whole archive is checked before anything is stored. `on_existing` decides what
+/// happens to manifests and envelopes whose ID is already in `storage`.
+pub fn import_store(
+    storage: &dyn StorageBackend,
+    input: &Path,
+    on_existing: OnExisting,
+) -> Result<TransferSummary> {
+    let (_, manifests, envelopes) = read_export(input)?;
+    if !envelopes.is_empty() && !storage.supports_envelopes() {
+        return Err(Error::Storage(format!(
+            "The archive holds {} envelope(s), which the target storage backend can't store",
+            envelopes.len()
+        )));
+    }
+
+    let mut summary = TransferSummary::default();
+    for manifest in &manifests {
+        let existing = match on_existing {
+            OnExisting::Store => None,
+            _ => storage.retrieve_manifest(&manifest.instance_id).ok(),
+        };
+        if is_kept(on_existing, existing.as_ref(), manifest)? {
+            summary.skipped += 1;
+            continue;
+        }
+        storage.store_manifest(manifest)?;
+        summary.manifests += 1;
+    }
+    for record in &envelopes {
+        let existing = match on_existing {
+            OnExisting::Store => None,
+            _ => storage.retrieve_envelope(&record.id).ok(),
+        };
+        if is_kept(on_existing, existing.as_ref(), record)? {
+            summary.skipped += 1;
+            continue;
+        }
+        storage.store_envelope(record)?;
+        summary.envelopes += 1;
+    }
+
+    Ok(summary)
+}
+
+// Whether the entry `existing` of the target store is kept instead of importing `entry`.
+// Entries are compared as JSON values, so that field order doesn't matter.
+fn is_kept<T: Serialize>(on_existing: OnExisting, existing: Option<&T>, entry: &T) -> Result<bool> {
+    Ok(match (on_existing, existing) {
+        (_, None) | (OnExisting::Store, _) => false,
+        (OnExisting::Skip, Some(_)) => true,
+        (OnExisting::Dedupe, Some(existing)) => {
+            serde_json::to_value(existing)? == serde_json::to_value(entry)?
+        }
+    })
+}
@@ -1,40 +1,66 @@
 use crate::error::{Error, Result};
-use crate::manifest::utils::determine_manifest_type;
-use crate::storage::traits::{ManifestMetadata, ManifestType, StorageBackend};
-use crate::utils::{safe_create_file, safe_open_file};
+use crate::storage::compression::{self, COMPRESSED_EXTENSION};
+use crate::storage::traits::{
+    EnvelopeMetadata, EnvelopeRecord, ManifestMetadata, ManifestType, StorageBackend,
+};
+use crate::utils::{file_url_to_path, safe_create_file, safe_open_file};
 use atlas_c2pa_lib::manifest::Manifest;
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::fs::{self, create_dir_all};
 use std::io::{Read, Write};
 use std::path::Path;
 use std::path::PathBuf;
+use std::time::UNIX_EPOCH;
+
+// Subdirectory of the storage directory holding envelopes
+const ENVELOPE_DIR: &str = "envelopes";
+
+// Metadata of the manifests by file name, so that listing doesn't parse every manifest
+const METADATA_INDEX: &str = "manifest_metadata.json";
+
+// Metadata of a manifest file, valid while the file keeps its size and modification time
+#[derive(Clone, Serialize, Deserialize)]
+struct MetadataRecord {
+    size: u64,
+    // Nanoseconds since the Unix epoch
+    modified: u64,
+    metadata: ManifestMetadata,
+}
 
 #[derive(Debug, Clone)]
 pub struct FilesystemStorage {
     base_path: PathBuf,
+    compression: bool,
 }
 
 impl FilesystemStorage {
     pub fn new<P: AsRef<Path>>(url: P) -> Result<Self> {
         // Parse the URL to extract the path
         let path_str = url.as_ref().to_string_lossy();
-        let path = if path_str.starts_with("file://") {
-            PathBuf::from(path_str.trim_start_matches("file://"))
-        } else {
-            // Assume it's a direct path if not using file:// scheme
-            PathBuf::from(path_str.to_string())
-        };
+        // Assume it's a direct path if not using file:// scheme
+        let path = file_url_to_path(&path_str).unwrap_or_else(|| PathBuf::from(path_str.as_ref()));
 
         // Create directory if it doesn't exist
         if !path.exists() {
             create_dir_all(&path)?;
         }
 
-        Ok(Self { base_path: path })
+        Ok(Self {
+            base_path: path,
+            compression: true,
+        })
+    }
+
+    /// Enables or disables zstd compression of newly stored manifests (enabled by default).
+    /// Compressed and uncompressed manifests are always readable.
+    pub fn with_compression(mut self, enabled: bool) -> Self {
+        self.compression = enabled;
+        self
     }
 
-    // Helper to get path for a manifest
+    // Helper to get the uncompressed path for a manifest
     fn manifest_path(&self, id: &str) -> PathBuf {
         // Create a hash of the ID to use as filename
         let digest = Sha256::digest(id.as_bytes());
@@ -43,13 +69,38 @@ impl FilesystemStorage {
         self.base_path.join(format!("{filename}.json"))
     }
 
+    // Helper to get the compressed path for a manifest
+    fn compressed_manifest_path(&self, id: &str) -> PathBuf {
+        compressed_path(&self.manifest_path(id))
+    }
+
+    // Helper to get the path of an envelope, kept apart from manifests
+    fn envelope_path(&self, id: &str) -> PathBuf {
+        let digest = Sha256::digest(id.as_bytes());
+        self.base_path
+            .join(ENVELOPE_DIR)
+            .join(format!("{}.json", hex::encode(digest)))
+    }
+
+    // Helper to find the stored file for a manifest, compressed or not
+    fn existing_manifest_path(&self, id: &str) -> Option<PathBuf> {
+        [self.compressed_manifest_path(id), self.manifest_path(id)]
+            .into_iter()
+            .find(|path| path.exists())
+    }
+
     // Helper to list all manifest files
     fn list_manifest_files(&self) -> Result<Vec<PathBuf>> {
         let entries = fs::read_dir(&self.base_path)?
             .filter_map(|entry| {
                 let entry = entry.ok()?;
                 let path = entry.path();
-                if path.is_file() && path.extension().is_some_and(|ext| ext == "json") {
+                let name = path.file_name()?.to_str()?;
+                let is_manifest = name != "manifest_index.json"
+                    && name != METADATA_INDEX
+                    && (name.ends_with(".json")
+                        || name.ends_with(&format!(".json.{COMPRESSED_EXTENSION}")));
+                if path.is_file() && is_manifest {
                     Some(path)
                 } else {
                     None
@@ -60,6 +111,72 @@ impl FilesystemStorage {
         Ok(entries)
     }
 
+    // Helper to read a manifest file, decompressing it if needed
+    fn read_manifest_file(&self, path: &Path) -> Result<Manifest> {
+        let mut file = safe_open_file(path, false)?;
+        let mut content = Vec::new();
+        file.read_to_end(&mut content)?;
+
+        serde_json::from_slice(&compression::decompress(&content)?)
+            .map_err(|e| Error::Serialization(format!("Failed to parse manifest: {e}")))
+    }
+
+    // Helper to write a manifest file, compressed if enabled
+    fn write_manifest_file(&self, manifest: &Manifest, compress: bool) -> Result<PathBuf> {
+        let id = &manifest.instance_id;
+        let (path, stale_path) = if compress {
+            (self.compressed_manifest_path(id), self.manifest_path(id))
+        } else {
+            (self.manifest_path(id), self.compressed_manifest_path(id))
+        };
+
+        let json =
+            serde_json::to_vec_pretty(manifest).map_err(|e| Error::Serialization(e.to_string()))?;
+        let content = if compress {
+            compression::compress(&json)?
+        } else {
+            json
+        };
+
+        let mut file = safe_create_file(&path, false)?;
+        file.write_all(&content)?;
+
+        // Don't leave the previous version around in the other format
+        if stale_path.exists() {
+            fs::remove_file(&stale_path)?;
+        }
+
+        // Update index for quick lookups
+        if let Some(filename) = path.file_name().and_then(|n| n.to_str()) {
+            self.update_index(id, filename)?;
+        }
+
+        Ok(path)
+    }
+
+    // Helper to read the metadata index, which is rebuilt if it is missing or unreadable
+    fn read_metadata_index(&self) -> HashMap<String, MetadataRecord> {
+        let mut content = Vec::new();
+        safe_open_file(&self.base_path.join(METADATA_INDEX), false)
+            .and_then(|mut file| Ok(file.read_to_end(&mut content)?))
+            .ok()
+            .and_then(|_| serde_json::from_slice(&content).ok())
+            .unwrap_or_default()
+    }
+
+    // Helper to replace the metadata index, atomically so that concurrent readers see the
+    // old or the new index
+    fn write_metadata_index(&self, index: &HashMap<String, MetadataRecord>) -> Result<()> {
+        let json = serde_json::to_vec(index).map_err(|e| Error::Serialization(e.to_string()))?;
+        let path = self.base_path.join(METADATA_INDEX);
+        let temp_path = self
+            .base_path
+            .join(format!(".{METADATA_INDEX}.{}", std::process::id()));
+        safe_create_file(&temp_path, false)?.write_all(&json)?;
+        fs::rename(&temp_path, &path)?;
+        Ok(())
+    }
+
     // Helper to update index file for quick ID lookups
     fn update_index(&self, id: &str, filename: &str) -> Result<()> {
         let index_path = self.base_path.join("manifest_index.json");
@@ -93,78 +210,78 @@ impl StorageBackend for FilesystemStorage {
     }
 
     fn store_manifest(&self, manifest: &Manifest) -> Result<String> {
-        let manifest_id = manifest.instance_id.clone();
-        let path = self.manifest_path(&manifest_id);
+        self.write_manifest_file(manifest, self.compression)?;
 
-        // Serialize to JSON
-        let json = serde_json::to_string_pretty(manifest)
-            .map_err(|e| Error::Serialization(e.to_string()))?;
-
-        // Write to file
-        let mut file = safe_create_file(&path, false)?;
-        file.write_all(json.as_bytes())?;
-
-        // Update index for quick lookups
-        if let Some(filename) = path.file_name().and_then(|n| n.to_str()) {
-            self.update_index(&manifest_id, filename)?;
-        }
-
-        Ok(manifest_id)
+        Ok(manifest.instance_id.clone())
     }
 
     fn retrieve_manifest(&self, id: &str) -> Result<Manifest> {
-        let path = self.manifest_path(id);
+        let path = self
+            .existing_manifest_path(id)
+            .ok_or_else(|| Error::NotFound(format!("Manifest not found: {id}")))?;
 
-        if !path.exists() {
-            return Err(Error::Storage(format!("Manifest not found: {id}")));
-        }
-
-        // Read file
-        let mut file = safe_open_file(&path, false)?;
-        let mut content = String::new();
-        file.read_to_string(&mut content)?;
-
-        // Deserialize
-        serde_json::from_str(&content)
-            .map_err(|e| Error::Serialization(format!("Failed to parse manifest: {e}")))
+        self.read_manifest_file(&path)
     }
 
     fn list_manifests(&self) -> Result<Vec<ManifestMetadata>> {
+        // Manifests are only parsed if they are new or changed since the index was written
+        let mut index = self.read_metadata_index();
+        let mut listed = HashMap::new();
+        let mut changed = false;
         let mut manifests = Vec::new();
 
         for path in self.list_manifest_files()? {
-            let mut file = safe_open_file(&path, false)?;
-            let mut content = String::new();
-            file.read_to_string(&mut content)?;
+            let Some(name) = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .map(str::to_string)
+            else {
+                continue;
+            };
+            let file_metadata = fs::metadata(&path)?;
+            let size = file_metadata.len();
+            let modified = file_metadata
+                .modified()?
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_nanos() as u64);
+
+            let record = match index.remove(&name) {
+                Some(record) if record.size == size && record.modified == modified => record,
+                _ => match self.read_manifest_file(&path) {
+                    Ok(manifest) => {
+                        changed = true;
+                        MetadataRecord {
+                            size,
+                            modified,
+                            metadata: ManifestMetadata::from_manifest(&manifest),
+                        }
+                    }
+                    Err(e) => {
+                        // Log but don't fail on unparseable manifest
+                        eprintln!("Error parsing manifest at {path:?}: {e}");
+                        continue;
+                    }
+                },
+            };
+            manifests.push(record.metadata.clone());
+            listed.insert(name, record);
+        }
 
-            match serde_json::from_str::<Manifest>(&content) {
-                Ok(manifest) => {
-                    // Determine manifest type
-                    let manifest_type = determine_manifest_type(&manifest);
-
-                    manifests.push(ManifestMetadata {
-                        id: manifest.instance_id.clone(),
-                        name: manifest.title.clone(),
-                        manifest_type,
-                        created_at: manifest.created_at.0.to_string(),
-                    });
-                }
-                Err(e) => {
-                    // Log but don't fail on unparseable manifest
-                    eprintln!("Error parsing manifest at {path:?}: {e}");
-                }
-            }
+        // Records left in the index are of deleted manifests
+        if (changed || !index.is_empty())
+            && let Err(e) = self.write_metadata_index(&listed)
+        {
+            // Listing works without the index, just slower
+            eprintln!("Warning: failed to update the manifest metadata index: {e}");
         }
 
         Ok(manifests)
     }
 
     fn delete_manifest(&self, id: &str) -> Result<()> {
-        let path = self.manifest_path(id);
-
-        if !path.exists() {
-            return Err(Error::Storage(format!("Manifest not found: {id}")));
-        }
+        let path = self
+            .existing_manifest_path(id)
+            .ok_or_else(|| Error::NotFound(format!("Manifest not found: {id}")))?;
 
         fs::remove_file(&path)?;
 
@@ -184,12 +301,96 @@ impl StorageBackend for FilesystemStorage {
             // Write back to file
             let json = serde_json::to_string_pretty(&index)
                 .map_err(|e| Error::Serialization(e.to_string()))?;
-            let mut file = safe_create_file(&path, false)?;
+            let mut file = safe_create_file(&index_path, false)?;
             file.write_all(json.as_bytes())?;
         }
 
         Ok(())
     }
+
+    fn migrate_compression(&self) -> Result<usize> {
+        let mut migrated = 0;
+
+        for path in self.list_manifest_files()? {
+            if path
+                .extension()
+                .is_some_and(|ext| ext == COMPRESSED_EXTENSION)
+            {
+                continue;
+            }
+
+            match self.read_manifest_file(&path) {
+                Ok(manifest) => {
+                    self.write_manifest_file(&manifest, true)?;
+                    migrated += 1;
+                }
+                Err(e) => {
+                    // Leave unparseable files untouched
+                    eprintln!("Error migrating manifest at {path:?}: {e}");
+                }
+            }
+        }
+
+        Ok(migrated)
+    }
+
+    fn supports_envelopes(&self) -> bool {
+        true
+    }
+
+    fn store_envelope(&self, record: &EnvelopeRecord) -> Result<String> {
+        let path = self.envelope_path(&record.id);
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent)?;
+        }
+
+        let json =
+            serde_json::to_vec_pretty(record).map_err(|e| Error::Serialization(e.to_string()))?;
+        let mut file = safe_create_file(&path, false)?;
+        file.write_all(&json)?;
+
+        Ok(record.id.clone())
+    }
+
+    fn retrieve_envelope(&self, id: &str) -> Result<EnvelopeRecord> {
+        let path = self.envelope_path(id);
+        if !path.exists() {
+            return Err(Error::NotFound(format!("Envelope not found: {id}")));
+        }
+
+        let mut content = Vec::new();
+        safe_open_file(&path, false)?.read_to_end(&mut content)?;
+        serde_json::from_slice(&content)
+            .map_err(|e| Error::Serialization(format!("Failed to parse envelope: {e}")))
+    }
+
+    fn list_envelopes(&self) -> Result<Vec<EnvelopeMetadata>> {
+        let dir = self.base_path.join(ENVELOPE_DIR);
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut envelopes = Vec::new();
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if !path.is_file() || path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+
+            let mut content = Vec::new();
+            safe_open_file(&path, false)?.read_to_end(&mut content)?;
+            match serde_json::from_slice::<EnvelopeRecord>(&content) {
+                Ok(record) => envelopes.push(record.metadata()),
+                Err(e) => {
+                    // Log but don't fail on unparseable envelope
+                    eprintln!("Error parsing envelope at {path:?}: {e}");
+                }
+            }
+        }
+
+        Ok(envelopes)
+    }
+
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
@@ -277,11 +478,9 @@ impl FilesystemStorage {
 
     // Get manifest file size
     pub fn get_manifest_size(&self, id: &str) -> Result<u64> {
-        let path = self.manifest_path(id);
-
-        if !path.exists() {
-            return Err(Error::Storage(format!("Manifest not found: {id}")));
-        }
+        let path = self
+            .existing_manifest_path(id)
+            .ok_or_else(|| Error::NotFound(format!("Manifest not found: {id}")))?;
 
         let metadata = fs::metadata(path)?;
         Ok(metadata.len())
@@ -308,7 +507,7 @@ impl FilesystemStorage {
 
     // Check if a manifest exists
     pub fn manifest_exists(&self, id: &str) -> bool {
-        self.manifest_path(id).exists()
+        self.existing_manifest_path(id).is_some()
     }
 
     // Backup all manifests to a directory
@@ -335,3 +534,11 @@ impl FilesystemStorage {
         Ok(())
     }
 }
+
+// Path of the compressed variant of a manifest file
+fn compressed_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".");
+    name.push(COMPRESSED_EXTENSION);
+    PathBuf::from(name)
+}
@@ -1,13 +1,27 @@
+pub mod auth;
+pub mod chunked;
+pub mod compression;
 pub mod config;
 pub mod database;
+pub mod export;
 pub mod filesystem;
+pub mod pinning;
+pub mod plugin;
+pub mod queue;
+pub mod rate_limit;
 pub mod rekor;
+pub mod retention;
+pub mod s3;
 pub mod traits;
 use crate::error::Result;
 pub use database::DatabaseStorage;
 pub use filesystem::FilesystemStorage;
+pub use plugin::PluginStorage;
 pub use rekor::RekorStorage;
-pub use traits::{ManifestMetadata, ManifestType, StorageBackend};
+pub use s3::S3Storage;
+pub use traits::{
+    EnvelopeKind, EnvelopeMetadata, EnvelopeRecord, ManifestMetadata, ManifestType, StorageBackend,
+};
 
 pub fn initialize_storage() -> Result<RekorStorage> {
     RekorStorage::new()
@@ -18,6 +32,10 @@ pub fn create_storage(storage_type: &str, url: String) -> Result<Box<dyn Storage
         "database" => Ok(Box::new(DatabaseStorage::new(url)?)),
         "rekor" => Ok(Box::new(RekorStorage::new_with_url(url)?)),
         "local-fs" => Ok(Box::new(FilesystemStorage::new(url)?)),
+        "s3" => Ok(Box::new(S3Storage::new(&url)?)),
+        kind if kind.starts_with(plugin::PLUGIN_STORAGE_PREFIX) => {
+            Ok(Box::new(PluginStorage::new(kind, &url)?))
+        }
         // Backwards compatibility with warnings
         "local" => {
             eprintln!(
@@ -32,7 +50,8 @@ pub fn create_storage(storage_type: &str, url: String) -> Result<Box<dyn Storage
             Ok(Box::new(FilesystemStorage::new(url)?))
         }
         _ => Err(crate::error::Error::Validation(
-            "Invalid storage type. Valid options are: database, rekor, local-fs".to_string(),
+            "Invalid storage type. Valid options are: database, rekor, local-fs, s3, plugin:<name>"
+                .to_string(),
         )),
     }
 }
@@ -0,0 +1,420 @@
+//! Trust-on-first-use pinning of storage endpoints.
+//!
+//! The first time a Rekor or database endpoint is used, its identity is recorded in a pin
+//! file: the SHA-256 of the public key of its TLS certificate and, for Rekor, the ID of the
+//! log new entries are added to (itself the SHA-256 of the log's public key). When a later
+//! connection shows a different identity, a warning is printed, or with `--strict` the
+//! request fails. Pins are never replaced automatically; remove the endpoint from the pin
+//! file to accept a new identity.
+//!
+//! TLS keys are checked during the TLS handshake, by the certificate verifier of clients
+//! made with [`client_builder`], so that in strict mode nothing of a request, neither its
+//! body nor its credentials, is sent to an endpoint whose key changed.
+//!
+//! Endpoints reached over plain HTTP have no identity to pin.
+
+use crate::cli::color;
+use crate::error::{Error, Result};
+use crate::hash;
+use crate::http;
+use atlas_c2pa_lib::cose::HashAlgorithm;
+use openssl::x509::X509;
+use reqwest::blocking::{ClientBuilder, RequestBuilder, Response};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::CryptoProvider;
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{DigitallySignedStruct, SignatureScheme};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use time::OffsetDateTime;
+
+/// Environment variable overriding the location of the pin file
+pub const PIN_FILE_ENV: &str = "ATLAS_PIN_FILE";
+
+static STRICT: AtomicBool = AtomicBool::new(false);
+
+// Identities already checked by this process, so the pin file is read once per endpoint.
+// The lock also serializes updates of the pin file by concurrent requests.
+static CHECKED: Mutex<Option<HashSet<(String, PinKind, String)>>> = Mutex::new(None);
+
+// Why the last handshake with an endpoint was refused in strict mode, keyed by endpoint, so
+// that the failed request reports the changed identity instead of a connection error
+static REFUSED: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+
+/// Fails requests to endpoints whose identity changed, instead of warning
+pub fn set_strict(strict: bool) {
+    STRICT.store(strict, Ordering::Relaxed);
+}
+
+/// Whether changed identities fail requests
+pub fn is_strict() -> bool {
+    STRICT.load(Ordering::Relaxed)
+}
+
+/// A pinned part of an endpoint's identity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PinKind {
+    /// SHA-256 of the public key of the endpoint's TLS certificate
+    TlsKey,
+    /// ID of the Rekor log that new entries are added to
+    LogId,
+}
+
+impl fmt::Display for PinKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PinKind::TlsKey => write!(f, "TLS key"),
+            PinKind::LogId => write!(f, "Rekor log ID"),
+        }
+    }
+}
+
+/// Identity recorded for an endpoint
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EndpointPin {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls_key_sha256: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_id: Option<String>,
+    /// When the endpoint was first used (RFC 3339)
+    pub first_seen: String,
+}
+
+impl EndpointPin {
+    fn value_mut(&mut self, kind: PinKind) -> &mut Option<String> {
+        match kind {
+            PinKind::TlsKey => &mut self.tls_key_sha256,
+            PinKind::LogId => &mut self.log_id,
+        }
+    }
+}
+
+/// Result of comparing an identity with the pin file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PinStatus {
+    /// The endpoint was not pinned yet; the identity is now recorded
+    Pinned,
+    Matches,
+    /// The identity differs from the recorded one, which is kept
+    Changed {
+        pinned: String,
+    },
+}
+
+/// Location of the pin file: `ATLAS_PIN_FILE`, or `~/.atlas/pins.json`
+pub fn pin_file() -> PathBuf {
+    match std::env::var_os(PIN_FILE_ENV) {
+        Some(path) => PathBuf::from(path),
+        None => std::env::var_os("HOME")
+            .or_else(|| std::env::var_os("USERPROFILE"))
+            .map(|home| PathBuf::from(home).join(".atlas"))
+            .unwrap_or_default()
+            .join("pins.json"),
+    }
+}
+
+/// Reads the pins of a pin file, keyed by endpoint origin
+pub fn read_pins(path: &Path) -> Result<BTreeMap<String, EndpointPin>> {
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    serde_json::from_slice(&fs::read(path)?)
+        .map_err(|e| Error::Storage(format!("Invalid pin file {}: {e}", path.display())))
+}
+
+/// Compares an identity of an endpoint with the pin file, pinning it on first use
+pub fn check_pin(path: &Path, endpoint: &str, kind: PinKind, value: &str) -> Result<PinStatus> {
+    let mut pins = read_pins(path)?;
+    let pin = pins
+        .entry(endpoint.to_string())
+        .or_insert_with(|| EndpointPin {
+            first_seen: OffsetDateTime::now_utc()
+                .format(&time::format_description::well_known::Rfc3339)
+                .unwrap_or_default(),
+            ..Default::default()
+        });
+
+    let status = match pin.value_mut(kind) {
+        Some(pinned) if pinned == value => return Ok(PinStatus::Matches),
+        Some(pinned) => {
+            return Ok(PinStatus::Changed {
+                pinned: pinned.clone(),
+            });
+        }
+        slot => {
+            *slot = Some(value.to_string());
+            PinStatus::Pinned
+        }
+    };
+
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        fs::create_dir_all(parent)?;
+    }
+    write_pins(path, &pins)?;
+    Ok(status)
+}
+
+// Replaces the pin file through a temporary file in the same directory, so that a crash or
+// a concurrent writer never leaves a truncated pin file behind
+fn write_pins(path: &Path, pins: &BTreeMap<String, EndpointPin>) -> Result<()> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(format!(".{}.tmp", std::process::id()));
+    let temporary = PathBuf::from(temporary);
+    let mut file = fs::File::create(&temporary)?;
+    file.write_all(&serde_json::to_vec_pretty(pins)?)?;
+    file.sync_all()?;
+    fs::rename(&temporary, path).inspect_err(|_| {
+        let _ = fs::remove_file(&temporary);
+    })?;
+    Ok(())
+}
+
+/// Checks an identity of an endpoint against the default pin file, warning about or, in
+/// strict mode, failing on a changed identity
+pub fn verify_identity(endpoint: &str, kind: PinKind, value: &str) -> Result<()> {
+    if !endpoint.starts_with("https://") {
+        return Ok(());
+    }
+
+    let mut checked = CHECKED.lock().unwrap_or_else(|e| e.into_inner());
+    let key = (endpoint.to_string(), kind, value.to_string());
+    if checked
+        .as_ref()
+        .is_some_and(|checked| checked.contains(&key))
+    {
+        return Ok(());
+    }
+
+    let path = pin_file();
+    match check_pin(&path, endpoint, kind, value)? {
+        PinStatus::Pinned | PinStatus::Matches => {}
+        PinStatus::Changed { pinned } => {
+            let message = format!(
+                "{kind} of {endpoint} changed since it was first used: pinned {pinned}, now {value}. \
+                 The endpoint may have been substituted. If the change is expected, remove \
+                 {endpoint} from {}",
+                path.display()
+            );
+            if is_strict() {
+                return Err(Error::Validation(message));
+            }
+            eprintln!("{} WARNING: {message}", color::warning_sign());
+        }
+    }
+    checked.get_or_insert_with(HashSet::new).insert(key);
+    Ok(())
+}
+
+/// Origin of a URL, such as `https://rekor.sigstore.dev:443`, identifying an endpoint
+pub fn endpoint(url: &reqwest::Url) -> String {
+    url.origin().ascii_serialization()
+}
+
+/// Pinned identity of a DER-encoded TLS certificate: the SHA-256 of its public key
+pub fn tls_key_fingerprint(certificate: &[u8]) -> Result<String> {
+    let key = X509::from_der(certificate)
+        .and_then(|certificate| certificate.public_key())
+        .and_then(|key| key.public_key_to_der())
+        .map_err(|e| Error::Storage(format!("Invalid TLS certificate: {e}")))?;
+    Ok(format!(
+        "sha256:{}",
+        hash::calculate_hash_with_algorithm(&key, &HashAlgorithm::Sha256)
+    ))
+}
+
+// Verifies certificates like the platform verifier, then checks the TLS key of the pinned
+// endpoint against its pin. Hosts the endpoint redirects to are only verified.
+#[derive(Debug)]
+struct PinningVerifier {
+    inner: rustls_platform_verifier::Verifier,
+    endpoint: String,
+    host: String,
+}
+
+impl ServerCertVerifier for PinningVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> std::result::Result<ServerCertVerified, rustls::Error> {
+        let verified = self.inner.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            ocsp_response,
+            now,
+        )?;
+        if server_name.to_str() != self.host {
+            return Ok(verified);
+        }
+
+        match tls_key_fingerprint(end_entity)
+            .and_then(|fingerprint| verify_identity(&self.endpoint, PinKind::TlsKey, &fingerprint))
+        {
+            Ok(()) => Ok(verified),
+            Err(Error::Validation(message)) => {
+                REFUSED
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .insert(self.endpoint.clone(), message.clone());
+                Err(rustls::Error::General(message))
+            }
+            Err(e) => Err(rustls::Error::General(e.to_string())),
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+/// Client builder for a storage endpoint at `url`, with the user agent and proxy of all
+/// requests. Over HTTPS, the TLS key of the endpoint is checked against its pin during the
+/// handshake, see [`verify_identity`].
+pub fn client_builder(url: &str) -> Result<ClientBuilder> {
+    let builder = http::client_builder();
+    let Some(url) = reqwest::Url::parse(url)
+        .ok()
+        .filter(|url| url.scheme() == "https")
+    else {
+        return Ok(builder);
+    };
+
+    // The provider reqwest would pick itself
+    let provider = CryptoProvider::get_default()
+        .cloned()
+        .unwrap_or_else(|| Arc::new(rustls::crypto::aws_lc_rs::default_provider()));
+    let tls_error = |e: rustls::Error| Error::Storage(format!("Failed to configure TLS: {e}"));
+    let verifier = PinningVerifier {
+        inner: rustls_platform_verifier::Verifier::new(provider.clone()).map_err(tls_error)?,
+        endpoint: endpoint(&url),
+        // Bracketed IPv6 addresses are compared without their brackets
+        host: url
+            .host_str()
+            .unwrap_or_default()
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_string(),
+    };
+
+    let mut config = rustls::ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(tls_error)?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(verifier))
+        .with_no_client_auth();
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(builder.tls_backend_preconfigured(config))
+}
+
+/// Sends storage requests to endpoints whose TLS key is checked against its pin
+pub(crate) trait SendPinned {
+    /// Sends the request, reporting failures as storage errors with `context`, and a
+    /// handshake refused over a changed identity as a validation error
+    fn send_pinned(self, context: &str) -> Result<Response>;
+}
+
+impl SendPinned for RequestBuilder {
+    fn send_pinned(self, context: &str) -> Result<Response> {
+        self.send().map_err(|e| {
+            let refused = e.url().and_then(|url| {
+                REFUSED
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .remove(&endpoint(url))
+            });
+            match refused {
+                // Not worth retrying, unlike other failed connections
+                Some(message) => Error::Validation(message),
+                None => Error::storage_request(context, e),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_pin() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("pins.json");
+        let endpoint = "https://rekor.example:443";
+
+        assert_eq!(
+            check_pin(&path, endpoint, PinKind::TlsKey, "sha256:aa")?,
+            PinStatus::Pinned
+        );
+        assert_eq!(
+            check_pin(&path, endpoint, PinKind::TlsKey, "sha256:aa")?,
+            PinStatus::Matches
+        );
+        // Each kind of identity is pinned on its own
+        assert_eq!(
+            check_pin(&path, endpoint, PinKind::LogId, "c0d23d")?,
+            PinStatus::Pinned
+        );
+        assert_eq!(
+            check_pin(&path, endpoint, PinKind::TlsKey, "sha256:bb")?,
+            PinStatus::Changed {
+                pinned: "sha256:aa".to_string()
+            }
+        );
+
+        // A changed identity doesn't replace the pin
+        let pins = read_pins(&path)?;
+        assert_eq!(pins[endpoint].tls_key_sha256.as_deref(), Some("sha256:aa"));
+        assert_eq!(pins[endpoint].log_id.as_deref(), Some("c0d23d"));
+
+        // The pin file is replaced whole, leaving no temporary file behind
+        assert_eq!(fs::read_dir(dir.path())?.count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pinned_clients_build() {
+        // reqwest rejects TLS configurations of another rustls version only when building
+        for url in [
+            "https://rekor.example",
+            "https://[::1]:8443",
+            "http://localhost:8080",
+        ] {
+            let client = client_builder(url).and_then(|builder| {
+                builder
+                    .build()
+                    .map_err(|e| Error::Storage(format!("Failed to create HTTP client: {e}")))
+            });
+            assert!(client.is_ok(), "{url}: {:?}", client.err());
+        }
+    }
+}
@@ -0,0 +1,382 @@
+//! Storage backends implemented by external programs.
+//!
+//! `--storage-type=plugin:<name>` hands every storage operation to an `atlas-storage-<name>`
+//! executable, looked up in the directories of `ATLAS_STORAGE_PLUGIN_PATH` and then of
+//! `PATH`, much like git finds its credential helpers. Manifests can so be kept in
+//! registries atlas-cli knows nothing about, without recompiling it.
+//!
+//! The helper is run once per operation, with the operation as its only argument: `store`,
+//! `retrieve`, `list`, `delete`, `capabilities`, `store-envelope`, `retrieve-envelope` or
+//! `list-envelopes`. It reads one JSON request from stdin and writes one JSON response to
+//! stdout. Every request carries the protocol version and the storage URL, plus the
+//! operation's parameters:
+//!
+//! | Operation | Request | Response |
+//! |---|---|---|
+//! | `store` | `manifest` | `id` |
+//! | `retrieve` | `id` | `manifest` |
+//! | `list` | | `manifests`: list of `id`, `name`, `manifest_type`, `created_at` |
+//! | `delete` | `id` | `{}` |
+//! | `capabilities` | | `envelopes`: whether envelopes can be stored |
+//! | `store-envelope` | `envelope` | `id` |
+//! | `retrieve-envelope` | `id` | `envelope` |
+//! | `list-envelopes` | | `envelopes`: list of envelope metadata |
+//!
+//! Failures are reported as `{"error": {"kind": "not_found", "message": "..."}}`, where
+//! `kind` is `not_found`, `unauthorized`, `unavailable` or `other`. A helper exiting with a
+//! non-zero status without such a response fails the operation with its stderr.
+//!
+//! `store` may be sent again after a failure, so helpers should store a manifest they
+//! already hold under the same ID, see [`StorageBackend::store_manifest`].
+
+use crate::error::{Error, Result};
+use crate::storage::traits::{EnvelopeMetadata, EnvelopeRecord, ManifestMetadata, StorageBackend};
+use atlas_c2pa_lib::manifest::Manifest;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+
+/// Prefix of storage types naming a plugin, as in `plugin:<name>`
+pub const PLUGIN_STORAGE_PREFIX: &str = "plugin:";
+
+/// Prefix of the executable names of storage plugins
+pub const PLUGIN_EXECUTABLE_PREFIX: &str = "atlas-storage-";
+
+/// Environment variable with directories searched for plugins before `PATH`
+pub const PLUGIN_PATH_ENV: &str = "ATLAS_STORAGE_PLUGIN_PATH";
+
+/// Version of the protocol spoken with plugins
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Returns the plugin name of a `plugin:<name>` storage type
+///
+/// # Examples
+///
+/// ```
+/// use atlas_cli::storage::plugin::plugin_name;
+///
+/// assert_eq!(plugin_name("plugin:artifactory"), Some("artifactory"));
+/// assert_eq!(plugin_name("plugin:"), None);
+/// assert_eq!(plugin_name("database"), None);
+/// ```
+pub fn plugin_name(storage_type: &str) -> Option<&str> {
+    storage_type
+        .strip_prefix(PLUGIN_STORAGE_PREFIX)
+        .filter(|name| !name.is_empty())
+}
+
+/// A storage plugin found on the search path
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DiscoveredPlugin {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+// Directories searched for plugins, in order
+fn search_path() -> Vec<PathBuf> {
+    [PLUGIN_PATH_ENV, "PATH"]
+        .iter()
+        .filter_map(std::env::var_os)
+        .flat_map(|paths| std::env::split_paths(&paths).collect::<Vec<_>>())
+        .collect()
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// Lists the storage plugins found in `ATLAS_STORAGE_PLUGIN_PATH` and `PATH`, by name.
+/// Where several directories hold a plugin of the same name, the first one is used.
+pub fn discover() -> Vec<DiscoveredPlugin> {
+    discover_in(&search_path())
+}
+
+fn discover_in(directories: &[PathBuf]) -> Vec<DiscoveredPlugin> {
+    let mut plugins = BTreeMap::new();
+    for directory in directories {
+        let Ok(entries) = std::fs::read_dir(directory) else {
+            continue;
+        };
+        let mut found: Vec<_> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let file_name = entry.file_name().into_string().ok()?;
+                let name = file_name
+                    .strip_suffix(std::env::consts::EXE_SUFFIX)
+                    .unwrap_or(&file_name)
+                    .strip_prefix(PLUGIN_EXECUTABLE_PREFIX)?
+                    .to_string();
+                let path = entry.path();
+                (!name.is_empty() && is_executable(&path)).then_some((name, path))
+            })
+            .collect();
+        found.sort();
+        for (name, path) in found {
+            plugins.entry(name).or_insert(path);
+        }
+    }
+
+    plugins
+        .into_iter()
+        .map(|(name, path)| DiscoveredPlugin { name, path })
+        .collect()
+}
+
+/// Finds the executable of the plugin `name`
+pub fn find_plugin(name: &str) -> Result<PathBuf> {
+    let file_name = format!(
+        "{PLUGIN_EXECUTABLE_PREFIX}{name}{}",
+        std::env::consts::EXE_SUFFIX
+    );
+    search_path()
+        .into_iter()
+        .map(|directory| directory.join(&file_name))
+        .find(|path| is_executable(path))
+        .ok_or_else(|| {
+            Error::Validation(format!(
+                "Storage plugin '{name}' not found: no executable {file_name} in {PLUGIN_PATH_ENV} or PATH"
+            ))
+        })
+}
+
+/// Kind of a failure reported by a plugin
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum FailureKind {
+    NotFound,
+    Unauthorized,
+    Unavailable,
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct Failure {
+    kind: FailureKind,
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct FailureResponse {
+    error: Failure,
+}
+
+#[derive(Deserialize)]
+struct IdResponse {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct ManifestResponse {
+    manifest: Manifest,
+}
+
+#[derive(Deserialize)]
+struct ManifestsResponse {
+    manifests: Vec<ManifestMetadata>,
+}
+
+#[derive(Deserialize)]
+struct CapabilitiesResponse {
+    #[serde(default)]
+    envelopes: bool,
+}
+
+#[derive(Deserialize)]
+struct EnvelopeResponse {
+    envelope: EnvelopeRecord,
+}
+
+#[derive(Deserialize)]
+struct EnvelopesResponse {
+    envelopes: Vec<EnvelopeMetadata>,
+}
+
+/// Storage backend implemented by an `atlas-storage-<name>` executable
+#[derive(Debug)]
+pub struct PluginStorage {
+    name: String,
+    executable: PathBuf,
+    url: String,
+    envelopes: OnceLock<bool>,
+}
+
+impl PluginStorage {
+    /// Creates the backend of a `plugin:<name>` storage type, finding its executable
+    pub fn new(storage_type: &str, url: &str) -> Result<Self> {
+        let name = plugin_name(storage_type).ok_or_else(|| {
+            Error::Validation(format!(
+                "Invalid storage type '{storage_type}'; plugins are named plugin:<name>"
+            ))
+        })?;
+        Ok(Self::with_executable(name, find_plugin(name)?, url))
+    }
+
+    /// Creates a backend running `executable` as the plugin `name`
+    pub fn with_executable(name: &str, executable: impl Into<PathBuf>, url: &str) -> Self {
+        PluginStorage {
+            name: name.to_string(),
+            executable: executable.into(),
+            url: url.to_string(),
+            envelopes: OnceLock::new(),
+        }
+    }
+
+    // Runs the plugin for one operation
+    fn call<T: DeserializeOwned>(&self, operation: &str, mut request: Value) -> Result<T> {
+        request["version"] = json!(PROTOCOL_VERSION);
+        request["url"] = json!(self.url);
+        let input = serde_json::to_vec(&request)?;
+
+        let mut child = Command::new(&self.executable)
+            .arg(operation)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| {
+                Error::Storage(format!(
+                    "Failed to run storage plugin {} ({}): {e}",
+                    self.name,
+                    self.executable.display()
+                ))
+            })?;
+        // Written from another thread, so a plugin answering before it read the whole
+        // request can't block on a full stdout pipe
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let writer = std::thread::spawn(move || stdin.write_all(&input));
+        let output = child.wait_with_output()?;
+        // A plugin may exit without reading a request it doesn't need
+        let _ = writer.join();
+
+        if let Ok(FailureResponse { error }) = serde_json::from_slice(&output.stdout) {
+            let message = format!("Storage plugin {}: {}", self.name, error.message);
+            return Err(match error.kind {
+                FailureKind::NotFound => Error::NotFound(message),
+                FailureKind::Unauthorized => Error::Unauthorized(message),
+                FailureKind::Unavailable => Error::StorageUnavailable(message),
+                FailureKind::Other => Error::Storage(message),
+            });
+        }
+        if !output.status.success() {
+            return Err(Error::Storage(format!(
+                "Storage plugin {} failed to {operation} ({}): {}",
+                self.name,
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+
+        serde_json::from_slice(&output.stdout).map_err(|e| {
+            Error::Storage(format!(
+                "Invalid response of storage plugin {} to {operation}: {e}",
+                self.name
+            ))
+        })
+    }
+}
+
+impl StorageBackend for PluginStorage {
+    fn get_base_uri(&self) -> String {
+        self.url.clone()
+    }
+
+    fn store_manifest(&self, manifest: &Manifest) -> Result<String> {
+        let response: IdResponse = self.call("store", json!({ "manifest": manifest }))?;
+        Ok(response.id)
+    }
+
+    fn retrieve_manifest(&self, id: &str) -> Result<Manifest> {
+        let response: ManifestResponse = self.call("retrieve", json!({ "id": id }))?;
+        Ok(response.manifest)
+    }
+
+    fn list_manifests(&self) -> Result<Vec<ManifestMetadata>> {
+        let response: ManifestsResponse = self.call("list", json!({}))?;
+        Ok(response.manifests)
+    }
+
+    fn delete_manifest(&self, id: &str) -> Result<()> {
+        self.call::<Value>("delete", json!({ "id": id }))?;
+        Ok(())
+    }
+
+    fn supports_envelopes(&self) -> bool {
+        // Plugins that don't know the operation have no envelope support
+        *self.envelopes.get_or_init(|| {
+            self.call::<CapabilitiesResponse>("capabilities", json!({}))
+                .is_ok_and(|capabilities| capabilities.envelopes)
+        })
+    }
+
+    fn store_envelope(&self, record: &EnvelopeRecord) -> Result<String> {
+        let response: IdResponse = self.call("store-envelope", json!({ "envelope": record }))?;
+        Ok(response.id)
+    }
+
+    fn retrieve_envelope(&self, id: &str) -> Result<EnvelopeRecord> {
+        let response: EnvelopeResponse = self.call("retrieve-envelope", json!({ "id": id }))?;
+        Ok(response.envelope)
+    }
+
+    fn list_envelopes(&self) -> Result<Vec<EnvelopeMetadata>> {
+        let response: EnvelopesResponse = self.call("list-envelopes", json!({}))?;
+        Ok(response.envelopes)
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn test_discover_plugins() -> Result<()> {
+        let first = tempfile::tempdir()?;
+        let second = tempfile::tempdir()?;
+        let write = |directory: &Path, name: &str, mode: u32| -> Result<PathBuf> {
+            let path = directory.join(name);
+            std::fs::write(&path, "#!/bin/sh\n")?;
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode))?;
+            Ok(path)
+        };
+        let registry = write(first.path(), "atlas-storage-registry", 0o755)?;
+        write(first.path(), "atlas-storage-disabled", 0o644)?;
+        write(second.path(), "atlas-storage-registry", 0o755)?;
+        let s3 = write(second.path(), "atlas-storage-s3", 0o755)?;
+        write(second.path(), "atlas-cli", 0o755)?;
+
+        let plugins = discover_in(&[first.path().to_path_buf(), second.path().to_path_buf()]);
+        assert_eq!(
+            plugins,
+            vec![
+                DiscoveredPlugin {
+                    name: "registry".to_string(),
+                    path: registry,
+                },
+                DiscoveredPlugin {
+                    name: "s3".to_string(),
+                    path: s3,
+                },
+            ]
+        );
+        Ok(())
+    }
+}
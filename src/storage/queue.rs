@@ -0,0 +1,263 @@
+//! Store-and-forward queue for manifests created while storage is unreachable.
+//!
+//! With `--offline-queue` or `ATLAS_OFFLINE_QUEUE=1`, a manifest whose storage backend can't
+//! be reached, such as on an air-gapped training node, is written to a local spool instead
+//! of failing the command. `queue flush` later stores the queued manifests in the order they
+//! were created. Manifests are queued as created and signed, so they keep their IDs and
+//! creation times; backends recording a store time, such as Rekor, record the flush.
+//!
+//! The spool is `ATLAS_QUEUE_DIR`, or `~/.atlas/queue`. Each manifest is one JSON file named
+//! after its position in the queue, written to a temporary file and synced before it is
+//! linked into place, so a crash never leaves a partial entry.
+
+use crate::error::{Error, Result};
+use crate::manifest::timestamp::to_rfc3339_utc;
+use crate::storage::traits::StorageBackend;
+use atlas_c2pa_lib::manifest::Manifest;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use time::OffsetDateTime;
+
+/// Environment variable that enables the queue
+pub const OFFLINE_QUEUE_ENV: &str = "ATLAS_OFFLINE_QUEUE";
+
+/// Environment variable naming the spool directory
+pub const QUEUE_DIR_ENV: &str = "ATLAS_QUEUE_DIR";
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Queues manifests whose storage is unreachable for the rest of the process
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Whether the queue is on, through [`enable`] or `ATLAS_OFFLINE_QUEUE`
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+        || std::env::var(OFFLINE_QUEUE_ENV)
+            .is_ok_and(|value| matches!(value.as_str(), "1" | "true"))
+}
+
+/// Location of the spool: `ATLAS_QUEUE_DIR`, or `~/.atlas/queue`
+pub fn queue_dir() -> PathBuf {
+    match std::env::var_os(QUEUE_DIR_ENV) {
+        Some(path) => PathBuf::from(path),
+        None => std::env::var_os("HOME")
+            .or_else(|| std::env::var_os("USERPROFILE"))
+            .map(|home| PathBuf::from(home).join(".atlas"))
+            .unwrap_or_default()
+            .join("queue"),
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    target: String,
+    queued_at: String,
+    manifest: Manifest,
+}
+
+/// A manifest waiting in the queue
+#[derive(Debug, Clone)]
+pub struct QueuedManifest {
+    /// Position in the queue
+    pub seq: u64,
+    /// Base URI of the storage backend the manifest was meant for
+    pub target: String,
+    pub queued_at: String,
+    pub manifest_id: String,
+    pub path: PathBuf,
+}
+
+/// Outcome of [`OfflineQueue::flush`]
+#[derive(Debug, Default)]
+pub struct FlushReport {
+    /// Queued manifest IDs with the IDs they were stored under, in queue order
+    pub stored: Vec<(String, String)>,
+    /// The manifest that failed to store, with the error; it and the manifests queued after
+    /// it stay queued
+    pub failed: Option<(String, Error)>,
+    /// Manifests left queued because they are meant for other storage
+    pub other_targets: usize,
+}
+
+/// A spool of manifests waiting to be stored
+pub struct OfflineQueue {
+    dir: PathBuf,
+}
+
+impl OfflineQueue {
+    /// Opens the spool at [`queue_dir`]
+    pub fn open_default() -> Result<Self> {
+        Self::new(queue_dir())
+    }
+
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(OfflineQueue { dir })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Queues a manifest meant for the storage backend with base URI `target`
+    pub fn enqueue(&self, target: &str, manifest: &Manifest) -> Result<QueuedManifest> {
+        let entry = Entry {
+            target: target.to_string(),
+            queued_at: to_rfc3339_utc(OffsetDateTime::now_utc()),
+            manifest: manifest.clone(),
+        };
+        let tmp = self
+            .dir
+            .join(format!(".{}.tmp", uuid::Uuid::new_v4().simple()));
+        let mut file = File::create(&tmp)?;
+        file.write_all(&serde_json::to_vec_pretty(&entry)?)?;
+        file.sync_all()?;
+        drop(file);
+
+        // Linking fails if another process took the position first, so try the next one
+        let mut seq = self.entries()?.last().map_or(1, |last| last.seq + 1);
+        let path = loop {
+            let path = self.dir.join(format!("{seq:012}.json"));
+            match fs::hard_link(&tmp, &path) {
+                Ok(()) => break path,
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => seq += 1,
+                Err(e) => {
+                    let _ = fs::remove_file(&tmp);
+                    return Err(e.into());
+                }
+            }
+        };
+        fs::remove_file(&tmp)?;
+
+        Ok(QueuedManifest {
+            seq,
+            target: entry.target,
+            queued_at: entry.queued_at,
+            manifest_id: manifest.instance_id.clone(),
+            path,
+        })
+    }
+
+    /// Lists the queued manifests in queue order
+    pub fn entries(&self) -> Result<Vec<QueuedManifest>> {
+        let mut entries = Vec::new();
+        for file in fs::read_dir(&self.dir)? {
+            let path = file?.path();
+            let Some(seq) = path
+                .file_name()
+                .and_then(|name| name.to_str()?.strip_suffix(".json")?.parse::<u64>().ok())
+            else {
+                continue;
+            };
+            let entry = read_entry(&path)?;
+            entries.push(QueuedManifest {
+                seq,
+                target: entry.target,
+                queued_at: entry.queued_at,
+                manifest_id: entry.manifest.instance_id,
+                path,
+            });
+        }
+        entries.sort_by_key(|entry| entry.seq);
+        Ok(entries)
+    }
+
+    /// Stores the queued manifests meant for `storage`, or all of them with `all_targets`,
+    /// in queue order. Stops at the first manifest that fails to store, so that manifests
+    /// are never stored out of order.
+    pub fn flush(&self, storage: &dyn StorageBackend, all_targets: bool) -> Result<FlushReport> {
+        let target = storage.get_base_uri();
+        let mut report = FlushReport::default();
+        for queued in self.entries()? {
+            if !all_targets && queued.target != target {
+                report.other_targets += 1;
+                continue;
+            }
+            let manifest = read_entry(&queued.path)?.manifest;
+            match storage.store_manifest(&manifest) {
+                Ok(id) => {
+                    fs::remove_file(&queued.path)?;
+                    report.stored.push((queued.manifest_id, id));
+                }
+                Err(e) => {
+                    report.failed = Some((queued.manifest_id, e));
+                    break;
+                }
+            }
+        }
+        Ok(report)
+    }
+}
+
+fn read_entry(path: &Path) -> Result<Entry> {
+    serde_json::from_slice(&fs::read(path)?)
+        .map_err(|e| Error::Serialization(format!("Invalid queue entry {}: {e}", path.display())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::filesystem::FilesystemStorage;
+    use atlas_c2pa_lib::datetime_wrapper::OffsetDateTimeWrapper;
+    use time::macros::datetime;
+
+    fn manifest(id: &str) -> Manifest {
+        Manifest {
+            claim_generator: "test".to_string(),
+            title: id.to_string(),
+            instance_id: format!("urn:c2pa:{}", uuid::Uuid::new_v4()),
+            ingredients: vec![],
+            claim: atlas_c2pa_lib::claim::ClaimV2 {
+                instance_id: format!("urn:c2pa:{}", uuid::Uuid::new_v4()),
+                ingredients: vec![],
+                created_assertions: vec![],
+                claim_generator_info: "test".to_string(),
+                signature: None,
+                created_at: OffsetDateTimeWrapper(datetime!(2024-03-01 09:30 UTC)),
+            },
+            created_at: OffsetDateTimeWrapper(datetime!(2024-03-01 09:30 UTC)),
+            cross_references: vec![],
+            claim_v2: None,
+            is_active: true,
+        }
+    }
+
+    #[test]
+    fn test_queue_and_flush() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let queue = OfflineQueue::new(dir.path().join("queue"))?;
+        let storage = FilesystemStorage::new(dir.path().join("storage"))?;
+        let target = storage.get_base_uri();
+
+        let first = queue.enqueue(&target, &manifest("first"))?;
+        queue.enqueue("https://elsewhere.example", &manifest("other"))?;
+        let second = queue.enqueue(&target, &manifest("second"))?;
+        let queued: Vec<u64> = queue.entries()?.iter().map(|entry| entry.seq).collect();
+        assert_eq!(queued, vec![1, 2, 3]);
+
+        let report = queue.flush(&storage, false)?;
+        assert!(report.failed.is_none());
+        assert_eq!(report.other_targets, 1);
+        let stored: Vec<&str> = report.stored.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(
+            stored,
+            vec![first.manifest_id.as_str(), second.manifest_id.as_str()]
+        );
+        assert_eq!(queue.entries()?.len(), 1);
+
+        // Queued manifests keep their creation time
+        let metadata = storage.list_manifests()?;
+        assert!(
+            metadata
+                .iter()
+                .all(|metadata| metadata.created_at == "2024-03-01T09:30:00Z")
+        );
+        Ok(())
+    }
+}
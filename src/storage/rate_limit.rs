@@ -0,0 +1,250 @@
+//! Client-side rate limiting of storage requests.
+//!
+//! Public services such as the Sigstore Rekor instance throttle clients that send too many
+//! requests, which bulk operations easily do. A [`RateLimiter`] spaces requests out with a
+//! token bucket: up to `burst` requests go out at once, after which they are sent at the
+//! sustained rate. Requests answered with 429 or 503 are retried after the delay given in
+//! their `Retry-After` header, during which every other request to the service waits too.
+
+use crate::error::{Error, Result};
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+use time::PrimitiveDateTime;
+use time::macros::format_description;
+
+/// Environment variable overriding the sustained rate of Rekor requests per second;
+/// `0` disables the limit
+pub const REKOR_RATE_LIMIT_ENV: &str = "REKOR_RATE_LIMIT";
+
+/// Sustained rate of Rekor requests per second unless configured otherwise
+pub const DEFAULT_REKOR_REQUESTS_PER_SECOND: f64 = 10.0;
+
+// Delay before retrying a throttled request that doesn't say how long to wait,
+// doubling with each retry
+const DEFAULT_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// How fast requests may be sent to a storage service
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimit {
+    /// Sustained rate of requests per second; unlimited if `None`
+    pub requests_per_second: Option<f64>,
+    /// Requests that may be sent at once after a quiet period
+    pub burst: u32,
+    /// Times a request answered with 429 or 503 is retried
+    pub max_retries: u32,
+    /// Longest `Retry-After` delay waited for; throttled requests asking for longer fail
+    pub max_retry_after: Duration,
+}
+
+impl RateLimit {
+    /// No limit on the request rate; throttled requests are still retried
+    pub fn unlimited() -> Self {
+        RateLimit {
+            requests_per_second: None,
+            burst: 1,
+            max_retries: 3,
+            max_retry_after: Duration::from_secs(60),
+        }
+    }
+
+    /// At most `rate` requests per second, in bursts of up to one second's worth; no limit
+    /// if `rate` is zero
+    pub fn per_second(rate: f64) -> Self {
+        RateLimit {
+            requests_per_second: (rate > 0.0).then_some(rate),
+            burst: rate.ceil().max(1.0) as u32,
+            ..Self::unlimited()
+        }
+    }
+
+    /// Limit of Rekor requests: `REKOR_RATE_LIMIT` requests per second, or
+    /// [`DEFAULT_REKOR_REQUESTS_PER_SECOND`]
+    pub fn rekor_from_env() -> Result<Self> {
+        let Ok(value) = std::env::var(REKOR_RATE_LIMIT_ENV) else {
+            return Ok(Self::per_second(DEFAULT_REKOR_REQUESTS_PER_SECOND));
+        };
+        let rate: f64 = value
+            .trim()
+            .parse()
+            .ok()
+            .filter(|rate: &f64| rate.is_finite() && *rate >= 0.0)
+            .ok_or_else(|| {
+                Error::Validation(format!(
+                    "{REKOR_RATE_LIMIT_ENV} must be a number of requests per second, got {value}"
+                ))
+            })?;
+        Ok(Self::per_second(rate))
+    }
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        Self::unlimited()
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    /// Requests wait until then, after a service asked to retry later
+    paused_until: Option<Instant>,
+}
+
+/// Spaces out requests to a service according to a [`RateLimit`]
+#[derive(Debug)]
+pub struct RateLimiter {
+    limit: RateLimit,
+    bucket: Mutex<Bucket>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: RateLimit) -> Self {
+        let bucket = Bucket {
+            tokens: limit.burst.max(1) as f64,
+            updated: Instant::now(),
+            paused_until: None,
+        };
+        RateLimiter {
+            limit,
+            bucket: Mutex::new(bucket),
+        }
+    }
+
+    /// Waits until a request may be sent
+    pub fn acquire(&self) {
+        let wait = self.reserve(Instant::now());
+        if !wait.is_zero() {
+            std::thread::sleep(wait);
+        }
+    }
+
+    /// Holds back all requests for `delay`, as asked by the service
+    pub fn pause(&self, delay: Duration) {
+        let until = Instant::now() + delay;
+        let mut bucket = self.bucket.lock().unwrap();
+        bucket.paused_until = Some(
+            bucket
+                .paused_until
+                .map_or(until, |paused| paused.max(until)),
+        );
+    }
+
+    // Takes a token for a request made at `now`, returning how long the request must wait
+    // for it. Tokens may be taken ahead of time, so concurrent callers queue up.
+    fn reserve(&self, now: Instant) -> Duration {
+        let mut bucket = self.bucket.lock().unwrap();
+        let paused = bucket
+            .paused_until
+            .map_or(Duration::ZERO, |until| until.saturating_duration_since(now));
+        let Some(rate) = self.limit.requests_per_second else {
+            return paused;
+        };
+
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(self.limit.burst.max(1) as f64);
+        bucket.updated = bucket.updated.max(now);
+        bucket.tokens -= 1.0;
+        let throttled = if bucket.tokens < 0.0 {
+            Duration::from_secs_f64(-bucket.tokens / rate)
+        } else {
+            Duration::ZERO
+        };
+        throttled.max(paused)
+    }
+
+    /// How long to wait before retrying a request throttled for the `retry`th time.
+    ///
+    /// Returns `None` if the request is not to be retried: it was retried often enough, or
+    /// the service asked for a longer wait than the limit allows.
+    pub fn retry_delay(&self, headers: &HeaderMap, retry: u32) -> Option<Duration> {
+        if retry >= self.limit.max_retries {
+            return None;
+        }
+        let delay = retry_after(headers, SystemTime::now())
+            .unwrap_or(DEFAULT_RETRY_DELAY * 2u32.saturating_pow(retry));
+        (delay <= self.limit.max_retry_after).then_some(delay)
+    }
+}
+
+/// Parses a `Retry-After` header, given either in seconds or as an HTTP date
+pub fn retry_after(headers: &HeaderMap, now: SystemTime) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let format = format_description!(
+        "[weekday repr:short], [day] [month repr:short] [year] [hour]:[minute]:[second] GMT"
+    );
+    let date: SystemTime = PrimitiveDateTime::parse(value, format)
+        .ok()?
+        .assume_utc()
+        .into();
+    Some(date.duration_since(now).unwrap_or(Duration::ZERO))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_rate_limiter() {
+        let limiter = RateLimiter::new(RateLimit {
+            requests_per_second: Some(2.0),
+            burst: 2,
+            ..RateLimit::unlimited()
+        });
+        let start = Instant::now();
+
+        // The burst goes out at once, then requests are spaced by half a second
+        assert_eq!(limiter.reserve(start), Duration::ZERO);
+        assert_eq!(limiter.reserve(start), Duration::ZERO);
+        assert_eq!(limiter.reserve(start), Duration::from_millis(500));
+        assert_eq!(limiter.reserve(start), Duration::from_secs(1));
+        // Tokens come back over time
+        assert_eq!(
+            limiter.reserve(start + Duration::from_secs(3)),
+            Duration::ZERO
+        );
+
+        let unlimited = RateLimiter::new(RateLimit::unlimited());
+        for _ in 0..100 {
+            assert_eq!(unlimited.reserve(Instant::now()), Duration::ZERO);
+        }
+        unlimited.pause(Duration::from_secs(30));
+        assert!(unlimited.reserve(Instant::now()) > Duration::from_secs(29));
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(784_111_777);
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers, now), None);
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("120"));
+        assert_eq!(retry_after(&headers, now), Some(Duration::from_secs(120)));
+
+        // Sun, 06 Nov 1994 08:49:37 GMT is 784111777 seconds after the epoch
+        headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from_static("Sun, 06 Nov 1994 08:50:07 GMT"),
+        );
+        assert_eq!(retry_after(&headers, now), Some(Duration::from_secs(30)));
+
+        let limiter = RateLimiter::new(RateLimit {
+            max_retry_after: Duration::from_secs(60),
+            ..RateLimit::unlimited()
+        });
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("3600"));
+        assert_eq!(limiter.retry_delay(&headers, 0), None);
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("5"));
+        assert_eq!(
+            limiter.retry_delay(&headers, 0),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(limiter.retry_delay(&headers, 3), None);
+    }
+}
@@ -1,12 +1,108 @@
 use crate::error::{Error, Result};
+use crate::hash;
+use crate::manifest::canonical;
+use crate::signing;
+use crate::storage::chunked;
+use crate::storage::pinning::{self, PinKind, SendPinned};
+use crate::storage::rate_limit::{RateLimit, RateLimiter};
 use crate::storage::traits::{ManifestMetadata, StorageBackend};
+use atlas_c2pa_lib::cose::HashAlgorithm;
 use atlas_c2pa_lib::manifest::Manifest;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use openssl::pkey::PKey;
+use openssl::x509::X509;
 use reqwest;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Environment variable with the path to the PEM public key of the manifest signer
+pub const REKOR_PUBLIC_KEY_ENV: &str = "REKOR_PUBLIC_KEY";
+
+/// Environment variable overriding where log entries of stored manifests are recorded
+pub const REKOR_ENTRY_INDEX_ENV: &str = "REKOR_ENTRY_INDEX";
+
+/// Location of a manifest in the transparency log, as returned when it was logged
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RekorEntry {
+    pub uuid: String,
+    pub log_index: u64,
+    /// Time the entry was integrated into the log (Unix seconds)
+    pub integrated_time: i64,
+    pub log_id: String,
+}
+
+/// A log entry found by artifact digest
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RekorSearchResult {
+    pub uuid: String,
+    pub log_index: u64,
+    /// Time the entry was integrated into the log (Unix seconds)
+    pub integrated_time: i64,
+    /// Entry type, such as `hashedrekord` or `dsse`
+    pub kind: String,
+    /// Who signed the entry: certificate identities, or fingerprints of bare public keys
+    pub signers: Vec<String>,
+}
+
+/// Log entry as returned by the Rekor API, keyed by UUID
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LogEntryResponse {
+    body: String,
+    integrated_time: i64,
+    #[serde(rename = "logID")]
+    log_id: String,
+    log_index: u64,
+}
+
+/// A buffered response from the log
+#[derive(Debug, Clone)]
+struct Reply {
+    status: reqwest::StatusCode,
+    location: Option<String>,
+    body: Arc<[u8]>,
+}
+
+impl Reply {
+    fn read(response: reqwest::blocking::Response, context: &str) -> Result<Self> {
+        let status = response.status();
+        let location = response
+            .headers()
+            .get(reqwest::header::LOCATION)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let body = response
+            .bytes()
+            .map_err(|e| Error::storage_request(context, e))?;
+        Ok(Reply {
+            status,
+            location,
+            body: body.to_vec().into(),
+        })
+    }
+
+    fn json<T: serde::de::DeserializeOwned>(&self, what: &str) -> Result<T> {
+        serde_json::from_slice(&self.body)
+            .map_err(|e| Error::Storage(format!("Failed to parse {what}: {e}")))
+    }
+}
+
+// Answers to lookups, keyed by request. Each answer has its own lock, held while the
+// lookup is in flight so that identical concurrent lookups wait for it instead of
+// sending their own.
+type Lookups = Mutex<HashMap<String, Arc<Mutex<Option<Reply>>>>>;
 
-#[allow(dead_code)]
 pub struct RekorStorage {
     client: reqwest::blocking::Client,
     base_url: String,
+    public_key_path: Option<PathBuf>,
+    entry_index: PathBuf,
+    limiter: RateLimiter,
+    lookups: Lookups,
 }
 
 impl RekorStorage {
@@ -15,11 +111,404 @@ impl RekorStorage {
     }
 
     pub fn new_with_url(url: String) -> Result<Self> {
+        let entry_index = match std::env::var_os(REKOR_ENTRY_INDEX_ENV) {
+            Some(path) => PathBuf::from(path),
+            None => std::env::var_os("HOME")
+                .or_else(|| std::env::var_os("USERPROFILE"))
+                .map(|home| PathBuf::from(home).join(".atlas"))
+                .unwrap_or_default()
+                .join("rekor_entries.json"),
+        };
+
+        let client = pinning::client_builder(&url)?
+            .build()
+            .map_err(|e| Error::Storage(format!("Failed to create HTTP client: {e}")))?;
+
         Ok(RekorStorage {
-            client: reqwest::blocking::Client::new(),
+            client,
             base_url: url,
+            public_key_path: std::env::var_os(REKOR_PUBLIC_KEY_ENV).map(PathBuf::from),
+            entry_index,
+            limiter: RateLimiter::new(RateLimit::rekor_from_env()?),
+            lookups: Lookups::default(),
         })
     }
+
+    /// Limits the rate of requests to the log instead of `REKOR_RATE_LIMIT`
+    pub fn with_rate_limit(mut self, limit: RateLimit) -> Self {
+        self.limiter = RateLimiter::new(limit);
+        self
+    }
+
+    /// Sets the PEM public key used to log signed manifests
+    pub fn with_public_key(mut self, path: impl Into<PathBuf>) -> Self {
+        self.public_key_path = Some(path.into());
+        self
+    }
+
+    /// Sets the file recording the log entries of stored manifests
+    pub fn with_entry_index(mut self, path: impl Into<PathBuf>) -> Self {
+        self.entry_index = path.into();
+        self
+    }
+
+    // Sends a request once the rate limit allows. Requests the log throttles with 429 or
+    // 503 are sent again after the delay it asks for, holding back other requests too.
+    fn send(
+        &self,
+        request: impl Fn() -> reqwest::blocking::RequestBuilder,
+        context: &str,
+    ) -> Result<Reply> {
+        let mut retry = 0;
+        loop {
+            self.limiter.acquire();
+            let response = request().send_pinned(context)?;
+            let status = response.status();
+            if (status == reqwest::StatusCode::TOO_MANY_REQUESTS
+                || status == reqwest::StatusCode::SERVICE_UNAVAILABLE)
+                && let Some(delay) = self.limiter.retry_delay(response.headers(), retry)
+            {
+                self.limiter.pause(delay);
+                retry += 1;
+                continue;
+            }
+            return Reply::read(response, context);
+        }
+    }
+
+    // Sends a request that only reads from the log. Successful answers are kept until the
+    // backend logs an entry, so repeated and concurrent lookups are sent once.
+    fn lookup(
+        &self,
+        key: String,
+        request: impl Fn() -> reqwest::blocking::RequestBuilder,
+        context: &str,
+    ) -> Result<Reply> {
+        let slot = self.lookups.lock().unwrap().entry(key).or_default().clone();
+        let mut answer = slot.lock().unwrap();
+        if let Some(reply) = answer.as_ref() {
+            return Ok(reply.clone());
+        }
+
+        let reply = self.send(request, context)?;
+        if reply.status.is_success() {
+            *answer = Some(reply.clone());
+        }
+        Ok(reply)
+    }
+
+    /// Returns the recorded log entry of a manifest, if it was logged from here
+    pub fn recorded_entry(&self, manifest_id: &str) -> Result<Option<RekorEntry>> {
+        Ok(read_entry_index(&self.entry_index)?.remove(manifest_id))
+    }
+
+    /// Fetches the recorded log entry of a manifest by UUID and checks that it logs the
+    /// manifest's claim
+    pub fn verify_entry(&self, manifest: &Manifest) -> Result<RekorEntry> {
+        let recorded = self.recorded_entry(&manifest.instance_id)?.ok_or_else(|| {
+            Error::Storage(format!(
+                "No Rekor entry recorded for manifest {}",
+                manifest.instance_id
+            ))
+        })?;
+
+        let url = format!("{}/api/v1/log/entries/{}", self.base_url, recorded.uuid);
+        let reply = self.lookup(
+            format!("GET {url}"),
+            || self.client.get(&url),
+            "Failed to fetch Rekor entry",
+        )?;
+        if !reply.status.is_success() {
+            return Err(Error::storage_status(
+                reply.status,
+                format!(
+                    "Failed to fetch Rekor entry {}: {}",
+                    recorded.uuid, reply.status
+                ),
+            ));
+        }
+        let (entry, body) = parse_log_entry(&reply)?;
+
+        if entry != recorded {
+            return Err(Error::Validation(format!(
+                "Rekor entry {} does not match the recorded log index and time",
+                recorded.uuid
+            )));
+        }
+
+        let data_hash = &body["spec"]["data"]["hash"];
+        let algorithm: HashAlgorithm = data_hash["algorithm"]
+            .as_str()
+            .unwrap_or_default()
+            .parse()
+            .map_err(|e| Error::Validation(format!("Invalid Rekor entry hash algorithm: {e}")))?;
+        let logged = claim_inputs(manifest)?.iter().any(|claim| {
+            data_hash["value"].as_str()
+                == Some(hash::calculate_hash_with_algorithm(claim, &algorithm).as_str())
+        });
+        if !logged {
+            return Err(Error::Validation(format!(
+                "Rekor entry {} does not log manifest {}",
+                recorded.uuid, manifest.instance_id
+            )));
+        }
+
+        Ok(entry)
+    }
+
+    /// Searches the log for entries of an artifact digest, given as `sha256:<hex>`,
+    /// `sha512:<hex>` or bare hex
+    pub fn find_entries(&self, digest: &str) -> Result<Vec<RekorSearchResult>> {
+        let digest = search_digest(digest)?;
+
+        let url = format!("{}/api/v1/index/retrieve", self.base_url);
+        let query = serde_json::json!({ "hash": digest });
+        let reply = self.lookup(
+            format!("POST {url} {query}"),
+            || self.client.post(&url).json(&query),
+            "Failed to search Rekor",
+        )?;
+        if !reply.status.is_success() {
+            return Err(Error::storage_status(
+                reply.status,
+                format!("Failed to search Rekor: {}", reply.status),
+            ));
+        }
+        let uuids: Vec<String> = reply.json("Rekor search results")?;
+        if uuids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let url = format!("{}/api/v1/log/entries/retrieve", self.base_url);
+        let query = serde_json::json!({ "entryUUIDs": uuids });
+        let reply = self.lookup(
+            format!("POST {url} {query}"),
+            || self.client.post(&url).json(&query),
+            "Failed to fetch Rekor entries",
+        )?;
+        if !reply.status.is_success() {
+            return Err(Error::storage_status(
+                reply.status,
+                format!("Failed to fetch Rekor entries: {}", reply.status),
+            ));
+        }
+        let entries: Vec<HashMap<String, LogEntryResponse>> = reply.json("Rekor entries")?;
+
+        let mut results = Vec::new();
+        for (uuid, entry) in entries.into_iter().flatten() {
+            let body = decode_body(&entry.body)?;
+            results.push(RekorSearchResult {
+                uuid,
+                log_index: entry.log_index,
+                integrated_time: entry.integrated_time,
+                kind: body["kind"].as_str().unwrap_or("unknown").to_string(),
+                signers: entry_signers(&body),
+            });
+        }
+        results.sort_by_key(|result| result.log_index);
+        Ok(results)
+    }
+
+    /// Builds the hashedrekord entry of a signed manifest
+    fn hashed_rekord(&self, manifest: &Manifest) -> Result<serde_json::Value> {
+        let signature = manifest.claim.signature.as_deref().ok_or_else(|| {
+            Error::Storage("Only signed manifests can be stored in Rekor".to_string())
+        })?;
+        let signature = STANDARD
+            .decode(signature)
+            .map_err(|e| Error::Storage(format!("Invalid manifest signature: {e}")))?;
+
+        let key_path = self.public_key_path.as_ref().ok_or_else(|| {
+            Error::Storage(format!(
+                "Storing in Rekor requires the signer's public key. Set {REKOR_PUBLIC_KEY_ENV} to its PEM file."
+            ))
+        })?;
+        let public_key_pem = fs::read(key_path)?;
+        let public_key = PKey::public_key_from_pem(&public_key_pem)
+            .map_err(|e| Error::Signing(format!("Invalid Rekor public key: {e}")))?;
+
+        // The log checks the signature against the digest, so use the encoding and algorithm it
+        // was made with
+        let (claim, algorithm) = claim_inputs(manifest)?
+            .into_iter()
+            .flat_map(|claim| {
+                [
+                    HashAlgorithm::Sha256,
+                    HashAlgorithm::Sha384,
+                    HashAlgorithm::Sha512,
+                ]
+                .map(|algorithm| (claim.clone(), algorithm))
+            })
+            .find(|(claim, algorithm)| {
+                signing::verify_signature_with_algorithm(claim, &signature, &public_key, algorithm)
+                    .unwrap_or(false)
+            })
+            .ok_or_else(|| {
+                Error::Signing("Manifest signature does not match the Rekor public key".to_string())
+            })?;
+
+        Ok(serde_json::json!({
+            "apiVersion": "0.0.1",
+            "kind": "hashedrekord",
+            "spec": {
+                "signature": {
+                    "content": STANDARD.encode(&signature),
+                    "publicKey": { "content": STANDARD.encode(&public_key_pem) },
+                },
+                "data": {
+                    "hash": {
+                        "algorithm": algorithm.as_str(),
+                        "value": hash::calculate_hash_with_algorithm(&claim, &algorithm),
+                    },
+                },
+            },
+        }))
+    }
+}
+
+/// Bytes the manifest signature may cover, which was made before the signature was added
+/// to the claim, see [`canonical::claim_signing_inputs`]
+fn claim_inputs(manifest: &Manifest) -> Result<Vec<Vec<u8>>> {
+    canonical::claim_signing_inputs(&manifest.claim)
+}
+
+/// Parses a single-entry Rekor response into the entry and its decoded body
+fn parse_log_entry(reply: &Reply) -> Result<(RekorEntry, serde_json::Value)> {
+    let entries: HashMap<String, LogEntryResponse> = reply.json("Rekor entry")?;
+    let (uuid, entry) = entries
+        .into_iter()
+        .next()
+        .ok_or_else(|| Error::Storage("Rekor returned no log entry".to_string()))?;
+
+    let body = decode_body(&entry.body)?;
+
+    Ok((
+        RekorEntry {
+            uuid,
+            log_index: entry.log_index,
+            integrated_time: entry.integrated_time,
+            log_id: entry.log_id,
+        },
+        body,
+    ))
+}
+
+fn decode_body(body: &str) -> Result<serde_json::Value> {
+    let body = STANDARD
+        .decode(body)
+        .map_err(|e| Error::Storage(format!("Invalid Rekor entry body: {e}")))?;
+    serde_json::from_slice(&body)
+        .map_err(|e| Error::Storage(format!("Invalid Rekor entry body: {e}")))
+}
+
+/// Normalizes a digest for the Rekor search index, which takes SHA-256 and SHA-512
+fn search_digest(digest: &str) -> Result<String> {
+    let (algorithm, value) = match digest.split_once(':') {
+        Some((algorithm, value)) => (algorithm.to_lowercase(), value),
+        None => match digest.len() {
+            64 => ("sha256".to_string(), digest),
+            128 => ("sha512".to_string(), digest),
+            _ => (String::new(), digest),
+        },
+    };
+
+    let expected_len = match algorithm.as_str() {
+        "sha256" => 64,
+        "sha512" => 128,
+        _ => {
+            return Err(Error::Validation(format!(
+                "Unsupported digest '{digest}'. Rekor can be searched by SHA-256 or SHA-512 digests."
+            )));
+        }
+    };
+    if value.len() != expected_len || !value.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(Error::Validation(format!(
+            "Invalid {algorithm} digest: {value}"
+        )));
+    }
+    Ok(format!("{algorithm}:{}", value.to_lowercase()))
+}
+
+/// Identities of the signers of a log entry, from the keys or certificates it records
+fn entry_signers(body: &serde_json::Value) -> Vec<String> {
+    let spec = &body["spec"];
+    let mut encoded: Vec<&str> = Vec::new();
+
+    // hashedrekord and rekord
+    encoded.extend(spec["signature"]["publicKey"]["content"].as_str());
+    // dsse
+    for signature in spec["signatures"].as_array().into_iter().flatten() {
+        encoded.extend(signature["verifier"].as_str());
+    }
+    // intoto
+    for signature in spec["content"]["envelope"]["signatures"]
+        .as_array()
+        .into_iter()
+        .flatten()
+    {
+        encoded.extend(signature["publicKey"].as_str());
+    }
+
+    encoded
+        .into_iter()
+        .filter_map(|content| STANDARD.decode(content).ok())
+        .map(|pem| signer_identity(&pem))
+        .collect()
+}
+
+/// Describes a PEM certificate by its identities and a PEM public key by its fingerprint
+fn signer_identity(pem: &[u8]) -> String {
+    if let Ok(certificate) = X509::from_pem(pem) {
+        let identities: Vec<String> = certificate
+            .subject_alt_names()
+            .into_iter()
+            .flatten()
+            .filter_map(|name| {
+                name.email()
+                    .or_else(|| name.uri())
+                    .or_else(|| name.dnsname())
+                    .map(str::to_string)
+            })
+            .collect();
+        if !identities.is_empty() {
+            return identities.join(", ");
+        }
+        return certificate
+            .subject_name()
+            .entries()
+            .filter_map(|entry| entry.data().as_utf8().ok().map(|data| data.to_string()))
+            .collect::<Vec<_>>()
+            .join(", ");
+    }
+
+    match PKey::public_key_from_pem(pem).and_then(|key| key.public_key_to_der()) {
+        Ok(der) => format!(
+            "key SHA256:{}",
+            hash::calculate_hash_with_algorithm(&der, &HashAlgorithm::Sha256)
+        ),
+        Err(_) => "unknown signer".to_string(),
+    }
+}
+
+fn read_entry_index(path: &Path) -> Result<BTreeMap<String, RekorEntry>> {
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    serde_json::from_slice(&fs::read(path)?)
+        .map_err(|e| Error::Storage(format!("Invalid Rekor entry index {}: {e}", path.display())))
+}
+
+fn record_entry(path: &Path, manifest_id: &str, entry: &RekorEntry) -> Result<()> {
+    let mut entries = read_entry_index(path)?;
+    entries.insert(manifest_id.to_string(), entry.clone());
+
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_vec_pretty(&entries)?)?;
+    Ok(())
 }
 
 impl StorageBackend for RekorStorage {
@@ -27,19 +516,84 @@ impl StorageBackend for RekorStorage {
         self.base_url.clone()
     }
 
-    fn store_manifest(&self, _manifest: &Manifest) -> Result<String> {
-        // TODO: Implement actual storage
-        println!("Would store manifest at: {}", self.base_url);
-        Ok("dummy-manifest-id".to_string()) // Return a dummy manifest ID for now
+    fn store_manifest(&self, manifest: &Manifest) -> Result<String> {
+        let entries_url = format!("{}/api/v1/log/entries", self.base_url);
+        let entry = self.hashed_rekord(manifest)?;
+        // Sending the entry again is safe: the log answers an entry it already holds with
+        // 409, so an attempt whose answer was lost doesn't log the manifest twice
+        let reply = chunked::with_retries(|| {
+            let reply = self.send(
+                || self.client.post(&entries_url).json(&entry),
+                "Failed to create Rekor entry",
+            )?;
+            if reply.status.is_server_error() {
+                return Err(Error::storage_status(
+                    reply.status,
+                    format!("Failed to create Rekor entry: {}", reply.status),
+                ));
+            }
+            Ok(reply)
+        })?;
+
+        // An entry for the same signature and digest already exists; use that one
+        let reply = if reply.status == reqwest::StatusCode::CONFLICT {
+            let location = reply.location.ok_or_else(|| {
+                Error::Storage("Rekor reported a conflict without an entry location".to_string())
+            })?;
+            let location = if location.starts_with('/') {
+                format!("{}{location}", self.base_url)
+            } else {
+                location
+            };
+            self.lookup(
+                format!("GET {location}"),
+                || self.client.get(&location),
+                "Failed to fetch Rekor entry",
+            )?
+        } else {
+            reply
+        };
+
+        if !reply.status.is_success() {
+            return Err(Error::storage_status(
+                reply.status,
+                format!("Failed to create Rekor entry: {}", reply.status),
+            ));
+        }
+
+        let (entry, _) = parse_log_entry(&reply)?;
+        // Searches made before may no longer find everything
+        self.lookups.lock().unwrap().clear();
+        // Entries found by search or fetched later may be in older shards of the log,
+        // while new entries are always added to the active one
+        if let Ok(url) = reqwest::Url::parse(&self.base_url) {
+            pinning::verify_identity(&pinning::endpoint(&url), PinKind::LogId, &entry.log_id)?;
+        }
+        record_entry(&self.entry_index, &manifest.instance_id, &entry)?;
+        println!(
+            "Logged manifest in Rekor: uuid {} at index {}",
+            entry.uuid, entry.log_index
+        );
+
+        Ok(manifest.instance_id.clone())
     }
 
     fn retrieve_manifest(&self, _id: &str) -> Result<Manifest> {
-        todo!("Implement manifest retrieval")
+        Err(Error::Storage(
+            "Retrieving manifests is not supported for Rekor storage".to_string(),
+        ))
     }
 
     fn list_manifests(&self) -> Result<Vec<ManifestMetadata>> {
-        todo!("Implement manifest listing")
+        Err(Error::Storage(
+            "Listing manifests is not supported for Rekor storage".to_string(),
+        ))
     }
+
+    fn supports_listing(&self) -> bool {
+        false
+    }
+
     fn delete_manifest(&self, _id: &str) -> Result<()> {
         // Implement deletion for Rekor if supported, or return error
         Err(Error::Storage(
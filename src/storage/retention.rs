@@ -0,0 +1,525 @@
+//! Retention policies for stored manifests.
+//!
+//! A retention policy says how long manifests of each type are kept, and what happens to
+//! them afterwards. It is read from `ATLAS_RETENTION_FILE` or `~/.atlas/retention.yaml`:
+//!
+//! ```yaml
+//! archive_dir: /var/lib/atlas/archive
+//! rules:
+//!   evaluation:
+//!     keep: 18mo
+//!     action: archive
+//!   dataset:
+//!     keep: 3y
+//!     action: delete
+//!   model:
+//!     keep: forever
+//! ```
+//!
+//! `store apply-retention` removes manifests older than their rule allows from the store.
+//! Archived manifests are first copied to `archive_dir/manifests`, and every removed
+//! manifest leaves a signed tombstone in `archive_dir/tombstones`: a Verifiable Credential
+//! naming the manifest, its subject digest, the rule that expired it and when it was
+//! removed. Manifests without a rule are kept, and expired manifests still
+//! cross-referenced by kept manifests are held, so that the kept manifests keep verifying.
+
+use crate::error::{Error, Result};
+use crate::manifest::attestation;
+use crate::manifest::claims;
+use crate::manifest::common::AssetKind;
+use crate::manifest::credential::{self, VC_CONTEXT};
+use crate::manifest::timestamp::to_rfc3339_utc;
+use crate::manifest::utils::determine_manifest_type;
+use crate::signing::SecurePrivateKey;
+use crate::storage::traits::{ManifestType, StorageBackend};
+use atlas_c2pa_lib::assertion::Assertion;
+use atlas_c2pa_lib::cose::HashAlgorithm;
+use atlas_c2pa_lib::manifest::Manifest;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use time::{Duration, OffsetDateTime};
+
+/// Environment variable naming the retention policy file
+pub const RETENTION_FILE_ENV: &str = "ATLAS_RETENTION_FILE";
+
+/// Credential type of tombstones, besides `VerifiableCredential`
+pub const TOMBSTONE_CREDENTIAL_TYPE: &str = "AtlasTombstoneCredential";
+
+/// How long manifests are kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetentionPeriod {
+    Forever,
+    Days(i64),
+    Months(i64),
+}
+
+impl FromStr for RetentionPeriod {
+    type Err = Error;
+
+    /// Parses `forever`, or a number followed by `d`, `w`, `mo` or `y` (e.g. `18mo`)
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || {
+            Error::Validation(format!(
+                "Invalid retention period '{s}'. Expected forever, or a number followed by d, w, mo or y (e.g. 18mo)"
+            ))
+        };
+
+        let s = s.trim();
+        if s == "forever" {
+            return Ok(RetentionPeriod::Forever);
+        }
+        let unit_start = s.find(|c: char| !c.is_ascii_digit()).ok_or_else(invalid)?;
+        let (value, unit) = s.split_at(unit_start);
+        let value: i64 = value.parse().map_err(|_| invalid())?;
+
+        match unit {
+            "d" => Ok(RetentionPeriod::Days(value)),
+            "w" => Ok(RetentionPeriod::Days(value * 7)),
+            "mo" => Ok(RetentionPeriod::Months(value)),
+            "y" => Ok(RetentionPeriod::Months(value * 12)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for RetentionPeriod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RetentionPeriod::Forever => write!(f, "forever"),
+            RetentionPeriod::Days(days) => write!(f, "{days}d"),
+            RetentionPeriod::Months(months) => write!(f, "{months}mo"),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for RetentionPeriod {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl RetentionPeriod {
+    /// When a manifest created at `created_at` expires, if ever. Months are calendar
+    /// months, ending on the last day of shorter months.
+    pub fn expires_at(self, created_at: OffsetDateTime) -> Option<OffsetDateTime> {
+        match self {
+            RetentionPeriod::Forever => None,
+            RetentionPeriod::Days(days) => created_at.checked_add(Duration::days(days)),
+            RetentionPeriod::Months(months) => {
+                let month_index =
+                    i64::from(created_at.year()) * 12 + i64::from(u8::from(created_at.month())) - 1
+                        + months;
+                let year = i32::try_from(month_index.div_euclid(12)).ok()?;
+                let month =
+                    time::Month::try_from(u8::try_from(month_index.rem_euclid(12) + 1).ok()?)
+                        .ok()?;
+                let day = created_at.day().min(month.length(year));
+                let date = time::Date::from_calendar_date(year, month, day).ok()?;
+                Some(created_at.replace_date(date))
+            }
+        }
+    }
+}
+
+/// What happens to expired manifests
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RetentionAction {
+    /// Copy the manifest to the archive before removing it from the store
+    #[default]
+    Archive,
+    /// Remove the manifest from the store
+    Delete,
+}
+
+impl fmt::Display for RetentionAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RetentionAction::Archive => write!(f, "archive"),
+            RetentionAction::Delete => write!(f, "delete"),
+        }
+    }
+}
+
+/// Retention of the manifests of one type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetentionRule {
+    pub keep: RetentionPeriod,
+    #[serde(default)]
+    pub action: RetentionAction,
+}
+
+impl fmt::Display for RetentionRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.keep {
+            RetentionPeriod::Forever => write!(f, "keep forever"),
+            keep => write!(f, "keep {keep}, then {}", self.action),
+        }
+    }
+}
+
+/// Retention rules by manifest type
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RetentionPolicy {
+    /// Where archived manifests and tombstones are written
+    pub archive_dir: Option<PathBuf>,
+    rules: BTreeMap<String, RetentionRule>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RetentionFile {
+    archive_dir: Option<PathBuf>,
+    rules: BTreeMap<String, RetentionRule>,
+}
+
+/// Location of the retention policy: `ATLAS_RETENTION_FILE`, or `~/.atlas/retention.yaml`
+pub fn retention_file() -> PathBuf {
+    match std::env::var_os(RETENTION_FILE_ENV) {
+        Some(path) => PathBuf::from(path),
+        None => atlas_dir().join("retention.yaml"),
+    }
+}
+
+/// Archive used when the policy names none, `~/.atlas/archive`
+pub fn default_archive_dir() -> PathBuf {
+    atlas_dir().join("archive")
+}
+
+fn atlas_dir() -> PathBuf {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(|home| PathBuf::from(home).join(".atlas"))
+        .unwrap_or_default()
+}
+
+impl RetentionPolicy {
+    /// Reads a retention policy
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Err(Error::Validation(format!(
+                "No retention policy found at {} (set {RETENTION_FILE_ENV} to use another file)",
+                path.display()
+            )));
+        }
+        Self::parse(&std::fs::read_to_string(path)?).map_err(|e| {
+            Error::Validation(format!("Invalid retention policy {}: {e}", path.display()))
+        })
+    }
+
+    /// Parses a retention policy from YAML
+    pub fn parse(yaml: &str) -> Result<Self> {
+        let file: RetentionFile =
+            serde_yaml::from_str(yaml).map_err(|e| Error::Validation(e.to_string()))?;
+        if let Some(kind) = file
+            .rules
+            .keys()
+            .find(|kind| !["model", "dataset", "software", "evaluation"].contains(&kind.as_str()))
+        {
+            return Err(Error::Validation(format!(
+                "Unknown manifest type '{kind}', expected model, dataset, software or evaluation"
+            )));
+        }
+        Ok(RetentionPolicy {
+            archive_dir: file.archive_dir,
+            rules: file.rules,
+        })
+    }
+
+    /// Rule of the manifests of a type, if any
+    pub fn rule(&self, kind: AssetKind) -> Option<RetentionRule> {
+        self.rules.get(&kind.to_string()).copied()
+    }
+}
+
+/// Type of a stored manifest, if it has one that retention rules apply to
+pub fn manifest_kind(manifest: &Manifest) -> Option<AssetKind> {
+    let claim = claims::signed_claim(manifest);
+    let is_evaluation = claim.created_assertions.iter().any(|assertion| {
+        matches!(assertion, Assertion::CreativeWork(work) if work.creative_type == "EvaluationResult")
+    });
+    if is_evaluation {
+        return Some(AssetKind::Evaluation);
+    }
+    match determine_manifest_type(manifest) {
+        ManifestType::Model => Some(AssetKind::Model),
+        ManifestType::Dataset => Some(AssetKind::Dataset),
+        ManifestType::Software => Some(AssetKind::Software),
+        ManifestType::Unknown => None,
+    }
+}
+
+/// A manifest whose retention period is over
+#[derive(Debug, Clone)]
+pub struct ExpiredManifest {
+    pub id: String,
+    pub name: String,
+    pub kind: AssetKind,
+    pub created_at: OffsetDateTime,
+    pub expired_at: OffsetDateTime,
+    pub rule: RetentionRule,
+}
+
+/// Expired manifests of a store
+#[derive(Debug, Default)]
+pub struct RetentionPlan {
+    /// Manifests to archive or delete, oldest first
+    pub expired: Vec<ExpiredManifest>,
+    /// Expired manifests kept because kept manifests cross-reference them, with the IDs of
+    /// those manifests
+    pub held: Vec<(ExpiredManifest, Vec<String>)>,
+}
+
+/// Finds the manifests of a store that expired by `now` under `policy`
+pub fn plan_retention(
+    storage: &dyn StorageBackend,
+    policy: &RetentionPolicy,
+    now: OffsetDateTime,
+) -> Result<RetentionPlan> {
+    let mut expired = BTreeMap::new();
+    let mut references: Vec<(String, Vec<String>)> = Vec::new();
+    for metadata in storage.list_manifests()? {
+        let manifest = storage.retrieve_manifest(&metadata.id)?;
+        references.push((
+            metadata.id.clone(),
+            manifest
+                .cross_references
+                .iter()
+                .map(|cross_ref| cross_ref.manifest_url.clone())
+                .collect(),
+        ));
+
+        let Some(kind) = manifest_kind(&manifest) else {
+            continue;
+        };
+        let Some(rule) = policy.rule(kind) else {
+            continue;
+        };
+        let created_at = manifest.created_at.0;
+        if let Some(expired_at) = rule.keep.expires_at(created_at).filter(|at| *at <= now) {
+            expired.insert(
+                metadata.id.clone(),
+                ExpiredManifest {
+                    id: metadata.id,
+                    name: manifest.title,
+                    kind,
+                    created_at,
+                    expired_at,
+                    rule,
+                },
+            );
+        }
+    }
+
+    // Holding a manifest keeps it, so the manifests it references are held in turn
+    let mut held: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    loop {
+        let mut changed = false;
+        for (id, targets) in &references {
+            if expired.contains_key(id) && !held.contains_key(id) {
+                continue;
+            }
+            for target in targets {
+                if expired.contains_key(target) {
+                    changed |= held.entry(target.clone()).or_default().insert(id.clone());
+                }
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    let mut plan = RetentionPlan::default();
+    for (id, manifest) in expired {
+        match held.remove(&id) {
+            Some(by) => plan.held.push((manifest, by.into_iter().collect())),
+            None => plan.expired.push(manifest),
+        }
+    }
+    plan.expired.sort_by_key(|manifest| manifest.created_at);
+    Ok(plan)
+}
+
+/// A manifest removed by [`apply_retention`]
+#[derive(Debug, Clone)]
+pub struct RemovedManifest {
+    pub id: String,
+    pub action: RetentionAction,
+    /// Where the manifest was archived
+    pub archive: Option<PathBuf>,
+    pub tombstone: PathBuf,
+}
+
+/// Builds an unsecured tombstone stating that `issuer` removed a manifest from the store at
+/// `removed_at` under `rule`
+pub fn tombstone(
+    manifest: &Manifest,
+    expired: &ExpiredManifest,
+    issuer: &str,
+    hash_alg: &HashAlgorithm,
+    removed_at: OffsetDateTime,
+) -> Result<Value> {
+    credential::validate_did(issuer)?;
+    let removed_at = to_rfc3339_utc(removed_at);
+
+    Ok(json!({
+        "@context": [VC_CONTEXT],
+        "type": ["VerifiableCredential", TOMBSTONE_CREDENTIAL_TYPE],
+        "issuer": issuer,
+        "validFrom": removed_at,
+        "credentialSubject": {
+            "id": expired.id,
+            "name": expired.name,
+            "type": expired.kind.to_string(),
+            "createdAt": to_rfc3339_utc(expired.created_at),
+            "digest": {
+                "algorithm": hash_alg.as_str(),
+                "value": attestation::manifest_subject_hash(manifest, hash_alg)?,
+            },
+            "retention": {
+                "keep": expired.rule.keep.to_string(),
+                "action": expired.rule.action,
+                "expiredAt": to_rfc3339_utc(expired.expired_at),
+                "removedAt": removed_at,
+            },
+        },
+    }))
+}
+
+/// Archives or deletes the expired manifests of a plan, writing a tombstone signed with
+/// `key` for each to `archive_dir/tombstones` before removing it from the store.
+///
+/// Without an `issuer`, tombstones are issued by the `did:key` of the signing key. Stops at
+/// the first manifest that can't be removed, which keeps no tombstone; the manifests
+/// removed before it keep theirs.
+pub fn apply_retention(
+    storage: &dyn StorageBackend,
+    plan: &RetentionPlan,
+    archive_dir: &Path,
+    key: &SecurePrivateKey,
+    issuer: Option<String>,
+    hash_alg: &HashAlgorithm,
+) -> Result<Vec<RemovedManifest>> {
+    let (issuer, kid) = credential::issuer_and_kid(key, issuer)?;
+    let manifests_dir = archive_dir.join("manifests");
+    let tombstones_dir = archive_dir.join("tombstones");
+    std::fs::create_dir_all(&tombstones_dir)?;
+
+    let mut removed = Vec::new();
+    for expired in &plan.expired {
+        let manifest = storage.retrieve_manifest(&expired.id)?;
+        let file_name = archive_file_name(&expired.id);
+
+        let archive = match expired.rule.action {
+            RetentionAction::Archive => {
+                std::fs::create_dir_all(&manifests_dir)?;
+                let path = manifests_dir.join(format!("{file_name}.json"));
+                std::fs::write(&path, serde_json::to_vec_pretty(&manifest)?)?;
+                Some(path)
+            }
+            RetentionAction::Delete => None,
+        };
+
+        let record = tombstone(
+            &manifest,
+            expired,
+            &issuer,
+            hash_alg,
+            OffsetDateTime::now_utc(),
+        )?;
+        let tombstone = tombstones_dir.join(format!("{file_name}.jwt"));
+        std::fs::write(&tombstone, credential::sign_credential(&record, key, &kid)?)?;
+
+        if let Err(e) = storage.delete_manifest(&expired.id) {
+            let _ = std::fs::remove_file(&tombstone);
+            return Err(e);
+        }
+        removed.push(RemovedManifest {
+            id: expired.id.clone(),
+            action: expired.rule.action,
+            archive,
+            tombstone,
+        });
+    }
+    Ok(removed)
+}
+
+// File name of the archive entries of a manifest ID
+fn archive_file_name(id: &str) -> String {
+    id.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    #[test]
+    fn test_retention_period() -> Result<()> {
+        assert_eq!(
+            "18mo".parse::<RetentionPeriod>()?,
+            RetentionPeriod::Months(18)
+        );
+        assert_eq!(
+            "2y".parse::<RetentionPeriod>()?,
+            RetentionPeriod::Months(24)
+        );
+        assert_eq!("2w".parse::<RetentionPeriod>()?, RetentionPeriod::Days(14));
+        assert_eq!(
+            "forever".parse::<RetentionPeriod>()?,
+            RetentionPeriod::Forever
+        );
+        assert!("18m".parse::<RetentionPeriod>().is_err());
+
+        let created = datetime!(2023-08-31 12:00 UTC);
+        assert_eq!(
+            RetentionPeriod::Months(18).expires_at(created),
+            Some(datetime!(2025-02-28 12:00 UTC))
+        );
+        assert_eq!(
+            RetentionPeriod::Days(30).expires_at(created),
+            Some(datetime!(2023-09-30 12:00 UTC))
+        );
+        assert_eq!(RetentionPeriod::Forever.expires_at(created), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_policy() -> Result<()> {
+        let policy = RetentionPolicy::parse(
+            "rules:\n  evaluation:\n    keep: 18mo\n  model:\n    keep: forever\n",
+        )?;
+        assert_eq!(
+            policy.rule(AssetKind::Evaluation),
+            Some(RetentionRule {
+                keep: RetentionPeriod::Months(18),
+                action: RetentionAction::Archive,
+            })
+        );
+        assert_eq!(policy.rule(AssetKind::Dataset), None);
+
+        assert!(RetentionPolicy::parse("rules:\n  notebook:\n    keep: 1y\n").is_err());
+        assert!(
+            RetentionPolicy::parse("rules:\n  model:\n    keep: 1y\n    action: shred\n").is_err()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,236 @@
+//! Manifests stored in an S3-compatible bucket.
+//!
+//! `--storage-type=s3 --storage-url=s3://bucket/prefix` keeps each manifest as the
+//! uncompressed JSON object `<prefix>/<SHA-256 of its ID>.json`. The prefix may be left out
+//! to store manifests at the top of the bucket. Manifests are not compressed.
+//!
+//! Each object carries the listing metadata of its manifest (ID, name, type, creation time
+//! and whether it is signed) as base64-encoded JSON in the `atlas-metadata` user metadata,
+//! so listing looks up each object with a HEAD request instead of downloading and parsing
+//! it. Objects stored before the metadata was recorded are still read in full.
+//!
+//! Manifests are written with conditional requests, so that concurrent writers don't
+//! overwrite each other: a new manifest only if no object has its key, and a changed one
+//! only if the object is still the one that was read.
+//!
+//! Requests are made as for S3 ingredients (see [`crate::manifest::object_store`]): to the
+//! endpoint in `AWS_ENDPOINT_URL` if set, such as a MinIO server, and signed with SigV4 when
+//! `AWS_ACCESS_KEY_ID` and `AWS_SECRET_ACCESS_KEY` are set.
+
+use crate::error::{Error, Result};
+use crate::manifest::object_store::{ObjectClient, ObjectStore, ObjectUrl, PutCondition};
+use crate::storage::traits::{ManifestMetadata, StorageBackend};
+use atlas_c2pa_lib::manifest::Manifest;
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use sha2::{Digest, Sha256};
+
+/// Scheme of the storage URLs of the S3 backend
+pub const S3_URL_SCHEME: &str = "s3://";
+
+// Attempts to replace a manifest that other writers keep changing
+const PUT_ATTEMPTS: usize = 3;
+
+// User metadata holding the listing metadata of the manifest in an object
+const METADATA_NAME: &str = "atlas-metadata";
+
+pub struct S3Storage {
+    client: ObjectClient,
+    bucket: String,
+    prefix: String,
+}
+
+impl S3Storage {
+    /// Creates a backend for `s3://bucket/prefix`, with a client configured from the
+    /// environment
+    pub fn new(url: &str) -> Result<Self> {
+        let (bucket, prefix) = parse_storage_url(url)?;
+        Ok(Self {
+            client: ObjectClient::from_env(ObjectStore::S3)?,
+            bucket,
+            prefix,
+        })
+    }
+
+    /// Sends requests with `client` instead of one configured from the environment
+    pub fn with_client(mut self, client: ObjectClient) -> Self {
+        self.client = client;
+        self
+    }
+
+    fn object_url(&self, key: String) -> ObjectUrl {
+        ObjectUrl {
+            store: ObjectStore::S3,
+            bucket: self.bucket.clone(),
+            key,
+            version: None,
+        }
+    }
+
+    fn manifest_url(&self, id: &str) -> ObjectUrl {
+        let name = format!("{}.json", hex::encode(Sha256::digest(id.as_bytes())));
+        self.object_url(format!("{}{name}", self.prefix))
+    }
+
+    fn read_manifest(&self, url: &ObjectUrl) -> Result<Manifest> {
+        let body = self.client.get_object(url)?;
+        serde_json::from_slice(&body)
+            .map_err(|e| Error::Storage(format!("Failed to parse manifest {url}: {e}")))
+    }
+
+    // Listing metadata of the manifest in an object, from its user metadata if it has any
+    fn read_metadata(&self, url: &ObjectUrl) -> Result<ManifestMetadata> {
+        let object = self.client.stat(url)?;
+        let Some(encoded) = object.metadata.get(METADATA_NAME) else {
+            return Ok(ManifestMetadata::from_manifest(&self.read_manifest(url)?));
+        };
+        let json = STANDARD
+            .decode(encoded)
+            .map_err(|e| Error::Storage(format!("Invalid metadata of {url}: {e}")))?;
+        serde_json::from_slice(&json)
+            .map_err(|e| Error::Storage(format!("Invalid metadata of {url}: {e}")))
+    }
+}
+
+// Splits `s3://bucket/prefix` into the bucket and the prefix of the object keys, which is
+// empty or ends with `/`
+fn parse_storage_url(url: &str) -> Result<(String, String)> {
+    let location = url.strip_prefix(S3_URL_SCHEME).ok_or_else(|| {
+        Error::Validation(format!(
+            "Invalid S3 storage URL {url}, expected {S3_URL_SCHEME}bucket/prefix"
+        ))
+    })?;
+    let (bucket, prefix) = location.split_once('/').unwrap_or((location, ""));
+    if bucket.is_empty() {
+        return Err(Error::Validation(format!(
+            "Invalid S3 storage URL {url}: no bucket"
+        )));
+    }
+
+    let prefix = prefix.trim_matches('/');
+    let prefix = if prefix.is_empty() {
+        String::new()
+    } else {
+        format!("{prefix}/")
+    };
+    Ok((bucket.to_string(), prefix))
+}
+
+// Whether an object name is that of a manifest, `<SHA-256 of its ID>.json`
+fn is_manifest_name(name: &str) -> bool {
+    name.strip_suffix(".json").is_some_and(|digest| {
+        digest.len() == 64
+            && digest
+                .chars()
+                .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c))
+    })
+}
+
+impl StorageBackend for S3Storage {
+    fn get_base_uri(&self) -> String {
+        format!("{S3_URL_SCHEME}{}/{}", self.bucket, self.prefix)
+    }
+
+    fn store_manifest(&self, manifest: &Manifest) -> Result<String> {
+        let id = &manifest.instance_id;
+        let url = self.manifest_url(id);
+        let body =
+            serde_json::to_vec_pretty(manifest).map_err(|e| Error::Serialization(e.to_string()))?;
+        let metadata = serde_json::to_vec(&ManifestMetadata::from_manifest(manifest))
+            .map_err(|e| Error::Serialization(e.to_string()))?;
+        let metadata = [(METADATA_NAME, STANDARD.encode(metadata))];
+
+        let mut condition = PutCondition::Absent;
+        for _ in 0..PUT_ATTEMPTS {
+            if self.client.put_object(
+                &url,
+                body.clone(),
+                "application/json",
+                &metadata,
+                &condition,
+            )? {
+                return Ok(id.clone());
+            }
+
+            // Storing the same manifest again, as a retry does, leaves the object as it is
+            let (current, etag) = match self.client.get_object_with_etag(&url) {
+                Ok(object) => object,
+                // Deleted in the meantime, so it is written as a new manifest
+                Err(Error::NotFound(_)) => {
+                    condition = PutCondition::Absent;
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            if current == body {
+                return Ok(id.clone());
+            }
+            let etag = etag.ok_or_else(|| {
+                Error::Storage(format!("No ETag reported for {url}, can't replace it"))
+            })?;
+            condition = PutCondition::Matches(etag);
+        }
+
+        Err(Error::StorageUnavailable(format!(
+            "Manifest {id} was changed by another writer while it was stored"
+        )))
+    }
+
+    fn retrieve_manifest(&self, id: &str) -> Result<Manifest> {
+        self.read_manifest(&self.manifest_url(id))
+            .map_err(|e| match e {
+                Error::NotFound(_) => Error::NotFound(format!("Manifest not found: {id}")),
+                e => e,
+            })
+    }
+
+    fn list_manifests(&self) -> Result<Vec<ManifestMetadata>> {
+        let mut manifests = Vec::new();
+        for key in self.client.list_objects(&self.bucket, &self.prefix)? {
+            // Objects in deeper prefixes or otherwise named are not manifests of this store
+            if !key.strip_prefix(&self.prefix).is_some_and(is_manifest_name) {
+                continue;
+            }
+            match self.read_metadata(&self.object_url(key)) {
+                Ok(metadata) => manifests.push(metadata),
+                // Deleted since the listing
+                Err(Error::NotFound(_)) => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(manifests)
+    }
+
+    fn delete_manifest(&self, id: &str) -> Result<()> {
+        let url = self.manifest_url(id);
+        // Deleting a missing object succeeds, so look it up first
+        self.client.stat(&url).map_err(|e| match e {
+            Error::NotFound(_) => Error::NotFound(format!("Manifest not found: {id}")),
+            e => e,
+        })?;
+        self.client.delete_object(&url)
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_storage_url() -> Result<()> {
+        assert_eq!(
+            parse_storage_url("s3://models/provenance/manifests/")?,
+            ("models".to_string(), "provenance/manifests/".to_string())
+        );
+        assert_eq!(
+            parse_storage_url("s3://models")?,
+            ("models".to_string(), String::new())
+        );
+        assert!(parse_storage_url("s3:///prefix").is_err());
+        assert!(parse_storage_url("http://localhost:9000/models").is_err());
+        Ok(())
+    }
+}
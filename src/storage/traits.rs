@@ -1,9 +1,15 @@
 use crate::error::Error;
 use crate::error::Result;
+use crate::in_toto::dsse::Envelope;
+use crate::manifest::claims;
+use crate::manifest::timestamp::to_rfc3339_utc;
+use crate::manifest::utils::determine_manifest_type;
+use atlas_c2pa_lib::cose::HashAlgorithm;
 use atlas_c2pa_lib::manifest::Manifest;
 use serde::{Deserialize, Serialize};
 use std::any::Any;
 use std::fmt;
+use std::fs;
 use std::path::PathBuf;
 
 /// Represents metadata about a stored manifest
@@ -18,28 +24,182 @@ use std::path::PathBuf;
 ///     name: "My Model".to_string(),
 ///     manifest_type: ManifestType::Model,
 ///     created_at: "2025-01-23T12:00:00Z".to_string(),
+///     signed: Some(true),
 /// };
 ///
 /// assert_eq!(metadata.id, "model-123");
 /// assert_eq!(metadata.manifest_type, ManifestType::Model);
 /// ```
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ManifestMetadata {
     pub id: String,
     pub name: String,
     pub manifest_type: ManifestType,
     pub created_at: String,
+    /// Whether the manifest is signed, if the backend records it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signed: Option<bool>,
+}
+
+impl ManifestMetadata {
+    /// Metadata of a manifest at hand
+    pub fn from_manifest(manifest: &Manifest) -> Self {
+        Self {
+            id: manifest.instance_id.clone(),
+            name: manifest.title.clone(),
+            manifest_type: determine_manifest_type(manifest),
+            created_at: to_rfc3339_utc(manifest.created_at.0),
+            signed: Some(claims::is_signed(manifest)),
+        }
+    }
 }
 
-pub trait StorageBackend {
+pub trait StorageBackend: Send + Sync {
     fn get_base_uri(&self) -> String;
+
+    /// Stores a manifest, returning the ID it is stored as.
+    ///
+    /// Storing is idempotent: storing a manifest whose canonical content (see
+    /// [`crate::manifest::content_id::canonical_form`]) and cross-references the backend
+    /// already holds, signed alike, under its ID or under a version of it returns that ID
+    /// rather than storing it again. Calls failing on the way may therefore be retried,
+    /// whether or not the backend stored the manifest before the failure, even if the retry
+    /// differs in instance IDs or creation times.
     fn store_manifest(&self, manifest: &Manifest) -> Result<String>;
     fn retrieve_manifest(&self, id: &str) -> Result<Manifest>;
     fn list_manifests(&self) -> Result<Vec<ManifestMetadata>>;
     fn delete_manifest(&self, id: &str) -> Result<()>;
+
+    /// Rewrites manifests stored before compression was enabled in compressed form,
+    /// returning how many were migrated
+    fn migrate_compression(&self) -> Result<usize> {
+        Err(Error::Storage(
+            "Compression is not supported by this storage backend".to_string(),
+        ))
+    }
+
+    /// Whether the backend can list the manifests it stores. Lookups over all stored
+    /// manifests, such as finding duplicates, are skipped for backends that can't.
+    fn supports_listing(&self) -> bool {
+        true
+    }
+
+    /// Whether the backend stores attestation envelopes
+    fn supports_envelopes(&self) -> bool {
+        false
+    }
+
+    /// Stores a signed attestation envelope, returning its ID
+    fn store_envelope(&self, _record: &EnvelopeRecord) -> Result<String> {
+        Err(Error::Storage(
+            "Envelopes are not supported by this storage backend".to_string(),
+        ))
+    }
+
+    fn retrieve_envelope(&self, _id: &str) -> Result<EnvelopeRecord> {
+        Err(Error::Storage(
+            "Envelopes are not supported by this storage backend".to_string(),
+        ))
+    }
+
+    fn list_envelopes(&self) -> Result<Vec<EnvelopeMetadata>> {
+        Err(Error::Storage(
+            "Envelopes are not supported by this storage backend".to_string(),
+        ))
+    }
+
     fn as_any(&self) -> &dyn Any;
 }
 
+/// Kind of attestation a stored envelope holds
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EnvelopeKind {
+    /// OMS manifest of a model, see `model create --format oms`
+    Oms,
+    /// SLSA build provenance of a pipeline
+    Slsa,
+    /// Statement with another predicate type, see `attestation create`
+    Custom,
+}
+
+impl fmt::Display for EnvelopeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EnvelopeKind::Oms => write!(f, "OMS"),
+            EnvelopeKind::Slsa => write!(f, "SLSA"),
+            EnvelopeKind::Custom => write!(f, "in-toto"),
+        }
+    }
+}
+
+/// A DSSE envelope stored alongside manifests
+///
+/// # Examples
+///
+/// ```
+/// use atlas_cli::in_toto::dsse::Envelope;
+/// use atlas_cli::storage::traits::{EnvelopeKind, EnvelopeRecord};
+///
+/// let envelope = Envelope::new(&b"{}".to_vec(), "application/vnd.in-toto+json".to_string());
+/// let record = EnvelopeRecord::new(EnvelopeKind::Slsa, "build.sh", None, envelope);
+///
+/// assert!(record.id.starts_with("urn:uuid:"));
+/// assert_eq!(record.metadata().kind, EnvelopeKind::Slsa);
+/// ```
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EnvelopeRecord {
+    pub id: String,
+    pub kind: EnvelopeKind,
+    /// Name of the attested model or pipeline
+    pub name: String,
+    /// ID of the stored manifest the envelope wraps, for OMS envelopes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manifest_id: Option<String>,
+    pub created_at: String,
+    pub envelope: Envelope,
+}
+
+impl EnvelopeRecord {
+    /// Creates a record with a new ID
+    pub fn new(
+        kind: EnvelopeKind,
+        name: &str,
+        manifest_id: Option<String>,
+        envelope: Envelope,
+    ) -> Self {
+        Self {
+            id: format!("urn:uuid:{}", uuid::Uuid::new_v4()),
+            kind,
+            name: name.to_string(),
+            manifest_id,
+            created_at: to_rfc3339_utc(time::OffsetDateTime::now_utc()),
+            envelope,
+        }
+    }
+
+    pub fn metadata(&self) -> EnvelopeMetadata {
+        EnvelopeMetadata {
+            id: self.id.clone(),
+            kind: self.kind,
+            name: self.name.clone(),
+            manifest_id: self.manifest_id.clone(),
+            created_at: self.created_at.clone(),
+        }
+    }
+}
+
+/// Represents metadata about a stored envelope
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EnvelopeMetadata {
+    pub id: String,
+    pub kind: EnvelopeKind,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manifest_id: Option<String>,
+    pub created_at: String,
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
 pub enum ManifestType {
     Dataset,
@@ -73,6 +233,7 @@ pub enum ManifestType {
 ///     url: "file:///path/to/file".to_string(),
 ///     file_path: Some(PathBuf::from("/path/to/file")),
 ///     hash: "a".repeat(64),
+///     size: Some(1024),
 /// };
 ///
 /// assert!(location.file_path.is_some());
@@ -82,6 +243,9 @@ pub struct ArtifactLocation {
     pub url: String,
     pub file_path: Option<PathBuf>,
     pub hash: String,
+    /// Size in bytes, compared before hashing when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
 }
 
 impl fmt::Display for ManifestType {
@@ -98,19 +262,35 @@ impl fmt::Display for ManifestType {
 impl ArtifactLocation {
     pub fn new(path: PathBuf) -> Result<Self> {
         let hash = crate::hash::calculate_file_hash(&path)?;
-        let url = format!("file://{}", path.to_string_lossy());
+        let url = crate::utils::path_to_file_url(&path);
+        let size = fs::metadata(&path)?.len();
 
         Ok(Self {
             url,
             file_path: Some(path),
             hash,
+            size: Some(size),
         })
     }
 
+    /// Verifies the file against the recorded hash, using the algorithm its length implies.
+    ///
+    /// If the size is known it is compared first, so that truncated or extended files
+    /// are rejected without hashing them.
     pub fn verify(&self) -> Result<bool> {
+        self.verify_with_algorithm(&crate::hash::detect_hash_algorithm(&self.hash))
+    }
+
+    /// Verifies the file against the recorded hash, computed with `algorithm`
+    pub fn verify_with_algorithm(&self, algorithm: &HashAlgorithm) -> Result<bool> {
         match &self.file_path {
             Some(path) => {
-                let current_hash = crate::hash::calculate_file_hash(path)?;
+                if self.size_mismatch()?.is_some() {
+                    return Ok(false);
+                }
+
+                let current_hash =
+                    crate::hash::calculate_file_hash_with_algorithm(path, algorithm)?;
                 Ok(current_hash == self.hash)
             }
             None => Err(Error::Validation(
@@ -118,4 +298,24 @@ impl ArtifactLocation {
             )),
         }
     }
+
+    /// Describes how the file size differs from the recorded size, e.g. "truncated by
+    /// 6 bytes". Returns `None` if the sizes match or no size was recorded.
+    pub fn size_mismatch(&self) -> Result<Option<String>> {
+        let (Some(path), Some(expected)) = (&self.file_path, self.size) else {
+            return Ok(None);
+        };
+
+        let actual = fs::metadata(path)?.len();
+        let change = match actual.cmp(&expected) {
+            std::cmp::Ordering::Equal => return Ok(None),
+            std::cmp::Ordering::Less => "truncated",
+            std::cmp::Ordering::Greater => "extended",
+        };
+
+        Ok(Some(format!(
+            "{change} by {} bytes (expected {expected} bytes, found {actual})",
+            actual.abs_diff(expected)
+        )))
+    }
 }
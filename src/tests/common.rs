@@ -1,7 +1,15 @@
 use crate::error::{Error, Result};
+use crate::hash::calculate_file_hash;
+use crate::manifest::common::AssetKind;
 use crate::storage::traits::{ManifestMetadata, ManifestType, StorageBackend};
+use atlas_c2pa_lib::assertion::{
+    Action, ActionAssertion, Assertion, Author, CreativeWorkAssertion,
+};
+use atlas_c2pa_lib::asset_type::AssetType;
 use atlas_c2pa_lib::claim::ClaimV2;
+use atlas_c2pa_lib::cross_reference::CrossReference;
 use atlas_c2pa_lib::datetime_wrapper::OffsetDateTimeWrapper;
+use atlas_c2pa_lib::ingredient::{Ingredient, IngredientData};
 use atlas_c2pa_lib::manifest::Manifest;
 use std::collections::HashMap;
 use std::sync::Mutex;
@@ -74,6 +82,7 @@ impl StorageBackend for MockStorageBackend {
                     ManifestType::Model
                 },
                 created_at: manifest.created_at.0.to_string(),
+                signed: Some(manifest.claim.signature.is_some()),
             })
             .collect())
     }
@@ -100,3 +109,158 @@ pub fn create_default_claim() -> ClaimV2 {
         created_at: OffsetDateTimeWrapper(OffsetDateTime::now_utc()),
     }
 }
+
+/// Builds unsigned test manifests: a random `urn:c2pa:` ID, no ingredients or
+/// cross-references, a default claim and no `claim_v2`, unless set otherwise
+pub struct ManifestBuilder {
+    manifest: Manifest,
+}
+
+impl ManifestBuilder {
+    pub fn new(title: &str) -> Self {
+        Self {
+            manifest: Manifest {
+                claim_generator: "test".to_string(),
+                title: title.to_string(),
+                instance_id: format!("urn:c2pa:{}", Uuid::new_v4()),
+                ingredients: vec![],
+                claim: create_default_claim(),
+                created_at: OffsetDateTimeWrapper(OffsetDateTime::now_utc()),
+                cross_references: vec![],
+                claim_v2: None,
+                is_active: true,
+            },
+        }
+    }
+
+    pub fn id(mut self, id: &str) -> Self {
+        self.manifest.instance_id = id.to_string();
+        self
+    }
+
+    pub fn claim_generator(mut self, claim_generator: &str) -> Self {
+        self.manifest.claim_generator = claim_generator.to_string();
+        self
+    }
+
+    pub fn ingredients(mut self, ingredients: Vec<Ingredient>) -> Self {
+        self.manifest.ingredients = ingredients;
+        self
+    }
+
+    pub fn claim(mut self, claim: ClaimV2) -> Self {
+        self.manifest.claim = claim;
+        self
+    }
+
+    pub fn claim_v2(mut self, claim_v2: Option<ClaimV2>) -> Self {
+        self.manifest.claim_v2 = claim_v2;
+        self
+    }
+
+    pub fn created_at(mut self, created_at: OffsetDateTime) -> Self {
+        self.manifest.created_at = OffsetDateTimeWrapper(created_at);
+        self
+    }
+
+    pub fn cross_references(mut self, cross_references: Vec<CrossReference>) -> Self {
+        self.manifest.cross_references = cross_references;
+        self
+    }
+
+    pub fn build(self) -> Manifest {
+        self.manifest
+    }
+}
+
+pub fn create_test_ingredient_internal(
+    path: &std::path::Path,
+    title: &str,
+    asset_type: AssetType,
+    format: &str,
+) -> Result<Ingredient> {
+    let url = format!("file://{}", path.to_string_lossy());
+    let hash = calculate_file_hash(path)?;
+
+    Ok(Ingredient {
+        title: title.to_string(),
+        format: format.to_string(),
+        relationship: "componentOf".to_string(),
+        document_id: format!("uuid:{}", Uuid::new_v4()),
+        instance_id: format!("uuid:{}", Uuid::new_v4()),
+        data: IngredientData {
+            url,
+            alg: "sha384".to_string(),
+            hash,
+            data_types: vec![asset_type],
+            linked_ingredient_url: None,
+            linked_ingredient_hash: None,
+        },
+        linked_ingredient: None,
+        public_key: None,
+    })
+}
+
+pub fn create_test_manifest_internal(
+    id: String,
+    ingredients: Vec<Ingredient>,
+    title: &str,
+    asset_kind: AssetKind,
+) -> Result<Manifest> {
+    // Create appropriate assertions based on asset kind
+    let creative_type = match asset_kind {
+        AssetKind::Model => "Model",
+        AssetKind::Dataset => "Dataset",
+        AssetKind::Software => "Software",
+        AssetKind::Evaluation => "EvaluationResult",
+    };
+
+    let digital_source_type = match asset_kind {
+        AssetKind::Model => "http://cv.iptc.org/newscodes/digitalsourcetype/algorithmicMedia",
+        AssetKind::Dataset => "http://cv.iptc.org/newscodes/digitalsourcetype/dataset",
+        AssetKind::Software => "http://cv.iptc.org/newscodes/digitalsourcetype/software",
+        AssetKind::Evaluation => "http://cv.iptc.org/newscodes/digitalsourcetype/evaluationResult",
+    };
+
+    let assertions = vec![
+        Assertion::CreativeWork(CreativeWorkAssertion {
+            context: "http://schema.org/".to_string(),
+            creative_type: creative_type.to_string(),
+            author: vec![Author {
+                author_type: "Organization".to_string(),
+                name: "Test Organization".to_string(),
+            }],
+        }),
+        Assertion::Action(ActionAssertion {
+            actions: vec![Action {
+                action: match asset_kind {
+                    AssetKind::Evaluation => "c2pa.evaluation".to_string(),
+                    _ => "c2pa.created".to_string(),
+                },
+                software_agent: Some("test".to_string()),
+                parameters: Some(serde_json::json!({
+                    "name": title,
+                })),
+                digital_source_type: Some(digital_source_type.to_string()),
+                instance_id: None,
+            }],
+        }),
+    ];
+
+    // Create claim with the assertions
+    let claim = ClaimV2 {
+        instance_id: format!("xmp:iid:{}", Uuid::new_v4()),
+        ingredients: ingredients.clone(),
+        created_assertions: assertions,
+        claim_generator_info: "test".to_string(),
+        signature: None,
+        created_at: OffsetDateTimeWrapper(OffsetDateTime::now_utc()),
+    };
+
+    Ok(ManifestBuilder::new(title)
+        .id(&id)
+        .ingredients(ingredients)
+        .claim(claim.clone())
+        .claim_v2(Some(claim))
+        .build())
+}
@@ -1,9 +1,11 @@
 use crate::error::Result;
-use crate::manifest::common::{AssetKind, create_manifest};
+use crate::manifest::common::{AssetKind, attach_cc_attestation, create_manifest};
 use crate::manifest::config::ManifestCreationConfig;
+use crate::signing::test_utils::generate_temp_key;
 use crate::storage::traits::StorageBackend;
 use crate::tests::common::MockStorageBackend;
 use crate::utils::safe_create_file;
+use atlas_c2pa_lib::assertion::Assertion;
 use atlas_c2pa_lib::cose::HashAlgorithm;
 use std::io::Write;
 use tempfile::tempdir;
@@ -34,6 +36,14 @@ fn test_manifest_with_cc_attestation() -> Result<()> {
     let config = ManifestCreationConfig {
         paths,
         ingredient_names,
+        ingredient_relationships: Vec::new(),
+        expand_archives: false,
+        profile_data: false,
+        max_sample_bytes: crate::manifest::profile::DEFAULT_MAX_SAMPLE_BYTES,
+        stream_connections: crate::manifest::object_store::DEFAULT_CONNECTIONS,
+        ingest_policy: crate::manifest::ingest::IngestPolicy::default(),
+        path_base: None,
+        dataset_ids: Vec::new(),
         name,
         author_org: Some("Test Organization".to_string()),
         author_name: Some("Test Author".to_string()),
@@ -42,12 +52,21 @@ fn test_manifest_with_cc_attestation() -> Result<()> {
         storage: Some(storage),
         print: true,
         output_encoding: "json".to_string(),
+        output_path: None,
         key_path: None,
+        pq_key_path: None,
         hash_alg: HashAlgorithm::Sha384,
+        valid_until: None,
+        created_at: None,
         with_cc,
+        tpm: None,
         software_type: None,
         version: None,
+        encryption: None,
+        content_addressed_id: false,
+        force_new: false,
         custom_fields: None,
+        inherited_assertions: vec![],
     };
 
     // Create the manifest with CC attestation enabled
@@ -92,6 +111,14 @@ fn test_manifest_without_cc_attestation() -> Result<()> {
     let config = ManifestCreationConfig {
         paths,
         ingredient_names,
+        ingredient_relationships: Vec::new(),
+        expand_archives: false,
+        profile_data: false,
+        max_sample_bytes: crate::manifest::profile::DEFAULT_MAX_SAMPLE_BYTES,
+        stream_connections: crate::manifest::object_store::DEFAULT_CONNECTIONS,
+        ingest_policy: crate::manifest::ingest::IngestPolicy::default(),
+        path_base: None,
+        dataset_ids: Vec::new(),
         name,
         author_org: Some("Test Organization".to_string()),
         author_name: Some("Test Author".to_string()),
@@ -100,12 +127,21 @@ fn test_manifest_without_cc_attestation() -> Result<()> {
         storage: Some(storage),
         print: true,
         output_encoding: "json".to_string(),
+        output_path: None,
         key_path: None,
+        pq_key_path: None,
         hash_alg: HashAlgorithm::Sha384,
+        valid_until: None,
+        created_at: None,
         with_cc,
+        tpm: None,
         software_type: None,
         version: None,
+        encryption: None,
+        content_addressed_id: false,
+        force_new: false,
         custom_fields: None,
+        inherited_assertions: vec![],
     };
 
     // Create the manifest without CC attestation
@@ -154,6 +190,14 @@ fn test_compare_manifests_with_and_without_attestation() -> Result<()> {
     let config_with_cc = ManifestCreationConfig {
         paths: paths.clone(),
         ingredient_names: ingredient_names.clone(),
+        ingredient_relationships: Vec::new(),
+        expand_archives: false,
+        profile_data: false,
+        max_sample_bytes: crate::manifest::profile::DEFAULT_MAX_SAMPLE_BYTES,
+        stream_connections: crate::manifest::object_store::DEFAULT_CONNECTIONS,
+        ingest_policy: crate::manifest::ingest::IngestPolicy::default(),
+        path_base: None,
+        dataset_ids: Vec::new(),
         name: name.clone(),
         author_org: Some("Test Organization".to_string()),
         author_name: Some("Test Author".to_string()),
@@ -162,12 +206,21 @@ fn test_compare_manifests_with_and_without_attestation() -> Result<()> {
         storage: Some(storage_with_cc),
         print: false,
         output_encoding: "json".to_string(),
+        output_path: None,
         key_path: None,
+        pq_key_path: None,
         hash_alg: HashAlgorithm::Sha384,
+        valid_until: None,
+        created_at: None,
         with_cc: true,
+        tpm: None,
         software_type: None,
         version: None,
+        encryption: None,
+        content_addressed_id: false,
+        force_new: false,
         custom_fields: None,
+        inherited_assertions: vec![],
     };
     create_manifest(config_with_cc, AssetKind::Model)?;
 
@@ -175,6 +228,14 @@ fn test_compare_manifests_with_and_without_attestation() -> Result<()> {
     let config_without_cc = ManifestCreationConfig {
         paths,
         ingredient_names,
+        ingredient_relationships: Vec::new(),
+        expand_archives: false,
+        profile_data: false,
+        max_sample_bytes: crate::manifest::profile::DEFAULT_MAX_SAMPLE_BYTES,
+        stream_connections: crate::manifest::object_store::DEFAULT_CONNECTIONS,
+        ingest_policy: crate::manifest::ingest::IngestPolicy::default(),
+        path_base: None,
+        dataset_ids: Vec::new(),
         name,
         author_org: Some("Test Organization".to_string()),
         author_name: Some("Test Author".to_string()),
@@ -183,12 +244,21 @@ fn test_compare_manifests_with_and_without_attestation() -> Result<()> {
         storage: Some(storage_without_cc),
         print: false,
         output_encoding: "json".to_string(),
+        output_path: None,
         key_path: None,
+        pq_key_path: None,
         hash_alg: HashAlgorithm::Sha384,
+        valid_until: None,
+        created_at: None,
         with_cc: false,
+        tpm: None,
         software_type: None,
         version: None,
+        encryption: None,
+        content_addressed_id: false,
+        force_new: false,
         custom_fields: None,
+        inherited_assertions: vec![],
     };
     create_manifest(config_without_cc, AssetKind::Model)?;
 
@@ -211,3 +281,79 @@ fn test_compare_manifests_with_and_without_attestation() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_attach_cc_attestation() -> Result<()> {
+    let dir = tempdir()?;
+    let test_file_path = dir.path().join("test_model.onnx");
+    {
+        let mut file = safe_create_file(&test_file_path, false)?;
+        file.write_all(b"test model data")?;
+    }
+
+    let storage = Box::leak(Box::new(MockStorageBackend::new_empty()));
+    let (_key, key_dir) = generate_temp_key()?;
+    let key_path = key_dir.path().join("test_key.pem");
+
+    let config = ManifestCreationConfig {
+        paths: vec![test_file_path],
+        ingredient_names: vec!["Test Model".to_string()],
+        ingredient_relationships: Vec::new(),
+        expand_archives: false,
+        profile_data: false,
+        max_sample_bytes: crate::manifest::profile::DEFAULT_MAX_SAMPLE_BYTES,
+        stream_connections: crate::manifest::object_store::DEFAULT_CONNECTIONS,
+        ingest_policy: crate::manifest::ingest::IngestPolicy::default(),
+        path_base: None,
+        dataset_ids: Vec::new(),
+        name: "Model Attested Later".to_string(),
+        author_org: None,
+        author_name: None,
+        description: None,
+        linked_manifests: None,
+        storage: Some(storage),
+        print: false,
+        output_encoding: "json".to_string(),
+        output_path: None,
+        key_path: Some(key_path.clone()),
+        pq_key_path: None,
+        hash_alg: HashAlgorithm::Sha384,
+        valid_until: None,
+        created_at: None,
+        with_cc: false,
+        tpm: None,
+        software_type: None,
+        version: None,
+        encryption: None,
+        content_addressed_id: false,
+        force_new: false,
+        custom_fields: None,
+        inherited_assertions: vec![],
+    };
+    create_manifest(config, AssetKind::Model)?;
+
+    let id = storage.list_manifests()?[0].id.clone();
+    let original = storage.retrieve_manifest(&id)?;
+    let original_hash = crate::hash::calculate_hash(serde_json::to_string(&original)?.as_bytes());
+
+    // a signed manifest cannot be updated without re-signing
+    assert!(attach_cc_attestation(&id, storage, None, HashAlgorithm::Sha384).is_err());
+
+    let updated_id = attach_cc_attestation(&id, storage, Some(key_path), HashAlgorithm::Sha384)?;
+    let updated = storage.retrieve_manifest(&updated_id)?;
+
+    assert_eq!(
+        updated.claim.created_assertions.len(),
+        original.claim.created_assertions.len() + 1
+    );
+    let attached = match updated.claim.created_assertions.last() {
+        Some(Assertion::CustomAssertion(custom)) => custom.data.clone(),
+        other => panic!("Expected attached CC assertion, got {other:?}"),
+    };
+    assert_eq!(attached["manifest_id"], id.as_str());
+    assert_eq!(attached["manifest_hash"], original_hash.as_str());
+    assert!(updated.claim.signature.is_some());
+    assert_ne!(updated.claim.signature, original.claim.signature);
+
+    Ok(())
+}
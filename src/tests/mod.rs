@@ -1,5 +1,6 @@
 mod cc_attestation;
-mod common;
+pub(crate) mod common;
 mod manifest;
 mod manifest_attestation;
+mod registry;
 mod storage;
@@ -0,0 +1,123 @@
+use super::common::{ManifestBuilder, MockStorageBackend};
+use crate::error::Result;
+use crate::registry::{
+    ManifestSummary, Registry, RegistryEntry, VerificationStatus, find_gaps, push_summaries,
+};
+use atlas_c2pa_lib::asset_type::AssetType;
+use atlas_c2pa_lib::ingredient::{Ingredient, IngredientData};
+use atlas_c2pa_lib::manifest::Manifest;
+use std::sync::Mutex;
+
+#[derive(Default)]
+struct MockRegistry {
+    entries: Vec<RegistryEntry>,
+    pushed: Mutex<Vec<(String, ManifestSummary)>>,
+}
+
+impl Registry for MockRegistry {
+    fn push(&self, entry: &str, summary: &ManifestSummary) -> Result<()> {
+        self.pushed
+            .lock()
+            .unwrap()
+            .push((entry.to_string(), summary.clone()));
+        Ok(())
+    }
+
+    fn entries(&self) -> Result<Vec<RegistryEntry>> {
+        Ok(self.entries.clone())
+    }
+}
+
+fn model_manifest(id: &str, title: &str) -> Manifest {
+    ManifestBuilder::new(title)
+        .id(id)
+        .ingredients(vec![Ingredient {
+            title: "weights".to_string(),
+            format: "application/octet-stream".to_string(),
+            relationship: "componentOf".to_string(),
+            document_id: "uuid:weights".to_string(),
+            instance_id: "uuid:weights".to_string(),
+            data: IngredientData {
+                url: "model.onnx".to_string(),
+                alg: "sha384".to_string(),
+                hash: "ab".repeat(48),
+                data_types: vec![AssetType::Model],
+                linked_ingredient_url: None,
+                linked_ingredient_hash: None,
+            },
+            linked_ingredient: None,
+            public_key: None,
+        }])
+        .build()
+}
+
+fn entry(name: &str, manifest_id: Option<&str>) -> RegistryEntry {
+    RegistryEntry {
+        name: name.to_string(),
+        manifest_id: manifest_id.map(str::to_string),
+    }
+}
+
+#[test]
+fn test_manifest_summary() {
+    let summary = ManifestSummary::new(
+        &model_manifest("urn:c2pa:model", "Model"),
+        VerificationStatus::Verified,
+    );
+
+    assert_eq!(
+        summary.digests["weights"],
+        format!("sha384:{}", "ab".repeat(48))
+    );
+    assert!(summary.model_card.starts_with("# Model\n"));
+    assert!(
+        summary
+            .model_card
+            .contains("Atlas manifest `urn:c2pa:model` (verified)")
+    );
+    assert!(summary.model_card.contains("| weights | `sha384:abab"));
+}
+
+#[test]
+fn test_registry_sync() -> Result<()> {
+    let storage = MockStorageBackend::new(model_manifest("urn:c2pa:model", "Model"));
+    let registry = MockRegistry {
+        entries: vec![
+            entry("org/Model", None),
+            entry("Untracked", None),
+            entry("Deleted", Some("urn:c2pa:deleted")),
+            entry("Synced", Some("urn:c2pa:model")),
+        ],
+        ..MockRegistry::default()
+    };
+
+    // Without IDs, every model manifest is pushed to the entry named like it
+    let results = push_summaries(&registry, &storage, &[], None, false, None)?;
+    assert_eq!(results.len(), 1);
+    assert!(results[0].outcome.is_ok());
+    {
+        let pushed = registry.pushed.lock().unwrap();
+        assert_eq!(pushed[0].0, "Model");
+        assert_eq!(pushed[0].1.manifest_id, "urn:c2pa:model");
+        assert_eq!(pushed[0].1.verification, VerificationStatus::NotChecked);
+    }
+
+    let ids = vec!["urn:c2pa:model".to_string()];
+    push_summaries(&registry, &storage, &ids, Some("org/Model"), false, None)?;
+    assert_eq!(registry.pushed.lock().unwrap()[1].0, "org/Model");
+    let ids = vec!["urn:c2pa:model".to_string(), "urn:c2pa:model".to_string()];
+    assert!(push_summaries(&registry, &storage, &ids, Some("org/Model"), false, None).is_err());
+
+    let gaps = find_gaps(&registry, &storage)?;
+    assert_eq!(gaps.missing, vec![entry("Untracked", None)]);
+    assert_eq!(
+        gaps.unsynced,
+        vec![(entry("org/Model", None), vec!["urn:c2pa:model".to_string()])]
+    );
+    assert_eq!(gaps.stale, vec![entry("Deleted", Some("urn:c2pa:deleted"))]);
+    assert!(
+        gaps.render()
+            .contains("stale     Deleted (urn:c2pa:deleted is not stored)")
+    );
+    Ok(())
+}
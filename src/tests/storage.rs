@@ -1,16 +1,34 @@
-use super::common::{MockStorageBackend, create_default_claim};
-use crate::cli::commands::DatasetCommands;
+use super::common::{ManifestBuilder, MockStorageBackend};
+use crate::cli::commands::{DatasetCommands, ListArgs, ListFormatChoice, SignatureFilterArgs};
 use crate::cli::handlers::handle_dataset_command;
 use crate::error::Result;
+use crate::manifest::object_store::{ObjectClient, ObjectStore};
+use crate::signing::signable::Signable;
+use crate::signing::test_utils::generate_temp_key;
+use crate::storage::auth::Credentials;
+use crate::storage::chunked::{
+    CHUNK_ATTEMPTS, CHUNK_SHA256_HEADER, CONTENT_SHA256_HEADER, CompleteUpload,
+};
+use crate::storage::database::{DatabaseStorage, IDEMPOTENCY_KEY_HEADER};
 use crate::storage::filesystem::FilesystemStorage;
+use crate::storage::plugin::PluginStorage;
+use crate::storage::rate_limit::RateLimit;
+use crate::storage::rekor::{RekorEntry, RekorStorage};
+use crate::storage::s3::S3Storage;
 use crate::storage::traits::ArtifactLocation;
 use crate::storage::traits::StorageBackend;
 use crate::utils::safe_create_file;
+use atlas_c2pa_lib::cose::HashAlgorithm;
 use atlas_c2pa_lib::datetime_wrapper::OffsetDateTimeWrapper;
 use atlas_c2pa_lib::manifest::Manifest;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
 use sha2::Digest;
+use std::collections::HashMap;
 use std::fs;
-use std::io::Write;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
 use tempfile::tempdir;
 use time::OffsetDateTime;
 use uuid::Uuid;
@@ -40,21 +58,45 @@ fn test_artifact_location() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_artifact_location_size_mismatch() -> Result<()> {
+    let dir = tempdir()?;
+    let file_path = dir.path().join("model.bin");
+    fs::write(&file_path, b"0123456789")?;
+
+    let location = ArtifactLocation::new(file_path.clone())?;
+    assert_eq!(location.size, Some(10));
+
+    fs::write(&file_path, b"0123")?;
+    assert_eq!(
+        location.size_mismatch()?.as_deref(),
+        Some("truncated by 6 bytes (expected 10 bytes, found 4)")
+    );
+    assert!(!location.verify()?);
+
+    fs::write(&file_path, b"0123456789abc")?;
+    assert_eq!(
+        location.size_mismatch()?.as_deref(),
+        Some("extended by 3 bytes (expected 10 bytes, found 13)")
+    );
+
+    // Without a recorded size only the hash is compared
+    let location = ArtifactLocation {
+        size: None,
+        ..location
+    };
+    assert!(!location.verify()?);
+
+    Ok(())
+}
+
 #[test]
 fn test_mock_storage() -> Result<()> {
     // Create test manifest
     let manifest_id = format!("test_manifest_{}", Uuid::new_v4());
-    let manifest = Manifest {
-        claim_generator: "test".to_string(),
-        title: "Test Manifest".to_string(),
-        instance_id: manifest_id.clone(),
-        ingredients: Vec::new(),
-        claim: create_default_claim(),
-        created_at: OffsetDateTimeWrapper(OffsetDateTime::now_utc()),
-        cross_references: vec![],
-        claim_v2: None,
-        is_active: true,
-    };
+    let manifest = ManifestBuilder::new("Test Manifest")
+        .id(&manifest_id)
+        .build();
 
     // Initialize storage
     let storage = MockStorageBackend::new(manifest.clone());
@@ -88,17 +130,9 @@ fn test_filesystem_storage() -> Result<()> {
     let manifest_id = format!("test_manifest_{}", Uuid::new_v4());
     println!("Created manifest ID: {manifest_id}");
 
-    let manifest = Manifest {
-        claim_generator: "test".to_string(),
-        title: "Test Filesystem Storage".to_string(),
-        instance_id: manifest_id.clone(),
-        ingredients: Vec::new(),
-        claim: create_default_claim(),
-        created_at: OffsetDateTimeWrapper(OffsetDateTime::now_utc()),
-        cross_references: vec![],
-        claim_v2: None,
-        is_active: true,
-    };
+    let manifest = ManifestBuilder::new("Test Filesystem Storage")
+        .id(&manifest_id)
+        .build();
 
     // Store the manifest
     let stored_id = fs_storage.store_manifest(&manifest)?;
@@ -128,6 +162,47 @@ fn test_filesystem_storage() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_filesystem_metadata_index() -> Result<()> {
+    let dir = tempdir()?;
+    let fs_storage = FilesystemStorage::new(dir.path())?;
+    let manifest = |title: &str| ManifestBuilder::new(title).build();
+    let mut first = manifest("First");
+    let second = manifest("Second");
+    fs_storage.store_manifest(&first)?;
+    fs_storage.store_manifest(&second)?;
+
+    let listed = fs_storage.list_manifests()?;
+    assert_eq!(listed.len(), 2);
+    assert!(listed.iter().all(|metadata| metadata.signed == Some(false)));
+
+    // Unchanged manifests are listed from the index without being parsed
+    let index_path = dir.path().join("manifest_metadata.json");
+    let index = fs::read_to_string(&index_path)?;
+    fs::write(&index_path, index.replace("\"Second\"", "\"Indexed\""))?;
+    let mut names: Vec<String> = fs_storage
+        .list_manifests()?
+        .into_iter()
+        .map(|metadata| metadata.name)
+        .collect();
+    names.sort();
+    assert_eq!(names, ["First", "Indexed"]);
+
+    // Changed manifests are parsed again, and deleted ones dropped from the index
+    first.title = "First, renamed".to_string();
+    fs_storage.store_manifest(&first)?;
+    fs_storage.delete_manifest(&second.instance_id)?;
+    let listed = fs_storage.list_manifests()?;
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].name, "First, renamed");
+    assert!(!fs::read_to_string(&index_path)?.contains(&second.instance_id));
+
+    // A damaged index is rebuilt
+    fs::write(&index_path, "not json")?;
+    assert_eq!(fs_storage.list_manifests()?[0].name, "First, renamed");
+    Ok(())
+}
+
 #[test]
 fn test_filesystem_storage_extended() -> Result<()> {
     // Create a temporary directory for storage
@@ -141,17 +216,9 @@ fn test_filesystem_storage_extended() -> Result<()> {
     let manifest_id = format!("test_manifest_{}", Uuid::new_v4());
     println!("Created manifest ID: {manifest_id}");
 
-    let manifest = Manifest {
-        claim_generator: "test".to_string(),
-        title: "Test Filesystem Storage".to_string(),
-        instance_id: manifest_id.clone(),
-        ingredients: Vec::new(),
-        claim: create_default_claim(),
-        created_at: OffsetDateTimeWrapper(OffsetDateTime::now_utc()),
-        cross_references: vec![],
-        claim_v2: None,
-        is_active: true,
-    };
+    let manifest = ManifestBuilder::new("Test Filesystem Storage")
+        .id(&manifest_id)
+        .build();
 
     // Store the manifest
     fs_storage.store_manifest(&manifest)?;
@@ -219,30 +286,10 @@ fn test_filesystem_storage_linking() -> Result<()> {
     println!("Created model ID: {model_id}");
 
     // Create dataset manifest
-    let dataset_manifest = Manifest {
-        claim_generator: "test".to_string(),
-        title: "Test Dataset".to_string(),
-        instance_id: dataset_id.clone(),
-        ingredients: Vec::new(),
-        claim: create_default_claim(),
-        created_at: OffsetDateTimeWrapper(OffsetDateTime::now_utc()),
-        cross_references: vec![],
-        claim_v2: None,
-        is_active: true,
-    };
+    let dataset_manifest = ManifestBuilder::new("Test Dataset").id(&dataset_id).build();
 
     // Create model manifest
-    let mut model_manifest = Manifest {
-        claim_generator: "test".to_string(),
-        title: "Test Model".to_string(),
-        instance_id: model_id.clone(),
-        ingredients: Vec::new(),
-        claim: create_default_claim(),
-        created_at: OffsetDateTimeWrapper(OffsetDateTime::now_utc()),
-        cross_references: vec![],
-        claim_v2: None,
-        is_active: true,
-    };
+    let mut model_manifest = ManifestBuilder::new("Test Model").id(&model_id).build();
 
     // Store both manifests
     fs_storage.store_manifest(&dataset_manifest)?;
@@ -320,6 +367,19 @@ fn test_cli_handler_storage_selection() -> Result<()> {
     for (storage_type, storage_url) in test_cases {
         // Mock the CLI command
         let cmd = DatasetCommands::List {
+            list: ListArgs {
+                format: ListFormatChoice::Text,
+                columns: Vec::new(),
+                sort: None,
+                reverse: false,
+                limit: None,
+                signature: SignatureFilterArgs {
+                    signed_only: false,
+                    unsigned_only: false,
+                    signed_by: Vec::new(),
+                    signed_by_revoked: false,
+                },
+            },
             storage_type: Box::new(storage_type.to_string()),
             storage_url: Box::new(storage_url.to_string()),
         };
@@ -360,3 +420,2329 @@ fn test_cli_handler_storage_selection() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_filesystem_storage_compression_migration() -> Result<()> {
+    let dir = tempdir()?;
+
+    // Store a manifest the way older versions did, as plain JSON
+    let legacy_storage =
+        FilesystemStorage::new(dir.path().to_string_lossy().to_string())?.with_compression(false);
+    let legacy_id = format!("legacy_manifest_{}", Uuid::new_v4());
+    let legacy_manifest = ManifestBuilder::new("Legacy Manifest")
+        .id(&legacy_id)
+        .build();
+    legacy_storage.store_manifest(&legacy_manifest)?;
+
+    let legacy_file = dir.path().join(format!(
+        "{}.json",
+        hex::encode(sha2::Sha256::digest(legacy_id.as_bytes()))
+    ));
+    assert!(legacy_file.exists());
+
+    // New manifests are compressed, and both formats remain readable
+    let fs_storage = FilesystemStorage::new(dir.path().to_string_lossy().to_string())?;
+    let mut manifest = legacy_manifest.clone();
+    manifest.instance_id = format!("test_manifest_{}", Uuid::new_v4());
+    manifest.title = "Compressed Manifest".to_string();
+    fs_storage.store_manifest(&manifest)?;
+
+    assert_eq!(fs_storage.list_manifests()?.len(), 2);
+    assert_eq!(
+        fs_storage.retrieve_manifest(&legacy_id)?.title,
+        "Legacy Manifest"
+    );
+    assert_eq!(
+        fs_storage.retrieve_manifest(&manifest.instance_id)?.title,
+        "Compressed Manifest"
+    );
+
+    // Migrate the legacy entry
+    assert_eq!(fs_storage.migrate_compression()?, 1);
+    assert!(!legacy_file.exists());
+    let compressed_file = legacy_file.with_extension("json.zst");
+    assert!(crate::storage::compression::is_compressed(&fs::read(
+        &compressed_file
+    )?));
+    assert_eq!(
+        fs_storage.retrieve_manifest(&legacy_id)?.title,
+        "Legacy Manifest"
+    );
+
+    // Nothing left to migrate
+    assert_eq!(fs_storage.migrate_compression()?, 0);
+
+    fs_storage.delete_manifest(&legacy_id)?;
+    assert!(!compressed_file.exists());
+    assert_eq!(fs_storage.list_manifests()?.len(), 1);
+
+    Ok(())
+}
+
+// Accepts one hashedrekord entry and serves it back by UUID and by search. The answers to
+// the first `lost_answers` entries are lost on the way, as a gateway timing out would lose
+// them; entries sent again are answered with 409 Conflict.
+fn serve_rekor(lost_answers: usize) -> (String, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+    let logged: Arc<Mutex<Option<Vec<u8>>>> = Arc::new(Mutex::new(None));
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let recorded = requests.clone();
+    let mut lost_answers = lost_answers;
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { continue };
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                    length = value.trim().parse().unwrap();
+                }
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            recorded
+                .lock()
+                .unwrap()
+                .push(request_line.trim().to_string());
+
+            let logged_entry = logged.lock().unwrap().clone();
+            let search = if request_line.contains("/index/retrieve") {
+                Some(match logged_entry {
+                    Some(_) => r#"["abc123"]"#.to_string(),
+                    None => "[]".to_string(),
+                })
+            } else {
+                None
+            };
+            if let Some(response) = search {
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{response}",
+                    response.len()
+                )
+                .unwrap();
+                continue;
+            }
+
+            if request_line.starts_with("POST") && logged_entry.is_some() {
+                write!(
+                    stream,
+                    "HTTP/1.1 409 Conflict\r\nLocation: /api/v1/log/entries/abc123\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                )
+                .unwrap();
+                continue;
+            }
+
+            let (status, entry) = if request_line.contains("/entries/retrieve") {
+                ("200 OK", logged_entry.unwrap())
+            } else if request_line.starts_with("POST") {
+                *logged.lock().unwrap() = Some(body.clone());
+                if lost_answers > 0 {
+                    lost_answers -= 1;
+                    write!(
+                        stream,
+                        "HTTP/1.1 502 Bad Gateway\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                    )
+                    .unwrap();
+                    continue;
+                }
+                ("201 Created", body)
+            } else if request_line.contains("/entries/abc123") {
+                ("200 OK", logged.lock().unwrap().clone().unwrap())
+            } else {
+                ("404 Not Found", b"{}".to_vec())
+            };
+            let mut response = serde_json::json!({
+                "abc123": {
+                    "body": STANDARD.encode(&entry),
+                    "integratedTime": 1700000000,
+                    "logID": "c0d23d6a",
+                    "logIndex": 42,
+                }
+            });
+            if request_line.contains("/entries/retrieve") {
+                response = serde_json::json!([response]);
+            }
+            let response = response.to_string();
+            write!(
+                stream,
+                "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{response}",
+                response.len()
+            )
+            .unwrap();
+        }
+    });
+
+    (endpoint, requests)
+}
+
+#[test]
+fn test_store_records_log_entry() -> Result<()> {
+    let (key, key_dir) = generate_temp_key()?;
+    let public_key_path = key_dir.path().join("public.pem");
+    fs::write(&public_key_path, key.as_pkey().public_key_to_pem().unwrap())?;
+
+    let mut manifest = ManifestBuilder::new("Logged Manifest").build();
+    manifest.sign(key_dir.path().join("test_key.pem"), HashAlgorithm::Sha512)?;
+
+    let index_dir = tempdir()?;
+    let (endpoint, _) = serve_rekor(0);
+    let storage = RekorStorage::new_with_url(endpoint)?
+        .with_public_key(&public_key_path)
+        .with_entry_index(index_dir.path().join("entries.json"));
+
+    // Rekor can't list manifests, so there are no duplicates to look for
+    assert!(storage.list_manifests().is_err());
+    assert_eq!(
+        crate::manifest::content_id::find_duplicate(&storage, &manifest)?,
+        None
+    );
+
+    assert_eq!(storage.store_manifest(&manifest)?, manifest.instance_id);
+    let expected = RekorEntry {
+        uuid: "abc123".to_string(),
+        log_index: 42,
+        integrated_time: 1700000000,
+        log_id: "c0d23d6a".to_string(),
+    };
+    assert_eq!(
+        storage.recorded_entry(&manifest.instance_id)?,
+        Some(expected.clone())
+    );
+    assert_eq!(storage.verify_entry(&manifest)?, expected);
+
+    // A different claim is not what the entry logs
+    let mut other = manifest.clone();
+    other.claim.claim_generator_info = "other".to_string();
+    assert!(storage.verify_entry(&other).is_err());
+
+    // Unsigned manifests can't be logged
+    other.claim.signature = None;
+    assert!(storage.store_manifest(&other).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_rekor_store_after_lost_answer() -> Result<()> {
+    let (key, key_dir) = generate_temp_key()?;
+    let public_key_path = key_dir.path().join("public.pem");
+    fs::write(&public_key_path, key.as_pkey().public_key_to_pem().unwrap())?;
+
+    let mut manifest = ManifestBuilder::new("Logged Manifest").build();
+    manifest.sign(key_dir.path().join("test_key.pem"), HashAlgorithm::Sha512)?;
+
+    let index_dir = tempdir()?;
+    let (endpoint, requests) = serve_rekor(1);
+    let storage = RekorStorage::new_with_url(endpoint)?
+        .with_public_key(&public_key_path)
+        .with_entry_index(index_dir.path().join("entries.json"));
+
+    // The entry is sent again after the lost answer, and the log points at the first one
+    assert_eq!(storage.store_manifest(&manifest)?, manifest.instance_id);
+    let posts = |requests: &[String]| {
+        requests
+            .iter()
+            .filter(|line| line.starts_with("POST"))
+            .count()
+    };
+    assert_eq!(posts(&requests.lock().unwrap()), 2);
+    assert_eq!(
+        storage
+            .recorded_entry(&manifest.instance_id)?
+            .map(|entry| entry.uuid),
+        Some("abc123".to_string())
+    );
+
+    // Storing the manifest again finds the same entry
+    assert_eq!(storage.store_manifest(&manifest)?, manifest.instance_id);
+    assert_eq!(posts(&requests.lock().unwrap()), 3);
+    assert_eq!(
+        storage
+            .recorded_entry(&manifest.instance_id)?
+            .map(|entry| entry.log_index),
+        Some(42)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_find_rekor_entries_by_hash() -> Result<()> {
+    let (key, key_dir) = generate_temp_key()?;
+    let public_key_pem = key.as_pkey().public_key_to_pem().unwrap();
+    let public_key_path = key_dir.path().join("public.pem");
+    fs::write(&public_key_path, &public_key_pem)?;
+
+    let index_dir = tempdir()?;
+    let (endpoint, _) = serve_rekor(0);
+    let storage = RekorStorage::new_with_url(endpoint)?
+        .with_public_key(&public_key_path)
+        .with_entry_index(index_dir.path().join("entries.json"));
+    let digest = "a".repeat(64);
+    assert!(storage.find_entries(&digest)?.is_empty());
+
+    let mut manifest = ManifestBuilder::new("Logged Manifest").build();
+    manifest.sign(key_dir.path().join("test_key.pem"), HashAlgorithm::Sha256)?;
+    storage.store_manifest(&manifest)?;
+
+    let results = storage.find_entries(&format!("sha256:{digest}"))?;
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].uuid, "abc123");
+    assert_eq!(results[0].kind, "hashedrekord");
+    let der = key.as_pkey().public_key_to_der().unwrap();
+    assert_eq!(
+        results[0].signers,
+        vec![format!(
+            "key SHA256:{}",
+            hex::encode(sha2::Sha256::digest(&der))
+        )]
+    );
+
+    // SHA-384 digests are not indexed by Rekor
+    assert!(storage.find_entries(&"a".repeat(96)).is_err());
+    assert!(storage.find_entries("sha256:xyz").is_err());
+    Ok(())
+}
+
+#[test]
+fn test_filesystem_storage_envelopes() -> Result<()> {
+    use crate::manifest::attestation::verify_envelope;
+    use crate::manifest::common::create_oms_manifest;
+    use crate::manifest::config::ManifestCreationConfig;
+    use crate::storage::traits::EnvelopeKind;
+    use openssl::pkey::PKey;
+
+    let dir = tempdir()?;
+    let weights = dir.path().join("weights.onnx");
+    let pipeline = dir.path().join("build.sh");
+    let product = dir.path().join("output.bin");
+    fs::write(&weights, b"model weights")?;
+    fs::write(&pipeline, b"build script")?;
+    fs::write(&product, b"output")?;
+
+    let storage: &'static FilesystemStorage = Box::leak(Box::new(FilesystemStorage::new(
+        dir.path().join("storage"),
+    )?));
+    let (key, key_dir) = generate_temp_key()?;
+    let key_path = key_dir.path().join("test_key.pem");
+    let public_key =
+        PKey::public_key_from_pem(&key.as_pkey().public_key_to_pem().unwrap()).unwrap();
+
+    create_oms_manifest(ManifestCreationConfig {
+        paths: vec![weights.clone()],
+        ingredient_names: vec!["weights".to_string()],
+        ingredient_relationships: Vec::new(),
+        expand_archives: false,
+        profile_data: false,
+        max_sample_bytes: crate::manifest::profile::DEFAULT_MAX_SAMPLE_BYTES,
+        stream_connections: crate::manifest::object_store::DEFAULT_CONNECTIONS,
+        ingest_policy: crate::manifest::ingest::IngestPolicy::default(),
+        path_base: None,
+        dataset_ids: vec![],
+        name: "OMS Model".to_string(),
+        author_org: None,
+        author_name: None,
+        description: None,
+        linked_manifests: None,
+        storage: Some(storage),
+        print: false,
+        output_encoding: "json".to_string(),
+        output_path: None,
+        key_path: Some(key_path.clone()),
+        pq_key_path: None,
+        hash_alg: HashAlgorithm::Sha384,
+        valid_until: None,
+        created_at: None,
+        with_cc: false,
+        tpm: None,
+        software_type: None,
+        version: None,
+        encryption: None,
+        content_addressed_id: false,
+        force_new: false,
+        custom_fields: None,
+        inherited_assertions: vec![],
+    })?;
+    crate::slsa::cli::generate_build_provenance(
+        vec![],
+        pipeline,
+        vec![product.clone()],
+        Some(key_path),
+        HashAlgorithm::Sha256,
+        "json".to_string(),
+        None,
+        false,
+        Some(storage),
+        false,
+    )?;
+
+    // Envelopes are listed apart from manifests
+    assert_eq!(storage.list_manifests()?.len(), 1);
+    let envelopes = storage.list_envelopes()?;
+    assert_eq!(envelopes.len(), 2);
+    let oms = envelopes
+        .iter()
+        .find(|e| e.kind == EnvelopeKind::Oms)
+        .unwrap();
+    let slsa = envelopes
+        .iter()
+        .find(|e| e.kind == EnvelopeKind::Slsa)
+        .unwrap();
+    assert_eq!(oms.name, "OMS Model");
+    assert_eq!(
+        oms.manifest_id.as_deref(),
+        Some(storage.list_manifests()?[0].id.as_str())
+    );
+    assert_eq!(slsa.name, "build.sh");
+    assert!(storage.retrieve_envelope(&oms.id)?.envelope.validate());
+
+    verify_envelope(&oms.id, storage, &public_key, None)?;
+    verify_envelope(&slsa.id, storage, &public_key, None)?;
+
+    fs::write(&product, b"modified output")?;
+    assert!(verify_envelope(&slsa.id, storage, &public_key, None).is_err());
+    assert!(storage.retrieve_envelope("urn:uuid:missing").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_attestation_create_sign_attach() -> Result<()> {
+    use crate::manifest::attestation::{
+        AttestationConfig, attach_attestation, attached_attestations, create_attestation,
+        emit_envelope, sign_attestation, verify_attestation,
+    };
+    use crate::storage::traits::EnvelopeKind;
+    use openssl::pkey::PKey;
+
+    let dir = tempdir()?;
+    let weights = dir.path().join("weights.onnx");
+    let predicate = dir.path().join("predicate.yaml");
+    let statement = dir.path().join("statement.json");
+    fs::write(&weights, b"model weights")?;
+    fs::write(&predicate, "evaluation:\n  accuracy: 0.93\n  suite: mmlu\n")?;
+
+    let storage: &'static FilesystemStorage = Box::leak(Box::new(FilesystemStorage::new(
+        dir.path().join("storage"),
+    )?));
+    let (key, key_dir) = generate_temp_key()?;
+    let key_path = key_dir.path().join("test_key.pem");
+    let public_key =
+        PKey::public_key_from_pem(&key.as_pkey().public_key_to_pem().unwrap()).unwrap();
+    let config = |key_path, output_path| AttestationConfig {
+        subjects: vec![weights.clone()],
+        subject_manifests: vec![],
+        predicate_type: "https://example.com/evaluation/v1".to_string(),
+        predicate_path: Some(predicate.clone()),
+        name: None,
+        key_path,
+        pq_key_path: None,
+        hash_alg: HashAlgorithm::Sha256,
+        output_encoding: "json".to_string(),
+        output_path,
+        print: false,
+        storage: Some(storage),
+    };
+
+    // Without a key, the statement is written unsigned and not stored
+    create_attestation(config(None, Some(statement.clone())))?;
+    assert!(storage.list_envelopes()?.is_empty());
+    let written: serde_json::Value = serde_json::from_slice(&fs::read(&statement)?)?;
+    assert_eq!(written["predicate"]["evaluation"]["suite"], "mmlu");
+
+    let envelope = sign_attestation(&statement, key_path.clone(), None, HashAlgorithm::Sha256)?;
+    verify_attestation(&envelope, &public_key, None, None)?;
+    emit_envelope(envelope, "evaluation", "json", None, false, Some(storage))?;
+
+    create_attestation(config(Some(key_path.clone()), None))?;
+    let envelopes = storage.list_envelopes()?;
+    assert_eq!(envelopes.len(), 2);
+    assert!(envelopes.iter().all(|e| e.kind == EnvelopeKind::Custom));
+    let stored = envelopes.iter().find(|e| e.name == "weights.onnx").unwrap();
+
+    let mut manifest = ManifestBuilder::new("Attested Model")
+        .id(&format!("urn:uuid:{}", Uuid::new_v4()))
+        .build();
+    manifest.sign(key_path.clone(), HashAlgorithm::Sha256)?;
+    let manifest_id = storage.store_manifest(&manifest)?;
+
+    // A signed manifest can only be re-signed with a key
+    assert!(
+        attach_attestation(
+            &manifest_id,
+            &stored.id,
+            storage,
+            None,
+            HashAlgorithm::Sha256
+        )
+        .is_err()
+    );
+    let attached_id = attach_attestation(
+        &manifest_id,
+        &stored.id,
+        storage,
+        Some(key_path),
+        HashAlgorithm::Sha256,
+    )?;
+    let attached = attached_attestations(&storage.retrieve_manifest(&attached_id)?)?;
+    assert_eq!(attached.len(), 1);
+    assert_eq!(attached[0].envelope_id, stored.id);
+    assert_eq!(
+        attached[0].predicate_type,
+        "https://example.com/evaluation/v1"
+    );
+
+    fs::write(&weights, b"modified weights")?;
+    let record = storage.retrieve_envelope(&stored.id)?;
+    assert!(verify_attestation(&record.envelope, &public_key, None, None).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_attestation_custom_predicate() -> Result<()> {
+    use crate::in_toto::read_statement;
+    use crate::manifest::attestation::{AttestationConfig, create_attestation, read_envelope};
+
+    let dir = tempdir()?;
+    let weights = dir.path().join("weights.onnx");
+    let tokenizer = dir.path().join("tokenizer.json");
+    let predicate = dir.path().join("predicate.json");
+    let output = dir.path().join("attestation.json");
+    fs::write(&weights, b"model weights")?;
+    fs::write(&tokenizer, b"{}")?;
+    fs::write(
+        &predicate,
+        r#"{"reviewer": "ml-platform", "epochs": 3, "samples": 12345678901234567, "tags": ["a", 1]}"#,
+    )?;
+
+    let (_, key_dir) = generate_temp_key()?;
+    let config = |predicate_type: &str, predicate_path| AttestationConfig {
+        subjects: vec![weights.clone(), tokenizer.clone()],
+        subject_manifests: vec![],
+        predicate_type: predicate_type.to_string(),
+        predicate_path,
+        name: None,
+        key_path: Some(key_dir.path().join("test_key.pem")),
+        pq_key_path: None,
+        hash_alg: HashAlgorithm::Sha256,
+        output_encoding: "json".to_string(),
+        output_path: Some(output.clone()),
+        print: false,
+        storage: None,
+    };
+
+    create_attestation(config(
+        "https://example.com/review/v1",
+        Some(predicate.clone()),
+    ))?;
+    let statement = read_statement(&read_envelope(&output)?)?;
+    assert_eq!(statement.predicate_type, "https://example.com/review/v1");
+    assert_eq!(statement.subject.len(), 2);
+    // The predicate is signed as given, integers included
+    assert_eq!(
+        statement.predicate,
+        serde_json::from_slice::<serde_json::Value>(&fs::read(&predicate)?)?
+    );
+
+    assert!(create_attestation(config("review-v1", Some(predicate.clone()))).is_err());
+    fs::write(&predicate, "[1, 2, 3]")?;
+    assert!(create_attestation(config("https://example.com/review/v1", Some(predicate))).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_attestation_manifest_subjects() -> Result<()> {
+    use crate::manifest::attestation::{
+        AttestationConfig, MANIFEST_SUBJECT_MEDIA_TYPE, attach_attestation, create_attestation,
+        verify_envelope,
+    };
+    use openssl::pkey::PKey;
+
+    let dir = tempdir()?;
+    let storage: &'static FilesystemStorage = Box::leak(Box::new(FilesystemStorage::new(
+        dir.path().join("storage"),
+    )?));
+    let (key, key_dir) = generate_temp_key()?;
+    let key_path = key_dir.path().join("test_key.pem");
+    let public_key =
+        PKey::public_key_from_pem(&key.as_pkey().public_key_to_pem().unwrap()).unwrap();
+
+    let mut manifest = ManifestBuilder::new("Released Model")
+        .id(&format!("urn:uuid:{}", Uuid::new_v4()))
+        .build();
+    let manifest_id = storage.store_manifest(&manifest)?;
+
+    create_attestation(AttestationConfig {
+        subjects: vec![],
+        subject_manifests: vec![manifest_id.clone()],
+        predicate_type: "https://example.com/release/v1".to_string(),
+        predicate_path: None,
+        name: None,
+        key_path: Some(key_path.clone()),
+        pq_key_path: None,
+        hash_alg: HashAlgorithm::Sha384,
+        output_encoding: "json".to_string(),
+        output_path: None,
+        print: false,
+        storage: Some(storage),
+    })?;
+    let stored = storage.list_envelopes()?.remove(0);
+    assert_eq!(stored.name, manifest_id);
+    let statement =
+        crate::in_toto::read_statement(&storage.retrieve_envelope(&stored.id)?.envelope)?;
+    assert_eq!(statement.subject[0].name, manifest_id);
+    assert_eq!(statement.subject[0].media_type, MANIFEST_SUBJECT_MEDIA_TYPE);
+    verify_envelope(&stored.id, storage, &public_key, None)?;
+
+    // Attaching the attestation to the manifest it covers keeps it valid
+    attach_attestation(
+        &manifest_id,
+        &stored.id,
+        storage,
+        None,
+        HashAlgorithm::Sha384,
+    )?;
+    verify_envelope(&stored.id, storage, &public_key, None)?;
+
+    manifest.title = "Changed Model".to_string();
+    storage.store_manifest(&manifest)?;
+    assert!(verify_envelope(&stored.id, storage, &public_key, None).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_attestation_bundle() -> Result<()> {
+    use crate::manifest::attestation::{
+        read_bundle, render_bundle_results, sign_attestation, verify_bundle, write_bundle,
+    };
+    use openssl::pkey::PKey;
+
+    let dir = tempdir()?;
+    let (key, key_dir) = generate_temp_key()?;
+    let key_path = key_dir.path().join("test_key.pem");
+    let public_key =
+        PKey::public_key_from_pem(&key.as_pkey().public_key_to_pem().unwrap()).unwrap();
+
+    let mut envelopes = Vec::new();
+    for name in ["weights.onnx", "tokenizer.json"] {
+        let subject = dir.path().join(name);
+        fs::write(&subject, name)?;
+        let statement = dir.path().join(format!("{name}.statement.json"));
+        fs::write(
+            &statement,
+            crate::in_toto::generate_statement_json_v1_with_value(
+                &[crate::in_toto::generate_file_resource_descriptor_from_path(
+                    &subject,
+                    &HashAlgorithm::Sha256,
+                )?],
+                "https://example.com/review/v1",
+                &serde_json::json!({"name": name}),
+            )?,
+        )?;
+        envelopes.push(sign_attestation(
+            &statement,
+            key_path.clone(),
+            None,
+            HashAlgorithm::Sha256,
+        )?);
+    }
+
+    let bundle = dir.path().join("bundle.jsonl");
+    write_bundle(&envelopes, Some(&bundle))?;
+    assert_eq!(read_bundle(&bundle)?.len(), 2);
+    let results = verify_bundle(&bundle, &public_key, None, None)?;
+    assert!(results.iter().all(|r| r.error.is_none()));
+
+    // Every statement is reported, including the ones after a failure
+    fs::write(dir.path().join("weights.onnx"), "modified")?;
+    let mut contents = fs::read_to_string(&bundle)?;
+    contents.push_str("\nnot an envelope\n");
+    fs::write(&bundle, contents)?;
+    let results = verify_bundle(&bundle, &public_key, None, None)?;
+    assert_eq!(results.len(), 3);
+    assert!(results[0].error.is_some());
+    assert!(results[1].error.is_none());
+    assert_eq!(results[2].line, 4);
+    assert!(results[2].predicate_type.is_none());
+    assert!(render_bundle_results(&results).contains("1 of 3 statements verified"));
+    assert!(read_bundle(&bundle).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_provenance_linked_to_product_manifest() -> Result<()> {
+    use crate::manifest::attestation::{ENVELOPE_MEDIA_TYPE, is_envelope_reference};
+    use crate::manifest::config::ManifestCreationConfig;
+
+    let dir = tempdir()?;
+    let weights = dir.path().join("weights.onnx");
+    let other = dir.path().join("other.onnx");
+    let pipeline = dir.path().join("train.sh");
+    fs::write(&weights, b"model weights")?;
+    fs::write(&other, b"unrelated weights")?;
+    fs::write(&pipeline, b"train script")?;
+
+    let storage: &'static FilesystemStorage = Box::leak(Box::new(FilesystemStorage::new(
+        dir.path().join("storage"),
+    )?));
+    let (_, key_dir) = generate_temp_key()?;
+    let key_path = key_dir.path().join("test_key.pem");
+
+    for (path, name) in [(&weights, "Trained Model"), (&other, "Other Model")] {
+        crate::manifest::model::create_manifest(ManifestCreationConfig {
+            paths: vec![path.clone()],
+            ingredient_names: vec![name.to_string()],
+            ingredient_relationships: Vec::new(),
+            expand_archives: false,
+            profile_data: false,
+            max_sample_bytes: crate::manifest::profile::DEFAULT_MAX_SAMPLE_BYTES,
+            stream_connections: crate::manifest::object_store::DEFAULT_CONNECTIONS,
+            ingest_policy: crate::manifest::ingest::IngestPolicy::default(),
+            path_base: None,
+            dataset_ids: vec![],
+            name: name.to_string(),
+            author_org: None,
+            author_name: None,
+            description: None,
+            linked_manifests: None,
+            storage: Some(storage),
+            print: false,
+            output_encoding: "json".to_string(),
+            output_path: None,
+            key_path: Some(key_path.clone()),
+            pq_key_path: None,
+            hash_alg: HashAlgorithm::Sha384,
+            valid_until: None,
+            created_at: None,
+            with_cc: false,
+            tpm: None,
+            software_type: None,
+            version: None,
+            encryption: None,
+            content_addressed_id: false,
+            force_new: false,
+            custom_fields: None,
+            inherited_assertions: vec![],
+        })?;
+    }
+
+    crate::slsa::cli::generate_build_provenance(
+        vec![],
+        pipeline,
+        vec![weights.clone()],
+        Some(key_path),
+        HashAlgorithm::Sha384,
+        "json".to_string(),
+        None,
+        false,
+        Some(storage),
+        false,
+    )?;
+    let envelope_id = storage.list_envelopes()?.remove(0).id;
+
+    let manifests: Vec<Manifest> = storage
+        .list_manifests()?
+        .iter()
+        .map(|metadata| storage.retrieve_manifest(&metadata.id))
+        .collect::<Result<_>>()?;
+    let trained = manifests
+        .iter()
+        .find(|m| m.title == "Trained Model")
+        .unwrap();
+    let other = manifests.iter().find(|m| m.title == "Other Model").unwrap();
+    assert!(other.cross_references.is_empty());
+    assert_eq!(trained.cross_references.len(), 1);
+    assert_eq!(trained.cross_references[0].manifest_url, envelope_id);
+    assert_eq!(
+        trained.cross_references[0].media_type.as_deref(),
+        Some(ENVELOPE_MEDIA_TYPE)
+    );
+    assert!(is_envelope_reference(&trained.cross_references[0]));
+
+    // The provenance is validated with the cross-references, and shows in the graph
+    crate::manifest::validate_linked_manifests(&trained.instance_id, storage)?;
+    assert!(
+        crate::manifest::fsck::check_store(storage)?
+            .issues
+            .is_empty()
+    );
+
+    let graph_path = dir.path().join("graph.json");
+    crate::manifest::export_provenance(
+        &trained.instance_id,
+        storage,
+        "json",
+        Some(graph_path.to_str().unwrap()),
+        5,
+    )?;
+    let graph: serde_json::Value = serde_json::from_slice(&fs::read(&graph_path)?)?;
+    assert_eq!(
+        graph["nodes"][&envelope_id]["manifest_type"],
+        "SLSA Attestation"
+    );
+    assert!(
+        graph["edges"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|edge| edge["target"] == envelope_id.as_str()
+                && edge["relation_type"] == "attestedBy")
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_backfilled_creation_time() -> Result<()> {
+    use crate::manifest::config::ManifestCreationConfig;
+    use crate::manifest::timestamp::parse_created_at;
+
+    let dir = tempdir()?;
+    let storage: &'static FilesystemStorage = Box::leak(Box::new(FilesystemStorage::new(
+        dir.path().join("storage"),
+    )?));
+    let path = dir.path().join("legacy.onnx");
+    fs::write(&path, "legacy weights")?;
+
+    crate::manifest::model::create_manifest(ManifestCreationConfig {
+        paths: vec![path],
+        ingredient_names: vec!["legacy".to_string()],
+        ingredient_relationships: Vec::new(),
+        expand_archives: false,
+        profile_data: false,
+        max_sample_bytes: crate::manifest::profile::DEFAULT_MAX_SAMPLE_BYTES,
+        stream_connections: crate::manifest::object_store::DEFAULT_CONNECTIONS,
+        ingest_policy: crate::manifest::ingest::IngestPolicy::default(),
+        path_base: None,
+        dataset_ids: vec![],
+        name: "Legacy Model".to_string(),
+        author_org: None,
+        author_name: None,
+        description: None,
+        linked_manifests: None,
+        storage: Some(storage),
+        print: false,
+        output_encoding: "json".to_string(),
+        output_path: None,
+        key_path: None,
+        pq_key_path: None,
+        hash_alg: HashAlgorithm::Sha384,
+        valid_until: None,
+        created_at: Some(parse_created_at("2019-11-05T08:00:00+01:00")?),
+        with_cc: false,
+        tpm: None,
+        software_type: None,
+        version: None,
+        encryption: None,
+        content_addressed_id: false,
+        force_new: false,
+        custom_fields: None,
+        inherited_assertions: vec![],
+    })?;
+
+    // The override is recorded in UTC, in the claim as well as the manifest
+    let metadata = storage.list_manifests()?;
+    assert_eq!(metadata.len(), 1);
+    assert_eq!(metadata[0].created_at, "2019-11-05T07:00:00Z");
+    let manifest = storage.retrieve_manifest(&metadata[0].id)?;
+    let expected = time::macros::datetime!(2019-11-05 07:00 UTC);
+    assert_eq!(manifest.created_at.0, expected);
+    assert_eq!(manifest.claim.created_at.0, expected);
+    assert_eq!(manifest.created_at.0.offset(), time::UtcOffset::UTC);
+
+    Ok(())
+}
+
+#[test]
+fn test_import_legacy_inventory() -> Result<()> {
+    use crate::manifest::common::AssetKind;
+    use crate::manifest::legacy::{
+        ImportOptions, LEGACY_IMPORT_ASSERTION_LABEL, import_inventory, read_inventory,
+    };
+    use atlas_c2pa_lib::assertion::Assertion;
+
+    let dir = tempdir()?;
+    let storage: &'static FilesystemStorage = Box::leak(Box::new(FilesystemStorage::new(
+        dir.path().join("storage"),
+    )?));
+    fs::write(dir.path().join("resnet.onnx"), "resnet weights")?;
+    fs::create_dir(dir.path().join("imagenet"))?;
+    fs::write(dir.path().join("imagenet/train.csv"), "label,path\n")?;
+    let inventory = dir.path().join("inventory.csv");
+    fs::write(
+        &inventory,
+        "path,name,type,author,date\n\
+         resnet.onnx,ResNet,,Jane Doe,2019-11-05\n\
+         imagenet,ImageNet,dataset,,\n\
+         missing.onnx,Missing,,,\n",
+    )?;
+
+    let rows = read_inventory(&inventory)?;
+    let options = ImportOptions {
+        default_kind: AssetKind::Model,
+        backdate: true,
+        jobs: 2,
+        author_org: Some("Research".to_string()),
+        path_base: None,
+        key_path: None,
+        hash_alg: HashAlgorithm::Sha384,
+    };
+    let results = import_inventory(&inventory, &rows, &options, storage)?;
+
+    // A missing artifact fails its own row only
+    assert_eq!(results.len(), 3);
+    assert!(results[2].id.is_err());
+    let resnet = storage.retrieve_manifest(results[0].id.as_ref().unwrap())?;
+    let imagenet = storage.retrieve_manifest(results[1].id.as_ref().unwrap())?;
+    assert_eq!(storage.list_manifests()?.len(), 2);
+
+    // Only rows with a date are backdated
+    let backdated = time::macros::datetime!(2019-11-05 00:00 UTC);
+    assert_eq!(resnet.created_at.0, backdated);
+    assert_eq!(resnet.claim.created_at.0, backdated);
+    assert!(imagenet.created_at.0 > backdated);
+
+    let import = resnet
+        .claim_v2
+        .as_ref()
+        .unwrap()
+        .created_assertions
+        .iter()
+        .find_map(|assertion| match assertion {
+            Assertion::CustomAssertion(custom) if custom.label == LEGACY_IMPORT_ASSERTION_LABEL => {
+                Some(custom.data.clone())
+            }
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(import["inventory"], "inventory.csv");
+    assert_eq!(import["line"], 2);
+    assert_eq!(import["backdated"], true);
+    assert!(import["imported_at"].as_str().unwrap() > "2019-11-05");
+
+    Ok(())
+}
+
+#[test]
+fn test_evaluation_history_across_model_versions() -> Result<()> {
+    use crate::manifest::config::ManifestCreationConfig;
+    use crate::manifest::evaluation::{evaluation_history, render_evaluation_history};
+
+    let dir = tempdir()?;
+    let storage: &'static FilesystemStorage = Box::leak(Box::new(FilesystemStorage::new(
+        dir.path().join("storage"),
+    )?));
+    let (_, key_dir) = generate_temp_key()?;
+    let key_path = key_dir.path().join("test_key.pem");
+
+    let config = |path: std::path::PathBuf, name: &str| ManifestCreationConfig {
+        paths: vec![path],
+        ingredient_names: vec![name.to_string()],
+        ingredient_relationships: Vec::new(),
+        expand_archives: false,
+        profile_data: false,
+        max_sample_bytes: crate::manifest::profile::DEFAULT_MAX_SAMPLE_BYTES,
+        stream_connections: crate::manifest::object_store::DEFAULT_CONNECTIONS,
+        ingest_policy: crate::manifest::ingest::IngestPolicy::default(),
+        path_base: None,
+        dataset_ids: vec![],
+        name: name.to_string(),
+        author_org: None,
+        author_name: None,
+        description: None,
+        linked_manifests: None,
+        storage: Some(storage),
+        print: false,
+        output_encoding: "json".to_string(),
+        output_path: None,
+        key_path: Some(key_path.clone()),
+        pq_key_path: None,
+        hash_alg: HashAlgorithm::Sha384,
+        valid_until: None,
+        created_at: None,
+        with_cc: false,
+        tpm: None,
+        software_type: None,
+        version: None,
+        encryption: None,
+        content_addressed_id: false,
+        force_new: false,
+        custom_fields: None,
+        inherited_assertions: vec![],
+    };
+    let id_of = |title: &str| -> Result<String> {
+        for metadata in storage.list_manifests()? {
+            if metadata.name == title {
+                return Ok(metadata.id);
+            }
+        }
+        panic!("no manifest named {title}");
+    };
+
+    for name in ["Base Model", "Fine-tuned Model", "Other Model"] {
+        let path = dir.path().join(format!("{name}.onnx"));
+        fs::write(&path, name)?;
+        crate::manifest::model::create_manifest(config(path, name))?;
+    }
+    let base_id = id_of("Base Model")?;
+    let fine_tune_id = id_of("Fine-tuned Model")?;
+    let other_id = id_of("Other Model")?;
+    crate::manifest::link_manifests(&fine_tune_id, &base_id, storage)?;
+
+    // The other model stands in for the evaluation dataset
+    for (name, model_id, metrics) in [
+        ("Base Eval", &base_id, vec!["accuracy=0.80", "loss=0.5"]),
+        ("Fine-tune Eval", &fine_tune_id, vec!["accuracy=0.85"]),
+        ("Other Eval", &other_id, vec!["accuracy=0.10", "note=n/a"]),
+    ] {
+        let path = dir.path().join(format!("{name}.json"));
+        fs::write(&path, name)?;
+        crate::manifest::evaluation::create_manifest(
+            config(path, name),
+            model_id.clone(),
+            other_id.clone(),
+            metrics.into_iter().map(String::from).collect(),
+            None,
+        )?;
+    }
+
+    let history = evaluation_history(&base_id, Some("accuracy"), storage)?;
+    assert_eq!(history.len(), 2);
+    let mut values: Vec<(String, f64)> = history
+        .iter()
+        .map(|point| (point.evaluation_name.clone(), point.value))
+        .collect();
+    values.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(
+        values,
+        vec![
+            ("Base Eval".to_string(), 0.80),
+            ("Fine-tune Eval".to_string(), 0.85)
+        ]
+    );
+    assert!(history.iter().all(|point| point.dataset_id == other_id));
+    assert!(history.windows(2).all(|w| w[0].timestamp <= w[1].timestamp));
+
+    // Without --metric every numeric metric is included
+    let history = evaluation_history(&other_id, None, storage)?;
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].metric, "accuracy");
+
+    let csv = render_evaluation_history(&history, "csv")?;
+    assert!(csv.starts_with("timestamp,evaluation_id,evaluation_name,model_id"));
+    assert!(csv.contains(",accuracy,0.1"));
+    let json: serde_json::Value =
+        serde_json::from_str(&render_evaluation_history(&history, "json")?)?;
+    assert_eq!(json[0]["value"], 0.1);
+    assert!(render_evaluation_history(&history, "xml").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_dataset_split() -> Result<()> {
+    use crate::manifest::config::ManifestCreationConfig;
+    use crate::manifest::dataset::{DatasetSplit, split_dataset};
+    use crate::manifest::linking::is_dataset_link;
+
+    let dir = tempdir()?;
+    let storage: &'static FilesystemStorage = Box::leak(Box::new(FilesystemStorage::new(
+        dir.path().join("storage"),
+    )?));
+    let (_, key_dir) = generate_temp_key()?;
+    let key_path = key_dir.path().join("test_key.pem");
+
+    let files: Vec<std::path::PathBuf> = ["a.csv", "b.csv", "c.csv", "c-copy.csv"]
+        .iter()
+        .map(|name| dir.path().join(name))
+        .collect();
+    for (path, content) in files.iter().zip(["x,1\n", "x,2\n", "x,3\n", "x,3\n"]) {
+        fs::write(path, content)?;
+    }
+
+    let config = |paths: Vec<std::path::PathBuf>, name: &str| ManifestCreationConfig {
+        ingredient_names: paths
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().to_string())
+            .collect(),
+        ingredient_relationships: Vec::new(),
+        paths,
+        expand_archives: false,
+        profile_data: false,
+        max_sample_bytes: crate::manifest::profile::DEFAULT_MAX_SAMPLE_BYTES,
+        stream_connections: crate::manifest::object_store::DEFAULT_CONNECTIONS,
+        ingest_policy: crate::manifest::ingest::IngestPolicy::default(),
+        path_base: None,
+        dataset_ids: vec![],
+        name: name.to_string(),
+        author_org: None,
+        author_name: None,
+        description: None,
+        linked_manifests: None,
+        storage: Some(storage),
+        print: false,
+        output_encoding: "json".to_string(),
+        output_path: None,
+        key_path: Some(key_path.clone()),
+        pq_key_path: None,
+        hash_alg: HashAlgorithm::Sha384,
+        valid_until: None,
+        created_at: None,
+        with_cc: false,
+        tpm: None,
+        software_type: None,
+        version: None,
+        encryption: None,
+        content_addressed_id: false,
+        force_new: false,
+        custom_fields: None,
+        inherited_assertions: vec![],
+    };
+
+    crate::manifest::dataset::create_manifest(config(files[..3].to_vec(), "Corpus"))?;
+    let parent_id = storage.list_manifests()?.remove(0).id;
+
+    // A file in two splits would leak test data into training; nothing is stored
+    let overlapping = split_dataset(
+        &parent_id,
+        vec![
+            (
+                DatasetSplit::Train,
+                vec![files[0].clone(), files[2].clone()],
+            ),
+            (DatasetSplit::Test, vec![files[3].clone()]),
+        ],
+        config(vec![], ""),
+    );
+    assert!(
+        matches!(overlapping, Err(crate::error::Error::Validation(ref e)) if e.contains("not disjoint"))
+    );
+    assert_eq!(storage.list_manifests()?.len(), 1);
+
+    let ids = split_dataset(
+        &parent_id,
+        vec![
+            (
+                DatasetSplit::Train,
+                vec![files[0].clone(), files[1].clone()],
+            ),
+            (DatasetSplit::Validation, vec![]),
+            (DatasetSplit::Test, vec![files[2].clone()]),
+        ],
+        config(vec![], ""),
+    )?;
+    assert_eq!(ids.len(), 2);
+
+    for (split, id) in &ids {
+        let manifest = storage.retrieve_manifest(id)?;
+        assert_eq!(manifest.title, format!("Corpus ({})", split.as_str()));
+        assert!(manifest.claim.signature.is_some());
+
+        let links: Vec<_> = manifest
+            .claim
+            .ingredients
+            .iter()
+            .filter(|i| is_dataset_link(i))
+            .collect();
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].relationship, split.relationship());
+        assert_eq!(links[0].data.url, parent_id);
+        assert_eq!(manifest.cross_references[0].manifest_url, parent_id);
+        assert_eq!(
+            crate::manifest::utils::determine_manifest_type(&manifest),
+            crate::storage::traits::ManifestType::Dataset
+        );
+    }
+    assert_eq!(
+        DatasetSplit::from_relationship("testSplitOf"),
+        Some(DatasetSplit::Test)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_dataset_dedup_check() -> Result<()> {
+    use crate::manifest::config::ManifestCreationConfig;
+    use crate::manifest::dataset::{
+        DatasetSplit, find_duplicates, render_duplicates, split_dataset,
+    };
+
+    let dir = tempdir()?;
+    let storage: &'static FilesystemStorage = Box::leak(Box::new(FilesystemStorage::new(
+        dir.path().join("storage"),
+    )?));
+    let (_, key_dir) = generate_temp_key()?;
+    let key_path = key_dir.path().join("test_key.pem");
+
+    let files: Vec<std::path::PathBuf> = ["a.csv", "b.csv", "c.csv", "holdout.csv"]
+        .iter()
+        .map(|name| dir.path().join(name))
+        .collect();
+    for (path, content) in files.iter().zip(["x,1\n", "x,2\n", "x,3\n", "x,3\n"]) {
+        fs::write(path, content)?;
+    }
+
+    let config = |paths: Vec<std::path::PathBuf>, name: &str| ManifestCreationConfig {
+        ingredient_names: paths
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().to_string())
+            .collect(),
+        ingredient_relationships: Vec::new(),
+        paths,
+        expand_archives: false,
+        profile_data: false,
+        max_sample_bytes: crate::manifest::profile::DEFAULT_MAX_SAMPLE_BYTES,
+        stream_connections: crate::manifest::object_store::DEFAULT_CONNECTIONS,
+        ingest_policy: crate::manifest::ingest::IngestPolicy::default(),
+        path_base: None,
+        dataset_ids: vec![],
+        name: name.to_string(),
+        author_org: None,
+        author_name: None,
+        description: None,
+        linked_manifests: None,
+        storage: Some(storage),
+        print: false,
+        output_encoding: "json".to_string(),
+        output_path: None,
+        key_path: Some(key_path.clone()),
+        pq_key_path: None,
+        hash_alg: HashAlgorithm::Sha384,
+        valid_until: None,
+        created_at: None,
+        with_cc: false,
+        tpm: None,
+        software_type: None,
+        version: None,
+        encryption: None,
+        content_addressed_id: false,
+        force_new: false,
+        custom_fields: None,
+        inherited_assertions: vec![],
+    };
+
+    crate::manifest::dataset::create_manifest(config(files[..3].to_vec(), "Corpus"))?;
+    let parent_id = storage.list_manifests()?.remove(0).id;
+    let ids = split_dataset(
+        &parent_id,
+        vec![
+            (
+                DatasetSplit::Train,
+                vec![files[0].clone(), files[1].clone()],
+            ),
+            (DatasetSplit::Test, vec![files[2].clone()]),
+        ],
+        config(vec![], ""),
+    )?;
+    let train_id = ids[0].1.clone();
+
+    // Splits share their content with the parent, which is not contamination
+    let report = find_duplicates(&[], storage)?;
+    assert_eq!(report.datasets_checked, 3);
+    assert!(report.is_clean());
+
+    crate::manifest::dataset::create_manifest(config(vec![files[3].clone()], "Holdout"))?;
+    let holdout_id = storage
+        .list_manifests()?
+        .into_iter()
+        .map(|metadata| metadata.id)
+        .find(|id| *id != parent_id && !ids.iter().any(|(_, split)| split == id))
+        .unwrap();
+
+    // The holdout file has the same content as the test split, and so as the corpus
+    let report = find_duplicates(&[], storage)?;
+    assert_eq!(report.overlaps.len(), 1);
+    let mut datasets: Vec<_> = report.overlaps[0]
+        .files
+        .iter()
+        .map(|file| file.dataset_name.as_str())
+        .collect();
+    datasets.sort();
+    assert_eq!(datasets, ["Corpus", "Corpus (test)", "Holdout"]);
+    assert!(render_duplicates(&report, "table")?.contains("holdout.csv"));
+
+    let report = find_duplicates(&[train_id, holdout_id], storage)?;
+    assert_eq!(report.files_checked, 3);
+    assert!(report.is_clean());
+    assert!(render_duplicates(&report, "xml").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_dataset_retire() -> Result<()> {
+    use crate::manifest::config::ManifestCreationConfig;
+    use crate::manifest::retention::{RetirementAction, retire_dataset, retirement};
+
+    let dir = tempdir()?;
+    let storage: &'static FilesystemStorage = Box::leak(Box::new(FilesystemStorage::new(
+        dir.path().join("storage"),
+    )?));
+    let (_, key_dir) = generate_temp_key()?;
+    let key_path = key_dir.path().join("test_key.pem");
+
+    let data = dir.path().join("users.csv");
+    fs::write(&data, "id,email\n1,a@example.com\n")?;
+    let evidence = dir.path().join("deletion_log.json");
+    fs::write(&evidence, r#"{"deleted": ["users.csv"]}"#)?;
+
+    let config = |paths: Vec<std::path::PathBuf>, name: &str| ManifestCreationConfig {
+        ingredient_names: paths
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().to_string())
+            .collect(),
+        ingredient_relationships: Vec::new(),
+        paths,
+        expand_archives: false,
+        profile_data: false,
+        max_sample_bytes: crate::manifest::profile::DEFAULT_MAX_SAMPLE_BYTES,
+        stream_connections: crate::manifest::object_store::DEFAULT_CONNECTIONS,
+        ingest_policy: crate::manifest::ingest::IngestPolicy::default(),
+        path_base: None,
+        dataset_ids: vec![],
+        name: name.to_string(),
+        author_org: None,
+        author_name: None,
+        description: None,
+        linked_manifests: None,
+        storage: Some(storage),
+        print: false,
+        output_encoding: "json".to_string(),
+        output_path: None,
+        key_path: Some(key_path.clone()),
+        pq_key_path: None,
+        hash_alg: HashAlgorithm::Sha384,
+        valid_until: None,
+        created_at: None,
+        with_cc: false,
+        tpm: None,
+        software_type: None,
+        version: None,
+        encryption: None,
+        content_addressed_id: false,
+        force_new: false,
+        custom_fields: None,
+        inherited_assertions: vec![],
+    };
+
+    crate::manifest::dataset::create_manifest(config(vec![data], "Users"))?;
+    let dataset_id = storage.list_manifests()?.remove(0).id;
+    let dataset = storage.retrieve_manifest(&dataset_id)?;
+
+    // Retention needs the end of the retention period
+    assert!(
+        retire_dataset(
+            &dataset_id,
+            RetirementAction::Retention,
+            &evidence,
+            None,
+            None,
+            config(vec![], "")
+        )
+        .is_err()
+    );
+
+    let id = retire_dataset(
+        &dataset_id,
+        RetirementAction::Deletion,
+        &evidence,
+        Some("erasure request 42".to_string()),
+        None,
+        config(vec![], ""),
+    )?
+    .unwrap();
+
+    let manifest = storage.retrieve_manifest(&id)?;
+    assert_eq!(manifest.title, "Users (deletion)");
+    assert!(manifest.claim.signature.is_some());
+    assert_eq!(manifest.claim.ingredients[0].title, "deletion_log.json");
+    assert_eq!(manifest.cross_references[0].manifest_url, dataset_id);
+
+    let recorded = retirement(&manifest)?.unwrap();
+    assert_eq!(recorded.action, RetirementAction::Deletion);
+    assert_eq!(recorded.dataset_id, dataset_id);
+    assert_eq!(recorded.reason.as_deref(), Some("erasure request 42"));
+    assert!(retirement(&dataset)?.is_none());
+
+    // The dataset manifest itself is unchanged
+    assert_eq!(
+        serde_json::to_string(&storage.retrieve_manifest(&dataset_id)?)?,
+        serde_json::to_string(&dataset)?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_apply_retention() -> Result<()> {
+    use crate::manifest::config::ManifestCreationConfig;
+    use crate::manifest::credential::verify_credential;
+    use crate::storage::retention::{
+        RetentionAction, RetentionPolicy, TOMBSTONE_CREDENTIAL_TYPE, apply_retention,
+        plan_retention,
+    };
+    use openssl::pkey::PKey;
+    use time::macros::datetime;
+
+    let dir = tempdir()?;
+    let storage: &'static FilesystemStorage = Box::leak(Box::new(FilesystemStorage::new(
+        dir.path().join("storage"),
+    )?));
+    let (_, key_dir) = generate_temp_key()?;
+    let key_path = key_dir.path().join("test_key.pem");
+
+    let config = |name: &str, created_at: OffsetDateTime, dataset_ids: Vec<String>| {
+        let path = dir.path().join(format!("{name}.bin"));
+        fs::write(&path, name).unwrap();
+        ManifestCreationConfig {
+            ingredient_names: vec![name.to_string()],
+            ingredient_relationships: Vec::new(),
+            paths: vec![path],
+            expand_archives: false,
+            profile_data: false,
+            max_sample_bytes: crate::manifest::profile::DEFAULT_MAX_SAMPLE_BYTES,
+            stream_connections: crate::manifest::object_store::DEFAULT_CONNECTIONS,
+            ingest_policy: crate::manifest::ingest::IngestPolicy::default(),
+            path_base: None,
+            dataset_ids,
+            name: name.to_string(),
+            author_org: None,
+            author_name: None,
+            description: None,
+            linked_manifests: None,
+            storage: Some(storage),
+            print: false,
+            output_encoding: "json".to_string(),
+            output_path: None,
+            key_path: Some(key_path.clone()),
+            pq_key_path: None,
+            hash_alg: HashAlgorithm::Sha384,
+            valid_until: None,
+            created_at: Some(created_at),
+            with_cc: false,
+            tpm: None,
+            software_type: None,
+            version: None,
+            encryption: None,
+            content_addressed_id: false,
+            force_new: false,
+            custom_fields: None,
+            inherited_assertions: vec![],
+        }
+    };
+    let id_of = |name: &str| -> Result<String> {
+        Ok(storage
+            .list_manifests()?
+            .into_iter()
+            .find(|metadata| metadata.name == name)
+            .unwrap()
+            .id)
+    };
+
+    let old = datetime!(2020-01-15 10:00 UTC);
+    crate::manifest::dataset::create_manifest(config("orphan", old, vec![]))?;
+    crate::manifest::dataset::create_manifest(config("training", old, vec![]))?;
+    crate::manifest::dataset::create_manifest(config("recent", OffsetDateTime::now_utc(), vec![]))?;
+    let training = id_of("training")?;
+    crate::manifest::model::create_manifest(config("model", old, vec![training.clone()]))?;
+    let orphan = id_of("orphan")?;
+
+    let policy = RetentionPolicy::parse(
+        "rules:\n  dataset:\n    keep: 1y\n    action: archive\n  model:\n    keep: forever\n",
+    )?;
+    let plan = plan_retention(storage, &policy, OffsetDateTime::now_utc())?;
+
+    // The training dataset expired, but the kept model still references it
+    let expired: Vec<&str> = plan.expired.iter().map(|m| m.id.as_str()).collect();
+    assert_eq!(expired, vec![orphan.as_str()]);
+    assert_eq!(plan.held.len(), 1);
+    assert_eq!(plan.held[0].0.id, training);
+    assert_eq!(plan.held[0].1, vec![id_of("model")?]);
+
+    // Tombstones are signed with a key that has a did:key
+    let key = PKey::generate_ed25519().unwrap();
+    let tombstone_key_path = dir.path().join("retention.pem");
+    fs::write(&tombstone_key_path, key.private_key_to_pem_pkcs8().unwrap())?;
+    let public_key = PKey::public_key_from_pem(&key.public_key_to_pem().unwrap()).unwrap();
+    let archive_dir = dir.path().join("archive");
+    let removed = apply_retention(
+        storage,
+        &plan,
+        &archive_dir,
+        &crate::signing::load_private_key(&tombstone_key_path)?,
+        None,
+        &HashAlgorithm::Sha384,
+    )?;
+    assert_eq!(removed.len(), 1);
+    assert_eq!(removed[0].action, RetentionAction::Archive);
+    assert!(storage.retrieve_manifest(&orphan).is_err());
+    assert_eq!(storage.list_manifests()?.len(), 3);
+
+    let archived: Manifest =
+        serde_json::from_slice(&fs::read(removed[0].archive.as_ref().unwrap())?)?;
+    assert_eq!(archived.instance_id, orphan);
+
+    let tombstone = verify_credential(&fs::read_to_string(&removed[0].tombstone)?, &public_key)?;
+    assert_eq!(tombstone["type"][1], TOMBSTONE_CREDENTIAL_TYPE);
+    assert_eq!(tombstone["credentialSubject"]["id"], orphan.as_str());
+    assert_eq!(tombstone["credentialSubject"]["type"], "dataset");
+    assert_eq!(
+        tombstone["credentialSubject"]["createdAt"],
+        "2020-01-15T10:00:00Z"
+    );
+    assert_eq!(
+        tombstone["credentialSubject"]["retention"]["action"],
+        "archive"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_store_export_and_import() -> Result<()> {
+    use crate::in_toto::dsse::Envelope;
+    use crate::storage::export::{self, EXPORT_FORMAT, INDEX_PATH};
+    use crate::storage::traits::{EnvelopeKind, EnvelopeRecord};
+
+    let dir = tempdir()?;
+    let source = FilesystemStorage::new(dir.path().join("dev"))?;
+    for title in ["Dataset", "Model"] {
+        source.store_manifest(&ManifestBuilder::new(title).build())?;
+    }
+    let envelope = Envelope::new(&b"{}".to_vec(), "application/vnd.in-toto+json".to_string());
+    let record = EnvelopeRecord::new(EnvelopeKind::Slsa, "build.sh", None, envelope);
+    source.store_envelope(&record)?;
+
+    let archive = dir.path().join("store.tar.zst");
+    let exported = export::export_store(&source, &archive)?;
+    assert_eq!((exported.manifests, exported.envelopes), (2, 1));
+
+    // Promote the store to an empty one, then import again without duplicates
+    let target = FilesystemStorage::new(dir.path().join("prod"))?;
+    let imported = export::import_store(&target, &archive, export::OnExisting::Store)?;
+    assert_eq!((imported.manifests, imported.envelopes), (2, 1));
+    for metadata in source.list_manifests()? {
+        assert_eq!(target.retrieve_manifest(&metadata.id)?.title, metadata.name);
+    }
+    assert_eq!(target.retrieve_envelope(&record.id)?.name, "build.sh");
+    assert_eq!(
+        export::import_store(&target, &archive, export::OnExisting::Skip)?.skipped,
+        3
+    );
+
+    // An archive whose entry doesn't match its checksum is rejected as a whole
+    let tampered = dir.path().join("tampered.tar.zst");
+    let manifest = serde_json::to_vec(&source.retrieve_manifest(&source.list_manifests()?[0].id)?)?;
+    let index = serde_json::to_vec(&serde_json::json!({
+        "format": EXPORT_FORMAT,
+        "created_at": "2025-01-23T12:00:00Z",
+        "source": "test",
+        "entries": [{
+            "path": "manifests/000000.json",
+            "kind": "manifest",
+            "id": "urn:c2pa:tampered",
+            "sha256": hex::encode(sha2::Sha256::digest(b"original")),
+        }],
+    }))?;
+    let mut builder =
+        tar::Builder::new(zstd::stream::Encoder::new(fs::File::create(&tampered)?, 3)?);
+    for (path, data) in [(INDEX_PATH, index), ("manifests/000000.json", manifest)] {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, path, data.as_slice())?;
+    }
+    builder.into_inner()?.finish()?;
+
+    let empty = FilesystemStorage::new(dir.path().join("empty"))?;
+    let error = export::import_store(&empty, &tampered, export::OnExisting::Store).unwrap_err();
+    assert!(error.to_string().contains("Checksum mismatch"));
+    assert!(empty.list_manifests()?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_differential_export() -> Result<()> {
+    use crate::storage::export::{self, ExportSince, OnExisting};
+
+    let dir = tempdir()?;
+    let source = FilesystemStorage::new(dir.path().join("edge"))?;
+    let manifest = |title: &str| ManifestBuilder::new(title).build();
+    let mut model = manifest("Model");
+    source.store_manifest(&model)?;
+    source.store_manifest(&manifest("Dataset"))?;
+
+    let full = dir.path().join("full.tar.zst");
+    let (exported, checkpoint) = export::export_store_since(&source, &full, None)?;
+    assert_eq!(exported.manifests, 2);
+    let checkpoint_path = dir.path().join("edge.checkpoint");
+    checkpoint.write(&checkpoint_path)?;
+
+    let target = FilesystemStorage::new(dir.path().join("central"))?;
+    export::import_store(&target, &full, OnExisting::Dedupe)?;
+
+    // Change one manifest and add another; only those two are exported
+    model.title = "Model v2".to_string();
+    source.store_manifest(&model)?;
+    source.store_manifest(&manifest("Evaluation"))?;
+
+    let since = ExportSince::parse(checkpoint_path.to_str().unwrap())?;
+    let delta = dir.path().join("delta.tar.zst");
+    let (exported, _) = export::export_store_since(&source, &delta, Some(&since))?;
+    assert_eq!((exported.manifests, exported.skipped), (2, 1));
+    let (index, _, _) = export::read_export(&delta)?;
+    assert_eq!(index.since, Some(checkpoint.created_at));
+
+    let imported = export::import_store(&target, &delta, OnExisting::Dedupe)?;
+    assert_eq!((imported.manifests, imported.skipped), (2, 0));
+    assert_eq!(
+        target.retrieve_manifest(&model.instance_id)?.title,
+        "Model v2"
+    );
+    assert_eq!(target.list_manifests()?.len(), 3);
+
+    // Importing the same changes again stores nothing
+    let imported = export::import_store(&target, &delta, OnExisting::Dedupe)?;
+    assert_eq!((imported.manifests, imported.skipped), (0, 2));
+
+    // Timestamps only select entries created after them
+    assert!(matches!(
+        ExportSince::parse("2020-01-01")?,
+        ExportSince::Timestamp(_)
+    ));
+    assert!(ExportSince::parse("last week").is_err());
+    let since = ExportSince::Timestamp(OffsetDateTime::now_utc());
+    let (exported, _) = export::export_store_since(&source, &delta, Some(&since))?;
+    assert_eq!((exported.manifests, exported.skipped), (0, 3));
+
+    // A checkpoint of another store is rejected
+    let other = FilesystemStorage::new(dir.path().join("other"))?;
+    let since = ExportSince::Checkpoint(checkpoint);
+    assert!(export::export_store_since(&other, &delta, Some(&since)).is_err());
+
+    Ok(())
+}
+
+// State of the mock database server behind `serve_database`
+#[derive(Default)]
+struct DatabaseState {
+    chunks: HashMap<usize, Vec<u8>>,
+    manifests: HashMap<String, Vec<u8>>,
+    // Indices of the chunks uploaded, in order
+    puts: Vec<usize>,
+    // Remaining failures of uploads of the second chunk
+    failing_puts: u32,
+    // Whether the next range from a non-zero offset fails
+    failing_range: bool,
+    ranges: usize,
+    // Idempotency keys of the manifests stored in single requests, in order
+    keys: Vec<String>,
+    // Remaining stores whose answer is lost after the manifest was stored
+    lost_answers: u32,
+}
+
+// Serves the chunked upload API and ranged downloads of the database backend
+fn serve_database() -> (String, Arc<Mutex<DatabaseState>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+    let state = Arc::new(Mutex::new(DatabaseState::default()));
+    let shared = state.clone();
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { continue };
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut parts = request_line.split_whitespace();
+            let (method, path) = (parts.next().unwrap(), parts.next().unwrap().to_string());
+
+            let mut headers = HashMap::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    headers.insert(name.to_ascii_lowercase(), value.trim().to_string());
+                }
+            }
+            let length = headers
+                .get("content-length")
+                .map_or(0, |value| value.parse().unwrap());
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+
+            let mut state = shared.lock().unwrap();
+            let segments: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+            let (status, extra_headers, response) = match (method, segments.as_slice()) {
+                ("GET", ["uploads", _]) => {
+                    let chunks: Vec<_> = state
+                        .chunks
+                        .iter()
+                        .map(|(index, data)| {
+                            serde_json::json!({
+                                "index": index,
+                                "sha256": hex::encode(sha2::Sha256::digest(data)),
+                            })
+                        })
+                        .collect();
+                    let status = serde_json::json!({ "chunks": chunks }).to_string();
+                    ("200 OK", String::new(), status.into_bytes())
+                }
+                ("PUT", ["uploads", _, "chunks", index]) => {
+                    let index: usize = index.parse().unwrap();
+                    state.puts.push(index);
+                    if index == 1 && state.failing_puts > 0 {
+                        state.failing_puts -= 1;
+                        ("503 Service Unavailable", String::new(), Vec::new())
+                    } else if headers[CHUNK_SHA256_HEADER]
+                        != hex::encode(sha2::Sha256::digest(&body))
+                    {
+                        ("422 Unprocessable Entity", String::new(), Vec::new())
+                    } else {
+                        state.chunks.insert(index, body);
+                        ("200 OK", String::new(), Vec::new())
+                    }
+                }
+                ("POST", ["uploads", _, "complete"]) => {
+                    let complete: CompleteUpload = serde_json::from_slice(&body).unwrap();
+                    let assembled: Vec<u8> = (0..complete.chunks)
+                        .flat_map(|index| state.chunks[&index].clone())
+                        .collect();
+                    assert_eq!(
+                        hex::encode(sha2::Sha256::digest(&assembled)),
+                        complete.sha256
+                    );
+                    let mut stored: serde_json::Value = serde_json::from_slice(
+                        &crate::storage::compression::decompress(&assembled).unwrap(),
+                    )
+                    .unwrap();
+                    // The server wraps the manifest the way it is stored
+                    stored["manifest"] = serde_json::json!({ "manifest": stored["manifest"] });
+                    state.chunks.clear();
+                    state
+                        .manifests
+                        .insert(complete.manifest_id, serde_json::to_vec(&stored).unwrap());
+                    ("201 Created", String::new(), Vec::new())
+                }
+                ("POST", ["manifests", id]) => {
+                    let key = headers[&IDEMPOTENCY_KEY_HEADER.to_ascii_lowercase()].clone();
+                    // A request with a known key is answered as the first one was
+                    if !state.keys.contains(&key) {
+                        let mut stored: serde_json::Value = serde_json::from_slice(
+                            &crate::storage::compression::decompress(&body).unwrap(),
+                        )
+                        .unwrap();
+                        stored["manifest"] = serde_json::json!({ "manifest": stored["manifest"] });
+                        state
+                            .manifests
+                            .insert(id.to_string(), serde_json::to_vec(&stored).unwrap());
+                    }
+                    state.keys.push(key);
+                    if state.lost_answers > 0 {
+                        state.lost_answers -= 1;
+                        ("502 Bad Gateway", String::new(), Vec::new())
+                    } else {
+                        ("201 Created", String::new(), Vec::new())
+                    }
+                }
+                ("GET", ["manifests"]) => {
+                    let manifests: Vec<serde_json::Value> = state
+                        .manifests
+                        .values()
+                        .map(|data| serde_json::from_slice(data).unwrap())
+                        .collect();
+                    let list = serde_json::to_vec(&manifests).unwrap();
+                    ("200 OK", String::new(), list)
+                }
+                ("GET", ["manifests", id]) => match state.manifests.get(*id).cloned() {
+                    None => ("404 Not Found", String::new(), Vec::new()),
+                    Some(data) => {
+                        let (first, last) = headers["range"]
+                            .trim_start_matches("bytes=")
+                            .split_once('-')
+                            .unwrap();
+                        let first: usize = first.parse().unwrap();
+                        let last = last.parse::<usize>().unwrap().min(data.len() - 1);
+                        if first > 0 && state.failing_range {
+                            state.failing_range = false;
+                            ("503 Service Unavailable", String::new(), Vec::new())
+                        } else {
+                            state.ranges += 1;
+                            let range = data[first..=last].to_vec();
+                            let headers = format!(
+                                "Content-Range: bytes {first}-{last}/{}\r\n{CHUNK_SHA256_HEADER}: {}\r\n{CONTENT_SHA256_HEADER}: {}\r\n",
+                                data.len(),
+                                hex::encode(sha2::Sha256::digest(&range)),
+                                hex::encode(sha2::Sha256::digest(&data)),
+                            );
+                            ("206 Partial Content", headers, range)
+                        }
+                    }
+                },
+                _ => ("404 Not Found", String::new(), Vec::new()),
+            };
+            drop(state);
+
+            write!(
+                stream,
+                "HTTP/1.1 {status}\r\nContent-Type: application/json\r\n{extra_headers}Content-Length: {}\r\nConnection: close\r\n\r\n",
+                response.len()
+            )
+            .unwrap();
+            stream.write_all(&response).unwrap();
+        }
+    });
+
+    (endpoint, state)
+}
+
+#[test]
+fn test_database_chunked_transfers() -> Result<()> {
+    let (endpoint, state) = serve_database();
+    let storage = DatabaseStorage::new(endpoint)?
+        .with_credentials(Credentials::Anonymous)
+        .with_compression(false)
+        .with_chunk_size(256);
+
+    let manifest = ManifestBuilder::new("Large Dataset").build();
+
+    // The second chunk keeps failing, so the upload gives up after the first one
+    state.lock().unwrap().failing_puts = CHUNK_ATTEMPTS;
+    let error = storage.store_manifest(&manifest).unwrap_err();
+    assert!(error.is_retryable());
+    assert!(
+        error
+            .to_string()
+            .contains("run the command again to resume")
+    );
+    assert_eq!(state.lock().unwrap().puts, vec![0, 1, 1, 1]);
+
+    // Trying again resumes the upload after the chunk the server already holds
+    let attempted = state.lock().unwrap().puts.len();
+    assert_eq!(storage.store_manifest(&manifest)?, manifest.instance_id);
+    assert!(!state.lock().unwrap().puts[attempted..].contains(&0));
+
+    // The download retries the failing range on its own
+    state.lock().unwrap().failing_range = true;
+    let retrieved = storage.retrieve_manifest(&manifest.instance_id)?;
+    assert_eq!(retrieved.title, manifest.title);
+    assert!(state.lock().unwrap().ranges > 1);
+
+    Ok(())
+}
+
+#[test]
+fn test_database_store_is_idempotent() -> Result<()> {
+    let (endpoint, state) = serve_database();
+    let storage = DatabaseStorage::new(endpoint)?.with_credentials(Credentials::Anonymous);
+
+    let mut manifest = ManifestBuilder::new("Retried Model").build();
+
+    // The request is sent again after the lost answer, with the same key
+    state.lock().unwrap().lost_answers = 1;
+    assert_eq!(storage.store_manifest(&manifest)?, manifest.instance_id);
+    {
+        let state = state.lock().unwrap();
+        assert_eq!(state.keys.len(), 2);
+        assert_eq!(state.keys[0], state.keys[1]);
+        assert_eq!(state.manifests.len(), 1);
+    }
+
+    // Storing the same content again doesn't create a new version, even when the retry was
+    // created anew
+    assert_eq!(storage.store_manifest(&manifest)?, manifest.instance_id);
+    let mut recreated = manifest.clone();
+    let later = OffsetDateTime::now_utc() + time::Duration::seconds(1);
+    recreated.created_at = OffsetDateTimeWrapper(later);
+    recreated.claim.created_at = OffsetDateTimeWrapper(later);
+    assert_eq!(storage.store_manifest(&recreated)?, manifest.instance_id);
+    assert_eq!(state.lock().unwrap().keys.len(), 2);
+
+    // Other content under the same ID does, once
+    manifest.title = "Retried Model v2".to_string();
+    let versioned = storage.store_manifest(&manifest)?;
+    assert_eq!(versioned, format!("{}:test:1_1", manifest.instance_id));
+    assert_eq!(storage.store_manifest(&manifest)?, versioned);
+    {
+        let state = state.lock().unwrap();
+        assert_eq!(state.keys.len(), 3);
+        assert_ne!(state.keys[2], state.keys[0]);
+        assert_eq!(state.manifests.len(), 2);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_database_link_manifests() -> Result<()> {
+    let (endpoint, state) = serve_database();
+    let storage = DatabaseStorage::new(endpoint)?.with_credentials(Credentials::Anonymous);
+
+    let manifest = |title: &str| ManifestBuilder::new(title).build();
+    let source = manifest("Fine-tuned Model");
+    let target = manifest("Base Model");
+    storage.store_manifest(&source)?;
+    storage.store_manifest(&target)?;
+
+    // The cross-reference changes the source manifest, which is stored as a new version
+    crate::manifest::link_manifests(&source.instance_id, &target.instance_id, &storage)?;
+    assert_eq!(state.lock().unwrap().manifests.len(), 3);
+
+    let linked = storage.retrieve_manifest(&format!("{}:test:1_1", source.instance_id))?;
+    assert_eq!(linked.cross_references.len(), 1);
+    assert_eq!(linked.cross_references[0].manifest_url, target.instance_id);
+    Ok(())
+}
+
+// Serves the objects of an S3 bucket with path-style requests, listing one key per page.
+// Objects are only written by conditional requests, and their ETag is the SHA-256 of their
+// content. The user metadata written with an object is kept until it is written or deleted
+// again, even if the test replaces the object.
+fn serve_s3() -> (String, Arc<Mutex<HashMap<String, Vec<u8>>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+    let objects: Arc<Mutex<HashMap<String, Vec<u8>>>> = Arc::default();
+    let shared = objects.clone();
+
+    std::thread::spawn(move || {
+        let mut user_metadata: HashMap<String, String> = HashMap::new();
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { continue };
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut parts = request_line.split_whitespace();
+            let (method, target) = (parts.next().unwrap(), parts.next().unwrap().to_string());
+
+            let mut headers = HashMap::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    headers.insert(name.to_ascii_lowercase(), value.trim().to_string());
+                }
+            }
+            let length = headers
+                .get("content-length")
+                .map_or(0, |value| value.parse().unwrap());
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+
+            let etag = |data: &[u8]| format!("\"{}\"", hex::encode(sha2::Sha256::digest(data)));
+            let (path, query) = target.split_once('?').unwrap_or((&target, ""));
+            let mut objects = shared.lock().unwrap();
+            let mut extra_headers = String::new();
+            let (status, response) = match (method, path.trim_start_matches('/').split_once('/')) {
+                ("GET", None) => {
+                    let param = |name: &str| {
+                        query
+                            .split('&')
+                            .find_map(|pair| pair.strip_prefix(&format!("{name}=")))
+                            .map(|value| value.replace("%2F", "/"))
+                    };
+                    let prefix = param("prefix").unwrap_or_default();
+                    let mut keys: Vec<&String> = objects
+                        .keys()
+                        .filter(|key| key.starts_with(&prefix))
+                        .collect();
+                    keys.sort();
+                    let start =
+                        param("continuation-token").map_or(0, |token| token.parse().unwrap());
+                    let mut listing = String::from("<ListBucketResult>");
+                    if let Some(key) = keys.get(start) {
+                        listing.push_str(&format!("<Contents><Key>{key}</Key></Contents>"));
+                    }
+                    if start + 1 < keys.len() {
+                        listing.push_str(&format!(
+                            "<IsTruncated>true</IsTruncated><NextContinuationToken>{}</NextContinuationToken>",
+                            start + 1
+                        ));
+                    } else {
+                        listing.push_str("<IsTruncated>false</IsTruncated>");
+                    }
+                    listing.push_str("</ListBucketResult>");
+                    ("200 OK", listing.into_bytes())
+                }
+                ("PUT", Some((_, key))) => {
+                    let current = objects.get(key).map(|data| etag(data));
+                    let allowed = match (headers.get("if-none-match"), headers.get("if-match")) {
+                        (Some(any), None) if any == "*" => Some(current.is_none()),
+                        (None, Some(expected)) => Some(current.as_ref() == Some(expected)),
+                        _ => None,
+                    };
+                    match allowed {
+                        Some(true) => {
+                            objects.insert(key.to_string(), body);
+                            let lines: String = headers
+                                .iter()
+                                .filter(|(name, _)| name.starts_with("x-amz-meta-"))
+                                .map(|(name, value)| format!("{name}: {value}\r\n"))
+                                .collect();
+                            user_metadata.insert(key.to_string(), lines);
+                            ("200 OK", Vec::new())
+                        }
+                        Some(false) => ("412 Precondition Failed", Vec::new()),
+                        None => ("400 Bad Request", Vec::new()),
+                    }
+                }
+                ("GET", Some((_, key))) => match objects.get(key) {
+                    Some(data) => {
+                        extra_headers = format!("ETag: {}\r\n", etag(data));
+                        ("200 OK", data.clone())
+                    }
+                    None => ("404 Not Found", Vec::new()),
+                },
+                ("HEAD", Some((_, key))) => match objects.get(key) {
+                    Some(_) => {
+                        extra_headers = user_metadata.get(key).cloned().unwrap_or_default();
+                        ("200 OK", Vec::new())
+                    }
+                    None => ("404 Not Found", Vec::new()),
+                },
+                ("DELETE", Some((_, key))) => {
+                    objects.remove(key);
+                    user_metadata.remove(key);
+                    ("204 No Content", Vec::new())
+                }
+                _ => ("400 Bad Request", Vec::new()),
+            };
+            drop(objects);
+
+            write!(
+                stream,
+                "HTTP/1.1 {status}\r\n{extra_headers}Content-Length: {}\r\nConnection: close\r\n\r\n",
+                response.len()
+            )
+            .unwrap();
+            if method != "HEAD" {
+                stream.write_all(&response).unwrap();
+            }
+        }
+    });
+
+    (endpoint, objects)
+}
+
+#[test]
+fn test_s3_storage() -> Result<()> {
+    let (endpoint, objects) = serve_s3();
+    let storage = S3Storage::new("s3://models/provenance/")?
+        .with_client(ObjectClient::with_endpoint(ObjectStore::S3, &endpoint)?);
+    assert_eq!(storage.get_base_uri(), "s3://models/provenance/");
+
+    let manifest = |title: &str| ManifestBuilder::new(title).build();
+    let first = manifest("First Model");
+    let second = manifest("Second Model");
+    assert_eq!(storage.store_manifest(&first)?, first.instance_id);
+    assert_eq!(storage.store_manifest(&second)?, second.instance_id);
+
+    // Manifests are named by the SHA-256 of their ID, below the prefix
+    let key = format!(
+        "provenance/{}.json",
+        hex::encode(sha2::Sha256::digest(first.instance_id.as_bytes()))
+    );
+    assert!(objects.lock().unwrap().contains_key(&key));
+    assert_eq!(
+        storage.retrieve_manifest(&first.instance_id)?.title,
+        "First Model"
+    );
+
+    // Storing the same manifest again leaves it as it is, and a changed one replaces it
+    assert_eq!(storage.store_manifest(&first)?, first.instance_id);
+    let mut linked = first.clone();
+    linked
+        .cross_references
+        .push(atlas_c2pa_lib::cross_reference::CrossReference::new(
+            second.instance_id.clone(),
+            "0".repeat(96),
+        ));
+    assert_eq!(storage.store_manifest(&linked)?, first.instance_id);
+    assert_eq!(
+        storage
+            .retrieve_manifest(&first.instance_id)?
+            .cross_references
+            .len(),
+        1
+    );
+
+    // Objects besides the manifests of the prefix are not listed
+    objects
+        .lock()
+        .unwrap()
+        .insert("provenance/notes.txt".to_string(), b"notes".to_vec());
+    objects
+        .lock()
+        .unwrap()
+        .insert("elsewhere/manifest.json".to_string(), b"{}".to_vec());
+    let mut names: Vec<String> = storage
+        .list_manifests()?
+        .into_iter()
+        .map(|metadata| metadata.name)
+        .collect();
+    names.sort();
+    assert_eq!(names, ["First Model", "Second Model"]);
+
+    // Listing reads the metadata stored with each object rather than the manifest, and
+    // reads manifests stored without metadata in full
+    let second_key = format!(
+        "provenance/{}.json",
+        hex::encode(sha2::Sha256::digest(second.instance_id.as_bytes()))
+    );
+    objects
+        .lock()
+        .unwrap()
+        .insert(second_key, b"not a manifest".to_vec());
+    let third = manifest("Third Model");
+    let third_key = format!(
+        "provenance/{}.json",
+        hex::encode(sha2::Sha256::digest(third.instance_id.as_bytes()))
+    );
+    objects
+        .lock()
+        .unwrap()
+        .insert(third_key.clone(), serde_json::to_vec(&third)?);
+    let mut names: Vec<String> = storage
+        .list_manifests()?
+        .into_iter()
+        .map(|metadata| metadata.name)
+        .collect();
+    names.sort();
+    assert_eq!(names, ["First Model", "Second Model", "Third Model"]);
+
+    // A manifest that can't be listed fails the listing instead of being left out
+    objects
+        .lock()
+        .unwrap()
+        .insert(third_key.clone(), b"not a manifest".to_vec());
+    assert!(storage.list_manifests().is_err());
+    objects.lock().unwrap().remove(&third_key);
+
+    storage.delete_manifest(&first.instance_id)?;
+    assert!(matches!(
+        storage.retrieve_manifest(&first.instance_id),
+        Err(crate::error::Error::NotFound(_))
+    ));
+    assert!(matches!(
+        storage.delete_manifest(&first.instance_id),
+        Err(crate::error::Error::NotFound(_))
+    ));
+    assert_eq!(storage.list_manifests()?.len(), 1);
+    Ok(())
+}
+
+// Answers the first request with 429 and a Retry-After of one second, and every later
+// search with no results, recording the request lines
+fn serve_throttled_rekor() -> (String, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let recorded = requests.clone();
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { continue };
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                    length = value.trim().parse().unwrap();
+                }
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+
+            let mut requests = recorded.lock().unwrap();
+            requests.push(request_line.trim().to_string());
+            let response = if requests.len() == 1 {
+                "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 1\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+            } else {
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 2\r\nConnection: close\r\n\r\n[]"
+            };
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+
+    (endpoint, requests)
+}
+
+#[test]
+fn test_rekor_throttling_and_coalescing() -> Result<()> {
+    let (endpoint, requests) = serve_throttled_rekor();
+    let storage = RekorStorage::new_with_url(endpoint)?.with_rate_limit(RateLimit::unlimited());
+    let digest = "a".repeat(64);
+
+    // The throttled search is sent again after the delay the log asked for
+    let started = std::time::Instant::now();
+    assert!(storage.find_entries(&digest)?.is_empty());
+    assert!(started.elapsed() >= std::time::Duration::from_secs(1));
+    assert_eq!(requests.lock().unwrap().len(), 2);
+
+    // Repeated searches are answered without asking the log again
+    assert!(
+        storage
+            .find_entries(&format!("sha256:{digest}"))?
+            .is_empty()
+    );
+    assert_eq!(requests.lock().unwrap().len(), 2);
+    Ok(())
+}
+
+// Storage plugin keeping each request next to itself and answering with canned responses
+#[cfg(unix)]
+const STORAGE_PLUGIN: &str = r#"#!/bin/sh
+dir=$(dirname "$0")
+cat > "$dir/$1.request"
+case "$1" in
+    store) echo '{"id": "urn:c2pa:plugin"}' ;;
+    retrieve) printf '{"manifest": '; cat "$dir/manifest.json"; echo '}' ;;
+    list) echo '{"manifests": [{"id": "urn:c2pa:plugin", "name": "Plugin Model", "manifest_type": "model", "created_at": "2026-01-01T00:00:00Z"}]}' ;;
+    delete) echo '{"error": {"kind": "not_found", "message": "no manifest urn:c2pa:missing"}}'; exit 1 ;;
+    *) echo "unknown operation $1" >&2; exit 2 ;;
+esac
+"#;
+
+#[cfg(unix)]
+#[test]
+fn test_plugin_storage() -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempdir()?;
+    let executable = dir.path().join("atlas-storage-test");
+    fs::write(&executable, STORAGE_PLUGIN)?;
+    fs::set_permissions(&executable, fs::Permissions::from_mode(0o755))?;
+    let storage = PluginStorage::with_executable("test", &executable, "registry://models");
+
+    let manifest = ManifestBuilder::new("Plugin Model")
+        .id("urn:c2pa:plugin")
+        .build();
+    fs::write(
+        dir.path().join("manifest.json"),
+        serde_json::to_vec(&manifest)?,
+    )?;
+
+    // Requests carry the protocol version, the storage URL and the operation's parameters
+    assert_eq!(storage.store_manifest(&manifest)?, "urn:c2pa:plugin");
+    let request: serde_json::Value =
+        serde_json::from_slice(&fs::read(dir.path().join("store.request"))?)?;
+    assert_eq!(request["version"], 1);
+    assert_eq!(request["url"], "registry://models");
+    assert_eq!(request["manifest"]["title"], "Plugin Model");
+
+    assert_eq!(
+        storage.retrieve_manifest("urn:c2pa:plugin")?.title,
+        "Plugin Model"
+    );
+    let listed = storage.list_manifests()?;
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].name, "Plugin Model");
+
+    // Reported failures keep their kind; plugins that fail otherwise report their stderr
+    assert!(matches!(
+        storage.delete_manifest("urn:c2pa:missing"),
+        Err(crate::error::Error::NotFound(_))
+    ));
+    let error = storage.retrieve_envelope("envelope").unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .contains("unknown operation retrieve-envelope")
+    );
+    assert!(!storage.supports_envelopes());
+    Ok(())
+}
+
+#[test]
+fn test_detached_payload() -> Result<()> {
+    use crate::manifest::attestation::{
+        AttestationConfig, create_attestation, detach_payload, read_envelope, sign_attestation,
+        verify_attestation,
+    };
+    use openssl::pkey::PKey;
+
+    let dir = tempdir()?;
+    let weights = dir.path().join("weights.onnx");
+    let statement = dir.path().join("statement.json");
+    let envelope_path = dir.path().join("envelope.json");
+    let detached_path = dir.path().join("detached.json");
+    let payload_path = dir.path().join("payload.json");
+    fs::write(&weights, b"model weights")?;
+
+    let (key, key_dir) = generate_temp_key()?;
+    let key_path = key_dir.path().join("test_key.pem");
+    let public_key =
+        PKey::public_key_from_pem(&key.as_pkey().public_key_to_pem().unwrap()).unwrap();
+    create_attestation(AttestationConfig {
+        subjects: vec![weights.clone()],
+        subject_manifests: vec![],
+        predicate_type: "https://example.com/evaluation/v1".to_string(),
+        predicate_path: None,
+        name: None,
+        key_path: None,
+        pq_key_path: None,
+        hash_alg: HashAlgorithm::Sha256,
+        output_encoding: "json".to_string(),
+        output_path: Some(statement.clone()),
+        print: false,
+        storage: None,
+    })?;
+    let envelope = sign_attestation(&statement, key_path.clone(), None, HashAlgorithm::Sha256)?;
+    fs::write(&envelope_path, serde_json::to_vec(&envelope)?)?;
+
+    detach_payload(
+        &envelope_path,
+        "payload.json".to_string(),
+        &payload_path,
+        &HashAlgorithm::Sha384,
+        "json",
+        &detached_path,
+    )?;
+    let detached = read_envelope(&detached_path)?;
+    assert!(detached.payload().is_empty());
+    assert_eq!(fs::read(&payload_path)?, envelope.payload());
+    let digest = &detached.payload_ref().unwrap().digest;
+    assert!(digest.contains_key("sha384"));
+
+    // The payload is fetched relative to the artifact root and checked before the signature
+    verify_attestation(&detached, &public_key, Some(dir.path()), None)?;
+    assert!(verify_attestation(&detached, &public_key, None, None).is_err());
+
+    // A detached envelope can't be co-signed without its payload
+    assert!(sign_attestation(&detached_path, key_path, None, HashAlgorithm::Sha256).is_err());
+
+    let mut tampered = fs::read(&payload_path)?;
+    tampered.push(b' ');
+    fs::write(&payload_path, tampered)?;
+    let error = verify_attestation(&detached, &public_key, Some(dir.path()), None)
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("doesn't match its sha384 digest"), "{error}");
+    Ok(())
+}
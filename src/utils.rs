@@ -99,7 +99,7 @@ fn is_safe_symlink_target(target: &Path) -> bool {
 /// ```
 pub fn safe_open_file(path: &Path, allow_symlinks: bool) -> Result<File> {
     let safe_path = safe_file_path(path, allow_symlinks)?;
-    File::open(&safe_path).map_err(Error::from)
+    File::open(long_path(&safe_path)).map_err(Error::from)
 }
 
 /// Safely creates a file for writing
@@ -123,7 +123,7 @@ pub fn safe_open_file(path: &Path, allow_symlinks: bool) -> Result<File> {
 /// ```
 pub fn safe_create_file(path: &Path, allow_symlinks: bool) -> Result<File> {
     let safe_path = safe_file_path(path, allow_symlinks)?;
-    File::create(&safe_path).map_err(Error::from)
+    File::create(long_path(&safe_path)).map_err(Error::from)
 }
 
 /// Safely opens a file with custom options
@@ -132,6 +132,233 @@ pub fn safe_open_options(path: &Path, allow_symlinks: bool) -> Result<OpenOption
     Ok(OpenOptions::new())
 }
 
+/// Converts a path to an RFC 8089 `file:` URL.
+///
+/// Relative paths are made absolute against the current directory, and characters that
+/// may not appear in a URL path are percent-encoded. On Windows, drive letters become
+/// `file:///C:/...` and UNC paths become `file://server/share/...`.
+///
+/// # Examples
+///
+/// ```
+/// use atlas_cli::utils::{file_url_to_path, path_to_file_url};
+/// use std::path::Path;
+///
+/// # #[cfg(unix)]
+/// # {
+/// let url = path_to_file_url(Path::new("/data/train set/part#1.csv"));
+/// assert_eq!(url, "file:///data/train%20set/part%231.csv");
+/// assert_eq!(file_url_to_path(&url).unwrap(), Path::new("/data/train set/part#1.csv"));
+/// # }
+/// ```
+pub fn path_to_file_url(path: &Path) -> String {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+
+    #[cfg(windows)]
+    {
+        windows_path_to_file_url(&path.to_string_lossy())
+    }
+
+    #[cfg(not(windows))]
+    {
+        format!("file://{}", percent_encode_path(&path.to_string_lossy()))
+    }
+}
+
+/// Converts a `file:` URL back to a path, returning `None` for other URLs.
+///
+/// Besides RFC 8089 URLs this accepts the `file://{path}` URLs of manifests created by
+/// earlier versions, which embed the raw path, possibly relative or with backslashes.
+pub fn file_url_to_path(url: &str) -> Option<PathBuf> {
+    let rest = url.strip_prefix("file://")?;
+
+    #[cfg(windows)]
+    {
+        Some(PathBuf::from(file_url_to_windows_path(rest)))
+    }
+
+    #[cfg(not(windows))]
+    {
+        let path = rest
+            .strip_prefix("localhost")
+            .filter(|path| path.starts_with('/'))
+            .unwrap_or(rest);
+        Some(PathBuf::from(percent_decode(path)))
+    }
+}
+
+/// Converts a path below `base` to a relative URL, for manifests that can be verified
+/// wherever the artifact tree is checked out
+///
+/// # Examples
+///
+/// ```
+/// use atlas_cli::utils::{relative_file_url, relative_url_to_path};
+/// use std::path::Path;
+///
+/// let url = relative_file_url(Path::new("/work/data/train set.csv"), Path::new("/work")).unwrap();
+/// assert_eq!(url, "data/train%20set.csv");
+/// assert_eq!(
+///     relative_url_to_path(&url).unwrap(),
+///     Path::new("data").join("train set.csv")
+/// );
+///
+/// assert!(relative_file_url(Path::new("/elsewhere/a.csv"), Path::new("/work")).is_err());
+/// ```
+pub fn relative_file_url(path: &Path, base: &Path) -> Result<String> {
+    let path = std::path::absolute(path)?;
+    let base = std::path::absolute(base)?;
+    let relative = path.strip_prefix(&base).map_err(|_| {
+        Error::Validation(format!(
+            "{} is not below the path base {}",
+            path.display(),
+            base.display()
+        ))
+    })?;
+
+    // A colon in the first segment would be read as a URL scheme
+    Ok(relative
+        .components()
+        .map(|component| {
+            percent_encode_path(&component.as_os_str().to_string_lossy()).replace(':', "%3A")
+        })
+        .collect::<Vec<_>>()
+        .join("/"))
+}
+
+/// Converts a relative URL back to a relative path, returning `None` for absolute URLs
+/// and URLs with a scheme
+pub fn relative_url_to_path(url: &str) -> Option<PathBuf> {
+    let has_scheme = url.split_once(':').is_some_and(|(scheme, _)| {
+        scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    });
+    if url.is_empty() || url.starts_with(['/', '\\']) || has_scheme {
+        return None;
+    }
+
+    Some(url.split('/').map(percent_decode).collect())
+}
+
+/// Prefixes long absolute paths with `\\?\` on Windows so that they can be opened
+/// despite the 260 character limit. Other paths are returned unchanged.
+pub fn long_path(path: &Path) -> PathBuf {
+    #[cfg(windows)]
+    {
+        const MAX_PATH: usize = 260;
+
+        let raw = path.to_string_lossy();
+        if raw.len() >= MAX_PATH && path.is_absolute() && !raw.starts_with(r"\\?\") {
+            return match raw.strip_prefix(r"\\") {
+                Some(unc) => PathBuf::from(format!(r"\\?\UNC\{unc}")),
+                None => PathBuf::from(format!(r"\\?\{raw}")),
+            };
+        }
+    }
+
+    path.to_path_buf()
+}
+
+// Percent-encodes a path for use in a URL, keeping `/` and `:` as well as unreserved
+// characters and sub-delimiters other than `!`, which separates archive members
+fn percent_encode_path(path: &str) -> String {
+    path.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z'
+            | b'a'..=b'z'
+            | b'0'..=b'9'
+            | b'-'
+            | b'.'
+            | b'_'
+            | b'~'
+            | b'$'
+            | b'&'
+            | b'\''
+            | b'('
+            | b')'
+            | b'*'
+            | b'+'
+            | b','
+            | b';'
+            | b'='
+            | b'@'
+            | b':'
+            | b'/' => (byte as char).to_string(),
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+// Decodes valid `%XX` sequences, leaving any other `%` as it is
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+// Windows conversions work on strings so that they are tested on every platform
+#[cfg(any(windows, test))]
+fn windows_path_to_file_url(path: &str) -> String {
+    // Verbatim paths (\\?\C:\..., \\?\UNC\server\...) are converted like their plain forms
+    let path = match path.strip_prefix(r"\\?\") {
+        Some(rest) => match rest.strip_prefix(r"UNC\") {
+            Some(unc) => format!(r"\\{unc}"),
+            None => rest.to_string(),
+        },
+        None => path.to_string(),
+    };
+
+    let slashed = path.replace('\\', "/");
+    match slashed.strip_prefix("//") {
+        // UNC path: the server is the URL authority
+        Some(unc) => format!("file://{}", percent_encode_path(unc)),
+        None => format!(
+            "file:///{}",
+            percent_encode_path(slashed.trim_start_matches('/'))
+        ),
+    }
+}
+
+#[cfg(any(windows, test))]
+fn file_url_to_windows_path(rest: &str) -> String {
+    let decoded = percent_decode(rest);
+    let rest = decoded.strip_prefix("localhost/").unwrap_or(&decoded);
+
+    let path = match rest.strip_prefix('/') {
+        // file:///C:/dir/file
+        Some(local) if local.as_bytes().get(1) == Some(&b':') => local.to_string(),
+        // file:////server/share, an older form of UNC URL
+        Some(local) if local.starts_with('/') => local.to_string(),
+        Some(local) => format!("/{local}"),
+        // file://C:\dir\file from earlier versions
+        None if rest.as_bytes().get(1) == Some(&b':') => rest.to_string(),
+        // file://server/share/file
+        None => format!("//{rest}"),
+    };
+
+    path.replace('/', "\\")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -421,4 +648,57 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_windows_file_urls() {
+        let cases = [
+            (
+                r"C:\data\train set\a.csv",
+                "file:///C:/data/train%20set/a.csv",
+            ),
+            (r"\\server\share\data.bin", "file://server/share/data.bin"),
+            (r"\\?\C:\long\path.bin", "file:///C:/long/path.bin"),
+            (r"\\?\UNC\server\share\x.bin", "file://server/share/x.bin"),
+        ];
+        for (path, url) in cases {
+            assert_eq!(windows_path_to_file_url(path), url);
+        }
+
+        let round_trips = [
+            (
+                "file:///C:/data/train%20set/a.csv",
+                r"C:\data\train set\a.csv",
+            ),
+            ("file://server/share/data.bin", r"\\server\share\data.bin"),
+            ("file://localhost/C:/x.bin", r"C:\x.bin"),
+            // Raw paths recorded by earlier versions
+            (r"file://C:\data\a.csv", r"C:\data\a.csv"),
+        ];
+        for (url, path) in round_trips {
+            assert_eq!(
+                file_url_to_windows_path(url.strip_prefix("file://").unwrap()),
+                path
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_file_url_round_trip() {
+        let path = Path::new("/data/100%/a!b.csv");
+        let url = path_to_file_url(path);
+        assert_eq!(url, "file:///data/100%25/a%21b.csv");
+        assert_eq!(file_url_to_path(&url).unwrap(), path);
+
+        // URLs of earlier versions embed the raw path
+        assert_eq!(
+            file_url_to_path("file:///data/100%/a.csv").unwrap(),
+            Path::new("/data/100%/a.csv")
+        );
+        assert_eq!(
+            file_url_to_path("file://localhost/data/a.csv").unwrap(),
+            Path::new("/data/a.csv")
+        );
+        assert!(file_url_to_path("s3://bucket/a.csv").is_none());
+    }
 }